@@ -0,0 +1,135 @@
+//! FIX parsing: the codec and borrowed field lookups against the previous
+//! `String`/`HashMap` parsing
+//!
+//! Messages are market data snapshots as cTrader sends them, with `entries`
+//! MDEntries (alternating bid and offer). `market_data` compares
+//! `MarketDataParser::parse_market_data` with the field-by-field parser it
+//! replaced, `lookup` reading a few header fields through `parse_fix_message`
+//! with reading them through `FixFields`, and `decode` measures framing a
+//! buffer of snapshots with `FixCodec`.
+//!
+//! Run with `cargo bench --bench fix_codec`.
+
+use bytes::BytesMut;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use order_book_api::ctrader_fix::market_data::tick_parser::{MDEntryType, MarketDataEntry};
+use order_book_api::ctrader_fix::messages::{parse_fix_message, FixMessage};
+use order_book_api::ctrader_fix::{parse_fix_field, MarketDataParser};
+use order_book_api::fix::{FixCodec, FixFields};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use tokio_util::codec::Decoder;
+
+const ENTRIES: [usize; 3] = [2, 10, 50];
+const MESSAGES_PER_BUFFER: usize = 100;
+
+fn snapshot(entries: usize) -> String {
+    let mut message = FixMessage::new();
+    message.add_field(49, "cServer");
+    message.add_field(56, "demo.ctrader.1");
+    message.add_field(34, 1042);
+    message.add_field(52, "20260101-12:00:00.000");
+    message.add_field(55, "41");
+    message.add_field(268, entries);
+    for entry in 0..entries {
+        message.add_field(269, entry % 2);
+        message.add_field(270, format!("{}.{:02}", 2650 + entry / 2, entry % 100));
+        message.add_field(271, 100_000);
+    }
+    message.build("W")
+}
+
+/// The market data parser before `FixFields`: every field split out of the
+/// string, entries built up as their fields go by
+fn previous_parse_market_data(raw_message: &str) -> Option<(String, Vec<MarketDataEntry>)> {
+    let mut symbol_id = None;
+    let mut entries = vec![];
+    let mut entry_type = None;
+    let mut price = None;
+
+    for field in raw_message.split('\x01') {
+        if let Some((tag, value)) = parse_fix_field(field) {
+            match tag {
+                55 => symbol_id = Some(value.to_string()),
+                269 => {
+                    if let (Some(entry_type), Some(price)) = (entry_type, price) {
+                        entries.push(MarketDataEntry { entry_type, price });
+                    }
+                    entry_type = value.chars().next().and_then(MDEntryType::from_char);
+                    price = None;
+                }
+                270 => price = Decimal::from_str(value).ok(),
+                _ => {}
+            }
+        }
+    }
+    if let (Some(entry_type), Some(price)) = (entry_type, price) {
+        entries.push(MarketDataEntry { entry_type, price });
+    }
+
+    symbol_id.map(|symbol| (symbol, entries))
+}
+
+fn bench_market_data(c: &mut Criterion) {
+    let mut group = c.benchmark_group("market_data");
+    let parser = MarketDataParser::new();
+
+    for entries in ENTRIES {
+        let message = snapshot(entries);
+        group.throughput(Throughput::Bytes(message.len() as u64));
+        group.bench_with_input(BenchmarkId::new("previous_parser", entries), &message, |b, message| {
+            b.iter(|| black_box(previous_parse_market_data(black_box(message))));
+        });
+        group.bench_with_input(BenchmarkId::new("fix_fields", entries), &message, |b, message| {
+            b.iter(|| black_box(parser.parse_market_data(black_box(message.as_bytes()))));
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lookup");
+    group.throughput(Throughput::Elements(1));
+
+    for entries in ENTRIES {
+        let message = snapshot(entries);
+        group.bench_with_input(BenchmarkId::new("parse_fix_message", entries), &message, |b, message| {
+            b.iter(|| {
+                let fields = parse_fix_message(black_box(message));
+                black_box((fields.get(&35).cloned(), fields.get(&34).cloned(), fields.get(&55).cloned()))
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("fix_fields", entries), &message, |b, message| {
+            b.iter(|| {
+                let fields = FixFields::new(black_box(message.as_bytes()));
+                black_box((fields.msg_type(), fields.get(34), fields.get(55)))
+            });
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+
+    for entries in ENTRIES {
+        let stream = snapshot(entries).repeat(MESSAGES_PER_BUFFER);
+        group.throughput(Throughput::Elements(MESSAGES_PER_BUFFER as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(entries), &stream, |b, stream| {
+            b.iter(|| {
+                let mut buf = BytesMut::from(stream.as_bytes());
+                let mut codec = FixCodec;
+                while let Some(frame) = codec.decode(&mut buf).unwrap() {
+                    black_box(frame);
+                }
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_market_data, bench_lookup, bench_decode);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+//! Order entry throughput against book depth
+//!
+//! Each book is pre-filled with `depth` price levels per side (one order per
+//! level). Throughput is measured for a passive order that rests and is
+//! cancelled again, and for an aggressive order that takes out the best level
+//! which is then replenished, so the depth stays constant across iterations.
+//!
+//! Run with `cargo bench --bench orderbook_depth`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use order_book_api::engine::OrderBookEngine;
+use order_book_api::models::{Order, OrderSide, OrderType};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+const SYMBOL: &str = "BENCH";
+const DEPTHS: [usize; 4] = [10, 100, 1_000, 10_000];
+
+fn limit_order(side: OrderSide, price: Decimal, quantity: Decimal) -> Order {
+    let user_id = match side {
+        OrderSide::Buy => "buyer",
+        OrderSide::Sell => "seller",
+    };
+    Order::new(SYMBOL.to_string(), side, OrderType::Limit, Some(price), quantity, user_id.to_string())
+}
+
+/// Engine with `depth` bid levels below 1000 and `depth` ask levels from 1000 up
+fn engine_with_depth(depth: usize) -> OrderBookEngine {
+    let engine = OrderBookEngine::new();
+    for level in 0..depth {
+        let offset = Decimal::from(level as u64) * dec!(0.01);
+        engine.add_order(limit_order(OrderSide::Buy, dec!(999.99) - offset, dec!(10))).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(1000) + offset, dec!(10))).unwrap();
+    }
+    engine
+}
+
+fn bench_passive_add_cancel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("passive_add_cancel");
+    group.throughput(Throughput::Elements(1));
+
+    for depth in DEPTHS {
+        let engine = engine_with_depth(depth);
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &depth, |b, _| {
+            b.iter(|| {
+                let (order, _) = engine.add_order(limit_order(OrderSide::Buy, dec!(500), dec!(1))).unwrap();
+                black_box(engine.cancel_order(SYMBOL, order.id).unwrap());
+            });
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_aggressive_match(c: &mut Criterion) {
+    let mut group = c.benchmark_group("aggressive_match");
+    group.throughput(Throughput::Elements(1));
+
+    for depth in DEPTHS {
+        let engine = engine_with_depth(depth);
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &depth, |b, _| {
+            b.iter(|| {
+                // Take out the best ask, then put it back
+                let (_, trades) = engine.add_order(limit_order(OrderSide::Buy, dec!(1000), dec!(10))).unwrap();
+                black_box(trades);
+                engine.add_order(limit_order(OrderSide::Sell, dec!(1000), dec!(10))).unwrap();
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_passive_add_cancel, bench_aggressive_match);
+criterion_main!(benches);
@@ -1,5 +1,6 @@
 use crate::algorithms::{AlgorithmStatus, TwapAlgorithm, VwapAlgorithm};
 use crate::engine::OrderBookEngine;
+use crate::persistence::AlgorithmSnapshot;
 use crate::websocket::Broadcaster;
 use chrono::Utc;
 use std::collections::HashMap;
@@ -17,15 +18,46 @@ pub struct AlgorithmManager {
 }
 
 impl AlgorithmManager {
+    /// Create a manager, picking up the algorithms the engine recovered from a snapshot
     pub fn new(engine: Arc<OrderBookEngine>, broadcaster: Broadcaster) -> Self {
+        let recovered = engine.take_recovered_algorithms().unwrap_or_else(|e| {
+            error!("Failed to take recovered algorithms: {}", e);
+            AlgorithmSnapshot::default()
+        });
+        if !recovered.twap.is_empty() || !recovered.vwap.is_empty() {
+            info!(
+                "Restored {} TWAP and {} VWAP algorithms from snapshot",
+                recovered.twap.len(),
+                recovered.vwap.len()
+            );
+        }
+
+        let twap_algos = recovered.twap.into_iter().map(|twap| (twap.id, twap)).collect();
+        let vwap_algos = recovered
+            .vwap
+            .into_iter()
+            .map(|mut vwap| {
+                vwap.rebuild_target_curve();
+                (vwap.id, vwap)
+            })
+            .collect();
+
         Self {
-            twap_algos: Arc::new(RwLock::new(HashMap::new())),
-            vwap_algos: Arc::new(RwLock::new(HashMap::new())),
+            twap_algos: Arc::new(RwLock::new(twap_algos)),
+            vwap_algos: Arc::new(RwLock::new(vwap_algos)),
             engine,
             broadcaster,
         }
     }
 
+    /// State of every algorithm, for engine checkpoints
+    pub fn snapshot(&self) -> Result<AlgorithmSnapshot, String> {
+        Ok(AlgorithmSnapshot {
+            twap: self.get_all_twap()?,
+            vwap: self.get_all_vwap()?,
+        })
+    }
+
     /// Submit a TWAP algorithm for execution
     pub fn submit_twap(&self, mut twap: TwapAlgorithm) -> Result<Uuid, String> {
         twap.start();
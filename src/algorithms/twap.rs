@@ -7,6 +7,7 @@ use utoipa::ToSchema;
 
 use crate::models::{Order, OrderSide, OrderType, OrderStatus, TimeInForce};
 use crate::models::order::SelfTradePreventionMode;
+use crate::utils::clock;
 
 /// TWAP execution algorithm
 /// Divides order evenly across time intervals
@@ -18,9 +19,11 @@ pub struct TwapAlgorithm {
     pub user_id: String,
 
     /// Total quantity to execute
+    #[serde(with = "crate::models::decimal")]
     pub total_quantity: Decimal,
 
     /// Quantity already executed
+    #[serde(with = "crate::models::decimal")]
     pub executed_quantity: Decimal,
 
     /// Start time of execution window
@@ -36,15 +39,18 @@ pub struct TwapAlgorithm {
     pub slices_completed: u32,
 
     /// Limit price (None = market orders)
+    #[serde(default, with = "crate::models::decimal::option")]
     pub limit_price: Option<Decimal>,
 
     /// Max participation rate (% of market volume)
+    #[serde(default, with = "crate::models::decimal::option")]
     pub max_participation: Option<Decimal>,
 
     /// Algorithm status
     pub status: AlgorithmStatus,
 
     /// Urgency factor: 1.0 = normal, >1 = front-load, <1 = back-load
+    #[serde(with = "crate::models::decimal")]
     pub urgency: Decimal,
 }
 
@@ -69,7 +75,7 @@ impl TwapAlgorithm {
         slice_interval_seconds: i64,
     ) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: clock::new_id(),
             symbol,
             side,
             user_id,
@@ -131,7 +137,7 @@ impl TwapAlgorithm {
         self.slices_completed += 1;
 
         Some(Order {
-            id: Uuid::new_v4(),
+            id: clock::new_id(),
             symbol: self.symbol.clone(),
             side: self.side,
             order_type: if self.limit_price.is_some() {
@@ -150,6 +156,7 @@ impl TwapAlgorithm {
             post_only: false,
             expire_time: None,
             iceberg: None,
+            peg_offset: None,
         })
     }
 
@@ -164,7 +171,7 @@ impl TwapAlgorithm {
 
     /// Calculate execution statistics
     pub fn execution_stats(&self) -> TwapStats {
-        let now = Utc::now();
+        let now = clock::now();
         let expected_progress = if self.end_time > self.start_time {
             let total = (self.end_time - self.start_time).num_milliseconds() as f64;
             let elapsed = (now - self.start_time).num_milliseconds().max(0) as f64;
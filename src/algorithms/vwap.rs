@@ -10,6 +10,7 @@ use utoipa::ToSchema;
 use crate::models::{Order, OrderSide, OrderType, OrderStatus, TimeInForce};
 use crate::models::order::SelfTradePreventionMode;
 use super::twap::AlgorithmStatus;
+use crate::utils::clock;
 
 /// VWAP execution algorithm
 /// Follows historical volume profile to minimize market impact
@@ -20,7 +21,9 @@ pub struct VwapAlgorithm {
     pub side: OrderSide,
     pub user_id: String,
 
+    #[serde(with = "crate::models::decimal")]
     pub total_quantity: Decimal,
+    #[serde(with = "crate::models::decimal")]
     pub executed_quantity: Decimal,
 
     pub start_time: DateTime<Utc>,
@@ -37,7 +40,9 @@ pub struct VwapAlgorithm {
     pub status: AlgorithmStatus,
 
     /// Actual VWAP achieved so far
+    #[serde(with = "crate::models::decimal")]
     pub achieved_vwap: Decimal,
+    #[serde(with = "crate::models::decimal")]
     total_notional: Decimal,
 }
 
@@ -143,7 +148,7 @@ impl VwapAlgorithm {
         let volume_profile = VolumeProfile::us_equity_default();
 
         let mut algo = Self {
-            id: Uuid::new_v4(),
+            id: clock::new_id(),
             symbol,
             side,
             user_id,
@@ -192,6 +197,15 @@ impl VwapAlgorithm {
         }
     }
 
+    /// Rebuild the target curve of a deserialized algorithm
+    ///
+    /// The volume profile and target curve are not serialized; the profile
+    /// comes back as the default one and the curve is derived from it again.
+    pub fn rebuild_target_curve(&mut self) {
+        self.target_curve.clear();
+        self.build_target_curve();
+    }
+
     /// Get the target quantity we should have executed by now
     pub fn target_at(&self, time: DateTime<Utc>) -> Decimal {
         self.target_curve
@@ -227,7 +241,7 @@ impl VwapAlgorithm {
         }
 
         Some(Order {
-            id: Uuid::new_v4(),
+            id: clock::new_id(),
             symbol: self.symbol.clone(),
             side: self.side,
             order_type: OrderType::Market,
@@ -242,6 +256,7 @@ impl VwapAlgorithm {
             post_only: false,
             expire_time: None,
             iceberg: None,
+            peg_offset: None,
         })
     }
 
@@ -276,7 +291,7 @@ impl VwapAlgorithm {
 
     /// Get execution statistics
     pub fn stats(&self) -> VwapStats {
-        let now = Utc::now();
+        let now = clock::now();
         let target = self.target_at(now);
         let actual_progress = if self.total_quantity > Decimal::ZERO {
             (self.executed_quantity / self.total_quantity).to_f64().unwrap_or(0.0)
@@ -0,0 +1,109 @@
+use crate::engine::{Balance, OrderBookEngine, OrderBookError};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Request to deposit or withdraw funds
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TransferRequest {
+    /// Asset to move (e.g. USD, BTC)
+    pub asset: String,
+    #[schema(value_type = String, example = "1000.00")]
+    pub amount: Decimal,
+}
+
+/// One asset balance of an account after a transfer
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AssetBalanceResponse {
+    pub user_id: String,
+    pub asset: String,
+    pub balance: Balance,
+}
+
+/// Every asset balance of an account
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AccountBalancesResponse {
+    pub user_id: String,
+    pub balances: BTreeMap<String, Balance>,
+}
+
+/// Credit funds to an account
+#[utoipa::path(
+    post,
+    path = "/api/v1/accounts/{user_id}/deposit",
+    params(
+        ("user_id" = String, Path, description = "Account owner")
+    ),
+    request_body = TransferRequest,
+    responses(
+        (status = 200, description = "Funds deposited", body = AssetBalanceResponse),
+        (status = 400, description = "Invalid amount or accounts not enabled")
+    ),
+    tag = "accounts"
+)]
+pub async fn deposit(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(user_id): Path<String>,
+    Json(request): Json<TransferRequest>,
+) -> Result<Json<AssetBalanceResponse>, OrderBookError> {
+    let balance = engine.deposit(&user_id, &request.asset, request.amount)?;
+    Ok(Json(AssetBalanceResponse {
+        user_id,
+        asset: request.asset,
+        balance,
+    }))
+}
+
+/// Withdraw funds from an account's available balance
+#[utoipa::path(
+    post,
+    path = "/api/v1/accounts/{user_id}/withdraw",
+    params(
+        ("user_id" = String, Path, description = "Account owner")
+    ),
+    request_body = TransferRequest,
+    responses(
+        (status = 200, description = "Funds withdrawn", body = AssetBalanceResponse),
+        (status = 400, description = "Insufficient funds, invalid amount or accounts not enabled")
+    ),
+    tag = "accounts"
+)]
+pub async fn withdraw(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(user_id): Path<String>,
+    Json(request): Json<TransferRequest>,
+) -> Result<Json<AssetBalanceResponse>, OrderBookError> {
+    let balance = engine.withdraw(&user_id, &request.asset, request.amount)?;
+    Ok(Json(AssetBalanceResponse {
+        user_id,
+        asset: request.asset,
+        balance,
+    }))
+}
+
+/// Get the available and reserved balance of every asset of an account
+#[utoipa::path(
+    get,
+    path = "/api/v1/accounts/{user_id}/balances",
+    params(
+        ("user_id" = String, Path, description = "Account owner")
+    ),
+    responses(
+        (status = 200, description = "Account balances", body = AccountBalancesResponse),
+        (status = 400, description = "Accounts not enabled")
+    ),
+    tag = "accounts"
+)]
+pub async fn get_balances(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<AccountBalancesResponse>, OrderBookError> {
+    let balances = engine.get_balances(&user_id)?;
+    Ok(Json(AccountBalancesResponse { user_id, balances }))
+}
@@ -0,0 +1,99 @@
+use crate::api::responses::TradeResponse;
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::models::{AuctionKind, AuctionResult, AuctionState, IndicativeUncross};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Request to start a call auction
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StartAuctionRequest {
+    pub kind: AuctionKind,
+}
+
+/// Outcome of ending a call auction
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuctionResultResponse {
+    pub symbol: String,
+    pub kind: AuctionKind,
+    /// Price and volume the book uncrossed at (`null` if nothing executed)
+    pub uncross: Option<IndicativeUncross>,
+    pub trades: Vec<TradeResponse>,
+}
+
+impl From<AuctionResult> for AuctionResultResponse {
+    fn from(result: AuctionResult) -> Self {
+        Self {
+            symbol: result.symbol,
+            kind: result.kind,
+            uncross: result.uncross,
+            trades: result.trades.into_iter().map(TradeResponse::from).collect(),
+        }
+    }
+}
+
+/// Start a call auction: orders accumulate without matching until it ends
+#[utoipa::path(
+    post,
+    path = "/api/v1/auctions/{symbol}/start",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    request_body = StartAuctionRequest,
+    responses(
+        (status = 200, description = "Auction started", body = AuctionState),
+        (status = 409, description = "Symbol is already in an auction")
+    ),
+    tag = "auctions"
+)]
+pub async fn start_auction(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+    Json(request): Json<StartAuctionRequest>,
+) -> Result<Json<AuctionState>, OrderBookError> {
+    Ok(Json(engine.start_auction(&symbol, request.kind)?))
+}
+
+/// Get a running auction with its indicative uncross price and volume
+#[utoipa::path(
+    get,
+    path = "/api/v1/auctions/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    responses(
+        (status = 200, description = "Auction state", body = AuctionState),
+        (status = 409, description = "Symbol is not in an auction")
+    ),
+    tag = "auctions"
+)]
+pub async fn get_auction(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<AuctionState>, OrderBookError> {
+    Ok(Json(engine.get_auction(&symbol)?))
+}
+
+/// End a call auction: uncross the book and resume continuous trading
+#[utoipa::path(
+    post,
+    path = "/api/v1/auctions/{symbol}/end",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    responses(
+        (status = 200, description = "Auction uncrossed", body = AuctionResultResponse),
+        (status = 409, description = "Symbol is not in an auction")
+    ),
+    tag = "auctions"
+)]
+pub async fn end_auction(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<AuctionResultResponse>, OrderBookError> {
+    Ok(Json(engine.end_auction(&symbol)?.into()))
+}
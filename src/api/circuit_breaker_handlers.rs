@@ -0,0 +1,134 @@
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::risk::{CircuitBreakerConfig, CircuitBreakerStatus};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Request to halt trading in a symbol
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct HaltRequest {
+    /// Minutes until the symbol reopens through a call auction
+    #[schema(example = 5)]
+    pub duration_minutes: i64,
+}
+
+/// Get the circuit breaker status of every symbol
+#[utoipa::path(
+    get,
+    path = "/api/v1/circuit-breakers",
+    responses(
+        (status = 200, description = "Circuit breaker status by symbol", body = BTreeMap<String, CircuitBreakerStatus>)
+    ),
+    tag = "circuit-breakers"
+)]
+pub async fn get_circuit_breakers(
+    State(engine): State<Arc<OrderBookEngine>>,
+) -> Result<Json<BTreeMap<String, CircuitBreakerStatus>>, OrderBookError> {
+    Ok(Json(engine.get_circuit_breakers()?))
+}
+
+/// Get the circuit breaker status and price band of a symbol
+#[utoipa::path(
+    get,
+    path = "/api/v1/circuit-breakers/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    responses(
+        (status = 200, description = "Circuit breaker status", body = CircuitBreakerStatus)
+    ),
+    tag = "circuit-breakers"
+)]
+pub async fn get_circuit_breaker(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<CircuitBreakerStatus>, OrderBookError> {
+    Ok(Json(engine.get_circuit_breaker(&symbol)?))
+}
+
+/// Halt trading in a symbol; orders are rejected until it reopens
+#[utoipa::path(
+    post,
+    path = "/api/v1/circuit-breakers/{symbol}/halt",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    request_body = HaltRequest,
+    responses(
+        (status = 200, description = "Symbol halted", body = CircuitBreakerStatus),
+        (status = 400, description = "Negative duration")
+    ),
+    tag = "circuit-breakers"
+)]
+pub async fn halt_trading(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+    Json(request): Json<HaltRequest>,
+) -> Result<Json<CircuitBreakerStatus>, OrderBookError> {
+    Ok(Json(engine.halt_trading(&symbol, request.duration_minutes)?))
+}
+
+/// Lift a symbol's halt and return it to normal trading at once
+#[utoipa::path(
+    post,
+    path = "/api/v1/circuit-breakers/{symbol}/resume",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    responses(
+        (status = 200, description = "Trading resumed", body = CircuitBreakerStatus)
+    ),
+    tag = "circuit-breakers"
+)]
+pub async fn resume_trading(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<CircuitBreakerStatus>, OrderBookError> {
+    Ok(Json(engine.resume_trading(&symbol)?))
+}
+
+/// Get the circuit breaker and price band configuration of a symbol
+#[utoipa::path(
+    get,
+    path = "/api/v1/circuit-breakers/{symbol}/config",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    responses(
+        (status = 200, description = "Circuit breaker configuration", body = CircuitBreakerConfig)
+    ),
+    tag = "circuit-breakers"
+)]
+pub async fn get_circuit_breaker_config(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<CircuitBreakerConfig>, OrderBookError> {
+    Ok(Json(engine.get_circuit_breaker_config(&symbol)?))
+}
+
+/// Set the circuit breaker and price band configuration of a symbol
+#[utoipa::path(
+    put,
+    path = "/api/v1/circuit-breakers/{symbol}/config",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    request_body = CircuitBreakerConfig,
+    responses(
+        (status = 200, description = "Circuit breaker status under the new configuration", body = CircuitBreakerStatus),
+        (status = 400, description = "Invalid configuration")
+    ),
+    tag = "circuit-breakers"
+)]
+pub async fn set_circuit_breaker_config(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+    Json(config): Json<CircuitBreakerConfig>,
+) -> Result<Json<CircuitBreakerStatus>, OrderBookError> {
+    Ok(Json(engine.set_circuit_breaker_config(&symbol, config)?))
+}
@@ -0,0 +1,183 @@
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::models::order::{Order, OrderSide, OrderType, SelfTradePreventionMode, TimeInForce};
+use crate::models::stop_order::{StopOrder, StopOrderStatus, StopOrderType, TriggerCondition};
+use crate::models::{ContingencyType, ContingentGroup, LegOrder};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// One leg of a contingent order request
+///
+/// The leg kind follows from which prices are set:
+/// - `price` only: limit order
+/// - neither: market order (entry only)
+/// - `trigger_price` only: stop-market order
+/// - both: stop-limit order
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ContingentLegRequest {
+    pub side: OrderSide,
+    #[schema(value_type = String, example = "10")]
+    pub quantity: Decimal,
+    #[schema(value_type = Option<String>, example = "100.00")]
+    pub price: Option<Decimal>,
+    #[schema(value_type = Option<String>, example = "95.00")]
+    pub trigger_price: Option<Decimal>,
+    pub time_in_force: Option<TimeInForce>,
+}
+
+/// Request to submit an OCO, OTO or bracket order
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SubmitContingentOrderRequest {
+    pub symbol: String,
+    pub user_id: String,
+    pub contingency_type: ContingencyType,
+    /// Entry order (required for OTO and bracket, not allowed for OCO)
+    pub entry: Option<ContingentLegRequest>,
+    /// Contingent legs released together (OCO) or after the entry fills (OTO / bracket)
+    pub legs: Vec<ContingentLegRequest>,
+}
+
+/// Build the order a leg request describes
+fn build_leg(symbol: &str, user_id: &str, leg: &ContingentLegRequest) -> LegOrder {
+    let time_in_force = leg.time_in_force.unwrap_or(TimeInForce::GTC);
+
+    match leg.trigger_price {
+        None => {
+            let order_type = if leg.price.is_some() {
+                OrderType::Limit
+            } else {
+                OrderType::Market
+            };
+            LegOrder::Order(Order::new_with_options(
+                symbol.to_string(),
+                leg.side,
+                order_type,
+                leg.price,
+                leg.quantity,
+                user_id.to_string(),
+                time_in_force,
+                SelfTradePreventionMode::None,
+                false,
+                None,
+            ))
+        }
+        Some(trigger_price) => LegOrder::Stop(StopOrder {
+            id: Uuid::new_v4(),
+            symbol: symbol.to_string(),
+            user_id: user_id.to_string(),
+            trigger_price,
+            // Buy stops fire on a rise, sell stops on a fall
+            trigger_condition: match leg.side {
+                OrderSide::Buy => TriggerCondition::AtOrAbove,
+                OrderSide::Sell => TriggerCondition::AtOrBelow,
+            },
+            stop_type: if leg.price.is_some() {
+                StopOrderType::StopLimit
+            } else {
+                StopOrderType::StopMarket
+            },
+            side: leg.side,
+            quantity: leg.quantity,
+            limit_price: leg.price,
+            trail_amount: None,
+            trail_percent: None,
+            highest_price: None,
+            lowest_price: None,
+            created_at: Utc::now(),
+            expire_time: None,
+            status: StopOrderStatus::Pending,
+            time_in_force,
+            stp_mode: SelfTradePreventionMode::None,
+            post_only: false,
+        }),
+    }
+}
+
+/// Submit a contingent order group
+#[utoipa::path(
+    post,
+    path = "/api/v1/contingent-orders",
+    request_body = SubmitContingentOrderRequest,
+    responses(
+        (status = 201, description = "Contingent order submitted", body = ContingentGroup),
+        (status = 400, description = "Invalid request")
+    ),
+    tag = "contingent-orders"
+)]
+pub async fn submit_contingent_order(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Json(request): Json<SubmitContingentOrderRequest>,
+) -> Result<(StatusCode, Json<ContingentGroup>), OrderBookError> {
+    if request.legs.iter().any(|leg| leg.price.is_none() && leg.trigger_price.is_none()) {
+        return Err(OrderBookError::InvalidContingentOrder(
+            "Only the entry order can be a market order".to_string(),
+        ));
+    }
+
+    let group = ContingentGroup::new(
+        request.symbol.clone(),
+        request.user_id.clone(),
+        request.contingency_type,
+        request
+            .entry
+            .as_ref()
+            .map(|entry| build_leg(&request.symbol, &request.user_id, entry)),
+        request
+            .legs
+            .iter()
+            .map(|leg| build_leg(&request.symbol, &request.user_id, leg))
+            .collect(),
+    );
+
+    let group = engine.submit_contingent_order(group)?;
+    Ok((StatusCode::CREATED, Json(group)))
+}
+
+/// Get a contingent order group
+#[utoipa::path(
+    get,
+    path = "/api/v1/contingent-orders/{group_id}",
+    params(
+        ("group_id" = Uuid, Path, description = "Contingent group ID")
+    ),
+    responses(
+        (status = 200, description = "Contingent order details", body = ContingentGroup),
+        (status = 404, description = "Contingent order not found")
+    ),
+    tag = "contingent-orders"
+)]
+pub async fn get_contingent_order(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(group_id): Path<Uuid>,
+) -> Result<Json<ContingentGroup>, OrderBookError> {
+    Ok(Json(engine.get_contingent_order(group_id)?))
+}
+
+/// Cancel a contingent order group and all of its working legs
+#[utoipa::path(
+    delete,
+    path = "/api/v1/contingent-orders/{group_id}",
+    params(
+        ("group_id" = Uuid, Path, description = "Contingent group ID")
+    ),
+    responses(
+        (status = 200, description = "Contingent order cancelled", body = ContingentGroup),
+        (status = 404, description = "Contingent order not found"),
+        (status = 400, description = "Contingent order already completed or cancelled")
+    ),
+    tag = "contingent-orders"
+)]
+pub async fn cancel_contingent_order(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(group_id): Path<Uuid>,
+) -> Result<Json<ContingentGroup>, OrderBookError> {
+    Ok(Json(engine.cancel_contingent_order(group_id)?))
+}
@@ -148,6 +148,7 @@ impl IntoResponse for DatasourceError {
         let error_response = ErrorResponse {
             error: error_type.to_string(),
             message: error_message,
+            code: None,
         };
 
         (status, Json(error_response)).into_response()
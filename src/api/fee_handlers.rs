@@ -0,0 +1,146 @@
+use crate::engine::{FeeRates, FeeSchedule, FeeSchedules, OrderBookEngine, OrderBookError};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use std::sync::Arc;
+
+/// Get the default, per-symbol and per-user fee schedules
+#[utoipa::path(
+    get,
+    path = "/api/v1/fees/schedules",
+    responses(
+        (status = 200, description = "Fee schedules", body = FeeSchedules)
+    ),
+    tag = "fees"
+)]
+pub async fn get_fee_schedules(
+    State(engine): State<Arc<OrderBookEngine>>,
+) -> Result<Json<FeeSchedules>, OrderBookError> {
+    Ok(Json(engine.get_fee_schedules()?))
+}
+
+/// Set the fee schedule of users and symbols without their own
+#[utoipa::path(
+    put,
+    path = "/api/v1/fees/schedules/default",
+    request_body = FeeSchedule,
+    responses(
+        (status = 200, description = "Fee schedules after the change", body = FeeSchedules),
+        (status = 400, description = "Invalid fee schedule")
+    ),
+    tag = "fees"
+)]
+pub async fn set_default_fee_schedule(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Json(schedule): Json<FeeSchedule>,
+) -> Result<Json<FeeSchedules>, OrderBookError> {
+    engine.set_default_fee_schedule(schedule)?;
+    Ok(Json(engine.get_fee_schedules()?))
+}
+
+/// Set the fee schedule of a symbol
+#[utoipa::path(
+    put,
+    path = "/api/v1/fees/schedules/symbols/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol")
+    ),
+    request_body = FeeSchedule,
+    responses(
+        (status = 200, description = "Fee schedules after the change", body = FeeSchedules),
+        (status = 400, description = "Invalid fee schedule")
+    ),
+    tag = "fees"
+)]
+pub async fn set_symbol_fee_schedule(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+    Json(schedule): Json<FeeSchedule>,
+) -> Result<Json<FeeSchedules>, OrderBookError> {
+    engine.set_symbol_fee_schedule(&symbol, Some(schedule))?;
+    Ok(Json(engine.get_fee_schedules()?))
+}
+
+/// Remove the fee schedule of a symbol; it falls back to the default
+#[utoipa::path(
+    delete,
+    path = "/api/v1/fees/schedules/symbols/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol")
+    ),
+    responses(
+        (status = 200, description = "Fee schedules after the change", body = FeeSchedules)
+    ),
+    tag = "fees"
+)]
+pub async fn remove_symbol_fee_schedule(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<FeeSchedules>, OrderBookError> {
+    engine.set_symbol_fee_schedule(&symbol, None)?;
+    Ok(Json(engine.get_fee_schedules()?))
+}
+
+/// Set the fee schedule of a user, for every symbol they trade
+#[utoipa::path(
+    put,
+    path = "/api/v1/fees/schedules/users/{user_id}",
+    params(
+        ("user_id" = String, Path, description = "User the schedule applies to")
+    ),
+    request_body = FeeSchedule,
+    responses(
+        (status = 200, description = "Fee schedules after the change", body = FeeSchedules),
+        (status = 400, description = "Invalid fee schedule")
+    ),
+    tag = "fees"
+)]
+pub async fn set_user_fee_schedule(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(user_id): Path<String>,
+    Json(schedule): Json<FeeSchedule>,
+) -> Result<Json<FeeSchedules>, OrderBookError> {
+    engine.set_user_fee_schedule(&user_id, Some(schedule))?;
+    Ok(Json(engine.get_fee_schedules()?))
+}
+
+/// Remove the fee schedule of a user; symbol and default schedules apply again
+#[utoipa::path(
+    delete,
+    path = "/api/v1/fees/schedules/users/{user_id}",
+    params(
+        ("user_id" = String, Path, description = "User the schedule applies to")
+    ),
+    responses(
+        (status = 200, description = "Fee schedules after the change", body = FeeSchedules)
+    ),
+    tag = "fees"
+)]
+pub async fn remove_user_fee_schedule(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<FeeSchedules>, OrderBookError> {
+    engine.set_user_fee_schedule(&user_id, None)?;
+    Ok(Json(engine.get_fee_schedules()?))
+}
+
+/// Get the fee tier and rates a user currently pays trading a symbol
+#[utoipa::path(
+    get,
+    path = "/api/v1/fees/rates/{user_id}/{symbol}",
+    params(
+        ("user_id" = String, Path, description = "User"),
+        ("symbol" = String, Path, description = "Trading symbol")
+    ),
+    responses(
+        (status = 200, description = "Current fee rates and 30-day volume", body = FeeRates)
+    ),
+    tag = "fees"
+)]
+pub async fn get_fee_rates(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path((user_id, symbol)): Path<(String, String)>,
+) -> Result<Json<FeeRates>, OrderBookError> {
+    Ok(Json(engine.get_fee_rates(&user_id, &symbol)?))
+}
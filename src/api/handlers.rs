@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
@@ -9,15 +9,20 @@ use serde::Deserialize;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::disruptor::IngestionPipeline;
 use crate::engine::{OrderBookEngine, OrderBookError};
 use crate::metrics::{calculate_spread_metrics, MicrostructureMetrics};
 use crate::models::Order;
+use crate::risk::{RiskError, RiskRejectReason};
 
 use super::responses::*;
 
 /// Shared application state
 pub type AppState = Arc<OrderBookEngine>;
 
+/// Order ingestion pipeline that order entry endpoints publish commands to
+pub type PipelineState = Arc<IngestionPipeline>;
+
 /// Query parameters for order book depth
 #[derive(Debug, Deserialize)]
 pub struct DepthQuery {
@@ -48,22 +53,57 @@ impl IntoResponse for OrderBookError {
             OrderBookError::InvalidPrice(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             OrderBookError::InvalidQuantity(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             OrderBookError::InvalidExpireTime(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            OrderBookError::InvalidAmendment(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            OrderBookError::InvalidContingentOrder(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            OrderBookError::InvalidTradingCalendar(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            OrderBookError::InvalidFeeSchedule(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            OrderBookError::InvalidMatchingPolicy(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            OrderBookError::InvalidRiskLimits(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            OrderBookError::InvalidCircuitBreakerConfig(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            OrderBookError::InvalidCommand(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             OrderBookError::InsufficientLiquidity => (StatusCode::BAD_REQUEST, self.to_string()),
             OrderBookError::SelfTrade => (StatusCode::BAD_REQUEST, self.to_string()),
+            OrderBookError::InsufficientFunds { .. } => (StatusCode::BAD_REQUEST, self.to_string()),
+            OrderBookError::RiskRejected(rejection) if rejection.reason == RiskRejectReason::MessageRate => {
+                (StatusCode::TOO_MANY_REQUESTS, self.to_string())
+            }
+            OrderBookError::RiskRejected(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
+            OrderBookError::CircuitBreakerRejected(RiskError::TradingHalted { .. }) => {
+                (StatusCode::CONFLICT, self.to_string())
+            }
+            OrderBookError::CircuitBreakerRejected(_) => (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()),
             OrderBookError::DuplicateOrder(_) => (StatusCode::CONFLICT, self.to_string()),
             OrderBookError::InvalidSymbol(_) => (StatusCode::BAD_REQUEST, self.to_string()),
             OrderBookError::OrderNotActive(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            OrderBookError::InvalidTradingPhase(_) => (StatusCode::CONFLICT, self.to_string()),
             OrderBookError::MatchingError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
             }
             OrderBookError::LockError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
             }
+            OrderBookError::PersistenceError(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
+            }
+            OrderBookError::PipelineUnavailable(_) => {
+                (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
+            }
+            OrderBookError::Standby => {
+                (StatusCode::SERVICE_UNAVAILABLE, self.to_string())
+            }
+            OrderBookError::ReplicationError(_) => {
+                (StatusCode::CONFLICT, self.to_string())
+            }
         };
 
         let body = Json(ErrorResponse {
             error: status.to_string(),
             message: error_message,
+            code: match &self {
+                OrderBookError::RiskRejected(rejection) => Some(rejection.reason.code().to_string()),
+                OrderBookError::CircuitBreakerRejected(error) => Some(error.code().to_string()),
+                _ => None,
+            },
         });
 
         (status, body).into_response()
@@ -99,9 +139,30 @@ pub async fn health_check() -> impl IntoResponse {
     )
 )]
 pub async fn submit_order(
-    State(engine): State<AppState>,
+    Extension(pipeline): Extension<PipelineState>,
     Json(request): Json<SubmitOrderRequest>,
 ) -> Result<(StatusCode, Json<SubmitOrderResponse>), OrderBookError> {
+    let order = order_from_request(request);
+
+    // Publish to the matcher of the symbol's shard
+    let (filled_order, trades) = pipeline.submit_order(order).await?;
+
+    // Convert trades to response format
+    let trade_responses: Vec<TradeResponse> = trades.into_iter().map(|t| t.into()).collect();
+
+    let response = SubmitOrderResponse {
+        order_id: filled_order.id,
+        status: filled_order.status,
+        filled_quantity: filled_order.filled_quantity,
+        trades: trade_responses,
+        timestamp: filled_order.timestamp,
+    };
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Build the order described by a submit request
+pub fn order_from_request(request: SubmitOrderRequest) -> Order {
     // Create iceberg config if both fields are provided
     let iceberg = if let (Some(total), Some(display)) = (request.iceberg_total_quantity, request.iceberg_display_quantity) {
         Some(crate::models::IcebergConfig::new(total, display))
@@ -122,6 +183,7 @@ pub async fn submit_order(
         request.post_only,
         request.expire_time,
     );
+    order.peg_offset = request.peg_offset;
 
     // Attach iceberg config if provided
     if let Some(iceberg_config) = iceberg {
@@ -130,21 +192,7 @@ pub async fn submit_order(
         order.quantity = request.iceberg_display_quantity.unwrap();
     }
 
-    // Add to engine
-    let (filled_order, trades) = engine.add_order(order)?;
-
-    // Convert trades to response format
-    let trade_responses: Vec<TradeResponse> = trades.into_iter().map(|t| t.into()).collect();
-
-    let response = SubmitOrderResponse {
-        order_id: filled_order.id,
-        status: filled_order.status,
-        filled_quantity: filled_order.filled_quantity,
-        trades: trade_responses,
-        timestamp: filled_order.timestamp,
-    };
-
-    Ok((StatusCode::CREATED, Json(response)))
+    order
 }
 
 /// Get order status
@@ -185,10 +233,10 @@ pub async fn get_order(
     )
 )]
 pub async fn cancel_order(
-    State(engine): State<AppState>,
+    Extension(pipeline): Extension<PipelineState>,
     Path((symbol, order_id)): Path<(String, Uuid)>,
 ) -> Result<Json<CancelOrderResponse>, OrderBookError> {
-    let cancelled_order = engine.cancel_order(&symbol, order_id)?;
+    let cancelled_order = pipeline.cancel_order(&symbol, order_id).await?;
 
     let response = CancelOrderResponse {
         order_id: cancelled_order.id,
@@ -200,6 +248,44 @@ pub async fn cancel_order(
     Ok(Json(response))
 }
 
+/// Amend an order
+#[utoipa::path(
+    patch,
+    path = "/api/v1/orders/{symbol}/{order_id}",
+    tag = "Orders",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)"),
+        ("order_id" = Uuid, Path, description = "Order ID")
+    ),
+    request_body = AmendOrderRequest,
+    responses(
+        (status = 200, description = "Order amended", body = AmendOrderResponse),
+        (status = 404, description = "Order not found", body = ErrorResponse),
+        (status = 400, description = "Invalid amendment", body = ErrorResponse)
+    )
+)]
+pub async fn amend_order(
+    Extension(pipeline): Extension<PipelineState>,
+    Path((symbol, order_id)): Path<(String, Uuid)>,
+    Json(request): Json<AmendOrderRequest>,
+) -> Result<Json<AmendOrderResponse>, OrderBookError> {
+    let (amended_order, trades) = pipeline
+        .amend_order(&symbol, order_id, request.price, request.quantity)
+        .await?;
+
+    let response = AmendOrderResponse {
+        order_id: amended_order.id,
+        status: amended_order.status,
+        price: amended_order.price,
+        quantity: amended_order.quantity,
+        filled_quantity: amended_order.filled_quantity,
+        trades: trades.into_iter().map(|t| t.into()).collect(),
+        timestamp: amended_order.timestamp,
+    };
+
+    Ok(Json(response))
+}
+
 /// Convert a price level to response format
 fn price_level_to_response(level: &crate::models::PriceLevel) -> PriceLevelResponse {
     PriceLevelResponse {
@@ -0,0 +1,78 @@
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::models::Instrument;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use std::sync::Arc;
+
+/// List the registered instruments
+#[utoipa::path(
+    get,
+    path = "/api/v1/instruments",
+    responses(
+        (status = 200, description = "Registered instruments", body = Vec<Instrument>)
+    ),
+    tag = "instruments"
+)]
+pub async fn list_instruments(
+    State(engine): State<Arc<OrderBookEngine>>,
+) -> Result<Json<Vec<Instrument>>, OrderBookError> {
+    Ok(Json(engine.get_instruments()?))
+}
+
+/// Get the reference data of an instrument
+#[utoipa::path(
+    get,
+    path = "/api/v1/instruments/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    responses(
+        (status = 200, description = "Instrument reference data", body = Instrument),
+        (status = 400, description = "Unknown symbol")
+    ),
+    tag = "instruments"
+)]
+pub async fn get_instrument(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<Instrument>, OrderBookError> {
+    engine
+        .get_instrument(&symbol)?
+        .map(Json)
+        .ok_or_else(|| OrderBookError::InvalidSymbol(format!("Unknown symbol: {}", symbol)))
+}
+
+/// Add or replace the reference data of an instrument
+#[utoipa::path(
+    put,
+    path = "/api/v1/instruments/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    request_body = Instrument,
+    responses(
+        (status = 200, description = "Instrument registered", body = Instrument),
+        (status = 400, description = "Invalid reference data")
+    ),
+    tag = "instruments"
+)]
+pub async fn put_instrument(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+    Json(instrument): Json<Instrument>,
+) -> Result<Json<Instrument>, OrderBookError> {
+    if instrument.symbol != symbol {
+        return Err(OrderBookError::InvalidSymbol(format!(
+            "Body symbol {} does not match path symbol {}",
+            instrument.symbol, symbol
+        )));
+    }
+    if instrument.tick_size <= rust_decimal::Decimal::ZERO {
+        return Err(OrderBookError::InvalidPrice("tick_size must be positive".to_string()));
+    }
+
+    engine.register_instrument(instrument.clone())?;
+    Ok(Json(instrument))
+}
@@ -0,0 +1,66 @@
+use crate::engine::{MatchingPolicyConfig, OrderBookEngine, OrderBookError};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Get the matching policy of every symbol that does not match FIFO
+#[utoipa::path(
+    get,
+    path = "/api/v1/matching-policies",
+    responses(
+        (status = 200, description = "Matching policy by symbol", body = BTreeMap<String, MatchingPolicyConfig>)
+    ),
+    tag = "matching-policies"
+)]
+pub async fn get_matching_policies(
+    State(engine): State<Arc<OrderBookEngine>>,
+) -> Result<Json<BTreeMap<String, MatchingPolicyConfig>>, OrderBookError> {
+    Ok(Json(engine.get_matching_policies()?))
+}
+
+/// Get the matching policy of a symbol
+#[utoipa::path(
+    get,
+    path = "/api/v1/matching-policies/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    responses(
+        (status = 200, description = "Matching policy", body = MatchingPolicyConfig)
+    ),
+    tag = "matching-policies"
+)]
+pub async fn get_matching_policy(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<MatchingPolicyConfig>, OrderBookError> {
+    Ok(Json(engine.get_matching_policy(&symbol)?))
+}
+
+/// Set how incoming orders are allocated across a price level of a symbol
+///
+/// Applies to orders matched from now on.
+#[utoipa::path(
+    put,
+    path = "/api/v1/matching-policies/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    request_body = MatchingPolicyConfig,
+    responses(
+        (status = 200, description = "Matching policy updated", body = MatchingPolicyConfig),
+        (status = 400, description = "Invalid matching policy")
+    ),
+    tag = "matching-policies"
+)]
+pub async fn set_matching_policy(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+    Json(policy): Json<MatchingPolicyConfig>,
+) -> Result<Json<MatchingPolicyConfig>, OrderBookError> {
+    engine.set_matching_policy(&symbol, policy)?;
+    Ok(Json(engine.get_matching_policy(&symbol)?))
+}
@@ -1,11 +1,22 @@
+pub mod account_handlers;
 pub mod algorithm_handlers;
+pub mod auction_handlers;
+pub mod circuit_breaker_handlers;
+pub mod contingent_order_handlers;
 pub mod database_handlers;
 pub mod datasource_handlers;
+pub mod fee_handlers;
 pub mod handlers;
+pub mod instrument_handlers;
+pub mod matching_policy_handlers;
 pub mod openapi;
+pub mod position_handlers;
 pub mod rabbitmq_handlers;
+pub mod replication_handlers;
 pub mod responses;
+pub mod risk_handlers;
 pub mod routes;
+pub mod session_handlers;
 pub mod stop_order_handlers;
 pub mod testing_handlers;
 
@@ -15,4 +26,4 @@ pub use handlers::*;
 pub use openapi::*;
 pub use rabbitmq_handlers::*;
 pub use responses::*;
-pub use routes::create_router;
+pub use routes::{create_replication_router, create_router};
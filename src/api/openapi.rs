@@ -30,6 +30,7 @@ use crate::rabbitmq::{RabbitMQConfig, ReconnectConfig, PublisherStats};
         handlers::submit_order,
         handlers::get_order,
         handlers::cancel_order,
+        handlers::amend_order,
         handlers::get_order_book,
         handlers::get_spread_metrics,
         handlers::get_trades,
@@ -46,6 +47,8 @@ use crate::rabbitmq::{RabbitMQConfig, ReconnectConfig, PublisherStats};
             SubmitOrderResponse,
             OrderResponse,
             CancelOrderResponse,
+            AmendOrderRequest,
+            AmendOrderResponse,
             TradeResponse,
             PriceLevelResponse,
             OrderBookResponse,
@@ -87,6 +90,7 @@ pub struct ApiDocV1;
         handlers::submit_order,
         handlers::get_order,
         handlers::cancel_order,
+        handlers::amend_order,
         handlers::get_order_book,
         handlers::get_spread_metrics,
         handlers::get_trades,
@@ -112,6 +116,8 @@ pub struct ApiDocV1;
             SubmitOrderResponse,
             OrderResponse,
             CancelOrderResponse,
+            AmendOrderRequest,
+            AmendOrderResponse,
             TradeResponse,
             PriceLevelResponse,
             OrderBookResponse,
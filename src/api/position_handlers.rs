@@ -0,0 +1,57 @@
+use crate::engine::OrderBookError;
+use crate::positions::{Position, PositionTracker};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Every position of a user
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PositionsResponse {
+    pub user_id: String,
+    pub positions: Vec<Position>,
+}
+
+/// Get a user's positions in every symbol they have traded
+#[utoipa::path(
+    get,
+    path = "/api/v1/positions/{user_id}",
+    params(
+        ("user_id" = String, Path, description = "Position owner")
+    ),
+    responses(
+        (status = 200, description = "Positions with realized and unrealized PnL", body = PositionsResponse)
+    ),
+    tag = "positions"
+)]
+pub async fn get_positions(
+    State(tracker): State<Arc<PositionTracker>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<PositionsResponse>, OrderBookError> {
+    let positions = tracker.positions(&user_id)?;
+    Ok(Json(PositionsResponse { user_id, positions }))
+}
+
+/// Get a user's position in one symbol
+#[utoipa::path(
+    get,
+    path = "/api/v1/positions/{user_id}/{symbol}",
+    params(
+        ("user_id" = String, Path, description = "Position owner"),
+        ("symbol" = String, Path, description = "Trading symbol")
+    ),
+    responses(
+        (status = 200, description = "Position with realized and unrealized PnL", body = Position),
+        (status = 404, description = "Symbol not found")
+    ),
+    tag = "positions"
+)]
+pub async fn get_position(
+    State(tracker): State<Arc<PositionTracker>>,
+    Path((user_id, symbol)): Path<(String, String)>,
+) -> Result<Json<Position>, OrderBookError> {
+    Ok(Json(tracker.position(&user_id, &symbol)?))
+}
@@ -0,0 +1,77 @@
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::replication::{FollowerLag, FollowerStatus, ReplicationFollower, ReplicationServer};
+use axum::{extract::State, Json};
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Shared state for replication endpoints
+#[derive(Clone)]
+pub struct ReplicationState {
+    pub engine: Arc<OrderBookEngine>,
+    /// Set when this node streams its log to followers
+    pub server: Option<Arc<ReplicationServer>>,
+    /// Set when this node was started as a follower
+    pub follower: Option<Arc<ReplicationFollower>>,
+}
+
+/// Replication role and lag of this node
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReplicationStatusResponse {
+    /// `primary` or `follower`
+    pub role: String,
+    /// Sequence number of the last journaled (or replicated) event
+    pub last_sequence: u64,
+    /// Followers streaming from this node
+    pub followers: Vec<FollowerLag>,
+    /// Progress against the primary, for a node started as a follower
+    pub primary: Option<FollowerStatus>,
+}
+
+fn replication_status(state: &ReplicationState) -> Result<ReplicationStatusResponse, OrderBookError> {
+    Ok(ReplicationStatusResponse {
+        role: if state.engine.is_standby() { "follower" } else { "primary" }.to_string(),
+        last_sequence: state.engine.last_sequence()?,
+        followers: match &state.server {
+            Some(server) => server.followers()?,
+            None => Vec::new(),
+        },
+        primary: state.follower.as_ref().map(|follower| follower.status()),
+    })
+}
+
+/// Get the node's replication role and lag metrics
+#[utoipa::path(
+    get,
+    path = "/api/v1/replication/status",
+    responses(
+        (status = 200, description = "Replication status", body = ReplicationStatusResponse)
+    ),
+    tag = "replication"
+)]
+pub async fn get_replication_status(
+    State(state): State<ReplicationState>,
+) -> Result<Json<ReplicationStatusResponse>, OrderBookError> {
+    Ok(Json(replication_status(&state)?))
+}
+
+/// Promote a follower to primary: stop following and start taking orders
+#[utoipa::path(
+    post,
+    path = "/api/v1/replication/promote",
+    responses(
+        (status = 200, description = "Promoted; new events continue after `last_sequence`", body = ReplicationStatusResponse),
+        (status = 409, description = "Node is not a follower or was already promoted")
+    ),
+    tag = "replication"
+)]
+pub async fn promote(
+    State(state): State<ReplicationState>,
+) -> Result<Json<ReplicationStatusResponse>, OrderBookError> {
+    let follower = state
+        .follower
+        .as_ref()
+        .ok_or_else(|| OrderBookError::ReplicationError("node is not a follower".to_string()))?;
+    follower.promote().await?;
+    Ok(Json(replication_status(&state)?))
+}
@@ -7,7 +7,7 @@ use uuid::Uuid;
 use crate::models::{Order, OrderSide, OrderStatus, OrderType, SelfTradePreventionMode, TimeInForce, Trade};
 
 /// Request to submit a new order
-#[derive(Debug, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct SubmitOrderRequest {
     pub symbol: String,
     pub side: OrderSide,
@@ -37,6 +37,10 @@ pub struct SubmitOrderRequest {
     #[schema(value_type = Option<String>, example = "100")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub iceberg_display_quantity: Option<Decimal>,
+    /// Offset added to the peg reference price (pegged order types only)
+    #[schema(value_type = Option<String>, example = "-0.01")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peg_offset: Option<Decimal>,
 }
 
 /// Response after submitting an order
@@ -97,6 +101,9 @@ pub struct OrderResponse {
     pub post_only: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expire_time: Option<DateTime<Utc>>,
+    #[schema(value_type = Option<String>, example = "-0.01")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peg_offset: Option<Decimal>,
     pub timestamp: DateTime<Utc>,
 }
 
@@ -115,6 +122,7 @@ impl From<Order> for OrderResponse {
             stp_mode: order.stp_mode,
             post_only: order.post_only,
             expire_time: order.expire_time,
+            peg_offset: order.peg_offset,
             timestamp: order.timestamp,
         }
     }
@@ -131,6 +139,33 @@ pub struct CancelOrderResponse {
     pub remaining_quantity: Decimal,
 }
 
+/// Request to amend (modify) a resting order
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AmendOrderRequest {
+    /// New limit price (changing the price loses time priority)
+    #[schema(value_type = Option<String>, example = "150.25")]
+    pub price: Option<Decimal>,
+    /// New total order quantity (increasing it loses time priority)
+    #[schema(value_type = Option<String>, example = "80")]
+    pub quantity: Option<Decimal>,
+}
+
+/// Response after amending an order
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AmendOrderResponse {
+    pub order_id: Uuid,
+    pub status: OrderStatus,
+    #[schema(value_type = Option<String>, example = "150.25")]
+    pub price: Option<Decimal>,
+    #[schema(value_type = String, example = "80")]
+    pub quantity: Decimal,
+    #[schema(value_type = String, example = "0")]
+    pub filled_quantity: Decimal,
+    /// Trades executed if the new price crossed the book
+    pub trades: Vec<TradeResponse>,
+    pub timestamp: DateTime<Utc>,
+}
+
 /// Price level in order book
 #[derive(Debug, Serialize, ToSchema)]
 pub struct PriceLevelResponse {
@@ -188,4 +223,7 @@ pub struct ExchangeMetricsResponse {
 pub struct ErrorResponse {
     pub error: String,
     pub message: String,
+    /// Machine-readable reason of a rejection (e.g. a risk check's `MAX_OPEN_ORDERS`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
 }
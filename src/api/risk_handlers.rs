@@ -0,0 +1,120 @@
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::risk::{AccountRiskStatus, RiskLimits};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Default risk limits and the accounts with their own
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RiskLimitsResponse {
+    pub default: RiskLimits,
+    pub accounts: BTreeMap<String, RiskLimits>,
+}
+
+fn limits_response(engine: &OrderBookEngine) -> Result<Json<RiskLimitsResponse>, OrderBookError> {
+    Ok(Json(RiskLimitsResponse {
+        default: engine.get_default_risk_limits()?,
+        accounts: engine.get_account_risk_overrides()?,
+    }))
+}
+
+/// Get the default risk limits and every account's own limits
+#[utoipa::path(
+    get,
+    path = "/api/v1/risk/limits",
+    responses(
+        (status = 200, description = "Risk limits", body = RiskLimitsResponse)
+    ),
+    tag = "risk"
+)]
+pub async fn get_risk_limits(
+    State(engine): State<Arc<OrderBookEngine>>,
+) -> Result<Json<RiskLimitsResponse>, OrderBookError> {
+    limits_response(&engine)
+}
+
+/// Set the risk limits of accounts without their own
+#[utoipa::path(
+    put,
+    path = "/api/v1/risk/limits/default",
+    request_body = RiskLimits,
+    responses(
+        (status = 200, description = "Risk limits after the change", body = RiskLimitsResponse),
+        (status = 400, description = "Invalid risk limits")
+    ),
+    tag = "risk"
+)]
+pub async fn set_default_risk_limits(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Json(limits): Json<RiskLimits>,
+) -> Result<Json<RiskLimitsResponse>, OrderBookError> {
+    engine.set_default_risk_limits(limits)?;
+    limits_response(&engine)
+}
+
+/// Get the risk limits that apply to an account and its current use of them
+#[utoipa::path(
+    get,
+    path = "/api/v1/risk/accounts/{user_id}",
+    params(
+        ("user_id" = String, Path, description = "Account owner")
+    ),
+    responses(
+        (status = 200, description = "Account risk limits and usage", body = AccountRiskStatus)
+    ),
+    tag = "risk"
+)]
+pub async fn get_account_risk(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<AccountRiskStatus>, OrderBookError> {
+    Ok(Json(engine.get_account_risk(&user_id)?))
+}
+
+/// Give an account its own risk limits, replacing the defaults
+#[utoipa::path(
+    put,
+    path = "/api/v1/risk/accounts/{user_id}/limits",
+    params(
+        ("user_id" = String, Path, description = "Account owner")
+    ),
+    request_body = RiskLimits,
+    responses(
+        (status = 200, description = "Account risk limits and usage", body = AccountRiskStatus),
+        (status = 400, description = "Invalid risk limits")
+    ),
+    tag = "risk"
+)]
+pub async fn set_account_risk_limits(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(user_id): Path<String>,
+    Json(limits): Json<RiskLimits>,
+) -> Result<Json<AccountRiskStatus>, OrderBookError> {
+    engine.set_account_risk_limits(&user_id, Some(limits))?;
+    Ok(Json(engine.get_account_risk(&user_id)?))
+}
+
+/// Remove an account's own risk limits; it falls back to the defaults
+#[utoipa::path(
+    delete,
+    path = "/api/v1/risk/accounts/{user_id}/limits",
+    params(
+        ("user_id" = String, Path, description = "Account owner")
+    ),
+    responses(
+        (status = 200, description = "Account risk limits and usage", body = AccountRiskStatus)
+    ),
+    tag = "risk"
+)]
+pub async fn remove_account_risk_limits(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(user_id): Path<String>,
+) -> Result<Json<AccountRiskStatus>, OrderBookError> {
+    engine.set_account_risk_limits(&user_id, None)?;
+    Ok(Json(engine.get_account_risk(&user_id)?))
+}
@@ -1,6 +1,6 @@
 use axum::{
-    extract::State,
-    routing::{delete, get, post},
+    extract::{Extension, State},
+    routing::{delete, get, patch, post, put},
     Json,
     Router,
 };
@@ -11,23 +11,45 @@ use utoipa_swagger_ui::SwaggerUi;
 use crate::algorithms::AlgorithmManager;
 use crate::api::database_handlers::DatabaseState;
 use crate::datasource::DatasourceManager;
-use crate::engine::OrderBookEngine;
+use crate::disruptor::{
+    EventConsumer, IngestionPipeline, JournalConsumer, MarketDataPublisher, PipelineConfig, SnapshotConsumer,
+};
+use crate::engine::{run_checkpointer, run_expiry_sweeper, run_reopening_timer, OrderBookEngine};
+use crate::fix::FixAcceptor;
+use crate::gateway::{ExecutionReportPublisher, OrderGateway, OrderRegistry};
 use crate::rabbitmq::RabbitMQService;
-use crate::websocket::{websocket_handler, Broadcaster, WsState};
+use crate::positions::PositionTracker;
+use crate::websocket::{
+    run_auction_publisher, run_position_publisher, run_trading_status_publisher, websocket_handler, Broadcaster, WsState,
+};
 use crate::market_data::TickDistributor;
 use crate::ctrader_fix::market_data::MarketTick;
 use crate::testing::{OrderProducer, ProducerConfig, TestingState};
 use tokio::sync::mpsc;
 
+use super::account_handlers;
 use super::algorithm_handlers::{self, AlgorithmState};
+use super::auction_handlers;
+use super::circuit_breaker_handlers;
+use super::contingent_order_handlers;
 use super::database_handlers::*;
 use super::datasource_handlers::{self, DatasourceState};
+use super::fee_handlers;
 use super::handlers::*;
+use super::instrument_handlers;
+use super::matching_policy_handlers;
 use super::openapi::{ApiDocV1, ApiDocV2};
+use super::position_handlers;
 use super::rabbitmq_handlers::{self, RabbitMQState};
+use super::replication_handlers::{self, ReplicationState};
+use super::risk_handlers;
+use super::session_handlers;
 use super::stop_order_handlers;
 use super::testing_handlers;
 
+/// Pipeline events between checkpoints of the snapshot consumer
+const EVENTS_PER_CHECKPOINT: u64 = 1_000_000;
+
 /// Create the API router with Swagger UI, WebSocket support, and TickDistributor
 pub fn create_router(
     engine: Arc<OrderBookEngine>,
@@ -38,10 +60,63 @@ pub fn create_router(
     tick_distributor: Option<Arc<TickDistributor>>,
     tick_distributor_tx: Option<mpsc::UnboundedSender<MarketTick>>,
 ) -> Router {
+    // Create algorithm manager (its state goes into the engine's snapshots)
+    let algorithm_manager = Arc::new(AlgorithmManager::new(engine.clone(), broadcaster.clone()));
+
+    // Order entry (REST, WebSocket, the binary gateway and FIX) goes through
+    // the ingestion pipeline; its market data consumer publishes the resulting
+    // trades and tickers, gateway orders get execution reports, and the WAL
+    // is synced and checkpointed as events go by
+    let gateway_orders = Arc::new(OrderRegistry::new());
+    let mut consumers: Vec<Box<dyn EventConsumer>> = vec![
+        Box::new(MarketDataPublisher::new(broadcaster.clone())),
+        Box::new(ExecutionReportPublisher::new(gateway_orders.clone())),
+    ];
+    if matches!(engine.wal_dir(), Ok(Some(_))) {
+        consumers.push(Box::new(JournalConsumer::new(engine.clone())));
+    }
+    if engine.has_snapshot_store() {
+        consumers.push(Box::new(SnapshotConsumer::new(
+            engine.clone(),
+            algorithm_manager.clone(),
+            EVENTS_PER_CHECKPOINT,
+        )));
+    }
+    let pipeline = Arc::new(IngestionPipeline::start(engine.clone(), PipelineConfig::default(), consumers));
+
+    // Binary order entry over TCP with ORDER_GATEWAY_LISTEN=host:port
+    if let Ok(addr) = std::env::var("ORDER_GATEWAY_LISTEN") {
+        let gateway = Arc::new(OrderGateway::new(pipeline.clone(), gateway_orders.clone()));
+        tokio::spawn(async move {
+            match tokio::net::TcpListener::bind(&addr).await {
+                Ok(listener) => gateway.serve(listener).await,
+                Err(e) => tracing::error!("❌ Failed to bind order gateway on {}: {}", addr, e),
+            }
+        });
+    }
+
+    // FIX 4.4 order entry with FIX_ACCEPTOR_LISTEN=host:port, as
+    // FIX_ACCEPTOR_COMP_ID (default ORDERBOOK)
+    if let Ok(addr) = std::env::var("FIX_ACCEPTOR_LISTEN") {
+        let comp_id = std::env::var("FIX_ACCEPTOR_COMP_ID").unwrap_or_else(|_| "ORDERBOOK".to_string());
+        let acceptor = Arc::new(FixAcceptor::new(comp_id, pipeline.clone(), gateway_orders));
+        tokio::spawn(async move {
+            match tokio::net::TcpListener::bind(&addr).await {
+                Ok(listener) => acceptor.serve(listener).await,
+                Err(e) => tracing::error!("❌ Failed to bind FIX acceptor on {}: {}", addr, e),
+            }
+        });
+    }
+
+    // Positions follow the engine's trades, marked to FIX ticks where they exist
+    let position_tracker = Arc::new(PositionTracker::new(engine.clone()));
+
     // Create WebSocket state
     let ws_state = Arc::new(WsState {
         broadcaster: broadcaster.clone(),
         engine: engine.clone(),
+        pipeline: pipeline.clone(),
+        positions: position_tracker.clone(),
     });
 
     // Create datasource state (includes optional RabbitMQ service and tick distributor tx)
@@ -62,13 +137,57 @@ pub fn create_router(
         producer.run().await;
     });
 
-    // Create algorithm manager and spawn executor
-    let algorithm_manager = Arc::new(AlgorithmManager::new(engine.clone(), broadcaster.clone()));
+    // Spawn the algorithm executor
     let algorithm_state = Arc::new(AlgorithmState {
         manager: algorithm_manager.clone(),
     });
+    let executor_manager = algorithm_manager.clone();
+    tokio::spawn(async move {
+        executor_manager.run_executor().await;
+    });
+
+    // Snapshot the engine and truncate its WAL while few events go by, too
+    if engine.has_snapshot_store() {
+        let checkpoint_engine = engine.clone();
+        tokio::spawn(async move {
+            run_checkpointer(checkpoint_engine, algorithm_manager).await;
+        });
+    }
+
+    // Expire resting DAY / GTD orders
+    let expiry_engine = engine.clone();
+    tokio::spawn(async move {
+        run_expiry_sweeper(expiry_engine).await;
+    });
+
+    // Reopen halted symbols through timed call auctions
+    let reopening_engine = engine.clone();
+    tokio::spawn(async move {
+        run_reopening_timer(reopening_engine).await;
+    });
+
+    // Publish indicative uncross prices of running call auctions
+    let auction_engine = engine.clone();
+    let auction_broadcaster = broadcaster.clone();
+    tokio::spawn(async move {
+        run_auction_publisher(auction_engine, auction_broadcaster).await;
+    });
+
+    // Broadcast circuit breaker halts and resumptions
+    let trading_status_engine = engine.clone();
+    let trading_status_broadcaster = broadcaster.clone();
+    tokio::spawn(async move {
+        run_trading_status_publisher(trading_status_engine, trading_status_broadcaster).await;
+    });
+
+    // Push position changes to the private positions channels
+    let position_publisher_tracker = position_tracker.clone();
+    let position_broadcaster = broadcaster.clone();
+    let position_ticks = tick_distributor
+        .as_ref()
+        .map(|distributor| distributor.register_consumer("positions".to_string()));
     tokio::spawn(async move {
-        algorithm_manager.run_executor().await;
+        run_position_publisher(position_publisher_tracker, position_broadcaster, position_ticks).await;
     });
 
     let router = Router::new()
@@ -103,6 +222,7 @@ pub fn create_router(
         .route("/api/v1/orders", post(submit_order))
         .route("/api/v1/orders/:symbol/:order_id", get(get_order))
         .route("/api/v1/orders/:symbol/:order_id", delete(cancel_order))
+        .route("/api/v1/orders/:symbol/:order_id", patch(amend_order))
         // Order book endpoints
         .route("/api/v1/orderbook/:symbol", get(get_order_book))
         .route("/api/v1/orderbook/:symbol/spread", get(get_spread_metrics))
@@ -111,6 +231,7 @@ pub fn create_router(
         // Metrics endpoints
         .route("/api/v1/metrics/exchange", get(get_exchange_metrics))
         .route("/api/v1/orderbook/:symbol/microstructure", get(get_microstructure_metrics))
+        .layer(Extension(pipeline))
         // Add state for REST endpoints
         .with_state(engine.clone());
 
@@ -176,6 +297,103 @@ pub fn create_router(
 
     let router = router.merge(stop_order_router);
 
+    // Add contingent (OCO / OTO / bracket) order endpoints
+    let contingent_order_router = Router::new()
+        .route("/api/v1/contingent-orders", post(contingent_order_handlers::submit_contingent_order))
+        .route("/api/v1/contingent-orders/:group_id", get(contingent_order_handlers::get_contingent_order))
+        .route("/api/v1/contingent-orders/:group_id", delete(contingent_order_handlers::cancel_contingent_order))
+        .with_state(engine.clone());
+
+    let router = router.merge(contingent_order_router);
+
+    // Add call auction endpoints
+    let auction_router = Router::new()
+        .route("/api/v1/auctions/:symbol", get(auction_handlers::get_auction))
+        .route("/api/v1/auctions/:symbol/start", post(auction_handlers::start_auction))
+        .route("/api/v1/auctions/:symbol/end", post(auction_handlers::end_auction))
+        .with_state(engine.clone());
+
+    let router = router.merge(auction_router);
+
+    // Add trading session calendar endpoints
+    let session_router = Router::new()
+        .route("/api/v1/sessions/:symbol", get(session_handlers::get_trading_session))
+        .route("/api/v1/sessions/:symbol", put(session_handlers::set_trading_session))
+        .with_state(engine.clone());
+
+    let router = router.merge(session_router);
+
+    // Add instrument reference data endpoints
+    let instrument_router = Router::new()
+        .route("/api/v1/instruments", get(instrument_handlers::list_instruments))
+        .route("/api/v1/instruments/:symbol", get(instrument_handlers::get_instrument))
+        .route("/api/v1/instruments/:symbol", put(instrument_handlers::put_instrument))
+        .with_state(engine.clone());
+
+    let router = router.merge(instrument_router);
+
+    // Add account balance endpoints
+    let account_router = Router::new()
+        .route("/api/v1/accounts/:user_id/deposit", post(account_handlers::deposit))
+        .route("/api/v1/accounts/:user_id/withdraw", post(account_handlers::withdraw))
+        .route("/api/v1/accounts/:user_id/balances", get(account_handlers::get_balances))
+        .with_state(engine.clone());
+
+    let router = router.merge(account_router);
+
+    // Add fee schedule administration endpoints
+    let fee_router = Router::new()
+        .route("/api/v1/fees/schedules", get(fee_handlers::get_fee_schedules))
+        .route("/api/v1/fees/schedules/default", put(fee_handlers::set_default_fee_schedule))
+        .route("/api/v1/fees/schedules/symbols/:symbol", put(fee_handlers::set_symbol_fee_schedule))
+        .route("/api/v1/fees/schedules/symbols/:symbol", delete(fee_handlers::remove_symbol_fee_schedule))
+        .route("/api/v1/fees/schedules/users/:user_id", put(fee_handlers::set_user_fee_schedule))
+        .route("/api/v1/fees/schedules/users/:user_id", delete(fee_handlers::remove_user_fee_schedule))
+        .route("/api/v1/fees/rates/:user_id/:symbol", get(fee_handlers::get_fee_rates))
+        .with_state(engine.clone());
+
+    let router = router.merge(fee_router);
+
+    // Add matching policy administration endpoints
+    let matching_policy_router = Router::new()
+        .route("/api/v1/matching-policies", get(matching_policy_handlers::get_matching_policies))
+        .route("/api/v1/matching-policies/:symbol", get(matching_policy_handlers::get_matching_policy))
+        .route("/api/v1/matching-policies/:symbol", put(matching_policy_handlers::set_matching_policy))
+        .with_state(engine.clone());
+
+    let router = router.merge(matching_policy_router);
+
+    // Add pre-trade risk limit administration endpoints
+    let risk_router = Router::new()
+        .route("/api/v1/risk/limits", get(risk_handlers::get_risk_limits))
+        .route("/api/v1/risk/limits/default", put(risk_handlers::set_default_risk_limits))
+        .route("/api/v1/risk/accounts/:user_id", get(risk_handlers::get_account_risk))
+        .route("/api/v1/risk/accounts/:user_id/limits", put(risk_handlers::set_account_risk_limits))
+        .route("/api/v1/risk/accounts/:user_id/limits", delete(risk_handlers::remove_account_risk_limits))
+        .with_state(engine.clone());
+
+    let router = router.merge(risk_router);
+
+    // Add circuit breaker administration endpoints
+    let circuit_breaker_router = Router::new()
+        .route("/api/v1/circuit-breakers", get(circuit_breaker_handlers::get_circuit_breakers))
+        .route("/api/v1/circuit-breakers/:symbol", get(circuit_breaker_handlers::get_circuit_breaker))
+        .route("/api/v1/circuit-breakers/:symbol/halt", post(circuit_breaker_handlers::halt_trading))
+        .route("/api/v1/circuit-breakers/:symbol/resume", post(circuit_breaker_handlers::resume_trading))
+        .route("/api/v1/circuit-breakers/:symbol/config", get(circuit_breaker_handlers::get_circuit_breaker_config))
+        .route("/api/v1/circuit-breakers/:symbol/config", put(circuit_breaker_handlers::set_circuit_breaker_config))
+        .with_state(engine.clone());
+
+    let router = router.merge(circuit_breaker_router);
+
+    // Add position and PnL endpoints
+    let position_router = Router::new()
+        .route("/api/v1/positions/:user_id", get(position_handlers::get_positions))
+        .route("/api/v1/positions/:user_id/:symbol", get(position_handlers::get_position))
+        .with_state(position_tracker);
+
+    let router = router.merge(position_router);
+
     // Add algorithm endpoints
     let algorithm_router = Router::new()
         .route("/api/v1/algorithms/twap", post(algorithm_handlers::submit_twap))
@@ -201,6 +419,17 @@ pub fn create_router(
     }
 }
 
+/// Create the replication status and promotion endpoints
+///
+/// Kept apart from `create_router` so that nodes which do not replicate can
+/// leave them out.
+pub fn create_replication_router(state: ReplicationState) -> Router {
+    Router::new()
+        .route("/api/v1/replication/status", get(replication_handlers::get_replication_status))
+        .route("/api/v1/replication/promote", post(replication_handlers::promote))
+        .with_state(state)
+}
+
 /// Get TickDistributor status and statistics
 #[utoipa::path(
     get,
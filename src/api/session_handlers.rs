@@ -0,0 +1,71 @@
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::models::{SessionPhase, TradingCalendar};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Trading session calendar of a symbol and its current phase
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TradingSessionResponse {
+    pub symbol: String,
+    pub phase: SessionPhase,
+    pub calendar: TradingCalendar,
+}
+
+fn session_response(engine: &OrderBookEngine, symbol: String) -> Result<TradingSessionResponse, OrderBookError> {
+    let calendar = engine.get_trading_calendar(&symbol)?;
+    Ok(TradingSessionResponse {
+        symbol,
+        phase: calendar.phase_at(Utc::now()),
+        calendar,
+    })
+}
+
+/// Get the trading session calendar of a symbol
+#[utoipa::path(
+    get,
+    path = "/api/v1/sessions/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    responses(
+        (status = 200, description = "Trading session calendar", body = TradingSessionResponse)
+    ),
+    tag = "sessions"
+)]
+pub async fn get_trading_session(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+) -> Result<Json<TradingSessionResponse>, OrderBookError> {
+    Ok(Json(session_response(&engine, symbol)?))
+}
+
+/// Set the trading session calendar of a symbol
+///
+/// Applies to DAY orders entered from now on.
+#[utoipa::path(
+    put,
+    path = "/api/v1/sessions/{symbol}",
+    params(
+        ("symbol" = String, Path, description = "Trading symbol (e.g., AAPL)")
+    ),
+    request_body = TradingCalendar,
+    responses(
+        (status = 200, description = "Trading session calendar updated", body = TradingSessionResponse),
+        (status = 400, description = "Invalid calendar")
+    ),
+    tag = "sessions"
+)]
+pub async fn set_trading_session(
+    State(engine): State<Arc<OrderBookEngine>>,
+    Path(symbol): Path<String>,
+    Json(calendar): Json<TradingCalendar>,
+) -> Result<Json<TradingSessionResponse>, OrderBookError> {
+    engine.set_trading_calendar(&symbol, calendar)?;
+    Ok(Json(session_response(&engine, symbol)?))
+}
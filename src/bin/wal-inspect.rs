@@ -0,0 +1,340 @@
+//! Inspect write-ahead log segments
+//!
+//! ```text
+//! wal-inspect dump   [--order <id>] [--symbol <symbol>] <dir|segment>...
+//! wal-inspect json   [--order <id>] [--symbol <symbol>] <dir|segment>...
+//! wal-inspect verify <dir|segment>...
+//! ```
+//!
+//! A directory stands for all of its `wal_{index}.log` segments in order.
+//! `dump` prints one line per event, `json` writes the events as JSON lines
+//! and `verify` checks segment headers, record checksums and that sequence
+//! numbers continue without gaps. The exit status is 1 if `verify` finds a
+//! problem or a segment cannot be read.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use chrono::{DateTime, SecondsFormat};
+use order_book_api::engine::FeeScheduleScope;
+use order_book_api::persistence::{list_segments, SegmentReader, WalEvent};
+use uuid::Uuid;
+
+const USAGE: &str = "usage: wal-inspect <dump|json|verify> [--order <id>] [--symbol <symbol>] <dir|segment>...";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Command {
+    Dump,
+    Json,
+    Verify,
+}
+
+struct Args {
+    command: Command,
+    order: Option<Uuid>,
+    symbol: Option<String>,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let command = match args.next().as_deref() {
+        Some("dump") => Command::Dump,
+        Some("json") => Command::Json,
+        Some("verify") => Command::Verify,
+        Some(other) => return Err(format!("unknown command '{}'", other)),
+        None => return Err("missing command".to_string()),
+    };
+
+    let mut parsed = Args {
+        command,
+        order: None,
+        symbol: None,
+        paths: Vec::new(),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--order" => {
+                let id = args.next().ok_or("--order needs an order ID")?;
+                parsed.order = Some(Uuid::parse_str(&id).map_err(|e| format!("invalid order ID '{}': {}", id, e))?);
+            }
+            "--symbol" => parsed.symbol = Some(args.next().ok_or("--symbol needs a symbol")?),
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ => parsed.paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if parsed.paths.is_empty() {
+        return Err("no WAL directory or segment given".to_string());
+    }
+    if command == Command::Verify && (parsed.order.is_some() || parsed.symbol.is_some()) {
+        return Err("verify does not take filters".to_string());
+    }
+    Ok(parsed)
+}
+
+/// Segment files named on the command line, with directories expanded
+fn segments(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut segments = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            segments.extend(list_segments(path)?.into_iter().map(|(_, segment)| segment));
+        } else {
+            segments.push(path.clone());
+        }
+    }
+    Ok(segments)
+}
+
+fn matches(args: &Args, event: &WalEvent) -> bool {
+    args.order.is_none_or(|id| event.involves_order(id))
+        && args.symbol.as_deref().is_none_or(|symbol| event.symbol() == Some(symbol))
+}
+
+fn timestamp(timestamp_ns: u64) -> String {
+    DateTime::from_timestamp_nanos(timestamp_ns as i64).to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// One-line summary of an event for `dump`
+fn describe(event: &WalEvent) -> (u64, &'static str, String) {
+    match event {
+        WalEvent::OrderSubmitted { timestamp_ns, order, .. } => (
+            *timestamp_ns,
+            "OrderSubmitted",
+            format!(
+                "{} {} {:?} {:?} {} @ {} {:?} user={}",
+                order.symbol,
+                order.id,
+                order.side,
+                order.order_type,
+                order.quantity,
+                order.price.map_or("MKT".to_string(), |price| price.to_string()),
+                order.time_in_force,
+                order.user_id
+            ),
+        ),
+        WalEvent::OrderCancelled { timestamp_ns, order_id, symbol, .. } => {
+            (*timestamp_ns, "OrderCancelled", format!("{} {}", symbol, order_id))
+        }
+        WalEvent::OrderExpired { timestamp_ns, symbol, order_ids, .. } => {
+            let order_ids: Vec<String> = order_ids.iter().map(Uuid::to_string).collect();
+            (*timestamp_ns, "OrderExpired", format!("{} {}", symbol, order_ids.join(",")))
+        }
+        WalEvent::TradeExecuted { timestamp_ns, trade, .. } => (
+            *timestamp_ns,
+            "TradeExecuted",
+            format!(
+                "{} {} {} @ {} buy={} sell={}",
+                trade.symbol, trade.id, trade.quantity, trade.price, trade.buyer_order_id, trade.seller_order_id
+            ),
+        ),
+        WalEvent::OrderModified {
+            timestamp_ns,
+            order_id,
+            symbol,
+            new_quantity,
+            new_price,
+            ..
+        } => (
+            *timestamp_ns,
+            "OrderModified",
+            format!("{} {} quantity={:?} price={:?}", symbol, order_id, new_quantity, new_price),
+        ),
+        WalEvent::StopOrderSubmitted { timestamp_ns, stop, .. } => (
+            *timestamp_ns,
+            "StopOrderSubmitted",
+            format!(
+                "{} {} {:?} {:?} {} trigger {} user={}",
+                stop.symbol, stop.id, stop.side, stop.stop_type, stop.quantity, stop.trigger_price, stop.user_id
+            ),
+        ),
+        WalEvent::StopOrderCancelled { timestamp_ns, order_id, .. } => {
+            (*timestamp_ns, "StopOrderCancelled", order_id.to_string())
+        }
+        WalEvent::AuctionStarted { timestamp_ns, symbol, kind, .. } => {
+            (*timestamp_ns, "AuctionStarted", format!("{} {:?}", symbol, kind))
+        }
+        WalEvent::AuctionEnded { timestamp_ns, symbol, .. } => (*timestamp_ns, "AuctionEnded", symbol.clone()),
+        WalEvent::Checkpoint { timestamp_ns, checkpoint_path, .. } => {
+            (*timestamp_ns, "Checkpoint", checkpoint_path.clone())
+        }
+        WalEvent::Deposited {
+            timestamp_ns,
+            user_id,
+            asset,
+            amount,
+            ..
+        } => (*timestamp_ns, "Deposited", format!("{} {} user={}", amount, asset, user_id)),
+        WalEvent::Withdrawn {
+            timestamp_ns,
+            user_id,
+            asset,
+            amount,
+            ..
+        } => (*timestamp_ns, "Withdrawn", format!("{} {} user={}", amount, asset, user_id)),
+        WalEvent::OrderRepriced {
+            timestamp_ns,
+            symbol,
+            order_id,
+            new_price,
+            ..
+        } => (*timestamp_ns, "OrderRepriced", format!("{} {} price={}", symbol, order_id, new_price)),
+        WalEvent::FeeScheduleSet {
+            timestamp_ns,
+            scope,
+            schedule,
+            ..
+        } => {
+            let scope = match scope {
+                FeeScheduleScope::Default => "default".to_string(),
+                FeeScheduleScope::Symbol(symbol) => format!("symbol={}", symbol),
+                FeeScheduleScope::User(user_id) => format!("user={}", user_id),
+            };
+            let schedule = match schedule {
+                Some(schedule) => format!("{} tiers {:?}", schedule.tiers.len(), schedule.fee_currency),
+                None => "removed".to_string(),
+            };
+            (*timestamp_ns, "FeeScheduleSet", format!("{} {}", scope, schedule))
+        }
+        WalEvent::ContingentGroupSubmitted { timestamp_ns, group, .. } => (
+            *timestamp_ns,
+            "ContingentGroupSubmitted",
+            format!(
+                "{} {} {:?} legs={} user={}",
+                group.symbol,
+                group.id,
+                group.contingency_type,
+                group.all_legs().count(),
+                group.user_id
+            ),
+        ),
+        WalEvent::ContingentGroupCancelled {
+            timestamp_ns,
+            symbol,
+            group_id,
+            ..
+        } => (*timestamp_ns, "ContingentGroupCancelled", format!("{} {}", symbol, group_id)),
+        WalEvent::MatchingPolicySet {
+            timestamp_ns,
+            symbol,
+            policy,
+            ..
+        } => (*timestamp_ns, "MatchingPolicySet", format!("{} {:?}", symbol, policy)),
+    }
+}
+
+/// Print the matching events of every segment (`dump` and `json`)
+fn print_events(args: &Args, segments: &[PathBuf]) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    for segment in segments {
+        let mut reader = SegmentReader::open(segment).map_err(|e| with_path(segment, e))?;
+        for record in reader.by_ref() {
+            let event = record.map_err(|e| with_path(segment, e))?.event;
+            if !matches(args, &event) {
+                continue;
+            }
+            if args.command == Command::Json {
+                serde_json::to_writer(&mut out, &event)?;
+                writeln!(out)?;
+            } else {
+                let (timestamp_ns, kind, details) = describe(&event);
+                writeln!(out, "{:>10}  {}  {:<18}  {}", event.sequence(), timestamp(timestamp_ns), kind, details)?;
+            }
+        }
+        if let Some(corrupt) = reader.corruption() {
+            eprintln!("{}: {}", segment.display(), corrupt);
+        }
+    }
+    out.flush()
+}
+
+/// Check every segment; returns whether all of them are intact and contiguous
+fn verify(segments: &[PathBuf]) -> bool {
+    let mut ok = true;
+    let mut next_sequence: Option<u64> = None;
+
+    for segment in segments {
+        let mut reader = match SegmentReader::open(segment) {
+            Ok(reader) => reader,
+            Err(e) => {
+                println!("{}: {}", segment.display(), e);
+                ok = false;
+                continue;
+            }
+        };
+
+        let mut records = 0u64;
+        let mut range: Option<(u64, u64)> = None;
+        let mut problems = Vec::new();
+        for record in reader.by_ref() {
+            let sequence = match record {
+                Ok(record) => record.event.sequence(),
+                Err(e) => {
+                    problems.push(e.to_string());
+                    break;
+                }
+            };
+            if let Some(expected) = next_sequence.filter(|&expected| expected != sequence) {
+                problems.push(format!("sequence {} follows {}", sequence, expected - 1));
+            }
+            next_sequence = Some(sequence + 1);
+            records += 1;
+            range = Some((range.map_or(sequence, |(first, _)| first), sequence));
+        }
+        if let Some(corrupt) = reader.corruption() {
+            problems.push(corrupt.to_string());
+        }
+
+        let sequences = range.map_or("no records".to_string(), |(first, last)| format!("sequences {}..={}", first, last));
+        if problems.is_empty() {
+            println!("{}: OK, {} records, {}", segment.display(), records, sequences);
+        } else {
+            ok = false;
+            println!("{}: {} records, {}; {}", segment.display(), records, sequences, problems.join("; "));
+        }
+    }
+    ok
+}
+
+fn with_path(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("wal-inspect: {}\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let segments = match segments(&args.paths) {
+        Ok(segments) => segments,
+        Err(e) => {
+            eprintln!("wal-inspect: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match args.command {
+        Command::Verify => {
+            if verify(&segments) {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Command::Dump | Command::Json => match print_events(&args, &segments) {
+            Ok(()) => ExitCode::SUCCESS,
+            // Stop quietly when piped into `head`
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("wal-inspect: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
@@ -1,15 +1,25 @@
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use futures::{SinkExt, StreamExt};
 use tokio::net::TcpStream;
-use tokio::net::tcp::OwnedWriteHalf;
-use tokio::time::{interval, Duration};
+use tokio::time::{interval, sleep, timeout, Duration, Instant, MissedTickBehavior};
 use std::sync::{Arc};
-use tokio::sync::{Mutex, mpsc};
+use tokio::sync::{mpsc, watch};
+use tokio_util::codec::Framed;
 use crate::ctrader_fix::symbol_data::parse_security_list_response;
 use crate::ctrader_fix::symbol_data::symbol_parser::SymbolData;
-use crate::models::datasource::FixConfig;
-use super::messages::{create_logon_message, create_market_data_request, create_heartbeat, create_security_list_request, parse_fix_message, format_for_display};
+use crate::fix::session::{msg_type, tag};
+use crate::fix::{FixCodec, FixFrame, FixSession, Inbound};
+use crate::models::datasource::{ConnectionState, FixConfig, FixSessionStatus};
+use super::messages::{logon_fields, market_data_request_fields, security_list_request_fields};
 use super::market_data::{MarketTick, MarketDataParser};
 
+/// Time allowed to open the TCP connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time the server has to answer our Logon
+const LOGON_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Connection = Framed<TcpStream, FixCodec>;
+
 /// Lightweight message for async display
 /// Contains only essential FIX fields for minimal output
 #[derive(Debug, Clone)]
@@ -26,16 +36,50 @@ struct DisplayMessage {
     elapsed_ms: i64,
 }
 
+/// How long to wait before connecting again after losing the session
+///
+/// The wait doubles after every failed attempt up to `max_backoff`, and
+/// starts over at `initial_backoff` once a Logon is accepted.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Give up after this many connections in a row fail; `None` retries forever
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+        }
+    }
+}
+
+/// How one connection ended
+#[derive(Debug, Default)]
+struct SessionEnd {
+    /// The server accepted our Logon
+    logged_on: bool,
+    /// The server sent a Logout
+    logged_out: bool,
+}
+
 pub struct CTraderFixClient {
     host: String,
     port: u16,
-    sender_comp_id: String,
-    target_comp_id: String,
-    sender_sub_id: String,
-    target_sub_id: String,
     username: String,
     password: String,
-    msg_seq_num: Arc<Mutex<u32>>,
+    /// Sequence numbers and resend store, kept across reconnects
+    session: FixSession,
+    heartbeat_interval_secs: u16,
+    reconnect_policy: ReconnectPolicy,
+    /// Symbol IDs subscribed to, subscribed again after reconnecting
+    subscribed_symbol_ids: Vec<String>,
+    /// Connection state for health reporting
+    status: watch::Sender<FixSessionStatus>,
     /// Channel for streaming market ticks to consumers
     tick_sender: Option<mpsc::UnboundedSender<MarketTick>>,
     /// Parser for market data messages
@@ -54,18 +98,21 @@ impl CTraderFixClient {
         config: FixConfig
     ) -> (Self, mpsc::UnboundedReceiver<MarketTick>) {
         let (tx, rx) = mpsc::unbounded_channel();
+        let credentials = config.credentials;
+        let session = FixSession::new(credentials.sender_comp_id, credentials.target_comp_id)
+            .with_sub_ids(credentials.sender_sub_id, credentials.target_sub_id);
 
         // In Rust, you can only use the shorthand syntax (just the value without field_name:) when the variable name exactly matches the field name. Since you have config.host (not a variable named host), you must use the full syntax
         let client = Self {
             host: config.host,
             port: config.port,
-            sender_comp_id: config.credentials.sender_comp_id,
-            target_comp_id: config.credentials.target_comp_id,
-            sender_sub_id: config.credentials.sender_sub_id,
-            target_sub_id: config.credentials.target_sub_id,
-            username: config.credentials.username,
-            password: config.credentials.password,
-            msg_seq_num: Arc::new(Mutex::new(1)),
+            username: credentials.username,
+            password: credentials.password,
+            session,
+            heartbeat_interval_secs: 30,
+            reconnect_policy: ReconnectPolicy::default(),
+            subscribed_symbol_ids: Vec::new(),
+            status: watch::Sender::new(FixSessionStatus::default()),
             tick_sender: Some(tx),
             parser: MarketDataParser::new(),
             display_sender: None,
@@ -86,17 +133,34 @@ impl CTraderFixClient {
         self.security_list_callback = Some(callback);
     }
 
+    /// Set the HeartBtInt asked for at Logon (default 30 seconds)
+    pub fn set_heartbeat_interval(&mut self, seconds: u16) {
+        self.heartbeat_interval_secs = seconds.max(1);
+    }
+
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.reconnect_policy = policy;
+    }
+
+    /// Follow the connection state as the client connects, logs on and reconnects
+    pub fn connection_status(&self) -> watch::Receiver<FixSessionStatus> {
+        self.status.subscribe()
+    }
+
+    /// Connect, log on and stream market data, reconnecting with backoff
+    /// whenever the session is lost
+    ///
+    /// The first Logon resets sequence numbers; later ones continue them, so
+    /// messages missed while disconnected are recovered with a ResendRequest.
+    /// A server that logs us out before accepting a Logon gets a reset on the
+    /// next attempt. Only returns once `ReconnectPolicy::max_attempts` is used up.
     pub async fn connect_and_run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         println!("🔌 Connecting to cTrader FIX API...");
         println!("   Host: {}:{}", self.host, self.port);
-        println!("   SenderCompID: {}", self.sender_comp_id);
-        println!("   TargetCompID: {}", self.target_comp_id);
+        println!("   SenderCompID: {}", self.session.sender_comp_id());
+        println!("   TargetCompID: {}", self.session.target_comp_id());
         println!();
 
-        // Connect to cTrader
-        let stream = TcpStream::connect(format!("{}:{}", self.host, self.port)).await?;
-        println!("✅ TCP connection established!");
-
         // Spawn an async display task for non-blocking output
         let (display_tx, mut display_rx) = mpsc::unbounded_channel::<DisplayMessage>();
         tokio::spawn(async move {
@@ -112,211 +176,181 @@ impl CTraderFixClient {
         });
         self.display_sender = Some(display_tx);
 
-        // Why mut on reader?
-        // Reading requires mutable access to track internal position
-
-        // Writing doesn't require outer mutability
-        let (mut reader, writer) = stream.into_split();
-
-        // Wrap writer in Arc<Mutex> for sharing across tasks
-        let writer = Arc::new(Mutex::new(writer));
-
-        // Send a Logon message
-        println!("\n📤 Sending Logon message...");
-        let logon_msg = create_logon_message(
-            &self.sender_comp_id,
-            &self.target_comp_id,
-            &self.sender_sub_id,
-            &self.target_sub_id,
-            &self.username,
-            &self.password,
-        );
-
-        println!("   Logon message: {}", format_for_display(&logon_msg));
-        {
-            let mut w = writer.lock().await;
-            w.write_all(logon_msg.as_bytes()).await?;
-            w.flush().await?;
-        }
+        let mut backoff = self.reconnect_policy.initial_backoff;
+        let mut reset = true;
+        loop {
+            self.status.send_modify(|status| status.state = ConnectionState::Connecting);
+            let mut end = SessionEnd::default();
+            let reason = match self.run_session(reset, &mut end).await {
+                Ok(()) => "connection closed by server".to_string(),
+                Err(reason) => reason,
+            };
+            eprintln!("🔴 FIX session ended: {}", reason);
+
+            if end.logged_on {
+                backoff = self.reconnect_policy.initial_backoff;
+                reset = false;
+            } else if end.logged_out {
+                reset = true;
+            }
+            let mut attempts = 0;
+            self.status.send_modify(|status| {
+                status.reconnect_attempts += 1;
+                status.last_error = Some(reason.clone());
+                status.state = ConnectionState::Reconnecting;
+                attempts = status.reconnect_attempts;
+            });
+            if self.reconnect_policy.max_attempts.is_some_and(|max| attempts >= max) {
+                self.status.send_modify(|status| status.state = ConnectionState::Disconnected);
+                return Err(format!("FIX connection failed {} times in a row: {}", attempts, reason).into());
+            }
 
-        // Increment sequence number
-        {
-            let mut seq = self.msg_seq_num.lock().await;
-            *seq += 1;
+            println!("🔁 Reconnecting in {:?} (attempt {})", backoff, attempts);
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(self.reconnect_policy.max_backoff);
         }
+    }
 
-        println!("✅ Logon message sent!");
-
-        // Spawn heartbeat task with a shared writer
-        let sender_comp_id = self.sender_comp_id.clone();
-        let target_comp_id = self.target_comp_id.clone();
-        let sender_sub_id = self.sender_sub_id.clone();
-        let target_sub_id = self.target_sub_id.clone();
-        let msg_seq_num = Arc::clone(&self.msg_seq_num);
-        let writer_clone = Arc::clone(&writer);
-
-        tokio::spawn(async move {
-            let mut heartbeat_interval = interval(Duration::from_secs(30));
-            loop {
-                heartbeat_interval.tick().await;
-                let seq = {
-                    let mut s = msg_seq_num.lock().await;
-                    let current = *s;
-                    *s += 1;
-                    current
-                };
-                let hb = create_heartbeat(&sender_comp_id, &target_comp_id, &sender_sub_id, &target_sub_id, seq);
-                println!("\n💓 Sending Heartbeat (seq {})", seq);
-
-                // Actually send the heartbeat!
-                let mut w = writer_clone.lock().await;
-                if let Err(e) = w.write_all(hb.as_bytes()).await {
-                    eprintln!("❌ Failed to send heartbeat: {}", e);
-                    break;
-                }
-                if let Err(e) = w.flush().await {
-                    eprintln!("❌ Failed to flush heartbeat: {}", e);
-                    break;
-                }
-            }
-        });
+    /// Run one connection until it is lost; `Ok` when the server closes it
+    async fn run_session(&mut self, reset: bool, end: &mut SessionEnd) -> Result<(), String> {
+        let address = format!("{}:{}", self.host, self.port);
+        let stream = timeout(CONNECT_TIMEOUT, TcpStream::connect(&address))
+            .await
+            .map_err(|_| format!("timed out connecting to {}", address))?
+            .map_err(|e| format!("failed to connect to {}: {}", address, e))?;
+        let _ = stream.set_nodelay(true);
+        println!("✅ TCP connection established!");
+        let mut connection = Framed::new(stream, FixCodec);
 
-        // Read responses
-        println!("\n📥 Waiting for responses from cTrader...\n");
-        let mut buffer = vec![0u8; 8192]; // Increased buffer size
-        let mut accumulated_data = Vec::new();
+        if reset {
+            self.session.reset();
+        }
+        self.session.start(Duration::from_secs(self.heartbeat_interval_secs.into()));
+        let logon = self.session.send(
+            msg_type::LOGON,
+            logon_fields(&self.username, &self.password, self.heartbeat_interval_secs, reset),
+        );
+        println!("\n📤 Sending Logon message (reset: {})...", reset);
+        send(&mut connection, logon).await?;
 
+        let logon_deadline = Instant::now() + LOGON_TIMEOUT;
+        let mut timer = interval(Duration::from_secs(1));
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
         loop {
-            match reader.read(&mut buffer).await {
-                Ok(0) => {
-                    println!("\n🔴 Connection closed by server");
-                    break;
+            tokio::select! {
+                message = connection.next() => {
+                    let frame = match message {
+                        Some(Ok(frame)) => frame,
+                        Some(Err(e)) => return Err(format!("malformed message: {}", e)),
+                        None => return Ok(()),
+                    };
+                    self.handle_message(&frame, &mut connection, end).await?;
                 }
-                Ok(n) => {
-                    accumulated_data.extend_from_slice(&buffer[..n]);
-
-                    // Try to extract complete FIX messages (terminated by SOH after checksum)
-                    while let Some(msg) = 
-                        self.extract_message(&mut accumulated_data) {
-                        self.handle_message(&msg, &writer).await?;
+                _ = timer.tick() => {
+                    if !end.logged_on && Instant::now() >= logon_deadline {
+                        return Err("no Logon response from server".to_string());
                     }
-                }
-                Err(e) => {
-                    eprintln!("❌ Error reading from stream: {}", e);
-                    break;
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    fn extract_message(&self, buffer: &mut Vec<u8>) -> Option<String> {
-        // Look for a complete FIX message (starts with "8=FIX" and ends with checksum)
-        // This is a simplified implementation
-        if let Ok(s) = String::from_utf8(buffer.clone()) {
-            if s.contains("10=") && s.contains("\x01") {
-                // Find the end of the first complete message
-                if let Some(checksum_pos) = s.find("10=") {
-                    if let Some(end_pos) = s[checksum_pos..].find("\x01") {
-                        let full_end = checksum_pos + end_pos + 1;
-                        let message = s[..full_end].to_string();
-                        buffer.drain(..full_end);
-                        return Some(message);
+                    match self.session.poll_timer(Instant::now()) {
+                        Ok(Some(message)) => send(&mut connection, message).await?,
+                        Ok(None) => {}
+                        Err(reason) => {
+                            let logout = self.session.logout(&reason);
+                            let _ = connection.send(logout).await;
+                            return Err(reason);
+                        }
                     }
                 }
             }
         }
-        None
     }
 
     async fn handle_message(
         &mut self,
-        raw_message: &str,
-        writer: &Arc<Mutex<OwnedWriteHalf>>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let fields = parse_fix_message(raw_message);
+        frame: &FixFrame,
+        connection: &mut Connection,
+        end: &mut SessionEnd,
+    ) -> Result<(), String> {
+        let fields = frame.fields();
 
         // Get a message type
-        let msg_type = fields.get(&35).map(|s| s.as_str()).unwrap_or("Unknown");
-
-        // Verbose message display removed for performance
-        // Market data display now handled by async display task
+        let msg_type = fields.get_str(tag::MSG_TYPE).unwrap_or("Unknown");
+
+        // Sequence numbers are checked before anything else: a gap asks for a
+        // resend and drops the message until it comes again
+        let inbound = match self.session.receive(&fields) {
+            Ok(inbound) => inbound,
+            Err(reason) => {
+                let logout = self.session.logout(&reason);
+                let _ = connection.send(logout).await;
+                return Err(reason);
+            }
+        };
 
         // Handle specific message types
-        match msg_type {
-            "A" => {
-                // Logon response received, send Security List Request
-                println!("✅ Logon successful! Sending Security List Request...\n");
-
-                let seq = {
-                    let mut s = self.msg_seq_num.lock().await;
-                    let current = *s;
-                    *s += 1;
-                    current
-                };
-
-                // Request list of all available symbols
-                let sec_list_req = create_security_list_request(
-                    &self.sender_comp_id,
-                    &self.target_comp_id,
-                    &self.sender_sub_id,
-                    &self.target_sub_id,
-                    seq,
-                    None,  // None = request ALL symbols
-                );
-
-                println!("📤 Security List Request: {}", format_for_display(&sec_list_req));
-                let mut w = writer.lock().await;
-                w.write_all(sec_list_req.as_bytes()).await?;
-                w.flush().await?;
-            }
-            "0" => {
-                // Heartbeat received - invoke callback
-                if let Some(ref callback) = self.heartbeat_callback {
-                    callback();
+        match inbound {
+            Inbound::Handled(replies) => {
+                for reply in replies {
+                    connection.feed(reply).await.map_err(|e| e.to_string())?;
                 }
-            }
-            "1" => {
-                // Test Request - respond with Heartbeat
-                let seq = {
-                    let mut s = self.msg_seq_num.lock().await;
-                    let current = *s;
-                    *s += 1;
-                    current
-                };
-                let hb = create_heartbeat(
-                    &self.sender_comp_id,
-                    &self.target_comp_id,
-                    &self.sender_sub_id,
-                    &self.target_sub_id,
-                    seq,
-                );
-                let mut w = writer.lock().await;
-                w.write_all(hb.as_bytes()).await?;
-                w.flush().await?;
-
-                // Also invoke heartbeat callback when responding to test request
-                if let Some(ref callback) = self.heartbeat_callback {
-                    callback();
+                connection.flush().await.map_err(|e| e.to_string())?;
+
+                match msg_type {
+                    msg_type::LOGON if !end.logged_on => {
+                        end.logged_on = true;
+                        self.on_logon(connection).await?;
+                    }
+                    msg_type::HEARTBEAT | msg_type::TEST_REQUEST => {
+                        // Heartbeat received (or answered) - invoke callback
+                        if let Some(ref callback) = self.heartbeat_callback {
+                            callback();
+                        }
+                    }
+                    _ => {}
                 }
             }
-            "W" => {
-                self.process_market_data(raw_message);
-            }
-            "X" => {
-                self.process_market_data(raw_message);
-            }
-            "y" => {
-                // Security List Response - parse and display symbols
-                self.handle_security_list_response(raw_message, writer).await?;
+            Inbound::Application => match msg_type {
+                "W" | "X" => self.process_market_data(frame.as_bytes()),
+                "y" => {
+                    // Security List Response - parse and display symbols
+                    self.handle_security_list_response(frame.as_bytes(), connection).await?;
+                }
+                _ => {}
+            },
+            Inbound::Logout(text) => {
+                end.logged_out = true;
+                let logout = self.session.logout("");
+                let _ = connection.send(logout).await;
+                return Err(format!("logged out by server: {}", text));
             }
-            _ => {}
         }
 
         Ok(())
     }
 
+    /// Logon accepted: ask for the symbols, or subscribe again to the ones we had
+    async fn on_logon(&mut self, connection: &mut Connection) -> Result<(), String> {
+        self.status.send_modify(|status| {
+            status.state = ConnectionState::Connected;
+            status.reconnect_attempts = 0;
+        });
+
+        if self.subscribed_symbol_ids.is_empty() {
+            // Request list of all available symbols
+            println!("✅ Logon successful! Sending Security List Request...\n");
+            let sec_list_req = self.session.send("x", security_list_request_fields(None));
+            send(connection, sec_list_req).await
+        } else {
+            println!("✅ Logon successful! Resubscribing to {} symbols\n", self.subscribed_symbol_ids.len());
+            self.subscribe_market_data(connection).await
+        }
+    }
+
+    async fn subscribe_market_data(&mut self, connection: &mut Connection) -> Result<(), String> {
+        let symbol_id_refs: Vec<&str> = self.subscribed_symbol_ids.iter().map(|s| s.as_str()).collect();
+        let md_request = self.session.send("V", market_data_request_fields(&symbol_id_refs));
+        send(connection, md_request).await
+    }
+
     fn display_security_list_header(request_id: &str, result_code: u32, symbol_data: &[SymbolData]){
         println!("📋 SECURITY LIST RESPONSE");
         println!("Request ID: {}", request_id);
@@ -338,12 +372,12 @@ impl CTraderFixClient {
 
     /// Handle Security List Response (MsgType=y)
     /// Parses and displays the list of available trading symbols
-    /// Then sends a Market Data Request for the first few symbols
+    /// Then sends a Market Data Request for the received symbols
     async fn handle_security_list_response(
         &mut self,
-        raw_message: &str,
-        writer: &Arc<Mutex<OwnedWriteHalf>>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+        raw_message: &[u8],
+        connection: &mut Connection,
+    ) -> Result<(), String> {
         if let Some((req_id, result, symbols)) = parse_security_list_response(raw_message) {
             Self::display_security_list_header(&req_id, result, &symbols);
 
@@ -354,36 +388,15 @@ impl CTraderFixClient {
 
             // Send Market Data Request using received symbols
             if !symbols.is_empty() && result == 0 {
-                let seq = {
-                    let mut s = self.msg_seq_num.lock().await;
-                    let current = *s;
-                    *s += 1;
-                    current
-                };
-
-                let symbol_ids: Vec<String> = symbols
+                self.subscribed_symbol_ids = symbols
                     .iter()
                     .map(|symbol_data| {
                         symbol_data.symbol_id.to_string()
                     })
                     .collect();
 
-                println!("  ✓ Subscribing to {} symbols", symbol_ids.len());
-                
-                let symbol_id_refs: Vec<&str> = symbol_ids.iter().map(|s| s.as_str()).collect();
-
-                let md_request = create_market_data_request(
-                    &self.sender_comp_id,
-                    &self.target_comp_id,
-                    &self.sender_sub_id,
-                    &self.target_sub_id,
-                    seq,
-                    &symbol_id_refs,
-                );
-
-                let mut w = writer.lock().await;
-                w.write_all(md_request.as_bytes()).await?;
-                w.flush().await?;
+                println!("  ✓ Subscribing to {} symbols", self.subscribed_symbol_ids.len());
+                self.subscribe_market_data(connection).await?;
             }
         } else {
             eprintln!("⚠️  Failed to parse Security List Response");
@@ -394,7 +407,7 @@ impl CTraderFixClient {
 
     /// Process market data - lightweight extraction and async display
     /// Non-blocking: sends to display a channel instead of printing directly
-    fn process_market_data(&self, raw_message: &str) {
+    fn process_market_data(&self, raw_message: &[u8]) {
         // Still build and send tick for other consumers if needed
         if let Some(ref tx) = self.tick_sender {
             if let Some((symbol_id, entries)) = self.parser.parse_market_data(raw_message) {
@@ -404,3 +417,7 @@ impl CTraderFixClient {
         }
     }
 }
+
+async fn send(connection: &mut Connection, message: String) -> Result<(), String> {
+    connection.send(message).await.map_err(|e| e.to_string())
+}
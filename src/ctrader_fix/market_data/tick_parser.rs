@@ -1,6 +1,6 @@
 use rust_decimal::Decimal;
-use std::str::FromStr;
-use crate::ctrader_fix::{parse_fix_field, MarketTick};
+use crate::ctrader_fix::MarketTick;
+use crate::fix::FixFields;
 
 /// Market data entry parsed from FIX message
 /// This represents a single entry in the NoMDEntries repeating group
@@ -29,55 +29,6 @@ impl MDEntryType {
     }
 }
 
-/// Helper struct to manage the state of building a market data entry
-/// This encapsulates the state machine for parsing repeating groups
-struct EntryBuilder {
-    entry_type: Option<MDEntryType>,
-    price: Option<Decimal>,
-}
-
-impl EntryBuilder {
-    /// Create a new empty entry builder
-    fn new() -> Self {
-        Self {
-            entry_type: None,
-            price: None,
-        }
-    }
-
-    /// Set the entry type from a FIX field value
-    fn set_entry_type(&mut self, value: &str) {
-        self.entry_type = value.chars().next().and_then(MDEntryType::from_char);
-    }
-
-    /// Set the price from a FIX field value
-    fn set_price(&mut self, value: &str) {
-        self.price = Decimal::from_str(value).ok();
-    }
-
-    /// Set the size from a FIX field value
-
-    /// Try to build a complete MarketDataEntry if all fields are present
-    /// Returns Some(entry) if complete, None if any field is missing
-    fn try_build(&self) -> Option<MarketDataEntry> {
-        match (self.entry_type, self.price) {
-            (Some(et), Some(p)) => Some(MarketDataEntry {
-                entry_type: et,
-                price: p,
-            }),
-            _ => None,
-        }
-    }
-
-    /// Reset the builder state to start a new entry
-    fn reset(&mut self) {
-        self.entry_type = None;
-        self.price = None;
-    }
-}
-
-
-
 /// Optimized FIX message parser for market data
 /// Uses zero-copy parsing where possible to minimize allocations
 pub struct MarketDataParser {
@@ -93,65 +44,34 @@ impl MarketDataParser {
         }
     }
 
-    /// Handle a single FIX field and update parser state accordingly
-    /// This processes the field based on its tag and delegates to appropriate handlers
-    fn handle_field(
-        tag: u32,
-        value: &str,
-        symbol_id: &mut Option<String>,
-        builder: &mut EntryBuilder,
-        entries: &mut Vec<MarketDataEntry>,
-    ) {
-        match tag {
-            55 => {
-                // Symbol - store the instrument identifier
-                *symbol_id = Some(value.to_string());
-            }
-            269 => {
-                // MDEntryType - signals start of new entry, finalize previous if complete
-                if let Some(entry) = builder.try_build() {
-                    entries.push(entry);
-                }
-                // Start new entry and reset state
-                builder.reset();
-                builder.set_entry_type(value);
-            }
-            270 => {
-                // MDEntryPx - price for current entry
-                builder.set_price(value);
-            }
-            _ => {
-                // Ignore unknown tags
-            }
-        }
-    }
-
     /// Parse a market data snapshot or incremental refresh message
     /// Returns (symbol_id, entries)
     ///
-    /// This function orchestrates the parsing by:
-    /// 1. Splitting the FIX message into fields
-    /// 2. Parsing each field into (tag, value) pairs
-    /// 3. Delegating field handling to process each tag
-    /// 4. Finalizing any incomplete entry at the end
-    pub fn parse_market_data(&self, raw_message: &str) -> Option<(String, Vec<MarketDataEntry>)> {
-        let mut symbol_id = None;
-        let mut entries = vec![]; 
-        let mut builder = EntryBuilder::new();
-
-        // Parse each field in the FIX message
-        for field in raw_message.split('\x01') {
-            if let Some((tag, value)) = parse_fix_field(field) {
-                Self::handle_field(tag, value, &mut symbol_id, &mut builder, &mut entries);
+    /// Fields are read in place: the symbol (55), then each entry of the
+    /// NoMDEntries (268) group, started by its MDEntryType (269). Entries
+    /// that are not a bid or offer, or have no valid MDEntryPx (270), are
+    /// skipped.
+    pub fn parse_market_data(&self, raw_message: &[u8]) -> Option<(String, Vec<MarketDataEntry>)> {
+        let fields = FixFields::new(raw_message);
+        let symbol_id = fields.get_str(55)?;
+
+        let mut entries = Vec::new();
+        for entry in fields.group(268, 269) {
+            let mut entry_type = None;
+            let mut price = None;
+            for (tag, value) in entry.iter() {
+                match tag {
+                    269 => entry_type = value.first().and_then(|&c| MDEntryType::from_char(c as char)),
+                    270 => price = std::str::from_utf8(value).ok().and_then(|value| value.parse::<Decimal>().ok()),
+                    _ => {}
+                }
+            }
+            if let (Some(entry_type), Some(price)) = (entry_type, price) {
+                entries.push(MarketDataEntry { entry_type, price });
             }
         }
 
-        // Finalize the last entry if it's complete
-        if let Some(entry) = builder.try_build() {
-            entries.push(entry);
-        }
-
-        symbol_id.map(|sym| (sym, entries))
+        Some((symbol_id.to_string(), entries))
     }
 
     /// Build a MarketTick from parsed entries
@@ -188,6 +108,7 @@ impl Default for MarketDataParser {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::str::FromStr;
 
     #[test]
     fn test_market_tick_calculations() {
@@ -209,7 +130,7 @@ mod tests {
         // Simplified FIX message
         let msg = "8=FIX.4.4\x0135=W\x0155=41\x01268=2\x01269=0\x01270=2650.50\x01271=100\x01269=1\x01270=2651.00\x01271=150\x0110=123\x01";
 
-        if let Some((symbol, entries)) = parser.parse_market_data(msg) {
+        if let Some((symbol, entries)) = parser.parse_market_data(msg.as_bytes()) {
             assert_eq!(symbol, "41");
             assert_eq!(entries.len(), 2);
             assert_eq!(entries[0].entry_type, MDEntryType::Bid);
@@ -118,12 +118,10 @@ pub fn create_logon_message(
     msg.add_field(34, 1);                     // MsgSeqNum (start at 1)
     msg.add_field(52, Utc::now().format("%Y%m%d-%H:%M:%S%.3f").to_string()); // SendingTime
 
-    // Logon specific fields
-    msg.add_field(98, 0);                     // EncryptMethod (0 = None)
-    msg.add_field(108, 30);                   // HeartBtInt (30 seconds)
-    msg.add_field(141, "Y");                  // ResetSeqNumFlag
-    msg.add_field(553, username);             // Username
-    msg.add_field(554, password);             // Password
+    // Logon specific fields: 30 second heartbeats, sequence numbers reset
+    for (tag, value) in logon_fields(username, password, 30, true) {
+        msg.add_field(tag, value);
+    }
 
     msg.build("A")
 }
@@ -142,7 +140,6 @@ pub fn create_market_data_request(
 ) -> String {
 
     let sending_time = Utc::now().format("%Y%m%d-%H:%M:%S%.3f").to_string();
-    let md_req_id = format!("REQ-{}", Utc::now().timestamp_millis());
 
     // Build message body with EXACT field order required by cTrader
     let mut body = String::new();
@@ -157,22 +154,10 @@ pub fn create_market_data_request(
     body.push_str(&format!("57={}\x01", target_sub_id));        // TargetSubID
 
     // Market Data Request fields in EXACT order
-    body.push_str(&format!("262={}\x01", md_req_id));           // MDReqID
-    body.push_str("263=1\x01");                                  // SubscriptionRequestType (Subscribe)
-    body.push_str("264=1\x01");                                  // MarketDepth (Spot)
-    body.push_str("265=1\x01");                                  // MDUpdateType (Incremental)
-
-    // FIRST repeating group: NoRelatedSym + Symbol(s)
-    body.push_str(&format!("146={}\x01", symbol_ids.len()));    // NoRelatedSym
-    for symbol_id in symbol_ids {
-        body.push_str(&format!("55={}\x01", symbol_id));        // Symbol (immediately after count!)
+    for (tag, value) in market_data_request_fields(symbol_ids) {
+        body.push_str(&format!("{}={}\x01", tag, value));
     }
 
-    // SECOND repeating group: NoMDEntryTypes + MDEntryType(s)
-    body.push_str("267=2\x01");                                  // NoMDEntryTypes
-    body.push_str("269=0\x01");                                  // MDEntryType = Bid
-    body.push_str("269=1\x01");                                  // MDEntryType = Offer
-
     // Build final message with header and trailer
     let mut message = String::new();
     message.push_str("8=FIX.4.4\x01");                          // BeginString
@@ -186,6 +171,45 @@ pub fn create_market_data_request(
     message
 }
 
+/// Body of a Market Data Request (MsgType=V) subscribing to the bid and
+/// offer of `symbol_ids`, with both repeating groups in the order cTrader
+/// requires
+pub fn market_data_request_fields(symbol_ids: &[&str]) -> Vec<(u32, String)> {
+    let md_req_id = format!("REQ-{}", Utc::now().timestamp_millis());
+    let mut fields = vec![
+        (262, md_req_id),                     // MDReqID
+        (263, "1".to_string()),               // SubscriptionRequestType (Subscribe)
+        (264, "1".to_string()),               // MarketDepth (Spot)
+        (265, "1".to_string()),               // MDUpdateType (Incremental)
+    ];
+
+    // FIRST repeating group: NoRelatedSym + Symbol(s)
+    fields.push((146, symbol_ids.len().to_string())); // NoRelatedSym
+    for symbol_id in symbol_ids {
+        fields.push((55, symbol_id.to_string())); // Symbol (immediately after count!)
+    }
+
+    // SECOND repeating group: NoMDEntryTypes + MDEntryType(s)
+    fields.push((267, "2".to_string()));      // NoMDEntryTypes
+    fields.push((269, "0".to_string()));      // MDEntryType = Bid
+    fields.push((269, "1".to_string()));      // MDEntryType = Offer
+    fields
+}
+
+/// Body of a Logon (MsgType=A); `reset` starts both sequence numbers over
+pub fn logon_fields(username: &str, password: &str, heartbeat_interval_secs: u16, reset: bool) -> Vec<(u32, String)> {
+    let mut fields = vec![
+        (98, "0".to_string()),                // EncryptMethod (0 = None)
+        (108, heartbeat_interval_secs.to_string()), // HeartBtInt
+    ];
+    if reset {
+        fields.push((141, "Y".to_string()));  // ResetSeqNumFlag
+    }
+    fields.push((553, username.to_string())); // Username
+    fields.push((554, password.to_string())); // Password
+    fields
+}
+
 /// Create a Heartbeat message (MsgType=0)
 pub fn create_heartbeat(
     sender_comp_id: &str,
@@ -226,17 +250,26 @@ pub fn create_security_list_request(
     msg.add_field(34, msg_seq_num);           // MsgSeqNum
     msg.add_field(52, Utc::now().format("%Y%m%d-%H:%M:%S%.3f").to_string()); // SendingTime
 
-    // Security List Request specific fields
+    for (tag, value) in security_list_request_fields(symbol_id) {
+        msg.add_field(tag, value);
+    }
+
+    msg.build("x")
+}
+
+/// Body of a Security List Request (MsgType=x), for `None` all symbols
+pub fn security_list_request_fields(symbol_id: Option<&str>) -> Vec<(u32, String)> {
     let req_id = format!("SECLST-{}", Utc::now().timestamp_millis());
-    msg.add_field(320, req_id);               // SecurityReqID (unique ID)
-    msg.add_field(559, 0);                    // SecurityListRequestType (0 = Symbol)
+    let mut fields = vec![
+        (320, req_id),                        // SecurityReqID (unique ID)
+        (559, "0".to_string()),               // SecurityListRequestType (0 = Symbol)
+    ];
 
     // Optional: request specific symbol
     if let Some(sym_id) = symbol_id {
-        msg.add_field(55, sym_id);            // Symbol
+        fields.push((55, sym_id.to_string())); // Symbol
     }
-
-    msg.build("x")
+    fields
 }
 
 
@@ -3,7 +3,7 @@ use crate::ctrader_fix::market_data::MarketTick;
 use crate::models::datasource::*;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::{RwLock, mpsc, watch};
 
 // JoinHandle is a Rust type from std::thread and tokio::task modules that represents a handle to a spawned thread or async task.
 // It allows you to:
@@ -26,6 +26,8 @@ pub struct DatasourceManager {
     fix_client_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
     distributor_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
     connection_metrics: Arc<RwLock<Option<ConnectionMetrics>>>,
+    /// State of the running client's FIX session
+    session_status: Arc<RwLock<Option<watch::Receiver<FixSessionStatus>>>>,
     subscribed_symbols: Arc<RwLock<Vec<SymbolData>>>,
     symbol_mapping: Arc<RwLock<HashMap<String, String>>>,
 }
@@ -37,6 +39,7 @@ impl DatasourceManager {
             fix_client_handle: Arc::new(RwLock::new(None)),
             distributor_handle: Arc::new(RwLock::new(None)),
             connection_metrics: Arc::new(RwLock::new(None)),
+            session_status: Arc::new(RwLock::new(None)),
             subscribed_symbols: Arc::new(RwLock::new(Vec::new())),
             symbol_mapping: Arc::new(RwLock::new(HashMap::new()))
         }
@@ -61,6 +64,7 @@ impl DatasourceManager {
         );
 
         self.setup_fix_callbacks(&mut client);
+        *self.session_status.write().await = Some(client.connection_status());
 
         let (client_handle, forward_handle) = self.spawn_connection_tasks(
             client,
@@ -75,14 +79,21 @@ impl DatasourceManager {
     }
 
     /// Validate that we're not already connected
-    /// The * tries to dereference and move the Vec out of the guard
+    ///
+    /// The client keeps reconnecting until stopped, so a running client
+    /// counts as connected whatever its session state
     async fn validate_connection_state(&self) -> Result<(), String> {
-        if !self.subscribed_symbols.read().await.is_empty() {
+        if self.fix_client_handle.read().await.as_ref().is_some_and(|handle| !handle.is_finished()) {
             return Err("Already connected to FIX server".to_string());
         }
         Ok(())
     }
 
+    /// State of the running client's FIX session
+    async fn session_status(&self) -> Option<FixSessionStatus> {
+        self.session_status.read().await.as_ref().map(|status| status.borrow().clone())
+    }
+
     /// Reset connection state and store new configuration
     async fn reset_connection_state(&self) {
         *self.connection_metrics.write().await = Some(ConnectionMetrics::new());
@@ -205,6 +216,7 @@ impl DatasourceManager {
         // Clear state
         *self.subscribed_symbols.write().await = Vec::new();
         *self.connection_metrics.write().await = None;
+        *self.session_status.write().await = None;
 
         tracing::info!("FIX connection stopped");
         Ok(())
@@ -232,8 +244,13 @@ impl DatasourceManager {
 
         let total_symbols = symbols_info.len();
 
+        let connected = self
+            .session_status()
+            .await
+            .is_some_and(|status| status.state == ConnectionState::Connected);
+
         DatasourceStatus {
-            connected: total_symbols > 0,
+            connected,
             uptime_seconds,
             last_heartbeat_seconds_ago,
             symbols_subscribed: symbols_info,
@@ -243,14 +260,13 @@ impl DatasourceManager {
 
     /// Get health status
     pub async fn get_health(&self) -> HealthStatus {
+        let session = self.session_status().await;
         let metrics = self.connection_metrics.read().await;
         let symbols = self.subscribed_symbols.read().await;
 
-        let connection_state = if !symbols.is_empty() {
-            ConnectionState::Connected
-        } else {
-            ConnectionState::Disconnected
-        };
+        let connection_state = session
+            .as_ref()
+            .map_or(ConnectionState::Disconnected, |status| status.state);
 
         let (uptime_seconds, last_heartbeat_seconds_ago, heartbeat_status, health_state, warning) =
             if let Some(ref m) = *metrics {
@@ -291,6 +307,34 @@ impl DatasourceManager {
                 )
             };
 
+        // Heartbeats only tell how a logged-on session is doing
+        let (health_state, warning) = match (&session, connection_state) {
+            (Some(status), ConnectionState::Connecting) if status.reconnect_attempts == 0 => (
+                HealthState::Degraded,
+                Some("Logging on to FIX server".to_string()),
+            ),
+            (Some(status), ConnectionState::Connecting | ConnectionState::Reconnecting) => (
+                HealthState::Unhealthy,
+                Some(format!(
+                    "FIX session lost ({}), reconnect attempt {}",
+                    status.last_error.as_deref().unwrap_or("unknown error"),
+                    status.reconnect_attempts
+                )),
+            ),
+            (Some(status), ConnectionState::Disconnected) => (
+                HealthState::Unhealthy,
+                Some(format!(
+                    "FIX client gave up: {}",
+                    status.last_error.as_deref().unwrap_or("unknown error")
+                )),
+            ),
+            _ => (health_state, warning),
+        };
+        let reconnect_attempts = session
+            .as_ref()
+            .map(|status| status.reconnect_attempts)
+            .filter(|attempts| *attempts > 0);
+
         let symbols_count = if symbols.is_empty() {
             None
         } else {
@@ -304,6 +348,7 @@ impl DatasourceManager {
             last_heartbeat_seconds_ago,
             uptime_seconds,
             symbols_count,
+            reconnect_attempts,
             warning,
             timestamp: Utc::now().to_rfc3339(),
         }
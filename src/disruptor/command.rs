@@ -0,0 +1,227 @@
+//! `Copy` encoding of engine commands
+//!
+//! Ring buffer slots hold plain `Copy` data, so the strings of an order
+//! (symbol, user ID) are stored inline in fixed-size buffers.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::engine::OrderBookError;
+use crate::models::{IcebergConfig, Order, OrderSide, OrderType, SelfTradePreventionMode, TimeInForce};
+
+/// Longest symbol a command can carry
+pub const MAX_SYMBOL_LEN: usize = 16;
+/// Longest user ID a command can carry
+pub const MAX_USER_ID_LEN: usize = 64;
+
+/// UTF-8 string stored inline in `N` bytes
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedStr<const N: usize> {
+    len: u8,
+    bytes: [u8; N],
+}
+
+pub type SymbolStr = FixedStr<MAX_SYMBOL_LEN>;
+pub type UserIdStr = FixedStr<MAX_USER_ID_LEN>;
+
+impl<const N: usize> FixedStr<N> {
+    /// Store `s` inline (`None` if it is longer than `N` bytes)
+    pub fn new(s: &str) -> Option<Self> {
+        if s.len() > N || s.len() > u8::MAX as usize {
+            return None;
+        }
+        let mut bytes = [0u8; N];
+        bytes[..s.len()].copy_from_slice(s.as_bytes());
+        Some(Self { len: s.len() as u8, bytes })
+    }
+
+    /// Store `s` inline, cutting it at the last character boundary that fits
+    pub fn truncated(s: &str) -> Self {
+        let mut end = s.len().min(N).min(u8::MAX as usize);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        Self::new(&s[..end]).expect("truncated string fits")
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only ever built from a `&str` cut at a character boundary
+        std::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+impl<const N: usize> fmt::Debug for FixedStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<const N: usize> fmt::Display for FixedStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A new order as it travels through the ring buffer
+///
+/// Iceberg orders carry only their total and display quantity; the hidden
+/// quantity is derived again when the order is rebuilt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderCommand {
+    pub id: Uuid,
+    pub symbol: SymbolStr,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    pub price: Option<Decimal>,
+    pub quantity: Decimal,
+    pub user_id: UserIdStr,
+    pub timestamp: DateTime<Utc>,
+    pub time_in_force: TimeInForce,
+    pub stp_mode: SelfTradePreventionMode,
+    pub post_only: bool,
+    pub expire_time: Option<DateTime<Utc>>,
+    pub peg_offset: Option<Decimal>,
+    pub iceberg_total_quantity: Option<Decimal>,
+    pub iceberg_display_quantity: Option<Decimal>,
+}
+
+impl TryFrom<&Order> for OrderCommand {
+    type Error = OrderBookError;
+
+    fn try_from(order: &Order) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: order.id,
+            symbol: encode_symbol(&order.symbol)?,
+            side: order.side,
+            order_type: order.order_type,
+            price: order.price,
+            quantity: order.quantity,
+            user_id: UserIdStr::new(&order.user_id).ok_or_else(|| {
+                OrderBookError::InvalidCommand(format!("User ID longer than {} bytes", MAX_USER_ID_LEN))
+            })?,
+            timestamp: order.timestamp,
+            time_in_force: order.time_in_force,
+            stp_mode: order.stp_mode,
+            post_only: order.post_only,
+            expire_time: order.expire_time,
+            peg_offset: order.peg_offset,
+            iceberg_total_quantity: order.iceberg.as_ref().map(|iceberg| iceberg.total_quantity),
+            iceberg_display_quantity: order.iceberg.as_ref().map(|iceberg| iceberg.display_quantity),
+        })
+    }
+}
+
+impl OrderCommand {
+    /// Rebuild the order the command was encoded from
+    pub fn to_order(&self) -> Order {
+        let mut order = Order::new_with_options(
+            self.symbol.to_string(),
+            self.side,
+            self.order_type,
+            self.price,
+            self.quantity,
+            self.user_id.to_string(),
+            self.time_in_force,
+            self.stp_mode,
+            self.post_only,
+            self.expire_time,
+        );
+        order.id = self.id;
+        order.timestamp = self.timestamp;
+        order.peg_offset = self.peg_offset;
+        if let (Some(total), Some(display)) = (self.iceberg_total_quantity, self.iceberg_display_quantity) {
+            order.iceberg = Some(IcebergConfig::new(total, display));
+        }
+        order
+    }
+}
+
+/// Encode a symbol, rejecting symbols that do not fit a command
+pub fn encode_symbol(symbol: &str) -> Result<SymbolStr, OrderBookError> {
+    SymbolStr::new(symbol)
+        .ok_or_else(|| OrderBookError::InvalidSymbol(format!("Symbol longer than {} bytes: {}", MAX_SYMBOL_LEN, symbol)))
+}
+
+/// Request to the matching engine
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EngineCommand {
+    Submit(OrderCommand),
+    Cancel {
+        symbol: SymbolStr,
+        order_id: Uuid,
+    },
+    Amend {
+        symbol: SymbolStr,
+        order_id: Uuid,
+        new_price: Option<Decimal>,
+        new_quantity: Option<Decimal>,
+    },
+}
+
+impl EngineCommand {
+    /// Symbol the command acts on (decides the shard)
+    pub fn symbol(&self) -> &SymbolStr {
+        match self {
+            EngineCommand::Submit(order) => &order.symbol,
+            EngineCommand::Cancel { symbol, .. } | EngineCommand::Amend { symbol, .. } => symbol,
+        }
+    }
+}
+
+/// A command and the ID its reply is correlated with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommandEnvelope {
+    pub correlation_id: u64,
+    pub command: EngineCommand,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_fixed_str() {
+        let symbol = SymbolStr::new("AAPL").unwrap();
+        assert_eq!(symbol.as_str(), "AAPL");
+        assert!(SymbolStr::new("A_VERY_LONG_SYMBOL_NAME").is_none());
+
+        // Never cut inside a multi-byte character
+        let truncated = FixedStr::<4>::truncated("abcé");
+        assert_eq!(truncated.as_str(), "abc");
+    }
+
+    #[test]
+    fn test_order_round_trip() {
+        let mut order = Order::new_with_options(
+            "AAPL".to_string(),
+            OrderSide::Buy,
+            OrderType::Limit,
+            Some(dec!(150.25)),
+            dec!(10),
+            "trader-42".to_string(),
+            TimeInForce::GTC,
+            SelfTradePreventionMode::CancelResting,
+            true,
+            None,
+        );
+        order.iceberg = Some(IcebergConfig::new(dec!(100), dec!(10)));
+
+        let command = OrderCommand::try_from(&order).unwrap();
+        let rebuilt = command.to_order();
+        assert_eq!(rebuilt.id, order.id);
+        assert_eq!(rebuilt.symbol, "AAPL");
+        assert_eq!(rebuilt.user_id, "trader-42");
+        assert_eq!(rebuilt.price, Some(dec!(150.25)));
+        assert_eq!(rebuilt.timestamp, order.timestamp);
+        assert_eq!(rebuilt.stp_mode, SelfTradePreventionMode::CancelResting);
+        assert!(rebuilt.post_only);
+        assert_eq!(rebuilt.iceberg.unwrap().hidden_quantity, dec!(90));
+
+        order.user_id = "u".repeat(MAX_USER_ID_LEN + 1);
+        assert!(matches!(OrderCommand::try_from(&order), Err(OrderBookError::InvalidCommand(_))));
+    }
+}
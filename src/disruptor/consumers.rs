@@ -0,0 +1,180 @@
+//! Downstream consumers of the engine's output events
+//!
+//! The engine appends to the write-ahead log itself, under the book lock
+//! (see `pipeline`); the journal consumer makes it durable and the snapshot
+//! consumer checkpoints it.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use rust_decimal::Decimal;
+use tracing::error;
+
+use crate::algorithms::AlgorithmManager;
+use crate::engine::OrderBookEngine;
+use crate::models::OrderSide;
+use crate::websocket::broadcaster::topics;
+use crate::websocket::{Broadcaster, WsMessage};
+
+use super::event::EngineEvent;
+use super::pipeline::EventConsumer;
+
+/// Publishes trades, cancellations and top-of-book changes to WebSocket subscribers
+pub struct MarketDataPublisher {
+    broadcaster: Broadcaster,
+}
+
+impl MarketDataPublisher {
+    pub fn new(broadcaster: Broadcaster) -> Self {
+        Self { broadcaster }
+    }
+}
+
+impl EventConsumer for MarketDataPublisher {
+    fn name(&self) -> &str {
+        "market-data"
+    }
+
+    fn on_event(&mut self, _shard: usize, _sequence: u64, event: &EngineEvent) {
+        match event {
+            EngineEvent::TradeExecuted { taker_side, trade, .. } => {
+                let message = WsMessage::Trade {
+                    symbol: trade.symbol.to_string(),
+                    trade_id: trade.id.to_string(),
+                    price: trade.price,
+                    quantity: trade.quantity,
+                    side: match taker_side {
+                        Some(OrderSide::Buy) => "buy".to_string(),
+                        Some(OrderSide::Sell) => "sell".to_string(),
+                        None => "auction".to_string(),
+                    },
+                    timestamp: trade.timestamp,
+                };
+                self.broadcaster.broadcast(&topics::trades(trade.symbol.as_str()), message.clone());
+                self.broadcaster.broadcast(topics::all_trades(), message);
+            }
+            EngineEvent::OrderCancelled {
+                symbol,
+                order_id,
+                user_id,
+                reason,
+                ..
+            } => {
+                self.broadcaster.broadcast(
+                    &topics::orders(symbol.as_str()),
+                    WsMessage::OrderCancelled {
+                        order_id: order_id.to_string(),
+                        symbol: symbol.to_string(),
+                        user_id: user_id.to_string(),
+                        reason: reason.as_str().to_string(),
+                        timestamp: Utc::now(),
+                    },
+                );
+            }
+            EngineEvent::TopOfBook {
+                symbol,
+                best_bid,
+                best_ask,
+            } => {
+                let (spread, mid_price) = match (best_bid, best_ask) {
+                    (Some(bid), Some(ask)) => (Some(ask - bid), Some((bid + ask) / Decimal::TWO)),
+                    _ => (None, None),
+                };
+                self.broadcaster.broadcast(
+                    &topics::ticker(symbol.as_str()),
+                    WsMessage::Ticker {
+                        symbol_id: symbol.to_string(),
+                        best_bid: *best_bid,
+                        best_ask: *best_ask,
+                        spread,
+                        mid_price,
+                        timestamp: Utc::now(),
+                    },
+                );
+            }
+            EngineEvent::OrderAccepted { .. } | EngineEvent::OrderAmended { .. } | EngineEvent::CommandRejected { .. } => {}
+        }
+    }
+}
+
+/// Makes the engine's write-ahead log durable: one fsync per batch of events
+///
+/// The engine's own `SyncMode` still applies; with `Batched` or `None` this
+/// bounds what a crash can lose to the events not yet consumed.
+pub struct JournalConsumer {
+    engine: Arc<OrderBookEngine>,
+    /// Set when events arrived since the last sync
+    dirty: bool,
+}
+
+impl JournalConsumer {
+    pub fn new(engine: Arc<OrderBookEngine>) -> Self {
+        Self { engine, dirty: false }
+    }
+}
+
+impl EventConsumer for JournalConsumer {
+    fn name(&self) -> &str {
+        "journal"
+    }
+
+    fn on_event(&mut self, _shard: usize, _sequence: u64, _event: &EngineEvent) {
+        self.dirty = true;
+    }
+
+    fn on_batch_end(&mut self) {
+        if !std::mem::take(&mut self.dirty) {
+            return;
+        }
+        if let Err(e) = self.engine.sync_journal() {
+            error!("Failed to sync the write-ahead log: {}", e);
+        }
+    }
+}
+
+/// Checkpoints the engine, with the running algorithms, every so many events
+pub struct SnapshotConsumer {
+    engine: Arc<OrderBookEngine>,
+    algorithms: Arc<AlgorithmManager>,
+    events_per_checkpoint: u64,
+    events_since_checkpoint: u64,
+}
+
+impl SnapshotConsumer {
+    pub fn new(engine: Arc<OrderBookEngine>, algorithms: Arc<AlgorithmManager>, events_per_checkpoint: u64) -> Self {
+        Self {
+            engine,
+            algorithms,
+            events_per_checkpoint,
+            events_since_checkpoint: 0,
+        }
+    }
+}
+
+impl EventConsumer for SnapshotConsumer {
+    fn name(&self) -> &str {
+        "snapshots"
+    }
+
+    fn on_event(&mut self, _shard: usize, _sequence: u64, _event: &EngineEvent) {
+        self.events_since_checkpoint += 1;
+    }
+
+    fn on_batch_end(&mut self) {
+        if self.events_since_checkpoint < self.events_per_checkpoint {
+            return;
+        }
+        self.events_since_checkpoint = 0;
+
+        let algorithms = match self.algorithms.snapshot() {
+            Ok(algorithms) => algorithms,
+            Err(e) => {
+                error!("Failed to capture algorithm state: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = self.engine.checkpoint(algorithms) {
+            error!("Checkpoint failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! `Copy` encoding of engine output events
+//!
+//! Matcher threads publish these after every command, and for what the
+//! engine did on its own; downstream consumers (market data, execution
+//! reports, journaling, persistence) read them through their own cursors.
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::engine::CancelReason;
+use crate::models::{FeeCurrency, OrderSide, OrderStatus, Trade};
+
+use super::command::{OrderCommand, SymbolStr, UserIdStr};
+
+/// A trade as it travels through the ring buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeEvent {
+    pub id: Uuid,
+    pub symbol: SymbolStr,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub buyer_order_id: Uuid,
+    pub seller_order_id: Uuid,
+    pub buyer_id: UserIdStr,
+    pub seller_id: UserIdStr,
+    pub maker_fee: Decimal,
+    pub taker_fee: Decimal,
+    pub timestamp: DateTime<Utc>,
+    pub maker_fee_currency: FeeCurrency,
+    pub taker_fee_currency: FeeCurrency,
+}
+
+impl From<&Trade> for TradeEvent {
+    /// User IDs of counterparties that entered outside the pipeline may be
+    /// longer than a command allows; they are truncated.
+    fn from(trade: &Trade) -> Self {
+        Self {
+            id: trade.id,
+            symbol: SymbolStr::truncated(&trade.symbol),
+            price: trade.price,
+            quantity: trade.quantity,
+            buyer_order_id: trade.buyer_order_id,
+            seller_order_id: trade.seller_order_id,
+            buyer_id: UserIdStr::truncated(&trade.buyer_id),
+            seller_id: UserIdStr::truncated(&trade.seller_id),
+            maker_fee: trade.maker_fee,
+            taker_fee: trade.taker_fee,
+            timestamp: trade.timestamp,
+            maker_fee_currency: trade.maker_fee_currency,
+            taker_fee_currency: trade.taker_fee_currency,
+        }
+    }
+}
+
+/// Outcome of a command, in the order the matcher produced it
+///
+/// A command emits its own event (accepted, amended, cancelled or rejected),
+/// then one `TradeExecuted` per trade, then the events of what the engine
+/// did on its own as a result (repriced pegs, triggered stops, self-trade
+/// and contingent cancels), then the symbol's new `TopOfBook`. Events of
+/// engine activity no command caused (auction uncrosses, expiries) carry
+/// correlation ID 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EngineEvent {
+    /// A new order was accepted (`order` is the order as submitted, or as the
+    /// engine submitted it for a triggered stop or contingent leg)
+    OrderAccepted {
+        correlation_id: u64,
+        order: OrderCommand,
+        status: OrderStatus,
+        filled_quantity: Decimal,
+    },
+    /// A resting order was amended
+    OrderAmended {
+        correlation_id: u64,
+        symbol: SymbolStr,
+        order_id: Uuid,
+        new_price: Option<Decimal>,
+        new_quantity: Option<Decimal>,
+        status: OrderStatus,
+        filled_quantity: Decimal,
+    },
+    /// A resting order left the book without filling
+    OrderCancelled {
+        correlation_id: u64,
+        symbol: SymbolStr,
+        order_id: Uuid,
+        user_id: UserIdStr,
+        remaining_quantity: Decimal,
+        reason: CancelReason,
+    },
+    /// A command failed; the caller gets the error through its reply
+    CommandRejected {
+        correlation_id: u64,
+        symbol: SymbolStr,
+        order_id: Uuid,
+    },
+    /// A command traded
+    TradeExecuted {
+        correlation_id: u64,
+        /// Side of the order that took liquidity (`None` for an auction uncross)
+        taker_side: Option<OrderSide>,
+        trade: TradeEvent,
+    },
+    /// Best bid and ask after a command that changed the book
+    TopOfBook {
+        symbol: SymbolStr,
+        best_bid: Option<Decimal>,
+        best_ask: Option<Decimal>,
+    },
+}
@@ -1,3 +1,11 @@
 pub mod ring_buffer;
+pub mod command;
+pub mod event;
+pub mod pipeline;
+pub mod consumers;
 
 pub use ring_buffer::{RingBuffer, OrderEvent};
+pub use command::{CommandEnvelope, EngineCommand, FixedStr, OrderCommand, SymbolStr, UserIdStr};
+pub use event::{EngineEvent, TradeEvent};
+pub use pipeline::{EventConsumer, IngestionPipeline, PipelineConfig};
+pub use consumers::{JournalConsumer, MarketDataPublisher, SnapshotConsumer};
@@ -0,0 +1,792 @@
+//! Lock-free order ingestion pipeline
+//!
+//! Gateways (REST, WebSocket, binary) publish commands into the command ring
+//! buffer of the symbol's shard. One matcher thread per shard consumes its
+//! ring, applies each command to the `OrderBookEngine` and publishes the
+//! resulting events to the shard's event ring. Every downstream consumer
+//! reads the event rings of all shards through its own cursor on its own
+//! thread, so a slow consumer only holds back the matchers once a ring is full.
+//!
+//! What the engine does on its own (triggered stops, released contingent
+//! legs, peg reprices, self-trade and contingent cancels, auction uncrosses,
+//! expiries) is recorded as engine notices. A matcher publishes the notices
+//! of a command's symbol right after the command's own events, and those of
+//! its other symbols after each batch and whenever its command ring is empty.
+//!
+//! Journaling and persistence are consumers too. The engine appends to its
+//! write-ahead log while it holds the book lock, so the log keeps each
+//! book's changes in the order recovery has to replay them; the
+//! `JournalConsumer` makes the log durable with one fsync per batch of
+//! events (group commit), off the matcher threads. The `SnapshotConsumer`
+//! checkpoints the engine every so many events.
+//!
+//! ```text
+//! gateways ──► [commands shard 0] ──► matcher 0 ──► [events shard 0] ──┬──► market data
+//!          └─► [commands shard 1] ──► matcher 1 ──► [events shard 1] ──┼──► execution reports
+//!                                                                      ├──► journal
+//!                                                                      └──► snapshots
+//! ```
+//!
+//! Replies (the order and its trades, or the error) are correlated back to
+//! the caller through the command's correlation ID. A caller whose shard's
+//! command ring is full gets `PipelineUnavailable` rather than waiting on an
+//! executor thread for room.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use tokio::sync::oneshot;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::engine::{CancelReason, EngineNotice, OrderBookEngine, OrderBookError};
+use crate::models::{Order, Trade};
+
+use super::command::{encode_symbol, CommandEnvelope, EngineCommand, OrderCommand, SymbolStr, UserIdStr};
+use super::event::{EngineEvent, TradeEvent};
+use super::ring_buffer::RingBuffer;
+
+/// Most commands or events taken from a ring in one read
+const BATCH_SIZE: usize = 256;
+
+/// Reader of the engine's output events
+///
+/// `on_event` runs on the consumer's own thread, in sequence order per shard.
+pub trait EventConsumer: Send {
+    /// Name of the consumer (used for its thread name and in logs)
+    fn name(&self) -> &str;
+
+    /// Handle one event; `shard` and `sequence` identify its slot
+    fn on_event(&mut self, shard: usize, sequence: u64, event: &EngineEvent);
+
+    /// Called after each pass over the rings that handed the consumer events
+    fn on_batch_end(&mut self) {}
+}
+
+/// Sizing of the pipeline
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    /// Number of matcher threads; symbols are spread over them by hash
+    pub shards: usize,
+    /// Slots in each shard's command ring (power of 2)
+    pub command_capacity: usize,
+    /// Slots in each shard's event ring (power of 2)
+    pub event_capacity: usize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            shards: 4,
+            command_capacity: 4096,
+            event_capacity: 16384,
+        }
+    }
+}
+
+/// Reply to a command, sent back to the caller that published it
+#[derive(Debug)]
+enum CommandReply {
+    /// Submitted or amended order with the trades it caused
+    Order(Order, Vec<Trade>),
+    Cancelled(Order),
+}
+
+type PendingReplies = DashMap<u64, oneshot::Sender<Result<CommandReply, OrderBookError>>>;
+
+struct Shard {
+    commands: Arc<RingBuffer<CommandEnvelope>>,
+}
+
+/// Command ingestion pipeline in front of an `OrderBookEngine`
+pub struct IngestionPipeline {
+    engine: Arc<OrderBookEngine>,
+    shards: Vec<Shard>,
+    pending: Arc<PendingReplies>,
+    next_correlation_id: AtomicU64,
+    /// Cleared to stop the matchers
+    running: Arc<AtomicBool>,
+    /// Set once every matcher has exited; nothing published after it is read
+    matchers_stopped: AtomicBool,
+    /// Cleared to stop the consumers, once the matchers are done
+    consuming: Arc<AtomicBool>,
+    matchers: Vec<JoinHandle<()>>,
+    consumers: Vec<JoinHandle<()>>,
+}
+
+impl IngestionPipeline {
+    /// Start the matcher threads and one thread per downstream consumer
+    pub fn start(engine: Arc<OrderBookEngine>, config: PipelineConfig, consumers: Vec<Box<dyn EventConsumer>>) -> Self {
+        assert!(config.shards > 0, "Pipeline needs at least one shard");
+
+        let running = Arc::new(AtomicBool::new(true));
+        let consuming = Arc::new(AtomicBool::new(true));
+        let pending = Arc::new(PendingReplies::new());
+        let mut matchers = Vec::with_capacity(config.shards);
+        let mut shards = Vec::with_capacity(config.shards);
+        let mut event_rings = Vec::with_capacity(config.shards);
+        // Nobody would take the notices without consumers
+        engine.set_notices_enabled(!consumers.is_empty());
+
+        for shard in 0..config.shards {
+            let commands = Arc::new(RingBuffer::new(config.command_capacity, 1));
+            // The event ring needs a cursor even without consumers; the matcher skips publishing then
+            let events = Arc::new(RingBuffer::new(config.event_capacity, consumers.len().max(1)));
+
+            let matcher = Matcher {
+                engine: engine.clone(),
+                shard,
+                shard_count: config.shards,
+                commands: commands.clone(),
+                events: (!consumers.is_empty()).then(|| events.clone()),
+                pending: pending.clone(),
+            };
+            let matcher_running = running.clone();
+            matchers.push(
+                thread::Builder::new()
+                    .name(format!("matcher-{}", shard))
+                    .spawn(move || matcher.run(&matcher_running))
+                    .expect("failed to spawn matcher thread"),
+            );
+
+            shards.push(Shard { commands });
+            event_rings.push(events);
+        }
+
+        let consumers = consumers
+            .into_iter()
+            .enumerate()
+            .map(|(consumer_id, consumer)| {
+                let rings = event_rings.clone();
+                let consumer_running = consuming.clone();
+                thread::Builder::new()
+                    .name(format!("consumer-{}", consumer.name()))
+                    .spawn(move || run_consumer(consumer, consumer_id, &rings, &consumer_running))
+                    .expect("failed to spawn consumer thread")
+            })
+            .collect();
+
+        info!("Ingestion pipeline started with {} shard(s)", config.shards);
+
+        Self {
+            engine,
+            shards,
+            pending,
+            next_correlation_id: AtomicU64::new(1),
+            running,
+            matchers_stopped: AtomicBool::new(false),
+            consuming,
+            matchers,
+            consumers,
+        }
+    }
+
+    /// Submit a new order; resolves once the matcher has processed it
+    pub async fn submit_order(&self, order: Order) -> Result<(Order, Vec<Trade>), OrderBookError> {
+        let command = EngineCommand::Submit(OrderCommand::try_from(&order)?);
+        match self.execute(command).await? {
+            CommandReply::Order(order, trades) => Ok((order, trades)),
+            CommandReply::Cancelled(_) => unreachable!("submit replies with an order"),
+        }
+    }
+
+    /// Cancel a resting order
+    pub async fn cancel_order(&self, symbol: &str, order_id: Uuid) -> Result<Order, OrderBookError> {
+        let command = EngineCommand::Cancel {
+            symbol: encode_symbol(symbol)?,
+            order_id,
+        };
+        match self.execute(command).await? {
+            CommandReply::Cancelled(order) => Ok(order),
+            CommandReply::Order(..) => unreachable!("cancel replies with the cancelled order"),
+        }
+    }
+
+    /// Amend the price and/or quantity of a resting order
+    pub async fn amend_order(
+        &self,
+        symbol: &str,
+        order_id: Uuid,
+        new_price: Option<Decimal>,
+        new_quantity: Option<Decimal>,
+    ) -> Result<(Order, Vec<Trade>), OrderBookError> {
+        let command = EngineCommand::Amend {
+            symbol: encode_symbol(symbol)?,
+            order_id,
+            new_price,
+            new_quantity,
+        };
+        match self.execute(command).await? {
+            CommandReply::Order(order, trades) => Ok((order, trades)),
+            CommandReply::Cancelled(_) => unreachable!("amend replies with an order"),
+        }
+    }
+
+    /// Publish a command to its shard and wait for the correlated reply
+    async fn execute(&self, command: EngineCommand) -> Result<CommandReply, OrderBookError> {
+        if !self.running.load(Ordering::Acquire) {
+            return Err(OrderBookError::PipelineUnavailable("Ingestion pipeline is stopped".to_string()));
+        }
+
+        let correlation_id = self.next_correlation_id.fetch_add(1, Ordering::Relaxed);
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.pending.insert(correlation_id, reply_tx);
+
+        let shard = &self.shards[self.shard_of(command.symbol())];
+        // Never wait for room on an executor thread: a full ring is backpressure
+        if shard.commands.try_publish(CommandEnvelope { correlation_id, command }).is_none() {
+            self.pending.remove(&correlation_id);
+            return Err(OrderBookError::PipelineUnavailable("Command queue is full".to_string()));
+        }
+
+        // Shutdown may have stopped the matchers between the check above and
+        // the publish; fail the command unless shutdown already did
+        if self.matchers_stopped.load(Ordering::SeqCst) && self.pending.remove(&correlation_id).is_some() {
+            return Err(OrderBookError::PipelineUnavailable("Ingestion pipeline is stopped".to_string()));
+        }
+
+        reply_rx
+            .await
+            .map_err(|_| OrderBookError::PipelineUnavailable("Matcher stopped before replying".to_string()))?
+    }
+
+    /// Shard that owns a symbol
+    fn shard_of(&self, symbol: &SymbolStr) -> usize {
+        shard_of(symbol.as_str(), self.shards.len())
+    }
+
+    /// Number of matcher shards
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Commands published but not yet taken by a matcher, per shard
+    pub fn queue_depths(&self) -> Vec<u64> {
+        self.shards.iter().map(|shard| shard.commands.available_count(0)).collect()
+    }
+
+    /// Stop accepting commands, drain the rings and join every thread
+    pub fn shutdown(&mut self) {
+        self.running.store(false, Ordering::Release);
+        // Matchers drain their command rings first, so consumers see every event
+        for handle in self.matchers.drain(..) {
+            if handle.join().is_err() {
+                error!("Matcher thread panicked");
+            }
+        }
+        // Commands published after their matcher exited never get a reply
+        self.matchers_stopped.store(true, Ordering::SeqCst);
+        self.engine.set_notices_enabled(false);
+        let unanswered: Vec<u64> = self.pending.iter().map(|entry| *entry.key()).collect();
+        for correlation_id in unanswered {
+            if let Some((_, reply_tx)) = self.pending.remove(&correlation_id) {
+                let _ = reply_tx.send(Err(OrderBookError::PipelineUnavailable(
+                    "Ingestion pipeline stopped before the command was matched".to_string(),
+                )));
+            }
+        }
+
+        self.consuming.store(false, Ordering::Release);
+        for handle in self.consumers.drain(..) {
+            if handle.join().is_err() {
+                error!("Event consumer thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for IngestionPipeline {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Shard of `shards` that owns a symbol
+fn shard_of(symbol: &str, shards: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    symbol.hash(&mut hasher);
+    (hasher.finish() % shards as u64) as usize
+}
+
+/// Single-threaded consumer of one shard's command ring
+struct Matcher {
+    engine: Arc<OrderBookEngine>,
+    shard: usize,
+    shard_count: usize,
+    commands: Arc<RingBuffer<CommandEnvelope>>,
+    events: Option<Arc<RingBuffer<EngineEvent>>>,
+    pending: Arc<PendingReplies>,
+}
+
+impl Matcher {
+    fn run(&self, running: &AtomicBool) {
+        let mut idle = 0;
+        // Drain what was published before shutdown
+        while running.load(Ordering::Acquire) || self.commands.has_available(0) {
+            let batch = self.commands.read_batch(0, BATCH_SIZE);
+            if batch.is_empty() {
+                if self.publish_pending_notices() {
+                    idle = 0;
+                } else {
+                    idle_wait(&mut idle);
+                }
+                continue;
+            }
+            idle = 0;
+
+            for envelope in batch {
+                let reply = self.process(&envelope);
+                if let Some((_, reply_tx)) = self.pending.remove(&envelope.correlation_id) {
+                    // The caller may have gone away (e.g. a dropped HTTP request)
+                    let _ = reply_tx.send(reply);
+                }
+            }
+            self.publish_pending_notices();
+        }
+        self.publish_pending_notices();
+    }
+
+    /// Apply one command to the engine and publish its events
+    fn process(&self, envelope: &CommandEnvelope) -> Result<CommandReply, OrderBookError> {
+        let correlation_id = envelope.correlation_id;
+        let symbol = *envelope.command.symbol();
+
+        let result = match envelope.command {
+            EngineCommand::Submit(command) => self.engine.add_order(command.to_order()).map(|(order, trades)| {
+                self.publish(EngineEvent::OrderAccepted {
+                    correlation_id,
+                    order: command,
+                    status: order.status,
+                    filled_quantity: order.filled_quantity,
+                });
+                self.publish_trades(correlation_id, &order, &trades);
+                CommandReply::Order(order, trades)
+            }),
+            EngineCommand::Amend {
+                order_id,
+                new_price,
+                new_quantity,
+                ..
+            } => self
+                .engine
+                .amend_order(symbol.as_str(), order_id, new_price, new_quantity)
+                .map(|(order, trades)| {
+                    self.publish(EngineEvent::OrderAmended {
+                        correlation_id,
+                        symbol,
+                        order_id,
+                        new_price,
+                        new_quantity,
+                        status: order.status,
+                        filled_quantity: order.filled_quantity,
+                    });
+                    self.publish_trades(correlation_id, &order, &trades);
+                    CommandReply::Order(order, trades)
+                }),
+            EngineCommand::Cancel { order_id, .. } => self.engine.cancel_order(symbol.as_str(), order_id).map(|order| {
+                self.publish(EngineEvent::OrderCancelled {
+                    correlation_id,
+                    symbol,
+                    order_id,
+                    user_id: UserIdStr::truncated(&order.user_id),
+                    remaining_quantity: order.remaining_quantity(),
+                    reason: CancelReason::Requested,
+                });
+                CommandReply::Cancelled(order)
+            }),
+        };
+
+        // What the engine did on its own because of the command
+        let engine_published = self.publish_notices(correlation_id, symbol.as_str());
+
+        match &result {
+            Ok(_) => self.publish_top_of_book(symbol.as_str()),
+            Err(_) => {
+                let order_id = match envelope.command {
+                    EngineCommand::Submit(command) => command.id,
+                    EngineCommand::Cancel { order_id, .. } | EngineCommand::Amend { order_id, .. } => order_id,
+                };
+                self.publish(EngineEvent::CommandRejected {
+                    correlation_id,
+                    symbol,
+                    order_id,
+                });
+                if engine_published {
+                    self.publish_top_of_book(symbol.as_str());
+                }
+            }
+        }
+
+        result
+    }
+
+    fn publish(&self, event: EngineEvent) {
+        if let Some(events) = &self.events {
+            events.publish(event);
+        }
+    }
+
+    fn publish_trades(&self, correlation_id: u64, taker: &Order, trades: &[Trade]) {
+        for trade in trades {
+            self.publish(EngineEvent::TradeExecuted {
+                correlation_id,
+                taker_side: Some(taker.side),
+                trade: TradeEvent::from(trade),
+            });
+        }
+    }
+
+    /// Publish the engine notices of a symbol; `false` if it had none
+    fn publish_notices(&self, correlation_id: u64, symbol: &str) -> bool {
+        if self.events.is_none() {
+            return false;
+        }
+        let notices = self.engine.take_notices(symbol);
+        for notice in &notices {
+            self.publish_notice(correlation_id, notice);
+        }
+        !notices.is_empty()
+    }
+
+    /// Publish the engine notices no command of this shard picked up (the
+    /// effects of admin calls, sweeps and timers) with correlation ID 0
+    fn publish_pending_notices(&self) -> bool {
+        if self.events.is_none() || !self.engine.has_notices() {
+            return false;
+        }
+        let mut published = false;
+        for symbol in self.engine.symbols_with_notices() {
+            if shard_of(&symbol, self.shard_count) == self.shard && self.publish_notices(0, &symbol) {
+                self.publish_top_of_book(&symbol);
+                published = true;
+            }
+        }
+        published
+    }
+
+    fn publish_notice(&self, correlation_id: u64, notice: &EngineNotice) {
+        match notice {
+            EngineNotice::OrderAccepted(order) => match OrderCommand::try_from(order) {
+                Ok(command) => self.publish(EngineEvent::OrderAccepted {
+                    correlation_id,
+                    order: command,
+                    status: order.status,
+                    filled_quantity: order.filled_quantity,
+                }),
+                Err(e) => error!("Failed to publish order {} submitted by the engine: {}", order.id, e),
+            },
+            EngineNotice::OrderCancelled { order, reason } => self.publish(EngineEvent::OrderCancelled {
+                correlation_id,
+                symbol: SymbolStr::truncated(&order.symbol),
+                order_id: order.id,
+                user_id: UserIdStr::truncated(&order.user_id),
+                remaining_quantity: order.remaining_quantity(),
+                reason: *reason,
+            }),
+            EngineNotice::TradeExecuted { taker_side, trade } => self.publish(EngineEvent::TradeExecuted {
+                correlation_id,
+                taker_side: *taker_side,
+                trade: TradeEvent::from(trade),
+            }),
+        }
+    }
+
+    fn publish_top_of_book(&self, symbol: &str) {
+        if self.events.is_none() {
+            return;
+        }
+        match self.engine.get_top_of_book(symbol) {
+            Ok((best_bid, best_ask)) => self.publish(EngineEvent::TopOfBook {
+                symbol: SymbolStr::truncated(symbol),
+                best_bid,
+                best_ask,
+            }),
+            Err(e) => error!("Failed to read top of book for {}: {}", symbol, e),
+        }
+    }
+}
+
+/// Read every shard's event ring through the consumer's own cursor
+fn run_consumer(
+    mut consumer: Box<dyn EventConsumer>,
+    consumer_id: usize,
+    rings: &[Arc<RingBuffer<EngineEvent>>],
+    running: &AtomicBool,
+) {
+    let mut idle = 0;
+    loop {
+        // Cleared only after every matcher has exited, so a final empty pass means done
+        let stopping = !running.load(Ordering::Acquire);
+
+        let mut consumed = false;
+        for (shard, ring) in rings.iter().enumerate() {
+            let first_sequence = ring.read_position(consumer_id);
+            let events = ring.read_batch(consumer_id, BATCH_SIZE);
+            for (offset, event) in events.iter().enumerate() {
+                consumer.on_event(shard, first_sequence + offset as u64, event);
+            }
+            consumed |= !events.is_empty();
+        }
+
+        if consumed {
+            consumer.on_batch_end();
+            idle = 0;
+        } else if stopping {
+            break;
+        } else {
+            idle_wait(&mut idle);
+        }
+    }
+    info!("Event consumer {} stopped", consumer.name());
+}
+
+/// Back off while a ring is empty: spin, then yield, then sleep briefly
+fn idle_wait(idle: &mut u32) {
+    *idle = idle.saturating_add(1);
+    if *idle < 100 {
+        std::hint::spin_loop();
+    } else if *idle < 1_000 {
+        thread::yield_now();
+    } else {
+        thread::sleep(Duration::from_micros(100));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{OrderSide, OrderStatus, OrderType};
+    use chrono::Utc;
+    use rust_decimal_macros::dec;
+    use std::sync::Mutex;
+
+    /// Records every event it sees
+    struct Recorder(Arc<Mutex<Vec<EngineEvent>>>);
+
+    impl EventConsumer for Recorder {
+        fn name(&self) -> &str {
+            "recorder"
+        }
+
+        fn on_event(&mut self, _shard: usize, _sequence: u64, event: &EngineEvent) {
+            self.0.lock().unwrap().push(*event);
+        }
+    }
+
+    fn limit_order(symbol: &str, side: OrderSide, price: Decimal, quantity: Decimal, user_id: &str) -> Order {
+        Order::new(symbol.to_string(), side, OrderType::Limit, Some(price), quantity, user_id.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_commands_are_matched_and_replied() {
+        let engine = Arc::new(OrderBookEngine::new());
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let mut pipeline = IngestionPipeline::start(
+            engine.clone(),
+            PipelineConfig {
+                shards: 2,
+                command_capacity: 64,
+                event_capacity: 64,
+            },
+            vec![Box::new(Recorder(recorded.clone()))],
+        );
+
+        let (sell, trades) = pipeline
+            .submit_order(limit_order("AAPL", OrderSide::Sell, dec!(100), dec!(10), "seller1"))
+            .await
+            .unwrap();
+        assert!(trades.is_empty());
+
+        let (buy, trades) = pipeline
+            .submit_order(limit_order("AAPL", OrderSide::Buy, dec!(100), dec!(4), "buyer1"))
+            .await
+            .unwrap();
+        assert_eq!(buy.status, OrderStatus::Filled);
+        assert_eq!(trades.len(), 1);
+
+        let (amended, _) = pipeline.amend_order("AAPL", sell.id, None, Some(dec!(8))).await.unwrap();
+        assert_eq!(amended.remaining_quantity(), dec!(4));
+
+        let cancelled = pipeline.cancel_order("AAPL", sell.id).await.unwrap();
+        assert_eq!(cancelled.status, OrderStatus::Cancelled);
+
+        // Errors are correlated back to the caller too
+        assert!(matches!(
+            pipeline.cancel_order("AAPL", sell.id).await,
+            Err(OrderBookError::OrderNotFound(_))
+        ));
+        assert!(engine.get_order_book("AAPL").unwrap().orders.is_empty());
+
+        pipeline.shutdown();
+        let events = recorded.lock().unwrap();
+        let trades: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                EngineEvent::TradeExecuted { taker_side, trade, .. } => Some((*taker_side, trade.quantity)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(trades, vec![(Some(OrderSide::Buy), dec!(4))]);
+        assert!(matches!(events.last(), Some(EngineEvent::CommandRejected { order_id, .. }) if *order_id == sell.id));
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, EngineEvent::TopOfBook { best_ask: Some(price), .. } if *price == dec!(100))));
+    }
+
+    #[tokio::test]
+    async fn test_what_the_engine_does_on_its_own_reaches_consumers() {
+        use crate::gateway::{ExecutionReportPublisher, OrderRegistry};
+        use crate::models::{SelfTradePreventionMode, StopOrder, StopOrderStatus, StopOrderType, TimeInForce, TriggerCondition};
+        use chrono::Duration as ChronoDuration;
+
+        let engine = Arc::new(OrderBookEngine::new());
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        let registry = Arc::new(OrderRegistry::new());
+        let mut pipeline = IngestionPipeline::start(
+            engine.clone(),
+            PipelineConfig {
+                shards: 2,
+                command_capacity: 64,
+                event_capacity: 64,
+            },
+            vec![
+                Box::new(Recorder(recorded.clone())),
+                Box::new(ExecutionReportPublisher::new(registry.clone())),
+            ],
+        );
+
+        pipeline
+            .submit_order(limit_order("AAPL", OrderSide::Sell, dec!(100), dec!(2), "dave"))
+            .await
+            .unwrap();
+        let own_ask = limit_order("AAPL", OrderSide::Sell, dec!(100), dec!(5), "alice");
+        assert!(registry.track(&own_ask));
+        pipeline.submit_order(own_ask.clone()).await.unwrap();
+        let mut gtd_ask = limit_order("AAPL", OrderSide::Sell, dec!(101), dec!(5), "bob");
+        gtd_ask.time_in_force = TimeInForce::GTD;
+        gtd_ask.expire_time = Some(Utc::now() + ChronoDuration::hours(1));
+        assert!(registry.track(&gtd_ask));
+        pipeline.submit_order(gtd_ask.clone()).await.unwrap();
+        engine
+            .add_stop_order(StopOrder {
+                id: Uuid::new_v4(),
+                symbol: "AAPL".to_string(),
+                user_id: "carol".to_string(),
+                trigger_price: dec!(100),
+                trigger_condition: TriggerCondition::AtOrAbove,
+                stop_type: StopOrderType::StopMarket,
+                side: OrderSide::Buy,
+                quantity: dec!(1),
+                limit_price: None,
+                trail_amount: None,
+                trail_percent: None,
+                highest_price: None,
+                lowest_price: None,
+                created_at: Utc::now(),
+                expire_time: None,
+                status: StopOrderStatus::Pending,
+                time_in_force: TimeInForce::GTC,
+                stp_mode: SelfTradePreventionMode::None,
+                post_only: false,
+            })
+            .unwrap();
+
+        // Trades with dave, cancels alice's own ask and triggers carol's stop
+        let mut bid = limit_order("AAPL", OrderSide::Buy, dec!(100), dec!(5), "alice");
+        bid.stp_mode = SelfTradePreventionMode::CancelResting;
+        let (_, trades) = pipeline.submit_order(bid).await.unwrap();
+        assert_eq!(trades.len(), 1);
+
+        // Expired outside any command
+        let expired = engine.expire_orders(Utc::now() + ChronoDuration::hours(2)).unwrap();
+        assert_eq!(expired.len(), 1);
+
+        pipeline.shutdown();
+        let events = recorded.lock().unwrap();
+        let cancels: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                EngineEvent::OrderCancelled {
+                    correlation_id,
+                    order_id,
+                    reason,
+                    ..
+                } => Some((*correlation_id != 0, *order_id, *reason)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            cancels,
+            vec![(true, own_ask.id, CancelReason::SelfTrade), (false, gtd_ask.id, CancelReason::Expired)]
+        );
+        assert!(events.iter().any(|event| matches!(
+            event,
+            EngineEvent::OrderAccepted { order, .. } if order.user_id.as_str() == "carol"
+        )));
+        let trades: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                EngineEvent::TradeExecuted { taker_side, trade, .. } => Some((*taker_side, trade.price)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(trades, vec![(Some(OrderSide::Buy), dec!(100)), (Some(OrderSide::Buy), dec!(101))]);
+        // Both gateway orders ended, so the registry forgot them
+        assert_eq!(registry.order_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_producers_across_shards() {
+        let engine = Arc::new(OrderBookEngine::new());
+        let pipeline = Arc::new(IngestionPipeline::start(engine.clone(), PipelineConfig::default(), Vec::new()));
+
+        let tasks: Vec<_> = ["AAPL", "MSFT", "TSLA", "NVDA", "AMZN"]
+            .into_iter()
+            .map(|symbol| {
+                let pipeline = pipeline.clone();
+                tokio::spawn(async move {
+                    for _ in 0..50 {
+                        pipeline
+                            .submit_order(limit_order(symbol, OrderSide::Sell, dec!(10), dec!(1), "seller1"))
+                            .await
+                            .unwrap();
+                        let (_, trades) = pipeline
+                            .submit_order(limit_order(symbol, OrderSide::Buy, dec!(10), dec!(1), "buyer1"))
+                            .await
+                            .unwrap();
+                        assert_eq!(trades.len(), 1);
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(engine.get_total_trades().unwrap(), 250);
+        assert!(pipeline.queue_depths().iter().all(|&depth| depth == 0));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_fails_commands_left_unmatched() {
+        let mut pipeline = IngestionPipeline::start(Arc::new(OrderBookEngine::new()), PipelineConfig::default(), Vec::new());
+
+        // A command that raced shutdown: registered, but published after its matcher exited
+        let (reply_tx, reply_rx) = oneshot::channel();
+        pipeline.pending.insert(u64::MAX, reply_tx);
+        pipeline.shutdown();
+
+        assert!(matches!(reply_rx.await, Ok(Err(OrderBookError::PipelineUnavailable(_)))));
+        assert!(pipeline.pending.is_empty());
+        assert!(matches!(
+            pipeline.cancel_order("AAPL", Uuid::new_v4()).await,
+            Err(OrderBookError::PipelineUnavailable(_))
+        ));
+    }
+}
@@ -5,6 +5,23 @@ use std::mem::MaybeUninit;
 /// Cache line size (64 bytes on most modern CPUs)
 const CACHE_LINE_SIZE: usize = 64;
 
+/// Busy-wait iterations before a waiting producer yields its time slice
+const SPIN_LIMIT: u32 = 64;
+
+/// Back off inside a wait loop: spin first, then let other threads run
+///
+/// Yielding matters when producers outnumber cores: the producer being waited
+/// for may otherwise never get scheduled.
+#[inline]
+fn backoff(spins: &mut u32) {
+    if *spins < SPIN_LIMIT {
+        *spins += 1;
+        std::hint::spin_loop();
+    } else {
+        std::thread::yield_now();
+    }
+}
+
 /// Padding to prevent false sharing between atomic variables
 #[repr(align(64))]
 struct CacheLinePadded<T>(T);
@@ -20,9 +37,15 @@ pub struct RingBuffer<T: Copy> {
     /// Mask for fast modulo: index & mask == index % capacity
     index_mask: usize,
 
-    /// Next sequence to write (only modified by producer)
+    /// Next sequence to claim (producers race on it with `fetch_add`)
     write_cursor: CacheLinePadded<AtomicU64>,
 
+    /// Sequences below this are written and visible to consumers
+    ///
+    /// Producers commit in claim order, so a consumer never sees a slot that a
+    /// slower producer claimed earlier but has not written yet.
+    published_cursor: CacheLinePadded<AtomicU64>,
+
     /// Sequences that consumers have processed
     /// Multiple consumers can have different positions
     read_cursors: Vec<CacheLinePadded<AtomicU64>>,
@@ -55,12 +78,15 @@ impl<T: Copy> RingBuffer<T> {
             capacity,
             index_mask: capacity - 1,
             write_cursor: CacheLinePadded(AtomicU64::new(0)),
+            published_cursor: CacheLinePadded(AtomicU64::new(0)),
             read_cursors,
             min_read_cursor: CacheLinePadded(AtomicU64::new(0)),
         }
     }
 
-    /// Publish a single item (producer only)
+    /// Publish a single item
+    ///
+    /// Safe to call from several producers at once.
     /// Returns the sequence number
     #[inline]
     pub fn publish(&self, item: T) -> u64 {
@@ -70,6 +96,42 @@ impl<T: Copy> RingBuffer<T> {
         sequence
     }
 
+    /// Publish a single item unless the ring is full
+    ///
+    /// Never waits for consumers, so it is safe to call from async code.
+    /// Returns the sequence number, or `None` if the slowest consumer is a
+    /// full ring behind.
+    #[inline]
+    pub fn try_publish(&self, item: T) -> Option<u64> {
+        let sequence = self.try_claim_next()?;
+        self.write(sequence, item);
+        self.commit(sequence);
+        Some(sequence)
+    }
+
+    /// Claim the next sequence for writing if there is room for it
+    #[inline]
+    fn try_claim_next(&self) -> Option<u64> {
+        let mut next = self.write_cursor.0.load(Ordering::Relaxed);
+        loop {
+            if next >= self.min_read_cursor.0.load(Ordering::Acquire) + self.capacity as u64 {
+                self.update_min_read_cursor();
+                if next >= self.min_read_cursor.0.load(Ordering::Acquire) + self.capacity as u64 {
+                    return None;
+                }
+            }
+            // Lost the race to another producer: retry with the sequence it left
+            match self
+                .write_cursor
+                .0
+                .compare_exchange_weak(next, next + 1, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => return Some(next),
+                Err(current) => next = current,
+            }
+        }
+    }
+
     /// Claim the next sequence for writing
     #[inline]
     fn claim_next(&self) -> u64 {
@@ -77,6 +139,7 @@ impl<T: Copy> RingBuffer<T> {
 
         // Wait if we would overwrite unread data
         // (when write catches up to slowest reader)
+        let mut spins = 0;
         loop {
             let min_read = self.min_read_cursor.0.load(Ordering::Acquire);
 
@@ -88,14 +151,13 @@ impl<T: Copy> RingBuffer<T> {
             // Update min_read_cursor from actual reader positions
             self.update_min_read_cursor();
 
-            // Spin wait (in production, might want to park or yield)
-            std::hint::spin_loop();
+            backoff(&mut spins);
         }
 
         next
     }
 
-    /// Write data to slot (no synchronization needed - single writer)
+    /// Write data to a claimed slot (no synchronization needed - the slot has a single writer)
     #[inline]
     fn write(&self, sequence: u64, item: T) {
         let index = (sequence as usize) & self.index_mask;
@@ -106,16 +168,19 @@ impl<T: Copy> RingBuffer<T> {
 
     /// Make the write visible to consumers
     #[inline]
-    fn commit(&self, _sequence: u64) {
-        // The fetch_add in claim_next already published the sequence
-        // A store fence ensures writes are visible
-        std::sync::atomic::fence(Ordering::Release);
+    fn commit(&self, sequence: u64) {
+        // Wait for producers that claimed earlier sequences to commit first
+        let mut spins = 0;
+        while self.published_cursor.0.load(Ordering::Acquire) != sequence {
+            backoff(&mut spins);
+        }
+        self.published_cursor.0.store(sequence + 1, Ordering::Release);
     }
 
     /// Read an item (consumer)
     #[inline]
     pub fn read(&self, consumer_id: usize, sequence: u64) -> Option<T> {
-        let write_seq = self.write_cursor.0.load(Ordering::Acquire);
+        let write_seq = self.published_cursor.0.load(Ordering::Acquire);
 
         if sequence >= write_seq {
             return None; // No data available yet
@@ -136,7 +201,7 @@ impl<T: Copy> RingBuffer<T> {
     pub fn read_batch(&self, consumer_id: usize, max_items: usize) -> Vec<T> {
         let mut items = Vec::with_capacity(max_items);
         let start_seq = self.read_cursors[consumer_id].0.load(Ordering::Relaxed);
-        let available = self.write_cursor.0.load(Ordering::Acquire);
+        let available = self.published_cursor.0.load(Ordering::Acquire);
 
         let end_seq = (start_seq + max_items as u64).min(available);
 
@@ -165,9 +230,9 @@ impl<T: Copy> RingBuffer<T> {
         self.min_read_cursor.0.store(min, Ordering::Release);
     }
 
-    /// Get the current write cursor position
+    /// Get the current write cursor position (items published so far)
     pub fn write_position(&self) -> u64 {
-        self.write_cursor.0.load(Ordering::Relaxed)
+        self.published_cursor.0.load(Ordering::Acquire)
     }
 
     /// Get a consumer's read position
@@ -188,14 +253,14 @@ impl<T: Copy> RingBuffer<T> {
     /// Check if a consumer has items available to read
     pub fn has_available(&self, consumer_id: usize) -> bool {
         let read_pos = self.read_cursors[consumer_id].0.load(Ordering::Relaxed);
-        let write_pos = self.write_cursor.0.load(Ordering::Acquire);
+        let write_pos = self.published_cursor.0.load(Ordering::Acquire);
         read_pos < write_pos
     }
 
     /// Get number of items available for a consumer
     pub fn available_count(&self, consumer_id: usize) -> u64 {
         let read_pos = self.read_cursors[consumer_id].0.load(Ordering::Relaxed);
-        let write_pos = self.write_cursor.0.load(Ordering::Acquire);
+        let write_pos = self.published_cursor.0.load(Ordering::Acquire);
         write_pos.saturating_sub(read_pos)
     }
 }
@@ -318,6 +383,33 @@ mod tests {
         assert_eq!(items1, items2);
     }
 
+    #[test]
+    fn test_multiple_producers() {
+        let buffer = Arc::new(RingBuffer::new(64, 1));
+
+        let producers: Vec<_> = (0..4u64)
+            .map(|producer| {
+                let buffer = buffer.clone();
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        buffer.publish(producer * 1000 + i);
+                    }
+                })
+            })
+            .collect();
+
+        let mut received = Vec::new();
+        while received.len() < 4000 {
+            received.extend(buffer.read_batch(0, 64));
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        received.sort();
+        assert_eq!(received, (0..4000).collect::<Vec<u64>>());
+    }
+
     #[test]
     fn test_order_event() {
         let buffer = Arc::new(RingBuffer::new(1024, 1));
@@ -334,6 +426,19 @@ mod tests {
         assert_eq!(received, Some(event));
     }
 
+    #[test]
+    fn test_try_publish_on_full_ring() {
+        let rb = RingBuffer::new(4, 1);
+        for i in 0..4u64 {
+            assert_eq!(rb.try_publish(i), Some(i));
+        }
+        assert_eq!(rb.try_publish(4), None);
+
+        assert_eq!(rb.read_batch(0, 2), vec![0, 1]);
+        assert_eq!(rb.try_publish(4), Some(4));
+        assert_eq!(rb.read_batch(0, 4), vec![2, 3, 4]);
+    }
+
     #[test]
     fn test_available_count() {
         let buffer = RingBuffer::new(1024, 1);
@@ -0,0 +1,417 @@
+//! Account balances and order reservations
+//!
+//! With accounts enabled every user holds a balance per asset, split into an
+//! available and a reserved part. Accepting an order moves what it may spend
+//! from available to reserved: the base asset for asks, the quote asset for
+//! bids (price × quantity), plus the most its fee can be when the fee is
+//! charged in that asset. Trades settle against those reservations, fees
+//! are credited to `FEE_ACCOUNT` (which pays maker rebates), and whatever an
+//! order leaves unspent is released when it leaves the book.
+//!
+//! A symbol `BASE/QUOTE` trades `BASE` against `QUOTE`; a symbol without a
+//! slash is quoted in `DEFAULT_QUOTE_ASSET`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::{FeeCurrency, Order, OrderBook, OrderSide, Trade};
+
+use super::errors::OrderBookError;
+use super::fees::FeeEngine;
+
+/// Quote asset of symbols that do not name one
+pub const DEFAULT_QUOTE_ASSET: &str = "USD";
+
+/// Account that trading fees are credited to
+pub const FEE_ACCOUNT: &str = "exchange";
+
+/// Base and quote asset of a symbol
+pub fn symbol_assets(symbol: &str) -> (String, String) {
+    match symbol.split_once('/') {
+        Some((base, quote)) => (base.to_string(), quote.to_string()),
+        None => (symbol.to_string(), DEFAULT_QUOTE_ASSET.to_string()),
+    }
+}
+
+/// Balance of one asset in one account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Balance {
+    /// Free to withdraw or commit to new orders
+    #[serde(with = "crate::models::decimal")]
+    #[schema(value_type = String, example = "1000.00")]
+    pub available: Decimal,
+    /// Held for open orders
+    #[serde(with = "crate::models::decimal")]
+    #[schema(value_type = String, example = "250.00")]
+    pub reserved: Decimal,
+}
+
+impl Balance {
+    /// Available plus reserved
+    pub fn total(&self) -> Decimal {
+        self.available + self.reserved
+    }
+}
+
+/// Funds held for one open order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Reservation {
+    user_id: String,
+    asset: String,
+    #[serde(with = "crate::models::decimal")]
+    amount: Decimal,
+}
+
+/// What an order has to reserve: the asset and the most it can spend
+///
+/// Asks reserve their remaining base quantity. Bids reserve the quote value
+/// of their remaining quantity at their limit price; market bids are valued
+/// against the asks currently in the book. Either adds the fee at the user's
+/// highest rate if the fee is charged in the reserved asset.
+pub fn required_funds(order: &Order, book: &OrderBook, fees: &FeeEngine) -> (String, Decimal) {
+    let (base, quote) = symbol_assets(&order.symbol);
+    let remaining = order.remaining_quantity();
+    let (fee_rate, fee_currency) = fees.max_fee_rate(&order.user_id, &order.symbol);
+
+    match order.side {
+        OrderSide::Sell => match fee_currency {
+            FeeCurrency::Base => (base, remaining + remaining * fee_rate),
+            FeeCurrency::Quote => (base, remaining),
+        },
+        OrderSide::Buy => {
+            let value = match order.price {
+                Some(price) => price * remaining,
+                None => {
+                    let mut left = remaining;
+                    let mut value = Decimal::ZERO;
+                    for level in book.asks.values() {
+                        if left <= Decimal::ZERO {
+                            break;
+                        }
+                        let quantity = left.min(level.total_quantity);
+                        value += level.price * quantity;
+                        left -= quantity;
+                    }
+                    value
+                }
+            };
+            match fee_currency {
+                FeeCurrency::Quote => (quote, value + value * fee_rate),
+                FeeCurrency::Base => (quote, value),
+            }
+        }
+    }
+}
+
+/// Balances of every account and the funds reserved by open orders
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Accounts {
+    balances: HashMap<String, BTreeMap<String, Balance>>,
+    /// Reservations by order ID
+    reservations: HashMap<Uuid, Reservation>,
+}
+
+impl Accounts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn balance_mut(&mut self, user_id: &str, asset: &str) -> &mut Balance {
+        self.balances
+            .entry(user_id.to_string())
+            .or_default()
+            .entry(asset.to_string())
+            .or_default()
+    }
+
+    /// Balance of one asset (zero if the account never held it)
+    pub fn balance(&self, user_id: &str, asset: &str) -> Balance {
+        self.balances
+            .get(user_id)
+            .and_then(|assets| assets.get(asset))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Every asset balance of an account
+    pub fn balances(&self, user_id: &str) -> BTreeMap<String, Balance> {
+        self.balances.get(user_id).cloned().unwrap_or_default()
+    }
+
+    /// Sum of all accounts' balances of an asset, including collected fees
+    pub fn total(&self, asset: &str) -> Decimal {
+        self.balances
+            .values()
+            .filter_map(|assets| assets.get(asset))
+            .map(Balance::total)
+            .sum()
+    }
+
+    /// Funds currently reserved for an order
+    pub fn reserved_for(&self, order_id: Uuid) -> Option<Decimal> {
+        self.reservations.get(&order_id).map(|reservation| reservation.amount)
+    }
+
+    /// Credit an account
+    pub fn deposit(&mut self, user_id: &str, asset: &str, amount: Decimal) -> Result<Balance, OrderBookError> {
+        if amount <= Decimal::ZERO {
+            return Err(OrderBookError::InvalidQuantity("Deposit amount must be positive".to_string()));
+        }
+
+        let balance = self.balance_mut(user_id, asset);
+        balance.available += amount;
+        Ok(*balance)
+    }
+
+    /// Debit an account's available balance
+    pub fn withdraw(&mut self, user_id: &str, asset: &str, amount: Decimal) -> Result<Balance, OrderBookError> {
+        if amount <= Decimal::ZERO {
+            return Err(OrderBookError::InvalidQuantity("Withdrawal amount must be positive".to_string()));
+        }
+
+        let available = self.balance(user_id, asset).available;
+        if available < amount {
+            return Err(OrderBookError::InsufficientFunds {
+                user_id: user_id.to_string(),
+                asset: asset.to_string(),
+                required: amount,
+                available,
+            });
+        }
+
+        let balance = self.balance_mut(user_id, asset);
+        balance.available -= amount;
+        Ok(*balance)
+    }
+
+    /// Move `amount` from available to reserved for an order
+    pub fn reserve(&mut self, order_id: Uuid, user_id: &str, asset: &str, amount: Decimal) -> Result<(), OrderBookError> {
+        let available = self.balance(user_id, asset).available;
+        if available < amount {
+            return Err(OrderBookError::InsufficientFunds {
+                user_id: user_id.to_string(),
+                asset: asset.to_string(),
+                required: amount,
+                available,
+            });
+        }
+
+        let balance = self.balance_mut(user_id, asset);
+        balance.available -= amount;
+        balance.reserved += amount;
+        self.reservations.insert(
+            order_id,
+            Reservation {
+                user_id: user_id.to_string(),
+                asset: asset.to_string(),
+                amount,
+            },
+        );
+        Ok(())
+    }
+
+    /// Grow or shrink an order's reservation to `amount`
+    ///
+    /// Growing it fails, leaving everything as it was, if the account does
+    /// not have the difference available. Orders without a reservation are
+    /// left alone.
+    pub fn resize(&mut self, order_id: Uuid, amount: Decimal) -> Result<(), OrderBookError> {
+        let Some(reservation) = self.reservations.get(&order_id) else {
+            return Ok(());
+        };
+        let (user_id, asset) = (reservation.user_id.clone(), reservation.asset.clone());
+        let top_up = amount - reservation.amount;
+
+        let available = self.balance(&user_id, &asset).available;
+        if top_up > available {
+            return Err(OrderBookError::InsufficientFunds {
+                user_id,
+                asset,
+                required: top_up,
+                available,
+            });
+        }
+
+        let balance = self.balance_mut(&user_id, &asset);
+        balance.available -= top_up;
+        balance.reserved += top_up;
+        if let Some(reservation) = self.reservations.get_mut(&order_id) {
+            reservation.amount = amount;
+        }
+        Ok(())
+    }
+
+    /// Return whatever is left of an order's reservation to available
+    pub fn release(&mut self, order_id: Uuid) {
+        let Some(reservation) = self.reservations.remove(&order_id) else {
+            return;
+        };
+        let balance = self.balance_mut(&reservation.user_id, &reservation.asset);
+        balance.reserved -= reservation.amount;
+        balance.available += reservation.amount;
+    }
+
+    /// Take `amount` out of an account, first from the order's reservation
+    ///
+    /// The reservation covers every fill of its order; only an order without
+    /// one (or a market bid that walked further than the book it was valued
+    /// against) draws on the available balance.
+    fn spend(&mut self, order_id: Uuid, user_id: &str, asset: &str, amount: Decimal) {
+        let from_reservation = match self.reservations.get_mut(&order_id) {
+            Some(reservation) if reservation.user_id == user_id && reservation.asset == asset => {
+                let taken = reservation.amount.min(amount);
+                reservation.amount -= taken;
+                taken
+            }
+            _ => Decimal::ZERO,
+        };
+
+        let balance = self.balance_mut(user_id, asset);
+        balance.reserved -= from_reservation;
+        balance.available -= amount - from_reservation;
+    }
+
+    /// Settle a trade between its buyer and seller
+    ///
+    /// The buyer pays the value in the quote asset and receives the base
+    /// quantity; the seller delivers the base quantity and receives the
+    /// value. Each side's fee is added to what it pays when charged in the
+    /// asset it pays with, and taken from what it receives otherwise; a
+    /// negative fee (maker rebate) is paid out of `FEE_ACCOUNT`. `taker` is
+    /// the incoming order, which pays the taker fee; the resting side pays
+    /// the maker fee. Auction trades have no taker: the buyer pays the
+    /// trade's maker fee and the seller its taker fee.
+    pub fn settle(&mut self, trade: &Trade, taker: Option<Uuid>) {
+        let (base, quote) = symbol_assets(&trade.symbol);
+        let value = trade.value();
+        let maker = (trade.maker_fee, trade.maker_fee_currency);
+        let taker_fee = (trade.taker_fee, trade.taker_fee_currency);
+        let (buyer_fee, seller_fee) = if taker == Some(trade.buyer_order_id) {
+            (taker_fee, maker)
+        } else {
+            (maker, taker_fee)
+        };
+        let in_asset = |(fee, currency): (Decimal, FeeCurrency), wanted: FeeCurrency| {
+            if currency == wanted {
+                fee
+            } else {
+                Decimal::ZERO
+            }
+        };
+
+        self.spend(trade.buyer_order_id, &trade.buyer_id, &quote, value + in_asset(buyer_fee, FeeCurrency::Quote));
+        self.balance_mut(&trade.buyer_id, &base).available += trade.quantity - in_asset(buyer_fee, FeeCurrency::Base);
+
+        self.spend(trade.seller_order_id, &trade.seller_id, &base, trade.quantity + in_asset(seller_fee, FeeCurrency::Base));
+        self.balance_mut(&trade.seller_id, &quote).available += value - in_asset(seller_fee, FeeCurrency::Quote);
+
+        for (fee, currency) in [buyer_fee, seller_fee] {
+            let asset = match currency {
+                FeeCurrency::Quote => &quote,
+                FeeCurrency::Base => &base,
+            };
+            self.balance_mut(FEE_ACCOUNT, asset).available += fee;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn trade(price: Decimal, quantity: Decimal, buy: Uuid, sell: Uuid) -> Trade {
+        let value = price * quantity;
+        Trade::new(
+            "BTC/USD".to_string(),
+            price,
+            quantity,
+            buy,
+            sell,
+            "buyer".to_string(),
+            "seller".to_string(),
+            value * dec!(0.001),
+            value * dec!(0.002),
+        )
+    }
+
+    #[test]
+    fn test_symbol_assets() {
+        assert_eq!(symbol_assets("BTC/USD"), ("BTC".to_string(), "USD".to_string()));
+        assert_eq!(symbol_assets("AAPL"), ("AAPL".to_string(), "USD".to_string()));
+    }
+
+    #[test]
+    fn test_reserve_and_release() {
+        let mut accounts = Accounts::new();
+        let order_id = Uuid::new_v4();
+        accounts.deposit("alice", "USD", dec!(100)).unwrap();
+
+        let err = accounts.reserve(order_id, "alice", "USD", dec!(150)).unwrap_err();
+        assert!(matches!(err, OrderBookError::InsufficientFunds { available, .. } if available == dec!(100)));
+
+        accounts.reserve(order_id, "alice", "USD", dec!(60)).unwrap();
+        assert_eq!(accounts.balance("alice", "USD"), Balance { available: dec!(40), reserved: dec!(60) });
+        assert!(accounts.withdraw("alice", "USD", dec!(50)).is_err());
+
+        assert!(accounts.resize(order_id, dec!(101)).is_err());
+        accounts.resize(order_id, dec!(20)).unwrap();
+        assert_eq!(accounts.balance("alice", "USD"), Balance { available: dec!(80), reserved: dec!(20) });
+
+        accounts.release(order_id);
+        assert_eq!(accounts.balance("alice", "USD"), Balance { available: dec!(100), reserved: dec!(0) });
+        assert_eq!(accounts.reserved_for(order_id), None);
+    }
+
+    #[test]
+    fn test_settle_moves_assets_and_collects_fees() {
+        let mut accounts = Accounts::new();
+        let (buy, sell) = (Uuid::new_v4(), Uuid::new_v4());
+        accounts.deposit("buyer", "USD", dec!(1000)).unwrap();
+        accounts.deposit("seller", "BTC", dec!(2)).unwrap();
+        accounts.reserve(buy, "buyer", "USD", dec!(1002)).unwrap_err();
+        accounts.reserve(buy, "buyer", "USD", dec!(1000)).unwrap();
+        accounts.reserve(sell, "seller", "BTC", dec!(2)).unwrap();
+
+        // The buyer takes: 500 value, 1 taker fee; the seller pays 0.5 maker fee
+        accounts.settle(&trade(dec!(500), dec!(1), buy, sell), Some(buy));
+
+        assert_eq!(accounts.balance("buyer", "USD"), Balance { available: dec!(0), reserved: dec!(499) });
+        assert_eq!(accounts.balance("buyer", "BTC").available, dec!(1));
+        assert_eq!(accounts.balance("seller", "BTC"), Balance { available: dec!(0), reserved: dec!(1) });
+        assert_eq!(accounts.balance("seller", "USD").available, dec!(499.5));
+        assert_eq!(accounts.balance(FEE_ACCOUNT, "USD").available, dec!(1.5));
+        assert_eq!(accounts.total("USD"), dec!(1000));
+        assert_eq!(accounts.total("BTC"), dec!(2));
+    }
+
+    #[test]
+    fn test_settle_charges_base_fees_and_pays_rebates() {
+        let mut accounts = Accounts::new();
+        let (buy, sell) = (Uuid::new_v4(), Uuid::new_v4());
+        accounts.deposit("buyer", "USD", dec!(500)).unwrap();
+        accounts.deposit("seller", "BTC", dec!(2)).unwrap();
+        accounts.reserve(buy, "buyer", "USD", dec!(500)).unwrap();
+        accounts.reserve(sell, "seller", "BTC", dec!(1.01)).unwrap();
+
+        // The seller takes and pays 0.01 BTC; the resting buyer earns a 0.25 USD rebate
+        let mut trade = trade(dec!(500), dec!(1), buy, sell);
+        trade.maker_fee = dec!(-0.25);
+        trade.taker_fee = dec!(0.01);
+        trade.taker_fee_currency = FeeCurrency::Base;
+        accounts.settle(&trade, Some(sell));
+
+        assert_eq!(accounts.balance("buyer", "USD"), Balance { available: dec!(0), reserved: dec!(0.25) });
+        assert_eq!(accounts.balance("buyer", "BTC").available, dec!(1));
+        assert_eq!(accounts.balance("seller", "BTC"), Balance { available: dec!(0.99), reserved: dec!(0) });
+        assert_eq!(accounts.balance("seller", "USD").available, dec!(500));
+        assert_eq!(accounts.balance(FEE_ACCOUNT, "USD").available, dec!(-0.25));
+        assert_eq!(accounts.balance(FEE_ACCOUNT, "BTC").available, dec!(0.01));
+        assert_eq!(accounts.total("USD"), dec!(500));
+        assert_eq!(accounts.total("BTC"), dec!(2));
+    }
+}
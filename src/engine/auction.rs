@@ -0,0 +1,353 @@
+//! Call auction uncrossing
+//!
+//! During a call auction orders accumulate in the book without matching. The
+//! auction ends with a single uncross where every executable order trades at
+//! one price. The uncross price is chosen by these rules, in order:
+//!
+//! 1. Maximum executable volume
+//! 2. Minimum imbalance (quantity left unmatched at the price)
+//! 3. Market pressure: if every remaining price leaves a buy surplus, the
+//!    highest price; if every remaining price leaves a sell surplus, the lowest
+//! 4. Closest to the reference price (the middle of the remaining prices when
+//!    there is no reference), lower price on a tie
+//!
+//! Market orders take part at any price and have priority over limit orders.
+//! Self-trade prevention does not apply to the uncross.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::{IndicativeUncross, Order, OrderBook, OrderSide, Trade};
+
+use super::fees::{FeeEngine, Liquidity};
+
+/// Quantity one side of the book is willing to trade
+struct SideInterest {
+    /// Market orders, executable at any price
+    market: Decimal,
+    /// Limit quantity by price
+    limits: BTreeMap<Decimal, Decimal>,
+}
+
+impl SideInterest {
+    fn collect(book: &OrderBook, side: OrderSide) -> Self {
+        let mut interest = Self {
+            market: Decimal::ZERO,
+            limits: BTreeMap::new(),
+        };
+
+        for order in book.orders.values().filter(|o| o.side == side) {
+            match order.price {
+                Some(price) => *interest.limits.entry(price).or_default() += order.remaining_quantity(),
+                None => interest.market += order.remaining_quantity(),
+            }
+        }
+
+        interest
+    }
+}
+
+/// Evaluate the executable volume and imbalance at a candidate price
+fn evaluate(price: Decimal, buys: &SideInterest, sells: &SideInterest) -> IndicativeUncross {
+    let demand = buys.market + buys.limits.range(price..).map(|(_, qty)| *qty).sum::<Decimal>();
+    let supply = sells.market + sells.limits.range(..=price).map(|(_, qty)| *qty).sum::<Decimal>();
+
+    let (imbalance, imbalance_side) = match demand.cmp(&supply) {
+        Ordering::Greater => (demand - supply, Some(OrderSide::Buy)),
+        Ordering::Less => (supply - demand, Some(OrderSide::Sell)),
+        Ordering::Equal => (Decimal::ZERO, None),
+    };
+
+    IndicativeUncross {
+        price,
+        volume: demand.min(supply),
+        imbalance,
+        imbalance_side,
+    }
+}
+
+/// Compute the price and volume the book would uncross at
+///
+/// Returns `None` when nothing would execute. If the book holds only market
+/// orders, the reference price is the only candidate.
+pub fn compute_uncross(book: &OrderBook, reference_price: Option<Decimal>) -> Option<IndicativeUncross> {
+    let buys = SideInterest::collect(book, OrderSide::Buy);
+    let sells = SideInterest::collect(book, OrderSide::Sell);
+
+    let mut prices: BTreeSet<Decimal> = buys.limits.keys().chain(sells.limits.keys()).copied().collect();
+    if prices.is_empty() {
+        prices.extend(reference_price);
+    }
+
+    let mut candidates: Vec<IndicativeUncross> = prices
+        .into_iter()
+        .map(|price| evaluate(price, &buys, &sells))
+        .filter(|candidate| candidate.volume > Decimal::ZERO)
+        .collect();
+
+    // 1. Maximum executable volume
+    let max_volume = candidates.iter().map(|c| c.volume).max()?;
+    candidates.retain(|c| c.volume == max_volume);
+
+    // 2. Minimum imbalance
+    let min_imbalance = candidates.iter().map(|c| c.imbalance).min()?;
+    candidates.retain(|c| c.imbalance == min_imbalance);
+
+    // 3. Market pressure (candidates are in ascending price order)
+    if candidates.iter().all(|c| c.imbalance_side == Some(OrderSide::Buy)) {
+        return candidates.pop();
+    }
+    if candidates.iter().all(|c| c.imbalance_side == Some(OrderSide::Sell)) {
+        return candidates.into_iter().next();
+    }
+
+    // 4. Reference price
+    let lowest = candidates.first()?.price;
+    let highest = candidates.last()?.price;
+    let anchor = reference_price.unwrap_or((lowest + highest) / Decimal::TWO);
+    candidates
+        .into_iter()
+        .min_by_key(|c| ((c.price - anchor).abs(), c.price))
+}
+
+/// Orders of one side that can execute at `price`, in auction priority:
+/// market orders first, then better price, then earlier time
+fn executable_orders(book: &OrderBook, side: OrderSide, price: Decimal) -> Vec<(Uuid, Decimal)> {
+    let mut orders: Vec<&Order> = book
+        .orders
+        .values()
+        .filter(|o| o.side == side)
+        .filter(|o| match (o.price, side) {
+            (None, _) => true,
+            (Some(limit), OrderSide::Buy) => limit >= price,
+            (Some(limit), OrderSide::Sell) => limit <= price,
+        })
+        .collect();
+
+    orders.sort_by(|a, b| {
+        let by_price = match (a.price, b.price) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(pa), Some(pb)) => match side {
+                OrderSide::Buy => pb.cmp(&pa),
+                OrderSide::Sell => pa.cmp(&pb),
+            },
+        };
+        by_price.then(a.timestamp.cmp(&b.timestamp))
+    });
+
+    orders.iter().map(|o| (o.id, o.remaining_quantity())).collect()
+}
+
+/// Apply an auction fill to a resting order and its price level
+fn apply_fill(book: &mut OrderBook, order_id: Uuid, quantity: Decimal) {
+    let Some(order) = book.orders.get_mut(&order_id) else {
+        return;
+    };
+    order.fill(quantity);
+    let (price, side, filled) = (order.price, order.side, order.is_filled());
+
+    if let Some(price) = price {
+        let levels = match side {
+            OrderSide::Buy => &mut book.bids,
+            OrderSide::Sell => &mut book.asks,
+        };
+        if let Some(level) = levels.get_mut(&price) {
+            if filled {
+                // Remaining quantity is gone from the level through `quantity` already
+                level.remove_order(order_id, Decimal::ZERO);
+            }
+            level.total_quantity -= quantity;
+            if level.is_empty() {
+                levels.remove(&price);
+            }
+        }
+    }
+
+    if filled {
+        book.remove_order(order_id);
+    }
+}
+
+/// Execute the uncross: trade `volume` at `price` between the executable
+/// buy and sell orders in auction priority
+///
+/// Filled orders leave the book, partially filled limit orders keep resting.
+/// Market orders that are not completely filled stay in the book; the caller
+/// decides what happens to them. There is no aggressor, so `fees` charges
+/// both sides their maker rate: the buyer's fee is recorded as the trade's
+/// maker fee and the seller's as its taker fee.
+pub fn execute_uncross(book: &mut OrderBook, price: Decimal, volume: Decimal, fees: &FeeEngine) -> Vec<Trade> {
+    let buys = executable_orders(book, OrderSide::Buy, price);
+    let sells = executable_orders(book, OrderSide::Sell, price);
+
+    let mut trades = Vec::new();
+    let mut left = volume;
+    let (mut b, mut s) = (0, 0);
+    let mut buy_left = buys.first().map(|(_, qty)| *qty).unwrap_or_default();
+    let mut sell_left = sells.first().map(|(_, qty)| *qty).unwrap_or_default();
+
+    while left > Decimal::ZERO && b < buys.len() && s < sells.len() {
+        let quantity = buy_left.min(sell_left).min(left);
+        let (buy_id, sell_id) = (buys[b].0, sells[s].0);
+        let buyer_id = book.orders[&buy_id].user_id.clone();
+        let seller_id = book.orders[&sell_id].user_id.clone();
+
+        let mut trade = Trade::new(
+            book.symbol.clone(),
+            price,
+            quantity,
+            buy_id,
+            sell_id,
+            buyer_id,
+            seller_id,
+            Decimal::ZERO,
+            Decimal::ZERO,
+        );
+        (trade.maker_fee, trade.maker_fee_currency) =
+            fees.fee(&trade.buyer_id, &book.symbol, Liquidity::Maker, price, quantity, trade.timestamp);
+        (trade.taker_fee, trade.taker_fee_currency) =
+            fees.fee(&trade.seller_id, &book.symbol, Liquidity::Maker, price, quantity, trade.timestamp);
+        trades.push(trade);
+
+        apply_fill(book, buy_id, quantity);
+        apply_fill(book, sell_id, quantity);
+        left -= quantity;
+        buy_left -= quantity;
+        sell_left -= quantity;
+
+        if buy_left <= Decimal::ZERO {
+            b += 1;
+            buy_left = buys.get(b).map(|(_, qty)| *qty).unwrap_or_default();
+        }
+        if sell_left <= Decimal::ZERO {
+            s += 1;
+            sell_left = sells.get(s).map(|(_, qty)| *qty).unwrap_or_default();
+        }
+    }
+
+    for trade in &trades {
+        book.add_trade(trade.clone());
+    }
+
+    trades
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{OrderType, PriceLevel};
+    use rust_decimal_macros::dec;
+
+    fn add(book: &mut OrderBook, side: OrderSide, price: Option<Decimal>, quantity: Decimal) -> Uuid {
+        let order_type = if price.is_some() { OrderType::Limit } else { OrderType::Market };
+        let order = Order::new("TEST".to_string(), side, order_type, price, quantity, "user1".to_string());
+        let id = order.id;
+        if let Some(price) = price {
+            let levels = match side {
+                OrderSide::Buy => &mut book.bids,
+                OrderSide::Sell => &mut book.asks,
+            };
+            levels
+                .entry(price)
+                .or_insert_with(|| PriceLevel::new(price))
+                .add_order(id, quantity);
+        }
+        book.insert_order(order);
+        id
+    }
+
+    #[test]
+    fn test_uncross_maximizes_volume() {
+        let mut book = OrderBook::new("TEST".to_string());
+        add(&mut book, OrderSide::Buy, Some(dec!(102)), dec!(10));
+        add(&mut book, OrderSide::Buy, Some(dec!(101)), dec!(20));
+        add(&mut book, OrderSide::Buy, Some(dec!(100)), dec!(30));
+        add(&mut book, OrderSide::Sell, Some(dec!(99)), dec!(15));
+        add(&mut book, OrderSide::Sell, Some(dec!(100)), dec!(15));
+        add(&mut book, OrderSide::Sell, Some(dec!(101)), dec!(20));
+
+        // Executable volume: 99 -> 15, 100 -> 30, 101 -> 30, 102 -> 10
+        // 100 leaves a buy surplus of 30, 101 a sell surplus of 20
+        let uncross = compute_uncross(&book, None).unwrap();
+        assert_eq!(uncross.price, dec!(101));
+        assert_eq!(uncross.volume, dec!(30));
+        assert_eq!(uncross.imbalance, dec!(20));
+        assert_eq!(uncross.imbalance_side, Some(OrderSide::Sell));
+    }
+
+    #[test]
+    fn test_uncross_balanced_range_uses_reference_price() {
+        let mut book = OrderBook::new("TEST".to_string());
+        add(&mut book, OrderSide::Buy, Some(dec!(101)), dec!(10));
+        add(&mut book, OrderSide::Sell, Some(dec!(100)), dec!(10));
+
+        // 100 and 101 both execute 10 with no imbalance
+        assert_eq!(compute_uncross(&book, None).unwrap().price, dec!(100));
+        assert_eq!(compute_uncross(&book, Some(dec!(105))).unwrap().price, dec!(101));
+        assert_eq!(compute_uncross(&book, Some(dec!(90))).unwrap().price, dec!(100));
+    }
+
+    #[test]
+    fn test_uncross_tie_breaks_on_imbalance_and_pressure() {
+        let mut book = OrderBook::new("TEST".to_string());
+        add(&mut book, OrderSide::Buy, Some(dec!(101)), dec!(30));
+        add(&mut book, OrderSide::Sell, Some(dec!(99)), dec!(10));
+        add(&mut book, OrderSide::Sell, Some(dec!(100)), dec!(10));
+
+        // Volume is 20 at 100 and 101, both leave a buy surplus of 10:
+        // buy pressure picks the highest price
+        let uncross = compute_uncross(&book, Some(dec!(99))).unwrap();
+        assert_eq!(uncross.price, dec!(101));
+        assert_eq!(uncross.volume, dec!(20));
+        assert_eq!(uncross.imbalance_side, Some(OrderSide::Buy));
+    }
+
+    #[test]
+    fn test_no_uncross_when_book_does_not_cross() {
+        let mut book = OrderBook::new("TEST".to_string());
+        add(&mut book, OrderSide::Buy, Some(dec!(99)), dec!(10));
+        add(&mut book, OrderSide::Sell, Some(dec!(100)), dec!(10));
+        assert!(compute_uncross(&book, Some(dec!(100))).is_none());
+
+        // Market orders only: uncross at the reference price
+        let mut book = OrderBook::new("TEST".to_string());
+        add(&mut book, OrderSide::Buy, None, dec!(5));
+        add(&mut book, OrderSide::Sell, None, dec!(8));
+        assert!(compute_uncross(&book, None).is_none());
+        let uncross = compute_uncross(&book, Some(dec!(50))).unwrap();
+        assert_eq!((uncross.price, uncross.volume), (dec!(50), dec!(5)));
+    }
+
+    #[test]
+    fn test_execute_uncross_fills_in_auction_priority() {
+        let mut book = OrderBook::new("TEST".to_string());
+        let market_buy = add(&mut book, OrderSide::Buy, None, dec!(5));
+        let buy_101 = add(&mut book, OrderSide::Buy, Some(dec!(101)), dec!(10));
+        let buy_100 = add(&mut book, OrderSide::Buy, Some(dec!(100)), dec!(10));
+        let sell_99 = add(&mut book, OrderSide::Sell, Some(dec!(99)), dec!(12));
+        let sell_100 = add(&mut book, OrderSide::Sell, Some(dec!(100)), dec!(10));
+
+        let uncross = compute_uncross(&book, None).unwrap();
+        assert_eq!((uncross.price, uncross.volume), (dec!(100), dec!(22)));
+
+        let trades = execute_uncross(&mut book, uncross.price, uncross.volume, &FeeEngine::new());
+        assert!(trades.iter().all(|t| t.price == dec!(100)));
+        assert_eq!(trades.iter().map(|t| t.quantity).sum::<Decimal>(), dec!(22));
+        assert_eq!(trades[0].buyer_order_id, market_buy);
+        assert_eq!(trades[0].seller_order_id, sell_99);
+
+        // The market order and the 101 bid fill, the 100 bid fills 7 of 10
+        assert!(!book.orders.contains_key(&market_buy));
+        assert!(!book.orders.contains_key(&buy_101));
+        assert!(!book.orders.contains_key(&sell_99));
+        assert!(!book.orders.contains_key(&sell_100));
+        assert_eq!(book.orders[&buy_100].remaining_quantity(), dec!(3));
+        assert_eq!(book.bids[&dec!(100)].total_quantity, dec!(3));
+        assert!(book.asks.is_empty());
+    }
+}
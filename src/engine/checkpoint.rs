@@ -0,0 +1,47 @@
+//! Periodic engine checkpoints
+//!
+//! Every five minutes the engine's state, together with the running execution
+//! algorithms, is written to its snapshot store and the WAL segments the
+//! snapshots cover are deleted, so recovery only replays the tail of the log.
+//! Snapshot IO runs on the blocking thread pool. Busy engines are also
+//! checkpointed by the pipeline's `SnapshotConsumer` every so many events.
+
+use std::sync::Arc;
+
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tracing::{error, info};
+
+use crate::algorithms::AlgorithmManager;
+
+use super::orderbook::OrderBookEngine;
+
+/// Time between checkpoints
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Checkpoint the engine every five minutes
+pub async fn run_checkpointer(engine: Arc<OrderBookEngine>, algorithm_manager: Arc<AlgorithmManager>) {
+    info!("Checkpointer starting ({}s interval)", CHECKPOINT_INTERVAL.as_secs());
+    let mut tick_interval = interval(CHECKPOINT_INTERVAL);
+    tick_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // The first tick completes immediately; recovery has just replayed the log
+    tick_interval.tick().await;
+
+    loop {
+        tick_interval.tick().await;
+
+        let algorithms = match algorithm_manager.snapshot() {
+            Ok(algorithms) => algorithms,
+            Err(e) => {
+                error!("Failed to capture algorithm state: {}", e);
+                continue;
+            }
+        };
+
+        let engine = engine.clone();
+        match tokio::task::spawn_blocking(move || engine.checkpoint(algorithms)).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => error!("Checkpoint failed: {}", e),
+            Err(e) => error!("Checkpoint task failed: {}", e),
+        }
+    }
+}
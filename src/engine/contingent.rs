@@ -0,0 +1,532 @@
+//! Contingent order management (OCO, OTO and bracket orders)
+//!
+//! The `ContingentOrderManager` only tracks group state. It never touches the
+//! order book directly: every state change returns a list of `ContingentAction`s
+//! that the `OrderBookEngine` carries out (placing or cancelling legs) once the
+//! manager's lock has been released.
+
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::{
+    ContingencyType, ContingentGroup, ContingentGroupStatus, LegOrder, LegStatus, Order,
+    OrderType, StopOrder,
+};
+use crate::utils::clock;
+
+use super::errors::OrderBookError;
+
+/// Work the engine must perform after a contingent state change
+#[derive(Debug, Clone)]
+pub enum ContingentAction {
+    /// Submit a leg's order to the order book
+    PlaceOrder(Order),
+    /// Register a leg's stop order with the trigger engine
+    PlaceStop(StopOrder),
+    /// Cancel a leg resting in the order book
+    CancelOrder { symbol: String, order_id: Uuid },
+    /// Cancel a leg waiting in the trigger engine
+    CancelStop(Uuid),
+}
+
+/// Tracks contingent groups and reacts to fills, triggers and cancellations of their legs
+#[derive(Debug, Default)]
+pub struct ContingentOrderManager {
+    groups: HashMap<Uuid, ContingentGroup>,
+    /// Index for O(1) lookup by leg order ID
+    leg_index: HashMap<Uuid, Uuid>, // leg order_id -> group_id
+}
+
+/// Action that places a leg's order
+fn place_action(order: &LegOrder) -> ContingentAction {
+    match order {
+        LegOrder::Order(order) => ContingentAction::PlaceOrder(order.clone()),
+        LegOrder::Stop(stop) => ContingentAction::PlaceStop(stop.clone()),
+    }
+}
+
+/// Action that cancels a leg's order
+fn cancel_action(order: &LegOrder) -> ContingentAction {
+    match order {
+        LegOrder::Order(order) => ContingentAction::CancelOrder {
+            symbol: order.symbol.clone(),
+            order_id: order.id,
+        },
+        LegOrder::Stop(stop) => ContingentAction::CancelStop(stop.id),
+    }
+}
+
+/// Validate the shape of a contingent group before it is accepted
+///
+/// # Rules
+/// - Every leg trades the group's symbol
+/// - OCO: no entry, exactly one limit leg and one stop leg
+/// - OTO: a non-stop entry and at least one child
+/// - Bracket: a non-stop entry, one take-profit limit and one stop-loss stop,
+///   both on the opposite side of the entry and for the entry quantity
+pub fn validate_contingent_group(group: &ContingentGroup) -> Result<(), OrderBookError> {
+    let invalid = |msg: &str| Err(OrderBookError::InvalidContingentOrder(msg.to_string()));
+
+    if group.all_legs().any(|leg| leg.symbol() != group.symbol) {
+        return invalid("All legs must trade the group symbol");
+    }
+
+    let is_limit = |order: &LegOrder| {
+        matches!(order, LegOrder::Order(o) if o.order_type == OrderType::Limit)
+    };
+
+    match group.contingency_type {
+        ContingencyType::Oco => {
+            if group.entry.is_some() {
+                return invalid("OCO groups do not take an entry order");
+            }
+            let limits = group.legs.iter().filter(|leg| is_limit(&leg.order)).count();
+            let stops = group.legs.iter().filter(|leg| leg.is_stop()).count();
+            if group.legs.len() != 2 || limits != 1 || stops != 1 {
+                return invalid("OCO groups need exactly one limit leg and one stop leg");
+            }
+        }
+        ContingencyType::Oto | ContingencyType::Bracket => {
+            let Some(entry) = &group.entry else {
+                return invalid("OTO and bracket groups need an entry order");
+            };
+            if entry.is_stop() {
+                return invalid("Entry order cannot be a stop order");
+            }
+            if group.legs.is_empty() {
+                return invalid("OTO groups need at least one child order");
+            }
+
+            if group.contingency_type == ContingencyType::Bracket {
+                let take_profits = group.legs.iter().filter(|leg| is_limit(&leg.order)).count();
+                let stop_losses = group.legs.iter().filter(|leg| leg.is_stop()).count();
+                if group.legs.len() != 2 || take_profits != 1 || stop_losses != 1 {
+                    return invalid("Bracket groups need one take-profit limit and one stop-loss stop");
+                }
+                if group.legs.iter().any(|leg| leg.side() == entry.side()) {
+                    return invalid("Bracket exits must be on the opposite side of the entry");
+                }
+                if group.legs.iter().any(|leg| leg.quantity() != entry.quantity()) {
+                    return invalid("Bracket exits must match the entry quantity");
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl ContingentOrderManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuild the manager from the groups of a snapshot
+    pub fn from_groups(groups: impl IntoIterator<Item = ContingentGroup>) -> Self {
+        let mut manager = Self::new();
+        for group in groups {
+            for leg in group.all_legs() {
+                manager.leg_index.insert(leg.id(), group.id);
+            }
+            manager.groups.insert(group.id, group);
+        }
+        manager
+    }
+
+    /// Every group, finished ones included
+    pub fn groups(&self) -> impl Iterator<Item = &ContingentGroup> {
+        self.groups.values()
+    }
+
+    /// Register a validated group and return the actions that make it live
+    ///
+    /// Groups with an entry only place the entry; OCO groups place both legs.
+    pub fn register(&mut self, mut group: ContingentGroup) -> Result<Vec<ContingentAction>, OrderBookError> {
+        validate_contingent_group(&group)?;
+
+        let mut actions = Vec::new();
+        match group.entry.as_mut() {
+            Some(entry) => {
+                entry.status = LegStatus::Working;
+                actions.push(place_action(&entry.order));
+            }
+            None => {
+                for leg in group.legs.iter_mut() {
+                    leg.status = LegStatus::Working;
+                    actions.push(place_action(&leg.order));
+                }
+            }
+        }
+
+        for leg in group.all_legs() {
+            self.leg_index.insert(leg.id(), group.id);
+        }
+        self.groups.insert(group.id, group);
+
+        Ok(actions)
+    }
+
+    /// Get a group by ID
+    pub fn get_group(&self, group_id: Uuid) -> Option<&ContingentGroup> {
+        self.groups.get(&group_id)
+    }
+
+    /// Get the group a leg belongs to
+    pub fn get_group_for_leg(&self, order_id: Uuid) -> Option<&ContingentGroup> {
+        self.leg_index
+            .get(&order_id)
+            .and_then(|group_id| self.groups.get(group_id))
+    }
+
+    /// Whether a leg is still waiting to be placed or working
+    ///
+    /// Placements are carried out after the lock is released, so a leg
+    /// cancelled by an earlier action of the same batch (a sibling that
+    /// filled on placement) must be skipped rather than placed.
+    pub fn is_leg_working(&self, order_id: Uuid) -> bool {
+        self.get_group_for_leg(order_id)
+            .and_then(|group| group.all_legs().find(|leg| leg.id() == order_id))
+            .is_some_and(|leg| leg.status == LegStatus::Working)
+    }
+
+    /// Get total number of groups that are still pending or active
+    pub fn get_total_active_groups(&self) -> usize {
+        self.groups.values().filter(|group| !group.is_finished()).count()
+    }
+
+    /// Record an execution against a leg
+    ///
+    /// - An entry fill that completes the entry releases the contingent legs
+    /// - Any fill of an exclusive (OCO / bracket) leg cancels its sibling
+    pub fn on_fill(&mut self, order_id: Uuid, quantity: Decimal) -> Vec<ContingentAction> {
+        let Some(group) = self.group_for_leg_mut(order_id) else {
+            return Vec::new();
+        };
+        if group.is_finished() {
+            return Vec::new();
+        }
+
+        let is_entry = group.entry.as_ref().is_some_and(|entry| entry.id() == order_id);
+        let Some(leg) = group.leg_mut(order_id) else {
+            return Vec::new();
+        };
+        if leg.status.is_terminal() {
+            return Vec::new();
+        }
+
+        leg.filled_quantity += quantity;
+        leg.status = if leg.filled_quantity >= leg.quantity() {
+            LegStatus::Filled
+        } else {
+            LegStatus::PartiallyFilled
+        };
+        let leg_filled = leg.status == LegStatus::Filled;
+
+        let mut actions = Vec::new();
+        if is_entry {
+            if leg_filled {
+                group.status = ContingentGroupStatus::Active;
+                for child in group.legs.iter_mut() {
+                    child.status = LegStatus::Working;
+                    actions.push(place_action(&child.order));
+                }
+            }
+        } else if group.legs_are_exclusive() {
+            actions.extend(Self::cancel_siblings(group, order_id));
+        }
+
+        Self::refresh_status(group);
+        actions
+    }
+
+    /// Record that a stop leg fired and submitted `triggered_order_id`
+    ///
+    /// For exclusive groups the sibling leg is cancelled.
+    pub fn on_stop_triggered(&mut self, stop_id: Uuid, triggered_order_id: Uuid) -> Vec<ContingentAction> {
+        let Some(group) = self.group_for_leg_mut(stop_id) else {
+            return Vec::new();
+        };
+        if group.is_finished() {
+            return Vec::new();
+        }
+
+        let Some(leg) = group.leg_mut(stop_id) else {
+            return Vec::new();
+        };
+        if leg.status.is_terminal() {
+            return Vec::new();
+        }
+        leg.status = LegStatus::Triggered;
+        leg.triggered_order_id = Some(triggered_order_id);
+
+        let actions = if group.legs_are_exclusive() {
+            Self::cancel_siblings(group, stop_id)
+        } else {
+            Vec::new()
+        };
+
+        Self::refresh_status(group);
+        actions
+    }
+
+    /// Record that a working leg was cancelled outside the group (e.g. by the user)
+    ///
+    /// Cancelling any working leg cancels the rest of the group.
+    pub fn on_leg_cancelled(&mut self, order_id: Uuid) -> Vec<ContingentAction> {
+        let Some(group) = self.group_for_leg_mut(order_id) else {
+            return Vec::new();
+        };
+        if group.is_finished() {
+            return Vec::new();
+        }
+
+        match group.leg_mut(order_id) {
+            Some(leg) if !leg.status.is_terminal() => leg.status = LegStatus::Cancelled,
+            _ => return Vec::new(),
+        }
+
+        Self::cancel_remaining(group)
+    }
+
+    /// Cancel a whole group and return the cancellations for its working legs
+    pub fn cancel_group(&mut self, group_id: Uuid) -> Result<Vec<ContingentAction>, OrderBookError> {
+        let group = self
+            .groups
+            .get_mut(&group_id)
+            .ok_or(OrderBookError::OrderNotFound(group_id))?;
+
+        if group.is_finished() {
+            return Err(OrderBookError::OrderNotActive(group_id));
+        }
+
+        Ok(Self::cancel_remaining(group))
+    }
+
+    fn group_for_leg_mut(&mut self, order_id: Uuid) -> Option<&mut ContingentGroup> {
+        let group_id = self.leg_index.get(&order_id)?;
+        self.groups.get_mut(group_id)
+    }
+
+    /// Cancel every non-terminal contingent leg other than `order_id`
+    fn cancel_siblings(group: &mut ContingentGroup, order_id: Uuid) -> Vec<ContingentAction> {
+        let mut actions = Vec::new();
+        for sibling in group.legs.iter_mut() {
+            if sibling.id() == order_id || sibling.status.is_terminal() {
+                continue;
+            }
+            if sibling.status != LegStatus::Pending {
+                actions.push(cancel_action(&sibling.order));
+            }
+            sibling.status = LegStatus::Cancelled;
+        }
+        actions
+    }
+
+    /// Cancel every non-terminal leg and mark the group cancelled
+    fn cancel_remaining(group: &mut ContingentGroup) -> Vec<ContingentAction> {
+        let mut actions = Vec::new();
+        for leg in group.entry.iter_mut().chain(group.legs.iter_mut()) {
+            if leg.status.is_terminal() {
+                continue;
+            }
+            if leg.status != LegStatus::Pending {
+                actions.push(cancel_action(&leg.order));
+            }
+            leg.status = LegStatus::Cancelled;
+        }
+        group.status = ContingentGroupStatus::Cancelled;
+        group.updated_at = clock::now();
+        actions
+    }
+
+    /// Mark the group completed once every leg is terminal
+    fn refresh_status(group: &mut ContingentGroup) {
+        if group.all_legs().all(|leg| leg.status.is_terminal()) {
+            group.status = ContingentGroupStatus::Completed;
+        }
+        group.updated_at = clock::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use crate::models::{
+        ContingentLeg, OrderSide, SelfTradePreventionMode, StopOrderStatus, StopOrderType, TimeInForce,
+        TriggerCondition,
+    };
+    use rust_decimal_macros::dec;
+
+    fn limit(side: OrderSide, price: Decimal, quantity: Decimal) -> LegOrder {
+        LegOrder::Order(Order::new(
+            "TEST".to_string(),
+            side,
+            OrderType::Limit,
+            Some(price),
+            quantity,
+            "trader1".to_string(),
+        ))
+    }
+
+    fn stop(side: OrderSide, trigger_price: Decimal, quantity: Decimal) -> LegOrder {
+        LegOrder::Stop(StopOrder {
+            id: Uuid::new_v4(),
+            symbol: "TEST".to_string(),
+            user_id: "trader1".to_string(),
+            trigger_price,
+            trigger_condition: TriggerCondition::AtOrBelow,
+            stop_type: StopOrderType::StopMarket,
+            side,
+            quantity,
+            limit_price: None,
+            trail_amount: None,
+            trail_percent: None,
+            highest_price: None,
+            lowest_price: None,
+            created_at: Utc::now(),
+            expire_time: None,
+            status: StopOrderStatus::Pending,
+            time_in_force: TimeInForce::GTC,
+            stp_mode: SelfTradePreventionMode::None,
+            post_only: false,
+        })
+    }
+
+    fn bracket() -> ContingentGroup {
+        ContingentGroup::new(
+            "TEST".to_string(),
+            "trader1".to_string(),
+            ContingencyType::Bracket,
+            Some(limit(OrderSide::Buy, dec!(100), dec!(10))),
+            vec![
+                limit(OrderSide::Sell, dec!(110), dec!(10)),
+                stop(OrderSide::Sell, dec!(95), dec!(10)),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_validate_oco_shape() {
+        let two_limits = ContingentGroup::new(
+            "TEST".to_string(),
+            "trader1".to_string(),
+            ContingencyType::Oco,
+            None,
+            vec![
+                limit(OrderSide::Sell, dec!(110), dec!(10)),
+                limit(OrderSide::Sell, dec!(111), dec!(10)),
+            ],
+        );
+        assert!(validate_contingent_group(&two_limits).is_err());
+
+        let mut bad_bracket = bracket();
+        bad_bracket.legs[0] = ContingentLeg::new(limit(OrderSide::Buy, dec!(110), dec!(10)));
+        assert!(validate_contingent_group(&bad_bracket).is_err());
+
+        assert!(validate_contingent_group(&bracket()).is_ok());
+    }
+
+    #[test]
+    fn test_oco_fill_cancels_stop() {
+        let mut manager = ContingentOrderManager::new();
+        let group = ContingentGroup::new(
+            "TEST".to_string(),
+            "trader1".to_string(),
+            ContingencyType::Oco,
+            None,
+            vec![
+                limit(OrderSide::Sell, dec!(110), dec!(10)),
+                stop(OrderSide::Sell, dec!(95), dec!(10)),
+            ],
+        );
+        let group_id = group.id;
+        let limit_id = group.legs[0].id();
+        let stop_id = group.legs[1].id();
+
+        let actions = manager.register(group).unwrap();
+        assert_eq!(actions.len(), 2);
+
+        // A partial fill of the limit leg is enough to cancel the stop
+        let actions = manager.on_fill(limit_id, dec!(4));
+        assert!(matches!(actions.as_slice(), [ContingentAction::CancelStop(id)] if *id == stop_id));
+
+        let group = manager.get_group(group_id).unwrap();
+        assert_eq!(group.legs[0].status, LegStatus::PartiallyFilled);
+        assert_eq!(group.legs[1].status, LegStatus::Cancelled);
+        assert_eq!(group.status, ContingentGroupStatus::Active);
+
+        manager.on_fill(limit_id, dec!(6));
+        assert_eq!(manager.get_group(group_id).unwrap().status, ContingentGroupStatus::Completed);
+    }
+
+    #[test]
+    fn test_bracket_releases_exits_on_entry_fill() {
+        let mut manager = ContingentOrderManager::new();
+        let group = bracket();
+        let group_id = group.id;
+        let entry_id = group.entry.as_ref().unwrap().id();
+        let take_profit_id = group.legs[0].id();
+
+        let actions = manager.register(group).unwrap();
+        assert_eq!(actions.len(), 1);
+
+        // Partial entry fill does not release the exits yet
+        assert!(manager.on_fill(entry_id, dec!(5)).is_empty());
+        assert_eq!(manager.get_group(group_id).unwrap().status, ContingentGroupStatus::PendingEntry);
+
+        let actions = manager.on_fill(entry_id, dec!(5));
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(actions[0], ContingentAction::PlaceOrder(ref o) if o.id == take_profit_id));
+        assert!(matches!(actions[1], ContingentAction::PlaceStop(_)));
+        assert_eq!(manager.get_group(group_id).unwrap().status, ContingentGroupStatus::Active);
+    }
+
+    #[test]
+    fn test_bracket_stop_trigger_cancels_take_profit() {
+        let mut manager = ContingentOrderManager::new();
+        let group = bracket();
+        let group_id = group.id;
+        let entry_id = group.entry.as_ref().unwrap().id();
+        let take_profit_id = group.legs[0].id();
+        let stop_loss_id = group.legs[1].id();
+
+        manager.register(group).unwrap();
+        manager.on_fill(entry_id, dec!(10));
+
+        let triggered_order_id = Uuid::new_v4();
+        let actions = manager.on_stop_triggered(stop_loss_id, triggered_order_id);
+        assert!(matches!(
+            actions.as_slice(),
+            [ContingentAction::CancelOrder { order_id, .. }] if *order_id == take_profit_id
+        ));
+
+        let group = manager.get_group(group_id).unwrap();
+        assert_eq!(group.status, ContingentGroupStatus::Completed);
+        assert_eq!(group.legs[1].triggered_order_id, Some(triggered_order_id));
+    }
+
+    #[test]
+    fn test_cancel_group_only_cancels_live_legs() {
+        let mut manager = ContingentOrderManager::new();
+        let group = bracket();
+        let group_id = group.id;
+        let entry_id = group.entry.as_ref().unwrap().id();
+
+        manager.register(group).unwrap();
+
+        // Exits were never placed, so only the entry needs cancelling
+        let actions = manager.cancel_group(group_id).unwrap();
+        assert!(matches!(
+            actions.as_slice(),
+            [ContingentAction::CancelOrder { order_id, .. }] if *order_id == entry_id
+        ));
+        assert_eq!(manager.get_group(group_id).unwrap().status, ContingentGroupStatus::Cancelled);
+
+        // Follow-up notifications for the same legs are no-ops
+        assert!(manager.on_leg_cancelled(entry_id).is_empty());
+        assert!(manager.cancel_group(group_id).is_err());
+    }
+}
@@ -3,10 +3,12 @@
 //! This module centralizes all error types used by the order book engine,
 //! making error handling consistent and maintainable across the codebase.
 
+use rust_decimal::Decimal;
 use thiserror::Error;
 use uuid::Uuid;
 
 use super::matching::MatchingError;
+use crate::risk::{RiskError, RiskRejection};
 
 /// Errors that can occur during order book operations
 ///
@@ -18,10 +20,10 @@ use super::matching::MatchingError;
 ///
 /// # Error Categories
 ///
-/// - **Validation Errors**: `InvalidPrice`, `InvalidQuantity`, `InvalidExpireTime`, `InvalidSymbol`
-/// - **State Errors**: `OrderNotFound`, `OrderNotActive`, `DuplicateOrder`
-/// - **Trading Errors**: `InsufficientLiquidity`, `SelfTrade`
-/// - **Internal Errors**: `MatchingError`
+/// - **Validation Errors**: `InvalidPrice`, `InvalidQuantity`, `InvalidExpireTime`, `InvalidSymbol`, `InvalidAmendment`, `InvalidContingentOrder`, `InvalidTradingCalendar`, `InvalidFeeSchedule`, `InvalidMatchingPolicy`, `InvalidRiskLimits`, `InvalidCircuitBreakerConfig`, `InvalidCommand`
+/// - **State Errors**: `OrderNotFound`, `OrderNotActive`, `DuplicateOrder`, `InvalidTradingPhase`, `Standby`
+/// - **Trading Errors**: `InsufficientLiquidity`, `SelfTrade`, `InsufficientFunds`, `RiskRejected`, `CircuitBreakerRejected`
+/// - **Internal Errors**: `MatchingError`, `LockError`, `PersistenceError`, `PipelineUnavailable`, `ReplicationError`
 #[derive(Debug, Error)]
 pub enum OrderBookError {
     /// Order with the specified ID was not found in the order book
@@ -40,6 +42,38 @@ pub enum OrderBookError {
     #[error("Invalid expire time: {0}")]
     InvalidExpireTime(String),
 
+    /// Amend request does not describe a valid modification of the order
+    #[error("Invalid amendment: {0}")]
+    InvalidAmendment(String),
+
+    /// Contingent (OCO / OTO / bracket) group is malformed
+    #[error("Invalid contingent order: {0}")]
+    InvalidContingentOrder(String),
+
+    /// Trading session calendar is inconsistent (e.g. close before open)
+    #[error("Invalid trading calendar: {0}")]
+    InvalidTradingCalendar(String),
+
+    /// Fee schedule is inconsistent (e.g. unsorted tiers, rebate above the taker rate)
+    #[error("Invalid fee schedule: {0}")]
+    InvalidFeeSchedule(String),
+
+    /// Matching policy parameters are unusable (e.g. a negative lot size)
+    #[error("Invalid matching policy: {0}")]
+    InvalidMatchingPolicy(String),
+
+    /// Risk limits are unusable (e.g. a negative loss limit)
+    #[error("Invalid risk limits: {0}")]
+    InvalidRiskLimits(String),
+
+    /// Circuit breaker configuration is unusable (e.g. a zero-width price band)
+    #[error("Invalid circuit breaker config: {0}")]
+    InvalidCircuitBreakerConfig(String),
+
+    /// Request cannot be encoded as an engine command (e.g. a user ID that is too long)
+    #[error("Invalid command: {0}")]
+    InvalidCommand(String),
+
     /// Not enough liquidity in the order book to fill the order
     #[error("Insufficient liquidity")]
     InsufficientLiquidity,
@@ -48,6 +82,24 @@ pub enum OrderBookError {
     #[error("Self-trade detected")]
     SelfTrade,
 
+    /// The account cannot cover an order or withdrawal from its available balance
+    #[error("Insufficient funds: {user_id} needs {required} {asset}, {available} available")]
+    InsufficientFunds {
+        user_id: String,
+        asset: String,
+        required: Decimal,
+        available: Decimal,
+    },
+
+    /// A pre-trade risk check of the account failed
+    #[error("Risk check failed: {0}")]
+    RiskRejected(#[from] RiskRejection),
+
+    /// The symbol's circuit breaker refused the order (trading halted, order
+    /// too large, price outside the band)
+    #[error("Order rejected by circuit breaker: {0}")]
+    CircuitBreakerRejected(#[from] RiskError),
+
     /// An order with the same ID already exists
     #[error("Duplicate order: {0}")]
     DuplicateOrder(Uuid),
@@ -60,6 +112,15 @@ pub enum OrderBookError {
     #[error("Order already filled or cancelled: {0}")]
     OrderNotActive(Uuid),
 
+    /// The request is not allowed in the symbol's current trading phase
+    /// (e.g. an IOC order during a call auction, ending an auction that is not running)
+    #[error("Invalid trading phase: {0}")]
+    InvalidTradingPhase(String),
+
+    /// The engine is a replication follower; orders go to the primary
+    #[error("Engine is a standby replica; send requests to the primary")]
+    Standby,
+
     /// An error occurred during order matching
     #[error("Matching error: {0}")]
     MatchingError(#[from] MatchingError),
@@ -67,6 +128,19 @@ pub enum OrderBookError {
     /// Failed to acquire lock (RwLock poisoned)
     #[error("Lock error: {0}")]
     LockError(String),
+
+    /// Failed to journal an event to the write-ahead log
+    #[error("Persistence error: {0}")]
+    PersistenceError(String),
+
+    /// The ingestion pipeline is stopped or its matcher went away before replying
+    #[error("Pipeline unavailable: {0}")]
+    PipelineUnavailable(String),
+
+    /// Replication cannot continue (sequence gap, follower ahead of its
+    /// primary) or the request does not fit the node's replication role
+    #[error("Replication error: {0}")]
+    ReplicationError(String),
 }
 
 impl OrderBookError {
@@ -78,6 +152,14 @@ impl OrderBookError {
                 | OrderBookError::InvalidQuantity(_)
                 | OrderBookError::InvalidExpireTime(_)
                 | OrderBookError::InvalidSymbol(_)
+                | OrderBookError::InvalidAmendment(_)
+                | OrderBookError::InvalidContingentOrder(_)
+                | OrderBookError::InvalidTradingCalendar(_)
+                | OrderBookError::InvalidFeeSchedule(_)
+                | OrderBookError::InvalidMatchingPolicy(_)
+                | OrderBookError::InvalidRiskLimits(_)
+                | OrderBookError::InvalidCircuitBreakerConfig(_)
+                | OrderBookError::InvalidCommand(_)
         )
     }
 
@@ -88,6 +170,8 @@ impl OrderBookError {
             OrderBookError::OrderNotFound(_)
                 | OrderBookError::OrderNotActive(_)
                 | OrderBookError::DuplicateOrder(_)
+                | OrderBookError::InvalidTradingPhase(_)
+                | OrderBookError::Standby
         )
     }
 
@@ -95,7 +179,11 @@ impl OrderBookError {
     pub fn is_trading_error(&self) -> bool {
         matches!(
             self,
-            OrderBookError::InsufficientLiquidity | OrderBookError::SelfTrade
+            OrderBookError::InsufficientLiquidity
+                | OrderBookError::SelfTrade
+                | OrderBookError::InsufficientFunds { .. }
+                | OrderBookError::RiskRejected(_)
+                | OrderBookError::CircuitBreakerRejected(_)
         )
     }
 }
@@ -0,0 +1,42 @@
+//! Background expiry of DAY and GTD orders
+//!
+//! Orders are checked for expiry when they arrive, but a resting order only
+//! leaves the book when something removes it. The sweeper runs once per
+//! second, expires every resting order past its `expire_time` (DAY orders get
+//! the session close as theirs on entry). The expiries reach market data and
+//! execution reports as engine notices through the ingestion pipeline.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use tokio::time::{interval, Duration};
+use tracing::{error, info};
+
+use super::orderbook::OrderBookEngine;
+
+/// Expire resting orders once per second
+pub async fn run_expiry_sweeper(engine: Arc<OrderBookEngine>) {
+    info!("Order expiry sweeper starting");
+    let mut tick_interval = interval(Duration::from_secs(1));
+
+    loop {
+        tick_interval.tick().await;
+
+        // A follower's orders expire when its primary's cancellations arrive
+        if engine.is_standby() {
+            continue;
+        }
+
+        let expired = match engine.expire_orders(Utc::now()) {
+            Ok(expired) => expired,
+            Err(e) => {
+                error!("Failed to expire orders: {}", e);
+                continue;
+            }
+        };
+
+        for order in expired {
+            info!("Order {} ({:?}) expired on {}", order.id, order.time_in_force, order.symbol);
+        }
+    }
+}
@@ -1,7 +1,20 @@
+//! Trading fees
+//!
+//! The `FeeEngine` decides what each side of a trade pays. A user's fee
+//! schedule is their own if one is set, otherwise the symbol's, otherwise the
+//! default schedule. Schedules are tiered by the user's traded value over
+//! the last 30 days, may pay makers a rebate (a negative maker rate), and
+//! choose whether fees are charged in the quote or the base asset.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use chrono::{DateTime, Duration, Utc};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use crate::models::Trade;
+use crate::models::{FeeCurrency, Trade};
 
 /// Maker fee rate (0.10%)
 const MAKER_FEE_RATE: Decimal = dec!(0.001);
@@ -9,25 +22,346 @@ const MAKER_FEE_RATE: Decimal = dec!(0.001);
 /// Taker fee rate (0.20%)
 const TAKER_FEE_RATE: Decimal = dec!(0.002);
 
-/// Calculate maker fee for a given trade value
+/// Traded value counts towards volume tiers for this many days
+pub const VOLUME_WINDOW_DAYS: i64 = 30;
+
+/// Calculate maker fee for a given trade value at the default rate
 pub fn calculate_maker_fee(trade_value: Decimal) -> Decimal {
     trade_value * MAKER_FEE_RATE
 }
 
-/// Calculate taker fee for a given trade value
+/// Calculate taker fee for a given trade value at the default rate
 pub fn calculate_taker_fee(trade_value: Decimal) -> Decimal {
     trade_value * TAKER_FEE_RATE
 }
 
-/// Calculate total exchange profit from a list of trades
+/// Calculate total exchange profit from a list of trades, net of maker rebates
 pub fn calculate_exchange_profit(trades: &[Trade]) -> Decimal {
     trades.iter().map(|trade| trade.total_fees()).sum()
 }
 
+/// Which side of a trade a fee is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liquidity {
+    /// The resting order (and both sides of an auction uncross)
+    Maker,
+    /// The incoming order
+    Taker,
+}
+
+/// Rates that apply from a 30-day traded value upwards
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FeeTier {
+    /// Traded value (in the quote asset) from which the tier applies
+    #[serde(with = "crate::models::decimal")]
+    #[schema(value_type = String, example = "1000000")]
+    pub min_volume: Decimal,
+    /// Negative for a maker rebate
+    #[serde(with = "crate::models::decimal")]
+    #[schema(value_type = String, example = "-0.0001")]
+    pub maker_rate: Decimal,
+    #[serde(with = "crate::models::decimal")]
+    #[schema(value_type = String, example = "0.0015")]
+    pub taker_rate: Decimal,
+}
+
+/// Fee rates by volume tier and the asset fees are charged in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FeeSchedule {
+    /// Tiers by ascending `min_volume`, the first starting at zero
+    pub tiers: Vec<FeeTier>,
+    #[serde(default)]
+    pub fee_currency: FeeCurrency,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self::flat(MAKER_FEE_RATE, TAKER_FEE_RATE)
+    }
+}
+
+impl FeeSchedule {
+    /// One tier for every volume, charged in the quote asset
+    pub fn flat(maker_rate: Decimal, taker_rate: Decimal) -> Self {
+        Self {
+            tiers: vec![FeeTier {
+                min_volume: Decimal::ZERO,
+                maker_rate,
+                taker_rate,
+            }],
+            fee_currency: FeeCurrency::Quote,
+        }
+    }
+
+    /// Check that the schedule covers every volume and never pays out more
+    /// in maker rebates than it charges takers
+    pub fn validate(&self) -> Result<(), String> {
+        let Some(first) = self.tiers.first() else {
+            return Err("at least one tier is required".to_string());
+        };
+        if !first.min_volume.is_zero() {
+            return Err("the first tier must start at zero volume".to_string());
+        }
+        if self.tiers.windows(2).any(|pair| pair[0].min_volume >= pair[1].min_volume) {
+            return Err("tiers must be in ascending min_volume order".to_string());
+        }
+        for tier in &self.tiers {
+            if tier.taker_rate < Decimal::ZERO || tier.taker_rate >= Decimal::ONE {
+                return Err("taker_rate must be at least 0 and below 1".to_string());
+            }
+            if tier.maker_rate >= Decimal::ONE {
+                return Err("maker_rate must be below 1".to_string());
+            }
+            if tier.maker_rate + tier.taker_rate < Decimal::ZERO {
+                return Err("a maker rebate cannot exceed the taker rate of its tier".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Tier for a 30-day traded value
+    pub fn tier(&self, volume: Decimal) -> &FeeTier {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| tier.min_volume <= volume)
+            .unwrap_or(&self.tiers[0])
+    }
+
+    /// Highest rate any trade under the schedule can be charged
+    pub fn max_rate(&self) -> Decimal {
+        self.tiers
+            .iter()
+            .map(|tier| tier.maker_rate.max(tier.taker_rate))
+            .max()
+            .unwrap_or_default()
+            .max(Decimal::ZERO)
+    }
+}
+
+/// Every configured schedule
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct FeeSchedules {
+    pub default: FeeSchedule,
+    pub symbols: BTreeMap<String, FeeSchedule>,
+    pub users: BTreeMap<String, FeeSchedule>,
+}
+
+/// Which schedule a change replaces
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeeScheduleScope {
+    Default,
+    Symbol(String),
+    User(String),
+}
+
+/// Where the schedule that applies to a user and symbol comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeScheduleSource {
+    User,
+    Symbol,
+    Default,
+}
+
+/// Rates a user currently pays trading a symbol
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeeRates {
+    pub source: FeeScheduleSource,
+    /// Traded value of the last 30 days
+    #[schema(value_type = String, example = "25000.00")]
+    pub rolling_volume: Decimal,
+    pub tier: FeeTier,
+    pub fee_currency: FeeCurrency,
+}
+
+/// Fee schedules and the rolling traded value they are tiered by
+///
+/// Volumes are kept per user as the value of each trade in the window,
+/// oldest first. They are not persisted: recovery rebuilds them from the
+/// restored trade history.
+#[derive(Debug, Default)]
+pub struct FeeEngine {
+    schedules: FeeSchedules,
+    volumes: HashMap<String, UserVolume>,
+}
+
+/// Trades of one user in the volume window and their total value
+#[derive(Debug, Default)]
+struct UserVolume {
+    trades: VecDeque<(DateTime<Utc>, Decimal)>,
+    total: Decimal,
+}
+
+impl FeeEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every configured schedule
+    pub fn schedules(&self) -> &FeeSchedules {
+        &self.schedules
+    }
+
+    /// Replace every schedule (when restoring a snapshot)
+    pub fn set_schedules(&mut self, schedules: FeeSchedules) {
+        self.schedules = schedules;
+    }
+
+    /// Set (or with `None`, remove) the schedule of a scope; removing the
+    /// default schedule puts back the built-in rates
+    pub fn set_schedule(&mut self, scope: &FeeScheduleScope, schedule: Option<FeeSchedule>) -> Result<(), String> {
+        match scope {
+            FeeScheduleScope::Default => self.set_default_schedule(schedule.unwrap_or_default()),
+            FeeScheduleScope::Symbol(symbol) => self.set_symbol_schedule(symbol, schedule),
+            FeeScheduleScope::User(user_id) => self.set_user_schedule(user_id, schedule),
+        }
+    }
+
+    /// Replace the schedule of users and symbols without their own
+    pub fn set_default_schedule(&mut self, schedule: FeeSchedule) -> Result<(), String> {
+        schedule.validate()?;
+        self.schedules.default = schedule;
+        Ok(())
+    }
+
+    /// Set (or with `None`, remove) the schedule of a symbol
+    pub fn set_symbol_schedule(&mut self, symbol: &str, schedule: Option<FeeSchedule>) -> Result<(), String> {
+        set_schedule(&mut self.schedules.symbols, symbol, schedule)
+    }
+
+    /// Set (or with `None`, remove) the schedule of a user; it applies to
+    /// every symbol the user trades
+    pub fn set_user_schedule(&mut self, user_id: &str, schedule: Option<FeeSchedule>) -> Result<(), String> {
+        set_schedule(&mut self.schedules.users, user_id, schedule)
+    }
+
+    /// Schedule that applies to a user trading a symbol
+    pub fn schedule_for(&self, user_id: &str, symbol: &str) -> &FeeSchedule {
+        self.source_of(user_id, symbol).1
+    }
+
+    fn source_of(&self, user_id: &str, symbol: &str) -> (FeeScheduleSource, &FeeSchedule) {
+        if let Some(schedule) = self.schedules.users.get(user_id) {
+            (FeeScheduleSource::User, schedule)
+        } else if let Some(schedule) = self.schedules.symbols.get(symbol) {
+            (FeeScheduleSource::Symbol, schedule)
+        } else {
+            (FeeScheduleSource::Default, &self.schedules.default)
+        }
+    }
+
+    /// Rates that apply to a user trading a symbol at `at`
+    pub fn rates(&self, user_id: &str, symbol: &str, at: DateTime<Utc>) -> FeeRates {
+        let (source, schedule) = self.source_of(user_id, symbol);
+        let rolling_volume = self.rolling_volume(user_id, at);
+        FeeRates {
+            source,
+            rolling_volume,
+            tier: *schedule.tier(rolling_volume),
+            fee_currency: schedule.fee_currency,
+        }
+    }
+
+    /// Value a user traded in the window before `at`
+    ///
+    /// The running total less the trades that have left the window since the
+    /// user last traded; only those oldest trades are visited.
+    pub fn rolling_volume(&self, user_id: &str, at: DateTime<Utc>) -> Decimal {
+        let since = at - Duration::days(VOLUME_WINDOW_DAYS);
+        self.volumes
+            .get(user_id)
+            .map(|volume| {
+                let expired: Decimal = volume
+                    .trades
+                    .iter()
+                    .take_while(|(time, _)| *time <= since)
+                    .map(|(_, value)| *value)
+                    .sum();
+                volume.total - expired
+            })
+            .unwrap_or_default()
+    }
+
+    /// Fee of one side of a trade and the asset it is charged in
+    pub fn fee(
+        &self,
+        user_id: &str,
+        symbol: &str,
+        liquidity: Liquidity,
+        price: Decimal,
+        quantity: Decimal,
+        at: DateTime<Utc>,
+    ) -> (Decimal, FeeCurrency) {
+        let schedule = self.schedule_for(user_id, symbol);
+        let tier = schedule.tier(self.rolling_volume(user_id, at));
+        let rate = match liquidity {
+            Liquidity::Maker => tier.maker_rate,
+            Liquidity::Taker => tier.taker_rate,
+        };
+        let fee = match schedule.fee_currency {
+            FeeCurrency::Quote => price * quantity * rate,
+            FeeCurrency::Base => quantity * rate,
+        };
+        (fee, schedule.fee_currency)
+    }
+
+    /// Highest fee rate a user can pay trading a symbol, and its asset
+    ///
+    /// Orders reserve fees at this rate, so a fill can never cost more than
+    /// its reservation whichever tier or side it ends up on.
+    pub fn max_fee_rate(&self, user_id: &str, symbol: &str) -> (Decimal, FeeCurrency) {
+        let schedule = self.schedule_for(user_id, symbol);
+        (schedule.max_rate(), schedule.fee_currency)
+    }
+
+    /// Count a trade towards both parties' volume
+    pub fn record_trade(&mut self, trade: &Trade) {
+        let since = trade.timestamp - Duration::days(VOLUME_WINDOW_DAYS);
+        for user_id in [&trade.buyer_id, &trade.seller_id] {
+            let volume = self.volumes.entry(user_id.clone()).or_default();
+            volume.trades.push_back((trade.timestamp, trade.value()));
+            volume.total += trade.value();
+            while let Some((_, value)) = volume.trades.front().filter(|(time, _)| *time <= since).copied() {
+                volume.trades.pop_front();
+                volume.total -= value;
+            }
+        }
+    }
+
+    /// Recount every user's volume from a trade history
+    pub fn rebuild_volumes<'a>(&mut self, trades: impl IntoIterator<Item = &'a Trade>) {
+        self.volumes.clear();
+        let mut trades: Vec<&Trade> = trades.into_iter().collect();
+        trades.sort_by_key(|trade| trade.timestamp);
+        for trade in trades {
+            self.record_trade(trade);
+        }
+    }
+}
+
+fn set_schedule(
+    schedules: &mut BTreeMap<String, FeeSchedule>,
+    key: &str,
+    schedule: Option<FeeSchedule>,
+) -> Result<(), String> {
+    match schedule {
+        Some(schedule) => {
+            schedule.validate()?;
+            schedules.insert(key.to_string(), schedule);
+        }
+        None => {
+            schedules.remove(key);
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rust_decimal_macros::dec;
+    use uuid::Uuid;
 
     #[test]
     fn test_maker_fee() {
@@ -56,4 +390,87 @@ mod tests {
         assert_eq!(maker_fee, dec!(15.05));
         assert_eq!(taker_fee, dec!(30.10));
     }
+
+    fn tiered() -> FeeSchedule {
+        FeeSchedule {
+            tiers: vec![
+                FeeTier { min_volume: dec!(0), maker_rate: dec!(0.001), taker_rate: dec!(0.002) },
+                FeeTier { min_volume: dec!(10000), maker_rate: dec!(-0.0005), taker_rate: dec!(0.001) },
+            ],
+            fee_currency: FeeCurrency::Quote,
+        }
+    }
+
+    fn trade_at(value: Decimal, at: DateTime<Utc>) -> Trade {
+        let mut trade = Trade::new(
+            "AAPL".to_string(),
+            value,
+            dec!(1),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "alice".to_string(),
+            "bob".to_string(),
+            dec!(0),
+            dec!(0),
+        );
+        trade.timestamp = at;
+        trade
+    }
+
+    #[test]
+    fn test_schedule_validation() {
+        assert!(tiered().validate().is_ok());
+        assert!(FeeSchedule { tiers: Vec::new(), fee_currency: FeeCurrency::Quote }.validate().is_err());
+
+        let mut unsorted = tiered();
+        unsorted.tiers.reverse();
+        assert!(unsorted.validate().is_err());
+
+        let mut generous = tiered();
+        generous.tiers[1].maker_rate = dec!(-0.002);
+        assert_eq!(generous.validate().unwrap_err(), "a maker rebate cannot exceed the taker rate of its tier");
+    }
+
+    #[test]
+    fn test_volume_tiers_roll_over_thirty_days() {
+        let mut fees = FeeEngine::new();
+        fees.set_default_schedule(tiered()).unwrap();
+        let start = Utc::now();
+
+        let (fee, _) = fees.fee("alice", "AAPL", Liquidity::Maker, dec!(100), dec!(10), start);
+        assert_eq!(fee, dec!(1));
+
+        fees.record_trade(&trade_at(dec!(12000), start));
+        let (rebate, _) = fees.fee("alice", "AAPL", Liquidity::Maker, dec!(100), dec!(10), start);
+        assert_eq!(rebate, dec!(-0.5));
+
+        let later = start + Duration::days(VOLUME_WINDOW_DAYS);
+        assert_eq!(fees.rolling_volume("bob", later), dec!(0));
+        let (fee, _) = fees.fee("bob", "AAPL", Liquidity::Taker, dec!(100), dec!(10), later);
+        assert_eq!(fee, dec!(2));
+
+        // The running total drops trades as they leave the window
+        fees.record_trade(&trade_at(dec!(3000), start + Duration::days(20)));
+        assert_eq!(fees.rolling_volume("bob", start + Duration::days(20)), dec!(15000));
+        assert_eq!(fees.rolling_volume("bob", later), dec!(3000));
+        fees.record_trade(&trade_at(dec!(500), later));
+        assert_eq!(fees.rolling_volume("bob", later), dec!(3500));
+    }
+
+    #[test]
+    fn test_user_schedule_overrides_symbol_and_default() {
+        let mut fees = FeeEngine::new();
+        fees.set_symbol_schedule("BTC/USD", Some(FeeSchedule::flat(dec!(0), dec!(0.0005)))).unwrap();
+        let mut in_base = FeeSchedule::flat(dec!(0.0002), dec!(0.0004));
+        in_base.fee_currency = FeeCurrency::Base;
+        fees.set_user_schedule("whale", Some(in_base)).unwrap();
+        let now = Utc::now();
+
+        assert_eq!(fees.fee("alice", "AAPL", Liquidity::Taker, dec!(100), dec!(10), now), (dec!(2), FeeCurrency::Quote));
+        assert_eq!(fees.fee("alice", "BTC/USD", Liquidity::Taker, dec!(100), dec!(10), now), (dec!(0.5), FeeCurrency::Quote));
+        assert_eq!(fees.fee("whale", "BTC/USD", Liquidity::Taker, dec!(100), dec!(10), now), (dec!(0.004), FeeCurrency::Base));
+
+        fees.set_user_schedule("whale", None).unwrap();
+        assert_eq!(fees.max_fee_rate("whale", "BTC/USD"), (dec!(0.0005), FeeCurrency::Quote));
+    }
 }
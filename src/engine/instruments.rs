@@ -0,0 +1,144 @@
+//! Instrument registry
+//!
+//! Holds the reference data (tick size, lot size, quantity limits, minimum
+//! notional, price collar) the engine validates orders against. A strict
+//! registry only accepts registered symbols; an open registry also accepts
+//! unknown symbols and skips the reference data checks for them.
+//!
+//! The registry is loaded from the `symbols` table (`SymbolRepository`) or from
+//! a JSON config file holding an array of instruments:
+//!
+//! ```json
+//! [
+//!   { "symbol": "AAPL", "tick_size": "0.01", "lot_size": "1", "min_notional": "10" },
+//!   { "symbol": "EURUSD", "tick_size": "0.00001", "contract_size": "100000" }
+//! ]
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use crate::database::connection::DatabaseError;
+use crate::database::models::Symbol;
+use crate::database::repositories::SymbolRepository;
+use crate::models::Instrument;
+
+/// Reference data of all tradable instruments
+#[derive(Debug, Clone, Default)]
+pub struct InstrumentRegistry {
+    instruments: HashMap<String, Instrument>,
+    /// Accept symbols that are not registered
+    allow_unknown: bool,
+}
+
+impl InstrumentRegistry {
+    /// Create an empty strict registry (unknown symbols are rejected)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty open registry (unknown symbols are accepted without reference data)
+    pub fn open() -> Self {
+        Self {
+            allow_unknown: true,
+            ..Self::default()
+        }
+    }
+
+    /// Create a strict registry from a list of instruments
+    pub fn from_instruments(instruments: impl IntoIterator<Item = Instrument>) -> Self {
+        let mut registry = Self::new();
+        for instrument in instruments {
+            registry.register(instrument);
+        }
+        registry
+    }
+
+    /// Load a strict registry from a JSON config file
+    pub fn from_config_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let instruments: Vec<Instrument> =
+            serde_json::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self::from_instruments(instruments))
+    }
+
+    /// Load a strict registry from the `symbols` table
+    pub fn from_symbol_repository(repository: &dyn SymbolRepository) -> Result<Self, DatabaseError> {
+        Ok(Self::from_instruments(repository.get_all()?.iter().map(Instrument::from)))
+    }
+
+    /// Add or replace an instrument
+    pub fn register(&mut self, instrument: Instrument) {
+        self.instruments.insert(instrument.symbol.clone(), instrument);
+    }
+
+    /// Get the reference data of a symbol
+    pub fn get(&self, symbol: &str) -> Option<&Instrument> {
+        self.instruments.get(symbol)
+    }
+
+    /// Check if orders on a symbol are accepted
+    pub fn accepts(&self, symbol: &str) -> bool {
+        self.allow_unknown || self.instruments.contains_key(symbol)
+    }
+
+    /// Check if unknown symbols are accepted
+    pub fn is_open(&self) -> bool {
+        self.allow_unknown
+    }
+
+    /// All registered instruments, sorted by symbol
+    pub fn instruments(&self) -> Vec<Instrument> {
+        let mut instruments: Vec<Instrument> = self.instruments.values().cloned().collect();
+        instruments.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        instruments
+    }
+}
+
+impl From<&Symbol> for Instrument {
+    fn from(symbol: &Symbol) -> Self {
+        Self {
+            contract_size: symbol.contract_size,
+            ..Instrument::new(symbol.symbol_name.clone(), symbol.tick_size)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use std::io::Write;
+
+    #[test]
+    fn test_strict_and_open_registries() {
+        let registry = InstrumentRegistry::from_instruments([Instrument::new("AAPL".to_string(), dec!(0.01))]);
+        assert!(registry.accepts("AAPL"));
+        assert!(!registry.accepts("MSFT"));
+
+        let mut open = InstrumentRegistry::open();
+        assert!(open.accepts("MSFT"));
+        open.register(Instrument::new("AAPL".to_string(), dec!(0.01)));
+        assert_eq!(open.get("AAPL").unwrap().tick_size, dec!(0.01));
+        assert!(open.get("MSFT").is_none());
+    }
+
+    #[test]
+    fn test_load_from_config_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"[{{"symbol": "EURUSD", "tick_size": "0.00001", "contract_size": "100000", "min_quantity": "0.01"}}]"#
+        )
+        .unwrap();
+
+        let registry = InstrumentRegistry::from_config_file(file.path()).unwrap();
+        let eurusd = registry.get("EURUSD").unwrap();
+        assert_eq!(eurusd.tick_size, dec!(0.00001));
+        assert_eq!(eurusd.notional(dec!(1.1), dec!(1)), dec!(110000));
+        assert_eq!(eurusd.min_quantity, Some(dec!(0.01)));
+        assert!(eurusd.lot_size.is_none());
+        assert!(!registry.is_open());
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Mutex;
 use rust_decimal::Decimal;
 use serde_json::Value::Bool;
@@ -8,7 +9,8 @@ use crate::models::{
 };
 
 // Use super:: to access parents then access siblings
-use super::fees::{calculate_maker_fee, calculate_taker_fee};
+use super::fees::{FeeEngine, Liquidity};
+use super::matching_policy::{Fifo, MatchingPolicy};
 
 // ============================================================================
 // Error Types
@@ -81,31 +83,36 @@ fn check_self_trade(incoming_order: &Order, resting_order: &Order) -> SelfTradeA
 // Shared Helper: Create Trade
 // ============================================================================
 
-/// Create a trade between two orders and update their fill quantities
+/// Create a trade between the incoming (taker) and resting (maker) order,
+/// charging each side what the fee engine says
 fn create_trade(
+    fees: &FeeEngine,
+    order_pair: &OrderPair,
     symbol: &str,
     price: Decimal,
     quantity: Decimal,
-    buy_order_id: Uuid,
-    sell_order_id: Uuid,
-    seller_user_id: String,
-    buyer_user_id: String,
 ) -> Trade {
-    let trade_value = price * quantity;
-    let maker_fee = calculate_maker_fee(trade_value);
-    let taker_fee = calculate_taker_fee(trade_value);
+    let (taker, maker) = (order_pair.incoming_order(), order_pair.resting_order());
+    let (buy, sell) = match taker.side {
+        OrderSide::Buy => (taker, maker),
+        OrderSide::Sell => (maker, taker),
+    };
 
-    let trade = Trade::new(
+    let mut trade = Trade::new(
         symbol.to_string(),
         price,
         quantity,
-        buy_order_id,
-        sell_order_id,
-        buyer_user_id,
-        seller_user_id,
-        maker_fee,
-        taker_fee,
+        buy.id,
+        sell.id,
+        buy.user_id.clone(),
+        sell.user_id.clone(),
+        Decimal::ZERO,
+        Decimal::ZERO,
     );
+    (trade.maker_fee, trade.maker_fee_currency) =
+        fees.fee(&maker.user_id, symbol, Liquidity::Maker, price, quantity, trade.timestamp);
+    (trade.taker_fee, trade.taker_fee_currency) =
+        fees.fee(&taker.user_id, symbol, Liquidity::Taker, price, quantity, trade.timestamp);
 
     trade
 }
@@ -127,18 +134,20 @@ enum ProcessResult {
 /// Process a single resting order against the incoming order
 /// Handles STP checks and trade execution
 fn process_resting_order(
+    fees: &FeeEngine,
     symbol: &str,
     price: Decimal,
+    allocation: Option<Decimal>,
     order_pair: &mut OrderPair,
     orders_to_remove: &mut Vec<Uuid>,
-    cancelled_orders: &mut Vec<Uuid>,
+    cancelled_orders: &mut Vec<Order>,
 ) -> ProcessResult {
     // Check self-trade prevention
     let stp_action = check_self_trade(order_pair.incoming_order(), order_pair.resting_order());
 
     match stp_action {
         SelfTradeAction::Allow => {
-            let trade = process_stp_allow_trade(order_pair, symbol, price);
+            let trade = process_stp_allow_trade(fees, order_pair, symbol, price, allocation);
 
             if order_pair.resting_order().is_filled() {
                 orders_to_remove.push(order_pair.resting_order().id);
@@ -152,7 +161,8 @@ fn process_resting_order(
         SelfTradeAction::DecrementBoth => {
             let qty = order_pair.incoming_order()
                 .remaining_quantity()
-                .min(order_pair.resting_order().remaining_quantity());
+                .min(order_pair.resting_order().remaining_quantity())
+                .min(allocation.unwrap_or(Decimal::MAX));
             order_pair.incoming_order_mut().fill(qty);
             order_pair.resting_order_mut().fill(qty);
 
@@ -165,13 +175,13 @@ fn process_resting_order(
         SelfTradeAction::CancelResting => {
             order_pair.resting_order_mut().status = OrderStatus::Cancelled;
             orders_to_remove.push(order_pair.resting_order().id);
-            cancelled_orders.push(order_pair.resting_order().id);
+            cancelled_orders.push(order_pair.resting_order().clone());
             ProcessResult::Skip
         }
 
         SelfTradeAction::CancelIncoming => {
             order_pair.incoming_order_mut().status = OrderStatus::Cancelled;
-            cancelled_orders.push(order_pair.incoming_order().id);
+            cancelled_orders.push(order_pair.incoming_order().clone());
             ProcessResult::CancelIncoming
         }
 
@@ -179,8 +189,8 @@ fn process_resting_order(
             order_pair.incoming_order_mut().status = OrderStatus::Cancelled;
             order_pair.resting_order_mut().status = OrderStatus::Cancelled;
             orders_to_remove.push(order_pair.resting_order().id);
-            cancelled_orders.push(order_pair.incoming_order().id);
-            cancelled_orders.push(order_pair.resting_order().id);
+            cancelled_orders.push(order_pair.incoming_order().clone());
+            cancelled_orders.push(order_pair.resting_order().clone());
             ProcessResult::CancelIncoming
         }
     }
@@ -188,21 +198,14 @@ fn process_resting_order(
 
 // Sequential borrows (one after another) is okay for mutable references
 // Simultaneous borrows (both alive at once)  like passing as parameters to the function is not allowed
-fn process_stp_allow_trade(order_pair: &mut OrderPair, symbol: &str, price: Decimal) -> Trade{
-    // Execute the trade
+fn process_stp_allow_trade(fees: &FeeEngine, order_pair: &mut OrderPair, symbol: &str, price: Decimal, allocation: Option<Decimal>) -> Trade{
+    // Execute the trade, capped by the matching policy's allocation for this resting order
     let quantity = order_pair.incoming_order_mut()
         .remaining_quantity()
-        .min(order_pair.resting_order_mut().remaining_quantity());
+        .min(order_pair.resting_order_mut().remaining_quantity())
+        .min(allocation.unwrap_or(Decimal::MAX));
 
-    // Create trade based on order sides
-    let trade = match order_pair.incoming_order().side {
-        OrderSide::Buy => {
-            create_trade(symbol, price, quantity, order_pair.incoming_order().id, order_pair.resting_order().id, order_pair.incoming_order().user_id.clone(), order_pair.resting_order().user_id.clone())
-        }
-        OrderSide::Sell => {
-            create_trade(symbol, price, quantity, order_pair.incoming_order().id, order_pair.resting_order().id, order_pair.incoming_order().user_id.clone(), order_pair.resting_order().user_id.clone())
-        }
-    };
+    let trade = create_trade(fees, order_pair, symbol, price, quantity);
 
     order_pair.incoming_order_mut().fill(quantity);
     order_pair.resting_order_mut().fill(quantity);
@@ -210,16 +213,70 @@ fn process_stp_allow_trade(order_pair: &mut OrderPair, symbol: &str, price: Deci
     trade
 }
 
+// ============================================================================
+// Shared Helper: Price Level Allocation
+// ============================================================================
+
+/// Ask the matching policy how much each resting order at a level may fill
+///
+/// Every resting order that takes quantity off the incoming order takes part
+/// in the allocation: orders of other users, and the incoming user's own
+/// orders under `DecrementBoth`. Own orders that self-trade prevention skips
+/// or cancels consume nothing and get no entry in the map.
+fn allocate_price_level(
+    order_book: &OrderBook,
+    incoming_order: &Order,
+    order_ids: &[Uuid],
+    policy: &dyn MatchingPolicy,
+) -> HashMap<Uuid, Decimal> {
+    let eligible: Vec<(Uuid, Decimal)> = order_ids
+        .iter()
+        .filter_map(|id| order_book.orders.get(id))
+        .filter(|resting| {
+            matches!(
+                check_self_trade(incoming_order, resting),
+                SelfTradeAction::Allow | SelfTradeAction::DecrementBoth
+            )
+        })
+        .map(|resting| (resting.id, resting.remaining_quantity()))
+        .collect();
+
+    let resting: Vec<Decimal> = eligible.iter().map(|(_, quantity)| *quantity).collect();
+    let allocations = policy.allocate(incoming_order.remaining_quantity(), &resting);
+
+    eligible
+        .into_iter()
+        .map(|(id, _)| id)
+        .zip(allocations)
+        .collect()
+}
+
 // ============================================================================
 // Main Entry Point
 // ============================================================================
 
-/// Match an incoming order against the order book
-/// Returns a vector of trades that were executed and IDs of cancelled orders
+/// Match an incoming order against the order book using price-time (FIFO) priority
+/// Returns a vector of trades that were executed and the orders self-trade
+/// prevention cancelled, as they were when cancelled
 pub fn match_order(
     orderbook: &mut OrderBook,
     incoming_order: &mut Order,
-) -> Result<(Vec<Trade>, Vec<Uuid>), MatchingError> {
+) -> Result<(Vec<Trade>, Vec<Order>), MatchingError> {
+    match_order_with_policy(orderbook, incoming_order, &Fifo, &FeeEngine::new())
+}
+
+/// Match an incoming order against the order book
+///
+/// `policy` decides how the incoming quantity is split across the resting
+/// orders within each price level; `fees` prices each side of every trade.
+/// Returns a vector of trades that were executed and the orders self-trade
+/// prevention cancelled, as they were when cancelled
+pub fn match_order_with_policy(
+    orderbook: &mut OrderBook,
+    incoming_order: &mut Order,
+    policy: &dyn MatchingPolicy,
+    fees: &FeeEngine,
+) -> Result<(Vec<Trade>, Vec<Order>), MatchingError> {
     // Validate order quantity
     if incoming_order.quantity <= Decimal::ZERO {
         return Err(MatchingError::InvalidQuantity);
@@ -239,8 +296,8 @@ pub fn match_order(
 
     // Match based on order side
     let (trades, cancelled_orders) = match incoming_order.side {
-        OrderSide::Buy => match_buy_order(orderbook, incoming_order)?,
-        OrderSide::Sell => match_sell_order(orderbook, incoming_order)?,
+        OrderSide::Buy => match_buy_order(orderbook, incoming_order, policy, fees)?,
+        OrderSide::Sell => match_sell_order(orderbook, incoming_order, policy, fees)?,
     };
 
     // Handle Fill-Or-Kill
@@ -280,7 +337,9 @@ fn would_match_immediately(orderbook: &OrderBook, order: &Order) -> bool {
 fn match_buy_order(
     orderbook: &mut OrderBook,
     buy_order: &mut Order,
-) -> Result<(Vec<Trade>, Vec<Uuid>), MatchingError> {
+    policy: &dyn MatchingPolicy,
+    fees: &FeeEngine,
+) -> Result<(Vec<Trade>, Vec<Order>), MatchingError> {
     let mut trades = Vec::new();
     let mut cancelled_orders = Vec::new();
     let mut empty_price_levels = Vec::new();
@@ -301,6 +360,8 @@ fn match_buy_order(
         let should_stop = match_at_price_level(
             orderbook, buy_order,
             ask_price,
+            policy,
+            fees,
             &mut trades,
             &mut cancelled_orders,
             &mut empty_price_levels,
@@ -319,12 +380,15 @@ fn match_buy_order(
 }
 
 // Match at a single ask price level (for buy orders)
+#[allow(clippy::too_many_arguments)]
 fn match_at_price_level(
     orderbook: &mut OrderBook,
     incoming_order: &mut Order,
     price: Decimal,
+    policy: &dyn MatchingPolicy,
+    fees: &FeeEngine,
     trades: &mut Vec<Trade>,
-    cancelled_orders: &mut Vec<Uuid>,
+    cancelled_orders: &mut Vec<Order>,
     empty_price_levels: &mut Vec<Decimal>,
 ) -> Result<bool, MatchingError> {
     // Get order IDs at this price level
@@ -339,6 +403,8 @@ fn match_at_price_level(
         orderbook,
         incoming_order,
         price,
+        policy,
+        fees,
         order_ids,
         &mut orders_to_remove,
         trades,
@@ -357,20 +423,30 @@ fn match_at_price_level(
 }
 
 // Process all resting orders at price level
+#[allow(clippy::too_many_arguments)]
 fn process_resting_orders(
     order_book: &mut OrderBook,
     incoming_order: &mut Order,
     price: Decimal,
+    policy: &dyn MatchingPolicy,
+    fees: &FeeEngine,
     order_ids: Vec<Uuid>,
     orders_to_remove: &mut Vec<Uuid>,
     trades: &mut Vec<Trade>,
-    cancelled_orders: &mut Vec<Uuid>,
+    cancelled_orders: &mut Vec<Order>,
 ) -> Result<bool, MatchingError> {
+    let allocations = allocate_price_level(order_book, incoming_order, &order_ids, policy);
+
     for resting_order_id in order_ids {
         if incoming_order.is_filled() {
             return Ok(false);
         }
 
+        let allocation = allocations.get(&resting_order_id).copied();
+        if allocation == Some(Decimal::ZERO) {
+            continue;
+        }
+
         let resting_order = order_book
             .orders
             .get_mut(&resting_order_id)
@@ -379,8 +455,10 @@ fn process_resting_orders(
         let mut order_pair = OrderPair::new(incoming_order, resting_order);
 
         let should_stop = process_single_resting_order(
+            fees,
             order_book.symbol.as_str(),
             price,
+            allocation,
             &mut order_pair,
             orders_to_remove,
             trades,
@@ -396,18 +474,23 @@ fn process_resting_orders(
 }
 
 // Process a single resting order (works for both buy and sell sides)
+#[allow(clippy::too_many_arguments)]
 fn process_single_resting_order(
+    fees: &FeeEngine,
     symbol: &str,
     price: Decimal,
+    allocation: Option<Decimal>,
     order_pair: &mut OrderPair,
     orders_to_remove: &mut Vec<Uuid>,
     trades: &mut Vec<Trade>,
-    cancelled_orders: &mut Vec<Uuid>,
+    cancelled_orders: &mut Vec<Order>,
 ) -> Result<bool, MatchingError> {
 
     match process_resting_order(
+        fees,
         symbol,
         price,
+        allocation,
         order_pair,
         orders_to_remove,
         cancelled_orders,
@@ -428,16 +511,17 @@ fn cleanup_and_mark_empty(
     orders_to_remove: Vec<Uuid>,
     empty_price_levels: &mut Vec<Decimal>,
 ) {
+    // Remove from main HashMap
+    let removed_quantity: Decimal = orders_to_remove
+        .iter()
+        .filter_map(|order_id| orderbook.remove_order(*order_id))
+        .map(|order| order.quantity)
+        .sum();
+
     if let Some(price_level) = orderbook.asks.get_mut(&price) {
-        // Remove orders from price level
+        // Remove orders from price level and update quantity
         price_level.orders.retain(|id| !orders_to_remove.contains(id));
-
-        // Update quantity and remove from main HashMap
-        for order_id in orders_to_remove {
-            if let Some(order) = orderbook.orders.remove(&order_id) {
-                price_level.total_quantity -= order.quantity;
-            }
-        }
+        price_level.total_quantity -= removed_quantity;
 
         if price_level.is_empty() {
             empty_price_levels.push(price);
@@ -453,7 +537,9 @@ fn cleanup_and_mark_empty(
 fn match_sell_order(
     orderbook: &mut OrderBook,
     sell_order: &mut Order,
-) -> Result<(Vec<Trade>, Vec<Uuid>), MatchingError> {
+    policy: &dyn MatchingPolicy,
+    fees: &FeeEngine,
+) -> Result<(Vec<Trade>, Vec<Order>), MatchingError> {
     let mut trades = Vec::new();
     let mut cancelled_orders = Vec::new();
     let mut empty_price_levels = Vec::new();
@@ -475,6 +561,8 @@ fn match_sell_order(
             orderbook,
             sell_order,
             bid_price,
+            policy,
+            fees,
             &mut trades,
             &mut cancelled_orders,
             &mut empty_price_levels,
@@ -493,12 +581,15 @@ fn match_sell_order(
 }
 
 // Match at a single bid price level (for sell orders)
+#[allow(clippy::too_many_arguments)]
 fn match_at_price_level_sell(
     orderbook: &mut OrderBook,
     incoming_order: &mut Order,
     price: Decimal,
+    policy: &dyn MatchingPolicy,
+    fees: &FeeEngine,
     trades: &mut Vec<Trade>,
-    cancelled_orders: &mut Vec<Uuid>,
+    cancelled_orders: &mut Vec<Order>,
     empty_price_levels: &mut Vec<Decimal>,
 ) -> Result<bool, MatchingError> {
     // Get order IDs at this price level
@@ -513,6 +604,8 @@ fn match_at_price_level_sell(
         orderbook,
         incoming_order,
         price,
+        policy,
+        fees,
         order_ids,
         &mut orders_to_remove,
         trades,
@@ -531,20 +624,30 @@ fn match_at_price_level_sell(
 }
 
 // Process all resting buy orders at a bid price level (for sell orders)
+#[allow(clippy::too_many_arguments)]
 fn process_resting_orders_sell(
     order_book: &mut OrderBook,
     incoming_order: &mut Order,
     price: Decimal,
+    policy: &dyn MatchingPolicy,
+    fees: &FeeEngine,
     order_ids: Vec<Uuid>,
     orders_to_remove: &mut Vec<Uuid>,
     trades: &mut Vec<Trade>,
-    cancelled_orders: &mut Vec<Uuid>,
+    cancelled_orders: &mut Vec<Order>,
 ) -> Result<bool, MatchingError> {
+    let allocations = allocate_price_level(order_book, incoming_order, &order_ids, policy);
+
     for resting_order_id in order_ids {
         if incoming_order.is_filled() {
             return Ok(false);
         }
 
+        let allocation = allocations.get(&resting_order_id).copied();
+        if allocation == Some(Decimal::ZERO) {
+            continue;
+        }
+
         let resting_order = order_book
             .orders
             .get_mut(&resting_order_id)
@@ -553,8 +656,10 @@ fn process_resting_orders_sell(
         let mut order_pair = OrderPair::new(incoming_order, resting_order);
 
         let should_stop = process_single_resting_order(
+            fees,
             order_book.symbol.as_str(),
             price,
+            allocation,
             &mut order_pair,
             orders_to_remove,
             trades,
@@ -576,16 +681,17 @@ fn cleanup_and_mark_empty_sell(
     orders_to_remove: Vec<Uuid>,
     empty_price_levels: &mut Vec<Decimal>,
 ) {
+    // Remove from main HashMap
+    let removed_quantity: Decimal = orders_to_remove
+        .iter()
+        .filter_map(|order_id| orderbook.remove_order(*order_id))
+        .map(|order| order.quantity)
+        .sum();
+
     if let Some(price_level) = orderbook.bids.get_mut(&price) {
-        // Remove orders from price level
+        // Remove orders from price level and update quantity
         price_level.orders.retain(|id| !orders_to_remove.contains(id));
-
-        // Update quantity and remove from main HashMap
-        for order_id in orders_to_remove {
-            if let Some(order) = orderbook.orders.remove(&order_id) {
-                price_level.total_quantity -= order.quantity;
-            }
-        }
+        price_level.total_quantity -= removed_quantity;
 
         if price_level.is_empty() {
             empty_price_levels.push(price);
@@ -625,7 +731,7 @@ mod tests {
         );
         let order_id = order.id;
 
-        orderbook.orders.insert(order.id, order.clone());
+        orderbook.insert_order(order.clone());
         let mut level = PriceLevel::new(price);
         level.add_order(order.id, quantity);
 
@@ -712,7 +818,7 @@ mod tests {
 
     #[test]
     fn test_sell_order_matching() {
-        let (mut orderbook, _) = setup_orderbook_with_bid(dec!(150.00), dec!(100));
+        let (mut orderbook, bid_id) = setup_orderbook_with_bid(dec!(150.00), dec!(100));
 
         let mut sell_order = Order::new(
             "AAPL".to_string(),
@@ -728,6 +834,10 @@ mod tests {
         assert_eq!(trades.len(), 1);
         assert_eq!(trades[0].quantity, dec!(50));
         assert!(sell_order.is_filled());
+
+        // The resting bid is the buying side of the trade
+        assert_eq!((trades[0].buyer_order_id, trades[0].seller_order_id), (bid_id, sell_order.id));
+        assert_eq!((trades[0].buyer_id.as_str(), trades[0].seller_id.as_str()), ("buyer1", "seller1"));
     }
 
     #[test]
@@ -766,4 +876,115 @@ mod tests {
         assert!(trades.is_empty());
         assert!(!buy_order.is_filled());
     }
+
+    /// Setup an orderbook with several asks at one price level, in time priority
+    fn setup_orderbook_with_ask_queue(price: Decimal, quantities: &[(Decimal, &str)]) -> (OrderBook, Vec<Uuid>) {
+        let mut orderbook = OrderBook::new("AAPL".to_string());
+        let mut level = PriceLevel::new(price);
+        let mut ids = Vec::new();
+
+        for (quantity, user_id) in quantities {
+            let order = Order::new(
+                "AAPL".to_string(),
+                OrderSide::Sell,
+                OrderType::Limit,
+                Some(price),
+                *quantity,
+                user_id.to_string(),
+            );
+            level.add_order(order.id, *quantity);
+            ids.push(order.id);
+            orderbook.insert_order(order);
+        }
+        orderbook.asks.insert(price, level);
+
+        (orderbook, ids)
+    }
+
+    #[test]
+    fn test_pro_rata_allocates_across_level() {
+        use crate::engine::matching_policy::ProRata;
+
+        let (mut orderbook, ids) = setup_orderbook_with_ask_queue(
+            dec!(100),
+            &[(dec!(10), "seller1"), (dec!(10), "seller2"), (dec!(10), "seller3")],
+        );
+        let policy = ProRata { min_allocation: dec!(1), lot_size: dec!(1) };
+
+        let mut buy_order = Order::new(
+            "AAPL".to_string(),
+            OrderSide::Buy,
+            OrderType::Limit,
+            Some(dec!(100)),
+            dec!(10),
+            "buyer1".to_string(),
+        );
+
+        let (trades, _) = match_order_with_policy(&mut orderbook, &mut buy_order, &policy, &FeeEngine::new()).unwrap();
+
+        // 3.33 each rounds down to 3, the leftover lot goes to the oldest order
+        let quantities: Vec<Decimal> = trades.iter().map(|t| t.quantity).collect();
+        assert_eq!(quantities, vec![dec!(4), dec!(3), dec!(3)]);
+        assert!(buy_order.is_filled());
+        for (id, remaining) in ids.iter().zip([dec!(6), dec!(7), dec!(7)]) {
+            assert_eq!(orderbook.orders[id].remaining_quantity(), remaining);
+        }
+    }
+
+    #[test]
+    fn test_pro_rata_excludes_own_orders_from_allocation() {
+        use crate::engine::matching_policy::ProRata;
+
+        // The middle order belongs to the buyer and is skipped by STP (mode None)
+        let (mut orderbook, ids) = setup_orderbook_with_ask_queue(
+            dec!(100),
+            &[(dec!(10), "seller1"), (dec!(10), "buyer1"), (dec!(10), "seller2")],
+        );
+        let policy = ProRata { min_allocation: dec!(1), lot_size: dec!(1) };
+
+        let mut buy_order = Order::new(
+            "AAPL".to_string(),
+            OrderSide::Buy,
+            OrderType::Limit,
+            Some(dec!(100)),
+            dec!(10),
+            "buyer1".to_string(),
+        );
+
+        let (trades, _) = match_order_with_policy(&mut orderbook, &mut buy_order, &policy, &FeeEngine::new()).unwrap();
+
+        let quantities: Vec<Decimal> = trades.iter().map(|t| t.quantity).collect();
+        assert_eq!(quantities, vec![dec!(5), dec!(5)]);
+        assert_eq!(orderbook.orders[&ids[1]].remaining_quantity(), dec!(10));
+    }
+
+    #[test]
+    fn test_pro_rata_caps_decrement_of_own_orders() {
+        use crate::engine::matching_policy::ProRata;
+
+        // The middle order belongs to the buyer, whose STP mode decrements both
+        let (mut orderbook, ids) = setup_orderbook_with_ask_queue(
+            dec!(100),
+            &[(dec!(10), "seller1"), (dec!(10), "buyer1"), (dec!(10), "seller2")],
+        );
+        let policy = ProRata { min_allocation: dec!(1), lot_size: dec!(1) };
+
+        let mut buy_order = Order::new(
+            "AAPL".to_string(),
+            OrderSide::Buy,
+            OrderType::Limit,
+            Some(dec!(100)),
+            dec!(9),
+            "buyer1".to_string(),
+        );
+        buy_order.stp_mode = SelfTradePreventionMode::DecrementBoth;
+
+        let (trades, _) = match_order_with_policy(&mut orderbook, &mut buy_order, &policy, &FeeEngine::new()).unwrap();
+
+        // The own order gets its pro-rata share like the others
+        let quantities: Vec<Decimal> = trades.iter().map(|t| t.quantity).collect();
+        assert_eq!(quantities, vec![dec!(3), dec!(3)]);
+        assert!(buy_order.is_filled());
+        assert_eq!(orderbook.orders[&ids[1]].remaining_quantity(), dec!(7));
+    }
 }
@@ -0,0 +1,282 @@
+//! Matching policies
+//!
+//! A matching policy decides how an incoming order's quantity is split across
+//! the resting orders at a single price level. Price priority is always
+//! respected; policies only differ within a level.
+//!
+//! # Rounding
+//! Pro-rata shares are rounded *down* to the policy's `lot_size`. Shares below
+//! `min_allocation` are dropped. Whatever is left after the pro-rata pass is
+//! handed out in time priority (FIFO), so a level always allocates exactly
+//! `min(incoming, level quantity)` and the result is fully deterministic.
+
+use std::fmt::Debug;
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Allocation strategy for the resting orders at one price level
+pub trait MatchingPolicy: Debug + Send + Sync {
+    /// Short identifier of the policy
+    fn name(&self) -> &'static str;
+
+    /// Split `quantity` across resting orders
+    ///
+    /// `resting` holds the remaining quantity of each eligible resting order in
+    /// time priority. The result has one entry per resting order; entries never
+    /// exceed the order's remaining quantity and sum to `min(quantity, total)`.
+    fn allocate(&self, quantity: Decimal, resting: &[Decimal]) -> Vec<Decimal>;
+}
+
+/// Price-time priority: the oldest order at the level fills first
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fifo;
+
+/// Pure pro-rata: allocation proportional to resting size
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ProRata {
+    /// Smallest pro-rata share an order can receive (smaller shares are dropped)
+    #[serde(with = "crate::models::decimal")]
+    #[schema(value_type = String, example = "1")]
+    pub min_allocation: Decimal,
+    /// Increment pro-rata shares are rounded down to
+    #[serde(with = "crate::models::decimal")]
+    #[schema(value_type = String, example = "1")]
+    pub lot_size: Decimal,
+}
+
+/// Hybrid: the top (oldest) order at the level fills first, the rest is pro-rata
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FifoTopProRata {
+    /// Smallest pro-rata share an order can receive (smaller shares are dropped)
+    #[serde(with = "crate::models::decimal")]
+    #[schema(value_type = String, example = "1")]
+    pub min_allocation: Decimal,
+    /// Increment pro-rata shares are rounded down to
+    #[serde(with = "crate::models::decimal")]
+    #[schema(value_type = String, example = "1")]
+    pub lot_size: Decimal,
+}
+
+/// Matching policy of a symbol, as configured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchingPolicyConfig {
+    #[default]
+    Fifo,
+    ProRata(ProRata),
+    FifoTopProRata(FifoTopProRata),
+}
+
+impl MatchingPolicyConfig {
+    /// Check that pro-rata parameters are not negative
+    pub fn validate(&self) -> Result<(), String> {
+        let (min_allocation, lot_size) = match self {
+            MatchingPolicyConfig::Fifo => return Ok(()),
+            MatchingPolicyConfig::ProRata(policy) => (policy.min_allocation, policy.lot_size),
+            MatchingPolicyConfig::FifoTopProRata(policy) => (policy.min_allocation, policy.lot_size),
+        };
+        if min_allocation < Decimal::ZERO {
+            return Err("min_allocation cannot be negative".to_string());
+        }
+        if lot_size < Decimal::ZERO {
+            return Err("lot_size cannot be negative".to_string());
+        }
+        Ok(())
+    }
+
+    fn policy(&self) -> &dyn MatchingPolicy {
+        match self {
+            MatchingPolicyConfig::Fifo => &Fifo,
+            MatchingPolicyConfig::ProRata(policy) => policy,
+            MatchingPolicyConfig::FifoTopProRata(policy) => policy,
+        }
+    }
+}
+
+impl MatchingPolicy for MatchingPolicyConfig {
+    fn name(&self) -> &'static str {
+        self.policy().name()
+    }
+
+    fn allocate(&self, quantity: Decimal, resting: &[Decimal]) -> Vec<Decimal> {
+        self.policy().allocate(quantity, resting)
+    }
+}
+
+impl MatchingPolicy for Fifo {
+    fn name(&self) -> &'static str {
+        "fifo"
+    }
+
+    fn allocate(&self, quantity: Decimal, resting: &[Decimal]) -> Vec<Decimal> {
+        let mut allocations = vec![Decimal::ZERO; resting.len()];
+        allocate_fifo_remainder(&mut allocations, resting, quantity);
+        allocations
+    }
+}
+
+impl MatchingPolicy for ProRata {
+    fn name(&self) -> &'static str {
+        "pro_rata"
+    }
+
+    fn allocate(&self, quantity: Decimal, resting: &[Decimal]) -> Vec<Decimal> {
+        pro_rata(quantity, resting, self.min_allocation, self.lot_size)
+    }
+}
+
+impl MatchingPolicy for FifoTopProRata {
+    fn name(&self) -> &'static str {
+        "fifo_top_pro_rata"
+    }
+
+    fn allocate(&self, quantity: Decimal, resting: &[Decimal]) -> Vec<Decimal> {
+        let Some((&top, rest)) = resting.split_first() else {
+            return Vec::new();
+        };
+
+        let top_fill = quantity.min(top).max(Decimal::ZERO);
+        let mut allocations = vec![top_fill];
+        allocations.extend(pro_rata(quantity - top_fill, rest, self.min_allocation, self.lot_size));
+        allocations
+    }
+}
+
+/// Round `quantity` down to a multiple of `lot_size` (no rounding if `lot_size` is not positive)
+fn round_down_to_lot(quantity: Decimal, lot_size: Decimal) -> Decimal {
+    if lot_size <= Decimal::ZERO {
+        return quantity;
+    }
+    (quantity / lot_size).floor() * lot_size
+}
+
+/// Pro-rata pass followed by a FIFO pass for the remainder
+fn pro_rata(quantity: Decimal, resting: &[Decimal], min_allocation: Decimal, lot_size: Decimal) -> Vec<Decimal> {
+    let mut allocations = vec![Decimal::ZERO; resting.len()];
+    let total: Decimal = resting.iter().sum();
+    if total <= Decimal::ZERO || quantity <= Decimal::ZERO {
+        return allocations;
+    }
+
+    let quantity = quantity.min(total);
+    for (allocation, &remaining) in allocations.iter_mut().zip(resting) {
+        let share = round_down_to_lot(quantity * remaining / total, lot_size).min(remaining);
+        if share >= min_allocation {
+            *allocation = share;
+        }
+    }
+
+    allocate_fifo_remainder(&mut allocations, resting, quantity);
+    allocations
+}
+
+/// Hand out whatever part of `quantity` is not yet allocated in time priority
+fn allocate_fifo_remainder(allocations: &mut [Decimal], resting: &[Decimal], quantity: Decimal) {
+    let allocated: Decimal = allocations.iter().sum();
+    let mut left = quantity - allocated;
+
+    for (allocation, &remaining) in allocations.iter_mut().zip(resting) {
+        if left <= Decimal::ZERO {
+            break;
+        }
+        let take = (remaining - *allocation).min(left);
+        *allocation += take;
+        left -= take;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_fifo_fills_in_time_priority() {
+        let allocations = Fifo.allocate(dec!(15), &[dec!(10), dec!(10), dec!(10)]);
+        assert_eq!(allocations, vec![dec!(10), dec!(5), dec!(0)]);
+    }
+
+    #[test]
+    fn test_pro_rata_remainder_goes_fifo() {
+        let policy = ProRata { min_allocation: dec!(1), lot_size: dec!(1) };
+
+        // 10 split over 30/30/40 -> 3/3/4 exactly
+        assert_eq!(
+            policy.allocate(dec!(10), &[dec!(30), dec!(30), dec!(40)]),
+            vec![dec!(3), dec!(3), dec!(4)]
+        );
+
+        // 10 split over 1/1/1 -> 3.33 rounds down to 3 each, leftover 1 to the oldest
+        assert_eq!(
+            policy.allocate(dec!(10), &[dec!(10), dec!(10), dec!(10)]),
+            vec![dec!(4), dec!(3), dec!(3)]
+        );
+    }
+
+    #[test]
+    fn test_pro_rata_min_allocation_drops_small_shares() {
+        let policy = ProRata { min_allocation: dec!(2), lot_size: dec!(1) };
+
+        // Shares 9.09 / 0.90: the small order's share is dropped, the
+        // freed unit goes FIFO to the oldest order with room
+        assert_eq!(
+            policy.allocate(dec!(10), &[dec!(100), dec!(10)]),
+            vec![dec!(10), dec!(0)]
+        );
+
+        // When the small order is first in time it still gets the remainder
+        assert_eq!(
+            policy.allocate(dec!(10), &[dec!(10), dec!(100)]),
+            vec![dec!(1), dec!(9)]
+        );
+    }
+
+    #[test]
+    fn test_pro_rata_never_over_allocates() {
+        let policy = ProRata { min_allocation: dec!(0), lot_size: dec!(1) };
+
+        // Incoming larger than the level: everyone fills completely
+        assert_eq!(policy.allocate(dec!(100), &[dec!(5), dec!(7)]), vec![dec!(5), dec!(7)]);
+
+        // Fractional remainder smaller than a lot is still allocated FIFO
+        let allocations = policy.allocate(dec!(2.5), &[dec!(10), dec!(10)]);
+        assert_eq!(allocations, vec![dec!(1.5), dec!(1)]);
+        assert_eq!(allocations.iter().sum::<Decimal>(), dec!(2.5));
+    }
+
+    #[test]
+    fn test_fifo_top_then_pro_rata() {
+        let policy = FifoTopProRata { min_allocation: dec!(1), lot_size: dec!(1) };
+
+        // Top order takes 5, remaining 15 split 10/20 -> 5/10
+        assert_eq!(
+            policy.allocate(dec!(20), &[dec!(5), dec!(10), dec!(20)]),
+            vec![dec!(5), dec!(5), dec!(10)]
+        );
+
+        // Top order absorbs everything when it is large enough
+        assert_eq!(
+            policy.allocate(dec!(3), &[dec!(5), dec!(10)]),
+            vec![dec!(3), dec!(0)]
+        );
+
+        assert!(policy.allocate(dec!(3), &[]).is_empty());
+    }
+
+    #[test]
+    fn test_config_validation_and_json() {
+        let config = MatchingPolicyConfig::ProRata(ProRata { min_allocation: dec!(1), lot_size: dec!(1) });
+        assert!(config.validate().is_ok());
+        assert_eq!(config.name(), "pro_rata");
+        assert_eq!(
+            serde_json::to_value(config).unwrap(),
+            serde_json::json!({ "pro_rata": { "min_allocation": "1", "lot_size": "1" } })
+        );
+        assert_eq!(serde_json::from_value::<MatchingPolicyConfig>(serde_json::json!("fifo")).unwrap(), MatchingPolicyConfig::Fifo);
+
+        let negative = MatchingPolicyConfig::FifoTopProRata(FifoTopProRata { min_allocation: dec!(1), lot_size: dec!(-1) });
+        assert_eq!(negative.validate().unwrap_err(), "lot_size cannot be negative");
+    }
+}
@@ -3,21 +3,54 @@
 //! This module contains the core order book functionality:
 //! - `errors` - Error types for order book operations
 //! - `validation` - Order validation functions
-//! - `fees` - Fee calculation utilities
+//! - `instruments` - Instrument reference data registry
+//! - `fees` - Fee schedules with volume tiers and maker rebates
 //! - `matching` - Order matching engine
-//! - `orderbook` - Main order book engine
+//! - `matching_policy` - Per-symbol allocation within a price level (FIFO, pro-rata)
+//! - `orderbook` - Main order book engine (one lock per symbol)
+//! - `trigger` - Stop order trigger engine
+//! - `contingent` - OCO / OTO / bracket order groups
+//! - `pegging` - Pegged order pricing
+//! - `auction` - Call auction uncross price and execution
+//! - `expiry` - Background expiry of DAY / GTD orders
+//! - `reopening` - Timed reopening auctions after circuit breaker halts
+//! - `checkpoint` - Periodic snapshots and WAL truncation
+//! - `notices` - Results of what the engine does on its own, for the ingestion pipeline
+//! - `accounts` - Account balances, order reservations and trade settlement
 
+pub mod accounts;
 pub mod errors;
 pub mod fees;
 pub mod matching;
+pub mod matching_policy;
 pub mod orderbook;
 pub mod validation;
+pub mod instruments;
 pub mod trigger;
+pub mod contingent;
+pub mod pegging;
+pub mod auction;
+pub mod expiry;
+pub mod reopening;
+pub mod checkpoint;
+pub mod notices;
 
 // Re-export commonly used types for convenience
+pub use accounts::{Accounts, Balance};
 pub use errors::OrderBookError;
-pub use fees::{calculate_exchange_profit, calculate_maker_fee, calculate_taker_fee};
-pub use matching::{match_order, MatchingError};
+pub use fees::{
+    calculate_exchange_profit, calculate_maker_fee, calculate_taker_fee, FeeEngine, FeeRates, FeeSchedule, FeeScheduleScope,
+    FeeSchedules, FeeTier,
+};
+pub use matching::{match_order, match_order_with_policy, MatchingError};
+pub use matching_policy::{Fifo, FifoTopProRata, MatchingPolicy, MatchingPolicyConfig, ProRata};
 pub use orderbook::OrderBookEngine;
-pub use validation::validate_order;
-pub use trigger::TriggerEngine;
+pub use validation::{validate_amendment, validate_instrument, validate_order};
+pub use instruments::InstrumentRegistry;
+pub use trigger::{TriggerEngine, TriggeredStop};
+pub use contingent::{ContingentAction, ContingentOrderManager};
+pub use auction::{compute_uncross, execute_uncross};
+pub use expiry::run_expiry_sweeper;
+pub use reopening::run_reopening_timer;
+pub use checkpoint::run_checkpointer;
+pub use notices::{CancelReason, EngineNotice};
@@ -0,0 +1,116 @@
+//! What the engine does on its own, for the ingestion pipeline
+//!
+//! A command's caller gets the order and trades the command produced. The
+//! engine does more than that by itself: triggered stops and released
+//! contingent legs are submitted, repriced pegs trade, self-trade prevention
+//! and contingent siblings cancel resting orders, auctions uncross and
+//! resting orders expire. Those results are recorded here, per symbol and in
+//! the order they happened, until the symbol's matcher publishes them.
+//!
+//! Nothing is recorded until a pipeline turns the notices on, nor while the
+//! log is re-applied.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use dashmap::DashMap;
+
+use crate::models::{Order, OrderSide, Trade};
+
+/// Why an order left the book without filling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelReason {
+    /// Its owner cancelled it
+    Requested,
+    /// Self-trade prevention cancelled the resting order
+    SelfTrade,
+    /// Its contingent group (OCO / bracket sibling) cancelled it
+    Contingent,
+    /// A market order the auction uncross did not fill
+    AuctionUnfilled,
+    /// Its DAY / GTD expire time passed
+    Expired,
+}
+
+impl CancelReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CancelReason::Requested => "cancelled",
+            CancelReason::SelfTrade => "self_trade",
+            CancelReason::Contingent => "contingent",
+            CancelReason::AuctionUnfilled => "auction_unfilled",
+            CancelReason::Expired => "expired",
+        }
+    }
+}
+
+/// One result of something the engine did on its own
+#[derive(Debug, Clone)]
+pub enum EngineNotice {
+    /// The engine submitted an order (a triggered stop or a contingent leg);
+    /// `order` is as it came out of matching, its trades follow
+    OrderAccepted(Order),
+    /// A resting order left the book
+    OrderCancelled { order: Order, reason: CancelReason },
+    /// A trade; `taker_side` is `None` for an auction uncross
+    TradeExecuted { taker_side: Option<OrderSide>, trade: Trade },
+}
+
+/// Per-symbol queues of engine notices
+#[derive(Default)]
+pub struct EngineNotices {
+    enabled: AtomicBool,
+    /// Notices recorded and not yet taken, over all symbols
+    pending: AtomicUsize,
+    queues: DashMap<String, Vec<EngineNotice>>,
+}
+
+impl EngineNotices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start or stop recording; stopping drops what was not taken
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Release);
+        if !enabled {
+            self.queues.clear();
+            self.pending.store(0, Ordering::Release);
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    pub fn record(&self, symbol: &str, notice: EngineNotice) {
+        if !self.is_enabled() {
+            return;
+        }
+        // Counted while the queue is held, so `take` never sees it uncounted
+        let mut queue = self.queues.entry(symbol.to_string()).or_default();
+        queue.push(notice);
+        self.pending.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Take the notices of a symbol, oldest first
+    pub fn take(&self, symbol: &str) -> Vec<EngineNotice> {
+        let Some((_, notices)) = self.queues.remove(symbol) else {
+            return Vec::new();
+        };
+        // Saturating: turning the notices off may have reset the count
+        let _ = self
+            .pending
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |pending| Some(pending.saturating_sub(notices.len())));
+        notices
+    }
+
+    /// Check if any symbol has notices waiting
+    pub fn has_pending(&self) -> bool {
+        self.pending.load(Ordering::Acquire) > 0
+    }
+
+    /// Symbols with notices waiting
+    pub fn pending_symbols(&self) -> Vec<String> {
+        self.queues.iter().map(|entry| entry.key().clone()).collect()
+    }
+}
@@ -4,17 +4,40 @@
 //! multiple order books (one per trading symbol) and handles order
 //! submission, cancellation, and matching.
 
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tokio::sync::mpsc::UnboundedReceiver;
 use uuid::Uuid;
 
-use crate::models::{Order, OrderBook, OrderSide, OrderStatus, OrderType, PriceLevel, Trade, StopOrder};
+use crate::models::{
+    AuctionKind, AuctionResult, AuctionState, ContingentGroup, Instrument, LegOrder, Order, OrderBook, OrderSide, OrderStatus,
+    PriceLevel, StopOrder, TimeInForce, Trade, TradingCalendar,
+};
+use crate::persistence::{
+    AlgorithmSnapshot, BookSnapshot, EngineSnapshot, LastTradePrice, SnapshotStore, WalEvent, WriteAheadLog,
+};
+use crate::risk::{
+    AccountRiskStatus, CircuitBreaker, CircuitBreakerConfig, CircuitBreakerStatus, CircuitState, RiskEngine, RiskError, RiskLimits,
+};
+use crate::utils::clock;
 
+use super::accounts::{required_funds, Accounts, Balance};
+use super::auction::{compute_uncross, execute_uncross};
+use super::contingent::{ContingentAction, ContingentOrderManager};
 use super::errors::OrderBookError;
-use super::matching::match_order;
+use super::fees::{FeeEngine, FeeRates, FeeSchedule, FeeScheduleScope, FeeSchedules};
+use super::instruments::InstrumentRegistry;
+use super::matching::match_order_with_policy;
+use super::matching_policy::{MatchingPolicy, MatchingPolicyConfig};
+use super::notices::{CancelReason, EngineNotice, EngineNotices};
+use super::pegging::{peg_price, reference_prices};
 use super::trigger::TriggerEngine;
-use super::validation::validate_order;
+use super::validation::{validate_amendment, validate_instrument, validate_order};
 
 // ============================================================================
 // Order Book Helper Functions
@@ -58,10 +81,99 @@ fn add_order_to_price_level(
     level.add_order(order_id, quantity);
 }
 
+/// Snapshots kept on disk; WAL segments are deleted once all of them cover them
+const SNAPSHOTS_TO_KEEP: usize = 2;
+
+/// Current wall-clock time in nanoseconds, as recorded in WAL events
+fn now_ns() -> u64 {
+    clock::now().timestamp_nanos_opt().unwrap_or(0) as u64
+}
+
+thread_local! {
+    /// Set while this thread re-applies logged events (recovery or replication)
+    static APPLYING_LOG: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` as a re-application of logged events: nothing it does is journaled
+/// again, and a standby engine accepts it
+fn applying_log<T>(f: impl FnOnce() -> T) -> T {
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            APPLYING_LOG.with(|applying| applying.set(false));
+        }
+    }
+
+    APPLYING_LOG.with(|applying| applying.set(true));
+    let _reset = Reset;
+    f()
+}
+
+fn is_applying_log() -> bool {
+    APPLYING_LOG.with(Cell::get)
+}
+
+thread_local! {
+    /// Set while this thread carries out something the engine started itself
+    /// (a triggered stop, a contingent leg placement or cancel)
+    static ENGINE_INITIATED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` as an operation the engine started on its own: its results are
+/// not returned to any caller, so they are recorded as engine notices
+fn engine_initiated<T>(f: impl FnOnce() -> T) -> T {
+    struct Restore(bool);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            ENGINE_INITIATED.with(|initiated| initiated.set(self.0));
+        }
+    }
+
+    let _restore = Restore(ENGINE_INITIATED.with(|initiated| initiated.replace(true)));
+    f()
+}
+
+fn is_engine_initiated() -> bool {
+    ENGINE_INITIATED.with(Cell::get)
+}
+
 /// Thread-safe order book engine
+///
+/// Every symbol's book sits behind its own lock and is mutated in place, so
+/// orders on different symbols never contend. The map lock is only taken for
+/// writing when a symbol gets its first book.
 pub struct OrderBookEngine {
-    books: Arc<RwLock<HashMap<String, OrderBook>>>,
+    books: Arc<RwLock<HashMap<String, Arc<Mutex<OrderBook>>>>>,
     trigger_engine: Arc<RwLock<TriggerEngine>>,
+    contingent: Arc<RwLock<ContingentOrderManager>>,
+    /// Per-symbol matching policies (symbols without an entry match FIFO)
+    matching_policies: Arc<RwLock<HashMap<String, MatchingPolicyConfig>>>,
+    /// Fee schedules and the traded volume their tiers are based on
+    fees: Arc<RwLock<FeeEngine>>,
+    /// Per-account pre-trade risk limits and what they are checked against
+    risk: Mutex<RiskEngine>,
+    /// Per-symbol circuit breakers and price bands, created on first use
+    circuit_breakers: Mutex<HashMap<String, CircuitBreaker>>,
+    /// Symbols currently in a call auction (all others trade continuously)
+    auctions: Arc<RwLock<HashMap<String, AuctionState>>>,
+    /// Per-symbol trading session calendars (symbols without an entry use the default calendar)
+    calendars: Arc<RwLock<HashMap<String, TradingCalendar>>>,
+    /// Instrument reference data; decides which symbols get a book
+    instruments: Arc<RwLock<InstrumentRegistry>>,
+    /// Optional write-ahead log that engine events are journaled to
+    wal: Option<Arc<Mutex<WriteAheadLog>>>,
+    /// Set on a replication follower: state only changes through `apply_replicated`
+    standby: AtomicBool,
+    /// Last replicated sequence number of a follower without a write-ahead log
+    replicated_sequence: AtomicU64,
+    /// Optional snapshot directory for checkpoints
+    snapshots: Option<SnapshotStore>,
+    /// Algorithm state from the snapshot recovery loaded, until the algorithm manager takes it
+    recovered_algorithms: Mutex<Option<AlgorithmSnapshot>>,
+    /// Optional account balances; orders must be funded when present
+    accounts: Option<Mutex<Accounts>>,
+    /// Results of what the engine does on its own, for the ingestion pipeline
+    notices: EngineNotices,
 }
 
 impl OrderBookEngine {
@@ -70,319 +182,3499 @@ impl OrderBookEngine {
         Self {
             books: Arc::new(RwLock::new(HashMap::new())),
             trigger_engine: Arc::new(RwLock::new(TriggerEngine::new())),
+            contingent: Arc::new(RwLock::new(ContingentOrderManager::new())),
+            matching_policies: Arc::new(RwLock::new(HashMap::new())),
+            fees: Arc::new(RwLock::new(FeeEngine::new())),
+            risk: Mutex::new(RiskEngine::new()),
+            circuit_breakers: Mutex::new(HashMap::new()),
+            auctions: Arc::new(RwLock::new(HashMap::new())),
+            calendars: Arc::new(RwLock::new(HashMap::new())),
+            instruments: Arc::new(RwLock::new(InstrumentRegistry::open())),
+            wal: None,
+            standby: AtomicBool::new(false),
+            replicated_sequence: AtomicU64::new(0),
+            snapshots: None,
+            recovered_algorithms: Mutex::new(None),
+            accounts: None,
+            notices: EngineNotices::new(),
         }
     }
 
-    /// Get or create an order book for a symbol
-    fn get_or_create_book(&self, symbol: &str) -> Result<OrderBook, OrderBookError> {
-        let mut books = self.books.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
-        Ok(books
-            .entry(symbol.to_string())
-            .or_insert_with(|| OrderBook::new(symbol.to_string()))
-            .clone())
+    /// Create a new order book engine that journals events to a write-ahead log
+    pub fn with_wal(wal: Arc<Mutex<WriteAheadLog>>) -> Self {
+        Self {
+            wal: Some(wal),
+            ..Self::new()
+        }
     }
 
-    /// Update an order book
-    fn update_book(&self, book: OrderBook) -> Result<(), OrderBookError> {
-        let mut books = self.books.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
-        books.insert(book.symbol.clone(), book);
-        Ok(())
+    /// Take checkpoints into a snapshot directory
+    ///
+    /// Only useful together with a write-ahead log: recovery loads the latest
+    /// snapshot and replays the log from there.
+    pub fn with_snapshot_store(mut self, store: SnapshotStore) -> Self {
+        self.snapshots = Some(store);
+        self
     }
 
-    /// Add an order to the order book and attempt to match it
-    pub fn add_order(&self, mut order: Order) -> Result<(Order, Vec<Trade>), OrderBookError> {
-        // Validate order using centralized validation
+    /// Check if checkpoints are taken
+    pub fn has_snapshot_store(&self) -> bool {
+        self.snapshots.is_some()
+    }
 
-        /*
-          What ? Does:
+    /// Keep account balances and only accept orders their owners can fund
+    ///
+    /// Accounts start empty; users need a deposit before their first order.
+    pub fn with_accounts(mut self) -> Self {
+        self.accounts = Some(Mutex::new(Accounts::new()));
+        self
+    }
 
-          validate_order(&order)?;
+    /// Check if orders are checked against account balances
+    pub fn has_accounts(&self) -> bool {
+        self.accounts.is_some()
+    }
 
-          Is equivalent to:
+    /// Record engine notices from now on (or stop, dropping those not taken)
+    ///
+    /// Turned on by the ingestion pipeline, whose matchers publish them.
+    pub fn set_notices_enabled(&self, enabled: bool) {
+        self.notices.set_enabled(enabled);
+    }
 
-          match validate_order(&order) {
-              Ok(value) => value,      // Continue execution (value is () here)z
-              Err(e) => return Err(e), // Return error immediately
+    /// Take the engine notices of a symbol, oldest first
+    pub fn take_notices(&self, symbol: &str) -> Vec<EngineNotice> {
+        self.notices.take(symbol)
+    }
+
+    /// Check if engine notices are waiting to be taken
+    pub fn has_notices(&self) -> bool {
+        self.notices.has_pending()
+    }
+
+    /// Symbols with engine notices waiting to be taken
+    pub fn symbols_with_notices(&self) -> Vec<String> {
+        self.notices.pending_symbols()
+    }
+
+    /// Record an engine notice (not while the log is re-applied)
+    fn notify(&self, symbol: &str, notice: EngineNotice) {
+        if !is_applying_log() {
+            self.notices.record(symbol, notice);
         }
-        */
-        validate_order(&order)?;
+    }
 
-        let mut book = self.get_or_create_book(&order.symbol)?;
+    /// Record the trades of something the engine did on its own
+    fn notify_trades(&self, taker_side: Option<OrderSide>, trades: &[Trade]) {
+        for trade in trades {
+            self.notify(
+                &trade.symbol,
+                EngineNotice::TradeExecuted {
+                    taker_side,
+                    trade: trade.clone(),
+                },
+            );
+        }
+    }
 
-        if book.check_order_in_book(order.id) {
-            return Err(OrderBookError::DuplicateOrder(order.id));    
+    /// Append an event to the write-ahead log (no-op when no WAL is attached
+    /// or the log is being replayed)
+    ///
+    /// The closure receives the sequence number the event will be written with.
+    /// Events are journaled under the lock of the book they change, so the log
+    /// holds each book's changes in the order they were applied.
+    fn journal(&self, build_event: impl FnOnce(u64) -> WalEvent) -> Result<(), OrderBookError> {
+        let Some(wal) = &self.wal else {
+            return Ok(());
+        };
+        if is_applying_log() {
+            return Ok(());
         }
+        self.ensure_primary()?;
 
-        // Attempt to match the order
-        let (trades, cancelled_order_ids) = match_order(&mut book, &mut order)?;
+        let mut wal = wal.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire WAL lock: {}", e)))?;
+        let event = build_event(wal.current_sequence() + 1);
+        wal.append(event)
+            .map_err(|e| OrderBookError::PersistenceError(format!("Failed to append to WAL: {}", e)))?;
+        Ok(())
+    }
 
-        // Cancelled orders is STP cancellation
-        // Remove cancelled orders from the book (STP cancellations)
-        // First, collect the data we need to avoid borrow checker issues
-        let cancellation_data: Vec<_> = cancelled_order_ids
-            .iter()
-            // |&id| pattern destructures the reference, giving the actual value instead of the reference to it
-            // Different from *, &id equals to match a reference and give me the results, this is just pattern matching
-            // let &value = r; value is i32 (5)
-            // let value = *r value is i32 (5)
-            .filter_map(|id| {
-                book.orders.get(id).map(|order| {
-                    (id, order.price, order.side.clone(), order.remaining_quantity())
-                })
-            })
-            .collect();
+    /// Make every event journaled so far durable (no-op without a WAL)
+    ///
+    /// The WAL lock is only held to flush; the fsync runs outside it.
+    pub fn sync_journal(&self) -> Result<(), OrderBookError> {
+        let Some(wal) = &self.wal else {
+            return Ok(());
+        };
+        let persistence_error = |e: std::io::Error| OrderBookError::PersistenceError(format!("Failed to sync WAL: {}", e));
+        let segment = {
+            let mut wal = wal.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire WAL lock: {}", e)))?;
+            wal.sync_handle().map_err(persistence_error)?
+        };
+        segment.sync_data().map_err(persistence_error)
+    }
 
-        // Now perform the removals
-        for (&cancelled_id, price_opt, side, remaining_qty) in cancellation_data {
-            if let Some(price) = price_opt {
-                remove_order_from_price_level(&mut book, cancelled_id, price, &side, remaining_qty);
-            }
-            book.orders.remove(&cancelled_id);
+    /// Journal the trades of an operation
+    fn journal_trades<'a>(&self, trades: impl IntoIterator<Item = &'a Trade>) -> Result<(), OrderBookError> {
+        for trade in trades {
+            self.journal(|sequence| WalEvent::TradeExecuted {
+                sequence,
+                timestamp_ns: now_ns(),
+                trade: trade.clone(),
+            })?;
         }
+        Ok(())
+    }
 
-        // We use &trades because we don't want to MOVE the trades into the for loop
-        // Add trades to book history
-        for trade in &trades {
-            book.add_trade(trade.clone());
+    /// Rebuild the engine's state from its latest snapshot and write-ahead log
+    ///
+    /// The newest readable snapshot (if a snapshot store is attached) is
+    /// loaded first; then the events after it are re-applied in log order.
+    /// Orders, amendments, cancellations, peg reprices, stop orders, auction
+    /// phases, fee schedule and matching policy changes are replayed, so matching produces the same trades again; `TradeExecuted`
+    /// events are not applied themselves. Orders released by triggered stops
+    /// are in the log as their own submissions and are not submitted a second
+    /// time. Contingent groups come back from the snapshot and their own
+    /// events; fills, triggers and cancellations of their legs move them on
+    /// again, while the legs they placed or cancelled follow in the log.
+    ///
+    /// Call this before the engine takes any orders, after configuring the
+    /// instruments the log was written with. New events
+    /// continue from the log's last sequence number. Returns the number of
+    /// events replayed; fails if the log does not continue where the snapshot
+    /// ends.
+    pub fn recover(&self) -> Result<u64, OrderBookError> {
+        let Some(wal) = &self.wal else {
+            return Ok(0);
+        };
+
+        let mut snapshot_sequence = 0;
+        if let Some(store) = &self.snapshots {
+            let latest = store
+                .load_latest()
+                .map_err(|e| OrderBookError::PersistenceError(format!("Failed to read snapshots: {}", e)))?;
+            if let Some((path, snapshot)) = latest {
+                tracing::info!("Loading snapshot {} at sequence {}", path.display(), snapshot.sequence);
+                snapshot_sequence = snapshot.sequence;
+                self.restore_snapshot(snapshot)?;
+            }
         }
 
-        // Add order to book if it should rest (based on TIF and fill status)
-        if order.should_rest_in_book() && order.order_type == OrderType::Limit {
-            let price = order.price.expect("Limit order must have price");
-            add_order_to_price_level(&mut book, order.id, price, &order.side, order.remaining_quantity());
-            book.orders.insert(order.id, order.clone());
+        let mut wal = wal.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire WAL lock: {}", e)))?;
+        let mut next_sequence = snapshot_sequence + 1;
+        let mut replayed = 0;
+        let result = applying_log(|| wal.replay(|event| {
+            let sequence = event.sequence();
+            if sequence <= snapshot_sequence {
+                return Ok(());
+            }
+            if sequence != next_sequence {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("log continues at sequence {} instead of {}", sequence, next_sequence),
+                ));
+            }
+            next_sequence += 1;
+            replayed += 1;
+
+            if let Err(e) = self.apply_wal_event(event) {
+                // Commands the engine rejected were rejected the first time as well
+                tracing::debug!("WAL event {} rejected on replay: {}", sequence, e);
+            }
+            Ok(())
+        }));
+
+        result.map_err(|e| OrderBookError::PersistenceError(format!("Failed to replay WAL: {}", e)))?;
+        if wal.current_sequence() < snapshot_sequence {
+            // The snapshot already holds these events; number new ones after it
+            tracing::warn!(
+                "WAL ends at sequence {} before the snapshot at {}; continuing after the snapshot",
+                wal.current_sequence(),
+                snapshot_sequence
+            );
+            wal.restart_at(snapshot_sequence)
+                .map_err(|e| OrderBookError::PersistenceError(format!("Failed to restart WAL: {}", e)))?;
         }
+        tracing::info!(
+            "Recovered {} WAL events after snapshot sequence {} up to sequence {}",
+            replayed,
+            snapshot_sequence,
+            wal.current_sequence()
+        );
+        Ok(replayed)
+    }
 
-        // Update the book
-        self.update_book(book)?;
+    /// Capture the full engine state at the current WAL sequence number
+    ///
+    /// Every book, the trigger engine, the accounts, the risk engine, the fee
+    /// schedules, the contingent groups, the matching policies and the WAL are
+    /// locked together, so the snapshot contains exactly the events up
+    /// to its sequence number. The WAL moves on to a new segment, which the snapshot records as the first one
+    /// it does not cover. Algorithm state is not part of the engine; the
+    /// returned snapshot has none.
+    pub fn capture_snapshot(&self) -> Result<EngineSnapshot, OrderBookError> {
+        // The map lock keeps new books from appearing while the books are locked
+        let books = self.books.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        let mut symbols: Vec<&String> = books.keys().collect();
+        symbols.sort();
+        let guards = symbols
+            .iter()
+            .map(|symbol| Self::lock_book(&books[*symbol]))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        // Check for triggered stop orders if any trades occurred
-        if !trades.is_empty() {
-            let last_trade_price = trades.last().unwrap().price;
-            let triggered_orders = {
-                let mut trigger_engine = self.trigger_engine.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
-                trigger_engine.on_trade(last_trade_price)
-            };
+        let trigger_engine = self.trigger_engine.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        let auctions = self.auctions.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        let accounts = self.lock_accounts()?;
+        let risk = self.lock_risk()?;
+        let fees = self.read_fees()?;
+        let contingent = self.contingent.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        let matching_policies = self.matching_policies.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
 
-            // Recursively submit triggered orders
-            for triggered_order in triggered_orders {
-                // Submit triggered order (ignore errors to prevent cascading failures)
-                let _ = self.add_order(triggered_order);
+        let (sequence, wal_segment) = match &self.wal {
+            Some(wal) => {
+                let mut wal = wal.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire WAL lock: {}", e)))?;
+                let segment = wal
+                    .start_segment()
+                    .map_err(|e| OrderBookError::PersistenceError(format!("Failed to start WAL segment: {}", e)))?;
+                (wal.current_sequence(), segment)
             }
+            None => (0, 0),
+        };
+
+        Ok(EngineSnapshot {
+            sequence,
+            wal_segment,
+            taken_at: Utc::now(),
+            books: guards.iter().map(|book| BookSnapshot::from(&**book)).collect(),
+            stop_orders: trigger_engine.all_stop_orders().into_iter().cloned().collect(),
+            last_trade_prices: trigger_engine
+                .last_trade_prices()
+                .into_iter()
+                .map(|(symbol, price)| LastTradePrice { symbol, price })
+                .collect(),
+            auctions: auctions.values().cloned().collect(),
+            algorithms: AlgorithmSnapshot::default(),
+            accounts: accounts.as_deref().cloned(),
+            daily_pnl: risk.daily_pnl(),
+            fee_schedules: fees.schedules().clone(),
+            contingent_groups: contingent.groups().cloned().collect(),
+            matching_policies: matching_policies.iter().map(|(symbol, policy)| (symbol.clone(), *policy)).collect(),
+        })
+    }
+
+    /// Replace the engine's state with a snapshot's
+    fn restore_snapshot(&self, snapshot: EngineSnapshot) -> Result<(), OrderBookError> {
+        let mut trigger_engine = TriggerEngine::new();
+        for stop in snapshot.stop_orders {
+            trigger_engine.add_stop_order(stop);
+        }
+        for LastTradePrice { symbol, price } in snapshot.last_trade_prices {
+            trigger_engine.set_last_trade_price(&symbol, price);
         }
 
-        Ok((order, trades))
+        let books: Vec<OrderBook> = snapshot.books.into_iter().map(|book| book.into_book()).collect();
+        {
+            let mut fees = self.write_fees()?;
+            fees.set_schedules(snapshot.fee_schedules);
+            fees.rebuild_volumes(books.iter().flat_map(|book| &book.trades));
+        }
+        self.lock_risk()?.rebuild(
+            books.iter().flat_map(|book| book.orders.values()),
+            books.iter().flat_map(|book| &book.trades),
+            snapshot.daily_pnl,
+        );
+        *self.books.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))? = books
+            .into_iter()
+            .map(|book| (book.symbol.clone(), Arc::new(Mutex::new(book))))
+            .collect();
+        *self.trigger_engine.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))? = trigger_engine;
+        *self.contingent.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))? =
+            ContingentOrderManager::from_groups(snapshot.contingent_groups);
+        *self.matching_policies.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))? =
+            snapshot.matching_policies.into_iter().collect();
+        *self.auctions.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))? = snapshot
+            .auctions
+            .into_iter()
+            .map(|auction| (auction.symbol.clone(), auction))
+            .collect();
+        if let Some(mut accounts) = self.lock_accounts()? {
+            *accounts = snapshot.accounts.unwrap_or_default();
+        }
+        *self.recovered_algorithms.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire lock: {}", e)))? = Some(snapshot.algorithms);
+        Ok(())
     }
 
-    /// Cancel an order
-    pub fn cancel_order(
-        &self,
-        symbol: &str,
-        order_id: Uuid,
-    ) -> Result<Order, OrderBookError> {
-        let mut book = self.get_or_create_book(symbol)?;
+    /// Take the algorithm state recovery loaded from a snapshot (empty if none)
+    pub fn take_recovered_algorithms(&self) -> Result<AlgorithmSnapshot, OrderBookError> {
+        let mut recovered = self.recovered_algorithms.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire lock: {}", e)))?;
+        Ok(recovered.take().unwrap_or_default())
+    }
 
-        // Get the order
-        let mut order = book
-            .orders
-            .remove(&order_id)
-            .ok_or(OrderBookError::OrderNotFound(order_id))?;
+    /// Write a snapshot of the engine (plus `algorithms`) and truncate the WAL
+    ///
+    /// The snapshot is recorded in the WAL as a `Checkpoint`. Only the newest
+    /// snapshots are kept, and WAL segments are deleted once every snapshot
+    /// left on disk covers them, so recovery can still fall back to the
+    /// previous snapshot if the newest one is damaged. Returns the snapshot
+    /// path (`None` without a snapshot store).
+    pub fn checkpoint(&self, algorithms: AlgorithmSnapshot) -> Result<Option<PathBuf>, OrderBookError> {
+        let Some(store) = &self.snapshots else {
+            return Ok(None);
+        };
+        let persistence_error = |e: std::io::Error| OrderBookError::PersistenceError(format!("Checkpoint failed: {}", e));
 
-        // Check if order can be cancelled
-        if order.status == OrderStatus::Filled || order.status == OrderStatus::Cancelled {
-            return Err(OrderBookError::OrderNotActive(order_id));
+        let mut snapshot = self.capture_snapshot()?;
+        snapshot.algorithms = algorithms;
+        let path = store.save(&snapshot).map_err(persistence_error)?;
+
+        // A follower's log holds only its primary's events
+        if !self.is_standby() {
+            self.journal(|sequence| WalEvent::Checkpoint {
+                sequence,
+                timestamp_ns: now_ns(),
+                checkpoint_path: path.to_string_lossy().into_owned(),
+            })?;
         }
 
-        // Remove from price level using helper function
-        if let Some(price) = order.price {
-            remove_order_from_price_level(
-                &mut book,
-                order_id,
-                price,
-                &order.side,
-                order.remaining_quantity(),
+        store.prune(SNAPSHOTS_TO_KEEP).map_err(persistence_error)?;
+        if let (Some(wal), Some(segment)) = (&self.wal, store.oldest_wal_segment().map_err(persistence_error)?) {
+            let wal = wal.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire WAL lock: {}", e)))?;
+            let removed = wal.remove_segments_before(segment).map_err(persistence_error)?;
+            tracing::info!(
+                "Checkpoint {} at sequence {} ({} WAL segments removed)",
+                path.display(),
+                snapshot.sequence,
+                removed
             );
         }
 
-        // Update order status
-        order.status = OrderStatus::Cancelled;
+        Ok(Some(path))
+    }
 
-        // Update the book
-        self.update_book(book)?;
+    /// Check if the engine is a replication follower
+    pub fn is_standby(&self) -> bool {
+        self.standby.load(Ordering::Acquire)
+    }
 
-        Ok(order)
+    /// Make the engine a follower (or promote it back to primary)
+    ///
+    /// A follower rejects order entry with `OrderBookError::Standby`; its
+    /// state only changes through `apply_replicated` and `install_snapshot`.
+    pub fn set_standby(&self, standby: bool) {
+        self.standby.store(standby, Ordering::Release);
     }
 
-    /// Get order status
-    pub fn get_order(&self, symbol: &str, order_id: Uuid) -> Result<Order, OrderBookError> {
-        let book = self.get_or_create_book(symbol)?;
-        book.orders
-            .get(&order_id)
-            .cloned()
-            .ok_or(OrderBookError::OrderNotFound(order_id))
+    /// Reject state changes on a follower, unless they re-apply logged events
+    fn ensure_primary(&self) -> Result<(), OrderBookError> {
+        if self.is_standby() && !is_applying_log() {
+            return Err(OrderBookError::Standby);
+        }
+        Ok(())
     }
 
-    /// Get the order book for a symbol
-    pub fn get_order_book(&self, symbol: &str) -> Result<OrderBook, OrderBookError> {
-        self.get_or_create_book(symbol)
+    /// Sequence number of the last journaled (or replicated) event
+    pub fn last_sequence(&self) -> Result<u64, OrderBookError> {
+        match &self.wal {
+            Some(wal) => {
+                let wal = wal.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire WAL lock: {}", e)))?;
+                Ok(wal.current_sequence())
+            }
+            None => Ok(self.replicated_sequence.load(Ordering::Acquire)),
+        }
     }
 
-    /// Get recent trades for a symbol
-    pub fn get_recent_trades(&self, symbol: &str, limit: usize) -> Result<Vec<Trade>, OrderBookError> {
-        let book = self.get_or_create_book(symbol)?;
-        Ok(book.get_recent_trades(limit))
+    /// Receive every event journaled from now on, for streaming to followers
+    ///
+    /// Returns the sequence number of the last event before the subscription;
+    /// the events up to it are in the WAL segments in `wal_dir`.
+    pub fn subscribe_journal(&self) -> Result<(u64, UnboundedReceiver<WalEvent>), OrderBookError> {
+        let wal = self
+            .wal
+            .as_ref()
+            .ok_or_else(|| OrderBookError::ReplicationError("replication needs a write-ahead log".to_string()))?;
+        let mut wal = wal.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire WAL lock: {}", e)))?;
+        wal.subscribe()
+            .map_err(|e| OrderBookError::PersistenceError(format!("Failed to flush WAL: {}", e)))
     }
 
-    /// Get all active symbols
-    pub fn get_symbols(&self) -> Result<Vec<String>, OrderBookError> {
-        let books = self.books.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
-        Ok(books.keys().cloned().collect())
+    /// Directory of the write-ahead log, if there is one
+    pub fn wal_dir(&self) -> Result<Option<PathBuf>, OrderBookError> {
+        match &self.wal {
+            Some(wal) => {
+                let wal = wal.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire WAL lock: {}", e)))?;
+                Ok(Some(wal.dir().to_path_buf()))
+            }
+            None => Ok(None),
+        }
     }
 
-    /// Get total number of active orders across all symbols
-    pub fn get_total_active_orders(&self) -> Result<usize, OrderBookError> {
-        let books = self.books.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
-        Ok(books.values().map(|book| book.orders.len()).sum())
+    /// Apply an event streamed from the primary
+    ///
+    /// Events must arrive in sequence order without gaps. The event is
+    /// written to the follower's own WAL with the primary's sequence number,
+    /// then applied the same way recovery replays it.
+    pub fn apply_replicated(&self, event: WalEvent) -> Result<(), OrderBookError> {
+        let sequence = event.sequence();
+        let expected = self.last_sequence()? + 1;
+        if sequence != expected {
+            return Err(OrderBookError::ReplicationError(format!(
+                "expected sequence {}, received {}",
+                expected, sequence
+            )));
+        }
+
+        match &self.wal {
+            Some(wal) => {
+                let mut wal = wal.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire WAL lock: {}", e)))?;
+                wal.append(event.clone())
+                    .map_err(|e| OrderBookError::PersistenceError(format!("Failed to append to WAL: {}", e)))?;
+            }
+            None => self.replicated_sequence.store(sequence, Ordering::Release),
+        }
+
+        if let Err(e) = applying_log(|| self.apply_wal_event(event)) {
+            // Rejected on the primary as well
+            tracing::debug!("Replicated event {} rejected: {}", sequence, e);
+        }
+        Ok(())
     }
 
-    /// Get total number of trades across all symbols
-    pub fn get_total_trades(&self) -> Result<usize, OrderBookError> {
-        let books = self.books.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
-        Ok(books.values().map(|book| book.trades.len()).sum())
+    /// Replace the follower's state with a snapshot of its primary
+    ///
+    /// Used when the primary no longer has the log from the follower's
+    /// position. A follower with a WAL saves the snapshot to its snapshot
+    /// store and restarts its log after the snapshot's sequence number.
+    pub fn install_snapshot(&self, mut snapshot: EngineSnapshot) -> Result<(), OrderBookError> {
+        let sequence = snapshot.sequence;
+        if let Some(wal) = &self.wal {
+            let store = self.snapshots.as_ref().ok_or_else(|| {
+                OrderBookError::ReplicationError("a follower with a WAL needs a snapshot store to install snapshots".to_string())
+            })?;
+            let mut wal = wal.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire WAL lock: {}", e)))?;
+            let persistence_error = |e: std::io::Error| OrderBookError::PersistenceError(format!("Failed to install snapshot: {}", e));
+
+            snapshot.wal_segment = wal.start_segment().map_err(persistence_error)?;
+            let path = store.save(&snapshot).map_err(persistence_error)?;
+            wal.restart_at(sequence).map_err(persistence_error)?;
+            tracing::info!("Installed primary snapshot at sequence {} as {}", sequence, path.display());
+        }
+
+        self.restore_snapshot(snapshot)?;
+        self.replicated_sequence.store(sequence, Ordering::Release);
+        Ok(())
     }
 
-    /// Get total volume across all symbols
-    pub fn get_total_volume(&self) -> Result<Decimal, OrderBookError> {
-        let books = self.books.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+    /// Apply one recorded event the way recovery re-applies the log
+    ///
+    /// Nothing is journaled, and orders released by triggered stops are not
+    /// submitted (they follow as their own `OrderSubmitted` events). Used to
+    /// replay recorded order flow into a separate engine.
+    pub fn replay_event(&self, event: WalEvent) -> Result<(), OrderBookError> {
+        applying_log(|| self.apply_wal_event(event))
+    }
+
+    /// Re-apply one journaled event at the time it was journaled
+    ///
+    /// The event runs on a clock stopped at its timestamp, so an order whose
+    /// expiry has passed since it was logged still arrives unexpired, and
+    /// anything stamped with the time gets the original one.
+    fn apply_wal_event(&self, event: WalEvent) -> Result<(), OrderBookError> {
+        let journaled_at = DateTime::from_timestamp_nanos(event.timestamp_ns() as i64);
+        clock::with_clock(Arc::new(clock::SimulatedClock::new(journaled_at)), || self.apply_logged_event(event))
+    }
+
+    fn apply_logged_event(&self, event: WalEvent) -> Result<(), OrderBookError> {
+        match event {
+            WalEvent::OrderSubmitted { order, .. } => {
+                let (placed, _) = self.add_order(order)?;
+                self.check_leg_placed(&placed)?;
+            }
+            WalEvent::OrderCancelled { order_id, symbol, .. } => {
+                self.cancel_order(&symbol, order_id)?;
+            }
+            WalEvent::OrderExpired { symbol, order_ids, .. } => {
+                self.expire_symbol_orders(&symbol, |order| order_ids.contains(&order.id))?;
+            }
+            WalEvent::OrderModified {
+                order_id,
+                symbol,
+                new_quantity,
+                new_price,
+                ..
+            } => {
+                self.amend_order(&symbol, order_id, new_price, new_quantity)?;
+            }
+            WalEvent::OrderRepriced { symbol, order_id, new_price, .. } => {
+                self.replay_peg_reprice(&symbol, order_id, new_price)?;
+            }
+            WalEvent::StopOrderSubmitted { stop, .. } => self.add_stop_order(stop)?,
+            WalEvent::StopOrderCancelled { order_id, .. } => {
+                self.cancel_stop_order(order_id)?;
+            }
+            WalEvent::AuctionStarted { symbol, kind, .. } => {
+                self.start_auction(&symbol, kind)?;
+            }
+            WalEvent::AuctionEnded { symbol, .. } => {
+                self.end_auction(&symbol)?;
+            }
+            WalEvent::Deposited { user_id, asset, amount, .. } => {
+                self.deposit(&user_id, &asset, amount)?;
+            }
+            WalEvent::Withdrawn { user_id, asset, amount, .. } => {
+                self.withdraw(&user_id, &asset, amount)?;
+            }
+            WalEvent::FeeScheduleSet { scope, schedule, .. } => {
+                self.set_fee_schedule(scope, schedule)?;
+            }
+            WalEvent::ContingentGroupSubmitted { group, .. } => {
+                self.submit_contingent_order(group)?;
+            }
+            WalEvent::ContingentGroupCancelled { group_id, .. } => {
+                self.cancel_contingent_order(group_id)?;
+            }
+            WalEvent::MatchingPolicySet { symbol, policy, .. } => {
+                self.set_matching_policy(&symbol, policy)?;
+            }
+            // Trades are produced again by matching the replayed orders
+            WalEvent::TradeExecuted { .. } | WalEvent::Checkpoint { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Get or create the order book shard of a symbol
+    ///
+    /// Books are only created for symbols the instrument registry accepts.
+    fn get_or_create_book(&self, symbol: &str) -> Result<Arc<Mutex<OrderBook>>, OrderBookError> {
+        self.check_symbol(symbol)?;
+
+        {
+            let books = self.books.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+            if let Some(book) = books.get(symbol) {
+                return Ok(book.clone());
+            }
+        }
+
+        let mut books = self.books.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
         Ok(books
-            .values()
-            .flat_map(|book| &book.trades)
-            .map(|trade| trade.value())
-            .sum())
+            .entry(symbol.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(OrderBook::new(symbol.to_string()))))
+            .clone())
     }
 
-    /// Get total fees collected across all symbols
-    pub fn get_total_fees(&self) -> Result<Decimal, OrderBookError> {
+    /// Lock a single order book
+    fn lock_book(book: &Mutex<OrderBook>) -> Result<MutexGuard<'_, OrderBook>, OrderBookError> {
+        book.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire book lock: {}", e)))
+    }
+
+    /// Run `f` on a symbol's order book while holding its lock
+    ///
+    /// Only the book itself is locked. Post-trade processing (contingent legs,
+    /// stop triggers) submits further orders and must run after `f` returns.
+    fn with_book<T>(&self, symbol: &str, f: impl FnOnce(&mut OrderBook) -> Result<T, OrderBookError>) -> Result<T, OrderBookError> {
+        let book = self.get_or_create_book(symbol)?;
+        let mut book = Self::lock_book(&book)?;
+        f(&mut book)
+    }
+
+    /// Handles of every existing order book
+    fn all_books(&self) -> Result<Vec<Arc<Mutex<OrderBook>>>, OrderBookError> {
         let books = self.books.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
-        Ok(books
-            .values()
-            // Flatten the trades from all books into one single iterator
-            .flat_map(|book| &book.trades)
-            .map(|trade| trade.total_fees())
-            .sum())
+        Ok(books.values().cloned().collect())
     }
 
-    // ============================================================================
-    // Stop Order Management
-    // ============================================================================
+    /// Reject symbols the instrument registry does not accept
+    fn check_symbol(&self, symbol: &str) -> Result<(), OrderBookError> {
+        let instruments = self.instruments.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        if !instruments.accepts(symbol) {
+            return Err(OrderBookError::InvalidSymbol(format!("Unknown symbol: {}", symbol)));
+        }
+        Ok(())
+    }
 
-    /// Add a stop order
-    pub fn add_stop_order(&self, stop: StopOrder) -> Result<(), OrderBookError> {
-        let mut trigger_engine = self.trigger_engine.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
-        trigger_engine.add_stop_order(stop);
+    /// Replace the instrument registry
+    ///
+    /// Books that already exist are kept even if the new registry does not
+    /// know their symbol; new orders on such symbols are rejected.
+    pub fn set_instrument_registry(&self, registry: InstrumentRegistry) -> Result<(), OrderBookError> {
+        let mut instruments = self.instruments.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+        *instruments = registry;
         Ok(())
     }
 
-    /// Cancel a stop order
-    pub fn cancel_stop_order(&self, order_id: Uuid) -> Result<StopOrder, OrderBookError> {
-        let mut trigger_engine = self.trigger_engine.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
-        trigger_engine
-            .cancel_stop_order(order_id)
-            .ok_or(OrderBookError::OrderNotFound(order_id))
+    /// Add or replace the reference data of one instrument
+    pub fn register_instrument(&self, instrument: Instrument) -> Result<(), OrderBookError> {
+        let mut instruments = self.instruments.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+        instruments.register(instrument);
+        Ok(())
     }
 
-    /// Get a stop order by ID
-    pub fn get_stop_order(&self, order_id: Uuid) -> Result<Option<StopOrder>, OrderBookError> {
-        let trigger_engine = self.trigger_engine.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
-        Ok(trigger_engine.get_stop_order(order_id).cloned())
+    /// Get the reference data of a symbol (`None` if it is not registered)
+    pub fn get_instrument(&self, symbol: &str) -> Result<Option<Instrument>, OrderBookError> {
+        let instruments = self.instruments.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        Ok(instruments.get(symbol).cloned())
     }
 
-    /// Get all stop orders for a symbol
-    pub fn get_stop_orders_by_symbol(&self, symbol: &str) -> Result<Vec<StopOrder>, OrderBookError> {
-        let trigger_engine = self.trigger_engine.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
-        Ok(trigger_engine
-            .get_stop_orders_by_symbol(symbol)
-            .into_iter()
-            .cloned()
-            .collect())
+    /// Get all registered instruments
+    pub fn get_instruments(&self) -> Result<Vec<Instrument>, OrderBookError> {
+        let instruments = self.instruments.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        Ok(instruments.instruments())
     }
 
-    /// Get total number of active stop orders
-    pub fn get_total_stop_orders(&self) -> Result<usize, OrderBookError> {
-        let trigger_engine = self.trigger_engine.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
-        Ok(trigger_engine.get_total_stop_orders())
+    /// Get the matching policy for a symbol (FIFO unless configured otherwise)
+    fn matching_policy(&self, symbol: &str) -> Result<MatchingPolicyConfig, OrderBookError> {
+        let policies = self.matching_policies.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        Ok(policies.get(symbol).copied().unwrap_or_default())
     }
-}
 
-impl Default for OrderBookEngine {
-    fn default() -> Self {
-        Self::new()
+    /// Get the matching policy of a symbol (FIFO unless configured otherwise)
+    pub fn get_matching_policy(&self, symbol: &str) -> Result<MatchingPolicyConfig, OrderBookError> {
+        self.matching_policy(symbol)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rust_decimal_macros::dec;
+    /// Get the matching policy of every symbol configured with one
+    pub fn get_matching_policies(&self) -> Result<BTreeMap<String, MatchingPolicyConfig>, OrderBookError> {
+        let policies = self.matching_policies.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        Ok(policies.iter().map(|(symbol, policy)| (symbol.clone(), *policy)).collect())
+    }
 
-    #[test]
-    fn test_add_and_match_order() {
-        let engine = OrderBookEngine::new();
+    /// Set the matching policy used within price levels of a symbol,
+    /// journaled as `WalEvent::MatchingPolicySet`
+    ///
+    /// Applies to orders matched from now on; resting orders keep their
+    /// place in the queue.
+    pub fn set_matching_policy(&self, symbol: &str, policy: MatchingPolicyConfig) -> Result<(), OrderBookError> {
+        self.ensure_primary()?;
+        policy.validate().map_err(OrderBookError::InvalidMatchingPolicy)?;
 
-        // Add a sell order
-        let sell_order = Order::new(
-            "AAPL".to_string(),
-            OrderSide::Sell,
-            OrderType::Limit,
-            Some(dec!(150.00)),
+        let mut policies = self.matching_policies.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+        policies.insert(symbol.to_string(), policy);
+        self.journal(|sequence| WalEvent::MatchingPolicySet {
+            sequence,
+            timestamp_ns: now_ns(),
+            symbol: symbol.to_string(),
+            policy,
+        })
+    }
+
+    // ============================================================================
+    // Fees
+    // ============================================================================
+
+    fn read_fees(&self) -> Result<RwLockReadGuard<'_, FeeEngine>, OrderBookError> {
+        self.fees
+            .read()
+            .map_err(|e| OrderBookError::LockError(format!("Failed to acquire fees lock: {}", e)))
+    }
+
+    fn write_fees(&self) -> Result<RwLockWriteGuard<'_, FeeEngine>, OrderBookError> {
+        self.fees
+            .write()
+            .map_err(|e| OrderBookError::LockError(format!("Failed to acquire fees lock: {}", e)))
+    }
+
+    /// Count trades towards their parties' volume tiers
+    fn record_fee_volume(&self, trades: &[Trade]) -> Result<(), OrderBookError> {
+        if !trades.is_empty() {
+            let mut fees = self.write_fees()?;
+            for trade in trades {
+                fees.record_trade(trade);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the default, per-symbol and per-user fee schedules
+    pub fn get_fee_schedules(&self) -> Result<FeeSchedules, OrderBookError> {
+        Ok(self.read_fees()?.schedules().clone())
+    }
+
+    /// Set the fee schedule of users and symbols without their own
+    pub fn set_default_fee_schedule(&self, schedule: FeeSchedule) -> Result<(), OrderBookError> {
+        self.set_fee_schedule(FeeScheduleScope::Default, Some(schedule))
+    }
+
+    /// Set (or with `None`, remove) the fee schedule of a symbol
+    ///
+    /// Applies to trades from now on; orders already resting keep the
+    /// reservation they were accepted with.
+    pub fn set_symbol_fee_schedule(&self, symbol: &str, schedule: Option<FeeSchedule>) -> Result<(), OrderBookError> {
+        self.set_fee_schedule(FeeScheduleScope::Symbol(symbol.to_string()), schedule)
+    }
+
+    /// Set (or with `None`, remove) the fee schedule of a user, which takes
+    /// precedence over symbol schedules
+    pub fn set_user_fee_schedule(&self, user_id: &str, schedule: Option<FeeSchedule>) -> Result<(), OrderBookError> {
+        self.set_fee_schedule(FeeScheduleScope::User(user_id.to_string()), schedule)
+    }
+
+    /// Change a schedule, journaled as `WalEvent::FeeScheduleSet`
+    ///
+    /// The fees lock is held while journaling, so the log has schedule
+    /// changes in the order they were made.
+    fn set_fee_schedule(&self, scope: FeeScheduleScope, schedule: Option<FeeSchedule>) -> Result<(), OrderBookError> {
+        self.ensure_primary()?;
+
+        let mut fees = self.write_fees()?;
+        fees.set_schedule(&scope, schedule.clone())
+            .map_err(OrderBookError::InvalidFeeSchedule)?;
+        self.journal(|sequence| WalEvent::FeeScheduleSet {
+            sequence,
+            timestamp_ns: now_ns(),
+            scope,
+            schedule,
+        })
+    }
+
+    /// Get the fee rates a user currently pays trading a symbol
+    pub fn get_fee_rates(&self, user_id: &str, symbol: &str) -> Result<FeeRates, OrderBookError> {
+        Ok(self.read_fees()?.rates(user_id, symbol, clock::now()))
+    }
+
+    // ============================================================================
+    // Risk limits
+    // ============================================================================
+
+    fn lock_risk(&self) -> Result<MutexGuard<'_, RiskEngine>, OrderBookError> {
+        self.risk
+            .lock()
+            .map_err(|e| OrderBookError::LockError(format!("Failed to acquire risk lock: {}", e)))
+    }
+
+    /// Count a message of an account against its rate limit (not while the
+    /// log is re-applied: what was accepted then is accepted again)
+    fn check_message_rate(&self, user_id: &str) -> Result<(), OrderBookError> {
+        if is_applying_log() {
+            return Ok(());
+        }
+        Ok(self.lock_risk()?.check_message(user_id, clock::now())?)
+    }
+
+    /// Count an amend or cancel against its order owner's rate limit
+    ///
+    /// Orders the risk engine does not know are not working; the request
+    /// fails on its own without being counted.
+    fn check_order_message_rate(&self, order_id: Uuid) -> Result<(), OrderBookError> {
+        if is_applying_log() {
+            return Ok(());
+        }
+        let mut risk = self.lock_risk()?;
+        let Some(user_id) = risk.owner(order_id).map(str::to_string) else {
+            return Ok(());
+        };
+        Ok(risk.check_message(&user_id, clock::now())?)
+    }
+
+    /// Check an order (or the amended form of one) against its owner's risk limits
+    fn check_order_risk(&self, order: &Order) -> Result<(), OrderBookError> {
+        if is_applying_log() {
+            return Ok(());
+        }
+        Ok(self.lock_risk()?.check_order(order, clock::now())?)
+    }
+
+    /// Get the risk limits of accounts without their own
+    pub fn get_default_risk_limits(&self) -> Result<RiskLimits, OrderBookError> {
+        Ok(self.lock_risk()?.default_limits().clone())
+    }
+
+    /// Set the risk limits of accounts without their own
+    pub fn set_default_risk_limits(&self, limits: RiskLimits) -> Result<(), OrderBookError> {
+        self.lock_risk()?
+            .set_default_limits(limits)
+            .map_err(OrderBookError::InvalidRiskLimits)
+    }
+
+    /// Get the accounts that have their own risk limits
+    pub fn get_account_risk_overrides(&self) -> Result<BTreeMap<String, RiskLimits>, OrderBookError> {
+        Ok(self.lock_risk()?.account_limits().clone())
+    }
+
+    /// Set (or with `None`, remove) an account's own risk limits, which
+    /// replace the default limits
+    pub fn set_account_risk_limits(&self, user_id: &str, limits: Option<RiskLimits>) -> Result<(), OrderBookError> {
+        self.lock_risk()?
+            .set_account_limits(user_id, limits)
+            .map_err(OrderBookError::InvalidRiskLimits)
+    }
+
+    /// Get the risk limits that apply to an account and its use of them
+    pub fn get_account_risk(&self, user_id: &str) -> Result<AccountRiskStatus, OrderBookError> {
+        Ok(self.lock_risk()?.status(user_id, clock::now()))
+    }
+
+    /// Add an order to the order book and attempt to match it
+    pub fn add_order(&self, mut order: Order) -> Result<(Order, Vec<Trade>), OrderBookError> {
+        self.ensure_primary()?;
+        self.check_message_rate(&order.user_id)?;
+
+        // Validate order using centralized validation
+
+        /*
+          What ? Does:
+
+          validate_order(&order)?;
+
+          Is equivalent to:
+
+          match validate_order(&order) {
+              Ok(value) => value,      // Continue execution (value is () here)z
+              Err(e) => return Err(e), // Return error immediately
+        }
+        */
+        if order.time_in_force == TimeInForce::DAY && order.expire_time.is_none() {
+            // DAY orders live until the close of the symbol's current trading session
+            order.expire_time = self.get_trading_calendar(&order.symbol)?.day_order_expiry(order.timestamp);
+        }
+
+        let instrument = self.get_instrument(&order.symbol)?;
+        let policy = self.matching_policy(&order.symbol)?;
+        let symbol = order.symbol.clone();
+        self.refresh_circuit_breaker(&symbol)?;
+
+        // The auction phase is checked under the book lock so that an order
+        // cannot match while an auction is being started or uncrossed
+        let (trades, peg_trades) = self.with_book(&symbol, |book| {
+            if self.in_auction(&symbol)? {
+                self.check_order_risk(&order)?;
+                // No price band: the uncross decides the price
+                self.check_circuit_breaker(&order)?;
+                self.add_auction_order(book, &order, instrument.as_ref())?;
+                self.journal(|sequence| WalEvent::OrderSubmitted {
+                    sequence,
+                    timestamp_ns: now_ns(),
+                    order: order.clone(),
+                })?;
+                if is_engine_initiated() {
+                    self.notify(&symbol, EngineNotice::OrderAccepted(order.clone()));
+                }
+                return Ok((Vec::new(), Vec::new()));
+            }
+
+            if order.order_type.is_pegged() && !is_applying_log() {
+                // Pegged orders take their price from the current top of book;
+                // the log holds the price they were given, band included
+                let tick_size = instrument.as_ref().map(|instrument| instrument.tick_size);
+                order.price = peg_price(&order, &reference_prices(book), tick_size);
+            }
+
+            // Account risk limits come before the symbol's circuit breaker and
+            // the order's own validation
+            self.check_order_risk(&order)?;
+            self.check_circuit_breaker(&order)?;
+            self.apply_price_band(&mut order, instrument.as_ref())?;
+            validate_order(&order, instrument.as_ref())?;
+
+            if book.check_order_in_book(order.id) {
+                return Err(OrderBookError::DuplicateOrder(order.id));
+            }
+
+            self.reserve_funds(book, &order)?;
+
+            // Journaled as submitted: replaying it re-runs the match
+            self.journal(|sequence| WalEvent::OrderSubmitted {
+                sequence,
+                timestamp_ns: now_ns(),
+                order: order.clone(),
+            })?;
+
+            let trades = self.execute_order(book, &mut order, &policy)?;
+            if is_engine_initiated() {
+                self.notify(&symbol, EngineNotice::OrderAccepted(order.clone()));
+                self.notify_trades(Some(order.side), &trades);
+            }
+            let peg_trades = self.reprice_pegged_orders(book, &policy)?;
+            self.journal_trades(trades.iter().chain(&peg_trades))?;
+            Ok((trades, peg_trades))
+        })?;
+
+        self.process_trades(&trades, &peg_trades)?;
+
+        Ok((order, trades))
+    }
+
+    /// Run post-trade processing (contingent legs, stop triggers) for the trades
+    /// of an operation and of any peg reprices it caused
+    fn process_trades(&self, trades: &[Trade], peg_trades: &[Trade]) -> Result<(), OrderBookError> {
+        let trades: Vec<Trade> = trades.iter().chain(peg_trades).cloned().collect();
+        self.process_contingent_fills(&trades)?;
+        self.process_triggered_stops(&trades)
+    }
+
+    /// Match an order against the book, apply STP cancellations, record trades,
+    /// and rest any remainder according to its time-in-force
+    ///
+    /// With accounts, the trades settle with `order` as the taker.
+    fn execute_order(&self, book: &mut OrderBook, order: &mut Order, policy: &dyn MatchingPolicy) -> Result<Vec<Trade>, OrderBookError> {
+        // Attempt to match the order
+        let matched = match_order_with_policy(book, order, policy, &*self.read_fees()?);
+        let (trades, cancelled_orders) = match matched {
+            Ok(result) => result,
+            Err(e) => {
+                self.release_funds([order.id])?;
+                return Err(e.into());
+            }
+        };
+        let cancelled_order_ids: Vec<Uuid> = cancelled_orders.iter().map(|cancelled| cancelled.id).collect();
+
+        // Cancelled orders is STP cancellation
+        // Remove cancelled orders from the book (STP cancellations)
+        // First, collect the data we need to avoid borrow checker issues
+        let cancellation_data: Vec<_> = cancelled_order_ids
+            .iter()
+            // |&id| pattern destructures the reference, giving the actual value instead of the reference to it
+            // Different from *, &id equals to match a reference and give me the results, this is just pattern matching
+            // let &value = r; value is i32 (5)
+            // let value = *r value is i32 (5)
+            .filter_map(|id| {
+                book.orders.get(id).map(|order| {
+                    (id, order.price, order.side.clone(), order.remaining_quantity())
+                })
+            })
+            .collect();
+
+        // Now perform the removals
+        for (&cancelled_id, price_opt, side, remaining_qty) in cancellation_data {
+            if let Some(price) = price_opt {
+                remove_order_from_price_level(book, cancelled_id, price, &side, remaining_qty);
+            }
+            book.remove_order(cancelled_id);
+        }
+
+        // The incoming order's own cancellation is in its result
+        for cancelled in cancelled_orders.into_iter().filter(|cancelled| cancelled.id != order.id) {
+            self.notify(
+                &book.symbol,
+                EngineNotice::OrderCancelled {
+                    order: cancelled,
+                    reason: CancelReason::SelfTrade,
+                },
+            );
+        }
+
+        // We use &trades because we don't want to MOVE the trades into the for loop
+        // Add trades to book history
+        for trade in &trades {
+            book.add_trade(trade.clone());
+        }
+        self.record_fee_volume(&trades)?;
+        self.feed_circuit_breaker(&trades)?;
+
+        // Add order to book if it should rest (based on TIF and fill status)
+        if order.should_rest_in_book() && order.order_type.rests_in_book() {
+            Self::rest_order(book, order);
+        }
+
+        self.settle_trades(book, &trades, Some(order.id), cancelled_order_ids.into_iter().chain([order.id]))?;
+
+        Ok(trades)
+    }
+
+    /// Insert an order into the book without matching it
+    ///
+    /// Orders without a price (market orders collected by a call auction) are
+    /// tracked by the book but do not join a price level.
+    fn rest_order(book: &mut OrderBook, order: &Order) {
+        if let Some(price) = order.price {
+            add_order_to_price_level(book, order.id, price, &order.side, order.remaining_quantity());
+        }
+        book.insert_order(order.clone());
+    }
+
+    /// Check for triggered stop orders after trades occurred and submit them
+    fn process_triggered_stops(&self, trades: &[Trade]) -> Result<(), OrderBookError> {
+        let Some(last_trade) = trades.last() else {
+            return Ok(());
+        };
+
+        let triggered_stops = {
+            let mut trigger_engine = self.trigger_engine.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+            trigger_engine.on_trade(&last_trade.symbol, last_trade.price)
+        };
+
+        // Recursively submit triggered orders
+        for triggered in triggered_stops {
+            // A stop leg of an OCO / bracket cancels its sibling before its order hits the book
+            let actions = {
+                let mut contingent = self.contingent.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+                contingent.on_stop_triggered(triggered.stop_order_id, triggered.order.id)
+            };
+            self.execute_contingent_actions(actions)?;
+
+            if is_applying_log() {
+                // The triggered order follows in the log as its own submission
+                continue;
+            }
+
+            // Submit triggered order (ignore errors to prevent cascading failures)
+            let _ = engine_initiated(|| self.add_order(triggered.order));
+        }
+
+        Ok(())
+    }
+
+    /// Report executions to the contingent order manager and carry out the resulting actions
+    fn process_contingent_fills(&self, trades: &[Trade]) -> Result<(), OrderBookError> {
+        if trades.is_empty() {
+            return Ok(());
+        }
+
+        let actions = {
+            let mut contingent = self.contingent.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+            trades
+                .iter()
+                .flat_map(|trade| {
+                    let mut actions = contingent.on_fill(trade.buyer_order_id, trade.quantity);
+                    actions.extend(contingent.on_fill(trade.seller_order_id, trade.quantity));
+                    actions
+                })
+                .collect::<Vec<_>>()
+        };
+
+        self.execute_contingent_actions(actions)
+    }
+
+    /// Tell the contingent order manager a leg is gone and cancel the rest of its group
+    fn notify_leg_cancelled(&self, order_id: Uuid) -> Result<(), OrderBookError> {
+        let actions = {
+            let mut contingent = self.contingent.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+            contingent.on_leg_cancelled(order_id)
+        };
+
+        self.execute_contingent_actions(actions)
+    }
+
+    /// Carry out a single contingent action
+    ///
+    /// Placing an order that neither fills nor rests (e.g. an unfilled IOC) counts
+    /// as a cancelled leg. Cancelling a leg that already filled is not an error.
+    /// A leg its group no longer has working (its sibling filled as it was
+    /// placed) is not placed. Nothing is carried out while the log is
+    /// re-applied: the placements and cancellations follow in it as their
+    /// own events.
+    fn apply_contingent_action(&self, action: ContingentAction) -> Result<(), OrderBookError> {
+        if is_applying_log() {
+            return Ok(());
+        }
+
+        let leg_id = match &action {
+            ContingentAction::PlaceOrder(order) => Some(order.id),
+            ContingentAction::PlaceStop(stop) => Some(stop.id),
+            _ => None,
+        };
+        if let Some(leg_id) = leg_id {
+            let contingent = self.contingent.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+            if !contingent.is_leg_working(leg_id) {
+                return Ok(());
+            }
+        }
+
+        match action {
+            ContingentAction::PlaceOrder(order) => {
+                let (placed, _) = engine_initiated(|| self.add_order(order))?;
+                self.check_leg_placed(&placed)?;
+            }
+            ContingentAction::PlaceStop(stop) => self.add_stop_order(stop)?,
+            ContingentAction::CancelOrder { symbol, order_id } => {
+                let _ = engine_initiated(|| self.cancel_order(&symbol, order_id));
+            }
+            ContingentAction::CancelStop(stop_id) => {
+                let _ = self.cancel_stop_order(stop_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// An order that neither filled nor rests (e.g. an unfilled IOC) is gone;
+    /// if it is a contingent leg, the rest of its group is cancelled
+    fn check_leg_placed(&self, placed: &Order) -> Result<(), OrderBookError> {
+        let resting = placed.should_rest_in_book() && placed.order_type.rests_in_book();
+        if !placed.is_filled() && !resting {
+            self.notify_leg_cancelled(placed.id)?;
+        }
+        Ok(())
+    }
+
+    /// Carry out contingent actions; a leg that cannot be placed cancels its group
+    fn execute_contingent_actions(&self, actions: Vec<ContingentAction>) -> Result<(), OrderBookError> {
+        for action in actions {
+            let placed_leg = match &action {
+                ContingentAction::PlaceOrder(order) => Some(order.id),
+                ContingentAction::PlaceStop(stop) => Some(stop.id),
+                _ => None,
+            };
+
+            if let Err(e) = self.apply_contingent_action(action) {
+                if let OrderBookError::LockError(_) = e {
+                    return Err(e);
+                }
+                if let Some(leg_id) = placed_leg {
+                    tracing::warn!("Failed to place contingent leg {}: {}", leg_id, e);
+                    self.notify_leg_cancelled(leg_id)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Amend the price and/or quantity of a resting order
+    ///
+    /// Queue-priority rules:
+    /// - Reducing the quantity at the same price keeps the order's place in the queue
+    /// - Changing the price or increasing the quantity loses priority: the order is
+    ///   re-timestamped, re-matched against the book (the new price may cross), and
+    ///   any remainder is queued at the back of its new price level
+    ///
+    /// `new_quantity` is the new total order quantity, not the remaining quantity.
+    /// The amendment is journaled as `WalEvent::OrderModified`.
+    pub fn amend_order(
+        &self,
+        symbol: &str,
+        order_id: Uuid,
+        new_price: Option<Decimal>,
+        new_quantity: Option<Decimal>,
+    ) -> Result<(Order, Vec<Trade>), OrderBookError> {
+        self.ensure_primary()?;
+
+        self.check_order_message_rate(order_id)?;
+        self.refresh_circuit_breaker(symbol)?;
+
+        let policy = self.matching_policy(symbol)?;
+        let instrument = self.get_instrument(symbol)?;
+
+        let (order, trades, peg_trades) = self.with_book(symbol, |book| {
+            let in_auction = self.in_auction(symbol)?;
+            let (order, trades) = self.amend_in_book(
+                book,
+                order_id,
+                new_price,
+                new_quantity,
+                instrument.as_ref(),
+                &policy,
+                in_auction,
+            )?;
+            self.journal(|sequence| WalEvent::OrderModified {
+                sequence,
+                timestamp_ns: now_ns(),
+                order_id,
+                symbol: symbol.to_string(),
+                new_quantity,
+                new_price,
+            })?;
+
+            let peg_trades = if in_auction {
+                Vec::new()
+            } else {
+                self.reprice_pegged_orders(book, &policy)?
+            };
+            self.journal_trades(trades.iter().chain(&peg_trades))?;
+
+            Ok((order, trades, peg_trades))
+        })?;
+
+        self.process_trades(&trades, &peg_trades)?;
+
+        Ok((order, trades))
+    }
+
+    /// Apply an amendment to a book and re-match the order if it lost priority
+    #[allow(clippy::too_many_arguments)]
+    fn amend_in_book(
+        &self,
+        book: &mut OrderBook,
+        order_id: Uuid,
+        new_price: Option<Decimal>,
+        new_quantity: Option<Decimal>,
+        instrument: Option<&Instrument>,
+        policy: &dyn MatchingPolicy,
+        in_auction: bool,
+    ) -> Result<(Order, Vec<Trade>), OrderBookError> {
+        let mut order = book
+            .orders
+            .get(&order_id)
+            .cloned()
+            .ok_or(OrderBookError::OrderNotFound(order_id))?;
+
+        if order.status == OrderStatus::Filled || order.status == OrderStatus::Cancelled {
+            return Err(OrderBookError::OrderNotActive(order_id));
+        }
+
+        validate_amendment(&order, new_price, new_quantity)?;
+
+        let current_price = order.price.expect("Resting limit order must have price");
+        let target_price = new_price.unwrap_or(current_price);
+        let target_quantity = new_quantity.unwrap_or(order.quantity);
+
+        if target_price == current_price && target_quantity == order.quantity {
+            return Err(OrderBookError::InvalidAmendment(
+                "Amend does not change price or quantity".to_string(),
+            ));
+        }
+
+        let mut amended = order.clone();
+        amended.price = Some(target_price);
+        amended.quantity = target_quantity;
+        let keeps_priority = target_price == current_price && target_quantity < order.quantity;
+        if !keeps_priority {
+            // Only amends that shrink an order in place cannot add risk
+            self.check_order_risk(&amended)?;
+            self.check_circuit_breaker(&amended)?;
+            if !in_auction {
+                // An amend is not repriced: its new price is taken or refused as asked
+                self.apply_price_band(&mut amended, instrument)?;
+                if amended.price != Some(target_price) {
+                    return Err(OrderBookError::InvalidAmendment(format!(
+                        "Price {} is outside the price band",
+                        target_price
+                    )));
+                }
+            }
+        }
+        if let Some(instrument) = instrument {
+            validate_instrument(&amended, instrument)?;
+        }
+        self.resize_funds(book, &amended)?;
+
+        let trades = if keeps_priority {
+            // Shrink in place: same slot in the price level queue
+            let reduction = order.quantity - target_quantity;
+            let levels = match order.side {
+                OrderSide::Buy => &mut book.bids,
+                OrderSide::Sell => &mut book.asks,
+            };
+            if let Some(level) = levels.get_mut(&current_price) {
+                level.total_quantity -= reduction;
+            }
+
+            order.quantity = target_quantity;
+            order.update_status();
+            book.insert_order(order.clone());
+            Vec::new()
+        } else if in_auction {
+            // Back of the queue at the new price, matched when the auction uncrosses
+            order.quantity = target_quantity;
+            Self::pull_order(book, &mut order, target_price);
+            Self::rest_order(book, &order);
+            Vec::new()
+        } else {
+            order.quantity = target_quantity;
+            self.requeue_order(book, &mut order, target_price, policy)?
+        };
+
+        Ok((order, trades))
+    }
+
+    /// Pull a resting order and re-enter it at `new_price` as a fresh arrival
+    ///
+    /// The order loses its queue priority: it is re-timestamped, re-matched
+    /// against the book, and any remainder is queued at the back of its level.
+    fn requeue_order(
+        &self,
+        book: &mut OrderBook,
+        order: &mut Order,
+        new_price: Decimal,
+        policy: &dyn MatchingPolicy,
+    ) -> Result<Vec<Trade>, OrderBookError> {
+        Self::pull_order(book, order, new_price);
+        self.execute_order(book, order, policy)
+    }
+
+    /// Take a resting order out of the book and re-time it as a new arrival at `new_price`
+    fn pull_order(book: &mut OrderBook, order: &mut Order, new_price: Decimal) {
+        let current_price = order.price.expect("Resting order must have price");
+        // Remaining quantity as it sits in the level, before any quantity change
+        let resting_qty = book
+            .orders
+            .get(&order.id)
+            .map(|resting| resting.remaining_quantity())
+            .unwrap_or_else(|| order.remaining_quantity());
+
+        remove_order_from_price_level(book, order.id, current_price, &order.side, resting_qty);
+        book.remove_order(order.id);
+
+        order.price = Some(new_price);
+        order.timestamp = clock::now();
+    }
+
+    /// Move pegged orders to follow the top of book
+    ///
+    /// Reprices follow the amend priority rules: an order whose peg price is
+    /// unchanged keeps its place, an order whose price moves is requeued (and
+    /// may cross). Orders are repriced in time priority; when a reprice trades,
+    /// the reference prices are recomputed before the next order. A bid whose
+    /// owner cannot fund its higher peg price stays where it is. Peg prices
+    /// are on the tick grid and held to the price band as at entry.
+    ///
+    /// Only the book's pegged orders are visited, and nothing is done while
+    /// the reference prices are those of the last pass and no peg has arrived
+    /// since.
+    ///
+    /// Each reprice is journaled as `WalEvent::OrderRepriced`. The price band
+    /// and funds decisions depend on state the log does not carry, so replay
+    /// does not reprice: it applies the journaled reprices.
+    fn reprice_pegged_orders(&self, book: &mut OrderBook, policy: &dyn MatchingPolicy) -> Result<Vec<Trade>, OrderBookError> {
+        if is_applying_log() || !book.has_pegged_orders() {
+            return Ok(Vec::new());
+        }
+        let mut reference = reference_prices(book);
+        if book.peg_reference() == Some((reference.best_bid, reference.best_ask)) {
+            return Ok(Vec::new());
+        }
+
+        let mut pegged: Vec<_> = book
+            .pegged_order_ids()
+            .filter_map(|order_id| book.get_order(order_id))
+            .map(|order| (order.timestamp, order.id))
+            .collect();
+        pegged.sort();
+
+        let instrument = self.get_instrument(&book.symbol)?;
+        let tick_size = instrument.as_ref().map(|instrument| instrument.tick_size);
+        let mut trades = Vec::new();
+
+        for (_, order_id) in pegged {
+            // An earlier reprice may have filled this order
+            let Some(order) = book.orders.get(&order_id) else {
+                continue;
+            };
+            let Some(target_price) = peg_price(order, &reference, tick_size) else {
+                // No reference: the peg stays where it is
+                continue;
+            };
+
+            // Held to the price band as at entry; a band that rejects leaves the peg where it is
+            let mut order = order.clone();
+            let mut repriced = order.clone();
+            repriced.price = Some(target_price);
+            match self.apply_price_band(&mut repriced, instrument.as_ref()) {
+                Ok(()) => {}
+                Err(OrderBookError::CircuitBreakerRejected(RiskError::OutsidePriceBand { .. })) => continue,
+                Err(e) => return Err(e),
+            }
+            let Some(target_price) = repriced.price.filter(|price| order.price != Some(*price)) else {
+                continue;
+            };
+            match self.resize_funds(book, &repriced) {
+                Ok(()) => {}
+                Err(OrderBookError::InsufficientFunds { .. }) => continue,
+                Err(e) => return Err(e),
+            }
+
+            self.journal(|sequence| WalEvent::OrderRepriced {
+                sequence,
+                timestamp_ns: now_ns(),
+                symbol: book.symbol.clone(),
+                order_id,
+                new_price: target_price,
+            })?;
+            let reprice_trades = self.requeue_order(book, &mut order, target_price, policy)?;
+            // The repriced peg is the aggressor
+            self.notify_trades(Some(order.side), &reprice_trades);
+            if !reprice_trades.is_empty() {
+                reference = reference_prices(book);
+                trades.extend(reprice_trades);
+            }
+        }
+
+        book.set_peg_reference((reference.best_bid, reference.best_ask));
+        Ok(trades)
+    }
+
+    /// Re-apply a journaled peg reprice: requeue the order at `new_price`
+    fn replay_peg_reprice(&self, symbol: &str, order_id: Uuid, new_price: Decimal) -> Result<(), OrderBookError> {
+        let policy = self.matching_policy(symbol)?;
+        let trades = self.with_book(symbol, |book| {
+            let mut order = book.get_order(order_id).cloned().ok_or(OrderBookError::OrderNotFound(order_id))?;
+            let mut repriced = order.clone();
+            repriced.price = Some(new_price);
+            self.resize_funds(book, &repriced)?;
+            self.requeue_order(book, &mut order, new_price, &policy)
+        })?;
+        self.process_trades(&[], &trades)
+    }
+
+    /// Cancel an order
+    pub fn cancel_order(
+        &self,
+        symbol: &str,
+        order_id: Uuid,
+    ) -> Result<Order, OrderBookError> {
+        self.ensure_primary()?;
+        self.check_order_message_rate(order_id)?;
+
+        let policy = self.matching_policy(symbol)?;
+
+        let (order, peg_trades) = self.with_book(symbol, |book| {
+            // Check if order can be cancelled before taking it out of the book
+            let status = book
+                .orders
+                .get(&order_id)
+                .map(|order| order.status)
+                .ok_or(OrderBookError::OrderNotFound(order_id))?;
+            if status == OrderStatus::Filled || status == OrderStatus::Cancelled {
+                return Err(OrderBookError::OrderNotActive(order_id));
+            }
+
+            let mut order = book
+                .remove_order(order_id)
+                .ok_or(OrderBookError::OrderNotFound(order_id))?;
+
+            // Remove from price level using helper function
+            if let Some(price) = order.price {
+                remove_order_from_price_level(
+                    book,
+                    order_id,
+                    price,
+                    &order.side,
+                    order.remaining_quantity(),
+                );
+            }
+
+            // Update order status
+            order.status = OrderStatus::Cancelled;
+            self.release_funds([order_id])?;
+
+            self.journal(|sequence| WalEvent::OrderCancelled {
+                sequence,
+                timestamp_ns: now_ns(),
+                order_id,
+                symbol: symbol.to_string(),
+            })?;
+            if is_engine_initiated() {
+                self.notify(
+                    symbol,
+                    EngineNotice::OrderCancelled {
+                        order: order.clone(),
+                        reason: CancelReason::Contingent,
+                    },
+                );
+            }
+
+            // Pegged orders stay where they are until a call auction has uncrossed
+            let peg_trades = if self.in_auction(symbol)? {
+                Vec::new()
+            } else {
+                self.reprice_pegged_orders(book, &policy)?
+            };
+            self.journal_trades(&peg_trades)?;
+
+            Ok((order, peg_trades))
+        })?;
+
+        self.notify_leg_cancelled(order_id)?;
+        self.process_trades(&[], &peg_trades)?;
+
+        Ok(order)
+    }
+
+    /// Get order status
+    pub fn get_order(&self, symbol: &str, order_id: Uuid) -> Result<Order, OrderBookError> {
+        self.with_book(symbol, |book| {
+            book.orders
+                .get(&order_id)
+                .cloned()
+                .ok_or(OrderBookError::OrderNotFound(order_id))
+        })
+    }
+
+    /// Get a snapshot of the order book for a symbol
+    pub fn get_order_book(&self, symbol: &str) -> Result<OrderBook, OrderBookError> {
+        self.with_book(symbol, |book| Ok(book.clone()))
+    }
+
+    /// Get the best bid and best ask of a symbol without copying its book
+    pub fn get_top_of_book(&self, symbol: &str) -> Result<(Option<Decimal>, Option<Decimal>), OrderBookError> {
+        self.with_book(symbol, |book| Ok((book.get_best_bid(), book.get_best_ask())))
+    }
+
+    /// Get the mid price of a symbol's book (`None` unless both sides have orders)
+    pub fn get_mid_price(&self, symbol: &str) -> Result<Option<Decimal>, OrderBookError> {
+        self.with_book(symbol, |book| Ok(book.get_mid_price()))
+    }
+
+    /// Get recent trades for a symbol
+    pub fn get_recent_trades(&self, symbol: &str, limit: usize) -> Result<Vec<Trade>, OrderBookError> {
+        self.with_book(symbol, |book| Ok(book.get_recent_trades(limit)))
+    }
+
+    /// Get the trades of a symbol from position `from` of its trade history on
+    pub fn get_trades_since(&self, symbol: &str, from: usize) -> Result<Vec<Trade>, OrderBookError> {
+        self.with_book(symbol, |book| Ok(book.trades.get(from..).map(<[Trade]>::to_vec).unwrap_or_default()))
+    }
+
+    /// Get all active symbols
+    pub fn get_symbols(&self) -> Result<Vec<String>, OrderBookError> {
+        let books = self.books.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        Ok(books.keys().cloned().collect())
+    }
+
+    /// Get total number of active orders across all symbols
+    pub fn get_total_active_orders(&self) -> Result<usize, OrderBookError> {
+        self.all_books()?
+            .iter()
+            .map(|book| Ok(Self::lock_book(book)?.orders.len()))
+            .sum()
+    }
+
+    /// Get total number of trades across all symbols
+    pub fn get_total_trades(&self) -> Result<usize, OrderBookError> {
+        self.all_books()?
+            .iter()
+            .map(|book| Ok(Self::lock_book(book)?.trades.len()))
+            .sum()
+    }
+
+    /// Get total volume across all symbols
+    pub fn get_total_volume(&self) -> Result<Decimal, OrderBookError> {
+        self.all_books()?
+            .iter()
+            .map(|book| Ok(Self::lock_book(book)?.trades.iter().map(|trade| trade.value()).sum::<Decimal>()))
+            .sum()
+    }
+
+    /// Get total fees collected across all symbols, net of maker rebates
+    ///
+    /// Fees charged in a base asset are counted at their trade's price.
+    pub fn get_total_fees(&self) -> Result<Decimal, OrderBookError> {
+        self.all_books()?
+            .iter()
+            .map(|book| Ok(Self::lock_book(book)?.trades.iter().map(|trade| trade.total_fees()).sum::<Decimal>()))
+            .sum()
+    }
+
+    // ============================================================================
+    // Stop Order Management
+    // ============================================================================
+
+    /// Add a stop order
+    pub fn add_stop_order(&self, stop: StopOrder) -> Result<(), OrderBookError> {
+        self.ensure_primary()?;
+
+        self.check_symbol(&stop.symbol)?;
+
+        let mut trigger_engine = self.trigger_engine.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+        self.journal(|sequence| WalEvent::StopOrderSubmitted {
+            sequence,
+            timestamp_ns: now_ns(),
+            stop: stop.clone(),
+        })?;
+        trigger_engine.add_stop_order(stop);
+        Ok(())
+    }
+
+    /// Cancel a stop order
+    pub fn cancel_stop_order(&self, order_id: Uuid) -> Result<StopOrder, OrderBookError> {
+        self.ensure_primary()?;
+
+        let stop = {
+            let mut trigger_engine = self.trigger_engine.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+            let stop = trigger_engine
+                .cancel_stop_order(order_id)
+                .ok_or(OrderBookError::OrderNotFound(order_id))?;
+            self.journal(|sequence| WalEvent::StopOrderCancelled {
+                sequence,
+                timestamp_ns: now_ns(),
+                order_id,
+            })?;
+            stop
+        };
+
+        self.notify_leg_cancelled(order_id)?;
+
+        Ok(stop)
+    }
+
+    /// Get a stop order by ID
+    pub fn get_stop_order(&self, order_id: Uuid) -> Result<Option<StopOrder>, OrderBookError> {
+        let trigger_engine = self.trigger_engine.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        Ok(trigger_engine.get_stop_order(order_id).cloned())
+    }
+
+    /// Get all stop orders for a symbol
+    pub fn get_stop_orders_by_symbol(&self, symbol: &str) -> Result<Vec<StopOrder>, OrderBookError> {
+        let trigger_engine = self.trigger_engine.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        Ok(trigger_engine
+            .get_stop_orders_by_symbol(symbol)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    /// Get the last trade price seen by the trigger engine for a symbol
+    pub fn get_last_trade_price(&self, symbol: &str) -> Result<Option<Decimal>, OrderBookError> {
+        let trigger_engine = self.trigger_engine.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        Ok(trigger_engine.get_last_trade_price(symbol))
+    }
+
+    /// Get total number of active stop orders
+    pub fn get_total_stop_orders(&self) -> Result<usize, OrderBookError> {
+        let trigger_engine = self.trigger_engine.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        Ok(trigger_engine.get_total_stop_orders())
+    }
+
+    // ============================================================================
+    // Contingent Order Management (OCO / OTO / Bracket)
+    // ============================================================================
+
+    /// Submit a contingent order group, journaled as `WalEvent::ContingentGroupSubmitted`
+    ///
+    /// Groups with an entry order place the entry first; its legs are released
+    /// once it is completely filled. OCO groups place both legs immediately.
+    /// If a leg cannot be placed the whole group is cancelled and the error returned.
+    pub fn submit_contingent_order(&self, group: ContingentGroup) -> Result<ContingentGroup, OrderBookError> {
+        self.ensure_primary()?;
+
+        for leg in group.all_legs() {
+            if let LegOrder::Order(order) = &leg.order {
+                self.check_symbol(&order.symbol)?;
+                validate_order(order, self.get_instrument(&order.symbol)?.as_ref())?;
+            }
+        }
+
+        let group_id = group.id;
+        let actions = {
+            let mut contingent = self.contingent.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+            let actions = contingent.register(group.clone())?;
+            // Journaled ahead of its legs, so replay knows the group before their fills
+            self.journal(|sequence| WalEvent::ContingentGroupSubmitted {
+                sequence,
+                timestamp_ns: now_ns(),
+                group,
+            })?;
+            actions
+        };
+
+        for action in actions {
+            if let Err(e) = self.apply_contingent_action(action) {
+                let _ = self.cancel_contingent_order(group_id);
+                return Err(e);
+            }
+        }
+
+        self.get_contingent_order(group_id)
+    }
+
+    /// Get a contingent order group by ID
+    pub fn get_contingent_order(&self, group_id: Uuid) -> Result<ContingentGroup, OrderBookError> {
+        let contingent = self.contingent.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        contingent
+            .get_group(group_id)
+            .cloned()
+            .ok_or(OrderBookError::OrderNotFound(group_id))
+    }
+
+    /// Cancel a contingent order group and every working leg in it, journaled
+    /// as `WalEvent::ContingentGroupCancelled`
+    pub fn cancel_contingent_order(&self, group_id: Uuid) -> Result<ContingentGroup, OrderBookError> {
+        self.ensure_primary()?;
+
+        let actions = {
+            let mut contingent = self.contingent.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+            let actions = contingent.cancel_group(group_id)?;
+            let symbol = contingent.get_group(group_id).map(|group| group.symbol.clone()).unwrap_or_default();
+            self.journal(|sequence| WalEvent::ContingentGroupCancelled {
+                sequence,
+                timestamp_ns: now_ns(),
+                symbol,
+                group_id,
+            })?;
+            actions
+        };
+
+        self.execute_contingent_actions(actions)?;
+
+        self.get_contingent_order(group_id)
+    }
+
+    // ============================================================================
+    // Trading Sessions and Order Expiry
+    // ============================================================================
+
+    /// Set the trading session calendar of a symbol
+    pub fn set_trading_calendar(&self, symbol: &str, calendar: TradingCalendar) -> Result<(), OrderBookError> {
+        calendar.validate().map_err(OrderBookError::InvalidTradingCalendar)?;
+
+        let mut calendars = self.calendars.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+        calendars.insert(symbol.to_string(), calendar);
+        Ok(())
+    }
+
+    /// Get the trading session calendar of a symbol
+    pub fn get_trading_calendar(&self, symbol: &str) -> Result<TradingCalendar, OrderBookError> {
+        let calendars = self.calendars.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        Ok(calendars.get(symbol).cloned().unwrap_or_default())
+    }
+
+    /// Expire every resting order whose `expire_time` is at or before `now`
+    ///
+    /// Covers GTD orders and DAY orders (which get the session close as their
+    /// expire time on entry). The expiries of each symbol are journaled as one
+    /// `WalEvent::OrderExpired`. Returns the expired orders.
+    pub fn expire_orders(&self, now: DateTime<Utc>) -> Result<Vec<Order>, OrderBookError> {
+        self.ensure_primary()?;
+
+        let mut expired = Vec::new();
+        for symbol in self.get_symbols()? {
+            let is_due = |order: &Order| order.expire_time.is_some_and(|expire_time| expire_time <= now);
+            expired.extend(self.expire_symbol_orders(&symbol, is_due)?);
+        }
+
+        Ok(expired)
+    }
+
+    /// Expire the resting orders of a symbol that `is_due` picks as one batch:
+    /// all of them leave the book before the pegs are repriced, the same live
+    /// and on replay
+    fn expire_symbol_orders(&self, symbol: &str, is_due: impl Fn(&Order) -> bool) -> Result<Vec<Order>, OrderBookError> {
+        let policy = self.matching_policy(symbol)?;
+
+        let (expired, peg_trades) = self.with_book(symbol, |book| {
+            let expired_ids: Vec<Uuid> = book
+                .orders
+                .values()
+                .filter(|order| is_due(order))
+                .map(|order| order.id)
+                .collect();
+
+            if expired_ids.is_empty() {
+                return Ok((Vec::new(), Vec::new()));
+            }
+
+            let mut expired = Vec::with_capacity(expired_ids.len());
+            for order_id in expired_ids {
+                let Some(mut order) = book.remove_order(order_id) else {
+                    continue;
+                };
+                if let Some(price) = order.price {
+                    remove_order_from_price_level(book, order_id, price, &order.side, order.remaining_quantity());
+                }
+                order.status = OrderStatus::Expired;
+                self.notify(
+                    symbol,
+                    EngineNotice::OrderCancelled {
+                        order: order.clone(),
+                        reason: CancelReason::Expired,
+                    },
+                );
+                expired.push(order);
+            }
+            self.release_funds(expired.iter().map(|order| order.id))?;
+
+            self.journal(|sequence| WalEvent::OrderExpired {
+                sequence,
+                timestamp_ns: now_ns(),
+                symbol: symbol.to_string(),
+                order_ids: expired.iter().map(|order| order.id).collect(),
+            })?;
+
+            let peg_trades = if self.in_auction(symbol)? {
+                Vec::new()
+            } else {
+                self.reprice_pegged_orders(book, &policy)?
+            };
+            self.journal_trades(&peg_trades)?;
+
+            Ok((expired, peg_trades))
+        })?;
+
+        for order in &expired {
+            self.notify_leg_cancelled(order.id)?;
+        }
+        if !expired.is_empty() {
+            self.process_trades(&[], &peg_trades)?;
+        }
+
+        Ok(expired)
+    }
+
+    // ============================================================================
+    // Accounts
+    // ============================================================================
+
+    /// Lock the accounts (`None` when the engine keeps no accounts)
+    fn lock_accounts(&self) -> Result<Option<MutexGuard<'_, Accounts>>, OrderBookError> {
+        self.accounts
+            .as_ref()
+            .map(|accounts| accounts.lock().map_err(|e| OrderBookError::LockError(format!("Failed to acquire accounts lock: {}", e))))
+            .transpose()
+    }
+
+    fn accounts_required(&self) -> Result<MutexGuard<'_, Accounts>, OrderBookError> {
+        self.lock_accounts()?
+            .ok_or_else(|| OrderBookError::InvalidCommand("Accounts are not enabled".to_string()))
+    }
+
+    /// Reserve what an order can spend (no-op without accounts) and count it
+    /// as working towards its owner's risk limits
+    fn reserve_funds(&self, book: &OrderBook, order: &Order) -> Result<(), OrderBookError> {
+        if let Some(mut accounts) = self.lock_accounts()? {
+            let (asset, amount) = required_funds(order, book, &*self.read_fees()?);
+            accounts.reserve(order.id, &order.user_id, &asset, amount)?;
+        }
+        self.lock_risk()?.track_order(order);
+        Ok(())
+    }
+
+    /// Resize a resting order's reservation to what `order` (its amended
+    /// form) can spend
+    fn resize_funds(&self, book: &OrderBook, order: &Order) -> Result<(), OrderBookError> {
+        if let Some(mut accounts) = self.lock_accounts()? {
+            let (_, amount) = required_funds(order, book, &*self.read_fees()?);
+            accounts.resize(order.id, amount)?;
+        }
+        self.lock_risk()?.track_order(order);
+        Ok(())
+    }
+
+    /// Return the unspent reservations of orders that left the book
+    fn release_funds(&self, order_ids: impl IntoIterator<Item = Uuid>) -> Result<(), OrderBookError> {
+        let mut accounts = self.lock_accounts()?;
+        let mut risk = self.lock_risk()?;
+        for order_id in order_ids {
+            if let Some(accounts) = accounts.as_mut() {
+                accounts.release(order_id);
+            }
+            risk.untrack_order(order_id);
+        }
+        Ok(())
+    }
+
+    /// Settle trades between their accounts and release the reservations of
+    /// every order involved (trade participants and `others`) that is no
+    /// longer in the book
+    ///
+    /// The risk engine books the fills and follows what is left of the
+    /// involved orders, with or without accounts.
+    fn settle_trades(
+        &self,
+        book: &OrderBook,
+        trades: &[Trade],
+        taker: Option<Uuid>,
+        others: impl IntoIterator<Item = Uuid>,
+    ) -> Result<(), OrderBookError> {
+        let mut accounts = self.lock_accounts()?;
+        let mut risk = self.lock_risk()?;
+
+        for trade in trades {
+            if let Some(accounts) = accounts.as_mut() {
+                accounts.settle(trade, taker);
+            }
+            risk.record_trade(trade);
+        }
+        let involved = trades
+            .iter()
+            .flat_map(|trade| [trade.buyer_order_id, trade.seller_order_id])
+            .chain(others);
+        for order_id in involved {
+            match book.orders.get(&order_id) {
+                Some(order) => risk.track_order(order),
+                None => {
+                    if let Some(accounts) = accounts.as_mut() {
+                        accounts.release(order_id);
+                    }
+                    risk.untrack_order(order_id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Credit an account, journaled as `WalEvent::Deposited`
+    pub fn deposit(&self, user_id: &str, asset: &str, amount: Decimal) -> Result<Balance, OrderBookError> {
+        self.ensure_primary()?;
+
+        let mut accounts = self.accounts_required()?;
+        let balance = accounts.deposit(user_id, asset, amount)?;
+        self.journal(|sequence| WalEvent::Deposited {
+            sequence,
+            timestamp_ns: now_ns(),
+            user_id: user_id.to_string(),
+            asset: asset.to_string(),
+            amount,
+        })?;
+        Ok(balance)
+    }
+
+    /// Debit an account's available balance, journaled as `WalEvent::Withdrawn`
+    pub fn withdraw(&self, user_id: &str, asset: &str, amount: Decimal) -> Result<Balance, OrderBookError> {
+        self.ensure_primary()?;
+
+        let mut accounts = self.accounts_required()?;
+        let balance = accounts.withdraw(user_id, asset, amount)?;
+        self.journal(|sequence| WalEvent::Withdrawn {
+            sequence,
+            timestamp_ns: now_ns(),
+            user_id: user_id.to_string(),
+            asset: asset.to_string(),
+            amount,
+        })?;
+        Ok(balance)
+    }
+
+    /// Get every asset balance of an account
+    pub fn get_balances(&self, user_id: &str) -> Result<BTreeMap<String, Balance>, OrderBookError> {
+        Ok(self.accounts_required()?.balances(user_id))
+    }
+
+    /// Get the funds reserved for an open order (`None` if it has no reservation)
+    pub fn get_reserved_funds(&self, order_id: Uuid) -> Result<Option<Decimal>, OrderBookError> {
+        Ok(self.accounts_required()?.reserved_for(order_id))
+    }
+
+    /// Sum of every account's balance of an asset, including collected fees
+    pub fn get_asset_total(&self, asset: &str) -> Result<Decimal, OrderBookError> {
+        Ok(self.accounts_required()?.total(asset))
+    }
+
+    // ============================================================================
+    // Call Auctions
+    // ============================================================================
+
+    /// Check if a symbol is in a call auction
+    fn in_auction(&self, symbol: &str) -> Result<bool, OrderBookError> {
+        let auctions = self.auctions.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        Ok(auctions.contains_key(symbol))
+    }
+
+    /// Collect an order into a running call auction without matching it
+    ///
+    /// IOC / FOK orders cannot wait for the uncross and pegged orders have no
+    /// reference price while the book is crossed, so both are rejected. So are
+    /// market bids when accounts are kept: nothing bounds what they can spend.
+    fn add_auction_order(&self, book: &mut OrderBook, order: &Order, instrument: Option<&Instrument>) -> Result<(), OrderBookError> {
+        if matches!(order.time_in_force, TimeInForce::IOC | TimeInForce::FOK) {
+            return Err(OrderBookError::InvalidTradingPhase(format!(
+                "{:?} orders are not accepted during a call auction",
+                order.time_in_force
+            )));
+        }
+        if order.order_type.is_pegged() {
+            return Err(OrderBookError::InvalidTradingPhase(
+                "Pegged orders are not accepted during a call auction".to_string(),
+            ));
+        }
+
+        validate_order(order, instrument)?;
+
+        if book.check_order_in_book(order.id) {
+            return Err(OrderBookError::DuplicateOrder(order.id));
+        }
+
+        if self.has_accounts() && order.side == OrderSide::Buy && order.price.is_none() {
+            return Err(OrderBookError::InvalidTradingPhase(
+                "Market buy orders are not accepted during a call auction".to_string(),
+            ));
+        }
+        self.reserve_funds(book, order)?;
+
+        Self::rest_order(book, order);
+        Ok(())
+    }
+
+    /// Put a symbol into a call auction
+    ///
+    /// Orders for the symbol accumulate without matching until `end_auction`
+    /// uncrosses the book. The last trade price is the reference price for the
+    /// uncross tie-break.
+    pub fn start_auction(&self, symbol: &str, kind: AuctionKind) -> Result<AuctionState, OrderBookError> {
+        self.ensure_primary()?;
+
+        let reference_price = self.get_last_trade_price(symbol)?;
+
+        // Held so that no order matches while the phase changes
+        let book = self.get_or_create_book(symbol)?;
+        let book = Self::lock_book(&book)?;
+
+        let mut state = {
+            let mut auctions = self.auctions.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+            if let Some(running) = auctions.get(symbol) {
+                return Err(OrderBookError::InvalidTradingPhase(format!(
+                    "{} is already in a {:?} auction",
+                    symbol, running.kind
+                )));
+            }
+            let state = AuctionState {
+                symbol: symbol.to_string(),
+                kind,
+                started_at: clock::now(),
+                reference_price,
+                indicative: None,
+            };
+            auctions.insert(symbol.to_string(), state.clone());
+            self.journal(|sequence| WalEvent::AuctionStarted {
+                sequence,
+                timestamp_ns: now_ns(),
+                symbol: symbol.to_string(),
+                kind,
+            })?;
+            state
+        };
+
+        state.indicative = compute_uncross(&book, state.reference_price);
+        Ok(state)
+    }
+
+    // ============================================================================
+    // Circuit breakers
+    // ============================================================================
+
+    fn lock_circuit_breakers(&self) -> Result<MutexGuard<'_, HashMap<String, CircuitBreaker>>, OrderBookError> {
+        self.circuit_breakers
+            .lock()
+            .map_err(|e| OrderBookError::LockError(format!("Failed to acquire circuit breaker lock: {}", e)))
+    }
+
+    /// Move a symbol whose halt has expired into its reopening auction
+    ///
+    /// Called before a symbol's book is locked, since starting the auction
+    /// locks it. A follower only reports the expiry; the auction reaches it
+    /// through the log.
+    fn refresh_circuit_breaker(&self, symbol: &str) -> Result<(), OrderBookError> {
+        if is_applying_log() {
+            return Ok(());
+        }
+
+        let reopen = {
+            let mut breakers = self.lock_circuit_breakers()?;
+            let breaker = breakers.entry(symbol.to_string()).or_default();
+            breaker.is_trading_allowed() && breaker.needs_reopening_auction()
+        };
+        if !reopen || self.is_standby() || self.in_auction(symbol)? {
+            return Ok(());
+        }
+
+        match self.start_auction(symbol, AuctionKind::Reopening) {
+            // Another request started it first
+            Ok(_) | Err(OrderBookError::InvalidTradingPhase(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Check an order against its symbol's circuit breaker (rejected while
+    /// the symbol is halted, or when too large)
+    fn check_circuit_breaker(&self, order: &Order) -> Result<(), OrderBookError> {
+        if is_applying_log() {
+            return Ok(());
+        }
+
+        match self.lock_circuit_breakers()?.get(&order.symbol) {
+            Some(breaker) => Ok(breaker.validate_order(order)?),
+            None => Ok(()),
+        }
+    }
+
+    /// Hold an order to its symbol's price band: a limit order is rejected,
+    /// or repriced to the band's edge on the instrument's tick grid; a market
+    /// order is capped at the edge
+    fn apply_price_band(&self, order: &mut Order, instrument: Option<&Instrument>) -> Result<(), OrderBookError> {
+        if is_applying_log() {
+            return Ok(());
+        }
+
+        match self.lock_circuit_breakers()?.get(&order.symbol) {
+            Some(breaker) => Ok(breaker.apply_price_band(order, instrument.map(|instrument| instrument.tick_size))?),
+            None => Ok(()),
+        }
+    }
+
+    /// Feed trades to their symbol's circuit breaker, which may halt it
+    fn feed_circuit_breaker(&self, trades: &[Trade]) -> Result<(), OrderBookError> {
+        if is_applying_log() || trades.is_empty() {
+            return Ok(());
+        }
+
+        let mut breakers = self.lock_circuit_breakers()?;
+        for trade in trades {
+            let breaker = breakers.entry(trade.symbol.clone()).or_default();
+            if breaker.get_state() == CircuitState::Halted {
+                continue;
+            }
+            if let Some(reason) = breaker.on_trade(trade.price, trade.timestamp) {
+                tracing::warn!("Circuit breaker halted {} ({:?}) at {}", trade.symbol, reason, trade.price);
+            }
+        }
+        Ok(())
+    }
+
+    /// Status of a symbol's circuit breaker, with the price band on the tick grid
+    fn circuit_breaker_status(&self, symbol: &str) -> Result<CircuitBreakerStatus, OrderBookError> {
+        self.refresh_circuit_breaker(symbol)?;
+        let tick_size = self.get_instrument(symbol)?.map(|instrument| instrument.tick_size);
+
+        let breakers = self.lock_circuit_breakers()?;
+        let breaker = breakers.get(symbol).ok_or_else(|| OrderBookError::InvalidSymbol(symbol.to_string()))?;
+        let mut status = breaker.get_status();
+        status.price_band = breaker.price_band(tick_size);
+        Ok(status)
+    }
+
+    /// Get the circuit breaker status of a symbol
+    ///
+    /// A halt that has expired moves the symbol into its reopening auction.
+    pub fn get_circuit_breaker(&self, symbol: &str) -> Result<CircuitBreakerStatus, OrderBookError> {
+        self.circuit_breaker_status(symbol)
+    }
+
+    /// Get the circuit breaker status of every symbol with a book
+    pub fn get_circuit_breakers(&self) -> Result<BTreeMap<String, CircuitBreakerStatus>, OrderBookError> {
+        self.get_symbols()?
+            .into_iter()
+            .map(|symbol| Ok((symbol.clone(), self.circuit_breaker_status(&symbol)?)))
+            .collect()
+    }
+
+    /// Set the circuit breaker and price band configuration of a symbol
+    ///
+    /// The breaker keeps its state and recent prices.
+    pub fn set_circuit_breaker_config(&self, symbol: &str, config: CircuitBreakerConfig) -> Result<CircuitBreakerStatus, OrderBookError> {
+        config.validate().map_err(OrderBookError::InvalidCircuitBreakerConfig)?;
+        self.lock_circuit_breakers()?
+            .entry(symbol.to_string())
+            .or_default()
+            .set_config(config);
+        self.circuit_breaker_status(symbol)
+    }
+
+    /// Get the circuit breaker configuration of a symbol
+    pub fn get_circuit_breaker_config(&self, symbol: &str) -> Result<CircuitBreakerConfig, OrderBookError> {
+        Ok(self
+            .lock_circuit_breakers()?
+            .entry(symbol.to_string())
+            .or_default()
+            .get_config()
+            .clone())
+    }
+
+    /// Halt trading in a symbol for `duration_minutes`
+    ///
+    /// Orders are rejected until the halt expires and the symbol reopens
+    /// through a call auction, or until `resume_trading`. Resting orders can
+    /// still be cancelled.
+    pub fn halt_trading(&self, symbol: &str, duration_minutes: i64) -> Result<CircuitBreakerStatus, OrderBookError> {
+        self.ensure_primary()?;
+        if duration_minutes < 0 {
+            return Err(OrderBookError::InvalidCommand("Halt duration must not be negative".to_string()));
+        }
+        self.lock_circuit_breakers()?
+            .entry(symbol.to_string())
+            .or_default()
+            .manual_halt(duration_minutes);
+        self.circuit_breaker_status(symbol)
+    }
+
+    /// Lift a symbol's halt at once and return it to normal trading
+    ///
+    /// A reopening auction that is already running keeps running until it
+    /// uncrosses.
+    pub fn resume_trading(&self, symbol: &str) -> Result<CircuitBreakerStatus, OrderBookError> {
+        self.ensure_primary()?;
+        self.lock_circuit_breakers()?
+            .entry(symbol.to_string())
+            .or_default()
+            .resume();
+        self.circuit_breaker_status(symbol)
+    }
+
+    /// Resume trading in a symbol after a circuit breaker halt
+    ///
+    /// Once the halt has expired the symbol enters a reopening auction instead
+    /// of continuous trading. The auction starts with the first order or
+    /// status request after the expiry, or with `end_reopening_auctions`;
+    /// this starts it if need be and returns it. The auction uncrosses after
+    /// the breaker's `reopening_auction_minutes` (or on `end_auction`), which
+    /// hands the uncross price back to the breaker.
+    pub fn reopen_after_halt(&self, symbol: &str) -> Result<AuctionState, OrderBookError> {
+        self.refresh_circuit_breaker(symbol)?;
+        let state = self.lock_circuit_breakers()?.get(symbol).map(CircuitBreaker::get_state);
+        if state != Some(CircuitState::CoolingOff) {
+            return Err(OrderBookError::InvalidTradingPhase(format!(
+                "{} is not ready to reopen (circuit breaker state {:?})",
+                symbol, state
+            )));
+        }
+
+        self.get_auction(symbol)
+    }
+
+    /// Start the reopening auctions of symbols whose halt has expired, and
+    /// uncross those that have run for their breaker's
+    /// `reopening_auction_minutes` as of `now`
+    ///
+    /// Driven once per second by the reopening timer. Returns the results of
+    /// the auctions it ended.
+    pub fn end_reopening_auctions(&self, now: DateTime<Utc>) -> Result<Vec<AuctionResult>, OrderBookError> {
+        self.ensure_primary()?;
+
+        let symbols: Vec<String> = self.lock_circuit_breakers()?.keys().cloned().collect();
+        for symbol in &symbols {
+            self.refresh_circuit_breaker(symbol)?;
+        }
+
+        let reopening: Vec<(String, DateTime<Utc>)> = {
+            let auctions = self.auctions.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+            auctions
+                .values()
+                .filter(|state| state.kind == AuctionKind::Reopening)
+                .map(|state| (state.symbol.clone(), state.started_at))
+                .collect()
+        };
+        let due: Vec<String> = {
+            let breakers = self.lock_circuit_breakers()?;
+            reopening
+                .into_iter()
+                .filter(|(symbol, started_at)| {
+                    let minutes = breakers
+                        .get(symbol)
+                        .map_or(CircuitBreakerConfig::default().reopening_auction_minutes, |breaker| {
+                            breaker.get_config().reopening_auction_minutes
+                        });
+                    *started_at + chrono::Duration::minutes(minutes) <= now
+                })
+                .map(|(symbol, _)| symbol)
+                .collect()
+        };
+
+        let mut results = Vec::with_capacity(due.len());
+        for symbol in due {
+            match self.end_auction(&symbol) {
+                Ok(result) => results.push(result),
+                // Ended by an admin in the meantime
+                Err(OrderBookError::InvalidTradingPhase(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(results)
+    }
+
+    /// Get a running call auction with its current indicative uncross
+    pub fn get_auction(&self, symbol: &str) -> Result<AuctionState, OrderBookError> {
+        let mut state = {
+            let auctions = self.auctions.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+            auctions
+                .get(symbol)
+                .cloned()
+                .ok_or_else(|| OrderBookError::InvalidTradingPhase(format!("{} is not in a call auction", symbol)))?
+        };
+
+        state.indicative = self.with_book(symbol, |book| Ok(compute_uncross(book, state.reference_price)))?;
+        Ok(state)
+    }
+
+    /// Get all running call auctions with their indicative uncross
+    pub fn get_auctions(&self) -> Result<Vec<AuctionState>, OrderBookError> {
+        let states: Vec<AuctionState> = {
+            let auctions = self.auctions.read().map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+            auctions.values().cloned().collect()
+        };
+
+        states
+            .into_iter()
+            .map(|mut state| {
+                state.indicative = self.with_book(&state.symbol, |book| Ok(compute_uncross(book, state.reference_price)))?;
+                Ok(state)
+            })
+            .collect()
+    }
+
+    /// End a call auction: uncross the book at a single price and return the
+    /// symbol to continuous trading
+    ///
+    /// Market orders the uncross does not fill are cancelled. Unfilled limit
+    /// orders keep resting, and the book is no longer crossed afterwards.
+    pub fn end_auction(&self, symbol: &str) -> Result<AuctionResult, OrderBookError> {
+        self.ensure_primary()?;
+
+        let policy = self.matching_policy(symbol)?;
+
+        let (state, uncross, trades, unfilled_market_orders, peg_trades) = self.with_book(symbol, |book| {
+            let state = {
+                let mut auctions = self.auctions.write().map_err(|e| OrderBookError::LockError(format!("Failed to acquire write lock: {}", e)))?;
+                auctions
+                    .remove(symbol)
+                    .ok_or_else(|| OrderBookError::InvalidTradingPhase(format!("{} is not in a call auction", symbol)))?
+            };
+
+            let uncross = compute_uncross(book, state.reference_price);
+            let trades = match &uncross {
+                Some(uncross) => execute_uncross(book, uncross.price, uncross.volume, &*self.read_fees()?),
+                None => Vec::new(),
+            };
+            self.record_fee_volume(&trades)?;
+            self.feed_circuit_breaker(&trades)?;
+            // No aggressor in an auction
+            self.notify_trades(None, &trades);
+            if state.kind == AuctionKind::Reopening && !is_applying_log() {
+                self.lock_circuit_breakers()?
+                    .entry(symbol.to_string())
+                    .or_default()
+                    .complete_reopening(uncross.as_ref().map(|uncross| uncross.price));
+            }
+
+            let unfilled_market_orders: Vec<Uuid> = book
+                .orders
+                .values()
+                .filter(|order| order.price.is_none())
+                .map(|order| order.id)
+                .collect();
+            for order_id in &unfilled_market_orders {
+                if let Some(mut order) = book.remove_order(*order_id) {
+                    order.status = OrderStatus::Cancelled;
+                    self.notify(
+                        symbol,
+                        EngineNotice::OrderCancelled {
+                            order,
+                            reason: CancelReason::AuctionUnfilled,
+                        },
+                    );
+                }
+            }
+            // No aggressor in an auction
+            self.settle_trades(book, &trades, None, unfilled_market_orders.iter().copied())?;
+
+            self.journal(|sequence| WalEvent::AuctionEnded {
+                sequence,
+                timestamp_ns: now_ns(),
+                symbol: symbol.to_string(),
+            })?;
+
+            let peg_trades = self.reprice_pegged_orders(book, &policy)?;
+            self.journal_trades(trades.iter().chain(&peg_trades))?;
+            Ok((state, uncross, trades, unfilled_market_orders, peg_trades))
+        })?;
+
+        for order_id in unfilled_market_orders {
+            self.notify_leg_cancelled(order_id)?;
+        }
+        self.process_trades(&trades, &peg_trades)?;
+
+        Ok(AuctionResult {
+            symbol: symbol.to_string(),
+            kind: state.kind,
+            uncross,
+            trades,
+        })
+    }
+}
+
+impl Default for OrderBookEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::OrderType;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_add_and_match_order() {
+        let engine = OrderBookEngine::new();
+
+        // Add a sell order
+        let sell_order = Order::new(
+            "AAPL".to_string(),
+            OrderSide::Sell,
+            OrderType::Limit,
+            Some(dec!(150.00)),
             dec!(100),
             "seller1".to_string(),
         );
 
-        let (order, trades) = engine.add_order(sell_order).unwrap();
-        assert_eq!(trades.len(), 0);
-        assert_eq!(order.status, OrderStatus::New);
+        let (order, trades) = engine.add_order(sell_order).unwrap();
+        assert_eq!(trades.len(), 0);
+        assert_eq!(order.status, OrderStatus::New);
+
+        // Add a matching buy order
+        let buy_order = Order::new(
+            "AAPL".to_string(),
+            OrderSide::Buy,
+            OrderType::Limit,
+            Some(dec!(150.00)),
+            dec!(50),
+            "buyer1".to_string(),
+        );
+
+        let (order, trades) = engine.add_order(buy_order).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].quantity, dec!(50));
+        assert_eq!(order.status, OrderStatus::Filled);
+    }
+
+    #[test]
+    fn test_cancel_order() {
+        let engine = OrderBookEngine::new();
+
+        // Add an order
+        let order = Order::new(
+            "AAPL".to_string(),
+            OrderSide::Buy,
+            OrderType::Limit,
+            Some(dec!(150.00)),
+            dec!(100),
+            "user1".to_string(),
+        );
+
+        let order_id = order.id;
+        let (_, trades) = engine.add_order(order).unwrap();
+        assert_eq!(trades.len(), 0);
+
+        // Cancel the order
+        let cancelled = engine.cancel_order("AAPL", order_id).unwrap();
+        assert_eq!(cancelled.status, OrderStatus::Cancelled);
+
+        // Try to get cancelled order
+        let result = engine.get_order("AAPL", order_id);
+        assert!(result.is_err());
+    }
+
+    fn limit_order(side: OrderSide, price: Decimal, quantity: Decimal, user_id: &str) -> Order {
+        Order::new(
+            "AAPL".to_string(),
+            side,
+            OrderType::Limit,
+            Some(price),
+            quantity,
+            user_id.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_amend_quantity_down_keeps_priority() {
+        let engine = OrderBookEngine::new();
+
+        let first = limit_order(OrderSide::Sell, dec!(150.00), dec!(100), "seller1");
+        let second = limit_order(OrderSide::Sell, dec!(150.00), dec!(100), "seller2");
+        let first_id = first.id;
+        engine.add_order(first).unwrap();
+        engine.add_order(second).unwrap();
+
+        let (amended, trades) = engine.amend_order("AAPL", first_id, None, Some(dec!(40))).unwrap();
+        assert!(trades.is_empty());
+        assert_eq!(amended.quantity, dec!(40));
+
+        let book = engine.get_order_book("AAPL").unwrap();
+        let level = book.asks.get(&dec!(150.00)).unwrap();
+        assert_eq!(level.orders.front(), Some(&first_id));
+        assert_eq!(level.total_quantity, dec!(140));
+
+        // The amended order is still first in line
+        let buy = limit_order(OrderSide::Buy, dec!(150.00), dec!(10), "buyer1");
+        let (_, trades) = engine.add_order(buy).unwrap();
+        assert_eq!(trades[0].seller_order_id, first_id);
+    }
+
+    #[test]
+    fn test_amend_quantity_up_loses_priority() {
+        let engine = OrderBookEngine::new();
+
+        let first = limit_order(OrderSide::Sell, dec!(150.00), dec!(100), "seller1");
+        let second = limit_order(OrderSide::Sell, dec!(150.00), dec!(100), "seller2");
+        let first_id = first.id;
+        let second_id = second.id;
+        engine.add_order(first).unwrap();
+        engine.add_order(second).unwrap();
+
+        engine.amend_order("AAPL", first_id, None, Some(dec!(150))).unwrap();
+
+        let book = engine.get_order_book("AAPL").unwrap();
+        let level = book.asks.get(&dec!(150.00)).unwrap();
+        assert_eq!(level.orders.iter().copied().collect::<Vec<_>>(), vec![second_id, first_id]);
+        assert_eq!(level.total_quantity, dec!(250));
+    }
+
+    #[test]
+    fn test_amend_price_loses_priority_and_can_cross() {
+        let engine = OrderBookEngine::new();
+
+        let sell = limit_order(OrderSide::Sell, dec!(151.00), dec!(50), "seller1");
+        let bid = limit_order(OrderSide::Buy, dec!(149.00), dec!(100), "buyer1");
+        let bid_id = bid.id;
+        engine.add_order(sell).unwrap();
+        let (original, _) = engine.add_order(bid).unwrap();
+
+        // Moving the bid through the offer executes against it
+        let (amended, trades) = engine.amend_order("AAPL", bid_id, Some(dec!(151.00)), None).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].quantity, dec!(50));
+        assert_eq!(amended.status, OrderStatus::PartiallyFilled);
+        assert!(amended.timestamp >= original.timestamp);
+
+        let book = engine.get_order_book("AAPL").unwrap();
+        assert!(!book.bids.contains_key(&dec!(149.00)));
+        assert_eq!(book.bids.get(&dec!(151.00)).unwrap().orders.front(), Some(&bid_id));
+        assert!(book.asks.is_empty());
+    }
+
+    #[test]
+    fn test_amend_rejects_invalid_requests() {
+        let engine = OrderBookEngine::new();
+
+        let order = limit_order(OrderSide::Buy, dec!(150.00), dec!(100), "buyer1");
+        let order_id = order.id;
+        engine.add_order(order).unwrap();
+
+        assert!(matches!(
+            engine.amend_order("AAPL", order_id, Some(dec!(150.00)), Some(dec!(100))),
+            Err(OrderBookError::InvalidAmendment(_))
+        ));
+        assert!(matches!(
+            engine.amend_order("AAPL", Uuid::new_v4(), None, Some(dec!(10))),
+            Err(OrderBookError::OrderNotFound(_))
+        ));
+
+        // A rejected amend leaves the order untouched
+        let unchanged = engine.get_order("AAPL", order_id).unwrap();
+        assert_eq!(unchanged.quantity, dec!(100));
+    }
+
+    #[test]
+    fn test_stops_only_trigger_on_own_symbol() {
+        let engine = OrderBookEngine::new();
+
+        let stop = StopOrder {
+            id: Uuid::new_v4(),
+            symbol: "EURUSD".to_string(),
+            user_id: "trader1".to_string(),
+            trigger_price: dec!(1.10),
+            trigger_condition: crate::models::TriggerCondition::AtOrAbove,
+            stop_type: crate::models::StopOrderType::StopMarket,
+            side: OrderSide::Buy,
+            quantity: dec!(10),
+            limit_price: None,
+            trail_amount: None,
+            trail_percent: None,
+            highest_price: None,
+            lowest_price: None,
+            created_at: Utc::now(),
+            expire_time: None,
+            status: crate::models::StopOrderStatus::Pending,
+            time_in_force: crate::models::TimeInForce::GTC,
+            stp_mode: crate::models::SelfTradePreventionMode::None,
+            post_only: false,
+        };
+        engine.add_stop_order(stop).unwrap();
+
+        // Trade XAUUSD well above the EURUSD trigger price
+        let ask = Order::new("XAUUSD".to_string(), OrderSide::Sell, OrderType::Limit, Some(dec!(2400)), dec!(1), "seller1".to_string());
+        let bid = Order::new("XAUUSD".to_string(), OrderSide::Buy, OrderType::Limit, Some(dec!(2400)), dec!(1), "buyer1".to_string());
+        engine.add_order(ask).unwrap();
+        let (_, trades) = engine.add_order(bid).unwrap();
+        assert_eq!(trades.len(), 1);
+
+        assert_eq!(engine.get_stop_orders_by_symbol("EURUSD").unwrap().len(), 1);
+        assert_eq!(engine.get_last_trade_price("XAUUSD").unwrap(), Some(dec!(2400)));
+        assert_eq!(engine.get_last_trade_price("EURUSD").unwrap(), None);
+    }
+
+    #[test]
+    fn test_amend_journals_order_modified() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let wal = Arc::new(Mutex::new(
+            WriteAheadLog::open(temp_dir.path(), crate::persistence::SyncMode::EveryWrite).unwrap(),
+        ));
+        let engine = OrderBookEngine::with_wal(wal.clone());
+
+        let order = limit_order(OrderSide::Buy, dec!(150.00), dec!(100), "buyer1");
+        let order_id = order.id;
+        engine.add_order(order).unwrap();
+
+        engine.amend_order("AAPL", order_id, None, Some(dec!(60))).unwrap();
+
+        let mut events = Vec::new();
+        wal.lock().unwrap().replay(|event| {
+            events.push(event);
+            Ok(())
+        }).unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], WalEvent::OrderSubmitted { .. }));
+        assert!(matches!(
+            &events[1],
+            WalEvent::OrderModified { sequence: 2, order_id: id, new_quantity: Some(quantity), .. }
+                if *id == order_id && *quantity == dec!(60)
+        ));
+    }
+
+    fn stop_loss(side: OrderSide, trigger_price: Decimal, quantity: Decimal) -> StopOrder {
+        StopOrder {
+            id: Uuid::new_v4(),
+            symbol: "AAPL".to_string(),
+            user_id: "trader1".to_string(),
+            trigger_price,
+            trigger_condition: crate::models::TriggerCondition::AtOrBelow,
+            stop_type: crate::models::StopOrderType::StopMarket,
+            side,
+            quantity,
+            limit_price: None,
+            trail_amount: None,
+            trail_percent: None,
+            highest_price: None,
+            lowest_price: None,
+            created_at: Utc::now(),
+            expire_time: None,
+            status: crate::models::StopOrderStatus::Pending,
+            time_in_force: crate::models::TimeInForce::GTC,
+            stp_mode: crate::models::SelfTradePreventionMode::None,
+            post_only: false,
+        }
+    }
+
+    fn trade_at(engine: &OrderBookEngine, price: Decimal) {
+        engine.add_order(limit_order(OrderSide::Sell, price, dec!(1), "mm_seller")).unwrap();
+        let (_, trades) = engine.add_order(limit_order(OrderSide::Buy, price, dec!(1), "mm_buyer")).unwrap();
+        assert_eq!(trades.len(), 1);
+    }
+
+    #[test]
+    fn test_bracket_entry_fill_releases_exits_and_stop_cancels_take_profit() {
+        use crate::models::{ContingencyType, ContingentGroupStatus, LegStatus};
+
+        let engine = OrderBookEngine::new();
+
+        let group = ContingentGroup::new(
+            "AAPL".to_string(),
+            "trader1".to_string(),
+            ContingencyType::Bracket,
+            Some(LegOrder::Order(limit_order(OrderSide::Buy, dec!(100), dec!(10), "trader1"))),
+            vec![
+                LegOrder::Order(limit_order(OrderSide::Sell, dec!(110), dec!(10), "trader1")),
+                LegOrder::Stop(stop_loss(OrderSide::Sell, dec!(95), dec!(10))),
+            ],
+        );
+        let take_profit_id = group.legs[0].id();
+
+        let group = engine.submit_contingent_order(group).unwrap();
+        assert_eq!(group.status, ContingentGroupStatus::PendingEntry);
+        assert_eq!(engine.get_total_stop_orders().unwrap(), 0);
 
-        // Add a matching buy order
-        let buy_order = Order::new(
+        // Fill the entry: take-profit rests, stop-loss is armed
+        engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(10), "seller1")).unwrap();
+        let active = engine.get_contingent_order(group.id).unwrap();
+        assert_eq!(active.status, ContingentGroupStatus::Active);
+        assert!(engine.get_order("AAPL", take_profit_id).is_ok());
+        assert_eq!(engine.get_total_stop_orders().unwrap(), 1);
+
+        // Price drops through the stop: take-profit is pulled from the book
+        trade_at(&engine, dec!(95));
+        let done = engine.get_contingent_order(group.id).unwrap();
+        assert_eq!(done.status, ContingentGroupStatus::Completed);
+        assert_eq!(done.legs[0].status, LegStatus::Cancelled);
+        assert_eq!(done.legs[1].status, LegStatus::Triggered);
+        assert!(engine.get_order("AAPL", take_profit_id).is_err());
+    }
+
+    #[test]
+    fn test_oco_cancel_leg_cancels_group() {
+        use crate::models::{ContingencyType, ContingentGroupStatus};
+
+        let engine = OrderBookEngine::new();
+
+        let stop = stop_loss(OrderSide::Sell, dec!(95), dec!(10));
+        let stop_id = stop.id;
+        let group = ContingentGroup::new(
             "AAPL".to_string(),
-            OrderSide::Buy,
-            OrderType::Limit,
-            Some(dec!(150.00)),
-            dec!(50),
-            "buyer1".to_string(),
+            "trader1".to_string(),
+            ContingencyType::Oco,
+            None,
+            vec![
+                LegOrder::Order(limit_order(OrderSide::Sell, dec!(110), dec!(10), "trader1")),
+                LegOrder::Stop(stop),
+            ],
         );
+        let limit_id = group.legs[0].id();
 
-        let (order, trades) = engine.add_order(buy_order).unwrap();
+        let group = engine.submit_contingent_order(group).unwrap();
+        assert_eq!(engine.get_total_stop_orders().unwrap(), 1);
+
+        // Cancelling the limit leg directly takes the stop with it
+        engine.cancel_order("AAPL", limit_id).unwrap();
+        assert!(engine.get_stop_order(stop_id).unwrap().is_none());
+        assert_eq!(
+            engine.get_contingent_order(group.id).unwrap().status,
+            ContingentGroupStatus::Cancelled
+        );
+        assert!(engine.cancel_contingent_order(group.id).is_err());
+    }
+
+    #[test]
+    fn test_oco_limit_leg_filled_on_placement_does_not_arm_the_stop() {
+        use crate::models::{ContingencyType, ContingentGroupStatus, LegStatus};
+
+        let engine = OrderBookEngine::new();
+        engine.add_order(limit_order(OrderSide::Buy, dec!(110), dec!(10), "buyer1")).unwrap();
+
+        let stop = stop_loss(OrderSide::Sell, dec!(95), dec!(10));
+        let stop_id = stop.id;
+        let group = ContingentGroup::new(
+            "AAPL".to_string(),
+            "trader1".to_string(),
+            ContingencyType::Oco,
+            None,
+            vec![
+                LegOrder::Order(limit_order(OrderSide::Sell, dec!(110), dec!(10), "trader1")),
+                LegOrder::Stop(stop),
+            ],
+        );
+
+        let group = engine.submit_contingent_order(group).unwrap();
+        assert_eq!(group.status, ContingentGroupStatus::Completed);
+        assert_eq!(group.legs[0].status, LegStatus::Filled);
+        assert_eq!(group.legs[1].status, LegStatus::Cancelled);
+        assert!(engine.get_stop_order(stop_id).unwrap().is_none());
+        assert_eq!(engine.get_total_stop_orders().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_bracket_take_profit_filled_on_release_does_not_arm_the_stop_loss() {
+        use crate::models::{ContingencyType, ContingentGroupStatus, LegStatus};
+
+        let engine = OrderBookEngine::new();
+        let group = ContingentGroup::new(
+            "AAPL".to_string(),
+            "trader1".to_string(),
+            ContingencyType::Bracket,
+            Some(LegOrder::Order(limit_order(OrderSide::Buy, dec!(100), dec!(10), "trader1"))),
+            vec![
+                LegOrder::Order(limit_order(OrderSide::Sell, dec!(99), dec!(10), "trader1")),
+                LegOrder::Stop(stop_loss(OrderSide::Sell, dec!(95), dec!(10))),
+            ],
+        );
+        let group = engine.submit_contingent_order(group).unwrap();
+
+        // A bid at the take-profit is waiting behind the entry when it fills
+        engine.add_order(limit_order(OrderSide::Buy, dec!(99), dec!(10), "buyer1")).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(10), "seller1")).unwrap();
+
+        let done = engine.get_contingent_order(group.id).unwrap();
+        assert_eq!(done.status, ContingentGroupStatus::Completed);
+        assert_eq!(done.legs[0].status, LegStatus::Filled);
+        assert_eq!(done.legs[1].status, LegStatus::Cancelled);
+        assert_eq!(engine.get_total_stop_orders().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_invalid_contingent_order_is_rejected() {
+        use crate::models::ContingencyType;
+
+        let engine = OrderBookEngine::new();
+
+        let group = ContingentGroup::new(
+            "AAPL".to_string(),
+            "trader1".to_string(),
+            ContingencyType::Bracket,
+            Some(LegOrder::Order(limit_order(OrderSide::Buy, dec!(100), dec!(10), "trader1"))),
+            vec![LegOrder::Order(limit_order(OrderSide::Sell, dec!(110), dec!(10), "trader1"))],
+        );
+
+        assert!(matches!(
+            engine.submit_contingent_order(group),
+            Err(OrderBookError::InvalidContingentOrder(_))
+        ));
+        assert_eq!(engine.get_total_active_orders().unwrap(), 0);
+    }
+
+    fn pegged_order(side: OrderSide, order_type: OrderType, offset: Option<Decimal>, user_id: &str) -> Order {
+        let mut order = Order::new("AAPL".to_string(), side, order_type, None, dec!(10), user_id.to_string());
+        order.peg_offset = offset;
+        order
+    }
+
+    #[test]
+    fn test_primary_peg_follows_best_bid() {
+        let engine = OrderBookEngine::new();
+
+        engine.add_order(limit_order(OrderSide::Buy, dec!(99), dec!(10), "buyer1")).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(102), dec!(10), "seller1")).unwrap();
+
+        let peg = pegged_order(OrderSide::Buy, OrderType::PrimaryPeg, Some(dec!(0.5)), "mm1");
+        let peg_id = peg.id;
+        let (peg, trades) = engine.add_order(peg).unwrap();
+        assert!(trades.is_empty());
+        assert_eq!(peg.price, Some(dec!(99.5)));
+
+        // Better bid arrives: the peg moves up behind it
+        let better = limit_order(OrderSide::Buy, dec!(100), dec!(10), "buyer2");
+        engine.add_order(better).unwrap();
+        assert_eq!(engine.get_order("AAPL", peg_id).unwrap().price, Some(dec!(100.5)));
+
+        // The peg is the best bid now, but it is priced off the 100 bid, not itself
+        let book = engine.get_order_book("AAPL").unwrap();
+        assert_eq!(book.get_best_bid(), Some(dec!(100.5)));
+        assert!(!book.bids.contains_key(&dec!(99.5)));
+    }
+
+    #[test]
+    fn test_peg_reprice_on_cancel_loses_priority() {
+        let engine = OrderBookEngine::new();
+
+        let best = limit_order(OrderSide::Buy, dec!(100), dec!(10), "buyer1");
+        let best_id = best.id;
+        engine.add_order(best).unwrap();
+        engine.add_order(limit_order(OrderSide::Buy, dec!(99), dec!(10), "buyer2")).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(102), dec!(10), "seller1")).unwrap();
+
+        let peg = pegged_order(OrderSide::Buy, OrderType::PrimaryPeg, None, "mm1");
+        let peg_id = peg.id;
+        engine.add_order(peg).unwrap();
+        let queued_at = engine.get_order("AAPL", peg_id).unwrap().timestamp;
+
+        // Best bid leaves: the peg drops to 99 and queues behind the resting order there
+        engine.cancel_order("AAPL", best_id).unwrap();
+        let peg = engine.get_order("AAPL", peg_id).unwrap();
+        assert_eq!(peg.price, Some(dec!(99)));
+        assert!(peg.timestamp >= queued_at);
+
+        let book = engine.get_order_book("AAPL").unwrap();
+        assert_eq!(book.bids.get(&dec!(99)).unwrap().orders.back(), Some(&peg_id));
+    }
+
+    #[test]
+    fn test_cancelled_peg_leaves_the_peg_index() {
+        let engine = OrderBookEngine::new();
+        engine.add_order(limit_order(OrderSide::Buy, dec!(99), dec!(10), "buyer1")).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(102), dec!(10), "seller1")).unwrap();
+
+        let (peg, _) = engine
+            .add_order(pegged_order(OrderSide::Buy, OrderType::PrimaryPeg, None, "mm1"))
+            .unwrap();
+        assert!(engine.get_order_book("AAPL").unwrap().has_pegged_orders());
+
+        engine.cancel_order("AAPL", peg.id).unwrap();
+        let book = engine.get_order_book("AAPL").unwrap();
+        assert!(!book.has_pegged_orders());
+        assert_eq!(book.pegged_order_ids().count(), 0);
+    }
+
+    #[test]
+    fn test_market_peg_follows_opposite_side_and_can_cross() {
+        let engine = OrderBookEngine::new();
+
+        engine.add_order(limit_order(OrderSide::Buy, dec!(99), dec!(10), "buyer1")).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(102), dec!(10), "seller1")).unwrap();
+
+        // Sell pegged one above the best bid
+        let peg = pegged_order(OrderSide::Sell, OrderType::MarketPeg, Some(dec!(1)), "mm1");
+        let peg_id = peg.id;
+        let (peg, _) = engine.add_order(peg).unwrap();
+        assert_eq!(peg.price, Some(dec!(100)));
+
+        // Best bid rises to 99.5: the peg reprices to 100.5
+        engine.add_order(limit_order(OrderSide::Buy, dec!(99.5), dec!(10), "buyer2")).unwrap();
+        assert_eq!(engine.get_order("AAPL", peg_id).unwrap().price, Some(dec!(100.5)));
+
+        // A buy pegged to the best ask with no offset takes it immediately
+        let taker = pegged_order(OrderSide::Buy, OrderType::MarketPeg, None, "mm2");
+        let (taker, trades) = engine.add_order(taker).unwrap();
         assert_eq!(trades.len(), 1);
-        assert_eq!(trades[0].quantity, dec!(50));
-        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(trades[0].price, dec!(100.5));
+        assert_eq!(taker.status, OrderStatus::Filled);
     }
 
     #[test]
-    fn test_cancel_order() {
+    fn test_peg_prices_stay_on_the_tick_grid() {
         let engine = OrderBookEngine::new();
+        engine.register_instrument(Instrument::new("AAPL".to_string(), dec!(0.01))).unwrap();
 
-        // Add an order
-        let order = Order::new(
+        let best_bid = limit_order(OrderSide::Buy, dec!(100.00), dec!(10), "buyer1");
+        let best_bid_id = best_bid.id;
+        engine.add_order(best_bid).unwrap();
+        engine.add_order(limit_order(OrderSide::Buy, dec!(99.97), dec!(10), "buyer2")).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(100.01), dec!(10), "seller1")).unwrap();
+
+        // The 100.005 midpoint rounds down for a bid and up for an offer
+        let (bid, _) = engine.add_order(pegged_order(OrderSide::Buy, OrderType::MidpointPeg, None, "mm1")).unwrap();
+        let (offer, _) = engine.add_order(pegged_order(OrderSide::Sell, OrderType::MidpointPeg, None, "mm2")).unwrap();
+        assert_eq!((bid.price, offer.price), (Some(dec!(100.00)), Some(dec!(100.01))));
+
+        // Repriced onto the grid too: the midpoint of 99.97 and 100.01 is 99.99
+        engine.cancel_order("AAPL", offer.id).unwrap();
+        engine.cancel_order("AAPL", best_bid_id).unwrap();
+        assert_eq!(engine.get_order("AAPL", bid.id).unwrap().price, Some(dec!(99.99)));
+
+        engine.add_order(limit_order(OrderSide::Buy, dec!(99.98), dec!(10), "buyer3")).unwrap();
+        assert_eq!(engine.get_order("AAPL", bid.id).unwrap().price, Some(dec!(99.99)));
+        engine.add_order(limit_order(OrderSide::Buy, dec!(99.99), dec!(10), "buyer4")).unwrap();
+        assert_eq!(engine.get_order("AAPL", bid.id).unwrap().price, Some(dec!(100.00)));
+    }
+
+    #[test]
+    fn test_peg_reprices_are_held_to_the_price_band() {
+        use crate::risk::PriceBandAction;
+
+        let engine = OrderBookEngine::new();
+        let config = CircuitBreakerConfig {
+            min_trades_for_activation: 1,
+            price_band_pct: Some(dec!(10)),
+            price_band_action: PriceBandAction::Reprice,
+            ..CircuitBreakerConfig::default()
+        };
+        engine.set_circuit_breaker_config("AAPL", config).unwrap();
+        trade_at(&engine, dec!(100));
+
+        engine.add_order(limit_order(OrderSide::Sell, dec!(105), dec!(10), "seller1")).unwrap();
+        let (peg, _) = engine
+            .add_order(pegged_order(OrderSide::Buy, OrderType::MarketPeg, Some(dec!(-1)), "mm1"))
+            .unwrap();
+        assert_eq!(peg.price, Some(dec!(104)));
+
+        // The best ask moves out to 125: the peg follows only as far as the band's 110
+        let (near, _) = engine.add_order(limit_order(OrderSide::Sell, dec!(104.5), dec!(10), "seller2")).unwrap();
+        engine.cancel_order("AAPL", near.id).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(125), dec!(10), "seller3")).unwrap();
+        let book = engine.get_order_book("AAPL").unwrap();
+        let ask_105 = book.orders.values().find(|order| order.price == Some(dec!(105))).unwrap().id;
+        engine.cancel_order("AAPL", ask_105).unwrap();
+        assert_eq!(engine.get_order("AAPL", peg.id).unwrap().price, Some(dec!(110)));
+    }
+
+    #[test]
+    fn test_pegged_order_without_reference_is_rejected() {
+        let engine = OrderBookEngine::new();
+
+        let peg = pegged_order(OrderSide::Buy, OrderType::MidpointPeg, None, "mm1");
+        assert!(matches!(engine.add_order(peg), Err(OrderBookError::InvalidPrice(_))));
+    }
+
+    #[test]
+    fn test_matching_policy_is_per_symbol() {
+        use crate::engine::matching_policy::ProRata;
+
+        let engine = OrderBookEngine::new();
+        engine
+            .set_matching_policy("IRS", MatchingPolicyConfig::ProRata(ProRata { min_allocation: dec!(1), lot_size: dec!(1) }))
+            .unwrap();
+
+        for symbol in ["IRS", "AAPL"] {
+            for seller in ["seller1", "seller2"] {
+                let order = Order::new(symbol.to_string(), OrderSide::Sell, OrderType::Limit, Some(dec!(100)), dec!(10), seller.to_string());
+                engine.add_order(order).unwrap();
+            }
+        }
+
+        let buy = |symbol: &str| {
+            let order = Order::new(symbol.to_string(), OrderSide::Buy, OrderType::Limit, Some(dec!(100)), dec!(10), "buyer1".to_string());
+            let (_, trades) = engine.add_order(order).unwrap();
+            trades.iter().map(|t| t.quantity).collect::<Vec<_>>()
+        };
+
+        assert_eq!(buy("IRS"), vec![dec!(5), dec!(5)]);
+        assert_eq!(buy("AAPL"), vec![dec!(10)]);
+    }
+
+    #[test]
+    fn test_auction_collects_orders_then_uncrosses() {
+        let engine = OrderBookEngine::new();
+        trade_at(&engine, dec!(100));
+
+        let state = engine.start_auction("AAPL", AuctionKind::Opening).unwrap();
+        assert_eq!(state.reference_price, Some(dec!(100)));
+        assert!(state.indicative.is_none());
+
+        let (_, trades) = engine.add_order(limit_order(OrderSide::Buy, dec!(102), dec!(10), "buyer1")).unwrap();
+        assert!(trades.is_empty());
+        engine.add_order(limit_order(OrderSide::Sell, dec!(99), dec!(4), "seller1")).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(101), dec!(4), "seller2")).unwrap();
+
+        // The book is crossed but nothing trades
+        let book = engine.get_order_book("AAPL").unwrap();
+        assert!(book.get_best_bid().unwrap() > book.get_best_ask().unwrap());
+
+        // 101 and 102 both execute 8 with a buy surplus of 2: buy pressure picks 102
+        let indicative = engine.get_auction("AAPL").unwrap().indicative.unwrap();
+        assert_eq!((indicative.price, indicative.volume), (dec!(102), dec!(8)));
+        assert_eq!(indicative.imbalance_side, Some(OrderSide::Buy));
+
+        let mut ioc = limit_order(OrderSide::Sell, dec!(99), dec!(1), "seller3");
+        ioc.time_in_force = TimeInForce::IOC;
+        assert!(matches!(engine.add_order(ioc), Err(OrderBookError::InvalidTradingPhase(_))));
+
+        let result = engine.end_auction("AAPL").unwrap();
+        assert_eq!(result.kind, AuctionKind::Opening);
+        assert_eq!(result.trades.len(), 2);
+        assert!(result.trades.iter().all(|t| t.price == dec!(102)));
+
+        let book = engine.get_order_book("AAPL").unwrap();
+        assert_eq!(book.get_best_bid(), Some(dec!(102)));
+        assert!(book.asks.is_empty());
+
+        // Back to continuous trading
+        let (_, trades) = engine.add_order(limit_order(OrderSide::Sell, dec!(102), dec!(2), "seller1")).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert!(matches!(engine.end_auction("AAPL"), Err(OrderBookError::InvalidTradingPhase(_))));
+    }
+
+    #[test]
+    fn test_auction_cancels_unfilled_market_orders() {
+        let engine = OrderBookEngine::new();
+        engine.start_auction("AAPL", AuctionKind::Opening).unwrap();
+        assert!(matches!(
+            engine.start_auction("AAPL", AuctionKind::Closing),
+            Err(OrderBookError::InvalidTradingPhase(_))
+        ));
+
+        let market_buy = Order::new("AAPL".to_string(), OrderSide::Buy, OrderType::Market, None, dec!(10), "buyer1".to_string());
+        let market_id = market_buy.id;
+        engine.add_order(market_buy).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(4), "seller1")).unwrap();
+
+        let result = engine.end_auction("AAPL").unwrap();
+        let uncross = result.uncross.unwrap();
+        assert_eq!((uncross.price, uncross.volume), (dec!(100), dec!(4)));
+        assert!(matches!(engine.get_order("AAPL", market_id), Err(OrderBookError::OrderNotFound(_))));
+    }
+
+    #[test]
+    fn test_trading_resumes_after_halt_through_reopening_auction() {
+        use crate::risk::RiskError;
+
+        let engine = OrderBookEngine::new();
+
+        engine.halt_trading("AAPL", 5).unwrap();
+        assert!(engine.reopen_after_halt("AAPL").is_err());
+        let rejected = engine.add_order(limit_order(OrderSide::Buy, dec!(100), dec!(5), "buyer1"));
+        assert!(matches!(
+            rejected,
+            Err(OrderBookError::CircuitBreakerRejected(RiskError::TradingHalted { .. }))
+        ));
+
+        // An expired halt reopens through an auction
+        let status = engine.halt_trading("AAPL", 0).unwrap();
+        assert_eq!(status.state, CircuitState::CoolingOff);
+        let state = engine.reopen_after_halt("AAPL").unwrap();
+        assert_eq!(state.kind, AuctionKind::Reopening);
+
+        engine.add_order(limit_order(OrderSide::Buy, dec!(100), dec!(5), "buyer1")).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(5), "seller1")).unwrap();
+
+        engine.end_auction("AAPL").unwrap();
+        let status = engine.get_circuit_breaker("AAPL").unwrap();
+        assert_eq!(status.state, CircuitState::Normal);
+        assert_eq!(status.reference_price, Some(dec!(100)));
+        assert_eq!(engine.get_last_trade_price("AAPL").unwrap(), Some(dec!(100)));
+    }
+
+    #[test]
+    fn test_reopening_auction_uncrosses_on_its_timer() {
+        let engine = OrderBookEngine::new();
+
+        engine.halt_trading("AAPL", 0).unwrap();
+        // Started by the timer, which leaves it running for its minute
+        assert!(engine.end_reopening_auctions(Utc::now()).unwrap().is_empty());
+        assert_eq!(engine.get_auction("AAPL").unwrap().kind, AuctionKind::Reopening);
+
+        engine.add_order(limit_order(OrderSide::Buy, dec!(100), dec!(5), "buyer1")).unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(5), "seller1")).unwrap();
+
+        let results = engine.end_reopening_auctions(Utc::now() + chrono::Duration::minutes(2)).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].trades.len(), 1);
+        assert!(engine.get_auction("AAPL").is_err());
+        assert_eq!(engine.get_circuit_breaker("AAPL").unwrap().state, CircuitState::Normal);
+    }
+
+    #[test]
+    fn test_circuit_breaker_bands_prices_and_halts_on_trades() {
+        use crate::risk::{HaltReason, PriceBandAction, RiskError};
+
+        let engine = OrderBookEngine::new();
+        let config = CircuitBreakerConfig {
+            min_trades_for_activation: 3,
+            price_band_pct: Some(dec!(20)),
+            price_band_action: PriceBandAction::Reprice,
+            ..CircuitBreakerConfig::default()
+        };
+        engine.set_circuit_breaker_config("AAPL", config).unwrap();
+        for _ in 0..3 {
+            engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(1), "seller1")).unwrap();
+            engine.add_order(limit_order(OrderSide::Buy, dec!(100), dec!(1), "buyer1")).unwrap();
+        }
+        let band = engine.get_circuit_breaker("AAPL").unwrap().price_band.unwrap();
+        assert_eq!((band.lower, band.upper), (dec!(80), dec!(120)));
+
+        // A buy through the band is repriced to its edge, then trades 15% away
+        engine.add_order(limit_order(OrderSide::Sell, dec!(115), dec!(1), "seller1")).unwrap();
+        let (resting, _) = engine.add_order(limit_order(OrderSide::Sell, dec!(119), dec!(1), "seller1")).unwrap();
+        let (buy, trades) = engine.add_order(limit_order(OrderSide::Buy, dec!(130), dec!(1), "buyer1")).unwrap();
+        assert_eq!(buy.price, Some(dec!(120)));
+        assert_eq!(trades[0].price, dec!(115));
+
+        let status = engine.get_circuit_breakers().unwrap()["AAPL"].clone();
+        assert_eq!(status.state, CircuitState::Halted);
+        assert_eq!(status.halt_reason, Some(HaltReason::PriceVolatility));
+        let rejected = engine.add_order(limit_order(OrderSide::Buy, dec!(119), dec!(1), "buyer1"));
+        assert!(matches!(
+            rejected,
+            Err(OrderBookError::CircuitBreakerRejected(RiskError::TradingHalted { .. }))
+        ));
+        assert!(engine.amend_order("AAPL", resting.id, Some(dec!(118)), None).is_err());
+        engine.cancel_order("AAPL", resting.id).unwrap();
+
+        assert_eq!(engine.resume_trading("AAPL").unwrap().state, CircuitState::Normal);
+        engine.add_order(limit_order(OrderSide::Buy, dec!(110), dec!(1), "buyer1")).unwrap();
+    }
+
+    #[test]
+    fn test_day_orders_expire_at_session_close() {
+        let engine = OrderBookEngine::new();
+        let calendar = TradingCalendar {
+            timezone: chrono_tz::Europe::London,
+            ..TradingCalendar::default()
+        };
+        engine.set_trading_calendar("AAPL", calendar.clone()).unwrap();
+
+        let mut day = limit_order(OrderSide::Buy, dec!(99), dec!(10), "buyer1");
+        day.time_in_force = TimeInForce::DAY;
+        let (day, _) = engine.add_order(day).unwrap();
+        let close = calendar.day_order_expiry(day.timestamp).unwrap();
+        assert_eq!(day.expire_time, Some(close));
+
+        let mut gtd = limit_order(OrderSide::Sell, dec!(101), dec!(10), "seller1");
+        gtd.time_in_force = TimeInForce::GTD;
+        gtd.expire_time = Some(close + chrono::Duration::hours(1));
+        let (gtd, _) = engine.add_order(gtd).unwrap();
+        let (gtc, _) = engine.add_order(limit_order(OrderSide::Sell, dec!(102), dec!(10), "seller2")).unwrap();
+
+        // Nothing is due before the close
+        assert!(engine.expire_orders(close - chrono::Duration::seconds(1)).unwrap().is_empty());
+
+        let expired = engine.expire_orders(close).unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, day.id);
+        assert_eq!(expired[0].status, OrderStatus::Expired);
+
+        let expired = engine.expire_orders(close + chrono::Duration::hours(2)).unwrap();
+        assert_eq!(expired.iter().map(|o| o.id).collect::<Vec<_>>(), vec![gtd.id]);
+
+        let book = engine.get_order_book("AAPL").unwrap();
+        assert!(book.bids.is_empty());
+        assert_eq!(book.get_best_ask(), Some(dec!(102)));
+        assert!(engine.get_order("AAPL", gtc.id).is_ok());
+    }
+
+    #[test]
+    fn test_expired_gtd_order_does_not_rest() {
+        let engine = OrderBookEngine::new();
+
+        let mut gtd = limit_order(OrderSide::Buy, dec!(99), dec!(10), "buyer1");
+        gtd.time_in_force = TimeInForce::GTD;
+        gtd.expire_time = Some(Utc::now() - chrono::Duration::minutes(1));
+        let (gtd, _) = engine.add_order(gtd).unwrap();
+
+        assert_eq!(gtd.status, OrderStatus::Expired);
+        assert!(engine.get_order("AAPL", gtd.id).is_err());
+    }
+
+    #[test]
+    fn test_expiry_journals_order_expired() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let wal = Arc::new(Mutex::new(
+            WriteAheadLog::open(temp_dir.path(), crate::persistence::SyncMode::EveryWrite).unwrap(),
+        ));
+        let engine = OrderBookEngine::with_wal(wal.clone());
+
+        let mut day = limit_order(OrderSide::Buy, dec!(99), dec!(10), "buyer1");
+        day.time_in_force = TimeInForce::DAY;
+        engine.add_order(day).unwrap();
+
+        let expired = engine.expire_orders(Utc::now() + chrono::Duration::days(2)).unwrap();
+        assert_eq!(expired.len(), 1);
+
+        let mut events = Vec::new();
+        wal.lock().unwrap().replay(|event| {
+            events.push(event);
+            Ok(())
+        }).unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[1], WalEvent::OrderExpired { order_ids, .. } if *order_ids == vec![expired[0].id]));
+    }
+
+    #[test]
+    fn test_invalid_trading_calendar_is_rejected() {
+        let engine = OrderBookEngine::new();
+        let calendar = TradingCalendar {
+            trading_days: Vec::new(),
+            ..TradingCalendar::default()
+        };
+        assert!(matches!(
+            engine.set_trading_calendar("AAPL", calendar),
+            Err(OrderBookError::InvalidTradingCalendar(_))
+        ));
+    }
+
+    #[test]
+    fn test_strict_instrument_registry() {
+        let engine = OrderBookEngine::new();
+        let aapl = Instrument {
+            lot_size: Some(dec!(10)),
+            ..Instrument::new("AAPL".to_string(), dec!(0.05))
+        };
+        engine.set_instrument_registry(InstrumentRegistry::from_instruments([aapl])).unwrap();
+
+        // Unknown symbols get no book
+        let msft = Order::new("MSFT".to_string(), OrderSide::Buy, OrderType::Limit, Some(dec!(100)), dec!(10), "buyer1".to_string());
+        assert!(matches!(engine.add_order(msft), Err(OrderBookError::InvalidSymbol(_))));
+        assert!(matches!(engine.get_order_book("MSFT"), Err(OrderBookError::InvalidSymbol(_))));
+        assert!(!engine.get_symbols().unwrap().contains(&"MSFT".to_string()));
+
+        // Reference data is enforced on entry and on amend
+        assert!(matches!(
+            engine.add_order(limit_order(OrderSide::Buy, dec!(100.01), dec!(10), "buyer1")),
+            Err(OrderBookError::InvalidPrice(_))
+        ));
+        assert!(matches!(
+            engine.add_order(limit_order(OrderSide::Buy, dec!(100), dec!(15), "buyer1")),
+            Err(OrderBookError::InvalidQuantity(_))
+        ));
+
+        let (order, _) = engine.add_order(limit_order(OrderSide::Buy, dec!(100.05), dec!(20), "buyer1")).unwrap();
+        assert!(matches!(
+            engine.amend_order("AAPL", order.id, Some(dec!(100.07)), None),
+            Err(OrderBookError::InvalidPrice(_))
+        ));
+        assert!(engine.amend_order("AAPL", order.id, None, Some(dec!(10))).is_ok());
+    }
+
+    #[test]
+    fn test_accounts_reserve_settle_and_release() {
+        use crate::engine::accounts::FEE_ACCOUNT;
+
+        let engine = OrderBookEngine::new().with_accounts();
+        engine.deposit("buyer1", "USD", dec!(1002)).unwrap();
+        engine.deposit("seller1", "AAPL", dec!(10)).unwrap();
+        let balance = |user: &str, asset: &str| engine.get_balances(user).unwrap().get(asset).copied().unwrap_or_default();
+
+        // 10 @ 100 reserves 1000 plus the 2 taker fee
+        assert!(matches!(
+            engine.add_order(limit_order(OrderSide::Buy, dec!(101), dec!(10), "buyer1")),
+            Err(OrderBookError::InsufficientFunds { .. })
+        ));
+        let (bid, _) = engine.add_order(limit_order(OrderSide::Buy, dec!(100), dec!(10), "buyer1")).unwrap();
+        assert_eq!(balance("buyer1", "USD"), Balance { available: dec!(0), reserved: dec!(1002) });
+        assert!(matches!(
+            engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(11), "seller1")),
+            Err(OrderBookError::InsufficientFunds { .. })
+        ));
+
+        // The seller takes 4: the resting buyer pays the 0.4 maker fee, the seller the 0.8 taker fee
+        engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(4), "seller1")).unwrap();
+        assert_eq!(balance("buyer1", "AAPL").available, dec!(4));
+        assert_eq!(balance("buyer1", "USD").reserved, dec!(601.6));
+        assert_eq!(balance("seller1", "USD").available, dec!(399.2));
+        assert_eq!(balance("seller1", "AAPL"), Balance { available: dec!(6), reserved: dec!(0) });
+        assert_eq!(balance(FEE_ACCOUNT, "USD").available, dec!(1.2));
+
+        // Growing the bid needs more than the buyer has left
+        assert!(matches!(
+            engine.amend_order("AAPL", bid.id, None, Some(dec!(12))),
+            Err(OrderBookError::InsufficientFunds { .. })
+        ));
+        assert_eq!(engine.get_order("AAPL", bid.id).unwrap().quantity, dec!(10));
+
+        // Cancelling returns what the rest of the bid did not spend
+        engine.cancel_order("AAPL", bid.id).unwrap();
+        assert_eq!(balance("buyer1", "USD"), Balance { available: dec!(601.6), reserved: dec!(0) });
+        assert_eq!(engine.get_asset_total("USD").unwrap(), dec!(1002));
+        engine.withdraw("buyer1", "USD", dec!(601.6)).unwrap();
+        assert!(matches!(
+            engine.withdraw("buyer1", "USD", dec!(0.1)),
+            Err(OrderBookError::InsufficientFunds { .. })
+        ));
+    }
+
+    #[test]
+    fn test_fee_schedules_tiers_rebates_and_base_currency() {
+        use crate::engine::accounts::FEE_ACCOUNT;
+        use crate::engine::fees::{FeeScheduleSource, FeeTier};
+        use crate::models::FeeCurrency;
+
+        let engine = OrderBookEngine::new().with_accounts();
+        let tiered = FeeSchedule {
+            tiers: vec![
+                FeeTier { min_volume: dec!(0), maker_rate: dec!(0.001), taker_rate: dec!(0.002) },
+                FeeTier { min_volume: dec!(1000), maker_rate: dec!(-0.001), taker_rate: dec!(0.001) },
+            ],
+            fee_currency: FeeCurrency::Quote,
+        };
+        engine.set_symbol_fee_schedule("AAPL", Some(tiered)).unwrap();
+        let mut in_base = FeeSchedule::flat(dec!(0), dec!(0.01));
+        in_base.fee_currency = FeeCurrency::Base;
+        engine.set_user_fee_schedule("seller1", Some(in_base)).unwrap();
+        assert!(matches!(
+            engine.set_default_fee_schedule(FeeSchedule { tiers: Vec::new(), fee_currency: FeeCurrency::Quote }),
+            Err(OrderBookError::InvalidFeeSchedule(_))
+        ));
+
+        engine.deposit("buyer1", "USD", dec!(3000)).unwrap();
+        engine.deposit("seller1", "AAPL", dec!(20)).unwrap();
+
+        // The seller pays fees in AAPL, so its ask reserves the 1% on top
+        let (ask, _) = engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(10), "seller1")).unwrap();
+        assert_eq!(engine.get_reserved_funds(ask.id).unwrap(), Some(dec!(10.1)));
+
+        let (_, trades) = engine.add_order(limit_order(OrderSide::Buy, dec!(100), dec!(10), "buyer1")).unwrap();
+        assert_eq!((trades[0].maker_fee, trades[0].maker_fee_currency), (dec!(0), FeeCurrency::Base));
+        assert_eq!((trades[0].taker_fee, trades[0].taker_fee_currency), (dec!(2), FeeCurrency::Quote));
+
+        // 1000 traded moves the buyer up a tier, where resting orders earn a rebate
+        let rates = engine.get_fee_rates("buyer1", "AAPL").unwrap();
+        assert_eq!((rates.source, rates.rolling_volume, rates.tier.maker_rate), (FeeScheduleSource::Symbol, dec!(1000), dec!(-0.001)));
+        engine.add_order(limit_order(OrderSide::Buy, dec!(100), dec!(5), "buyer1")).unwrap();
+        let (_, trades) = engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(5), "seller1")).unwrap();
+        assert_eq!((trades[0].maker_fee, trades[0].taker_fee), (dec!(-0.5), dec!(0.05)));
+
+        let balance = |user: &str, asset: &str| engine.get_balances(user).unwrap().get(asset).copied().unwrap_or_default();
+        assert_eq!(balance("buyer1", "USD"), Balance { available: dec!(1498.5), reserved: dec!(0) });
+        assert_eq!(balance("buyer1", "AAPL").available, dec!(15));
+        assert_eq!(balance("seller1", "AAPL"), Balance { available: dec!(4.95), reserved: dec!(0) });
+        assert_eq!(balance(FEE_ACCOUNT, "USD").available, dec!(1.5));
+        assert_eq!(balance(FEE_ACCOUNT, "AAPL").available, dec!(0.05));
+
+        // Net of the rebate, with AAPL fees valued at the trade price
+        assert_eq!(engine.get_total_fees().unwrap(), dec!(6.5));
+    }
+
+    #[test]
+    fn test_risk_limits_reject_orders_amends_and_cancels() {
+        use crate::risk::RiskRejectReason;
+
+        let reason = |result: Result<(Order, Vec<Trade>), OrderBookError>| match result {
+            Err(OrderBookError::RiskRejected(rejection)) => Some(rejection.reason),
+            _ => None,
+        };
+
+        let engine = OrderBookEngine::new();
+        engine
+            .set_account_risk_limits(
+                "alice",
+                Some(RiskLimits {
+                    max_open_orders: Some(2),
+                    max_price_deviation_pct: Some(dec!(10)),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(5), "bob")).unwrap();
+        engine.add_order(limit_order(OrderSide::Buy, dec!(100), dec!(2), "alice")).unwrap();
+
+        let (low, _) = engine.add_order(limit_order(OrderSide::Buy, dec!(90), dec!(1), "alice")).unwrap();
+        let (high, _) = engine.add_order(limit_order(OrderSide::Buy, dec!(91), dec!(1), "alice")).unwrap();
+        let third = engine.add_order(limit_order(OrderSide::Buy, dec!(92), dec!(1), "alice"));
+        assert_eq!(reason(third), Some(RiskRejectReason::MaxOpenOrders));
+        assert_eq!(engine.get_order_book("AAPL").unwrap().orders.len(), 3);
+
+        // 150 is 50% above the last trade at 100, for a new order and an amend alike
+        engine.cancel_order("AAPL", low.id).unwrap();
+        let fat_finger = engine.add_order(limit_order(OrderSide::Buy, dec!(150), dec!(1), "alice"));
+        assert_eq!(reason(fat_finger), Some(RiskRejectReason::PriceDeviation));
+        let (working, _) = engine.add_order(limit_order(OrderSide::Buy, dec!(95), dec!(1), "alice")).unwrap();
+        let amend = engine.amend_order("AAPL", working.id, Some(dec!(150)), None);
+        assert_eq!(reason(amend), Some(RiskRejectReason::PriceDeviation));
+        assert_eq!(engine.get_order("AAPL", working.id).unwrap().price, Some(dec!(95)));
+
+        // Filled orders stop counting; other accounts are not limited
+        assert_eq!(engine.get_account_risk("alice").unwrap().usage.open_orders, 2);
+        let (_, trades) = engine.add_order(limit_order(OrderSide::Sell, dec!(95), dec!(1), "bob")).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(engine.get_account_risk("alice").unwrap().usage.open_orders, 1);
+        assert!(!engine.get_account_risk("bob").unwrap().own_limits);
+
+        // A zero message rate blocks the account, cancels included
+        engine
+            .set_account_risk_limits(
+                "alice",
+                Some(RiskLimits {
+                    max_messages_per_second: Some(0),
+                    ..Default::default()
+                }),
+            )
+            .unwrap();
+        let cancel = engine.cancel_order("AAPL", high.id);
+        assert!(matches!(
+            cancel,
+            Err(OrderBookError::RiskRejected(rejection)) if rejection.reason == RiskRejectReason::MessageRate
+        ));
+        engine.set_account_risk_limits("alice", None).unwrap();
+        engine.cancel_order("AAPL", high.id).unwrap();
+    }
+
+    #[test]
+    fn test_accounts_release_unfilled_ioc_and_stp_cancellations() {
+        use crate::models::SelfTradePreventionMode;
+
+        let engine = OrderBookEngine::new().with_accounts();
+        engine.deposit("trader1", "AAPL", dec!(5)).unwrap();
+        engine.deposit("trader1", "USD", dec!(1000)).unwrap();
+        engine.deposit("trader2", "USD", dec!(1000)).unwrap();
+
+        let (ask, _) = engine.add_order(limit_order(OrderSide::Sell, dec!(100), dec!(5), "trader1")).unwrap();
+        assert_eq!(engine.get_reserved_funds(ask.id).unwrap(), Some(dec!(5)));
+
+        // An IOC bid fills 5 of 8; the rest of its reservation goes back
+        let ioc = Order::new_with_options(
             "AAPL".to_string(),
             OrderSide::Buy,
             OrderType::Limit,
-            Some(dec!(150.00)),
-            dec!(100),
-            "user1".to_string(),
+            Some(dec!(100)),
+            dec!(8),
+            "trader2".to_string(),
+            TimeInForce::IOC,
+            SelfTradePreventionMode::None,
+            false,
+            None,
         );
+        let (ioc, trades) = engine.add_order(ioc).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(engine.get_reserved_funds(ioc.id).unwrap(), None);
+        assert_eq!(engine.get_reserved_funds(ask.id).unwrap(), None);
+        let trader2 = engine.get_balances("trader2").unwrap();
+        assert_eq!(trader2["USD"], Balance { available: dec!(499), reserved: dec!(0) });
+        assert_eq!(trader2["AAPL"].available, dec!(5));
 
-        let order_id = order.id;
-        let (_, trades) = engine.add_order(order).unwrap();
-        assert_eq!(trades.len(), 0);
+        // STP cancels trader2's resting ask when trader2 bids into it
+        let (resting, _) = engine.add_order(limit_order(OrderSide::Sell, dec!(99), dec!(5), "trader2")).unwrap();
+        let bid = Order::new_with_options(
+            "AAPL".to_string(),
+            OrderSide::Buy,
+            OrderType::Limit,
+            Some(dec!(99)),
+            dec!(1),
+            "trader2".to_string(),
+            TimeInForce::GTC,
+            SelfTradePreventionMode::CancelResting,
+            false,
+            None,
+        );
+        engine.add_order(bid).unwrap();
+        assert_eq!(engine.get_reserved_funds(resting.id).unwrap(), None);
+        assert_eq!(engine.get_balances("trader2").unwrap()["AAPL"], Balance { available: dec!(5), reserved: dec!(0) });
+    }
 
-        // Cancel the order
-        let cancelled = engine.cancel_order("AAPL", order_id).unwrap();
-        assert_eq!(cancelled.status, OrderStatus::Cancelled);
+    #[test]
+    fn test_symbols_trade_concurrently_in_place() {
+        let engine = Arc::new(OrderBookEngine::new());
 
-        // Try to get cancelled order
-        let result = engine.get_order("AAPL", order_id);
-        assert!(result.is_err());
+        let handles: Vec<_> = ["AAPL", "MSFT", "TSLA", "NVDA"]
+            .into_iter()
+            .map(|symbol| {
+                let engine = engine.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        let price = dec!(100) + Decimal::from(i % 10);
+                        let sell = Order::new(symbol.to_string(), OrderSide::Sell, OrderType::Limit, Some(price), dec!(1), "seller1".to_string());
+                        let buy = Order::new(symbol.to_string(), OrderSide::Buy, OrderType::Limit, Some(price), dec!(1), "buyer1".to_string());
+                        engine.add_order(sell).unwrap();
+                        let (_, trades) = engine.add_order(buy).unwrap();
+                        assert_eq!(trades.len(), 1);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(engine.get_total_trades().unwrap(), 400);
+        assert_eq!(engine.get_total_active_orders().unwrap(), 0);
+        assert_eq!(engine.get_recent_trades("TSLA", 1000).unwrap().len(), 100);
     }
 }
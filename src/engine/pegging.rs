@@ -0,0 +1,171 @@
+//! Pegged order pricing
+//!
+//! Pegged orders take their price from the top of book. The reference prices
+//! leave the pegged orders out, so a peg never follows itself (or another
+//! peg) once it becomes the best price. The book indexes its pegged orders,
+//! so reading the reference prices does not walk the whole book.
+//! Peg prices are rounded onto the instrument's tick grid on the passive
+//! side: bids down, asks up.
+
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+
+use crate::models::{Order, OrderBook, OrderSide, OrderType, PriceLevel};
+
+/// Best bid and ask of the book with the pegged orders left out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PegReference {
+    pub best_bid: Option<Decimal>,
+    pub best_ask: Option<Decimal>,
+}
+
+impl PegReference {
+    /// Average of the best bid and best ask
+    pub fn mid_price(&self) -> Option<Decimal> {
+        match (self.best_bid, self.best_ask) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / Decimal::from(2)),
+            _ => None,
+        }
+    }
+}
+
+/// Read the reference prices from the book's pegged order index
+///
+/// Only the levels at the top that hold nothing but pegged orders are looked
+/// at, so this does not depend on the depth of the book.
+pub fn reference_prices(book: &OrderBook) -> PegReference {
+    let mut bid_pegs: HashMap<Decimal, usize> = HashMap::new();
+    let mut ask_pegs: HashMap<Decimal, usize> = HashMap::new();
+    for order in book.pegged_order_ids().filter_map(|order_id| book.get_order(order_id)) {
+        let Some(price) = order.price else {
+            continue;
+        };
+        let pegs = match order.side {
+            OrderSide::Buy => &mut bid_pegs,
+            OrderSide::Sell => &mut ask_pegs,
+        };
+        *pegs.entry(price).or_default() += 1;
+    }
+
+    // A level counts once it queues anything besides pegs
+    let unpegged = |level: &PriceLevel, pegs: &HashMap<Decimal, usize>| {
+        level.orders.len() > pegs.get(&level.price).copied().unwrap_or(0)
+    };
+    PegReference {
+        best_bid: book.bids.values().rev().find(|level| unpegged(level, &bid_pegs)).map(|level| level.price),
+        best_ask: book.asks.values().find(|level| unpegged(level, &ask_pegs)).map(|level| level.price),
+    }
+}
+
+/// Price a pegged order against the reference prices, on the tick grid if
+/// `tick_size` is given
+///
+/// Returns `None` if the order is not pegged, the reference side is empty,
+/// or the resulting price would not be positive.
+pub fn peg_price(order: &Order, reference: &PegReference, tick_size: Option<Decimal>) -> Option<Decimal> {
+    let reference_price = match (order.order_type, order.side) {
+        (OrderType::PrimaryPeg, OrderSide::Buy) | (OrderType::MarketPeg, OrderSide::Sell) => {
+            reference.best_bid
+        }
+        (OrderType::PrimaryPeg, OrderSide::Sell) | (OrderType::MarketPeg, OrderSide::Buy) => {
+            reference.best_ask
+        }
+        (OrderType::MidpointPeg, _) => reference.mid_price(),
+        (OrderType::Limit | OrderType::Market, _) => None,
+    }?;
+
+    let price = reference_price + order.peg_offset.unwrap_or(Decimal::ZERO);
+    let price = match tick_size.filter(|tick| *tick > Decimal::ZERO) {
+        Some(tick) => round_passive(price, order.side, tick),
+        None => price,
+    };
+    (price > Decimal::ZERO).then_some(price)
+}
+
+/// Round a price onto the tick grid away from the opposite side
+fn round_passive(price: Decimal, side: OrderSide, tick_size: Decimal) -> Decimal {
+    let ticks = price / tick_size;
+    let ticks = match side {
+        OrderSide::Buy => ticks.floor(),
+        OrderSide::Sell => ticks.ceil(),
+    };
+    ticks * tick_size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PriceLevel;
+    use rust_decimal_macros::dec;
+
+    fn rest(book: &mut OrderBook, order: Order) {
+        let price = order.price.unwrap();
+        let levels = match order.side {
+            OrderSide::Buy => &mut book.bids,
+            OrderSide::Sell => &mut book.asks,
+        };
+        levels
+            .entry(price)
+            .or_insert_with(|| PriceLevel::new(price))
+            .add_order(order.id, order.remaining_quantity());
+        book.insert_order(order);
+    }
+
+    fn order(side: OrderSide, order_type: OrderType, price: Decimal) -> Order {
+        Order::new("TEST".to_string(), side, order_type, Some(price), dec!(10), "user1".to_string())
+    }
+
+    #[test]
+    fn test_peg_reference_prices() {
+        let mut book = OrderBook::new("TEST".to_string());
+        rest(&mut book, order(OrderSide::Buy, OrderType::Limit, dec!(99)));
+        rest(&mut book, order(OrderSide::Sell, OrderType::Limit, dec!(101)));
+        let reference = reference_prices(&book);
+
+        let mut peg = order(OrderSide::Buy, OrderType::PrimaryPeg, dec!(1));
+        assert_eq!(peg_price(&peg, &reference, None), Some(dec!(99)));
+
+        peg.order_type = OrderType::MarketPeg;
+        peg.peg_offset = Some(dec!(-0.5));
+        assert_eq!(peg_price(&peg, &reference, None), Some(dec!(100.5)));
+
+        peg.order_type = OrderType::MidpointPeg;
+        peg.side = OrderSide::Sell;
+        assert_eq!(peg_price(&peg, &reference, None), Some(dec!(99.5)));
+    }
+
+    #[test]
+    fn test_peg_prices_round_to_the_passive_tick() {
+        let mut book = OrderBook::new("TEST".to_string());
+        rest(&mut book, order(OrderSide::Buy, OrderType::Limit, dec!(100.00)));
+        rest(&mut book, order(OrderSide::Sell, OrderType::Limit, dec!(100.01)));
+        let reference = reference_prices(&book);
+
+        let mut peg = order(OrderSide::Buy, OrderType::MidpointPeg, dec!(1));
+        assert_eq!(peg_price(&peg, &reference, None), Some(dec!(100.005)));
+        assert_eq!(peg_price(&peg, &reference, Some(dec!(0.01))), Some(dec!(100.00)));
+        peg.side = OrderSide::Sell;
+        assert_eq!(peg_price(&peg, &reference, Some(dec!(0.01))), Some(dec!(100.01)));
+
+        // Offsets off the grid are rounded the same way
+        peg.order_type = OrderType::PrimaryPeg;
+        peg.peg_offset = Some(dec!(0.004));
+        assert_eq!(peg_price(&peg, &reference, Some(dec!(0.01))), Some(dec!(100.02)));
+    }
+
+    #[test]
+    fn test_reference_prices_ignore_pegs() {
+        let mut book = OrderBook::new("TEST".to_string());
+        rest(&mut book, order(OrderSide::Buy, OrderType::Limit, dec!(99)));
+        rest(&mut book, order(OrderSide::Buy, OrderType::PrimaryPeg, dec!(100)));
+        rest(&mut book, order(OrderSide::Sell, OrderType::MarketPeg, dec!(101)));
+        rest(&mut book, order(OrderSide::Sell, OrderType::Limit, dec!(101)));
+
+        assert_eq!(book.get_best_bid(), Some(dec!(100)));
+        let reference = reference_prices(&book);
+        assert_eq!(reference.best_bid, Some(dec!(99)));
+        // A level shared with a non-pegged order still counts
+        assert_eq!(reference.best_ask, Some(dec!(101)));
+    }
+}
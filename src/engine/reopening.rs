@@ -0,0 +1,40 @@
+//! Reopening of symbols after a circuit breaker halt
+//!
+//! A halted symbol reopens through a call auction once its halt expires. The
+//! timer runs once per second: it starts the reopening auctions of symbols
+//! whose halt has expired and uncrosses those that have collected orders for
+//! their breaker's `reopening_auction_minutes`, returning the symbols to
+//! continuous trading.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use tokio::time::{interval, Duration};
+use tracing::{error, info};
+
+use super::orderbook::OrderBookEngine;
+
+/// Start and end reopening auctions once per second
+pub async fn run_reopening_timer(engine: Arc<OrderBookEngine>) {
+    info!("Reopening auction timer starting");
+    let mut tick_interval = interval(Duration::from_secs(1));
+
+    loop {
+        tick_interval.tick().await;
+
+        // A follower's auctions end when its primary's do
+        if engine.is_standby() {
+            continue;
+        }
+
+        match engine.end_reopening_auctions(Utc::now()) {
+            Ok(results) => {
+                for result in results {
+                    let price = result.uncross.map(|uncross| uncross.price);
+                    info!("{} reopened after its halt (uncross {:?}, {} trades)", result.symbol, price, result.trades.len());
+                }
+            }
+            Err(e) => error!("Failed to end reopening auctions: {}", e),
+        }
+    }
+}
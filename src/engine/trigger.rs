@@ -1,157 +1,192 @@
 use std::collections::{BTreeMap, HashMap};
 use rust_decimal::Decimal;
 use uuid::Uuid;
-use chrono::Utc;
 
 use crate::models::{Order, OrderType, OrderStatus, StopOrder, StopOrderType, StopOrderStatus};
-
-/// Engine that monitors prices and triggers stop orders
-pub struct TriggerEngine {
+use crate::utils::clock;
+
+/// Stop orders and last trade price for a single symbol
+///
+/// Each instrument gets its own trigger book so that a trade in one symbol
+/// can never trigger stops resting on another.
+#[derive(Debug, Default)]
+struct SymbolTriggerBook {
     /// Stop orders indexed by trigger price for efficient scanning
     /// Key: trigger price, Value: orders at that trigger level
     buy_stops: BTreeMap<Decimal, Vec<StopOrder>>,   // Trigger at or above
     sell_stops: BTreeMap<Decimal, Vec<StopOrder>>,  // Trigger at or below
 
-    /// Index for O(1) lookup by order ID
-    order_index: HashMap<Uuid, (Decimal, bool)>,  // order_id -> (trigger_price, is_buy)
-
-    /// Last known trade price
+    /// Last known trade price for this symbol
     last_trade_price: Option<Decimal>,
 }
 
+impl SymbolTriggerBook {
+    fn side_mut(&mut self, is_buy: bool) -> &mut BTreeMap<Decimal, Vec<StopOrder>> {
+        if is_buy {
+            &mut self.buy_stops
+        } else {
+            &mut self.sell_stops
+        }
+    }
+
+    fn side(&self, is_buy: bool) -> &BTreeMap<Decimal, Vec<StopOrder>> {
+        if is_buy {
+            &self.buy_stops
+        } else {
+            &self.sell_stops
+        }
+    }
+
+    fn len(&self) -> usize {
+        let buy_count: usize = self.buy_stops.values().map(|v| v.len()).sum();
+        let sell_count: usize = self.sell_stops.values().map(|v| v.len()).sum();
+        buy_count + sell_count
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buy_stops.is_empty() && self.sell_stops.is_empty()
+    }
+
+    fn update_trailing_stops(&mut self, price: Decimal) {
+        // Update all trailing stops with new price
+        for stops in self.buy_stops.values_mut().chain(self.sell_stops.values_mut()) {
+            for stop in stops.iter_mut() {
+                if stop.stop_type == StopOrderType::TrailingStop {
+                    stop.update_trailing(price);
+                }
+            }
+        }
+    }
+}
+
+/// A stop order that fired, together with the order it produced
+#[derive(Debug, Clone)]
+pub struct TriggeredStop {
+    /// ID of the stop order that triggered
+    pub stop_order_id: Uuid,
+    /// Order ready to be submitted to the main order book
+    pub order: Order,
+}
+
+/// Engine that monitors prices and triggers stop orders
+///
+/// Stops are partitioned by symbol: `on_trade` only scans the trigger book
+/// of the symbol that traded.
+pub struct TriggerEngine {
+    /// Per-symbol trigger books
+    books: HashMap<String, SymbolTriggerBook>,
+
+    /// Index for O(1) lookup by order ID
+    order_index: HashMap<Uuid, (String, Decimal, bool)>,  // order_id -> (symbol, trigger_price, is_buy)
+}
+
 impl TriggerEngine {
     pub fn new() -> Self {
         Self {
-            buy_stops: BTreeMap::new(),
-            sell_stops: BTreeMap::new(),
+            books: HashMap::new(),
             order_index: HashMap::new(),
-            last_trade_price: None,
         }
     }
 
     /// Add a new stop order
     pub fn add_stop_order(&mut self, stop: StopOrder) {
         let is_buy = matches!(stop.side, crate::models::OrderSide::Buy);
-        self.order_index.insert(stop.id, (stop.trigger_price, is_buy));
-
-        let map = if is_buy {
-            &mut self.buy_stops
-        } else {
-            &mut self.sell_stops
-        };
-
-        map.entry(stop.trigger_price)
-            .or_insert_with(Vec::new)
+        self.order_index.insert(stop.id, (stop.symbol.clone(), stop.trigger_price, is_buy));
+
+        self.books
+            .entry(stop.symbol.clone())
+            .or_default()
+            .side_mut(is_buy)
+            .entry(stop.trigger_price)
+            .or_default()
             .push(stop);
     }
 
     /// Cancel a stop order
     pub fn cancel_stop_order(&mut self, order_id: Uuid) -> Option<StopOrder> {
-        if let Some((trigger_price, is_buy)) = self.order_index.remove(&order_id) {
-            
-            let map = if is_buy {
-                &mut self.buy_stops
-            } else {
-                &mut self.sell_stops
-            };
-
-            if let Some(orders) = map.get_mut(&trigger_price) {
-                if let Some(pos) = orders.iter().position(|o| o.id == order_id) {
-                    let mut order = orders.remove(pos);
-                    order.status = StopOrderStatus::Cancelled;
-
-                    // Clean up empty price levels
-                    if orders.is_empty() {
-                        map.remove(&trigger_price);
-                    }
-
-                    return Some(order);
-                }
-            }
+        let (symbol, trigger_price, is_buy) = self.order_index.remove(&order_id)?;
+        let book = self.books.get_mut(&symbol)?;
+        let map = book.side_mut(is_buy);
+
+        let orders = map.get_mut(&trigger_price)?;
+        let pos = orders.iter().position(|o| o.id == order_id)?;
+        let mut order = orders.remove(pos);
+        order.status = StopOrderStatus::Cancelled;
+
+        // Clean up empty price levels
+        if orders.is_empty() {
+            map.remove(&trigger_price);
         }
-        None
+
+        Some(order)
     }
 
-    /// Process a new trade and return any triggered orders
+    /// Process a new trade in `symbol` and return any triggered orders
     ///
     /// This is called after every trade execution in the matching engine.
-    /// Returns a Vec of Orders ready to be submitted to the main order book.
-    pub fn on_trade(&mut self, trade_price: Decimal) -> Vec<Order> {
+    /// Only stops on the traded symbol are considered.
+    /// Returns the triggered stops with the orders to submit to the main order book.
+    pub fn on_trade(&mut self, symbol: &str, trade_price: Decimal) -> Vec<TriggeredStop> {
         let mut triggered_orders = Vec::new();
-        let current_time = Utc::now();
+        let current_time = clock::now();
+
+        let book = self.books.entry(symbol.to_string()).or_default();
 
         // Update trailing stops first
-        self.update_trailing_stops(trade_price);
+        book.update_trailing_stops(trade_price);
 
         // Check buy stops (trigger at or above)
         // Use range to efficiently get all stops at or below current price
-        let triggered_buy_prices: Vec<Decimal> = self.buy_stops
+        let triggered_buy_prices: Vec<Decimal> = book.buy_stops
             .range(..=trade_price)
             .map(|(price, _)| *price)
             .collect();
 
-        for price in triggered_buy_prices {
-            if let Some(stops) = self.buy_stops.remove(&price) {
-                for mut stop in stops {
-                    // Check expiration
-                    if stop.is_expired(current_time) {
-                        stop.status = StopOrderStatus::Expired;
-                        continue;
-                    }
-
-                    if stop.should_trigger(trade_price) {
-                        stop.status = StopOrderStatus::Triggered;
-                        triggered_orders.push(self.convert_to_order(&stop));
-                        self.order_index.remove(&stop.id);
-                    } else {
-                        // Put back if not triggered
-                        self.buy_stops
-                            .entry(stop.trigger_price)
-                            .or_insert_with(Vec::new)
-                            .push(stop);
-                    }
-                }
-            }
-        }
-
         // Check sell stops (trigger at or below)
-        let triggered_sell_prices: Vec<Decimal> = self.sell_stops
+        let triggered_sell_prices: Vec<Decimal> = book.sell_stops
             .range(trade_price..)
             .map(|(price, _)| *price)
             .collect();
 
-        for price in triggered_sell_prices {
-            if let Some(stops) = self.sell_stops.remove(&price) {
-                for mut stop in stops {
-                    // Check expiration
-                    if stop.is_expired(current_time) {
-                        stop.status = StopOrderStatus::Expired;
-                        continue;
-                    }
-
-                    if stop.should_trigger(trade_price) {
-                        stop.status = StopOrderStatus::Triggered;
-                        triggered_orders.push(self.convert_to_order(&stop));
-                        self.order_index.remove(&stop.id);
-                    } else {
-                        // Put back if not triggered
-                        self.sell_stops
-                            .entry(stop.trigger_price)
-                            .or_insert_with(Vec::new)
-                            .push(stop);
+        for (is_buy, prices) in [(true, triggered_buy_prices), (false, triggered_sell_prices)] {
+            let map = book.side_mut(is_buy);
+
+            for price in prices {
+                if let Some(stops) = map.remove(&price) {
+                    for mut stop in stops {
+                        // Check expiration
+                        if stop.is_expired(current_time) {
+                            stop.status = StopOrderStatus::Expired;
+                            self.order_index.remove(&stop.id);
+                            continue;
+                        }
+
+                        if stop.should_trigger(trade_price) {
+                            stop.status = StopOrderStatus::Triggered;
+                            triggered_orders.push(TriggeredStop {
+                                stop_order_id: stop.id,
+                                order: Self::convert_to_order(&stop),
+                            });
+                            self.order_index.remove(&stop.id);
+                        } else {
+                            // Put back if not triggered
+                            map.entry(stop.trigger_price)
+                                .or_default()
+                                .push(stop);
+                        }
                     }
                 }
             }
         }
 
-        self.last_trade_price = Some(trade_price);
+        book.last_trade_price = Some(trade_price);
         triggered_orders
     }
 
     /// Convert a triggered stop order into a regular order
-    fn convert_to_order(&self, stop: &StopOrder) -> Order {
+    fn convert_to_order(stop: &StopOrder) -> Order {
         Order {
-            id: Uuid::new_v4(), // New ID for the actual order
+            id: clock::new_id(), // New ID for the actual order
             symbol: stop.symbol.clone(),
             side: stop.side,
             order_type: match stop.stop_type {
@@ -163,128 +198,104 @@ impl TriggerEngine {
             filled_quantity: Decimal::ZERO,
             status: OrderStatus::New,
             user_id: stop.user_id.clone(),
-            timestamp: Utc::now(),
+            timestamp: clock::now(),
             time_in_force: stop.time_in_force,
             stp_mode: stop.stp_mode,
             post_only: stop.post_only,
             expire_time: stop.expire_time,
             iceberg: None,
-        }
-    }
-
-    fn update_trailing_stops(&mut self, price: Decimal) {
-        // Update all trailing stops with new price
-        for stops in self.buy_stops.values_mut() {
-            for stop in stops.iter_mut() {
-                if stop.stop_type == StopOrderType::TrailingStop {
-                    stop.update_trailing(price);
-                }
-            }
-        }
-        for stops in self.sell_stops.values_mut() {
-            for stop in stops.iter_mut() {
-                if stop.stop_type == StopOrderType::TrailingStop {
-                    stop.update_trailing(price);
-                }
-            }
+            peg_offset: None,
         }
     }
 
     /// Get a stop order by ID
     pub fn get_stop_order(&self, order_id: Uuid) -> Option<&StopOrder> {
-        if let Some((trigger_price, is_buy)) = self.order_index.get(&order_id) {
-            let map = if *is_buy {
-                &self.buy_stops
-            } else {
-                &self.sell_stops
-            };
-
-            if let Some(orders) = map.get(trigger_price) {
-                return orders.iter().find(|o| o.id == order_id);
-            }
-        }
-        None
+        let (symbol, trigger_price, is_buy) = self.order_index.get(&order_id)?;
+        self.books
+            .get(symbol)?
+            .side(*is_buy)
+            .get(trigger_price)?
+            .iter()
+            .find(|o| o.id == order_id)
     }
 
     /// Get all active stop orders for a symbol
     pub fn get_stop_orders_by_symbol(&self, symbol: &str) -> Vec<&StopOrder> {
-        let mut result = Vec::new();
-
-        for stops in self.buy_stops.values() {
-            for stop in stops {
-                if stop.symbol == symbol && stop.is_active() {
-                    result.push(stop);
-                }
-            }
-        }
-
-        for stops in self.sell_stops.values() {
-            for stop in stops {
-                if stop.symbol == symbol && stop.is_active() {
-                    result.push(stop);
-                }
-            }
-        }
+        let Some(book) = self.books.get(symbol) else {
+            return Vec::new();
+        };
 
-        result
+        book.buy_stops
+            .values()
+            .chain(book.sell_stops.values())
+            .flatten()
+            .filter(|stop| stop.is_active())
+            .collect()
     }
 
     /// Get total number of active stop orders
     pub fn get_total_stop_orders(&self) -> usize {
-        let buy_count: usize = self.buy_stops.values().map(|v| v.len()).sum();
-        let sell_count: usize = self.sell_stops.values().map(|v| v.len()).sum();
-        buy_count + sell_count
+        self.books.values().map(|book| book.len()).sum()
+    }
+
+    /// Get last trade price for a symbol
+    pub fn get_last_trade_price(&self, symbol: &str) -> Option<Decimal> {
+        self.books.get(symbol).and_then(|book| book.last_trade_price)
+    }
+
+    /// Get every stop order, in trigger priority within each symbol and price
+    pub fn all_stop_orders(&self) -> Vec<&StopOrder> {
+        self.books
+            .values()
+            .flat_map(|book| book.buy_stops.values().chain(book.sell_stops.values()).flatten())
+            .collect()
     }
 
-    /// Get last trade price
-    pub fn get_last_trade_price(&self) -> Option<Decimal> {
-        self.last_trade_price
+    /// Get the last trade price of every symbol that has traded
+    pub fn last_trade_prices(&self) -> Vec<(String, Decimal)> {
+        self.books
+            .iter()
+            .filter_map(|(symbol, book)| book.last_trade_price.map(|price| (symbol.clone(), price)))
+            .collect()
+    }
+
+    /// Set the last trade price of a symbol without evaluating its stops
+    ///
+    /// Used when restoring state from a snapshot.
+    pub fn set_last_trade_price(&mut self, symbol: &str, price: Decimal) {
+        self.books.entry(symbol.to_string()).or_default().last_trade_price = Some(price);
     }
 
     /// Clean up expired stop orders
     pub fn cleanup_expired(&mut self) -> usize {
-        let current_time = Utc::now();
+        let current_time = clock::now();
         let mut expired_count = 0;
 
-        // Clean buy stops
-        let mut empty_levels = Vec::new();
-        for (price, stops) in self.buy_stops.iter_mut() {
-            stops.retain(|stop| {
-                if stop.is_expired(current_time) {
-                    self.order_index.remove(&stop.id);
-                    expired_count += 1;
-                    false
-                } else {
-                    true
+        for book in self.books.values_mut() {
+            for map in [&mut book.buy_stops, &mut book.sell_stops] {
+                let mut empty_levels = Vec::new();
+                for (price, stops) in map.iter_mut() {
+                    stops.retain(|stop| {
+                        if stop.is_expired(current_time) {
+                            self.order_index.remove(&stop.id);
+                            expired_count += 1;
+                            false
+                        } else {
+                            true
+                        }
+                    });
+                    if stops.is_empty() {
+                        empty_levels.push(*price);
+                    }
                 }
-            });
-            if stops.is_empty() {
-                empty_levels.push(*price);
-            }
-        }
-        for price in empty_levels {
-            self.buy_stops.remove(&price);
-        }
-
-        // Clean sell stops
-        let mut empty_levels = Vec::new();
-        for (price, stops) in self.sell_stops.iter_mut() {
-            stops.retain(|stop| {
-                if stop.is_expired(current_time) {
-                    self.order_index.remove(&stop.id);
-                    expired_count += 1;
-                    false
-                } else {
-                    true
+                for price in empty_levels {
+                    map.remove(&price);
                 }
-            });
-            if stops.is_empty() {
-                empty_levels.push(*price);
             }
         }
-        for price in empty_levels {
-            self.sell_stops.remove(&price);
-        }
+
+        // Drop trigger books that no longer hold stops or price state
+        self.books.retain(|_, book| !book.is_empty() || book.last_trade_price.is_some());
 
         expired_count
     }
@@ -299,6 +310,7 @@ impl Default for TriggerEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Utc;
     use rust_decimal_macros::dec;
     use crate::models::{OrderSide, TimeInForce, TriggerCondition};
     use crate::models::order::SelfTradePreventionMode;
@@ -366,14 +378,14 @@ mod tests {
         engine.add_stop_order(stop);
 
         // Price below trigger - no trigger
-        let triggered = engine.on_trade(dec!(99));
+        let triggered = engine.on_trade("TEST", dec!(99));
         assert_eq!(triggered.len(), 0);
         assert_eq!(engine.get_total_stop_orders(), 1);
 
         // Price at trigger - should trigger
-        let triggered = engine.on_trade(dec!(100));
+        let triggered = engine.on_trade("TEST", dec!(100));
         assert_eq!(triggered.len(), 1);
-        assert_eq!(triggered[0].side, OrderSide::Buy);
+        assert_eq!(triggered[0].order.side, OrderSide::Buy);
         assert_eq!(engine.get_total_stop_orders(), 0);
     }
 
@@ -385,13 +397,13 @@ mod tests {
         engine.add_stop_order(stop);
 
         // Price above trigger - no trigger
-        let triggered = engine.on_trade(dec!(101));
+        let triggered = engine.on_trade("TEST", dec!(101));
         assert_eq!(triggered.len(), 0);
 
         // Price at trigger - should trigger
-        let triggered = engine.on_trade(dec!(100));
+        let triggered = engine.on_trade("TEST", dec!(100));
         assert_eq!(triggered.len(), 1);
-        assert_eq!(triggered[0].side, OrderSide::Sell);
+        assert_eq!(triggered[0].order.side, OrderSide::Sell);
     }
 
     #[test]
@@ -411,4 +423,57 @@ mod tests {
         let other_stops = engine.get_stop_orders_by_symbol("OTHER");
         assert_eq!(other_stops.len(), 1);
     }
+
+    #[test]
+    fn test_trade_in_other_symbol_does_not_trigger() {
+        let mut engine = TriggerEngine::new();
+
+        let mut eurusd_stop = create_test_stop(OrderSide::Buy, dec!(1.10), TriggerCondition::AtOrAbove);
+        eurusd_stop.symbol = "EURUSD".to_string();
+        let mut eurusd_sell = create_test_stop(OrderSide::Sell, dec!(1.05), TriggerCondition::AtOrBelow);
+        eurusd_sell.symbol = "EURUSD".to_string();
+        engine.add_stop_order(eurusd_stop);
+        engine.add_stop_order(eurusd_sell);
+
+        // XAUUSD prints that would cross both EURUSD triggers must be ignored
+        let triggered = engine.on_trade("XAUUSD", dec!(2400));
+        assert!(triggered.is_empty());
+        let triggered = engine.on_trade("XAUUSD", dec!(0.5));
+        assert!(triggered.is_empty());
+        assert_eq!(engine.get_stop_orders_by_symbol("EURUSD").len(), 2);
+
+        // A print in the right symbol still triggers
+        let triggered = engine.on_trade("EURUSD", dec!(1.11));
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(triggered[0].order.symbol, "EURUSD");
+    }
+
+    #[test]
+    fn test_last_trade_price_per_symbol() {
+        let mut engine = TriggerEngine::new();
+
+        engine.on_trade("EURUSD", dec!(1.10));
+        engine.on_trade("XAUUSD", dec!(2400));
+
+        assert_eq!(engine.get_last_trade_price("EURUSD"), Some(dec!(1.10)));
+        assert_eq!(engine.get_last_trade_price("XAUUSD"), Some(dec!(2400)));
+        assert_eq!(engine.get_last_trade_price("GBPUSD"), None);
+    }
+
+    #[test]
+    fn test_trailing_stops_follow_own_symbol_only() {
+        let mut engine = TriggerEngine::new();
+
+        let mut stop = create_test_stop(OrderSide::Sell, dec!(95), TriggerCondition::AtOrBelow);
+        stop.stop_type = StopOrderType::TrailingStop;
+        stop.trail_amount = Some(dec!(5));
+        let stop_id = stop.id;
+        engine.add_stop_order(stop);
+
+        engine.on_trade("OTHER", dec!(1000));
+        assert_eq!(engine.get_stop_order(stop_id).unwrap().trigger_price, dec!(95));
+
+        engine.on_trade("TEST", dec!(110));
+        assert_eq!(engine.get_stop_order(stop_id).unwrap().trigger_price, dec!(105));
+    }
 }
@@ -7,7 +7,7 @@
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 
-use crate::models::{Order, OrderType, TimeInForce};
+use crate::models::{Instrument, Order, OrderType, TimeInForce};
 
 use super::errors::OrderBookError;
 
@@ -92,6 +92,168 @@ pub fn validate_expire_time(
     Ok(())
 }
 
+/// Validate an amend (modify) request against the order it targets
+///
+/// # Rules
+/// - At least one of price or quantity must be provided
+/// - Only limit orders can be amended (market orders never rest)
+/// - Iceberg orders cannot be amended (their visible/hidden split would be lost)
+/// - New price must be positive
+/// - New quantity must be greater than the quantity already filled
+///
+/// # Arguments
+/// * `order` - The resting order being amended
+/// * `new_price` - Optional replacement limit price
+/// * `new_quantity` - Optional replacement total (original) quantity
+///
+/// # Returns
+/// * `Ok(())` if the amendment is valid
+/// * `Err(OrderBookError)` with the specific validation failure
+pub fn validate_amendment(
+    order: &Order,
+    new_price: Option<Decimal>,
+    new_quantity: Option<Decimal>,
+) -> Result<(), OrderBookError> {
+    if new_price.is_none() && new_quantity.is_none() {
+        return Err(OrderBookError::InvalidAmendment(
+            "Amend must change price and/or quantity".to_string(),
+        ));
+    }
+
+    if order.order_type != OrderType::Limit {
+        return Err(OrderBookError::InvalidAmendment(
+            "Only limit orders can be amended".to_string(),
+        ));
+    }
+
+    if order.iceberg.is_some() {
+        return Err(OrderBookError::InvalidAmendment(
+            "Iceberg orders cannot be amended".to_string(),
+        ));
+    }
+
+    if new_price.is_some() {
+        validate_price(new_price, &order.order_type)?;
+    }
+
+    if let Some(quantity) = new_quantity {
+        validate_quantity(quantity)?;
+        if quantity <= order.filled_quantity {
+            return Err(OrderBookError::InvalidQuantity(format!(
+                "Quantity must exceed filled quantity {}, got: {}",
+                order.filled_quantity, quantity
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate the peg settings of an order
+///
+/// # Rules
+/// - `peg_offset` is only meaningful for pegged order types
+/// - Pegged orders must have had their price derived before validation
+///
+/// # Arguments
+/// * `order` - The order to validate
+///
+/// # Returns
+/// * `Ok(())` if the peg settings are consistent
+/// * `Err(OrderBookError::InvalidPrice)` otherwise
+pub fn validate_peg(order: &Order) -> Result<(), OrderBookError> {
+    if order.peg_offset.is_some() && !order.order_type.is_pegged() {
+        return Err(OrderBookError::InvalidPrice(
+            "peg_offset is only valid for pegged orders".to_string(),
+        ));
+    }
+
+    if order.order_type.is_pegged() && order.price.is_none() {
+        return Err(OrderBookError::InvalidPrice(
+            "No reference price available for pegged order".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validate an order against its instrument's reference data
+///
+/// # Rules
+/// - Price must be a multiple of the tick size
+/// - Price must lie within the static collar around the reference price
+/// - Quantity must be a multiple of the lot size and within min / max quantity
+/// - Order value must reach the minimum notional
+///
+/// Price rules are skipped for orders without a price (market orders).
+///
+/// # Arguments
+/// * `order` - The order to validate
+/// * `instrument` - Reference data of the order's symbol
+///
+/// # Returns
+/// * `Ok(())` if the order fits the instrument
+/// * `Err(OrderBookError::InvalidPrice)` or `Err(OrderBookError::InvalidQuantity)` otherwise
+pub fn validate_instrument(order: &Order, instrument: &Instrument) -> Result<(), OrderBookError> {
+    if let Some(price) = order.price {
+        let on_tick = instrument.tick_size <= Decimal::ZERO || (price % instrument.tick_size).is_zero();
+        if !on_tick {
+            return Err(OrderBookError::InvalidPrice(format!(
+                "Price {} is not a multiple of tick size {}",
+                price, instrument.tick_size
+            )));
+        }
+
+        if let Some((low, high)) = instrument.price_collar() {
+            if price < low || price > high {
+                return Err(OrderBookError::InvalidPrice(format!(
+                    "Price {} is outside the price collar [{}, {}]",
+                    price, low, high
+                )));
+            }
+        }
+
+        if let Some(min_notional) = instrument.min_notional {
+            let notional = instrument.notional(price, order.quantity);
+            if notional < min_notional {
+                return Err(OrderBookError::InvalidQuantity(format!(
+                    "Order value {} is below the minimum notional {}",
+                    notional, min_notional
+                )));
+            }
+        }
+    }
+
+    if let Some(lot_size) = instrument.lot_size.filter(|lot| *lot > Decimal::ZERO) {
+        if !(order.quantity % lot_size).is_zero() {
+            return Err(OrderBookError::InvalidQuantity(format!(
+                "Quantity {} is not a multiple of lot size {}",
+                order.quantity, lot_size
+            )));
+        }
+    }
+
+    if let Some(min_quantity) = instrument.min_quantity {
+        if order.quantity < min_quantity {
+            return Err(OrderBookError::InvalidQuantity(format!(
+                "Quantity {} is below the minimum {}",
+                order.quantity, min_quantity
+            )));
+        }
+    }
+
+    if let Some(max_quantity) = instrument.max_quantity {
+        if order.quantity > max_quantity {
+            return Err(OrderBookError::InvalidQuantity(format!(
+                "Quantity {} is above the maximum {}",
+                order.quantity, max_quantity
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 // ============================================================================
 // Composite Validation Function
 // ============================================================================
@@ -105,9 +267,13 @@ pub fn validate_expire_time(
 /// 1. Quantity must be positive
 /// 2. Price must be valid for the order type
 /// 3. GTD orders must have an expire_time
+/// 4. Peg settings must be consistent with the order type
+/// 5. Tick size, lot size, quantity limits, minimum notional and price collar
+///    of the instrument (when reference data is available)
 ///
 /// # Arguments
 /// * `order` - The order to validate
+/// * `instrument` - Reference data of the order's symbol, if any
 ///
 /// # Returns
 /// * `Ok(())` if all validations pass
@@ -116,13 +282,17 @@ pub fn validate_expire_time(
 /// # Example
 /// ```ignore
 /// let order = Order::new(...);
-/// validate_order(&order)?; // Will return early if validation fails
+/// validate_order(&order, Some(&instrument))?; // Will return early if validation fails
 /// // Continue processing...
 /// ```
-pub fn validate_order(order: &Order) -> Result<(), OrderBookError> {
+pub fn validate_order(order: &Order, instrument: Option<&Instrument>) -> Result<(), OrderBookError> {
     validate_quantity(order.quantity)?;
     validate_price(order.price, &order.order_type)?;
     validate_expire_time(&order.time_in_force, order.expire_time)?;
+    validate_peg(order)?;
+    if let Some(instrument) = instrument {
+        validate_instrument(order, instrument)?;
+    }
     Ok(())
 }
 
@@ -181,6 +351,55 @@ mod tests {
         assert!(validate_expire_time(&TimeInForce::GTD, Some(Utc::now())).is_ok());
     }
 
+    #[test]
+    fn test_validate_amendment() {
+        use crate::models::OrderSide;
+
+        let mut order = Order::new(
+            "AAPL".to_string(),
+            OrderSide::Buy,
+            OrderType::Limit,
+            Some(dec!(100)),
+            dec!(10),
+            "user1".to_string(),
+        );
+        order.fill(dec!(4));
+
+        assert!(validate_amendment(&order, Some(dec!(101)), None).is_ok());
+        assert!(validate_amendment(&order, None, Some(dec!(5))).is_ok());
+
+        // Nothing to change
+        assert!(validate_amendment(&order, None, None).is_err());
+        // Cannot amend below what is already filled
+        assert!(validate_amendment(&order, None, Some(dec!(4))).is_err());
+        // Price must still be positive
+        assert!(validate_amendment(&order, Some(dec!(0)), None).is_err());
+    }
+
+    #[test]
+    fn test_validate_peg() {
+        use crate::models::OrderSide;
+
+        let mut order = Order::new(
+            "AAPL".to_string(),
+            OrderSide::Buy,
+            OrderType::MidpointPeg,
+            None,
+            dec!(10),
+            "user1".to_string(),
+        );
+        // No reference price was derived
+        assert!(validate_peg(&order).is_err());
+
+        order.price = Some(dec!(100));
+        order.peg_offset = Some(dec!(-0.5));
+        assert!(validate_peg(&order).is_ok());
+
+        // Offset on a plain limit order
+        order.order_type = OrderType::Limit;
+        assert!(validate_peg(&order).is_err());
+    }
+
     #[test]
     fn test_validate_expire_time_other_tif() {
         // Other TIF types don't require expire_time
@@ -189,4 +408,42 @@ mod tests {
         assert!(validate_expire_time(&TimeInForce::FOK, None).is_ok());
         assert!(validate_expire_time(&TimeInForce::DAY, None).is_ok());
     }
+
+    #[test]
+    fn test_validate_instrument() {
+        use crate::models::OrderSide;
+
+        let instrument = Instrument {
+            lot_size: Some(dec!(10)),
+            min_quantity: Some(dec!(10)),
+            max_quantity: Some(dec!(1000)),
+            min_notional: Some(dec!(2000)),
+            reference_price: Some(dec!(100)),
+            price_collar_pct: Some(dec!(5)),
+            ..Instrument::new("AAPL".to_string(), dec!(0.05))
+        };
+        let order = |price: Decimal, quantity: Decimal| {
+            Order::new("AAPL".to_string(), OrderSide::Buy, OrderType::Limit, Some(price), quantity, "user1".to_string())
+        };
+
+        assert!(validate_instrument(&order(dec!(100.05), dec!(20)), &instrument).is_ok());
+
+        // Off tick, outside the 95..105 collar
+        assert!(matches!(validate_instrument(&order(dec!(100.01), dec!(20)), &instrument), Err(OrderBookError::InvalidPrice(_))));
+        assert!(matches!(validate_instrument(&order(dec!(105.05), dec!(20)), &instrument), Err(OrderBookError::InvalidPrice(_))));
+
+        // Off lot, above max quantity, below min notional
+        assert!(matches!(validate_instrument(&order(dec!(100), dec!(25)), &instrument), Err(OrderBookError::InvalidQuantity(_))));
+        assert!(matches!(validate_instrument(&order(dec!(100), dec!(1010)), &instrument), Err(OrderBookError::InvalidQuantity(_))));
+        assert!(matches!(validate_instrument(&order(dec!(100), dec!(10)), &instrument), Err(OrderBookError::InvalidQuantity(_))));
+
+        // Pegs are priced onto the grid before they are validated, so there is no exception for them
+        let mut peg = order(dec!(100.025), dec!(20));
+        peg.order_type = OrderType::MidpointPeg;
+        assert!(validate_instrument(&peg, &instrument).is_err());
+
+        // Reference data is only checked when it is available
+        assert!(validate_order(&order(dec!(100.01), dec!(25)), None).is_ok());
+        assert!(validate_order(&order(dec!(100.01), dec!(25)), Some(&instrument)).is_err());
+    }
 }
@@ -0,0 +1,824 @@
+//! FIX order entry sessions over TCP
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{NaiveDateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use rust_decimal::Decimal;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::time::{interval, timeout, Duration, Instant, MissedTickBehavior};
+use tokio_util::codec::Framed;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::disruptor::command::MAX_USER_ID_LEN;
+use crate::disruptor::IngestionPipeline;
+use crate::engine::OrderBookError;
+use crate::gateway::server::MAX_HEARTBEAT_INTERVAL_SECS;
+use crate::gateway::{reject_reason, OrderRegistry};
+use crate::models::{Order, OrderSide, OrderStatus, OrderType, SelfTradePreventionMode, TimeInForce};
+use crate::protocol::{ExecType, ExecutionReport, Message, RejectReason};
+use crate::utils::clock;
+
+use super::codec::FixCodec;
+use super::session::{msg_type, tag, FixSession, Inbound, TIMESTAMP_FORMAT};
+
+/// Time a client has to log on after connecting
+const LOGON_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Application message types
+pub mod app_msg_type {
+    pub const EXECUTION_REPORT: &str = "8";
+    pub const ORDER_CANCEL_REJECT: &str = "9";
+    pub const NEW_ORDER_SINGLE: &str = "D";
+    pub const ORDER_CANCEL_REQUEST: &str = "F";
+    pub const ORDER_CANCEL_REPLACE_REQUEST: &str = "G";
+    pub const BUSINESS_MESSAGE_REJECT: &str = "j";
+    pub const ORDER_MASS_CANCEL_REQUEST: &str = "q";
+    pub const ORDER_MASS_CANCEL_REPORT: &str = "r";
+}
+
+/// Tags of the order entry messages
+pub mod app_tag {
+    pub const AVG_PX: u32 = 6;
+    pub const CL_ORD_ID: u32 = 11;
+    pub const CUM_QTY: u32 = 14;
+    pub const EXEC_ID: u32 = 17;
+    pub const EXEC_INST: u32 = 18;
+    pub const LAST_PX: u32 = 31;
+    pub const LAST_QTY: u32 = 32;
+    pub const ORDER_ID: u32 = 37;
+    pub const ORDER_QTY: u32 = 38;
+    pub const ORD_STATUS: u32 = 39;
+    pub const ORD_TYPE: u32 = 40;
+    pub const ORIG_CL_ORD_ID: u32 = 41;
+    pub const PRICE: u32 = 44;
+    pub const SIDE: u32 = 54;
+    pub const SYMBOL: u32 = 55;
+    pub const TIME_IN_FORCE: u32 = 59;
+    pub const TRANSACT_TIME: u32 = 60;
+    pub const CXL_REJ_REASON: u32 = 102;
+    pub const ORD_REJ_REASON: u32 = 103;
+    pub const MAX_FLOOR: u32 = 111;
+    pub const EXPIRE_TIME: u32 = 126;
+    pub const EXEC_TYPE: u32 = 150;
+    pub const LEAVES_QTY: u32 = 151;
+    pub const PEG_OFFSET_VALUE: u32 = 211;
+    pub const BUSINESS_REJECT_REASON: u32 = 380;
+    pub const CXL_REJ_RESPONSE_TO: u32 = 434;
+    pub const MASS_CANCEL_REQUEST_TYPE: u32 = 530;
+    pub const MASS_CANCEL_RESPONSE: u32 = 531;
+    pub const MASS_CANCEL_REJECT_REASON: u32 = 532;
+    pub const TOTAL_AFFECTED_ORDERS: u32 = 533;
+}
+
+use app_tag as at;
+
+/// SessionRejectReason (373) codes
+const REQUIRED_TAG_MISSING: u32 = 1;
+const VALUE_INCORRECT: u32 = 5;
+
+/// A field of an incoming message that cannot be used, answered with a
+/// session-level Reject
+struct FieldError {
+    tag: u32,
+    reason: u32,
+    text: String,
+}
+
+fn required(fields: &HashMap<u32, String>, tag: u32) -> Result<&str, FieldError> {
+    fields.get(&tag).map(String::as_str).filter(|value| !value.is_empty()).ok_or(FieldError {
+        tag,
+        reason: REQUIRED_TAG_MISSING,
+        text: format!("Required tag missing: {}", tag),
+    })
+}
+
+fn invalid(tag: u32, value: &str) -> FieldError {
+    FieldError {
+        tag,
+        reason: VALUE_INCORRECT,
+        text: format!("Value is incorrect for tag {}: {}", tag, value),
+    }
+}
+
+fn decimal(fields: &HashMap<u32, String>, tag: u32) -> Result<Option<Decimal>, FieldError> {
+    fields
+        .get(&tag)
+        .map(|value| Decimal::from_str(value).map_err(|_| invalid(tag, value)))
+        .transpose()
+}
+
+/// A FIX order as its reports describe it
+#[derive(Debug, Clone)]
+struct FixOrder {
+    user_id: String,
+    cl_ord_id: String,
+    /// ClOrdID of a cancel or replace request in flight, and the ClOrdID it refers to
+    pending: Option<(String, String)>,
+    order_qty: Decimal,
+    /// Sum of price × quantity of the fills, for AvgPx
+    filled_notional: Decimal,
+}
+
+/// ClOrdIDs of the working FIX orders
+#[derive(Default)]
+struct FixOrders {
+    orders: HashMap<Uuid, FixOrder>,
+    /// Current ClOrdID of each working order, by user
+    by_cl_ord_id: HashMap<(String, String), Uuid>,
+}
+
+impl FixOrders {
+    fn insert(&mut self, order_id: Uuid, order: FixOrder) {
+        self.by_cl_ord_id
+            .insert((order.user_id.clone(), order.cl_ord_id.clone()), order_id);
+        self.orders.insert(order_id, order);
+    }
+
+    fn remove(&mut self, order_id: Uuid) {
+        if let Some(order) = self.orders.remove(&order_id) {
+            self.by_cl_ord_id.remove(&(order.user_id, order.cl_ord_id));
+        }
+    }
+
+    /// Order a cancel or replace request refers to: by OrderID, else by OrigClOrdID
+    fn find(&self, user_id: &str, fields: &HashMap<u32, String>) -> Option<Uuid> {
+        let by_order_id = fields
+            .get(&at::ORDER_ID)
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .filter(|id| self.orders.get(id).is_some_and(|order| order.user_id == user_id));
+        by_order_id.or_else(|| {
+            let orig_cl_ord_id = fields.get(&at::ORIG_CL_ORD_ID)?;
+            self.by_cl_ord_id
+                .get(&(user_id.to_string(), orig_cl_ord_id.clone()))
+                .copied()
+        })
+    }
+
+    /// FIX fields of a report from the order registry
+    fn report_fields(&mut self, report: &ExecutionReport) -> Vec<(u32, String)> {
+        let order_id = report.order_id;
+        let mut fields = vec![(at::ORDER_ID, order_id.to_string())];
+        let (order_qty, avg_px) = match self.orders.get_mut(&order_id) {
+            Some(order) => {
+                if let (Some(price), ExecType::Trade) = (report.last_price, report.exec_type) {
+                    order.filled_notional += price * report.last_quantity;
+                }
+                match (report.exec_type, order.pending.take()) {
+                    (ExecType::Cancelled | ExecType::Replaced, Some((cl_ord_id, orig_cl_ord_id))) => {
+                        fields.push((at::CL_ORD_ID, cl_ord_id.clone()));
+                        fields.push((at::ORIG_CL_ORD_ID, orig_cl_ord_id));
+                        if report.exec_type == ExecType::Replaced {
+                            self.by_cl_ord_id.remove(&(order.user_id.clone(), order.cl_ord_id.clone()));
+                            self.by_cl_ord_id.insert((order.user_id.clone(), cl_ord_id.clone()), order_id);
+                            order.cl_ord_id = cl_ord_id;
+                        }
+                    }
+                    (_, pending) => {
+                        order.pending = pending;
+                        fields.push((at::CL_ORD_ID, order.cl_ord_id.clone()));
+                    }
+                }
+                if report.exec_type == ExecType::Replaced {
+                    order.order_qty = report.cumulative_quantity + report.leaves_quantity;
+                }
+                let avg_px = if report.cumulative_quantity.is_zero() {
+                    Decimal::ZERO
+                } else {
+                    (order.filled_notional / report.cumulative_quantity).normalize()
+                };
+                (order.order_qty, avg_px)
+            }
+            // Entered through another gateway of the user
+            None => {
+                fields.push((at::CL_ORD_ID, order_id.to_string()));
+                let avg_px = report.last_price.unwrap_or_default();
+                (report.cumulative_quantity + report.leaves_quantity, avg_px)
+            }
+        };
+
+        let exec_type = match (report.exec_type, report.status) {
+            (ExecType::New, _) => "0",
+            (ExecType::Trade, _) => "F",
+            (ExecType::Cancelled, OrderStatus::Expired) => "C",
+            (ExecType::Cancelled, _) => "4",
+            (ExecType::Replaced, _) => "5",
+            (ExecType::Rejected | ExecType::CancelRejected, _) => "8",
+        };
+        fields.push((at::EXEC_ID, clock::new_id().to_string()));
+        fields.push((at::EXEC_TYPE, exec_type.to_string()));
+        fields.push((at::ORD_STATUS, ord_status(report.status).to_string()));
+        fields.push((at::SYMBOL, report.symbol.clone()));
+        if let Some(side) = report.side {
+            fields.push((at::SIDE, side_code(side).to_string()));
+        }
+        fields.push((at::ORDER_QTY, order_qty.to_string()));
+        if let Some(price) = report.price {
+            fields.push((at::PRICE, price.to_string()));
+        }
+        if let (Some(price), ExecType::Trade) = (report.last_price, report.exec_type) {
+            fields.push((at::LAST_PX, price.to_string()));
+            fields.push((at::LAST_QTY, report.last_quantity.to_string()));
+        }
+        fields.push((at::LEAVES_QTY, report.leaves_quantity.to_string()));
+        fields.push((at::CUM_QTY, report.cumulative_quantity.to_string()));
+        fields.push((at::AVG_PX, avg_px.to_string()));
+        fields.push((at::TRANSACT_TIME, report.timestamp.format(TIMESTAMP_FORMAT).to_string()));
+        if !report.text.is_empty() {
+            fields.push((tag::TEXT, report.text.clone()));
+        }
+
+        if matches!(
+            report.status,
+            OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Expired | OrderStatus::Rejected
+        ) {
+            self.remove(order_id);
+        }
+        fields
+    }
+}
+
+fn ord_status(status: OrderStatus) -> &'static str {
+    match status {
+        OrderStatus::New => "0",
+        OrderStatus::PartiallyFilled => "1",
+        OrderStatus::Filled => "2",
+        OrderStatus::Cancelled => "4",
+        OrderStatus::Rejected => "8",
+        OrderStatus::Expired => "C",
+    }
+}
+
+fn side_code(side: OrderSide) -> &'static str {
+    match side {
+        OrderSide::Buy => "1",
+        OrderSide::Sell => "2",
+    }
+}
+
+/// OrdRejReason (103) of a reject
+fn ord_rej_reason(reason: RejectReason) -> &'static str {
+    match reason {
+        RejectReason::TradingHalted | RejectReason::InvalidTradingPhase => "2",
+        RejectReason::RiskLimit | RejectReason::InsufficientFunds => "3",
+        RejectReason::UnknownOrder => "5",
+        RejectReason::DuplicateOrder => "6",
+        _ => "99",
+    }
+}
+
+/// Order of a NewOrderSingle
+fn parse_new_order(user_id: &str, fields: &HashMap<u32, String>) -> Result<Order, FieldError> {
+    let symbol = required(fields, at::SYMBOL)?;
+    let side = match required(fields, at::SIDE)? {
+        "1" => OrderSide::Buy,
+        "2" => OrderSide::Sell,
+        other => return Err(invalid(at::SIDE, other)),
+    };
+    let exec_inst = fields.get(&at::EXEC_INST).map(String::as_str).unwrap_or_default();
+    let order_type = match required(fields, at::ORD_TYPE)? {
+        "1" => OrderType::Market,
+        "2" => OrderType::Limit,
+        "P" if exec_inst.contains('R') => OrderType::PrimaryPeg,
+        "P" if exec_inst.contains('M') => OrderType::MidpointPeg,
+        "P" if exec_inst.contains('P') => OrderType::MarketPeg,
+        "P" => return Err(invalid(at::EXEC_INST, exec_inst)),
+        other => return Err(invalid(at::ORD_TYPE, other)),
+    };
+    let quantity = decimal(fields, at::ORDER_QTY)?.ok_or(FieldError {
+        tag: at::ORDER_QTY,
+        reason: REQUIRED_TAG_MISSING,
+        text: format!("Required tag missing: {}", at::ORDER_QTY),
+    })?;
+    let time_in_force = match fields.get(&at::TIME_IN_FORCE).map(String::as_str).unwrap_or("0") {
+        "0" => TimeInForce::DAY,
+        "1" => TimeInForce::GTC,
+        "3" => TimeInForce::IOC,
+        "4" => TimeInForce::FOK,
+        "6" => TimeInForce::GTD,
+        other => return Err(invalid(at::TIME_IN_FORCE, other)),
+    };
+    let expire_time = fields
+        .get(&at::EXPIRE_TIME)
+        .map(|value| {
+            NaiveDateTime::parse_from_str(value, "%Y%m%d-%H:%M:%S%.f")
+                .map(|time| time.and_utc())
+                .map_err(|_| invalid(at::EXPIRE_TIME, value))
+        })
+        .transpose()?;
+    if fields.contains_key(&at::MAX_FLOOR) {
+        return Err(FieldError {
+            tag: at::MAX_FLOOR,
+            reason: VALUE_INCORRECT,
+            text: "Reserve orders (MaxFloor) are not supported".to_string(),
+        });
+    }
+
+    let mut order = Order::new_with_options(
+        symbol.to_string(),
+        side,
+        order_type,
+        decimal(fields, at::PRICE)?,
+        quantity,
+        user_id.to_string(),
+        time_in_force,
+        SelfTradePreventionMode::None,
+        exec_inst.contains('6'),
+        expire_time,
+    );
+    order.peg_offset = decimal(fields, at::PEG_OFFSET_VALUE)?;
+    Ok(order)
+}
+
+/// FIX 4.4 order entry acceptor
+///
+/// Clients log on with their user ID as SenderCompID and this acceptor's
+/// CompID as TargetCompID, then send NewOrderSingle (D),
+/// OrderCancelRequest (F), OrderCancelReplaceRequest (G) and
+/// OrderMassCancelRequest (q). Orders go through the ingestion pipeline like
+/// REST orders and are reported with ExecutionReports (8); rejected cancel
+/// and replace requests get an OrderCancelReject (9). Sequence numbers
+/// survive reconnects until a Logon with ResetSeqNumFlag (141=Y). A user
+/// has one session at a time, across this acceptor and the binary gateway.
+///
+/// Self-trade prevention and reserve orders have no FIX 4.4 fields and are
+/// not available; orders default to a time in force of Day.
+pub struct FixAcceptor {
+    comp_id: String,
+    pipeline: Arc<IngestionPipeline>,
+    registry: Arc<OrderRegistry>,
+    /// Sequence state of each client's session while it is logged out
+    sessions: Mutex<HashMap<String, FixSession>>,
+    orders: Mutex<FixOrders>,
+}
+
+type Connection = Framed<TcpStream, FixCodec>;
+
+impl FixAcceptor {
+    /// Create an acceptor submitting to `pipeline`, whose consumers include
+    /// an `ExecutionReportPublisher` for `registry`
+    pub fn new(comp_id: impl Into<String>, pipeline: Arc<IngestionPipeline>, registry: Arc<OrderRegistry>) -> Self {
+        Self {
+            comp_id: comp_id.into(),
+            pipeline,
+            registry,
+            sessions: Mutex::new(HashMap::new()),
+            orders: Mutex::new(FixOrders::default()),
+        }
+    }
+
+    fn orders(&self) -> MutexGuard<'_, FixOrders> {
+        self.orders.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Accept clients until the listener fails
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) {
+        info!("FIX acceptor {} listening on {:?}", self.comp_id, listener.local_addr());
+        loop {
+            let (stream, address) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    error!("FIX acceptor stopped accepting clients: {}", e);
+                    return;
+                }
+            };
+
+            let acceptor = self.clone();
+            tokio::spawn(async move {
+                info!("FIX client {} connected", address);
+                if let Err(e) = acceptor.run_session(stream, address).await {
+                    warn!("FIX session of {} ended: {}", address, e);
+                }
+                info!("FIX client {} disconnected", address);
+            });
+        }
+    }
+
+    /// Log a client on and serve its session
+    async fn run_session(&self, stream: TcpStream, address: SocketAddr) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut connection = Framed::new(stream, FixCodec);
+
+        // Anything but a Logon first is dropped without an answer
+        let logon = match timeout(LOGON_TIMEOUT, connection.next()).await {
+            Ok(Some(message)) => message?.fields().to_map(),
+            Ok(None) | Err(_) => return Ok(()),
+        };
+        if logon.get(&tag::MSG_TYPE).map(String::as_str) != Some(msg_type::LOGON) {
+            warn!("FIX client {} sent {:?} before logging on", address, logon.get(&tag::MSG_TYPE));
+            return Ok(());
+        }
+
+        let user_id = logon.get(&tag::SENDER_COMP_ID).cloned().unwrap_or_default();
+        let heartbeat_interval = logon
+            .get(&tag::HEART_BT_INT)
+            .and_then(|secs| secs.parse::<u16>().ok())
+            .unwrap_or(0);
+        let problem = if user_id.is_empty() || user_id.len() > MAX_USER_ID_LEN {
+            Some(format!("SenderCompID must be 1 to {} bytes", MAX_USER_ID_LEN))
+        } else if logon.get(&tag::TARGET_COMP_ID) != Some(&self.comp_id) {
+            Some(format!("TargetCompID must be {}", self.comp_id))
+        } else if logon.get(&tag::ENCRYPT_METHOD).map(String::as_str) != Some("0") {
+            Some("EncryptMethod must be 0".to_string())
+        } else if !(1..=MAX_HEARTBEAT_INTERVAL_SECS).contains(&heartbeat_interval) {
+            Some(format!("HeartBtInt must be 1 to {} seconds", MAX_HEARTBEAT_INTERVAL_SECS))
+        } else {
+            None
+        };
+        if let Some(reason) = problem {
+            let mut session = FixSession::new(self.comp_id.clone(), user_id);
+            return logout(&mut connection, &mut session, &reason).await;
+        }
+
+        let (outbox, mut reports) = mpsc::unbounded_channel();
+        if !self.registry.open_session(&user_id, outbox) {
+            let mut session = FixSession::new(self.comp_id.clone(), user_id.clone());
+            return logout(&mut connection, &mut session, &format!("{} is already logged in", user_id)).await;
+        }
+        let mut session = self
+            .sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&user_id)
+            .unwrap_or_else(|| FixSession::new(self.comp_id.clone(), user_id.clone()));
+        info!("FIX client {} logged on as {}", address, user_id);
+
+        let result = self
+            .serve_session(&mut connection, &mut session, &logon, heartbeat_interval, &mut reports)
+            .await;
+        self.registry.close_session(&user_id);
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(user_id, session);
+        result
+    }
+
+    async fn serve_session(
+        &self,
+        connection: &mut Connection,
+        session: &mut FixSession,
+        logon: &HashMap<u32, String>,
+        heartbeat_interval_secs: u16,
+        reports: &mut mpsc::UnboundedReceiver<Message>,
+    ) -> io::Result<()> {
+        let reset = logon.get(&tag::RESET_SEQ_NUM_FLAG).map(String::as_str) == Some("Y");
+        if reset {
+            session.reset();
+        }
+        let logon_seq = logon.get(&tag::MSG_SEQ_NUM).and_then(|seq| seq.parse::<u64>().ok());
+        if logon_seq.is_none_or(|seq| seq < session.next_target_seq()) {
+            let reason = format!(
+                "MsgSeqNum too low, expecting {} but received {:?}",
+                session.next_target_seq(),
+                logon_seq
+            );
+            return logout(connection, session, &reason).await;
+        }
+
+        session.start(Duration::from_secs(heartbeat_interval_secs.into()));
+        let mut reply = vec![
+            (tag::ENCRYPT_METHOD, "0".to_string()),
+            (tag::HEART_BT_INT, heartbeat_interval_secs.to_string()),
+        ];
+        if reset {
+            reply.push((tag::RESET_SEQ_NUM_FLAG, "Y".to_string()));
+        }
+        connection.send(session.send(msg_type::LOGON, reply)).await?;
+        match session.receive(logon) {
+            Ok(Inbound::Handled(replies)) => send_all(connection, replies).await?,
+            Ok(_) => {}
+            Err(reason) => return logout(connection, session, &reason).await,
+        }
+
+        let mut timer = interval(Duration::from_secs(1));
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                message = connection.next() => {
+                    let fields = match message {
+                        Some(Ok(message)) => message.fields().to_map(),
+                        Some(Err(e)) => return logout(connection, session, &format!("malformed message: {}", e)).await,
+                        None => return Ok(()),
+                    };
+                    match session.receive(&fields) {
+                        Ok(Inbound::Application) => {
+                            let user_id = session.target_comp_id().to_string();
+                            let replies = self.on_application_message(session, &user_id, &fields).await;
+                            send_all(connection, replies).await?;
+                        }
+                        Ok(Inbound::Handled(replies)) => send_all(connection, replies).await?,
+                        Ok(Inbound::Logout(_)) => return logout(connection, session, "").await,
+                        Err(reason) => return logout(connection, session, &reason).await,
+                    }
+                }
+                Some(report) = reports.recv() => {
+                    // Send everything already queued before flushing
+                    let mut next = Some(report);
+                    while let Some(Message::ExecutionReport(report)) = next {
+                        let fields = self.orders().report_fields(&report);
+                        connection.feed(session.send(app_msg_type::EXECUTION_REPORT, fields)).await?;
+                        next = reports.try_recv().ok();
+                    }
+                    connection.flush().await?;
+                }
+                _ = timer.tick() => {
+                    match session.poll_timer(Instant::now()) {
+                        Ok(Some(message)) => connection.send(message).await?,
+                        Ok(None) => {}
+                        Err(reason) => return logout(connection, session, &reason).await,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handle an application message in sequence; the replies to send
+    async fn on_application_message(
+        &self,
+        session: &mut FixSession,
+        user_id: &str,
+        fields: &HashMap<u32, String>,
+    ) -> Vec<String> {
+        let seq = fields
+            .get(&tag::MSG_SEQ_NUM)
+            .and_then(|seq| seq.parse::<u64>().ok())
+            .unwrap_or_default();
+        let msg_type = fields.get(&tag::MSG_TYPE).map(String::as_str).unwrap_or_default();
+        let result = match msg_type {
+            app_msg_type::NEW_ORDER_SINGLE => self.new_order(user_id, fields).await,
+            app_msg_type::ORDER_CANCEL_REQUEST => self.cancel_order(user_id, fields).await,
+            app_msg_type::ORDER_CANCEL_REPLACE_REQUEST => self.replace_order(user_id, fields).await,
+            app_msg_type::ORDER_MASS_CANCEL_REQUEST => self.mass_cancel(user_id, fields).await,
+            other => {
+                let reject = vec![
+                    (tag::REF_SEQ_NUM, seq.to_string()),
+                    (tag::REF_MSG_TYPE, other.to_string()),
+                    (at::BUSINESS_REJECT_REASON, "3".to_string()),
+                    (tag::TEXT, format!("Unsupported message type: {}", other)),
+                ];
+                return vec![session.send(app_msg_type::BUSINESS_MESSAGE_REJECT, reject)];
+            }
+        };
+        match result {
+            Ok(Some((msg_type, fields))) => vec![session.send(msg_type, fields)],
+            Ok(None) => Vec::new(),
+            Err(e) => vec![session.reject(seq, Some(e.tag), e.reason, &e.text)],
+        }
+    }
+
+    async fn new_order(
+        &self,
+        user_id: &str,
+        fields: &HashMap<u32, String>,
+    ) -> Result<Option<(&'static str, Vec<(u32, String)>)>, FieldError> {
+        let cl_ord_id = required(fields, at::CL_ORD_ID)?.to_string();
+        let mut order = parse_new_order(user_id, fields)?;
+        order.id = clock::new_id();
+        order.timestamp = clock::now();
+
+        {
+            let mut orders = self.orders();
+            if orders.by_cl_ord_id.contains_key(&(user_id.to_string(), cl_ord_id.clone())) {
+                let text = format!("Duplicate ClOrdID: {}", cl_ord_id);
+                return Ok(Some(rejection(&order, &cl_ord_id, RejectReason::DuplicateOrder, text)));
+            }
+            orders.insert(
+                order.id,
+                FixOrder {
+                    user_id: user_id.to_string(),
+                    cl_ord_id: cl_ord_id.clone(),
+                    pending: None,
+                    order_qty: order.quantity,
+                    filled_notional: Decimal::ZERO,
+                },
+            );
+        }
+        if !self.registry.track(&order) {
+            self.orders().remove(order.id);
+            let text = format!("Duplicate order ID: {}", order.id);
+            return Ok(Some(rejection(&order, &cl_ord_id, RejectReason::DuplicateOrder, text)));
+        }
+        if let Err(e) = self.pipeline.submit_order(order.clone()).await {
+            self.registry.untrack(order.id);
+            self.orders().remove(order.id);
+            return Ok(Some(rejection(&order, &cl_ord_id, reject_reason(&e), e.to_string())));
+        }
+        Ok(None)
+    }
+
+    async fn cancel_order(
+        &self,
+        user_id: &str,
+        fields: &HashMap<u32, String>,
+    ) -> Result<Option<(&'static str, Vec<(u32, String)>)>, FieldError> {
+        let cl_ord_id = required(fields, at::CL_ORD_ID)?.to_string();
+        let orig_cl_ord_id = required(fields, at::ORIG_CL_ORD_ID)?.to_string();
+        let Some((order_id, symbol)) = self.begin_request(user_id, fields, &cl_ord_id, &orig_cl_ord_id) else {
+            return Ok(Some(cancel_reject(None, &cl_ord_id, &orig_cl_ord_id, "1", RejectReason::UnknownOrder, "Unknown order")));
+        };
+        match self.pipeline.cancel_order(&symbol, order_id).await {
+            Ok(_) => Ok(None),
+            Err(e) => Ok(Some(self.fail_request(order_id, &cl_ord_id, &orig_cl_ord_id, "1", &e))),
+        }
+    }
+
+    async fn replace_order(
+        &self,
+        user_id: &str,
+        fields: &HashMap<u32, String>,
+    ) -> Result<Option<(&'static str, Vec<(u32, String)>)>, FieldError> {
+        let cl_ord_id = required(fields, at::CL_ORD_ID)?.to_string();
+        let orig_cl_ord_id = required(fields, at::ORIG_CL_ORD_ID)?.to_string();
+        let new_price = decimal(fields, at::PRICE)?;
+        let new_quantity = decimal(fields, at::ORDER_QTY)?;
+        let Some((order_id, symbol)) = self.begin_request(user_id, fields, &cl_ord_id, &orig_cl_ord_id) else {
+            return Ok(Some(cancel_reject(None, &cl_ord_id, &orig_cl_ord_id, "2", RejectReason::UnknownOrder, "Unknown order")));
+        };
+        match self.pipeline.amend_order(&symbol, order_id, new_price, new_quantity).await {
+            Ok(_) => Ok(None),
+            Err(e) => Ok(Some(self.fail_request(order_id, &cl_ord_id, &orig_cl_ord_id, "2", &e))),
+        }
+    }
+
+    /// Find the order of a cancel or replace request and mark the request
+    /// pending, so the report it leads to carries its ClOrdIDs
+    fn begin_request(
+        &self,
+        user_id: &str,
+        fields: &HashMap<u32, String>,
+        cl_ord_id: &str,
+        orig_cl_ord_id: &str,
+    ) -> Option<(Uuid, String)> {
+        let mut orders = self.orders();
+        let order_id = orders.find(user_id, fields)?;
+        let symbol = self.registry.order_symbol(order_id, user_id)?;
+        let order = orders.orders.get_mut(&order_id)?;
+        order.pending = Some((cl_ord_id.to_string(), orig_cl_ord_id.to_string()));
+        Some((order_id, symbol))
+    }
+
+    fn fail_request(
+        &self,
+        order_id: Uuid,
+        cl_ord_id: &str,
+        orig_cl_ord_id: &str,
+        response_to: &str,
+        error: &OrderBookError,
+    ) -> (&'static str, Vec<(u32, String)>) {
+        if let Some(order) = self.orders().orders.get_mut(&order_id) {
+            order.pending = None;
+        }
+        let reason = reject_reason(error);
+        cancel_reject(Some(order_id), cl_ord_id, orig_cl_ord_id, response_to, reason, &error.to_string())
+    }
+
+    async fn mass_cancel(
+        &self,
+        user_id: &str,
+        fields: &HashMap<u32, String>,
+    ) -> Result<Option<(&'static str, Vec<(u32, String)>)>, FieldError> {
+        let cl_ord_id = required(fields, at::CL_ORD_ID)?.to_string();
+        let request_type = required(fields, at::MASS_CANCEL_REQUEST_TYPE)?.to_string();
+        let mut report = vec![
+            (at::ORDER_ID, "NONE".to_string()),
+            (at::CL_ORD_ID, cl_ord_id),
+            (at::MASS_CANCEL_REQUEST_TYPE, request_type.clone()),
+        ];
+        let symbol = match request_type.as_str() {
+            "1" => Some(required(fields, at::SYMBOL)?),
+            "7" => None,
+            _ => {
+                report.push((at::MASS_CANCEL_RESPONSE, "0".to_string()));
+                report.push((at::MASS_CANCEL_REJECT_REASON, "0".to_string()));
+                report.push((tag::TEXT, "Only cancels by symbol (1) and of all orders (7) are supported".to_string()));
+                return Ok(Some((app_msg_type::ORDER_MASS_CANCEL_REPORT, report)));
+            }
+        };
+
+        let mut cancelled = 0;
+        for (order_id, symbol) in self.registry.working_orders(user_id, symbol) {
+            if self.pipeline.cancel_order(&symbol, order_id).await.is_ok() {
+                cancelled += 1;
+            }
+        }
+        report.push((at::MASS_CANCEL_RESPONSE, request_type));
+        report.push((at::TOTAL_AFFECTED_ORDERS, cancelled.to_string()));
+        Ok(Some((app_msg_type::ORDER_MASS_CANCEL_REPORT, report)))
+    }
+}
+
+/// ExecutionReport rejecting a new order
+fn rejection(order: &Order, cl_ord_id: &str, reason: RejectReason, text: String) -> (&'static str, Vec<(u32, String)>) {
+    let mut fields = vec![
+        (at::ORDER_ID, order.id.to_string()),
+        (at::CL_ORD_ID, cl_ord_id.to_string()),
+        (at::EXEC_ID, clock::new_id().to_string()),
+        (at::EXEC_TYPE, "8".to_string()),
+        (at::ORD_STATUS, "8".to_string()),
+        (at::ORD_REJ_REASON, ord_rej_reason(reason).to_string()),
+        (at::SYMBOL, order.symbol.clone()),
+        (at::SIDE, side_code(order.side).to_string()),
+        (at::ORDER_QTY, order.quantity.to_string()),
+    ];
+    if let Some(price) = order.price {
+        fields.push((at::PRICE, price.to_string()));
+    }
+    fields.extend([
+        (at::LEAVES_QTY, "0".to_string()),
+        (at::CUM_QTY, "0".to_string()),
+        (at::AVG_PX, "0".to_string()),
+        (at::TRANSACT_TIME, Utc::now().format(TIMESTAMP_FORMAT).to_string()),
+        (tag::TEXT, text),
+    ]);
+    (app_msg_type::EXECUTION_REPORT, fields)
+}
+
+/// OrderCancelReject of a cancel (`response_to` 1) or replace (2) request
+fn cancel_reject(
+    order_id: Option<Uuid>,
+    cl_ord_id: &str,
+    orig_cl_ord_id: &str,
+    response_to: &str,
+    reason: RejectReason,
+    text: &str,
+) -> (&'static str, Vec<(u32, String)>) {
+    let fields = vec![
+        (at::ORDER_ID, order_id.map_or_else(|| "NONE".to_string(), |id| id.to_string())),
+        (at::CL_ORD_ID, cl_ord_id.to_string()),
+        (at::ORIG_CL_ORD_ID, orig_cl_ord_id.to_string()),
+        // The order is unknown, or unchanged and still working
+        (at::ORD_STATUS, if order_id.is_some() { "0" } else { "8" }.to_string()),
+        (at::CXL_REJ_RESPONSE_TO, response_to.to_string()),
+        (at::CXL_REJ_REASON, if reason == RejectReason::UnknownOrder { "1" } else { "99" }.to_string()),
+        (tag::TEXT, text.to_string()),
+    ];
+    (app_msg_type::ORDER_CANCEL_REJECT, fields)
+}
+
+async fn send_all(connection: &mut Connection, messages: Vec<String>) -> io::Result<()> {
+    for message in messages {
+        connection.feed(message).await?;
+    }
+    connection.flush().await
+}
+
+/// Say goodbye and end the session
+async fn logout(connection: &mut Connection, session: &mut FixSession, text: &str) -> io::Result<()> {
+    if !text.is_empty() {
+        warn!("Ending FIX session with {}: {}", session.target_comp_id(), text);
+    }
+    connection.send(session.logout(text)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn fields(pairs: &[(u32, &str)]) -> HashMap<u32, String> {
+        pairs.iter().map(|(tag, value)| (*tag, value.to_string())).collect()
+    }
+
+    #[test]
+    fn test_new_order_single_fields() {
+        let order = parse_new_order(
+            "alice",
+            &fields(&[
+                (at::SYMBOL, "AAPL"),
+                (at::SIDE, "2"),
+                (at::ORD_TYPE, "P"),
+                (at::EXEC_INST, "M6"),
+                (at::ORDER_QTY, "10"),
+                (at::TIME_IN_FORCE, "6"),
+                (at::EXPIRE_TIME, "20300101-12:00:00.000"),
+                (at::PEG_OFFSET_VALUE, "-0.05"),
+            ]),
+        )
+        .unwrap_or_else(|e| panic!("{}", e.text));
+        assert_eq!((order.side, order.order_type, order.time_in_force), (OrderSide::Sell, OrderType::MidpointPeg, TimeInForce::GTD));
+        assert!(order.post_only);
+        assert_eq!((order.quantity, order.peg_offset), (dec!(10), Some(dec!(-0.05))));
+        assert_eq!(order.expire_time.unwrap().to_rfc3339(), "2030-01-01T12:00:00+00:00");
+        assert_eq!(order.user_id, "alice");
+
+        // Day unless told otherwise
+        let order = parse_new_order(
+            "alice",
+            &fields(&[(at::SYMBOL, "AAPL"), (at::SIDE, "1"), (at::ORD_TYPE, "2"), (at::PRICE, "1.5"), (at::ORDER_QTY, "1")]),
+        )
+        .unwrap_or_else(|e| panic!("{}", e.text));
+        assert_eq!((order.time_in_force, order.price), (TimeInForce::DAY, Some(dec!(1.5))));
+
+        let missing = parse_new_order("alice", &fields(&[(at::SYMBOL, "AAPL"), (at::SIDE, "1")])).err().unwrap();
+        assert_eq!((missing.tag, missing.reason), (at::ORD_TYPE, REQUIRED_TAG_MISSING));
+        let wrong = parse_new_order("alice", &fields(&[(at::SYMBOL, "AAPL"), (at::SIDE, "B")])).err().unwrap();
+        assert_eq!((wrong.tag, wrong.reason), (at::SIDE, VALUE_INCORRECT));
+    }
+}
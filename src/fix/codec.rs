@@ -0,0 +1,207 @@
+//! FIX message framing on a TCP stream
+
+use bytes::{BufMut, Bytes, BytesMut};
+use std::fmt;
+use std::io::{self, Error, ErrorKind};
+use tokio_util::codec::{Decoder, Encoder};
+
+use super::fields::FixFields;
+
+/// Largest BodyLength accepted from a peer
+pub const MAX_MESSAGE_LEN: usize = 64 * 1024;
+
+const SOH: u8 = 0x01;
+
+/// Longest BeginString value we wait for (`FIXT.1.1` and the like)
+const MAX_BEGIN_STRING_LEN: usize = 16;
+
+/// Digits of a BodyLength up to `MAX_MESSAGE_LEN`
+const MAX_BODY_LENGTH_DIGITS: usize = 5;
+
+/// The CheckSum field that ends every message: `10=nnn|`
+const CHECKSUM_LEN: usize = 7;
+
+/// A complete message off the wire, its BodyLength and CheckSum checked
+#[derive(Clone, PartialEq, Eq)]
+pub struct FixFrame {
+    bytes: Bytes,
+}
+
+impl FixFrame {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.bytes
+    }
+
+    /// Tag lookups over the message, borrowing it
+    pub fn fields(&self) -> FixFields<'_> {
+        FixFields::new(&self.bytes)
+    }
+
+    pub fn msg_type(&self) -> Option<&str> {
+        self.fields().msg_type()
+    }
+}
+
+impl fmt::Debug for FixFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FixFrame({})", String::from_utf8_lossy(&self.bytes).replace('\x01', "|"))
+    }
+}
+
+/// Frames FIX messages (`8=FIX.4.4|9=...|...|10=nnn|`) by their BodyLength
+/// (9) and checks their CheckSum (10)
+///
+/// Bytes before the BeginString of a message are skipped. A BodyLength that
+/// does not follow the BeginString, is not a number or is over
+/// `MAX_MESSAGE_LEN`, and a CheckSum that is missing or wrong, are errors:
+/// the stream cannot be trusted after them.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FixCodec;
+
+impl Decoder for FixCodec {
+    type Item = FixFrame;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<FixFrame>> {
+        match src.windows(2).position(|window| window == b"8=") {
+            Some(0) => {}
+            Some(start) => {
+                let _ = src.split_to(start);
+            }
+            None => {
+                // Keep a trailing '8' that may start the next message
+                let keep = usize::from(src.last() == Some(&b'8'));
+                let _ = src.split_to(src.len() - keep);
+                return Ok(None);
+            }
+        }
+
+        let Some(begin_string_end) = find_soh(src, 2, MAX_BEGIN_STRING_LEN)? else {
+            return Ok(None);
+        };
+        let body_length_start = begin_string_end + 1;
+        let Some(tag) = src.get(body_length_start..body_length_start + 2) else {
+            return Ok(None);
+        };
+        if tag != b"9=" {
+            return Err(invalid("BodyLength (9) must follow BeginString".to_string()));
+        }
+        let Some(body_length_end) = find_soh(src, body_length_start + 2, MAX_BODY_LENGTH_DIGITS)? else {
+            return Ok(None);
+        };
+        let digits = &src[body_length_start + 2..body_length_end];
+        if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+            return Err(invalid(format!("BodyLength is not a number: {}", String::from_utf8_lossy(digits))));
+        }
+        let body_length = digits.iter().fold(0usize, |len, digit| len * 10 + usize::from(digit - b'0'));
+        if body_length > MAX_MESSAGE_LEN {
+            return Err(invalid(format!("FIX message longer than {} bytes", MAX_MESSAGE_LEN)));
+        }
+
+        let checksum_start = body_length_end + 1 + body_length;
+        let len = checksum_start + CHECKSUM_LEN;
+        if src.len() < len {
+            src.reserve(len - src.len());
+            return Ok(None);
+        }
+        let trailer = &src[checksum_start..len];
+        if &trailer[..3] != b"10=" || trailer[6] != SOH || !trailer[3..6].iter().all(u8::is_ascii_digit) {
+            return Err(invalid(format!("no CheckSum (10) after a BodyLength of {}", body_length)));
+        }
+        let expected = trailer[3..6].iter().fold(0u32, |sum, digit| sum * 10 + u32::from(digit - b'0'));
+        let actual = checksum(&src[..checksum_start]);
+        if expected != u32::from(actual) {
+            return Err(invalid(format!("CheckSum is {:03}, expected {:03}", expected, actual)));
+        }
+
+        Ok(Some(FixFrame {
+            bytes: src.split_to(len).freeze(),
+        }))
+    }
+}
+
+/// Sum of the bytes modulo 256
+pub fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+/// Offset of the SOH ending a value that starts at `from` and is at most
+/// `max_len` long; `None` until it arrives
+fn find_soh(src: &BytesMut, from: usize, max_len: usize) -> io::Result<Option<usize>> {
+    let window = &src[from.min(src.len())..src.len().min(from + max_len + 1)];
+    match window.iter().position(|&b| b == SOH) {
+        Some(len) => Ok(Some(from + len)),
+        None if window.len() > max_len => Err(invalid(format!(
+            "field at byte {} longer than {} bytes",
+            from, max_len
+        ))),
+        None => Ok(None),
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+impl Encoder<String> for FixCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: String, dst: &mut BytesMut) -> io::Result<()> {
+        dst.put_slice(message.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ctrader_fix::messages::FixMessage;
+
+    fn heartbeat() -> String {
+        let mut heartbeat = FixMessage::new();
+        heartbeat.add_field(49, "CLIENT");
+        heartbeat.add_field(34, 2);
+        heartbeat.build("0")
+    }
+
+    #[test]
+    fn test_messages_are_framed_by_body_length() {
+        let heartbeat = heartbeat();
+        // A CheckSum lookalike inside a value does not end the message
+        let mut text = FixMessage::new();
+        text.add_field(58, "a\x0110=123\x01b");
+        let text = text.build("1");
+
+        let mut buf = BytesMut::from(format!("noise{}{}{}", heartbeat, text, heartbeat).as_bytes());
+        let mut partial = buf.split_to(buf.len() - 3);
+        assert_eq!(FixCodec.decode(&mut partial).unwrap().unwrap().as_bytes(), heartbeat.as_bytes());
+        let frame = FixCodec.decode(&mut partial).unwrap().unwrap();
+        assert_eq!(frame.as_bytes(), text.as_bytes());
+        assert_eq!(frame.msg_type(), Some("1"));
+        assert!(FixCodec.decode(&mut partial).unwrap().is_none());
+
+        partial.unsplit(buf);
+        assert_eq!(FixCodec.decode(&mut partial).unwrap().unwrap().fields().get_str(34), Some("2"));
+        assert!(partial.is_empty());
+    }
+
+    #[test]
+    fn test_bad_body_length_or_checksum_is_an_error() {
+        let heartbeat = heartbeat();
+        let wrong_checksum = format!("{}{:03}\x01", &heartbeat[..heartbeat.len() - 4], (checksum(heartbeat.as_bytes()) as u32 + 1) % 256);
+        let error = FixCodec.decode(&mut BytesMut::from(wrong_checksum.as_bytes())).unwrap_err();
+        assert!(error.to_string().contains("CheckSum"), "{}", error);
+
+        let short_body = &b"8=FIX.4.4\x019=5\x0135=0\x0134=2\x0110=000\x01"[..];
+        assert!(FixCodec.decode(&mut BytesMut::from(short_body)).is_err());
+
+        let huge = format!("8=FIX.4.4\x019={}\x01", MAX_MESSAGE_LEN + 1);
+        assert!(FixCodec.decode(&mut BytesMut::from(huge.as_bytes())).is_err());
+        assert!(FixCodec.decode(&mut BytesMut::from(&b"8=FIX.4.4\x0135=0\x01"[..])).is_err());
+        assert!(FixCodec.decode(&mut BytesMut::from(&b"8=FIX.4.4\x019=12"[..])).unwrap().is_none());
+    }
+}
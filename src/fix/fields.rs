@@ -0,0 +1,223 @@
+//! FIX fields read in place from the bytes of a message
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+const SOH: u8 = 0x01;
+
+/// CheckSum, the last field of every message
+const CHECKSUM: u32 = 10;
+
+/// Tag lookups, over parsed maps and borrowed fields alike
+pub trait FieldMap {
+    /// Value of `tag` as text
+    fn field(&self, tag: u32) -> Option<&str>;
+}
+
+impl FieldMap for HashMap<u32, String> {
+    fn field(&self, tag: u32) -> Option<&str> {
+        self.get(&tag).map(String::as_str)
+    }
+}
+
+impl FieldMap for FixFields<'_> {
+    fn field(&self, tag: u32) -> Option<&str> {
+        self.get_str(tag)
+    }
+}
+
+/// The fields of a raw FIX message (`8=FIX.4.4|9=...|35=...|...|10=nnn|`),
+/// looked up without copying
+///
+/// Lookups scan the message, which for the few tags read per message beats
+/// building a map. Fields that are not `tag=value` with a numeric tag are
+/// skipped.
+#[derive(Debug, Clone, Copy)]
+pub struct FixFields<'a> {
+    raw: &'a [u8],
+}
+
+impl<'a> FixFields<'a> {
+    pub fn new(raw: &'a [u8]) -> Self {
+        Self { raw }
+    }
+
+    pub fn iter(&self) -> FieldIter<'a> {
+        FieldIter { raw: self.raw, pos: 0 }
+    }
+
+    /// Value of the first `tag` field
+    pub fn get(&self, tag: u32) -> Option<&'a [u8]> {
+        self.iter().find(|(field_tag, _)| *field_tag == tag).map(|(_, value)| value)
+    }
+
+    /// Value of the first `tag` field, if it is UTF-8
+    pub fn get_str(&self, tag: u32) -> Option<&'a str> {
+        self.get(tag).and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    /// Value of the first `tag` field parsed as `T`
+    pub fn parse<T: FromStr>(&self, tag: u32) -> Option<T> {
+        self.get_str(tag)?.parse().ok()
+    }
+
+    pub fn msg_type(&self) -> Option<&'a str> {
+        self.get_str(35)
+    }
+
+    /// Entries of the repeating group counted by `count_tag`, each starting
+    /// at a `delimiter` field (e.g. NoMDEntries 268 and MDEntryType 269)
+    ///
+    /// An entry runs up to the next delimiter, the last one up to the
+    /// CheckSum, so fields after a group that ends the body are read as part
+    /// of its last entry. At most the group's count of entries is returned.
+    pub fn group(&self, count_tag: u32, delimiter: u32) -> Group<'a> {
+        let mut fields = self.iter();
+        let count = fields
+            .by_ref()
+            .find(|(tag, _)| *tag == count_tag)
+            .and_then(|(_, count)| std::str::from_utf8(count).ok()?.parse::<usize>().ok())
+            .unwrap_or(0);
+        let start = loop {
+            match fields.next_field() {
+                Some((start, tag, _)) if tag == delimiter => break Some(start),
+                Some((_, CHECKSUM, _)) | None => break None,
+                Some(_) => {}
+            }
+        };
+
+        match start {
+            Some(start) => Group {
+                rest: &fields.raw[start..],
+                delimiter,
+                remaining: count,
+            },
+            None => Group {
+                rest: &[],
+                delimiter,
+                remaining: 0,
+            },
+        }
+    }
+
+    /// All fields as owned strings; of repeated tags the last one wins, as
+    /// with `parse_fix_message`
+    pub fn to_map(&self) -> HashMap<u32, String> {
+        self.iter()
+            .map(|(tag, value)| (tag, String::from_utf8_lossy(value).into_owned()))
+            .collect()
+    }
+}
+
+/// Fields of a message in order, as `(tag, value)`
+pub struct FieldIter<'a> {
+    raw: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> FieldIter<'a> {
+    /// Next field and the offset it starts at
+    fn next_field(&mut self) -> Option<(usize, u32, &'a [u8])> {
+        while self.pos < self.raw.len() {
+            let start = self.pos;
+            // The tag is read as its digits go by, in the same pass that
+            // finds the end of the field
+            let mut tag = 0u32;
+            let mut digits = 0;
+            let mut at = start;
+            while let Some(&digit @ b'0'..=b'9') = self.raw.get(at) {
+                tag = tag.wrapping_mul(10).wrapping_add(u32::from(digit - b'0'));
+                digits += 1;
+                at += 1;
+            }
+            let value_start = at + 1;
+            let end = match self.raw[at..].iter().position(|&b| b == SOH) {
+                Some(len) => at + len,
+                None => self.raw.len(),
+            };
+            self.pos = end + 1;
+            // Nine digits always fit a u32
+            if (1..=9).contains(&digits) && self.raw.get(at) == Some(&b'=') {
+                return Some((start, tag, &self.raw[value_start..end]));
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for FieldIter<'a> {
+    type Item = (u32, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_field().map(|(_, tag, value)| (tag, value))
+    }
+}
+
+/// Entries of a repeating group, see `FixFields::group`
+pub struct Group<'a> {
+    /// From the delimiter of the next entry on
+    rest: &'a [u8],
+    delimiter: u32,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Group<'a> {
+    type Item = FixFields<'a>;
+
+    fn next(&mut self) -> Option<FixFields<'a>> {
+        if self.remaining == 0 || self.rest.is_empty() {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let mut fields = FieldIter { raw: self.rest, pos: 0 };
+        fields.next_field();
+        let (end, more) = loop {
+            match fields.next_field() {
+                Some((start, tag, _)) if tag == self.delimiter => break (start, true),
+                Some((start, CHECKSUM, _)) => break (start, false),
+                Some(_) => {}
+                None => break (self.rest.len(), false),
+            }
+        };
+
+        let entry = FixFields::new(&self.rest[..end]);
+        self.rest = if more { &self.rest[end..] } else { &[] };
+        Some(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAPSHOT: &[u8] =
+        b"8=FIX.4.4\x019=99\x0135=W\x0155=41\x01268=3\x01269=0\x01270=2650.5\x01271=100\x01269=1\x01270=2651\x01269=2\x01270=2650.8\x0110=000\x01";
+
+    #[test]
+    fn test_lookups_and_repeating_groups() {
+        let fields = FixFields::new(SNAPSHOT);
+        assert_eq!(fields.msg_type(), Some("W"));
+        assert_eq!(fields.get(55), Some(&b"41"[..]));
+        assert_eq!(fields.parse::<u32>(268), Some(3));
+        // The first of repeated tags
+        assert_eq!(fields.get_str(270), Some("2650.5"));
+        assert_eq!(fields.get(999), None);
+        assert_eq!(fields.to_map()[&270], "2650.8");
+
+        let entries: Vec<_> = fields
+            .group(268, 269)
+            .map(|entry| (entry.get_str(269).unwrap(), entry.get_str(270).unwrap(), entry.get_str(271)))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![("0", "2650.5", Some("100")), ("1", "2651", None), ("2", "2650.8", None)]
+        );
+
+        // A count lower than the entries there limits the group; no count, no group
+        let short = b"35=W\x01268=1\x01269=0\x01270=1\x01269=1\x01270=2\x01";
+        assert_eq!(FixFields::new(short).group(268, 269).count(), 1);
+        assert_eq!(FixFields::new(b"35=W\x01269=0\x01").group(268, 269).count(), 0);
+        assert_eq!(FixFields::new(b"x=1\x01=2\x0135=0").iter().collect::<Vec<_>>(), vec![(35, &b"0"[..])]);
+    }
+}
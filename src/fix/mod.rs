@@ -0,0 +1,23 @@
+//! FIX 4.4 order entry
+//!
+//! An acceptor for clients that speak FIX rather than the binary gateway
+//! protocol. Orders take the same ingestion pipeline and order registry as
+//! gateway orders, so a user is logged on through one of the two at a time.
+//!
+//! - `codec` - Message framing on a TCP stream, by BodyLength and CheckSum
+//! - `fields` - Tag and repeating group lookups over the bytes of a message
+//! - `session` - Logon state, sequence numbers, resends and heartbeats;
+//!   also the session layer of the cTrader market data client
+//! - `acceptor` - Listener and the order entry messages
+//!
+//! Messages are built with `FixMessage::build` from `ctrader_fix`.
+
+pub mod acceptor;
+pub mod codec;
+pub mod fields;
+pub mod session;
+
+pub use acceptor::FixAcceptor;
+pub use codec::{FixCodec, FixFrame};
+pub use fields::{FieldMap, FixFields};
+pub use session::{FixSession, Inbound};
@@ -0,0 +1,607 @@
+//! FIX 4.4 session layer
+//!
+//! Sequence numbers, the store of sent messages behind ResendRequest,
+//! SequenceReset, TestRequest and heartbeats. `FixSession` does no IO: it
+//! stamps outgoing messages and returns the wire strings to send, and tells
+//! its owner what to do with incoming ones.
+
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use tokio::time::{Duration, Instant};
+
+use super::fields::FieldMap;
+use crate::ctrader_fix::messages::FixMessage;
+
+/// Application messages kept for resending unless `with_resend_limit` says
+/// otherwise; older ones are answered with a gap fill
+pub const DEFAULT_RESEND_LIMIT: usize = 10_000;
+
+pub const BEGIN_STRING: &str = "FIX.4.4";
+
+/// Session-level message types
+pub mod msg_type {
+    pub const HEARTBEAT: &str = "0";
+    pub const TEST_REQUEST: &str = "1";
+    pub const RESEND_REQUEST: &str = "2";
+    pub const REJECT: &str = "3";
+    pub const SEQUENCE_RESET: &str = "4";
+    pub const LOGOUT: &str = "5";
+    pub const LOGON: &str = "A";
+}
+
+/// Tags of the standard header and the session-level messages
+pub mod tag {
+    pub const BEGIN_SEQ_NO: u32 = 7;
+    pub const BEGIN_STRING: u32 = 8;
+    pub const END_SEQ_NO: u32 = 16;
+    pub const MSG_SEQ_NUM: u32 = 34;
+    pub const MSG_TYPE: u32 = 35;
+    pub const NEW_SEQ_NO: u32 = 36;
+    pub const POSS_DUP_FLAG: u32 = 43;
+    pub const REF_SEQ_NUM: u32 = 45;
+    pub const SENDER_COMP_ID: u32 = 49;
+    pub const SENDER_SUB_ID: u32 = 50;
+    pub const SENDING_TIME: u32 = 52;
+    pub const TARGET_COMP_ID: u32 = 56;
+    pub const TARGET_SUB_ID: u32 = 57;
+    pub const TEXT: u32 = 58;
+    pub const ENCRYPT_METHOD: u32 = 98;
+    pub const HEART_BT_INT: u32 = 108;
+    pub const TEST_REQ_ID: u32 = 112;
+    pub const ORIG_SENDING_TIME: u32 = 122;
+    pub const GAP_FILL_FLAG: u32 = 123;
+    pub const RESET_SEQ_NUM_FLAG: u32 = 141;
+    pub const REF_TAG_ID: u32 = 371;
+    pub const REF_MSG_TYPE: u32 = 372;
+    pub const SESSION_REJECT_REASON: u32 = 373;
+}
+
+/// Format of SendingTime and other UTC timestamps
+pub const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H:%M:%S%.3f";
+
+pub fn is_admin(msg_type: &str) -> bool {
+    matches!(msg_type, "0" | "1" | "2" | "3" | "4" | "5" | "A")
+}
+
+/// What to do with an incoming message
+#[derive(Debug, PartialEq)]
+pub enum Inbound {
+    /// An application message in sequence: process it
+    Application,
+    /// A session-level message (or a duplicate, or one after a gap) the
+    /// session handled; send the replies
+    Handled(Vec<String>),
+    /// The peer logs out with this text; reply with a Logout and disconnect
+    Logout(String),
+}
+
+/// An application message kept for resending
+#[derive(Debug, Clone)]
+struct SentMessage {
+    msg_type: String,
+    fields: Vec<(u32, String)>,
+    sending_time: String,
+}
+
+/// One side of a FIX session between two CompIDs
+///
+/// Sequence numbers start at 1 and survive disconnects until `reset`, so a
+/// session can pick up where it left off when the peer logs on again.
+#[derive(Debug)]
+pub struct FixSession {
+    sender_comp_id: String,
+    target_comp_id: String,
+    /// SenderSubID (50) and TargetSubID (57) stamped on outgoing messages
+    sub_ids: Option<(String, String)>,
+    next_sender_seq: u64,
+    next_target_seq: u64,
+    /// The last application messages sent, by sequence number
+    sent: BTreeMap<u64, SentMessage>,
+    /// How many messages `sent` keeps
+    resend_limit: usize,
+    /// Incoming messages up to this sequence number were asked for again
+    resend_requested_to: Option<u64>,
+    heartbeat_interval: Duration,
+    last_received: Instant,
+    last_sent: Instant,
+    /// When the pending TestRequest was sent
+    test_request_sent: Option<Instant>,
+}
+
+impl FixSession {
+    pub fn new(sender_comp_id: impl Into<String>, target_comp_id: impl Into<String>) -> Self {
+        let now = Instant::now();
+        Self {
+            sender_comp_id: sender_comp_id.into(),
+            target_comp_id: target_comp_id.into(),
+            sub_ids: None,
+            next_sender_seq: 1,
+            next_target_seq: 1,
+            sent: BTreeMap::new(),
+            resend_limit: DEFAULT_RESEND_LIMIT,
+            resend_requested_to: None,
+            heartbeat_interval: Duration::from_secs(30),
+            last_received: now,
+            last_sent: now,
+            test_request_sent: None,
+        }
+    }
+
+    /// Stamp SenderSubID and TargetSubID on every outgoing message
+    pub fn with_sub_ids(mut self, sender_sub_id: impl Into<String>, target_sub_id: impl Into<String>) -> Self {
+        self.sub_ids = Some((sender_sub_id.into(), target_sub_id.into()));
+        self
+    }
+
+    /// Keep only the last `limit` application messages for resending
+    pub fn with_resend_limit(mut self, limit: usize) -> Self {
+        self.resend_limit = limit;
+        self
+    }
+
+    pub fn sender_comp_id(&self) -> &str {
+        &self.sender_comp_id
+    }
+
+    pub fn target_comp_id(&self) -> &str {
+        &self.target_comp_id
+    }
+
+    /// Sequence number of the next message sent
+    pub fn next_sender_seq(&self) -> u64 {
+        self.next_sender_seq
+    }
+
+    /// Sequence number expected of the next message received
+    pub fn next_target_seq(&self) -> u64 {
+        self.next_target_seq
+    }
+
+    /// Start both sequences over at 1 and forget what was sent
+    pub fn reset(&mut self) {
+        self.next_sender_seq = 1;
+        self.next_target_seq = 1;
+        self.sent.clear();
+        self.resend_requested_to = None;
+    }
+
+    /// Start the heartbeat clock of a new connection
+    pub fn start(&mut self, heartbeat_interval: Duration) {
+        let now = Instant::now();
+        self.heartbeat_interval = heartbeat_interval;
+        self.last_received = now;
+        self.last_sent = now;
+        self.test_request_sent = None;
+        self.resend_requested_to = None;
+    }
+
+    pub fn heartbeat_interval(&self) -> Duration {
+        self.heartbeat_interval
+    }
+
+    /// Stamp the header on a message and number it
+    pub fn send(&mut self, msg_type: &str, fields: Vec<(u32, String)>) -> String {
+        let seq = self.next_sender_seq;
+        self.next_sender_seq += 1;
+        let sending_time = Utc::now().format(TIMESTAMP_FORMAT).to_string();
+        let message = self.build(msg_type, seq, &sending_time, &fields, &[]);
+        if !is_admin(msg_type) {
+            self.sent.insert(
+                seq,
+                SentMessage {
+                    msg_type: msg_type.to_string(),
+                    fields,
+                    sending_time,
+                },
+            );
+            while self.sent.len() > self.resend_limit {
+                self.sent.pop_first();
+            }
+        }
+        message
+    }
+
+    fn build(
+        &mut self,
+        msg_type: &str,
+        seq: u64,
+        sending_time: &str,
+        fields: &[(u32, String)],
+        extra_header: &[(u32, String)],
+    ) -> String {
+        self.last_sent = Instant::now();
+        let mut message = FixMessage::new();
+        message.add_field(tag::SENDER_COMP_ID, &self.sender_comp_id);
+        message.add_field(tag::TARGET_COMP_ID, &self.target_comp_id);
+        message.add_field(tag::MSG_SEQ_NUM, seq);
+        message.add_field(tag::SENDING_TIME, sending_time);
+        if let Some((sender_sub_id, target_sub_id)) = &self.sub_ids {
+            message.add_field(tag::SENDER_SUB_ID, sender_sub_id);
+            message.add_field(tag::TARGET_SUB_ID, target_sub_id);
+        }
+        for (tag, value) in extra_header.iter().chain(fields) {
+            message.add_field(*tag, value);
+        }
+        message.build(msg_type)
+    }
+
+    pub fn heartbeat(&mut self, test_req_id: Option<&str>) -> String {
+        let fields = test_req_id
+            .map(|id| vec![(tag::TEST_REQ_ID, id.to_string())])
+            .unwrap_or_default();
+        self.send(msg_type::HEARTBEAT, fields)
+    }
+
+    pub fn logout(&mut self, text: &str) -> String {
+        let fields = if text.is_empty() {
+            Vec::new()
+        } else {
+            vec![(tag::TEXT, text.to_string())]
+        };
+        self.send(msg_type::LOGOUT, fields)
+    }
+
+    /// Session-level Reject of an incoming message
+    pub fn reject(&mut self, ref_seq_num: u64, ref_tag_id: Option<u32>, reason: u32, text: &str) -> String {
+        let mut fields = vec![(tag::REF_SEQ_NUM, ref_seq_num.to_string())];
+        if let Some(ref_tag_id) = ref_tag_id {
+            fields.push((tag::REF_TAG_ID, ref_tag_id.to_string()));
+        }
+        fields.push((tag::SESSION_REJECT_REASON, reason.to_string()));
+        fields.push((tag::TEXT, text.to_string()));
+        self.send(msg_type::REJECT, fields)
+    }
+
+    /// Check the header of an incoming message and track its sequence number
+    ///
+    /// An error is fatal to the session: log out with it as the text.
+    pub fn receive(&mut self, fields: &impl FieldMap) -> Result<Inbound, String> {
+        self.last_received = Instant::now();
+        self.test_request_sent = None;
+
+        if fields.field(tag::BEGIN_STRING) != Some(BEGIN_STRING) {
+            return Err(format!("BeginString must be {}", BEGIN_STRING));
+        }
+        if fields.field(tag::SENDER_COMP_ID) != Some(self.target_comp_id.as_str())
+            || fields.field(tag::TARGET_COMP_ID) != Some(self.sender_comp_id.as_str())
+        {
+            return Err("CompID problem".to_string());
+        }
+        let msg_type = fields.field(tag::MSG_TYPE).unwrap_or_default();
+        let seq = fields
+            .field(tag::MSG_SEQ_NUM)
+            .and_then(|seq| seq.parse::<u64>().ok())
+            .ok_or_else(|| "MsgSeqNum missing".to_string())?;
+
+        // A reset (not a gap fill) applies whatever its sequence number
+        if msg_type == msg_type::SEQUENCE_RESET && !flag(fields, tag::GAP_FILL_FLAG) {
+            let new_seq = new_seq_no(fields)?;
+            if new_seq < self.next_target_seq {
+                return Err(format!("NewSeqNo {} lower than expected {}", new_seq, self.next_target_seq));
+            }
+            self.next_target_seq = new_seq;
+            return Ok(Inbound::Handled(Vec::new()));
+        }
+
+        if seq > self.next_target_seq {
+            // Ask once for everything missing; the message comes again with it
+            if msg_type == msg_type::LOGOUT {
+                return Ok(Inbound::Logout(text(fields)));
+            }
+            // A ResendRequest is answered before our own: the peer may be
+            // waiting on a gap too, and would otherwise ignore ours in turn
+            let mut replies = if msg_type == msg_type::RESEND_REQUEST {
+                self.resend(fields)?
+            } else {
+                Vec::new()
+            };
+            if let Some(to) = self.resend_requested_to.as_mut() {
+                // Already asked up to infinity; it comes again with the rest
+                *to = (*to).max(seq);
+                return Ok(Inbound::Handled(replies));
+            }
+            self.resend_requested_to = Some(seq);
+            let request = vec![
+                (tag::BEGIN_SEQ_NO, self.next_target_seq.to_string()),
+                (tag::END_SEQ_NO, "0".to_string()),
+            ];
+            replies.push(self.send(msg_type::RESEND_REQUEST, request));
+            return Ok(Inbound::Handled(replies));
+        }
+        if seq < self.next_target_seq {
+            if flag(fields, tag::POSS_DUP_FLAG) {
+                return Ok(Inbound::Handled(Vec::new()));
+            }
+            return Err(format!(
+                "MsgSeqNum too low, expecting {} but received {}",
+                self.next_target_seq, seq
+            ));
+        }
+
+        self.next_target_seq += 1;
+        if self.resend_requested_to.is_some_and(|to| self.next_target_seq > to) {
+            self.resend_requested_to = None;
+        }
+        match msg_type {
+            msg_type::HEARTBEAT | msg_type::REJECT | msg_type::LOGON => Ok(Inbound::Handled(Vec::new())),
+            msg_type::TEST_REQUEST => {
+                let test_req_id = fields.field(tag::TEST_REQ_ID).unwrap_or_default().to_string();
+                Ok(Inbound::Handled(vec![self.heartbeat(Some(&test_req_id))]))
+            }
+            msg_type::RESEND_REQUEST => Ok(Inbound::Handled(self.resend(fields)?)),
+            msg_type::SEQUENCE_RESET => {
+                let new_seq = new_seq_no(fields)?;
+                if new_seq < self.next_target_seq {
+                    return Err(format!("NewSeqNo {} lower than expected {}", new_seq, self.next_target_seq));
+                }
+                self.next_target_seq = new_seq;
+                Ok(Inbound::Handled(Vec::new()))
+            }
+            msg_type::LOGOUT => Ok(Inbound::Logout(text(fields))),
+            _ => Ok(Inbound::Application),
+        }
+    }
+
+    /// Answer a ResendRequest: application messages still stored again, flagged
+    /// as possible duplicates, and gap fills over everything else, including
+    /// messages pruned from the store
+    fn resend(&mut self, fields: &impl FieldMap) -> Result<Vec<String>, String> {
+        let seq_field = |tag| {
+            fields
+                .field(tag)
+                .and_then(|seq| seq.parse::<u64>().ok())
+                .ok_or_else(|| "ResendRequest without a valid BeginSeqNo and EndSeqNo".to_string())
+        };
+        let begin = seq_field(tag::BEGIN_SEQ_NO)?.max(1);
+        let last_sent = self.next_sender_seq - 1;
+        let end = match seq_field(tag::END_SEQ_NO)? {
+            0 => last_sent,
+            end => end.min(last_sent),
+        };
+        if begin > end {
+            return Ok(Vec::new());
+        }
+
+        let mut replies = Vec::new();
+        let mut gap_start: Option<u64> = None;
+        let stored: Vec<(u64, SentMessage)> = self
+            .sent
+            .range(begin..=end)
+            .map(|(seq, message)| (*seq, message.clone()))
+            .collect();
+        let mut stored = stored.into_iter().peekable();
+        for seq in begin..=end {
+            match stored.next_if(|(stored_seq, _)| *stored_seq == seq) {
+                Some((_, message)) => {
+                    if let Some(start) = gap_start.take() {
+                        replies.push(self.gap_fill(start, seq));
+                    }
+                    let header = [
+                        (tag::POSS_DUP_FLAG, "Y".to_string()),
+                        (tag::ORIG_SENDING_TIME, message.sending_time.clone()),
+                    ];
+                    let now = Utc::now().format(TIMESTAMP_FORMAT).to_string();
+                    replies.push(self.build(&message.msg_type, seq, &now, &message.fields, &header));
+                }
+                None => {
+                    gap_start.get_or_insert(seq);
+                }
+            }
+        }
+        if let Some(start) = gap_start {
+            replies.push(self.gap_fill(start, end + 1));
+        }
+        Ok(replies)
+    }
+
+    /// SequenceReset-GapFill numbered `seq`, skipping to `new_seq`
+    fn gap_fill(&mut self, seq: u64, new_seq: u64) -> String {
+        let now = Utc::now().format(TIMESTAMP_FORMAT).to_string();
+        let fields = [
+            (tag::GAP_FILL_FLAG, "Y".to_string()),
+            (tag::NEW_SEQ_NO, new_seq.to_string()),
+        ];
+        self.build(
+            msg_type::SEQUENCE_RESET,
+            seq,
+            &now,
+            &fields,
+            &[(tag::POSS_DUP_FLAG, "Y".to_string())],
+        )
+    }
+
+    /// Heartbeat or TestRequest due now, or an error once the peer has been
+    /// silent through a TestRequest
+    pub fn poll_timer(&mut self, now: Instant) -> Result<Option<String>, String> {
+        let interval = self.heartbeat_interval;
+        if let Some(sent) = self.test_request_sent {
+            if now.duration_since(sent) >= interval {
+                return Err("heartbeat timeout".to_string());
+            }
+        } else if now.duration_since(self.last_received) >= interval + interval / 5 {
+            self.test_request_sent = Some(now);
+            let id = format!("TEST-{}", self.next_sender_seq);
+            let test_request = self.send(msg_type::TEST_REQUEST, vec![(tag::TEST_REQ_ID, id)]);
+            self.last_sent = now;
+            return Ok(Some(test_request));
+        }
+        if now.duration_since(self.last_sent) >= interval {
+            let heartbeat = self.heartbeat(None);
+            self.last_sent = now;
+            return Ok(Some(heartbeat));
+        }
+        Ok(None)
+    }
+}
+
+fn flag(fields: &impl FieldMap, tag: u32) -> bool {
+    fields.field(tag) == Some("Y")
+}
+
+fn new_seq_no(fields: &impl FieldMap) -> Result<u64, String> {
+    fields
+        .field(tag::NEW_SEQ_NO)
+        .and_then(|seq| seq.parse::<u64>().ok())
+        .ok_or_else(|| "SequenceReset without a valid NewSeqNo".to_string())
+}
+
+fn text(fields: &impl FieldMap) -> String {
+    fields.field(tag::TEXT).unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ctrader_fix::messages::parse_fix_message;
+    use std::collections::HashMap;
+
+    /// A message from the peer of `session`
+    fn incoming(msg_type: &str, seq: u64, fields: &[(u32, &str)]) -> HashMap<u32, String> {
+        let mut peer = FixSession::new("CLIENT", "EXCHANGE");
+        peer.next_sender_seq = seq;
+        let fields = fields.iter().map(|(tag, value)| (*tag, value.to_string())).collect();
+        parse_fix_message(&peer.send(msg_type, fields))
+    }
+
+    fn field(message: &str, tag: u32) -> Option<String> {
+        parse_fix_message(message).remove(&tag)
+    }
+
+    #[test]
+    fn test_gaps_are_requested_once_and_low_sequence_numbers_are_fatal() {
+        let mut session = FixSession::new("EXCHANGE", "CLIENT");
+        assert_eq!(session.receive(&incoming("A", 1, &[])), Ok(Inbound::Handled(Vec::new())));
+        assert_eq!(session.receive(&incoming("D", 2, &[])), Ok(Inbound::Application));
+
+        let Ok(Inbound::Handled(replies)) = session.receive(&incoming("D", 5, &[])) else {
+            panic!("expected a resend request");
+        };
+        assert_eq!(field(&replies[0], tag::MSG_TYPE).as_deref(), Some("2"));
+        assert_eq!(field(&replies[0], tag::BEGIN_SEQ_NO).as_deref(), Some("3"));
+        assert_eq!(session.receive(&incoming("D", 6, &[])), Ok(Inbound::Handled(Vec::new())));
+
+        // The peer fills the gap
+        let gap_fill = incoming("4", 3, &[(tag::GAP_FILL_FLAG, "Y"), (tag::NEW_SEQ_NO, "5")]);
+        assert_eq!(session.receive(&gap_fill), Ok(Inbound::Handled(Vec::new())));
+        assert_eq!(session.receive(&incoming("D", 5, &[(tag::POSS_DUP_FLAG, "Y")])), Ok(Inbound::Application));
+        assert_eq!(session.next_target_seq(), 6);
+
+        assert_eq!(session.receive(&incoming("0", 5, &[(tag::POSS_DUP_FLAG, "Y")])), Ok(Inbound::Handled(Vec::new())));
+        assert!(session.receive(&incoming("0", 5, &[])).unwrap_err().contains("too low"));
+    }
+
+    #[test]
+    fn test_resend_repeats_application_messages_and_fills_gaps() {
+        let mut session = FixSession::new("EXCHANGE", "CLIENT");
+        session.send("A", Vec::new());
+        session.send("8", vec![(37, "first".to_string())]);
+        session.heartbeat(None);
+        session.heartbeat(None);
+        session.send("8", vec![(37, "second".to_string())]);
+
+        let request = incoming("2", 1, &[(tag::BEGIN_SEQ_NO, "1"), (tag::END_SEQ_NO, "0")]);
+        let Ok(Inbound::Handled(replies)) = session.receive(&request) else {
+            panic!("expected resent messages");
+        };
+        let summary: Vec<_> = replies
+            .iter()
+            .map(|reply| {
+                let fields = parse_fix_message(reply);
+                (fields[&tag::MSG_TYPE].clone(), fields[&tag::MSG_SEQ_NUM].clone(), fields.get(&tag::NEW_SEQ_NO).cloned())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("4".to_string(), "1".to_string(), Some("2".to_string())),
+                ("8".to_string(), "2".to_string(), None),
+                ("4".to_string(), "3".to_string(), Some("5".to_string())),
+                ("8".to_string(), "5".to_string(), None),
+            ]
+        );
+        assert_eq!(field(&replies[1], tag::POSS_DUP_FLAG).as_deref(), Some("Y"));
+        assert_eq!(field(&replies[1], 37).as_deref(), Some("first"));
+        assert_eq!(session.next_sender_seq(), 6);
+    }
+
+    #[test]
+    fn test_pruned_messages_are_gap_filled() {
+        let mut session = FixSession::new("EXCHANGE", "CLIENT").with_resend_limit(2);
+        for id in ["first", "second", "third"] {
+            session.send("8", vec![(37, id.to_string())]);
+        }
+        assert_eq!(session.sent.len(), 2);
+
+        let request = incoming("2", 1, &[(tag::BEGIN_SEQ_NO, "1"), (tag::END_SEQ_NO, "0")]);
+        let Ok(Inbound::Handled(replies)) = session.receive(&request) else {
+            panic!("expected resent messages");
+        };
+        assert_eq!(replies.len(), 3);
+        assert_eq!(field(&replies[0], tag::MSG_TYPE).as_deref(), Some("4"));
+        assert_eq!(field(&replies[0], tag::NEW_SEQ_NO).as_deref(), Some("2"));
+        assert_eq!(field(&replies[1], 37).as_deref(), Some("second"));
+        assert_eq!(field(&replies[2], 37).as_deref(), Some("third"));
+    }
+
+    #[test]
+    fn test_crossing_resend_requests_are_both_answered() {
+        let mut exchange = FixSession::new("EXCHANGE", "CLIENT");
+        let mut client = FixSession::new("CLIENT", "EXCHANGE");
+
+        /// Hand messages to a session, returning what it sends back
+        fn deliver(to: &mut FixSession, messages: &[String]) -> Vec<String> {
+            let mut replies = Vec::new();
+            for message in messages {
+                match to.receive(&parse_fix_message(message)) {
+                    Ok(Inbound::Handled(handled)) => replies.extend(handled),
+                    Ok(Inbound::Application) => {}
+                    other => panic!("unexpected {:?}", other),
+                }
+            }
+            replies
+        }
+
+        deliver(&mut exchange, &[client.send("A", Vec::new())]);
+        deliver(&mut client, &[exchange.send("A", Vec::new())]);
+
+        // Each side loses a message from the other and then hears the next one
+        exchange.send("8", vec![(37, "lost".to_string())]);
+        client.send("D", vec![(11, "lost".to_string())]);
+        let to_client = exchange.send("8", vec![(37, "next".to_string())]);
+        let to_exchange = client.send("D", vec![(11, "next".to_string())]);
+        let client_request = deliver(&mut client, &[to_client]);
+        let exchange_request = deliver(&mut exchange, &[to_exchange]);
+        assert_eq!(field(&client_request[0], tag::MSG_TYPE).as_deref(), Some("2"));
+        assert_eq!(field(&exchange_request[0], tag::MSG_TYPE).as_deref(), Some("2"));
+
+        // The requests cross: both are above what their receiver expects, and both are answered
+        let resent_to_client = deliver(&mut exchange, &client_request);
+        let resent_to_exchange = deliver(&mut client, &exchange_request);
+        assert_eq!(resent_to_client.len(), 3);
+        assert_eq!(resent_to_exchange.len(), 3);
+
+        assert!(deliver(&mut client, &resent_to_client).is_empty());
+        assert!(deliver(&mut exchange, &resent_to_exchange).is_empty());
+        assert_eq!(client.next_target_seq(), exchange.next_sender_seq());
+        assert_eq!(exchange.next_target_seq(), client.next_sender_seq());
+    }
+
+    #[test]
+    fn test_silent_peer_gets_a_test_request_then_times_out() {
+        let mut session = FixSession::new("EXCHANGE", "CLIENT");
+        session.start(Duration::from_secs(10));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(session.poll_timer(at(5)).unwrap().is_none());
+        let heartbeat = session.poll_timer(at(10)).unwrap().unwrap();
+        assert_eq!(field(&heartbeat, tag::MSG_TYPE).as_deref(), Some("0"));
+
+        let test_request = session.poll_timer(at(12)).unwrap().unwrap();
+        assert_eq!(field(&test_request, tag::MSG_TYPE).as_deref(), Some("1"));
+        assert!(session.poll_timer(at(13)).unwrap().is_none());
+
+        assert_eq!(session.poll_timer(at(22)), Err("heartbeat timeout".to_string()));
+    }
+}
@@ -0,0 +1,21 @@
+//! Binary order entry gateway
+//!
+//! A TCP server speaking the `protocol::messages` wire format, for clients
+//! that want to bypass JSON over HTTP. Orders take the same ingestion
+//! pipeline as REST orders; the pipeline's events come back as execution
+//! reports.
+//!
+//! - `protocol` - Session and order messages on the `protocol::binary` framing
+//! - `server` - Listener, login, sequence numbers and heartbeats
+//! - `reports` - Execution reports built from the pipeline's events
+//!
+//! Only what goes through the pipeline is reported: orders expired by the
+//! sweeper or uncrossed in an auction do not get reports.
+
+pub mod protocol;
+pub mod reports;
+pub mod server;
+
+pub use protocol::{GatewayCodec, SequencedMessage};
+pub use reports::{ExecutionReportPublisher, OrderRegistry};
+pub use server::{reject_reason, OrderGateway};
@@ -0,0 +1,152 @@
+//! Gateway messages
+//!
+//! Every message is one `protocol::binary` frame (2-byte big-endian length
+//! prefix). The payload starts with the sender's sequence number (big-endian
+//! `u64`, counting from 1 in each direction of a session), followed by a
+//! `protocol::messages::Message` in its versioned layout. Sessions carry
+//! `Login`, `LoginAck`, `Logout`, `Heartbeat`, the order messages and
+//! execution reports.
+
+use bytes::{Buf, BufMut, BytesMut};
+use std::io::{self, Error, ErrorKind};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::protocol::{FramedCodec, Message};
+
+/// A message with the sender's sequence number
+#[derive(Debug, Clone)]
+pub struct SequencedMessage {
+    pub sequence: u64,
+    pub message: Message,
+}
+
+/// Frames `SequencedMessage`s on a TCP stream
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GatewayCodec;
+
+impl Encoder<SequencedMessage> for GatewayCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: SequencedMessage, dst: &mut BytesMut) -> io::Result<()> {
+        let mut payload = BytesMut::with_capacity(128);
+        payload.put_u64(item.sequence);
+        item.message.encode(&mut payload)?;
+        FramedCodec::encode_frame(&payload, dst)
+    }
+}
+
+impl Decoder for GatewayCodec {
+    type Item = SequencedMessage;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<SequencedMessage>> {
+        let Some(mut payload) = FramedCodec::decode_frame(src) else {
+            return Ok(None);
+        };
+        if payload.remaining() < 8 {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "Incomplete message"));
+        }
+        let sequence = payload.get_u64();
+        let message = Message::decode(&mut payload)?;
+        if matches!(message, Message::OrderBookSnapshot(_) | Message::Trade(_)) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{:?} is not a gateway message", message.message_type()),
+            ));
+        }
+        Ok(Some(SequencedMessage { sequence, message }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Order, OrderSide, OrderType};
+    use crate::protocol::messages::put_header;
+    use crate::protocol::MessageType;
+    use rust_decimal_macros::dec;
+
+    fn round_trip(message: Message) -> SequencedMessage {
+        let mut buf = BytesMut::new();
+        GatewayCodec.encode(SequencedMessage { sequence: 7, message }, &mut buf).unwrap();
+        let decoded = GatewayCodec.decode(&mut buf).unwrap().unwrap();
+        assert!(buf.is_empty());
+        assert_eq!(decoded.sequence, 7);
+        decoded
+    }
+
+    #[test]
+    fn test_messages_round_trip() {
+        match round_trip(Message::Login {
+            user_id: "trader1".to_string(),
+            heartbeat_interval_secs: 5,
+        })
+        .message
+        {
+            Message::Login {
+                user_id,
+                heartbeat_interval_secs,
+            } => assert_eq!((user_id.as_str(), heartbeat_interval_secs), ("trader1", 5)),
+            other => panic!("unexpected message {:?}", other),
+        }
+        assert!(matches!(
+            round_trip(Message::LoginAck { heartbeat_interval_secs: 30 }).message,
+            Message::LoginAck { heartbeat_interval_secs: 30 }
+        ));
+        assert!(matches!(round_trip(Message::Heartbeat).message, Message::Heartbeat));
+        match round_trip(Message::Logout { reason: "bye".to_string() }).message {
+            Message::Logout { reason } => assert_eq!(reason, "bye"),
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        let order = Order::new("AAPL".to_string(), OrderSide::Sell, OrderType::Limit, Some(dec!(101.5)), dec!(3), "u".to_string());
+        match round_trip(Message::NewOrder(order.clone())).message {
+            Message::NewOrder(decoded) => assert_eq!((decoded.id, decoded.price), (order.id, order.price)),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_waits_for_whole_frame() {
+        let mut buf = BytesMut::new();
+        GatewayCodec
+            .encode(
+                SequencedMessage {
+                    sequence: 1,
+                    message: Message::Heartbeat,
+                },
+                &mut buf,
+            )
+            .unwrap();
+        let mut partial = buf.split_to(4);
+        assert!(GatewayCodec.decode(&mut partial).unwrap().is_none());
+
+        partial.unsplit(buf);
+        assert!(matches!(
+            GatewayCodec.decode(&mut partial).unwrap(),
+            Some(SequencedMessage {
+                sequence: 1,
+                message: Message::Heartbeat
+            })
+        ));
+    }
+
+    #[test]
+    fn test_malformed_messages_are_errors() {
+        // Replication messages are not gateway messages
+        let mut buf = BytesMut::new();
+        let mut payload = BytesMut::new();
+        payload.put_u64(1);
+        put_header(&mut payload, MessageType::ReplicationAck);
+        FramedCodec::encode_frame(&payload, &mut buf).unwrap();
+        assert!(GatewayCodec.decode(&mut buf).is_err());
+
+        // A truncated order
+        let mut payload = BytesMut::new();
+        payload.put_u64(1);
+        put_header(&mut payload, MessageType::NewOrder);
+        payload.put_u8(0);
+        FramedCodec::encode_frame(&payload, &mut buf).unwrap();
+        assert!(GatewayCodec.decode(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,362 @@
+//! Execution reports for orders entered through the gateway
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rust_decimal::Decimal;
+use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
+
+use crate::disruptor::{EngineEvent, EventConsumer};
+use crate::engine::CancelReason;
+use crate::models::{Order, OrderSide, OrderStatus, TimeInForce};
+use crate::protocol::{ExecType, ExecutionReport, Message, RejectReason};
+use crate::utils::clock;
+
+/// A gateway order as its reports describe it
+#[derive(Debug, Clone)]
+struct TrackedOrder {
+    user_id: String,
+    symbol: String,
+    side: OrderSide,
+    price: Option<Decimal>,
+    quantity: Decimal,
+    filled_quantity: Decimal,
+    /// Set when the order turns out not to rest (IOC, FOK and market
+    /// remainders, STP cancels): it is done once its fills reach the quantity
+    /// and end in the status
+    done_at: Option<(Decimal, OrderStatus)>,
+}
+
+impl TrackedOrder {
+    fn status(&self) -> OrderStatus {
+        if self.filled_quantity >= self.quantity {
+            OrderStatus::Filled
+        } else if self.filled_quantity > Decimal::ZERO {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::New
+        }
+    }
+
+    fn report(&self, order_id: Uuid, exec_type: ExecType, status: OrderStatus) -> ExecutionReport {
+        let leaves_quantity = match status {
+            OrderStatus::New | OrderStatus::PartiallyFilled => self.quantity - self.filled_quantity,
+            _ => Decimal::ZERO,
+        };
+        execution_report(ExecutionReportFields {
+            exec_type,
+            status,
+            reject_reason: RejectReason::None,
+            order_id,
+            symbol: &self.symbol,
+            side: Some(self.side),
+            price: self.price,
+            cumulative_quantity: self.filled_quantity,
+            leaves_quantity,
+            text: String::new(),
+        })
+    }
+}
+
+/// Everything of an execution report but the fill
+pub(crate) struct ExecutionReportFields<'a> {
+    pub exec_type: ExecType,
+    pub status: OrderStatus,
+    pub reject_reason: RejectReason,
+    pub order_id: Uuid,
+    pub symbol: &'a str,
+    pub side: Option<OrderSide>,
+    pub price: Option<Decimal>,
+    pub cumulative_quantity: Decimal,
+    pub leaves_quantity: Decimal,
+    pub text: String,
+}
+
+pub(crate) fn execution_report(fields: ExecutionReportFields<'_>) -> ExecutionReport {
+    ExecutionReport {
+        order_id: fields.order_id,
+        symbol: fields.symbol.to_string(),
+        exec_type: fields.exec_type,
+        status: fields.status,
+        side: fields.side,
+        reject_reason: fields.reject_reason,
+        price: fields.price,
+        last_price: None,
+        last_quantity: Decimal::ZERO,
+        cumulative_quantity: fields.cumulative_quantity,
+        leaves_quantity: fields.leaves_quantity,
+        timestamp: clock::now(),
+        text: fields.text,
+    }
+}
+
+#[derive(Default)]
+struct RegistryState {
+    /// Working orders entered through the gateway
+    orders: HashMap<Uuid, TrackedOrder>,
+    /// Outgoing messages of the logged-in sessions, by user
+    sessions: HashMap<String, UnboundedSender<Message>>,
+}
+
+impl RegistryState {
+    fn deliver(&self, user_id: &str, report: ExecutionReport) {
+        if let Some(session) = self.sessions.get(user_id) {
+            // A session that just went away drops its reports
+            let _ = session.send(Message::ExecutionReport(report));
+        }
+    }
+
+    /// Report the end of an order that stopped working and forget it
+    fn finish(&mut self, order_id: Uuid, status: OrderStatus) {
+        if let Some(order) = self.orders.remove(&order_id) {
+            self.deliver(&order.user_id, order.report(order_id, ExecType::Cancelled, status));
+        }
+    }
+
+    fn fill(&mut self, order_id: Uuid, price: Decimal, quantity: Decimal) {
+        let Some(order) = self.orders.get_mut(&order_id) else {
+            return;
+        };
+        order.filled_quantity += quantity;
+        let status = order.status();
+        let mut report = order.report(order_id, ExecType::Trade, status);
+        report.last_price = Some(price);
+        report.last_quantity = quantity;
+        let (user_id, filled_quantity, done_at) = (order.user_id.clone(), order.filled_quantity, order.done_at);
+        self.deliver(&user_id, report);
+
+        if status == OrderStatus::Filled {
+            self.orders.remove(&order_id);
+        } else if let Some((filled, done_status)) = done_at {
+            if filled_quantity >= filled {
+                self.finish(order_id, done_status);
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: &EngineEvent) {
+        match event {
+            EngineEvent::OrderAccepted {
+                order,
+                status,
+                filled_quantity,
+                ..
+            } => {
+                let Some(tracked) = self.orders.get_mut(&order.id) else {
+                    return;
+                };
+                let rests = order.order_type.rests_in_book()
+                    && !matches!(order.time_in_force, TimeInForce::IOC | TimeInForce::FOK)
+                    && matches!(status, OrderStatus::New | OrderStatus::PartiallyFilled);
+                if !rests && *status != OrderStatus::Filled {
+                    let done_status = if *status == OrderStatus::Expired {
+                        OrderStatus::Expired
+                    } else {
+                        OrderStatus::Cancelled
+                    };
+                    tracked.done_at = Some((*filled_quantity, done_status));
+                }
+                let report = tracked.report(order.id, ExecType::New, OrderStatus::New);
+                let (user_id, done_at) = (tracked.user_id.clone(), tracked.done_at);
+                self.deliver(&user_id, report);
+
+                // Dropped without trading; otherwise its fills come next
+                if let Some((filled, done_status)) = done_at {
+                    if filled.is_zero() {
+                        self.finish(order.id, done_status);
+                    }
+                }
+            }
+            EngineEvent::OrderAmended {
+                order_id,
+                new_price,
+                new_quantity,
+                ..
+            } => {
+                let Some(tracked) = self.orders.get_mut(order_id) else {
+                    return;
+                };
+                if new_price.is_some() {
+                    tracked.price = *new_price;
+                }
+                if let Some(quantity) = new_quantity {
+                    tracked.quantity = *quantity;
+                }
+                let report = tracked.report(*order_id, ExecType::Replaced, tracked.status());
+                let user_id = tracked.user_id.clone();
+                self.deliver(&user_id, report);
+            }
+            EngineEvent::OrderCancelled { order_id, reason, .. } => {
+                let status = if *reason == CancelReason::Expired {
+                    OrderStatus::Expired
+                } else {
+                    OrderStatus::Cancelled
+                };
+                self.finish(*order_id, status);
+            }
+            EngineEvent::TradeExecuted { trade, .. } => {
+                self.fill(trade.buyer_order_id, trade.price, trade.quantity);
+                self.fill(trade.seller_order_id, trade.price, trade.quantity);
+            }
+            EngineEvent::CommandRejected { .. } | EngineEvent::TopOfBook { .. } => {}
+        }
+    }
+}
+
+/// Orders entered through the gateway and the sessions their reports go to
+///
+/// Orders are tracked from before they are submitted until they are filled
+/// or cancelled, so their reports follow them across reconnects of the
+/// user's session. Reports for a user without a session are dropped.
+#[derive(Default)]
+pub struct OrderRegistry {
+    state: Mutex<RegistryState>,
+}
+
+impl OrderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, RegistryState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Route a user's reports to a session; `false` if the user already has one
+    pub fn open_session(&self, user_id: &str, session: UnboundedSender<Message>) -> bool {
+        let mut state = self.lock();
+        if state.sessions.get(user_id).is_some_and(|existing| !existing.is_closed()) {
+            return false;
+        }
+        state.sessions.insert(user_id.to_string(), session);
+        true
+    }
+
+    pub fn close_session(&self, user_id: &str) {
+        self.lock().sessions.remove(user_id);
+    }
+
+    /// Start tracking an order about to be submitted; `false` if its ID is taken
+    pub fn track(&self, order: &Order) -> bool {
+        let mut state = self.lock();
+        if state.orders.contains_key(&order.id) {
+            return false;
+        }
+        state.orders.insert(
+            order.id,
+            TrackedOrder {
+                user_id: order.user_id.clone(),
+                symbol: order.symbol.clone(),
+                side: order.side,
+                price: order.price,
+                quantity: order.quantity,
+                filled_quantity: Decimal::ZERO,
+                done_at: None,
+            },
+        );
+        true
+    }
+
+    /// Stop tracking an order the engine rejected
+    pub fn untrack(&self, order_id: Uuid) {
+        self.lock().orders.remove(&order_id);
+    }
+
+    /// Symbol of a working order of the user
+    pub fn order_symbol(&self, order_id: Uuid, user_id: &str) -> Option<String> {
+        self.lock()
+            .orders
+            .get(&order_id)
+            .filter(|order| order.user_id == user_id)
+            .map(|order| order.symbol.clone())
+    }
+
+    /// IDs and symbols of the user's working orders, optionally in one symbol
+    pub fn working_orders(&self, user_id: &str, symbol: Option<&str>) -> Vec<(Uuid, String)> {
+        self.lock()
+            .orders
+            .iter()
+            .filter(|(_, order)| order.user_id == user_id && symbol.is_none_or(|symbol| order.symbol == symbol))
+            .map(|(order_id, order)| (*order_id, order.symbol.clone()))
+            .collect()
+    }
+
+    /// Report of a rejected cancel or modify request for a working order
+    pub fn cancel_reject(&self, order_id: Uuid, reason: RejectReason, text: String) -> Option<ExecutionReport> {
+        let state = self.lock();
+        let order = state.orders.get(&order_id)?;
+        let mut report = order.report(order_id, ExecType::CancelRejected, order.status());
+        report.reject_reason = reason;
+        report.text = text;
+        Some(report)
+    }
+
+    /// Number of working gateway orders
+    pub fn order_count(&self) -> usize {
+        self.lock().orders.len()
+    }
+}
+
+/// Turns the pipeline's events into execution reports for gateway orders
+pub struct ExecutionReportPublisher {
+    registry: Arc<OrderRegistry>,
+}
+
+impl ExecutionReportPublisher {
+    pub fn new(registry: Arc<OrderRegistry>) -> Self {
+        Self { registry }
+    }
+}
+
+impl EventConsumer for ExecutionReportPublisher {
+    fn name(&self) -> &str {
+        "execution-reports"
+    }
+
+    fn on_event(&mut self, _shard: usize, _sequence: u64, event: &EngineEvent) {
+        self.registry.lock().on_event(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disruptor::OrderCommand;
+    use crate::models::OrderType;
+    use rust_decimal_macros::dec;
+    use tokio::sync::mpsc;
+
+    fn reports(rx: &mut mpsc::UnboundedReceiver<Message>) -> Vec<(ExecType, OrderStatus)> {
+        let mut reports = Vec::new();
+        while let Ok(Message::ExecutionReport(report)) = rx.try_recv() {
+            reports.push((report.exec_type, report.status));
+        }
+        reports
+    }
+
+    #[test]
+    fn test_unfilled_ioc_is_accepted_then_cancelled() {
+        let registry = OrderRegistry::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        assert!(registry.open_session("alice", tx.clone()));
+        assert!(!registry.open_session("alice", tx));
+
+        let mut order = Order::new("AAPL".to_string(), OrderSide::Buy, OrderType::Limit, Some(dec!(10)), dec!(1), "alice".to_string());
+        order.time_in_force = TimeInForce::IOC;
+        assert!(registry.track(&order));
+        assert!(!registry.track(&order));
+
+        registry.lock().on_event(&EngineEvent::OrderAccepted {
+            correlation_id: 1,
+            order: OrderCommand::try_from(&order).unwrap(),
+            status: OrderStatus::New,
+            filled_quantity: Decimal::ZERO,
+        });
+        assert_eq!(
+            reports(&mut rx),
+            vec![(ExecType::New, OrderStatus::New), (ExecType::Cancelled, OrderStatus::Cancelled)]
+        );
+        assert_eq!(registry.order_count(), 0);
+    }
+}
@@ -0,0 +1,360 @@
+//! Order entry sessions over TCP
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt};
+use rust_decimal::Decimal;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::time::{interval, timeout, Duration, Instant, MissedTickBehavior};
+use tokio_util::codec::Framed;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::disruptor::command::MAX_USER_ID_LEN;
+use crate::disruptor::IngestionPipeline;
+use crate::engine::OrderBookError;
+use crate::models::{Order, OrderStatus};
+use crate::protocol::{ExecType, ExecutionReport, Message, RejectReason};
+use crate::risk::{RiskError, RiskRejectReason};
+use crate::utils::clock;
+
+use super::protocol::{GatewayCodec, SequencedMessage};
+use super::reports::{execution_report, ExecutionReportFields, OrderRegistry};
+
+/// Time a client has to log in after connecting
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest heartbeat interval a client may ask for
+pub const MAX_HEARTBEAT_INTERVAL_SECS: u16 = 300;
+
+/// Binary order entry gateway
+///
+/// Clients log in for a user, then send orders that go through the same
+/// ingestion pipeline as REST orders and get execution reports back. Both
+/// sides number their messages from 1; a message out of sequence ends the
+/// session. Either side sends a heartbeat after an interval without other
+/// messages, and the gateway ends a session it has not heard from in two
+/// intervals. Orders stay in the book when their session ends.
+pub struct OrderGateway {
+    pipeline: Arc<IngestionPipeline>,
+    registry: Arc<OrderRegistry>,
+}
+
+type Connection = Framed<TcpStream, GatewayCodec>;
+
+/// Map an engine error to the reason code of a reject
+pub fn reject_reason(error: &OrderBookError) -> RejectReason {
+    match error {
+        OrderBookError::OrderNotFound(_) | OrderBookError::OrderNotActive(_) => RejectReason::UnknownOrder,
+        OrderBookError::DuplicateOrder(_) => RejectReason::DuplicateOrder,
+        OrderBookError::RiskRejected(rejection) if rejection.reason == RiskRejectReason::MessageRate => {
+            RejectReason::RateLimit
+        }
+        OrderBookError::RiskRejected(_) => RejectReason::RiskLimit,
+        OrderBookError::CircuitBreakerRejected(RiskError::TradingHalted { .. }) => RejectReason::TradingHalted,
+        OrderBookError::CircuitBreakerRejected(RiskError::OutsidePriceBand { .. }) => RejectReason::OutsidePriceBand,
+        OrderBookError::CircuitBreakerRejected(RiskError::RateLimitExceeded { .. }) => RejectReason::RateLimit,
+        OrderBookError::CircuitBreakerRejected(_) => RejectReason::RiskLimit,
+        OrderBookError::InsufficientFunds { .. } => RejectReason::InsufficientFunds,
+        OrderBookError::InsufficientLiquidity => RejectReason::InsufficientLiquidity,
+        OrderBookError::SelfTrade => RejectReason::SelfTrade,
+        OrderBookError::InvalidTradingPhase(_) => RejectReason::InvalidTradingPhase,
+        OrderBookError::Standby | OrderBookError::PipelineUnavailable(_) => RejectReason::Unavailable,
+        e if e.is_validation_error() => RejectReason::InvalidOrder,
+        _ => RejectReason::Other,
+    }
+}
+
+impl OrderGateway {
+    /// Create a gateway submitting to `pipeline`, whose consumers include an
+    /// `ExecutionReportPublisher` for `registry`
+    pub fn new(pipeline: Arc<IngestionPipeline>, registry: Arc<OrderRegistry>) -> Self {
+        Self { pipeline, registry }
+    }
+
+    /// Accept clients until the listener fails
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) {
+        info!("Order gateway listening on {:?}", listener.local_addr());
+        loop {
+            let (stream, address) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    error!("Order gateway stopped accepting clients: {}", e);
+                    return;
+                }
+            };
+
+            let gateway = self.clone();
+            tokio::spawn(async move {
+                info!("Gateway client {} connected", address);
+                if let Err(e) = gateway.run_session(stream, address).await {
+                    warn!("Gateway session of {} ended: {}", address, e);
+                }
+                info!("Gateway client {} disconnected", address);
+            });
+        }
+    }
+
+    /// Log a client in and serve its session
+    async fn run_session(&self, stream: TcpStream, address: SocketAddr) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut connection = Framed::new(stream, GatewayCodec);
+        let mut session = Session { sent: 0 };
+
+        let login = match timeout(LOGIN_TIMEOUT, connection.next()).await {
+            Ok(Some(Ok(message))) => message,
+            Ok(Some(Err(e))) => return session.logout(&mut connection, format!("malformed message: {}", e)).await,
+            Ok(None) => return Ok(()),
+            Err(_) => return session.logout(&mut connection, "login timeout".to_string()).await,
+        };
+        let (user_id, heartbeat_interval_secs) = match login {
+            SequencedMessage {
+                sequence: 1,
+                message:
+                    Message::Login {
+                        user_id,
+                        heartbeat_interval_secs,
+                    },
+            } => (user_id, heartbeat_interval_secs),
+            other => {
+                let reason = format!("expected login with sequence number 1, received {:?}", other);
+                return session.logout(&mut connection, reason).await;
+            }
+        };
+        if user_id.is_empty() || user_id.len() > MAX_USER_ID_LEN {
+            let reason = format!("user ID must be 1 to {} bytes", MAX_USER_ID_LEN);
+            return session.logout(&mut connection, reason).await;
+        }
+        if !(1..=MAX_HEARTBEAT_INTERVAL_SECS).contains(&heartbeat_interval_secs) {
+            let reason = format!("heartbeat interval must be 1 to {} seconds", MAX_HEARTBEAT_INTERVAL_SECS);
+            return session.logout(&mut connection, reason).await;
+        }
+
+        let (outbox, mut reports) = mpsc::unbounded_channel();
+        if !self.registry.open_session(&user_id, outbox.clone()) {
+            return session.logout(&mut connection, format!("{} is already logged in", user_id)).await;
+        }
+        info!("Gateway client {} logged in as {}", address, user_id);
+
+        let result = self
+            .serve_session(&mut connection, &mut session, &user_id, heartbeat_interval_secs, &outbox, &mut reports)
+            .await;
+        self.registry.close_session(&user_id);
+        result
+    }
+
+    async fn serve_session(
+        &self,
+        connection: &mut Connection,
+        session: &mut Session,
+        user_id: &str,
+        heartbeat_interval_secs: u16,
+        outbox: &mpsc::UnboundedSender<Message>,
+        reports: &mut mpsc::UnboundedReceiver<Message>,
+    ) -> io::Result<()> {
+        session
+            .send(connection, Message::LoginAck { heartbeat_interval_secs })
+            .await?;
+
+        let heartbeat_interval = Duration::from_secs(heartbeat_interval_secs.into());
+        let mut heartbeat = interval(heartbeat_interval);
+        heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut expected_sequence = 2;
+        let mut last_received = Instant::now();
+        let mut last_sent = Instant::now();
+
+        loop {
+            tokio::select! {
+                message = connection.next() => {
+                    let Some(message) = message else {
+                        return Ok(());
+                    };
+                    let SequencedMessage { sequence, message } = match message {
+                        Ok(message) => message,
+                        Err(e) => return session.logout(connection, format!("malformed message: {}", e)).await,
+                    };
+                    last_received = Instant::now();
+                    if sequence != expected_sequence {
+                        let reason = format!("expected sequence number {}, received {}", expected_sequence, sequence);
+                        return session.logout(connection, reason).await;
+                    }
+                    expected_sequence += 1;
+
+                    match message {
+                        Message::Heartbeat => {}
+                        Message::Logout { .. } => {
+                            return session.logout(connection, String::new()).await;
+                        }
+                        Message::NewOrder(order) => self.new_order(user_id, order, outbox).await,
+                        Message::CancelOrder { order_id, symbol } => {
+                            self.cancel_order(user_id, order_id, &symbol, outbox).await
+                        }
+                        Message::ModifyOrder {
+                            order_id,
+                            symbol,
+                            new_price,
+                            new_quantity,
+                        } => {
+                            self.modify_order(user_id, order_id, &symbol, new_price, new_quantity, outbox)
+                                .await
+                        }
+                        other => {
+                            let reason = format!("unexpected {:?}", other);
+                            return session.logout(connection, reason).await;
+                        }
+                    }
+                }
+                Some(report) = reports.recv() => {
+                    // Send everything already queued before flushing
+                    let mut next = Some(report);
+                    while let Some(report) = next {
+                        session.feed(connection, report).await?;
+                        next = reports.try_recv().ok();
+                    }
+                    connection.flush().await?;
+                    last_sent = Instant::now();
+                }
+                _ = heartbeat.tick() => {
+                    if last_received.elapsed() >= heartbeat_interval * 2 {
+                        return session.logout(connection, "heartbeat timeout".to_string()).await;
+                    }
+                    if last_sent.elapsed() >= heartbeat_interval {
+                        session.send(connection, Message::Heartbeat).await?;
+                        last_sent = Instant::now();
+                    }
+                }
+            }
+        }
+    }
+
+    async fn new_order(&self, user_id: &str, mut order: Order, outbox: &mpsc::UnboundedSender<Message>) {
+        order.user_id = user_id.to_string();
+        order.timestamp = clock::now();
+        if order.id.is_nil() {
+            order.id = clock::new_id();
+        }
+
+        if !self.registry.track(&order) {
+            let text = format!("Duplicate order ID: {}", order.id);
+            let _ = outbox.send(rejection(&order, RejectReason::DuplicateOrder, text));
+            return;
+        }
+        if let Err(e) = self.pipeline.submit_order(order.clone()).await {
+            self.registry.untrack(order.id);
+            let _ = outbox.send(rejection(&order, reject_reason(&e), e.to_string()));
+        }
+    }
+
+    async fn cancel_order(&self, user_id: &str, order_id: Uuid, symbol: &str, outbox: &mpsc::UnboundedSender<Message>) {
+        let Some(symbol) = self.registry.order_symbol(order_id, user_id) else {
+            let _ = outbox.send(unknown_order(order_id, symbol));
+            return;
+        };
+        if let Err(e) = self.pipeline.cancel_order(&symbol, order_id).await {
+            self.send_cancel_reject(order_id, &symbol, &e, outbox);
+        }
+    }
+
+    async fn modify_order(
+        &self,
+        user_id: &str,
+        order_id: Uuid,
+        symbol: &str,
+        new_price: Option<Decimal>,
+        new_quantity: Option<Decimal>,
+        outbox: &mpsc::UnboundedSender<Message>,
+    ) {
+        let Some(symbol) = self.registry.order_symbol(order_id, user_id) else {
+            let _ = outbox.send(unknown_order(order_id, symbol));
+            return;
+        };
+        if let Err(e) = self.pipeline.amend_order(&symbol, order_id, new_price, new_quantity).await {
+            self.send_cancel_reject(order_id, &symbol, &e, outbox);
+        }
+    }
+
+    fn send_cancel_reject(
+        &self,
+        order_id: Uuid,
+        symbol: &str,
+        error: &OrderBookError,
+        outbox: &mpsc::UnboundedSender<Message>,
+    ) {
+        let reason = reject_reason(error);
+        let report = match self.registry.cancel_reject(order_id, reason, error.to_string()) {
+            Some(report) => report,
+            // Finished while the request was in flight
+            None => cancel_rejection(order_id, symbol, reason, error.to_string()),
+        };
+        let _ = outbox.send(Message::ExecutionReport(report));
+    }
+}
+
+/// Outgoing half of a session: numbers what it sends
+struct Session {
+    sent: u64,
+}
+
+impl Session {
+    async fn feed(&mut self, connection: &mut Connection, message: Message) -> io::Result<()> {
+        self.sent += 1;
+        connection
+            .feed(SequencedMessage {
+                sequence: self.sent,
+                message,
+            })
+            .await
+    }
+
+    async fn send(&mut self, connection: &mut Connection, message: Message) -> io::Result<()> {
+        self.feed(connection, message).await?;
+        connection.flush().await
+    }
+
+    /// Say goodbye and end the session
+    async fn logout(&mut self, connection: &mut Connection, reason: String) -> io::Result<()> {
+        if !reason.is_empty() {
+            warn!("Ending gateway session: {}", reason);
+        }
+        self.send(connection, Message::Logout { reason }).await
+    }
+}
+
+fn rejection(order: &Order, reason: RejectReason, text: String) -> Message {
+    Message::ExecutionReport(execution_report(ExecutionReportFields {
+        exec_type: ExecType::Rejected,
+        status: OrderStatus::Rejected,
+        reject_reason: reason,
+        order_id: order.id,
+        symbol: &order.symbol,
+        side: Some(order.side),
+        price: order.price,
+        cumulative_quantity: Decimal::ZERO,
+        leaves_quantity: Decimal::ZERO,
+        text,
+    }))
+}
+
+/// Reject of a cancel or modify request for an order the gateway does not track
+fn cancel_rejection(order_id: Uuid, symbol: &str, reason: RejectReason, text: String) -> ExecutionReport {
+    execution_report(ExecutionReportFields {
+        exec_type: ExecType::CancelRejected,
+        status: OrderStatus::Rejected,
+        reject_reason: reason,
+        order_id,
+        symbol,
+        side: None,
+        price: None,
+        cumulative_quantity: Decimal::ZERO,
+        leaves_quantity: Decimal::ZERO,
+        text,
+    })
+}
+
+fn unknown_order(order_id: Uuid, symbol: &str) -> Message {
+    let text = format!("Unknown order: {}", order_id);
+    Message::ExecutionReport(cancel_rejection(order_id, symbol, RejectReason::UnknownOrder, text))
+}
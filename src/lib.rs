@@ -11,12 +11,17 @@ pub mod database;
 pub mod datasource;
 pub mod disruptor;
 pub mod engine;
+pub mod fix;
+pub mod gateway;
 pub mod jobs;
 pub mod market_data;
 pub mod metrics;
 pub mod models;
 pub mod persistence;
+pub mod positions;
 pub mod protocol;
+pub mod replay;
+pub mod replication;
 pub mod rabbitmq;
 pub mod risk;
 pub mod testing;
@@ -1,4 +1,6 @@
 use order_book_api::{create_router, Broadcaster, DatasourceManager, OrderBookEngine};
+use order_book_api::api::create_replication_router;
+use order_book_api::api::replication_handlers::ReplicationState;
 use order_book_api::rabbitmq::{RabbitMQService, RabbitMQConfig};
 use order_book_api::market_data::TickDistributor;
 use order_book_api::ctrader_fix::FixToWebSocketBridge;
@@ -19,8 +21,8 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    // Create the order book engine
-    let engine = Arc::new(OrderBookEngine::new());
+    // Create the order book engine (journaling to the write-ahead log if configured)
+    let engine = Arc::new(enable_accounts(create_engine()));
 
     // Create the WebSocket broadcaster
     let broadcaster = Broadcaster::new();
@@ -50,6 +52,18 @@ async fn main() {
     // Registers tick queue with distributor
     let database_state = initialize_database(tick_distributor.clone()).await;
 
+    // Load instrument reference data (rejects orders on unknown symbols)
+    load_instrument_registry(&engine, database_state.as_ref());
+
+    // Rebuild the order books from the write-ahead log before taking orders
+    if let Err(e) = engine.recover() {
+        tracing::error!("❌ Failed to recover from the write-ahead log: {}", e);
+        std::process::exit(1);
+    }
+
+    // Stream the log to followers and/or follow a primary
+    let replication = start_replication(&engine).await;
+
     // Initialize cron scheduler (only if database is enabled)
     if database_state.is_some() {
         initialize_cron_scheduler(database_state.as_ref().unwrap(), datasource_manager.clone())
@@ -69,7 +83,8 @@ async fn main() {
         database_state,
         Some(tick_distributor.clone()),
         Some(tick_distributor_tx),
-    );
+    )
+    .merge(create_replication_router(replication));
 
     // Define the address
     let addr = "127.0.0.1:3000";
@@ -79,6 +94,12 @@ async fn main() {
     tracing::info!("📊 Health check: http://{}/api/v1/health", addr);
     tracing::info!("📚 Swagger UI: http://{}/swagger-ui", addr);
     tracing::info!("🔌 WebSocket: ws://{}/ws", addr);
+    if let Ok(gateway_addr) = std::env::var("ORDER_GATEWAY_LISTEN") {
+        tracing::info!("⚡ Binary order gateway: tcp://{}", gateway_addr);
+    }
+    if let Ok(fix_addr) = std::env::var("FIX_ACCEPTOR_LISTEN") {
+        tracing::info!("⚡ FIX 4.4 acceptor: tcp://{}", fix_addr);
+    }
     tracing::info!("🔧 Datasource control: http://{}/api/v1/datasource/*", addr);
     tracing::info!("");
     tracing::info!("📡 WebSocket Subscription Examples:");
@@ -186,6 +207,164 @@ async fn initialize_database(
     Some(database_state)
 }
 
+/// Load instrument reference data into the engine
+///
+/// `INSTRUMENTS_FILE` (a JSON array of instruments) takes precedence over the
+/// `symbols` table. Without either the engine keeps accepting any symbol.
+fn load_instrument_registry(
+    engine: &OrderBookEngine,
+    database_state: Option<&order_book_api::api::DatabaseState>,
+) {
+    use order_book_api::engine::InstrumentRegistry;
+
+    let registry = if let Ok(path) = std::env::var("INSTRUMENTS_FILE") {
+        match InstrumentRegistry::from_config_file(&path) {
+            Ok(registry) => {
+                tracing::info!("📐 Loaded {} instruments from {}", registry.instruments().len(), path);
+                registry
+            }
+            Err(e) => {
+                tracing::error!("❌ Failed to load instruments from {}: {}", path, e);
+                return;
+            }
+        }
+    } else if let Some(db_state) = database_state {
+        match InstrumentRegistry::from_symbol_repository(db_state.symbol_repository.as_ref()) {
+            Ok(registry) => {
+                tracing::info!("📐 Loaded {} instruments from the symbols table", registry.instruments().len());
+                registry
+            }
+            Err(e) => {
+                tracing::error!("❌ Failed to load instruments from the symbols table: {}", e);
+                return;
+            }
+        }
+    } else {
+        tracing::warn!("⚠️  No instrument reference data configured, orders on any symbol are accepted");
+        return;
+    };
+
+    if let Err(e) = engine.set_instrument_registry(registry) {
+        tracing::error!("❌ Failed to install instrument registry: {}", e);
+    }
+}
+
+/// Create the order book engine
+///
+/// With `WAL_DIR` set, every order, cancel and trade is journaled to a
+/// write-ahead log in that directory and replayed on the next start.
+/// `WAL_SYNC_BATCH=N` fsyncs every N events instead of after each one.
+/// Snapshots go to `SNAPSHOT_DIR` (default `{WAL_DIR}/snapshots`), and the
+/// log is truncated behind them.
+fn create_engine() -> OrderBookEngine {
+    use order_book_api::persistence::{SnapshotStore, SyncMode, WriteAheadLog};
+    use std::sync::Mutex;
+
+    let Ok(wal_dir) = std::env::var("WAL_DIR") else {
+        tracing::warn!("⚠️  WAL_DIR not set, order book state is not persisted");
+        return OrderBookEngine::new();
+    };
+
+    let sync_mode = std::env::var("WAL_SYNC_BATCH")
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+        .filter(|&n| n > 0)
+        .map(SyncMode::Batched)
+        .unwrap_or(SyncMode::EveryWrite);
+
+    let engine = match WriteAheadLog::open(&wal_dir, sync_mode) {
+        Ok(wal) => {
+            tracing::info!("📝 Journaling to write-ahead log in {} ({:?})", wal_dir, sync_mode);
+            OrderBookEngine::with_wal(Arc::new(Mutex::new(wal)))
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to open write-ahead log in {}: {}", wal_dir, e);
+            std::process::exit(1);
+        }
+    };
+
+    let snapshot_dir = std::env::var("SNAPSHOT_DIR").unwrap_or_else(|_| format!("{}/snapshots", wal_dir));
+    match SnapshotStore::open(&snapshot_dir) {
+        Ok(store) => {
+            tracing::info!("📸 Writing engine snapshots to {}", snapshot_dir);
+            engine.with_snapshot_store(store)
+        }
+        Err(e) => {
+            tracing::error!("❌ Failed to open snapshot directory {}: {}", snapshot_dir, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Keep account balances when `ACCOUNTS_ENABLED=true`
+///
+/// Orders then need funds deposited through `/api/v1/accounts`. Followers
+/// must use the same setting as their primary.
+fn enable_accounts(engine: OrderBookEngine) -> OrderBookEngine {
+    let enabled = std::env::var("ACCOUNTS_ENABLED")
+        .ok()
+        .and_then(|v| v.parse::<bool>().ok())
+        .unwrap_or(false);
+    if !enabled {
+        return engine;
+    }
+
+    tracing::info!("💰 Accounts enabled, orders must be funded");
+    engine.with_accounts()
+}
+
+/// Set up hot-standby replication
+///
+/// With `REPLICATION_LISTEN=host:port` the node streams its write-ahead log to
+/// followers that connect there. With `REPLICATION_PRIMARY=host:port` it
+/// starts as a follower of that primary and rejects orders until promoted
+/// (`POST /api/v1/replication/promote`). Both can be set, so a follower can
+/// serve its own followers and keeps serving them once promoted.
+async fn start_replication(engine: &Arc<OrderBookEngine>) -> ReplicationState {
+    use order_book_api::replication::{ReplicationFollower, ReplicationServer};
+
+    let server = match std::env::var("REPLICATION_LISTEN") {
+        Ok(addr) => {
+            if !matches!(engine.wal_dir(), Ok(Some(_))) {
+                tracing::error!("❌ REPLICATION_LISTEN needs WAL_DIR: followers are served from the write-ahead log");
+                std::process::exit(1);
+            }
+            let listener = match tokio::net::TcpListener::bind(&addr).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    tracing::error!("❌ Failed to bind replication listener on {}: {}", addr, e);
+                    std::process::exit(1);
+                }
+            };
+            tracing::info!("🔁 Streaming the write-ahead log to followers on {}", addr);
+            let server = Arc::new(ReplicationServer::new(engine.clone()));
+            tokio::spawn(server.clone().serve(listener));
+            Some(server)
+        }
+        Err(_) => None,
+    };
+
+    let follower = match std::env::var("REPLICATION_PRIMARY") {
+        Ok(primary) => match ReplicationFollower::start(engine.clone(), primary.clone()) {
+            Ok(follower) => {
+                tracing::info!("🔁 Standby replica of primary {}; orders are rejected until promoted", primary);
+                Some(follower)
+            }
+            Err(e) => {
+                tracing::error!("❌ Failed to start following {}: {}", primary, e);
+                std::process::exit(1);
+            }
+        },
+        Err(_) => None,
+    };
+
+    ReplicationState {
+        engine: engine.clone(),
+        server,
+        follower,
+    }
+}
+
 /// Initialize cron scheduler for periodic jobs
 async fn initialize_cron_scheduler(
     database_state: &order_book_api::api::DatabaseState,
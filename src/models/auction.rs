@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{OrderSide, Trade};
+
+/// Why a symbol is in a call auction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuctionKind {
+    /// Before continuous trading starts
+    Opening,
+    /// At the end of continuous trading
+    Closing,
+    /// Resuming trading after a circuit breaker halt
+    Reopening,
+}
+
+/// Price and volume the auction would uncross at if it ended now
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct IndicativeUncross {
+    #[schema(value_type = String, example = "100.00")]
+    #[serde(with = "crate::models::decimal")]
+    pub price: Decimal,
+    /// Quantity that would execute at `price`
+    #[schema(value_type = String, example = "250")]
+    #[serde(with = "crate::models::decimal")]
+    pub volume: Decimal,
+    /// Quantity left unmatched at `price`
+    #[schema(value_type = String, example = "40")]
+    #[serde(with = "crate::models::decimal")]
+    pub imbalance: Decimal,
+    /// Side with the unmatched quantity (`None` when balanced)
+    pub imbalance_side: Option<OrderSide>,
+}
+
+/// A symbol's running call auction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuctionState {
+    pub symbol: String,
+    pub kind: AuctionKind,
+    pub started_at: DateTime<Utc>,
+    /// Tie-break price for the uncross (last trade price when the auction started)
+    #[schema(value_type = Option<String>, example = "100.00")]
+    #[serde(default, with = "crate::models::decimal::option")]
+    pub reference_price: Option<Decimal>,
+    /// Current indicative uncross (`None` while the book does not cross)
+    pub indicative: Option<IndicativeUncross>,
+}
+
+/// Outcome of ending a call auction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuctionResult {
+    pub symbol: String,
+    pub kind: AuctionKind,
+    /// Price and volume the book uncrossed at (`None` if nothing executed)
+    pub uncross: Option<IndicativeUncross>,
+    pub trades: Vec<Trade>,
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Part of the trading day a point in time falls into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionPhase {
+    /// Outside every session, on a weekend or on a holiday
+    Closed,
+    /// Pre-market session before the regular open
+    PreMarket,
+    /// Regular trading session
+    Regular,
+    /// Post-market session after the regular close
+    PostMarket,
+}
+
+/// Trading session calendar of a symbol
+///
+/// Session times are wall-clock times in `timezone`, so daylight saving
+/// changes are handled by the time zone rules.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TradingCalendar {
+    /// IANA time zone of the session times
+    #[schema(value_type = String, example = "America/New_York")]
+    pub timezone: Tz,
+    /// Start of the pre-market session (no pre-market if unset)
+    #[schema(value_type = Option<String>, example = "04:00:00")]
+    pub pre_open: Option<NaiveTime>,
+    /// Regular session open
+    #[schema(value_type = String, example = "09:30:00")]
+    pub open: NaiveTime,
+    /// Regular session close
+    #[schema(value_type = String, example = "16:00:00")]
+    pub close: NaiveTime,
+    /// End of the post-market session (no post-market if unset)
+    #[schema(value_type = Option<String>, example = "20:00:00")]
+    pub post_close: Option<NaiveTime>,
+    /// Days of the week the symbol trades
+    #[schema(value_type = Vec<String>, example = json!(["Mon", "Tue", "Wed", "Thu", "Fri"]))]
+    pub trading_days: Vec<Weekday>,
+    /// Dates (in `timezone`) the symbol does not trade
+    #[schema(value_type = Vec<String>, example = json!(["2026-12-25"]))]
+    pub holidays: BTreeSet<NaiveDate>,
+}
+
+impl TradingCalendar {
+    /// Check the session times are in order: pre-open < open < close < post-close
+    pub fn validate(&self) -> Result<(), String> {
+        if self.open >= self.close {
+            return Err("open must be before close".to_string());
+        }
+        if self.pre_open.is_some_and(|pre_open| pre_open >= self.open) {
+            return Err("pre_open must be before open".to_string());
+        }
+        if self.post_close.is_some_and(|post_close| post_close <= self.close) {
+            return Err("post_close must be after close".to_string());
+        }
+        if self.trading_days.is_empty() {
+            return Err("at least one trading day is required".to_string());
+        }
+        Ok(())
+    }
+
+    /// Check if the symbol trades on a (local) date
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        self.trading_days.contains(&date.weekday()) && !self.holidays.contains(&date)
+    }
+
+    /// Session phase at a point in time
+    pub fn phase_at(&self, at: DateTime<Utc>) -> SessionPhase {
+        let local = at.with_timezone(&self.timezone);
+        if !self.is_trading_day(local.date_naive()) {
+            return SessionPhase::Closed;
+        }
+
+        let time = local.time();
+        if time >= self.open && time < self.close {
+            SessionPhase::Regular
+        } else if self.pre_open.is_some_and(|pre_open| time >= pre_open && time < self.open) {
+            SessionPhase::PreMarket
+        } else if self.post_close.is_some_and(|post_close| time >= self.close && time < post_close) {
+            SessionPhase::PostMarket
+        } else {
+            SessionPhase::Closed
+        }
+    }
+
+    /// When a DAY order entered at `entered_at` expires
+    ///
+    /// Orders entered before the regular close expire at that close, orders
+    /// entered in the post-market session at its end, and orders entered while
+    /// the market is closed at the close of the next trading day.
+    pub fn day_order_expiry(&self, entered_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local = entered_at.with_timezone(&self.timezone);
+        let date = local.date_naive();
+        let time = local.time();
+
+        if self.is_trading_day(date) {
+            if time < self.close {
+                return Some(self.to_utc(date, self.close));
+            }
+            if let Some(post_close) = self.post_close.filter(|&post_close| time < post_close) {
+                return Some(self.to_utc(date, post_close));
+            }
+        }
+
+        // A year always holds a trading day unless every day is a holiday
+        (1..=366)
+            .map(|days| date + Duration::days(days))
+            .find(|&next| self.is_trading_day(next))
+            .map(|next| self.to_utc(next, self.close))
+    }
+
+    /// Convert a local session time to UTC (times skipped by a DST change move forward an hour)
+    fn to_utc(&self, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+        let local = date.and_time(time);
+        self.timezone
+            .from_local_datetime(&local)
+            .earliest()
+            .or_else(|| self.timezone.from_local_datetime(&(local + Duration::hours(1))).earliest())
+            .map(|at| at.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc.from_utc_datetime(&local))
+    }
+}
+
+impl Default for TradingCalendar {
+    /// Round-the-clock trading in UTC: DAY orders expire at the end of the UTC day
+    fn default() -> Self {
+        Self {
+            timezone: Tz::UTC,
+            pre_open: None,
+            open: NaiveTime::MIN,
+            close: NaiveTime::from_hms_opt(23, 59, 59).expect("valid time"),
+            post_close: None,
+            trading_days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+                Weekday::Sun,
+            ],
+            holidays: BTreeSet::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn us_equities() -> TradingCalendar {
+        TradingCalendar {
+            timezone: chrono_tz::America::New_York,
+            pre_open: NaiveTime::from_hms_opt(4, 0, 0),
+            open: NaiveTime::from_hms_opt(9, 30, 0).unwrap(),
+            close: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+            post_close: NaiveTime::from_hms_opt(20, 0, 0),
+            trading_days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+            holidays: [NaiveDate::from_ymd_opt(2026, 12, 25).unwrap()].into_iter().collect(),
+        }
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_session_phases() {
+        let calendar = us_equities();
+        assert!(calendar.validate().is_ok());
+
+        // Wednesday 2026-07-15, New York is UTC-4 in summer
+        assert_eq!(calendar.phase_at(utc("2026-07-15T07:00:00Z")), SessionPhase::Closed);
+        assert_eq!(calendar.phase_at(utc("2026-07-15T12:00:00Z")), SessionPhase::PreMarket);
+        assert_eq!(calendar.phase_at(utc("2026-07-15T13:30:00Z")), SessionPhase::Regular);
+        assert_eq!(calendar.phase_at(utc("2026-07-15T21:00:00Z")), SessionPhase::PostMarket);
+
+        // Weekend and holiday
+        assert_eq!(calendar.phase_at(utc("2026-07-18T15:00:00Z")), SessionPhase::Closed);
+        assert_eq!(calendar.phase_at(utc("2026-12-25T15:00:00Z")), SessionPhase::Closed);
+    }
+
+    #[test]
+    fn test_day_order_expiry() {
+        let calendar = us_equities();
+
+        // Regular session: the same day's close (UTC-4 in summer, UTC-5 in winter)
+        assert_eq!(calendar.day_order_expiry(utc("2026-07-15T14:00:00Z")), Some(utc("2026-07-15T20:00:00Z")));
+        assert_eq!(calendar.day_order_expiry(utc("2026-01-14T15:00:00Z")), Some(utc("2026-01-14T21:00:00Z")));
+
+        // Post-market: the end of the post-market session
+        assert_eq!(calendar.day_order_expiry(utc("2026-07-15T21:00:00Z")), Some(utc("2026-07-16T00:00:00Z")));
+
+        // Friday night: Monday's close; Christmas Eve after hours skips the holiday
+        assert_eq!(calendar.day_order_expiry(utc("2026-07-18T02:00:00Z")), Some(utc("2026-07-20T20:00:00Z")));
+        assert_eq!(calendar.day_order_expiry(utc("2026-12-25T02:00:00Z")), Some(utc("2026-12-28T21:00:00Z")));
+    }
+
+    #[test]
+    fn test_calendar_validation() {
+        let mut calendar = us_equities();
+        calendar.post_close = NaiveTime::from_hms_opt(15, 0, 0);
+        assert!(calendar.validate().is_err());
+
+        let mut calendar = us_equities();
+        calendar.close = calendar.open;
+        assert!(calendar.validate().is_err());
+
+        assert!(TradingCalendar::default().validate().is_ok());
+    }
+}
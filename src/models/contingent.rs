@@ -0,0 +1,238 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::utils::clock;
+
+use super::{Order, OrderSide, StopOrder};
+
+/// How the orders in a contingent group relate to each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ContingencyType {
+    /// One-Cancels-Other: a limit leg and a stop leg, a fill or trigger of one cancels the other
+    Oco,
+    /// One-Triggers-Other: children are released once the entry order is completely filled
+    Oto,
+    /// Entry order whose fill releases a take-profit limit and a stop-loss stop as an OCO pair
+    Bracket,
+}
+
+/// Lifecycle of the whole contingent group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ContingentGroupStatus {
+    /// Entry order is working, children are not live yet
+    PendingEntry,
+    /// Contingent legs are working in the book / trigger engine
+    Active,
+    /// Every leg reached a terminal state through execution
+    Completed,
+    /// Group was cancelled before completing
+    Cancelled,
+}
+
+/// Lifecycle of a single leg in a contingent group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LegStatus {
+    /// Waiting for the entry order to fill
+    Pending,
+    /// Live in the order book or trigger engine
+    Working,
+    PartiallyFilled,
+    Filled,
+    /// Stop leg fired and submitted its order
+    Triggered,
+    Cancelled,
+}
+
+impl LegStatus {
+    /// Check if the leg can no longer change state
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, LegStatus::Filled | LegStatus::Triggered | LegStatus::Cancelled)
+    }
+}
+
+/// The order a leg places when it goes live
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", content = "order", rename_all = "snake_case")]
+pub enum LegOrder {
+    /// Regular (limit or market) order submitted to the order book
+    Order(Order),
+    /// Stop order registered with the trigger engine
+    Stop(StopOrder),
+}
+
+/// Serde encoding of `LegOrder` that works for JSON and for bincode
+///
+/// The tagged JSON form needs `deserialize_identifier`, which bincode (used
+/// by the write-ahead log and snapshots) does not support; binary formats
+/// get a plain enum instead.
+mod leg_order {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{LegOrder, Order, StopOrder};
+
+    #[derive(Serialize)]
+    enum BinaryRef<'a> {
+        Order(&'a Order),
+        Stop(&'a StopOrder),
+    }
+
+    #[derive(Deserialize)]
+    enum Binary {
+        Order(Order),
+        Stop(StopOrder),
+    }
+
+    pub fn serialize<S: Serializer>(value: &LegOrder, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return value.serialize(serializer);
+        }
+        match value {
+            LegOrder::Order(order) => BinaryRef::Order(order),
+            LegOrder::Stop(stop) => BinaryRef::Stop(stop),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<LegOrder, D::Error> {
+        if deserializer.is_human_readable() {
+            return LegOrder::deserialize(deserializer);
+        }
+        Ok(match Binary::deserialize(deserializer)? {
+            Binary::Order(order) => LegOrder::Order(order),
+            Binary::Stop(stop) => LegOrder::Stop(stop),
+        })
+    }
+}
+
+/// One order in a contingent group
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ContingentLeg {
+    #[serde(with = "leg_order")]
+    pub order: LegOrder,
+    pub status: LegStatus,
+    #[serde(with = "crate::models::decimal")]
+    #[schema(value_type = String, example = "0")]
+    pub filled_quantity: Decimal,
+    /// ID of the order submitted when a stop leg triggered
+    pub triggered_order_id: Option<Uuid>,
+}
+
+impl ContingentLeg {
+    pub fn new(order: LegOrder) -> Self {
+        Self {
+            order,
+            status: LegStatus::Pending,
+            filled_quantity: Decimal::ZERO,
+            triggered_order_id: None,
+        }
+    }
+
+    /// ID of the underlying order or stop order
+    pub fn id(&self) -> Uuid {
+        match &self.order {
+            LegOrder::Order(order) => order.id,
+            LegOrder::Stop(stop) => stop.id,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        match &self.order {
+            LegOrder::Order(order) => &order.symbol,
+            LegOrder::Stop(stop) => &stop.symbol,
+        }
+    }
+
+    pub fn side(&self) -> OrderSide {
+        match &self.order {
+            LegOrder::Order(order) => order.side,
+            LegOrder::Stop(stop) => stop.side,
+        }
+    }
+
+    pub fn quantity(&self) -> Decimal {
+        match &self.order {
+            LegOrder::Order(order) => order.quantity,
+            LegOrder::Stop(stop) => stop.quantity,
+        }
+    }
+
+    pub fn is_stop(&self) -> bool {
+        matches!(self.order, LegOrder::Stop(_))
+    }
+}
+
+/// A group of orders linked by a contingency (OCO, OTO or bracket)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ContingentGroup {
+    pub id: Uuid,
+    pub symbol: String,
+    pub user_id: String,
+    pub contingency_type: ContingencyType,
+    pub status: ContingentGroupStatus,
+    /// Entry order (OTO and bracket only)
+    pub entry: Option<ContingentLeg>,
+    /// Contingent legs: the OCO pair, the OTO children, or the bracket take-profit/stop-loss
+    pub legs: Vec<ContingentLeg>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ContingentGroup {
+    /// Create a new group; OCO groups start active, groups with an entry wait for it to fill
+    pub fn new(
+        symbol: String,
+        user_id: String,
+        contingency_type: ContingencyType,
+        entry: Option<LegOrder>,
+        legs: Vec<LegOrder>,
+    ) -> Self {
+        let now = clock::now();
+        Self {
+            id: clock::new_id(),
+            symbol,
+            user_id,
+            contingency_type,
+            status: if entry.is_some() {
+                ContingentGroupStatus::PendingEntry
+            } else {
+                ContingentGroupStatus::Active
+            },
+            entry: entry.map(ContingentLeg::new),
+            legs: legs.into_iter().map(ContingentLeg::new).collect(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Whether the contingent legs cancel each other (OCO pair or bracket exits)
+    pub fn legs_are_exclusive(&self) -> bool {
+        matches!(self.contingency_type, ContingencyType::Oco | ContingencyType::Bracket)
+    }
+
+    /// Check if the group can no longer change state
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            ContingentGroupStatus::Completed | ContingentGroupStatus::Cancelled
+        )
+    }
+
+    /// Iterate over every leg, entry first
+    pub fn all_legs(&self) -> impl Iterator<Item = &ContingentLeg> {
+        self.entry.iter().chain(self.legs.iter())
+    }
+
+    /// Mutable access to the leg with the given order ID
+    pub fn leg_mut(&mut self, order_id: Uuid) -> Option<&mut ContingentLeg> {
+        self.entry
+            .iter_mut()
+            .chain(self.legs.iter_mut())
+            .find(|leg| leg.id() == order_id)
+    }
+}
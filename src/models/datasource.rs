@@ -64,6 +64,9 @@ pub struct HealthStatus {
     pub uptime_seconds: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub symbols_count: Option<usize>,
+    /// Connections lost or failed since the FIX session was last logged on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reconnect_attempts: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub warning: Option<String>,
     pub timestamp: String,
@@ -80,10 +83,35 @@ pub enum HealthState {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ConnectionState {
+    /// Logged on to the FIX server
     Connected,
+    /// Connecting and logging on
+    Connecting,
+    /// Waiting to connect again after losing the session
+    Reconnecting,
     Disconnected,
 }
 
+/// State of the FIX client's session, published as it changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixSessionStatus {
+    pub state: ConnectionState,
+    /// Connections lost or failed since the session was last logged on
+    pub reconnect_attempts: u32,
+    /// Why the last connection ended
+    pub last_error: Option<String>,
+}
+
+impl Default for FixSessionStatus {
+    fn default() -> Self {
+        Self {
+            state: ConnectionState::Disconnected,
+            reconnect_attempts: 0,
+            last_error: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum HeartbeatState {
@@ -0,0 +1,72 @@
+//! Serde encoding of `Decimal` fields that works for JSON and for bincode
+//!
+//! `rust_decimal` deserializes through `deserialize_any`, which binary formats
+//! such as bincode (used by the write-ahead log) do not support. Fields marked
+//! `#[serde(with = "crate::models::decimal")]` keep the usual encoding in
+//! human-readable formats and are written as the 16-byte `Decimal::serialize`
+//! representation otherwise.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        Serialize::serialize(value, serializer)
+    } else {
+        Serialize::serialize(&value.serialize(), serializer)
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+    if deserializer.is_human_readable() {
+        <Decimal as Deserialize>::deserialize(deserializer)
+    } else {
+        <[u8; 16]>::deserialize(deserializer).map(Decimal::deserialize)
+    }
+}
+
+/// The same encoding for `Option<Decimal>` fields
+pub mod option {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Wrapped(#[serde(with = "super")] Decimal);
+
+    pub fn serialize<S: Serializer>(value: &Option<Decimal>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.map(Wrapped).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Decimal>, D::Error> {
+        Ok(Option::<Wrapped>::deserialize(deserializer)?.map(|Wrapped(value)| value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Prices {
+        #[serde(with = "super")]
+        price: Decimal,
+        #[serde(with = "super::option")]
+        limit: Option<Decimal>,
+    }
+
+    #[test]
+    fn test_round_trips_in_bincode_and_json() {
+        let prices = Prices {
+            price: dec!(-150.2500),
+            limit: Some(dec!(0.0001)),
+        };
+
+        let bytes = bincode::serialize(&prices).unwrap();
+        assert_eq!(bincode::deserialize::<Prices>(&bytes).unwrap(), prices);
+
+        // JSON keeps accepting plain numbers
+        let json: Prices = serde_json::from_str(r#"{"price": 1.5, "limit": null}"#).unwrap();
+        assert_eq!(json, Prices { price: dec!(1.5), limit: None });
+        assert_eq!(serde_json::from_str::<Prices>(&serde_json::to_string(&prices).unwrap()).unwrap(), prices);
+    }
+}
@@ -6,20 +6,25 @@ use utoipa::ToSchema;
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct IcebergConfig {
     /// Total quantity of the entire order (visible + hidden)
+    #[serde(with = "crate::models::decimal")]
     pub total_quantity: Decimal,
 
     /// Quantity to display in the order book
+    #[serde(with = "crate::models::decimal")]
     pub display_quantity: Decimal,
 
     /// Current hidden quantity remaining
+    #[serde(with = "crate::models::decimal")]
     pub hidden_quantity: Decimal,
 
     /// Minimum visible quantity before replenishing
     /// Some exchanges replenish when visible hits 0, others at a threshold
+    #[serde(with = "crate::models::decimal")]
     pub replenish_threshold: Decimal,
 
     /// Variance to add to display quantity on replenish (anti-detection)
     /// e.g., 0.1 means ±10% randomization
+    #[serde(default, with = "crate::models::decimal::option")]
     pub display_variance: Option<Decimal>,
 }
 
@@ -0,0 +1,70 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Reference data of a tradable instrument
+///
+/// Only `symbol` and `tick_size` are required; every other limit is skipped
+/// when unset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Instrument {
+    pub symbol: String,
+    /// Minimum price increment
+    #[schema(value_type = String, example = "0.01")]
+    pub tick_size: Decimal,
+    /// Quantity increment
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "1")]
+    pub lot_size: Option<Decimal>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "1")]
+    pub min_quantity: Option<Decimal>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "100000")]
+    pub max_quantity: Option<Decimal>,
+    /// Minimum order value (price × quantity × contract size)
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "10")]
+    pub min_notional: Option<Decimal>,
+    /// Units per contract (1 if unset)
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "100")]
+    pub contract_size: Option<Decimal>,
+    /// Reference price the static price collar is centred on
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "150.00")]
+    pub reference_price: Option<Decimal>,
+    /// Maximum distance of a limit price from the reference price, in percent
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "10")]
+    pub price_collar_pct: Option<Decimal>,
+}
+
+impl Instrument {
+    /// Create an instrument with only a tick size
+    pub fn new(symbol: String, tick_size: Decimal) -> Self {
+        Self {
+            symbol,
+            tick_size,
+            lot_size: None,
+            min_quantity: None,
+            max_quantity: None,
+            min_notional: None,
+            contract_size: None,
+            reference_price: None,
+            price_collar_pct: None,
+        }
+    }
+
+    /// Order value of `quantity` at `price`
+    pub fn notional(&self, price: Decimal, quantity: Decimal) -> Decimal {
+        price * quantity * self.contract_size.unwrap_or(Decimal::ONE)
+    }
+
+    /// Lowest and highest limit price the static collar allows
+    pub fn price_collar(&self) -> Option<(Decimal, Decimal)> {
+        let reference = self.reference_price?;
+        let band = reference * self.price_collar_pct? / Decimal::ONE_HUNDRED;
+        Some((reference - band, reference + band))
+    }
+}
@@ -5,10 +5,19 @@ pub mod datasource;
 pub mod stop_order;
 pub mod iceberg;
 pub mod order_pair;
+pub mod contingent;
+pub mod auction;
+pub mod calendar;
+pub mod instrument;
+pub mod decimal;
 
 pub use order::{Order, OrderSide, OrderType, OrderStatus, TimeInForce, SelfTradePreventionMode};
-pub use trade::Trade;
+pub use trade::{FeeCurrency, Trade};
 pub use orderbook::{OrderBook, PriceLevel};
 pub use stop_order::{StopOrder, StopOrderType, StopOrderStatus, TriggerCondition};
 pub use iceberg::{IcebergConfig, IcebergFillResult};
 pub use order_pair::OrderPair;
+pub use contingent::{ContingencyType, ContingentGroup, ContingentGroupStatus, ContingentLeg, LegOrder, LegStatus};
+pub use auction::{AuctionKind, AuctionResult, AuctionState, IndicativeUncross};
+pub use calendar::{SessionPhase, TradingCalendar};
+pub use instrument::Instrument;
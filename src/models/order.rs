@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::utils::clock;
+
 use super::iceberg::IcebergConfig;
 
 /// Represents a trading order in the order book
@@ -13,8 +15,11 @@ pub struct Order {
     pub symbol: String,
     pub side: OrderSide,
     pub order_type: OrderType,
+    #[serde(default, with = "crate::models::decimal::option")]
     pub price: Option<Decimal>,
+    #[serde(with = "crate::models::decimal")]
     pub quantity: Decimal,
+    #[serde(with = "crate::models::decimal")]
     pub filled_quantity: Decimal,
     pub status: OrderStatus,
     pub user_id: String,
@@ -29,11 +34,12 @@ pub struct Order {
     #[serde(default)]
     pub post_only: bool,
     /// Expiration time for GTD orders
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub expire_time: Option<DateTime<Utc>>,
     /// Iceberg configuration (None for regular orders)
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub iceberg: Option<IcebergConfig>,
+    /// Offset added to the peg reference price (pegged orders only)
+    #[serde(default, with = "crate::models::decimal::option")]
+    pub peg_offset: Option<Decimal>,
 }
 
 /// Order side: Buy or Sell
@@ -44,12 +50,36 @@ pub enum OrderSide {
     Sell,
 }
 
-/// Order type: Limit or Market
+/// Order type: Limit, Market, or one of the pegged types
+///
+/// Pegged orders rest in the book like limit orders, but their price is
+/// derived from the top of book plus `Order::peg_offset` and follows it.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum OrderType {
     Limit,
     Market,
+    /// Pegged to the same-side best price (buy: best bid, sell: best ask)
+    #[serde(rename = "primary_peg")]
+    PrimaryPeg,
+    /// Pegged to the mid price
+    #[serde(rename = "midpoint_peg")]
+    MidpointPeg,
+    /// Pegged to the opposite-side best price (buy: best ask, sell: best bid)
+    #[serde(rename = "market_peg")]
+    MarketPeg,
+}
+
+impl OrderType {
+    /// Check if the order price follows the top of book
+    pub fn is_pegged(&self) -> bool {
+        matches!(self, OrderType::PrimaryPeg | OrderType::MidpointPeg | OrderType::MarketPeg)
+    }
+
+    /// Check if an unfilled remainder of this order type rests in the book
+    pub fn rests_in_book(&self) -> bool {
+        *self == OrderType::Limit || self.is_pegged()
+    }
 }
 
 /// Order status throughout its lifecycle
@@ -139,7 +169,7 @@ impl Order {
         expire_time: Option<DateTime<Utc>>,
     ) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: clock::new_id(),
             symbol,
             side,
             order_type,
@@ -148,12 +178,13 @@ impl Order {
             filled_quantity: Decimal::ZERO,
             status: OrderStatus::New,
             user_id,
-            timestamp: Utc::now(),
+            timestamp: clock::now(),
             time_in_force,
             stp_mode,
             post_only,
             expire_time,
             iceberg: None,
+            peg_offset: None,
         }
     }
 
@@ -180,7 +211,7 @@ impl Order {
 
             if result.replenished {
                 // IMPORTANT: Update timestamp - order loses time priority!
-                self.timestamp = Utc::now();
+                self.timestamp = clock::now();
                 self.update_status();
                 return true; // Signal that order was modified
             }
@@ -213,14 +244,17 @@ impl Order {
     /// Check if order has expired
     pub fn is_expired(&self) -> bool {
         if let Some(expire_time) = self.expire_time {
-            Utc::now() > expire_time
+            clock::now() > expire_time
         } else {
             false
         }
     }
 
-    /// Check if order should be added to the book (based on TIF)
+    /// Check if order should be added to the book (based on TIF; expired orders never rest)
     pub fn should_rest_in_book(&self) -> bool {
+        if self.status == OrderStatus::Expired {
+            return false;
+        }
         match self.time_in_force {
             TimeInForce::GTC | TimeInForce::GTD | TimeInForce::DAY => !self.is_filled(),
             TimeInForce::IOC | TimeInForce::FOK => false,
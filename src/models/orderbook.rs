@@ -1,6 +1,6 @@
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use uuid::Uuid;
 
 use super::{Order, Trade};
@@ -8,7 +8,9 @@ use super::{Order, Trade};
 /// Represents a price level in the order book
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PriceLevel {
+    #[serde(with = "crate::models::decimal")]
     pub price: Decimal,
+    #[serde(with = "crate::models::decimal")]
     pub total_quantity: Decimal,
     pub orders: VecDeque<Uuid>,
 }
@@ -57,6 +59,12 @@ pub struct OrderBook {
     pub asks: BTreeMap<Decimal, PriceLevel>,
     #[serde(skip)]
     pub orders: HashMap<Uuid, Order>,
+    /// IDs of the pegged orders in `orders`
+    #[serde(skip)]
+    pegged: HashSet<Uuid>,
+    /// Reference prices the pegged orders were last repriced against
+    #[serde(skip)]
+    peg_reference: Option<(Option<Decimal>, Option<Decimal>)>,
     pub trades: Vec<Trade>,
 }
 
@@ -68,6 +76,8 @@ impl OrderBook {
             bids: BTreeMap::new(),
             asks: BTreeMap::new(),
             orders: HashMap::new(),
+            pegged: HashSet::new(),
+            peg_reference: None,
             trades: Vec::new(),
         }
     }
@@ -124,6 +134,46 @@ impl OrderBook {
         self.orders.get_mut(&order_id)
     }
 
+    /// Track an order in the book (its price level is kept separately)
+    ///
+    /// A pegged order is indexed and makes the next reprice pass run.
+    pub fn insert_order(&mut self, order: Order) -> Option<Order> {
+        if order.order_type.is_pegged() {
+            self.pegged.insert(order.id);
+            self.peg_reference = None;
+        } else {
+            self.pegged.remove(&order.id);
+        }
+        self.orders.insert(order.id, order)
+    }
+
+    /// Stop tracking an order (its price level is kept separately)
+    pub fn remove_order(&mut self, order_id: Uuid) -> Option<Order> {
+        self.pegged.remove(&order_id);
+        self.orders.remove(&order_id)
+    }
+
+    /// IDs of the pegged orders in the book, in no particular order
+    pub fn pegged_order_ids(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.pegged.iter().copied()
+    }
+
+    /// Check if the book holds any pegged orders
+    pub fn has_pegged_orders(&self) -> bool {
+        !self.pegged.is_empty()
+    }
+
+    /// Reference best bid and ask of the last reprice pass (`None` before the
+    /// first pass and after a pegged order arrives)
+    pub fn peg_reference(&self) -> Option<(Option<Decimal>, Option<Decimal>)> {
+        self.peg_reference
+    }
+
+    /// Record the reference best bid and ask the pegs were repriced against
+    pub fn set_peg_reference(&mut self, reference: (Option<Decimal>, Option<Decimal>)) {
+        self.peg_reference = Some(reference);
+    }
+
     /// Add a trade to the history
     pub fn add_trade(&mut self, trade: Trade) {
         self.trades.push(trade);
@@ -192,4 +242,28 @@ mod tests {
         assert_eq!(book.get_spread(), Some(dec!(0.50)));
         assert_eq!(book.get_mid_price(), Some(dec!(100.25)));
     }
+
+    #[test]
+    fn test_pegged_order_index() {
+        use crate::models::{OrderSide, OrderType};
+
+        let mut book = OrderBook::new("AAPL".to_string());
+        let order = |order_type| Order::new("AAPL".to_string(), OrderSide::Buy, order_type, Some(dec!(100)), dec!(10), "user1".to_string());
+        let limit = order(OrderType::Limit);
+        let peg = order(OrderType::MidpointPeg);
+        let (limit_id, peg_id) = (limit.id, peg.id);
+
+        book.insert_order(limit);
+        assert!(!book.has_pegged_orders());
+        book.set_peg_reference((Some(dec!(100)), None));
+
+        // A new peg clears the reference of the last reprice
+        book.insert_order(peg);
+        assert_eq!(book.pegged_order_ids().collect::<Vec<_>>(), vec![peg_id]);
+        assert_eq!(book.peg_reference(), None);
+
+        assert!(book.remove_order(peg_id).is_some());
+        assert!(!book.has_pegged_orders());
+        assert!(book.check_order_in_book(limit_id));
+    }
 }
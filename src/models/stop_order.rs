@@ -41,19 +41,26 @@ pub struct StopOrder {
     pub user_id: String,
 
     // Trigger configuration
+    #[serde(with = "crate::models::decimal")]
     pub trigger_price: Decimal,
     pub trigger_condition: TriggerCondition,
     pub stop_type: StopOrderType,
 
     // Order to submit when triggered
     pub side: OrderSide,
+    #[serde(with = "crate::models::decimal")]
     pub quantity: Decimal,
+    #[serde(default, with = "crate::models::decimal::option")]
     pub limit_price: Option<Decimal>, // For stop-limit orders
 
     // Trailing stop specific
+    #[serde(default, with = "crate::models::decimal::option")]
     pub trail_amount: Option<Decimal>,     // Fixed offset
+    #[serde(default, with = "crate::models::decimal::option")]
     pub trail_percent: Option<Decimal>,    // Percentage offset
+    #[serde(default, with = "crate::models::decimal::option")]
     pub highest_price: Option<Decimal>,    // Tracked high (for sell trailing)
+    #[serde(default, with = "crate::models::decimal::option")]
     pub lowest_price: Option<Decimal>,     // Tracked low (for buy trailing)
 
     // Metadata
@@ -1,23 +1,47 @@
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::utils::clock;
+
+/// Asset a fee is charged in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeCurrency {
+    /// The quote asset, as a share of the trade value
+    #[default]
+    Quote,
+    /// The base asset, as a share of the quantity traded
+    Base,
+}
+
 /// Represents a completed trade between two orders
 /// You can understand it as Deal also
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub id: Uuid,
     pub symbol: String,
+    #[serde(with = "crate::models::decimal")]
     pub price: Decimal,
+    #[serde(with = "crate::models::decimal")]
     pub quantity: Decimal,
     pub buyer_order_id: Uuid,
     pub seller_order_id: Uuid,
     pub buyer_id: String,
     pub seller_id: String,
+    #[serde(with = "crate::models::decimal")]
     pub maker_fee: Decimal,
+    #[serde(with = "crate::models::decimal")]
     pub taker_fee: Decimal,
     pub timestamp: DateTime<Utc>,
+    /// Asset the maker fee is charged in
+    #[serde(default)]
+    pub maker_fee_currency: FeeCurrency,
+    /// Asset the taker fee is charged in
+    #[serde(default)]
+    pub taker_fee_currency: FeeCurrency,
 }
 
 impl Trade {
@@ -34,7 +58,7 @@ impl Trade {
         taker_fee: Decimal,
     ) -> Self {
         Self {
-            id: Uuid::new_v4(),
+            id: clock::new_id(),
             symbol,
             price,
             quantity,
@@ -44,7 +68,9 @@ impl Trade {
             seller_id,
             maker_fee,
             taker_fee,
-            timestamp: Utc::now(),
+            timestamp: clock::now(),
+            maker_fee_currency: FeeCurrency::Quote,
+            taker_fee_currency: FeeCurrency::Quote,
         }
     }
 
@@ -53,9 +79,19 @@ impl Trade {
         self.price * self.quantity
     }
 
-    /// Get total fees collected from this trade
+    /// Value of a fee in the quote asset
+    fn fee_value(&self, fee: Decimal, currency: FeeCurrency) -> Decimal {
+        match currency {
+            FeeCurrency::Quote => fee,
+            FeeCurrency::Base => fee * self.price,
+        }
+    }
+
+    /// Get total fees collected from this trade, in the quote asset
+    ///
+    /// A maker rebate is a negative maker fee, so this is net of rebates.
     pub fn total_fees(&self) -> Decimal {
-        self.maker_fee + self.taker_fee
+        self.fee_value(self.maker_fee, self.maker_fee_currency) + self.fee_value(self.taker_fee, self.taker_fee_currency)
     }
 }
 
@@ -84,4 +120,23 @@ mod tests {
         assert_eq!(trade.value(), dec!(15050.00));
         assert_eq!(trade.total_fees(), dec!(0.45));
     }
+
+    #[test]
+    fn test_total_fees_are_net_of_rebates_in_quote() {
+        let mut trade = Trade::new(
+            "BTC/USD".to_string(),
+            dec!(20000),
+            dec!(2),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "buyer1".to_string(),
+            "seller1".to_string(),
+            dec!(-4),
+            dec!(0.001),
+        );
+        trade.taker_fee_currency = FeeCurrency::Base;
+
+        // 0.001 BTC at 20000 is 20 USD, less the 4 USD rebate
+        assert_eq!(trade.total_fees(), dec!(16));
+    }
 }
@@ -1,3 +1,5 @@
 pub mod wal;
+pub mod snapshot;
 
-pub use wal::{WalEvent, WriteAheadLog, SyncMode};
+pub use wal::{list_segments, replay_dir, CorruptRecord, Corruption, SegmentReader, SyncMode, WalEvent, WalRecord, WriteAheadLog};
+pub use snapshot::{AlgorithmSnapshot, BookSnapshot, EngineSnapshot, LastTradePrice, SnapshotStore};
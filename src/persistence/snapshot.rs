@@ -0,0 +1,405 @@
+//! Point-in-time snapshots of the engine state
+//!
+//! A snapshot holds every order book (resting orders with their iceberg
+//! state, price level queues and trade history), the stop orders and last
+//! trade prices of the trigger engine, running call auctions, account
+//! balances, daily realized PnL of the risk engine, fee schedules, contingent
+//! order groups, matching policies and the state of execution algorithms. It is taken at a WAL
+//! sequence number, so recovery loads the latest snapshot and replays only
+//! the events after it.
+//!
+//! Each snapshot is its own file `snapshot_{sequence}.bin`: a header (8-byte
+//! magic, then little-endian `u32` format version, `u64` sequence and `u64`
+//! WAL segment) followed by the bincode-encoded `EngineSnapshot`. Files are
+//! written to a temporary name, synced and then renamed, so a crash never
+//! leaves a half-written snapshot under its final name.
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::algorithms::{TwapAlgorithm, VwapAlgorithm};
+use crate::engine::accounts::Accounts;
+use crate::engine::fees::FeeSchedules;
+use crate::engine::matching_policy::MatchingPolicyConfig;
+use crate::models::{AuctionState, ContingentGroup, Order, OrderBook, PriceLevel, StopOrder, Trade};
+use crate::risk::DailyPnl;
+
+/// Magic bytes at the start of every snapshot file
+pub const SNAPSHOT_MAGIC: [u8; 8] = *b"OBSNAP\0\0";
+
+/// Version of the snapshot encoding; bump it when `EngineSnapshot` changes shape
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 7;
+
+/// One order book
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookSnapshot {
+    pub symbol: String,
+    /// Bid levels from the lowest price up, each with its order queue
+    pub bids: Vec<PriceLevel>,
+    /// Ask levels from the lowest price up, each with its order queue
+    pub asks: Vec<PriceLevel>,
+    /// Every order the book tracks (including iceberg hidden quantity)
+    pub orders: Vec<Order>,
+    pub trades: Vec<Trade>,
+}
+
+impl From<&OrderBook> for BookSnapshot {
+    fn from(book: &OrderBook) -> Self {
+        Self {
+            symbol: book.symbol.clone(),
+            bids: book.bids.values().cloned().collect(),
+            asks: book.asks.values().cloned().collect(),
+            orders: book.orders.values().cloned().collect(),
+            trades: book.trades.clone(),
+        }
+    }
+}
+
+impl BookSnapshot {
+    /// Rebuild the order book
+    pub fn into_book(self) -> OrderBook {
+        let mut book = OrderBook::new(self.symbol);
+        book.bids = self.bids.into_iter().map(|level| (level.price, level)).collect::<BTreeMap<_, _>>();
+        book.asks = self.asks.into_iter().map(|level| (level.price, level)).collect::<BTreeMap<_, _>>();
+        for order in self.orders {
+            book.insert_order(order);
+        }
+        book.trades = self.trades;
+        book
+    }
+}
+
+/// Last trade price of a symbol, as seen by the trigger engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastTradePrice {
+    pub symbol: String,
+    #[serde(with = "crate::models::decimal")]
+    pub price: Decimal,
+}
+
+/// Execution algorithms and their progress
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlgorithmSnapshot {
+    pub twap: Vec<TwapAlgorithm>,
+    pub vwap: Vec<VwapAlgorithm>,
+}
+
+/// Full engine state at a WAL sequence number
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineSnapshot {
+    /// Last WAL sequence number the snapshot includes
+    pub sequence: u64,
+    /// WAL segment holding the first event after `sequence`; earlier segments
+    /// are not needed once this snapshot exists
+    pub wal_segment: u64,
+    pub taken_at: DateTime<Utc>,
+    pub books: Vec<BookSnapshot>,
+    /// Stop orders in trigger priority within each symbol and price
+    pub stop_orders: Vec<StopOrder>,
+    pub last_trade_prices: Vec<LastTradePrice>,
+    pub auctions: Vec<AuctionState>,
+    pub algorithms: AlgorithmSnapshot,
+    /// Account balances and order reservations (`None` without accounts)
+    pub accounts: Option<Accounts>,
+    /// Realized PnL of each account on its latest trading day, counted
+    /// against its daily loss limit
+    pub daily_pnl: Vec<DailyPnl>,
+    pub fee_schedules: FeeSchedules,
+    /// OCO / OTO / bracket groups with the state of each leg
+    pub contingent_groups: Vec<ContingentGroup>,
+    /// Symbols that do not match FIFO and their policies
+    pub matching_policies: BTreeMap<String, MatchingPolicyConfig>,
+}
+
+/// Fixed-size start of a snapshot file, readable without decoding the state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotHeader {
+    pub version: u32,
+    pub sequence: u64,
+    pub wal_segment: u64,
+}
+
+impl SnapshotHeader {
+    fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&SNAPSHOT_MAGIC)?;
+        writer.write_all(&self.version.to_le_bytes())?;
+        writer.write_all(&self.sequence.to_le_bytes())?;
+        writer.write_all(&self.wal_segment.to_le_bytes())
+    }
+
+    /// Read and check a header (magic and supported format version)
+    fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != SNAPSHOT_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a snapshot file"));
+        }
+
+        let mut buf = [0u8; 20];
+        reader.read_exact(&mut buf)?;
+        let header = Self {
+            version: u32::from_le_bytes(buf[0..4].try_into().expect("4 bytes")),
+            sequence: u64::from_le_bytes(buf[4..12].try_into().expect("8 bytes")),
+            wal_segment: u64::from_le_bytes(buf[12..20].try_into().expect("8 bytes")),
+        };
+        if header.version != SNAPSHOT_FORMAT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported snapshot format version {}", header.version),
+            ));
+        }
+        Ok(header)
+    }
+}
+
+/// Directory of snapshot files
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    /// Open (and create if needed) a snapshot directory
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Write a snapshot and return its path
+    pub fn save(&self, snapshot: &EngineSnapshot) -> io::Result<PathBuf> {
+        let path = self.dir.join(format!("snapshot_{:020}.bin", snapshot.sequence));
+        let tmp_path = path.with_extension("bin.tmp");
+
+        {
+            let file = OpenOptions::new().create(true).write(true).truncate(true).open(&tmp_path)?;
+            let mut writer = BufWriter::new(file);
+            SnapshotHeader {
+                version: SNAPSHOT_FORMAT_VERSION,
+                sequence: snapshot.sequence,
+                wal_segment: snapshot.wal_segment,
+            }
+            .write(&mut writer)?;
+            bincode::serialize_into(&mut writer, snapshot).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+        }
+
+        std::fs::rename(&tmp_path, &path)?;
+        // Make the rename itself durable
+        File::open(&self.dir)?.sync_all()?;
+
+        Ok(path)
+    }
+
+    /// Read a snapshot file, checking its magic and format version
+    pub fn load(path: impl AsRef<Path>) -> io::Result<EngineSnapshot> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let header = SnapshotHeader::read(&mut reader)?;
+
+        let snapshot: EngineSnapshot =
+            bincode::deserialize_from(reader).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if snapshot.sequence != header.sequence || snapshot.wal_segment != header.wal_segment {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "snapshot header does not match its body"));
+        }
+        Ok(snapshot)
+    }
+
+    /// Read only the header of a snapshot file
+    pub fn read_header(path: impl AsRef<Path>) -> io::Result<SnapshotHeader> {
+        SnapshotHeader::read(&mut File::open(path)?)
+    }
+
+    /// Load the newest snapshot that can be read, skipping damaged ones
+    pub fn load_latest(&self) -> io::Result<Option<(PathBuf, EngineSnapshot)>> {
+        for (_, path) in self.snapshot_files()?.into_iter().rev() {
+            match Self::load(&path) {
+                Ok(snapshot) => return Ok(Some((path, snapshot))),
+                Err(e) => tracing::warn!("Skipping unreadable snapshot {}: {}", path.display(), e),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Earliest WAL segment any snapshot on disk still needs
+    ///
+    /// Segments before it can be deleted without losing a recovery path,
+    /// whichever snapshot recovery ends up loading.
+    pub fn oldest_wal_segment(&self) -> io::Result<Option<u64>> {
+        let mut oldest = None;
+        for (_, path) in self.snapshot_files()? {
+            match Self::read_header(&path) {
+                Ok(header) => oldest = Some(oldest.map_or(header.wal_segment, |segment: u64| segment.min(header.wal_segment))),
+                Err(e) => tracing::warn!("Skipping unreadable snapshot {}: {}", path.display(), e),
+            }
+        }
+        Ok(oldest)
+    }
+
+    /// Sequence numbers of the snapshots on disk, oldest first
+    pub fn sequences(&self) -> io::Result<Vec<u64>> {
+        Ok(self.snapshot_files()?.into_iter().map(|(sequence, _)| sequence).collect())
+    }
+
+    /// Delete all but the newest `keep` snapshots (and leftover temporary
+    /// files); returns how many snapshots were deleted
+    pub fn prune(&self, keep: usize) -> io::Result<usize> {
+        for entry in std::fs::read_dir(&self.dir)?.filter_map(|e| e.ok()) {
+            if entry.path().to_string_lossy().ends_with(".bin.tmp") {
+                std::fs::remove_file(entry.path())?;
+            }
+        }
+
+        let files = self.snapshot_files()?;
+        let excess = files.len().saturating_sub(keep);
+        for (_, path) in &files[..excess] {
+            std::fs::remove_file(path)?;
+        }
+        Ok(excess)
+    }
+
+    /// Snapshot files sorted by sequence
+    fn snapshot_files(&self) -> io::Result<Vec<(u64, PathBuf)>> {
+        let mut files: Vec<_> = std::fs::read_dir(&self.dir)?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let path = e.path();
+                let sequence = path
+                    .file_name()?
+                    .to_str()?
+                    .strip_prefix("snapshot_")?
+                    .strip_suffix(".bin")?
+                    .parse::<u64>()
+                    .ok()?;
+                Some((sequence, path))
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::fees::FeeSchedule;
+    use crate::engine::matching_policy::ProRata;
+    use crate::models::{IcebergConfig, OrderSide, OrderType};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+    use tempfile::TempDir;
+
+    fn snapshot(sequence: u64) -> EngineSnapshot {
+        let mut book = OrderBook::new("AAPL".to_string());
+        let mut order = Order::new(
+            "AAPL".to_string(),
+            OrderSide::Sell,
+            OrderType::Limit,
+            Some(dec!(101.5)),
+            dec!(100),
+            "seller".to_string(),
+        );
+        order.iceberg = Some(IcebergConfig::new(dec!(100), dec!(10)));
+        let mut level = PriceLevel::new(dec!(101.5));
+        level.add_order(order.id, dec!(10));
+        book.asks.insert(dec!(101.5), level);
+        let mut accounts = Accounts::new();
+        accounts.deposit("seller", "AAPL", dec!(100)).unwrap();
+        accounts.reserve(order.id, "seller", "AAPL", dec!(100)).unwrap();
+        book.insert_order(order);
+
+        EngineSnapshot {
+            sequence,
+            wal_segment: 3,
+            taken_at: Utc::now(),
+            books: vec![BookSnapshot::from(&book)],
+            stop_orders: Vec::new(),
+            last_trade_prices: vec![LastTradePrice {
+                symbol: "AAPL".to_string(),
+                price: dec!(101),
+            }],
+            auctions: Vec::new(),
+            algorithms: AlgorithmSnapshot::default(),
+            accounts: Some(accounts),
+            daily_pnl: vec![DailyPnl {
+                user_id: "seller".to_string(),
+                day: NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+                pnl: dec!(-250.5),
+            }],
+            fee_schedules: FeeSchedules {
+                users: BTreeMap::from([("seller".to_string(), FeeSchedule::flat(dec!(-0.0001), dec!(0.0005)))]),
+                ..FeeSchedules::default()
+            },
+            contingent_groups: Vec::new(),
+            matching_policies: BTreeMap::from([(
+                "AAPL".to_string(),
+                MatchingPolicyConfig::ProRata(ProRata { min_allocation: dec!(1), lot_size: dec!(0.5) }),
+            )]),
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::open(temp_dir.path()).unwrap();
+
+        store.save(&snapshot(42)).unwrap();
+        let (_, loaded) = store.load_latest().unwrap().unwrap();
+
+        assert_eq!(loaded.sequence, 42);
+        assert_eq!(loaded.last_trade_prices[0].price, dec!(101));
+        assert_eq!(loaded.daily_pnl[0].pnl, dec!(-250.5));
+        assert_eq!(loaded.fee_schedules.users["seller"].tiers[0].maker_rate, dec!(-0.0001));
+        assert_eq!(loaded.matching_policies, snapshot(42).matching_policies);
+        let book = loaded.books.into_iter().next().unwrap().into_book();
+        assert_eq!(book.asks[&dec!(101.5)].total_quantity, dec!(10));
+        let order = book.orders.values().next().unwrap();
+        assert_eq!(order.iceberg.as_ref().unwrap().hidden_quantity, dec!(90));
+        let accounts = loaded.accounts.unwrap();
+        assert_eq!(accounts.balance("seller", "AAPL").reserved, dec!(100));
+        assert_eq!(accounts.reserved_for(order.id), Some(dec!(100)));
+    }
+
+    #[test]
+    fn test_load_latest_skips_damaged_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::open(temp_dir.path()).unwrap();
+
+        store.save(&snapshot(10)).unwrap();
+        let newest = store.save(&snapshot(20)).unwrap();
+
+        // Bit rot in the body of the newest snapshot
+        let mut bytes = std::fs::read(&newest).unwrap();
+        bytes.truncate(bytes.len() / 2);
+        std::fs::write(&newest, bytes).unwrap();
+        assert_eq!(store.load_latest().unwrap().unwrap().1.sequence, 10);
+
+        // A snapshot from another format version is not read either
+        let mut bytes = std::fs::read(temp_dir.path().join(format!("snapshot_{:020}.bin", 10))).unwrap();
+        bytes[8..12].copy_from_slice(&(SNAPSHOT_FORMAT_VERSION + 1).to_le_bytes());
+        std::fs::write(temp_dir.path().join(format!("snapshot_{:020}.bin", 10)), bytes).unwrap();
+        assert!(store.load_latest().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_prune_keeps_newest() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = SnapshotStore::open(temp_dir.path()).unwrap();
+
+        for sequence in [5, 15, 25] {
+            store.save(&snapshot(sequence)).unwrap();
+        }
+
+        assert_eq!(store.prune(2).unwrap(), 1);
+        assert_eq!(store.sequences().unwrap(), vec![15, 25]);
+    }
+}
@@ -1,14 +1,39 @@
+//! Write-ahead log of engine events
+//!
+//! Events are appended to numbered segment files `wal_{index}.log`. Each
+//! segment starts with an 8-byte magic and a little-endian `u32` format
+//! version; each record is a little-endian `u32` payload length, the CRC32 of
+//! the payload and the bincode-encoded `WalEvent`. A record that is cut short
+//! or fails its checksum ends the readable part of a segment.
+
 use std::fs::{File, OpenOptions};
 use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 use bincode;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 use uuid::Uuid;
 use rust_decimal::Decimal;
 
-use crate::models::{Order, Trade};
+use crate::engine::{FeeSchedule, FeeScheduleScope, MatchingPolicyConfig};
+use crate::models::{AuctionKind, ContingentGroup, Order, StopOrder, Trade};
+
+/// Magic bytes at the start of every WAL segment
+pub const WAL_MAGIC: [u8; 8] = *b"OBWAL\0\0\0";
+
+/// Version of the segment format; bump it when the record layout or `WalEvent` changes shape
+pub const WAL_FORMAT_VERSION: u32 = 2;
+
+/// Length of a segment header: magic and little-endian `u32` version
+pub const WAL_HEADER_LEN: u64 = 12;
+
+/// Bytes in front of each record: little-endian `u32` length and CRC32 of the payload
+const RECORD_HEADER_LEN: u64 = 8;
 
 /// Events that get persisted to WAL
+///
+/// Records store the variant index, so new variants go at the end; anything
+/// else changes the shape and needs a new `WAL_FORMAT_VERSION`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WalEvent {
     /// New order submitted
@@ -38,16 +63,218 @@ pub enum WalEvent {
         sequence: u64,
         timestamp_ns: u64,
         order_id: Uuid,
+        symbol: String,
+        #[serde(with = "crate::models::decimal::option")]
         new_quantity: Option<Decimal>,
+        #[serde(with = "crate::models::decimal::option")]
         new_price: Option<Decimal>,
     },
 
+    /// Stop order placed with the trigger engine
+    StopOrderSubmitted {
+        sequence: u64,
+        timestamp_ns: u64,
+        stop: StopOrder,
+    },
+
+    /// Stop order cancelled before it triggered
+    StopOrderCancelled {
+        sequence: u64,
+        timestamp_ns: u64,
+        order_id: Uuid,
+    },
+
+    /// Symbol entered a call auction
+    AuctionStarted {
+        sequence: u64,
+        timestamp_ns: u64,
+        symbol: String,
+        kind: AuctionKind,
+    },
+
+    /// Call auction uncrossed and the symbol returned to continuous trading
+    AuctionEnded {
+        sequence: u64,
+        timestamp_ns: u64,
+        symbol: String,
+    },
+
     /// Checkpoint marker (state was snapshotted)
     Checkpoint {
         sequence: u64,
         timestamp_ns: u64,
         checkpoint_path: String,
     },
+
+    /// Funds credited to an account
+    Deposited {
+        sequence: u64,
+        timestamp_ns: u64,
+        user_id: String,
+        asset: String,
+        #[serde(with = "crate::models::decimal")]
+        amount: Decimal,
+    },
+
+    /// Funds debited from an account
+    Withdrawn {
+        sequence: u64,
+        timestamp_ns: u64,
+        user_id: String,
+        asset: String,
+        #[serde(with = "crate::models::decimal")]
+        amount: Decimal,
+    },
+
+    /// Resting orders of a symbol expired in one sweep; pegs are repriced
+    /// once, after all of them are gone
+    OrderExpired {
+        sequence: u64,
+        timestamp_ns: u64,
+        symbol: String,
+        order_ids: Vec<Uuid>,
+    },
+
+    /// Pegged order moved to a new price with the top of book; replay
+    /// requeues it there instead of repricing the pegs again
+    OrderRepriced {
+        sequence: u64,
+        timestamp_ns: u64,
+        symbol: String,
+        order_id: Uuid,
+        #[serde(with = "crate::models::decimal")]
+        new_price: Decimal,
+    },
+
+    /// Fee schedule of a scope set, or removed with `None`
+    FeeScheduleSet {
+        sequence: u64,
+        timestamp_ns: u64,
+        scope: FeeScheduleScope,
+        schedule: Option<FeeSchedule>,
+    },
+
+    /// Contingent group (OCO, OTO or bracket) accepted; the orders and stops
+    /// of its legs follow as their own submissions
+    ContingentGroupSubmitted {
+        sequence: u64,
+        timestamp_ns: u64,
+        group: ContingentGroup,
+    },
+
+    /// Contingent group cancelled; cancellations of its working legs follow
+    ContingentGroupCancelled {
+        sequence: u64,
+        timestamp_ns: u64,
+        symbol: String,
+        group_id: Uuid,
+    },
+
+    /// Matching policy of a symbol set
+    MatchingPolicySet {
+        sequence: u64,
+        timestamp_ns: u64,
+        symbol: String,
+        policy: MatchingPolicyConfig,
+    },
+}
+
+impl WalEvent {
+    /// Sequence number the event was written with
+    pub fn sequence(&self) -> u64 {
+        match self {
+            WalEvent::OrderSubmitted { sequence, .. }
+            | WalEvent::OrderCancelled { sequence, .. }
+            | WalEvent::OrderExpired { sequence, .. }
+            | WalEvent::TradeExecuted { sequence, .. }
+            | WalEvent::OrderModified { sequence, .. }
+            | WalEvent::StopOrderSubmitted { sequence, .. }
+            | WalEvent::StopOrderCancelled { sequence, .. }
+            | WalEvent::AuctionStarted { sequence, .. }
+            | WalEvent::AuctionEnded { sequence, .. }
+            | WalEvent::Checkpoint { sequence, .. }
+            | WalEvent::Deposited { sequence, .. }
+            | WalEvent::Withdrawn { sequence, .. }
+            | WalEvent::OrderRepriced { sequence, .. }
+            | WalEvent::FeeScheduleSet { sequence, .. }
+            | WalEvent::ContingentGroupSubmitted { sequence, .. }
+            | WalEvent::ContingentGroupCancelled { sequence, .. }
+            | WalEvent::MatchingPolicySet { sequence, .. } => *sequence,
+        }
+    }
+
+    /// Wall-clock time the event was journaled, in nanoseconds since the epoch
+    pub fn timestamp_ns(&self) -> u64 {
+        match self {
+            WalEvent::OrderSubmitted { timestamp_ns, .. }
+            | WalEvent::OrderCancelled { timestamp_ns, .. }
+            | WalEvent::OrderExpired { timestamp_ns, .. }
+            | WalEvent::TradeExecuted { timestamp_ns, .. }
+            | WalEvent::OrderModified { timestamp_ns, .. }
+            | WalEvent::StopOrderSubmitted { timestamp_ns, .. }
+            | WalEvent::StopOrderCancelled { timestamp_ns, .. }
+            | WalEvent::AuctionStarted { timestamp_ns, .. }
+            | WalEvent::AuctionEnded { timestamp_ns, .. }
+            | WalEvent::Checkpoint { timestamp_ns, .. }
+            | WalEvent::Deposited { timestamp_ns, .. }
+            | WalEvent::Withdrawn { timestamp_ns, .. }
+            | WalEvent::OrderRepriced { timestamp_ns, .. }
+            | WalEvent::FeeScheduleSet { timestamp_ns, .. }
+            | WalEvent::ContingentGroupSubmitted { timestamp_ns, .. }
+            | WalEvent::ContingentGroupCancelled { timestamp_ns, .. }
+            | WalEvent::MatchingPolicySet { timestamp_ns, .. } => *timestamp_ns,
+        }
+    }
+
+    /// Symbol the event applies to (`None` for stop cancellations, checkpoints,
+    /// account transfers and fee schedules other than a symbol's)
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            WalEvent::OrderSubmitted { order, .. } => Some(&order.symbol),
+            WalEvent::OrderCancelled { symbol, .. }
+            | WalEvent::OrderExpired { symbol, .. }
+            | WalEvent::OrderModified { symbol, .. }
+            | WalEvent::OrderRepriced { symbol, .. }
+            | WalEvent::AuctionStarted { symbol, .. }
+            | WalEvent::AuctionEnded { symbol, .. }
+            | WalEvent::ContingentGroupCancelled { symbol, .. }
+            | WalEvent::MatchingPolicySet { symbol, .. } => Some(symbol),
+            WalEvent::ContingentGroupSubmitted { group, .. } => Some(&group.symbol),
+            WalEvent::TradeExecuted { trade, .. } => Some(&trade.symbol),
+            WalEvent::StopOrderSubmitted { stop, .. } => Some(&stop.symbol),
+            WalEvent::FeeScheduleSet { scope: FeeScheduleScope::Symbol(symbol), .. } => Some(symbol),
+            WalEvent::FeeScheduleSet { .. }
+            | WalEvent::StopOrderCancelled { .. }
+            | WalEvent::Checkpoint { .. }
+            | WalEvent::Deposited { .. }
+            | WalEvent::Withdrawn { .. } => None,
+        }
+    }
+
+    /// Check if the event is about an order (either side of a trade counts)
+    pub fn involves_order(&self, id: Uuid) -> bool {
+        match self {
+            WalEvent::OrderSubmitted { order, .. } => order.id == id,
+            WalEvent::OrderCancelled { order_id, .. }
+            | WalEvent::OrderModified { order_id, .. }
+            | WalEvent::OrderRepriced { order_id, .. }
+            | WalEvent::StopOrderCancelled { order_id, .. } => *order_id == id,
+            WalEvent::OrderExpired { order_ids, .. } => order_ids.contains(&id),
+            WalEvent::TradeExecuted { trade, .. } => trade.buyer_order_id == id || trade.seller_order_id == id,
+            WalEvent::StopOrderSubmitted { stop, .. } => stop.id == id,
+            WalEvent::ContingentGroupSubmitted { group, .. } => {
+                group.id == id || group.all_legs().any(|leg| leg.id() == id)
+            }
+            WalEvent::ContingentGroupCancelled { group_id, .. } => *group_id == id,
+            WalEvent::AuctionStarted { .. }
+            | WalEvent::AuctionEnded { .. }
+            | WalEvent::Checkpoint { .. }
+            | WalEvent::Deposited { .. }
+            | WalEvent::Withdrawn { .. }
+            | WalEvent::FeeScheduleSet { .. }
+            | WalEvent::MatchingPolicySet { .. } => false,
+        }
+    }
 }
 
 /// Write-Ahead Log for durability
@@ -72,6 +299,9 @@ pub struct WriteAheadLog {
 
     /// Sync mode
     sync_mode: SyncMode,
+
+    /// Receivers of every appended event (replication)
+    subscribers: Vec<UnboundedSender<WalEvent>>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -85,20 +315,41 @@ pub enum SyncMode {
 }
 
 impl WriteAheadLog {
+    /// Open the WAL in a directory, continuing after its last valid record
+    ///
+    /// A crash in the middle of an append, or a damaged final record, leaves
+    /// bytes at the end of the latest segment that fail their checksum; they
+    /// are truncated so new records follow the last valid one. Segments with
+    /// a foreign header or an unsupported format version are refused.
     pub fn open(wal_dir: impl AsRef<Path>, sync_mode: SyncMode) -> io::Result<Self> {
         let wal_dir = wal_dir.as_ref().to_path_buf();
         std::fs::create_dir_all(&wal_dir)?;
 
         // Find the latest WAL file or create new one
-        let (file_index, sequence) = Self::find_latest_wal(&wal_dir)?;
+        let (file_index, sequence, valid_len) = Self::find_latest_wal(&wal_dir)?;
 
-        let wal_path = wal_dir.join(format!("wal_{:08}.log", file_index));
-        let file = OpenOptions::new()
+        let wal_path = segment_path(&wal_dir, file_index);
+        let mut file = OpenOptions::new()
             .create(true)
             .append(true)
             .open(&wal_path)?;
 
-        let current_size = std::fs::metadata(&wal_path).map(|m| m.len()).unwrap_or(0);
+        let current_size = file.metadata()?.len();
+        if current_size > valid_len {
+            tracing::warn!(
+                "Truncating {} bytes of corrupt or incomplete WAL records from {}",
+                current_size - valid_len,
+                wal_path.display()
+            );
+            file.set_len(valid_len)?;
+        }
+        let current_size = if valid_len == 0 {
+            write_segment_header(&mut file)?;
+            file.sync_data()?;
+            WAL_HEADER_LEN
+        } else {
+            valid_len
+        };
 
         Ok(Self {
             file: BufWriter::new(file),
@@ -108,6 +359,7 @@ impl WriteAheadLog {
             max_file_size: 100 * 1024 * 1024, // 100MB
             current_size,
             sync_mode,
+            subscribers: Vec::new(),
         })
     }
 
@@ -116,16 +368,21 @@ impl WriteAheadLog {
         self.sequence += 1;
         let seq = self.sequence;
 
-        // Serialize event with length prefix
+        // Serialize event with length and checksum prefix
         let encoded = bincode::serialize(&event)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
-        // Write length prefix (4 bytes) + data
+        // Write length (4 bytes) + CRC32 (4 bytes) + data
         let len = encoded.len() as u32;
         self.file.write_all(&len.to_le_bytes())?;
+        self.file.write_all(&crc32fast::hash(&encoded).to_le_bytes())?;
         self.file.write_all(&encoded)?;
 
-        self.current_size += 4 + encoded.len() as u64;
+        self.current_size += RECORD_HEADER_LEN + encoded.len() as u64;
+
+        if !self.subscribers.is_empty() {
+            self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        }
 
         // Handle sync mode
         match self.sync_mode {
@@ -154,69 +411,87 @@ impl WriteAheadLog {
         self.file.get_ref().sync_data()?;
 
         self.file_index += 1;
-        let new_path = self.wal_dir.join(format!("wal_{:08}.log", self.file_index));
+        let new_path = segment_path(&self.wal_dir, self.file_index);
 
-        let new_file = OpenOptions::new()
+        let mut new_file = OpenOptions::new()
             .create(true)
             .append(true)
             .open(&new_path)?;
+        write_segment_header(&mut new_file)?;
 
         self.file = BufWriter::new(new_file);
-        self.current_size = 0;
+        self.current_size = WAL_HEADER_LEN;
 
         Ok(())
     }
 
-    /// Replay all events from WAL files
-    pub fn replay<F>(&self, mut handler: F) -> io::Result<u64>
-    where
-        F: FnMut(WalEvent) -> io::Result<()>,
-    {
-        let mut count = 0;
-
-        // Get all WAL files sorted by index
-        let mut wal_files: Vec<_> = std::fs::read_dir(&self.wal_dir)?
-            .filter_map(|e| e.ok())
-            .filter(|e| {
-                e.path()
-                    .extension()
-                    .map(|ext| ext == "log")
-                    .unwrap_or(false)
-            })
-            .map(|e| e.path())
-            .collect();
-
-        wal_files.sort();
-
-        for wal_path in wal_files {
-            let file = File::open(&wal_path)?;
-            let mut reader = BufReader::new(file);
+    /// Continue in a new segment file, unless the current one is still empty
+    ///
+    /// Every event written so far is then in an earlier segment. Returns the
+    /// index of the segment the next event goes to.
+    pub fn start_segment(&mut self) -> io::Result<u64> {
+        if self.current_size > WAL_HEADER_LEN {
+            self.rotate()?;
+        }
+        Ok(self.file_index)
+    }
 
-            loop {
-                // Read length prefix
-                let mut len_buf = [0u8; 4];
-                match reader.read_exact(&mut len_buf) {
-                    Ok(_) => {}
-                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
-                    Err(e) => return Err(e),
-                }
+    /// Index of the segment events are currently appended to
+    pub fn current_segment(&self) -> u64 {
+        self.file_index
+    }
 
-                let len = u32::from_le_bytes(len_buf) as usize;
+    /// Receive every event appended from now on
+    ///
+    /// Returns the sequence number of the last event already written, which
+    /// is flushed to the segment files, so a reader can take the log up to it
+    /// from disk and continue with the received events.
+    pub fn subscribe(&mut self) -> io::Result<(u64, UnboundedReceiver<WalEvent>)> {
+        self.file.flush()?;
+        let (tx, rx) = unbounded_channel();
+        self.subscribers.push(tx);
+        Ok((self.sequence, rx))
+    }
 
-                // Read event data
-                let mut data = vec![0u8; len];
-                reader.read_exact(&mut data)?;
+    /// Discard every segment and continue numbering after `sequence`
+    ///
+    /// For a log whose events up to `sequence` are covered by a snapshot it
+    /// does not contain (a replica that installed its primary's snapshot, or
+    /// a log that ends before the snapshot recovery loaded).
+    pub fn restart_at(&mut self, sequence: u64) -> io::Result<()> {
+        let segment = self.start_segment()?;
+        self.remove_segments_before(segment)?;
+        self.sequence = sequence;
+        Ok(())
+    }
 
-                // Deserialize
-                let event: WalEvent = bincode::deserialize(&data)
-                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    /// Directory the segment files are in
+    pub fn dir(&self) -> &Path {
+        &self.wal_dir
+    }
 
-                handler(event)?;
-                count += 1;
+    /// Delete the segment files before `file_index`; returns how many were deleted
+    pub fn remove_segments_before(&self, file_index: u64) -> io::Result<usize> {
+        let mut removed = 0;
+        for (index, path) in list_segments(&self.wal_dir)? {
+            if index < file_index {
+                std::fs::remove_file(path)?;
+                removed += 1;
             }
         }
+        Ok(removed)
+    }
 
-        Ok(count)
+    /// Replay all events from WAL files
+    ///
+    /// A corrupt record at the end of the latest segment (which `open`
+    /// truncates) ends the replay; one in an earlier segment is an error,
+    /// as for `replay_dir`.
+    pub fn replay<F>(&self, handler: F) -> io::Result<u64>
+    where
+        F: FnMut(WalEvent) -> io::Result<()>,
+    {
+        replay_dir(&self.wal_dir, handler)
     }
 
     /// Force sync to disk
@@ -225,68 +500,285 @@ impl WriteAheadLog {
         self.file.get_ref().sync_data()
     }
 
+    /// Flush buffered records and return a handle to the current segment,
+    /// so the caller can fsync it without holding up appends
+    ///
+    /// Earlier segments were synced when the log rotated away from them.
+    pub fn sync_handle(&mut self) -> io::Result<File> {
+        self.file.flush()?;
+        self.file.get_ref().try_clone()
+    }
+
     /// Get current sequence number
     pub fn current_sequence(&self) -> u64 {
         self.sequence
     }
 
-    fn find_latest_wal(wal_dir: &Path) -> io::Result<(u64, u64)> {
-        // Find highest file index
-        let mut max_index = 0u64;
-        let mut max_sequence = 0u64;
-
-        if let Ok(entries) = std::fs::read_dir(wal_dir) {
-            for entry in entries.filter_map(|e| e.ok()) {
-                let path = entry.path();
-                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                    if name.starts_with("wal_") && name.ends_with(".log") {
-                        if let Ok(idx) = name[4..12].parse::<u64>() {
-                            if idx > max_index {
-                                max_index = idx;
-                            }
-                        }
-                    }
-                }
+    /// Find the latest WAL file and scan it for the last sequence number
+    ///
+    /// Returns the file index, the highest sequence and the length of the
+    /// file up to the end of its last valid record (0 if it has no complete
+    /// header).
+    fn find_latest_wal(wal_dir: &Path) -> io::Result<(u64, u64, u64)> {
+        let segments = list_segments(wal_dir)?;
+        let max_index = segments.last().map(|(index, _)| *index).unwrap_or(0);
+
+        // Scan the latest file to find max sequence. A file that was just
+        // rotated to holds no records yet; the sequence is then in an earlier one.
+        let (mut max_sequence, valid_len) = Self::scan_file(&segment_path(wal_dir, max_index))?;
+        for (_, path) in segments.iter().rev().skip(1) {
+            if max_sequence.is_some() {
+                break;
+            }
+            max_sequence = Self::scan_file(path)?.0;
+        }
+
+        Ok((max_index, max_sequence.unwrap_or(0), valid_len))
+    }
+
+    /// Read a WAL file up to its last valid record
+    ///
+    /// Returns the highest sequence in the file (`None` if it has no valid
+    /// record or does not exist) and the length of the header and valid records.
+    fn scan_file(path: &Path) -> io::Result<(Option<u64>, u64)> {
+        let mut reader = match SegmentReader::open(path) {
+            Ok(reader) => reader,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((None, 0)),
+            Err(e) => return Err(e),
+        };
+
+        let mut max_sequence = None;
+        for record in reader.by_ref() {
+            max_sequence = max_sequence.max(Some(record?.event.sequence()));
+        }
+        Ok((max_sequence, reader.valid_len()))
+    }
+}
+
+/// Path of the segment file with the given index
+fn segment_path(wal_dir: &Path, index: u64) -> PathBuf {
+    wal_dir.join(format!("wal_{:08}.log", index))
+}
+
+fn write_segment_header(writer: &mut impl Write) -> io::Result<()> {
+    writer.write_all(&WAL_MAGIC)?;
+    writer.write_all(&WAL_FORMAT_VERSION.to_le_bytes())
+}
+
+/// Read every event of a WAL directory in order without opening it for writing
+///
+/// Reading stops cleanly at a corrupt record at the end of the latest
+/// segment (a torn write). A corrupt record in an earlier segment means
+/// events after it would be lost, and is an error.
+pub fn replay_dir<F>(wal_dir: &Path, mut handler: F) -> io::Result<u64>
+where
+    F: FnMut(WalEvent) -> io::Result<()>,
+{
+    let mut count = 0;
+
+    let segments = list_segments(wal_dir)?;
+    let last_index = segments.last().map(|(index, _)| *index);
+
+    for (index, wal_path) in segments {
+        let mut reader = SegmentReader::open(&wal_path)?;
+        for record in reader.by_ref() {
+            handler(record?.event)?;
+            count += 1;
+        }
+
+        if let Some(corrupt) = reader.corruption() {
+            if Some(index) != last_index {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", wal_path.display(), corrupt),
+                ));
             }
+            tracing::warn!("WAL replay stopped at {}: {}", wal_path.display(), corrupt);
+        }
+    }
+
+    Ok(count)
+}
+
+/// Segment files (`wal_{index}.log`) in a WAL directory with their index, oldest first
+pub fn list_segments(wal_dir: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+    let mut files: Vec<_> = std::fs::read_dir(wal_dir)?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let path = e.path();
+            let index = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix("wal_")?
+                .strip_suffix(".log")?
+                .parse::<u64>()
+                .ok()?;
+            Some((index, path))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Why the rest of a segment cannot be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Corruption {
+    /// The file ends part-way through the header or a record (torn write)
+    Truncated,
+    /// The record's payload does not match its checksum
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The checksum matches but the payload is not a WAL event
+    Undecodable(String),
+}
+
+/// First unreadable record of a segment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorruptRecord {
+    /// Byte offset of the record (or header) in the file
+    pub offset: u64,
+    pub corruption: Corruption,
+}
+
+impl std::fmt::Display for CorruptRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.corruption {
+            Corruption::Truncated => write!(f, "incomplete record at offset {}", self.offset),
+            Corruption::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch at offset {} (expected {:08x}, found {:08x})",
+                self.offset, expected, actual
+            ),
+            Corruption::Undecodable(e) => write!(f, "undecodable record at offset {}: {}", self.offset, e),
+        }
+    }
+}
 
-            // Scan the latest file to find max sequence
-            if max_index > 0 {
-                let latest_path = wal_dir.join(format!("wal_{:08}.log", max_index));
-                if latest_path.exists() {
-                    if let Ok(file) = File::open(&latest_path) {
-                        let mut reader = BufReader::new(file);
-                        loop {
-                            let mut len_buf = [0u8; 4];
-                            match reader.read_exact(&mut len_buf) {
-                                Ok(_) => {}
-                                Err(_) => break,
-                            }
-
-                            let len = u32::from_le_bytes(len_buf) as usize;
-                            let mut data = vec![0u8; len];
-                            if reader.read_exact(&mut data).is_err() {
-                                break;
-                            }
-
-                            if let Ok(event) = bincode::deserialize::<WalEvent>(&data) {
-                                let seq = match event {
-                                    WalEvent::OrderSubmitted { sequence, .. } => sequence,
-                                    WalEvent::OrderCancelled { sequence, .. } => sequence,
-                                    WalEvent::TradeExecuted { sequence, .. } => sequence,
-                                    WalEvent::OrderModified { sequence, .. } => sequence,
-                                    WalEvent::Checkpoint { sequence, .. } => sequence,
-                                };
-                                if seq > max_sequence {
-                                    max_sequence = seq;
-                                }
-                            }
-                        }
-                    }
-                }
+/// A valid record read from a segment
+#[derive(Debug, Clone)]
+pub struct WalRecord {
+    /// Byte offset of the record in the file
+    pub offset: u64,
+    pub event: WalEvent,
+}
+
+/// Reads the records of one segment file, stopping at the first invalid one
+///
+/// Iterating yields every record up to the first one that is incomplete or
+/// fails its checksum; `corruption` then tells where and why reading stopped.
+/// Errors from the file itself are yielded as `Err`.
+pub struct SegmentReader {
+    reader: BufReader<File>,
+    file_len: u64,
+    /// End of the header and the last valid record
+    valid_len: u64,
+    corruption: Option<CorruptRecord>,
+    finished: bool,
+}
+
+impl SegmentReader {
+    /// Open a segment and check its header
+    ///
+    /// A file shorter than the header counts as a torn write; a full header
+    /// with the wrong magic or format version is an `InvalidData` error.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = Self {
+            reader: BufReader::new(file),
+            file_len,
+            valid_len: 0,
+            corruption: None,
+            finished: false,
+        };
+
+        if file_len < WAL_HEADER_LEN {
+            // Created but never written, or torn while writing the header
+            reader.finished = true;
+            if file_len > 0 {
+                reader.corruption = Some(CorruptRecord { offset: 0, corruption: Corruption::Truncated });
+            }
+            return Ok(reader);
+        }
+
+        let mut header = [0u8; WAL_HEADER_LEN as usize];
+        reader.reader.read_exact(&mut header)?;
+        if header[..8] != WAL_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a WAL segment"));
+        }
+        let version = u32::from_le_bytes(header[8..12].try_into().expect("4 bytes"));
+        if version != WAL_FORMAT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported WAL format version {}", version),
+            ));
+        }
+        reader.valid_len = WAL_HEADER_LEN;
+        Ok(reader)
+    }
+
+    /// Where and why reading stopped early, if it did
+    pub fn corruption(&self) -> Option<&CorruptRecord> {
+        self.corruption.as_ref()
+    }
+
+    /// Length of the header and the records read so far
+    pub fn valid_len(&self) -> u64 {
+        self.valid_len
+    }
+
+    fn read_record(&mut self) -> io::Result<Result<WalRecord, Corruption>> {
+        let offset = self.valid_len;
+        if self.file_len - offset < RECORD_HEADER_LEN {
+            return Ok(Err(Corruption::Truncated));
+        }
+
+        let mut prefix = [0u8; RECORD_HEADER_LEN as usize];
+        self.reader.read_exact(&mut prefix)?;
+        let len = u32::from_le_bytes(prefix[0..4].try_into().expect("4 bytes")) as u64;
+        let expected = u32::from_le_bytes(prefix[4..8].try_into().expect("4 bytes"));
+
+        // A damaged length must not make us allocate past the end of the file
+        if self.file_len - offset - RECORD_HEADER_LEN < len {
+            return Ok(Err(Corruption::Truncated));
+        }
+
+        let mut data = vec![0u8; len as usize];
+        self.reader.read_exact(&mut data)?;
+        let actual = crc32fast::hash(&data);
+        if actual != expected {
+            return Ok(Err(Corruption::ChecksumMismatch { expected, actual }));
+        }
+
+        match bincode::deserialize::<WalEvent>(&data) {
+            Ok(event) => {
+                self.valid_len += RECORD_HEADER_LEN + len;
+                Ok(Ok(WalRecord { offset, event }))
             }
+            Err(e) => Ok(Err(Corruption::Undecodable(e.to_string()))),
+        }
+    }
+}
+
+impl Iterator for SegmentReader {
+    type Item = io::Result<WalRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished || self.valid_len == self.file_len {
+            return None;
         }
 
-        Ok((max_index, max_sequence))
+        match self.read_record() {
+            Ok(Ok(record)) => Some(Ok(record)),
+            Ok(Err(corruption)) => {
+                self.finished = true;
+                self.corruption = Some(CorruptRecord { offset: self.valid_len, corruption });
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
     }
 }
 
@@ -316,6 +808,7 @@ mod tests {
             post_only: false,
             expire_time: None,
             iceberg: None,
+            peg_offset: None,
         }
     }
 
@@ -380,4 +873,192 @@ mod tests {
 
         assert_eq!(count, 5);
     }
+
+    #[test]
+    fn test_wal_reopen_resumes_sequence_and_drops_torn_record() {
+        let temp_dir = TempDir::new().unwrap();
+        {
+            let mut wal = WriteAheadLog::open(temp_dir.path(), SyncMode::EveryWrite).unwrap();
+            for i in 1..=3 {
+                wal.append(WalEvent::OrderSubmitted {
+                    sequence: i,
+                    timestamp_ns: 0,
+                    order: create_test_order(),
+                })
+                .unwrap();
+            }
+        }
+
+        // Crash in the middle of the fourth append
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(temp_dir.path().join("wal_00000000.log"))
+            .unwrap();
+        file.write_all(&100u32.to_le_bytes()).unwrap();
+        file.write_all(&[0u8; 10]).unwrap();
+
+        let mut wal = WriteAheadLog::open(temp_dir.path(), SyncMode::EveryWrite).unwrap();
+        assert_eq!(wal.current_sequence(), 3);
+        let seq = wal
+            .append(WalEvent::OrderSubmitted {
+                sequence: 4,
+                timestamp_ns: 0,
+                order: create_test_order(),
+            })
+            .unwrap();
+        assert_eq!(seq, 4);
+
+        let mut sequences = Vec::new();
+        wal.replay(|event| {
+            sequences.push(event.sequence());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(sequences, vec![1, 2, 3, 4]);
+    }
+
+    fn append_orders(wal: &mut WriteAheadLog, count: u64) {
+        for _ in 0..count {
+            let sequence = wal.current_sequence() + 1;
+            wal.append(WalEvent::OrderSubmitted {
+                sequence,
+                timestamp_ns: 0,
+                order: create_test_order(),
+            })
+            .unwrap();
+        }
+    }
+
+    fn flip_byte(path: &Path, offset_from_end: u64) {
+        let mut bytes = std::fs::read(path).unwrap();
+        let index = bytes.len() - offset_from_end as usize;
+        bytes[index] ^= 0xFF;
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_checksum_mismatch_in_last_record_is_truncated() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("wal_00000000.log");
+        {
+            let mut wal = WriteAheadLog::open(temp_dir.path(), SyncMode::EveryWrite).unwrap();
+            append_orders(&mut wal, 3);
+        }
+        assert_eq!(&std::fs::read(&path).unwrap()[..8], &WAL_MAGIC);
+
+        // Bit rot in the payload of the third record
+        flip_byte(&path, 5);
+        let mut reader = SegmentReader::open(&path).unwrap();
+        assert_eq!(reader.by_ref().count(), 2);
+        let corrupt = reader.corruption().unwrap().clone();
+        assert!(matches!(corrupt.corruption, Corruption::ChecksumMismatch { .. }));
+
+        let mut wal = WriteAheadLog::open(temp_dir.path(), SyncMode::EveryWrite).unwrap();
+        assert_eq!(wal.current_sequence(), 2);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), corrupt.offset);
+
+        append_orders(&mut wal, 1);
+        let mut sequences = Vec::new();
+        wal.replay(|event| {
+            sequences.push(event.sequence());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(sequences, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_corruption_before_the_last_segment_fails_replay() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut wal = WriteAheadLog::open(temp_dir.path(), SyncMode::EveryWrite).unwrap();
+        append_orders(&mut wal, 2);
+        assert_eq!(wal.start_segment().unwrap(), 1);
+        append_orders(&mut wal, 2);
+        wal.sync().unwrap();
+
+        flip_byte(&temp_dir.path().join("wal_00000000.log"), 5);
+        let err = wal.replay(|_| Ok(())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_foreign_segment_is_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("wal_00000000.log"), [0u8; 64]).unwrap();
+
+        let err = WriteAheadLog::open(temp_dir.path(), SyncMode::None).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_segment_written_before_order_expired_still_decodes() {
+        /// `WalEvent` as it was before `OrderExpired`; only the variant
+        /// indices matter, so the variants that are not written carry no fields
+        #[derive(Serialize)]
+        #[allow(dead_code)]
+        enum Version2Event {
+            OrderSubmitted,
+            OrderCancelled,
+            TradeExecuted,
+            OrderModified,
+            StopOrderSubmitted,
+            StopOrderCancelled,
+            AuctionStarted,
+            AuctionEnded,
+            Checkpoint {
+                sequence: u64,
+                timestamp_ns: u64,
+                checkpoint_path: String,
+            },
+            Deposited {
+                sequence: u64,
+                timestamp_ns: u64,
+                user_id: String,
+                asset: String,
+                #[serde(with = "crate::models::decimal")]
+                amount: Decimal,
+            },
+        }
+
+        let mut bytes = WAL_MAGIC.to_vec();
+        bytes.extend_from_slice(&WAL_FORMAT_VERSION.to_le_bytes());
+        let events = [
+            Version2Event::Deposited {
+                sequence: 1,
+                timestamp_ns: 0,
+                user_id: "alice".to_string(),
+                asset: "USD".to_string(),
+                amount: dec!(250),
+            },
+            Version2Event::Checkpoint {
+                sequence: 2,
+                timestamp_ns: 0,
+                checkpoint_path: "snapshot_2.bin".to_string(),
+            },
+        ];
+        for event in &events {
+            let encoded = bincode::serialize(event).unwrap();
+            bytes.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&crc32fast::hash(&encoded).to_le_bytes());
+            bytes.extend_from_slice(&encoded);
+        }
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("wal_00000000.log"), bytes).unwrap();
+
+        let wal = WriteAheadLog::open(temp_dir.path(), SyncMode::None).unwrap();
+        assert_eq!(wal.current_sequence(), 2);
+        let mut decoded = Vec::new();
+        wal.replay(|event| {
+            decoded.push(event);
+            Ok(())
+        })
+        .unwrap();
+        assert!(matches!(
+            &decoded[..],
+            [
+                WalEvent::Deposited { user_id, amount, .. },
+                WalEvent::Checkpoint { checkpoint_path, .. },
+            ] if user_id == "alice" && *amount == dec!(250) && checkpoint_path == "snapshot_2.bin"
+        ));
+    }
 }
@@ -0,0 +1,14 @@
+//! Positions and PnL per user and symbol
+//!
+//! For derivatives-style trading users hold positions rather than balances.
+//! The `PositionTracker` follows the engine's trades and keeps every user's
+//! net quantity, average entry price and realized PnL per symbol, and marks
+//! the open quantity to the current mid price for unrealized PnL. The REST
+//! API serves positions on request; `websocket::positions` pushes them to
+//! the private `positions:{user_id}` channel.
+
+pub mod position;
+pub mod tracker;
+
+pub use position::{Position, PositionState};
+pub use tracker::PositionTracker;
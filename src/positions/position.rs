@@ -0,0 +1,147 @@
+//! Net position and PnL of one user in one symbol
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::OrderSide;
+
+/// Running position built from a user's fills, on an average-cost basis
+///
+/// Fills that add to the position move the average entry price; fills
+/// against it realize PnL at the average entry price. A fill that flips the
+/// position closes the old one and opens the rest at its own price.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PositionState {
+    /// Positive when long, negative when short
+    pub net_quantity: Decimal,
+    /// Average entry price of the open quantity (zero when flat)
+    pub average_entry_price: Decimal,
+    pub realized_pnl: Decimal,
+}
+
+impl PositionState {
+    /// Apply a fill of `quantity` at `price`
+    pub fn apply_fill(&mut self, side: OrderSide, price: Decimal, quantity: Decimal) {
+        let signed = match side {
+            OrderSide::Buy => quantity,
+            OrderSide::Sell => -quantity,
+        };
+
+        if self.net_quantity.is_zero() || self.net_quantity.is_sign_positive() == signed.is_sign_positive() {
+            let open = self.net_quantity.abs();
+            self.average_entry_price = (self.average_entry_price * open + price * quantity) / (open + quantity);
+            self.net_quantity += signed;
+            return;
+        }
+
+        let closed = quantity.min(self.net_quantity.abs());
+        let direction = if self.net_quantity.is_sign_positive() { Decimal::ONE } else { -Decimal::ONE };
+        self.realized_pnl += (price - self.average_entry_price) * closed * direction;
+        self.net_quantity += signed;
+
+        if self.net_quantity.is_zero() {
+            self.average_entry_price = Decimal::ZERO;
+        } else if closed < quantity {
+            // Flipped: the rest of the fill is a new position at its price
+            self.average_entry_price = price;
+        }
+    }
+
+    /// PnL of the open quantity marked at `mark_price`
+    pub fn unrealized_pnl(&self, mark_price: Decimal) -> Decimal {
+        (mark_price - self.average_entry_price) * self.net_quantity
+    }
+}
+
+/// Position of a user in a symbol, marked to the current price
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Position {
+    pub user_id: String,
+    pub symbol: String,
+    /// Positive when long, negative when short
+    #[schema(value_type = String, example = "-5")]
+    pub net_quantity: Decimal,
+    /// Average entry price of the open quantity (`null` when flat)
+    #[schema(value_type = Option<String>, example = "150.25")]
+    pub average_entry_price: Option<Decimal>,
+    /// PnL of closed quantity, before fees
+    #[schema(value_type = String, example = "12.50")]
+    pub realized_pnl: Decimal,
+    /// Mid price the open quantity is marked to (`null` without a two-sided market)
+    #[schema(value_type = Option<String>, example = "151.00")]
+    pub mark_price: Option<Decimal>,
+    /// PnL of the open quantity at the mark price (`null` without a mark)
+    #[schema(value_type = Option<String>, example = "-3.75")]
+    pub unrealized_pnl: Option<Decimal>,
+}
+
+impl Position {
+    pub fn new(user_id: &str, symbol: &str, state: &PositionState, mark_price: Option<Decimal>) -> Self {
+        let flat = state.net_quantity.is_zero();
+        Self {
+            user_id: user_id.to_string(),
+            symbol: symbol.to_string(),
+            net_quantity: state.net_quantity,
+            average_entry_price: (!flat).then_some(state.average_entry_price),
+            realized_pnl: state.realized_pnl,
+            mark_price,
+            unrealized_pnl: if flat {
+                Some(Decimal::ZERO)
+            } else {
+                mark_price.map(|mark| state.unrealized_pnl(mark))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_adding_averages_the_entry_price() {
+        let mut state = PositionState::default();
+        state.apply_fill(OrderSide::Buy, dec!(100), dec!(10));
+        state.apply_fill(OrderSide::Buy, dec!(110), dec!(30));
+
+        assert_eq!(state.net_quantity, dec!(40));
+        assert_eq!(state.average_entry_price, dec!(107.5));
+        assert_eq!(state.realized_pnl, dec!(0));
+        assert_eq!(state.unrealized_pnl(dec!(110)), dec!(100));
+    }
+
+    #[test]
+    fn test_reducing_realizes_at_the_average_price() {
+        let mut state = PositionState::default();
+        state.apply_fill(OrderSide::Sell, dec!(50), dec!(4));
+        state.apply_fill(OrderSide::Buy, dec!(45), dec!(1));
+
+        // Short 4 @ 50, bought back 1 @ 45
+        assert_eq!(state.net_quantity, dec!(-3));
+        assert_eq!(state.average_entry_price, dec!(50));
+        assert_eq!(state.realized_pnl, dec!(5));
+        assert_eq!(state.unrealized_pnl(dec!(48)), dec!(6));
+
+        state.apply_fill(OrderSide::Buy, dec!(52), dec!(3));
+        assert_eq!(state.net_quantity, dec!(0));
+        assert_eq!(state.average_entry_price, dec!(0));
+        assert_eq!(state.realized_pnl, dec!(-1));
+    }
+
+    #[test]
+    fn test_flipping_opens_the_rest_at_the_fill_price() {
+        let mut state = PositionState::default();
+        state.apply_fill(OrderSide::Buy, dec!(10), dec!(2));
+        state.apply_fill(OrderSide::Sell, dec!(12), dec!(5));
+
+        assert_eq!(state.net_quantity, dec!(-3));
+        assert_eq!(state.average_entry_price, dec!(12));
+        assert_eq!(state.realized_pnl, dec!(4));
+
+        let position = Position::new("alice", "AAPL", &state, None);
+        assert_eq!(position.average_entry_price, Some(dec!(12)));
+        assert_eq!(position.unrealized_pnl, None);
+    }
+}
@@ -0,0 +1,176 @@
+//! Positions of every user, kept up to date from the engine's trades
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rust_decimal::Decimal;
+
+use crate::ctrader_fix::market_data::MarketTick;
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::models::{OrderSide, Trade};
+
+use super::position::{Position, PositionState};
+
+#[derive(Default)]
+struct TrackerState {
+    /// Position state by user, then symbol
+    positions: HashMap<String, BTreeMap<String, PositionState>>,
+    /// Trades of each symbol's history already applied
+    cursors: HashMap<String, usize>,
+    /// Latest FIX mid price of externally priced symbols
+    external_marks: HashMap<String, Decimal>,
+}
+
+impl TrackerState {
+    fn apply_trade(&mut self, trade: &Trade) {
+        for (user_id, side) in [(&trade.buyer_id, OrderSide::Buy), (&trade.seller_id, OrderSide::Sell)] {
+            self.positions
+                .entry(user_id.clone())
+                .or_default()
+                .entry(trade.symbol.clone())
+                .or_default()
+                .apply_fill(side, trade.price, trade.quantity);
+        }
+    }
+}
+
+/// Net positions and PnL per user and symbol
+///
+/// Positions are built from each symbol's trade history, which the engine
+/// keeps in its snapshots, so they survive a restart without their own
+/// persistence. Every read first catches up with the trades executed since
+/// the last one. Open quantity is marked to the order book mid price, or to
+/// the mid of the latest FIX `MarketTick` for symbols priced externally.
+pub struct PositionTracker {
+    engine: Arc<OrderBookEngine>,
+    state: Mutex<TrackerState>,
+}
+
+impl PositionTracker {
+    pub fn new(engine: Arc<OrderBookEngine>) -> Self {
+        Self {
+            engine,
+            state: Mutex::new(TrackerState::default()),
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, TrackerState>, OrderBookError> {
+        self.state
+            .lock()
+            .map_err(|e| OrderBookError::LockError(format!("Failed to acquire positions lock: {}", e)))
+    }
+
+    /// Apply the trades executed since the last call
+    fn catch_up(&self, state: &mut TrackerState) -> Result<(), OrderBookError> {
+        for symbol in self.engine.get_symbols()? {
+            let from = state.cursors.get(&symbol).copied().unwrap_or(0);
+            let trades = self.engine.get_trades_since(&symbol, from)?;
+            for trade in &trades {
+                state.apply_trade(trade);
+            }
+            state.cursors.insert(symbol, from + trades.len());
+        }
+        Ok(())
+    }
+
+    /// Take a FIX tick's mid price as the mark of its symbol from now on
+    pub fn on_tick(&self, tick: &MarketTick) -> Result<(), OrderBookError> {
+        if let Some(mid) = tick.mid_price() {
+            self.lock()?.external_marks.insert(tick.symbol_id.clone(), mid);
+        }
+        Ok(())
+    }
+
+    fn mark_price(&self, state: &TrackerState, symbol: &str) -> Result<Option<Decimal>, OrderBookError> {
+        match state.external_marks.get(symbol) {
+            Some(mark) => Ok(Some(*mark)),
+            None => self.engine.get_mid_price(symbol),
+        }
+    }
+
+    /// Every position a user has held, marked to the current prices
+    pub fn positions(&self, user_id: &str) -> Result<Vec<Position>, OrderBookError> {
+        let mut state = self.lock()?;
+        self.catch_up(&mut state)?;
+
+        let Some(symbols) = state.positions.get(user_id) else {
+            return Ok(Vec::new());
+        };
+        symbols
+            .iter()
+            .map(|(symbol, position)| Ok(Position::new(user_id, symbol, position, self.mark_price(&state, symbol)?)))
+            .collect()
+    }
+
+    /// A user's position in one symbol (flat if the user never traded it)
+    pub fn position(&self, user_id: &str, symbol: &str) -> Result<Position, OrderBookError> {
+        let mut state = self.lock()?;
+        self.catch_up(&mut state)?;
+
+        let position = state
+            .positions
+            .get(user_id)
+            .and_then(|symbols| symbols.get(symbol))
+            .copied()
+            .unwrap_or_default();
+        Ok(Position::new(user_id, symbol, &position, self.mark_price(&state, symbol)?))
+    }
+
+    /// Users that have traded
+    pub fn users(&self) -> Result<Vec<String>, OrderBookError> {
+        let mut state = self.lock()?;
+        self.catch_up(&mut state)?;
+        Ok(state.positions.keys().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Order, OrderType};
+    use chrono::Utc;
+    use rust_decimal_macros::dec;
+
+    fn limit(side: OrderSide, price: Decimal, quantity: Decimal, user_id: &str) -> Order {
+        Order::new("AAPL".to_string(), side, OrderType::Limit, Some(price), quantity, user_id.to_string())
+    }
+
+    #[test]
+    fn test_positions_follow_trades_and_marks() {
+        let engine = Arc::new(OrderBookEngine::new());
+        let tracker = PositionTracker::new(engine.clone());
+
+        engine.add_order(limit(OrderSide::Sell, dec!(100), dec!(10), "seller")).unwrap();
+        engine.add_order(limit(OrderSide::Buy, dec!(100), dec!(4), "buyer")).unwrap();
+        let position = tracker.position("buyer", "AAPL").unwrap();
+        assert_eq!(position.net_quantity, dec!(4));
+        assert_eq!(position.mark_price, None);
+        assert_eq!(position.unrealized_pnl, None);
+
+        // A two-sided book marks to its mid
+        engine.add_order(limit(OrderSide::Buy, dec!(98), dec!(1), "other")).unwrap();
+        let position = tracker.position("buyer", "AAPL").unwrap();
+        assert_eq!(position.mark_price, Some(dec!(99)));
+        assert_eq!(position.unrealized_pnl, Some(dec!(-4)));
+
+        engine.add_order(limit(OrderSide::Buy, dec!(100), dec!(2), "buyer")).unwrap();
+        let seller = tracker.positions("seller").unwrap();
+        assert_eq!(seller.len(), 1);
+        assert_eq!(seller[0].net_quantity, dec!(-6));
+        assert_eq!(seller[0].average_entry_price, Some(dec!(100)));
+
+        // An external tick takes over the mark
+        tracker
+            .on_tick(&MarketTick {
+                symbol_id: "AAPL".to_string(),
+                timestamp: Utc::now(),
+                bid_price: Some(dec!(90)),
+                ask_price: Some(dec!(92)),
+            })
+            .unwrap();
+        let seller = tracker.position("seller", "AAPL").unwrap();
+        assert_eq!(seller.mark_price, Some(dec!(91)));
+        assert_eq!(seller.unrealized_pnl, Some(dec!(54)));
+        assert!(tracker.positions("nobody").unwrap().is_empty());
+    }
+}
@@ -22,6 +22,24 @@ pub enum MessageType {
     ExecutionReport = 4,
     OrderBookSnapshot = 5,
     Trade = 6,
+    /// Gateway: client opens a session
+    Login = 7,
+    /// Gateway: session accepted
+    LoginAck = 8,
+    /// Gateway: either side ends the session
+    Logout = 9,
+    /// Replication: follower announces the last sequence number it applied
+    ReplicationHello = 16,
+    /// Replication: follower reports its applied sequence number
+    ReplicationAck = 17,
+    /// Replication: one WAL event from the primary
+    ReplicationEvent = 18,
+    /// Replication: part of an engine snapshot from the primary
+    ReplicationSnapshot = 19,
+    /// Replication: primary's latest sequence number while idle
+    ReplicationHeartbeat = 20,
+    /// Replication: primary refuses the follower
+    ReplicationReject = 21,
     Heartbeat = 255,
 }
 
@@ -36,6 +54,15 @@ impl TryFrom<u8> for MessageType {
             4 => Ok(MessageType::ExecutionReport),
             5 => Ok(MessageType::OrderBookSnapshot),
             6 => Ok(MessageType::Trade),
+            7 => Ok(MessageType::Login),
+            8 => Ok(MessageType::LoginAck),
+            9 => Ok(MessageType::Logout),
+            16 => Ok(MessageType::ReplicationHello),
+            17 => Ok(MessageType::ReplicationAck),
+            18 => Ok(MessageType::ReplicationEvent),
+            19 => Ok(MessageType::ReplicationSnapshot),
+            20 => Ok(MessageType::ReplicationHeartbeat),
+            21 => Ok(MessageType::ReplicationReject),
             255 => Ok(MessageType::Heartbeat),
             _ => Err(Error::new(ErrorKind::InvalidData, "Unknown message type")),
         }
@@ -43,6 +70,10 @@ impl TryFrom<u8> for MessageType {
 }
 
 /// Binary order message (52 bytes)
+///
+/// The original fixed, unversioned layout: limit and market orders only,
+/// 8-byte symbols and fixed-point (× 10^8) values. `messages::Message`
+/// carries every order option.
 #[repr(C, packed)]
 #[derive(Debug, Clone, Copy)]
 pub struct BinaryOrderMessage {
@@ -144,27 +175,15 @@ impl BinaryOrderMessage {
             post_only: false,
             expire_time: None,
             iceberg: None,
+            peg_offset: None,
         })
     }
 
     /// Create from domain Order type
     pub fn from_order(order: &Order) -> Self {
-        let mut symbol = [0u8; 8];
-        let symbol_bytes = order.symbol.as_bytes();
-        let copy_len = symbol_bytes.len().min(8);
-        symbol[..copy_len].copy_from_slice(&symbol_bytes[..copy_len]);
-
-        let price = order.price
-            .map(|p| {
-                let scaled = p * Decimal::new(PRICE_SCALE, 0);
-                scaled.to_i64().unwrap_or(0)
-            })
-            .unwrap_or(0);
-
-        let quantity = {
-            let scaled = order.quantity * Decimal::new(PRICE_SCALE, 0);
-            scaled.to_i64().unwrap_or(0)
-        };
+        let symbol = encode_symbol(&order.symbol);
+        let price = order.price.map(to_fixed_point).unwrap_or(0);
+        let quantity = to_fixed_point(order.quantity);
 
         let timestamp_ns = order.timestamp
             .timestamp_nanos_opt()
@@ -190,10 +209,66 @@ impl BinaryOrderMessage {
     }
 }
 
+/// Encode a decimal as fixed-point (× 10^8); out-of-range values become 0
+pub fn to_fixed_point(value: Decimal) -> i64 {
+    (value * Decimal::new(PRICE_SCALE, 0)).to_i64().unwrap_or(0)
+}
+
+/// Decode a fixed-point (× 10^8) value
+pub fn from_fixed_point(value: i64) -> Decimal {
+    Decimal::new(value, 8)
+}
+
+/// Null-pad a symbol to 8 bytes, truncating longer symbols
+pub fn encode_symbol(symbol: &str) -> [u8; 8] {
+    let mut bytes = [0u8; 8];
+    let copy_len = symbol.len().min(8);
+    bytes[..copy_len].copy_from_slice(&symbol.as_bytes()[..copy_len]);
+    bytes
+}
+
+/// Symbol of a null-padded 8-byte field
+pub fn decode_symbol(bytes: &[u8; 8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end_matches('\0').to_string()
+}
+
 /// Framed message with length prefix
 pub struct FramedCodec;
 
 impl FramedCodec {
+    /// Largest payload a frame can carry
+    pub const MAX_PAYLOAD: usize = u16::MAX as usize;
+
+    /// Encode an arbitrary payload with the 2-byte length prefix
+    pub fn encode_frame(payload: &[u8], buf: &mut BytesMut) -> io::Result<()> {
+        if payload.len() > Self::MAX_PAYLOAD {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Payload of {} bytes does not fit in a frame", payload.len()),
+            ));
+        }
+        buf.reserve(2 + payload.len());
+        buf.put_u16(payload.len() as u16);
+        buf.put_slice(payload);
+        Ok(())
+    }
+
+    /// Take the payload of a complete frame off the front of the buffer
+    pub fn decode_frame(buf: &mut BytesMut) -> Option<BytesMut> {
+        if buf.len() < 2 {
+            return None; // Need more data
+        }
+
+        let len = u16::from_be_bytes([buf[0], buf[1]]) as usize;
+        if buf.len() < 2 + len {
+            buf.reserve(2 + len - buf.len());
+            return None; // Need more data
+        }
+
+        buf.advance(2);
+        Some(buf.split_to(len))
+    }
+
     /// Encode a message with 2-byte length prefix
     pub fn encode_framed(msg: &BinaryOrderMessage, buf: &mut BytesMut) {
         buf.put_u16(BinaryOrderMessage::SIZE as u16);
@@ -248,6 +323,7 @@ mod tests {
             post_only: false,
             expire_time: None,
             iceberg: None,
+            peg_offset: None,
         }
     }
 
@@ -297,6 +373,25 @@ mod tests {
         assert_eq!(decoded_msg.side, binary_msg.side);
     }
 
+    #[test]
+    fn test_frame_payloads() {
+        let mut buf = BytesMut::new();
+        FramedCodec::encode_frame(&[MessageType::ReplicationAck as u8, 1, 2, 3], &mut buf).unwrap();
+        FramedCodec::encode_frame(&[], &mut buf).unwrap();
+
+        // Incomplete frames stay in the buffer
+        let mut partial = BytesMut::from(&buf[..4]);
+        assert!(FramedCodec::decode_frame(&mut partial).is_none());
+        assert_eq!(partial.len(), 4);
+
+        assert_eq!(&FramedCodec::decode_frame(&mut buf).unwrap()[..], &[17, 1, 2, 3]);
+        assert!(FramedCodec::decode_frame(&mut buf).unwrap().is_empty());
+        assert!(buf.is_empty());
+
+        let too_large = vec![0u8; FramedCodec::MAX_PAYLOAD + 1];
+        assert!(FramedCodec::encode_frame(&too_large, &mut buf).is_err());
+    }
+
     #[test]
     fn test_symbol_truncation() {
         let mut order = create_test_order();
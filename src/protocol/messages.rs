@@ -0,0 +1,846 @@
+//! Versioned message layouts
+//!
+//! Every message starts with a two-byte header: its `MessageType` and the
+//! schema version of its layout (`PROTOCOL_VERSION`). The body follows in
+//! big-endian field order:
+//!
+//! | Field     | Encoding                                                          |
+//! |-----------|-------------------------------------------------------------------|
+//! | decimal   | `i64` mantissa, `u8` scale (0-28): exact, not rounded to a tick   |
+//! | string    | `u8` length, UTF-8 bytes (`u16` length for free text)             |
+//! | timestamp | `i64` nanoseconds since the Unix epoch                            |
+//! | UUID      | 16 bytes                                                          |
+//! | option    | `u8` 0 (absent) or 1, then the value                              |
+//! | enum      | `u8` code                                                         |
+//!
+//! Bodies by message type (schema version 1):
+//!
+//! - `NewOrder`: order ID, symbol, side, order type, time in force, STP mode,
+//!   post-only (`u8`), price (option), quantity, user ID, timestamp, expire
+//!   time (option), peg offset (option), iceberg (option of total quantity
+//!   and display quantity)
+//! - `CancelOrder`: order ID, symbol
+//! - `ModifyOrder`: order ID, symbol, new price (option), new total quantity (option)
+//! - `ExecutionReport`: order ID, symbol, exec type, order status, side (option), reject
+//!   reason, price (option), last price (option), last quantity, cumulative
+//!   quantity, leaves quantity, timestamp, text
+//! - `OrderBookSnapshot`: symbol, timestamp, then bids and asks, each a `u16`
+//!   count of (price, quantity, `u32` order count) levels, best first
+//! - `Trade`: trade ID, symbol, price, quantity, buyer and seller order IDs,
+//!   buyer and seller IDs, maker and taker fees, maker and taker fee
+//!   currencies, timestamp
+//! - `Login`: heartbeat interval in seconds (`u16`), user ID
+//! - `LoginAck`: heartbeat interval in seconds (`u16`)
+//! - `Logout`: reason (text)
+//! - `Heartbeat`: empty
+//!
+//! Replication messages share the header; their bodies are defined by
+//! `replication::protocol`. Decoding rejects unknown versions and codes,
+//! truncated bodies and trailing bytes.
+
+use bytes::{Buf, BufMut, BytesMut};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::io::{self, Error, ErrorKind};
+use uuid::Uuid;
+
+use crate::models::{
+    FeeCurrency, IcebergConfig, Order, OrderBook, OrderSide, OrderStatus, OrderType, PriceLevel,
+    SelfTradePreventionMode, TimeInForce, Trade,
+};
+
+use super::binary::MessageType;
+
+/// Schema version written in the header of every message
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Write a message header
+pub fn put_header(buf: &mut BytesMut, msg_type: MessageType) {
+    buf.put_u8(msg_type as u8);
+    buf.put_u8(PROTOCOL_VERSION);
+}
+
+/// Read a message header, rejecting layouts of other schema versions
+pub fn get_header(buf: &mut impl Buf) -> io::Result<MessageType> {
+    if buf.remaining() < 2 {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Incomplete message"));
+    }
+    let msg_type = MessageType::try_from(buf.get_u8())?;
+    let version = buf.get_u8();
+    if version != PROTOCOL_VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Unsupported schema version {} of {:?}", version, msg_type),
+        ));
+    }
+    Ok(msg_type)
+}
+
+/// What an execution report reports
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecType {
+    /// Order accepted
+    New = 0,
+    /// Order (partially) filled; `last_price` / `last_quantity` hold the fill
+    Trade = 1,
+    /// Order cancelled, or the unfilled rest of an IOC / FOK / market order dropped
+    Cancelled = 2,
+    /// Order price or quantity amended
+    Replaced = 3,
+    /// New order rejected
+    Rejected = 4,
+    /// Cancel or modify request rejected; the order is unchanged
+    CancelRejected = 5,
+}
+
+/// Why a request was rejected (`RejectReason::None` on other reports)
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    None = 0,
+    InvalidOrder = 1,
+    UnknownOrder = 2,
+    DuplicateOrder = 3,
+    RiskLimit = 4,
+    RateLimit = 5,
+    TradingHalted = 6,
+    OutsidePriceBand = 7,
+    InsufficientFunds = 8,
+    InsufficientLiquidity = 9,
+    SelfTrade = 10,
+    InvalidTradingPhase = 11,
+    Unavailable = 12,
+    Other = 255,
+}
+
+/// Execution report for one step of an order's life
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionReport {
+    pub order_id: Uuid,
+    pub symbol: String,
+    pub exec_type: ExecType,
+    pub status: OrderStatus,
+    /// `None` when rejecting a request for an order the sender does not have
+    pub side: Option<OrderSide>,
+    pub reject_reason: RejectReason,
+    /// Limit price of the order (`None` for market orders)
+    pub price: Option<Decimal>,
+    /// Price of this fill (`None` unless `exec_type` is `Trade`)
+    pub last_price: Option<Decimal>,
+    /// Quantity of this fill (zero unless `exec_type` is `Trade`)
+    pub last_quantity: Decimal,
+    pub cumulative_quantity: Decimal,
+    /// Quantity still working in the book
+    pub leaves_quantity: Decimal,
+    pub timestamp: DateTime<Utc>,
+    /// Reject message, empty on other reports
+    pub text: String,
+}
+
+/// Aggregated price level of an order book snapshot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookLevel {
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub orders: u32,
+}
+
+/// Top levels of both sides of an order book
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookSnapshot {
+    pub symbol: String,
+    pub timestamp: DateTime<Utc>,
+    /// Highest price first
+    pub bids: Vec<BookLevel>,
+    /// Lowest price first
+    pub asks: Vec<BookLevel>,
+}
+
+impl BookSnapshot {
+    /// Snapshot of up to `depth` levels per side
+    pub fn from_book(book: &OrderBook, depth: usize, timestamp: DateTime<Utc>) -> Self {
+        let level = |(price, level): (&Decimal, &PriceLevel)| BookLevel {
+            price: *price,
+            quantity: level.total_quantity,
+            orders: level.orders.len() as u32,
+        };
+        Self {
+            symbol: book.symbol.clone(),
+            timestamp,
+            bids: book.bids.iter().rev().take(depth).map(level).collect(),
+            asks: book.asks.iter().take(depth).map(level).collect(),
+        }
+    }
+}
+
+/// A message of any non-replication `MessageType`
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// A new order with every option REST orders have
+    NewOrder(Order),
+    CancelOrder {
+        order_id: Uuid,
+        symbol: String,
+    },
+    /// Change the price and/or total quantity of a resting order
+    ModifyOrder {
+        order_id: Uuid,
+        symbol: String,
+        new_price: Option<Decimal>,
+        new_quantity: Option<Decimal>,
+    },
+    ExecutionReport(ExecutionReport),
+    OrderBookSnapshot(BookSnapshot),
+    Trade(Trade),
+    /// Client → gateway, first message: who the session trades for
+    Login {
+        user_id: String,
+        heartbeat_interval_secs: u16,
+    },
+    /// Gateway → client: the session is open
+    LoginAck { heartbeat_interval_secs: u16 },
+    /// Either side: the session ends after this message
+    Logout { reason: String },
+    /// Either side, while idle
+    Heartbeat,
+}
+
+impl Message {
+    pub fn message_type(&self) -> MessageType {
+        match self {
+            Message::NewOrder(_) => MessageType::NewOrder,
+            Message::CancelOrder { .. } => MessageType::CancelOrder,
+            Message::ModifyOrder { .. } => MessageType::ModifyOrder,
+            Message::ExecutionReport(_) => MessageType::ExecutionReport,
+            Message::OrderBookSnapshot(_) => MessageType::OrderBookSnapshot,
+            Message::Trade(_) => MessageType::Trade,
+            Message::Login { .. } => MessageType::Login,
+            Message::LoginAck { .. } => MessageType::LoginAck,
+            Message::Logout { .. } => MessageType::Logout,
+            Message::Heartbeat => MessageType::Heartbeat,
+        }
+    }
+
+    /// Encode the header and body; fails on values the layout cannot hold
+    /// (strings too long, decimals with more than 64 bits of mantissa,
+    /// timestamps outside 1677-2262)
+    pub fn encode(&self, buf: &mut BytesMut) -> io::Result<()> {
+        put_header(buf, self.message_type());
+        match self {
+            Message::NewOrder(order) => {
+                put_uuid(buf, order.id);
+                put_str(buf, &order.symbol)?;
+                buf.put_u8(side_code(order.side));
+                buf.put_u8(order_type_code(order.order_type));
+                buf.put_u8(time_in_force_code(order.time_in_force));
+                buf.put_u8(stp_mode_code(order.stp_mode));
+                buf.put_u8(order.post_only as u8);
+                put_option(buf, order.price, put_decimal)?;
+                put_decimal(buf, order.quantity)?;
+                put_str(buf, &order.user_id)?;
+                put_timestamp(buf, order.timestamp)?;
+                put_option(buf, order.expire_time, put_timestamp)?;
+                put_option(buf, order.peg_offset, put_decimal)?;
+                put_option(buf, order.iceberg.as_ref(), |buf, iceberg| {
+                    put_decimal(buf, iceberg.total_quantity)?;
+                    put_decimal(buf, iceberg.display_quantity)
+                })?;
+            }
+            Message::CancelOrder { order_id, symbol } => {
+                put_uuid(buf, *order_id);
+                put_str(buf, symbol)?;
+            }
+            Message::ModifyOrder {
+                order_id,
+                symbol,
+                new_price,
+                new_quantity,
+            } => {
+                put_uuid(buf, *order_id);
+                put_str(buf, symbol)?;
+                put_option(buf, *new_price, put_decimal)?;
+                put_option(buf, *new_quantity, put_decimal)?;
+            }
+            Message::ExecutionReport(report) => {
+                put_uuid(buf, report.order_id);
+                put_str(buf, &report.symbol)?;
+                buf.put_u8(report.exec_type as u8);
+                buf.put_u8(order_status_code(report.status));
+                put_option(buf, report.side, |buf, side| {
+                    buf.put_u8(side_code(side));
+                    Ok(())
+                })?;
+                buf.put_u8(report.reject_reason as u8);
+                put_option(buf, report.price, put_decimal)?;
+                put_option(buf, report.last_price, put_decimal)?;
+                put_decimal(buf, report.last_quantity)?;
+                put_decimal(buf, report.cumulative_quantity)?;
+                put_decimal(buf, report.leaves_quantity)?;
+                put_timestamp(buf, report.timestamp)?;
+                put_text(buf, &report.text)?;
+            }
+            Message::OrderBookSnapshot(snapshot) => {
+                put_str(buf, &snapshot.symbol)?;
+                put_timestamp(buf, snapshot.timestamp)?;
+                for levels in [&snapshot.bids, &snapshot.asks] {
+                    let count = u16::try_from(levels.len())
+                        .map_err(|_| Error::new(ErrorKind::InvalidInput, "More than 65535 levels"))?;
+                    buf.put_u16(count);
+                    for level in levels {
+                        put_decimal(buf, level.price)?;
+                        put_decimal(buf, level.quantity)?;
+                        buf.put_u32(level.orders);
+                    }
+                }
+            }
+            Message::Trade(trade) => {
+                put_uuid(buf, trade.id);
+                put_str(buf, &trade.symbol)?;
+                put_decimal(buf, trade.price)?;
+                put_decimal(buf, trade.quantity)?;
+                put_uuid(buf, trade.buyer_order_id);
+                put_uuid(buf, trade.seller_order_id);
+                put_str(buf, &trade.buyer_id)?;
+                put_str(buf, &trade.seller_id)?;
+                put_decimal(buf, trade.maker_fee)?;
+                put_decimal(buf, trade.taker_fee)?;
+                buf.put_u8(fee_currency_code(trade.maker_fee_currency));
+                buf.put_u8(fee_currency_code(trade.taker_fee_currency));
+                put_timestamp(buf, trade.timestamp)?;
+            }
+            Message::Login {
+                user_id,
+                heartbeat_interval_secs,
+            } => {
+                buf.put_u16(*heartbeat_interval_secs);
+                put_str(buf, user_id)?;
+            }
+            Message::LoginAck { heartbeat_interval_secs } => buf.put_u16(*heartbeat_interval_secs),
+            Message::Logout { reason } => put_text(buf, reason)?,
+            Message::Heartbeat => {}
+        }
+        Ok(())
+    }
+
+    /// Decode a whole message, header included
+    pub fn decode(buf: &mut impl Buf) -> io::Result<Self> {
+        let message = match get_header(buf)? {
+            MessageType::NewOrder => {
+                let id = get_uuid(buf)?;
+                let symbol = get_str(buf)?;
+                let side = decode_side(get_u8(buf)?)?;
+                let order_type = decode_order_type(get_u8(buf)?)?;
+                let time_in_force = decode_time_in_force(get_u8(buf)?)?;
+                let stp_mode = decode_stp_mode(get_u8(buf)?)?;
+                let post_only = get_bool(buf)?;
+                let price = get_option(buf, get_decimal)?;
+                let quantity = get_decimal(buf)?;
+                let user_id = get_str(buf)?;
+                let timestamp = get_timestamp(buf)?;
+                let expire_time = get_option(buf, get_timestamp)?;
+
+                let mut order = Order::new_with_options(
+                    symbol,
+                    side,
+                    order_type,
+                    price,
+                    quantity,
+                    user_id,
+                    time_in_force,
+                    stp_mode,
+                    post_only,
+                    expire_time,
+                );
+                order.id = id;
+                order.timestamp = timestamp;
+                order.peg_offset = get_option(buf, get_decimal)?;
+                order.iceberg = get_option(buf, |buf| {
+                    let total = get_decimal(buf)?;
+                    Ok(IcebergConfig::new(total, get_decimal(buf)?))
+                })?;
+                Message::NewOrder(order)
+            }
+            MessageType::CancelOrder => Message::CancelOrder {
+                order_id: get_uuid(buf)?,
+                symbol: get_str(buf)?,
+            },
+            MessageType::ModifyOrder => Message::ModifyOrder {
+                order_id: get_uuid(buf)?,
+                symbol: get_str(buf)?,
+                new_price: get_option(buf, get_decimal)?,
+                new_quantity: get_option(buf, get_decimal)?,
+            },
+            MessageType::ExecutionReport => Message::ExecutionReport(ExecutionReport {
+                order_id: get_uuid(buf)?,
+                symbol: get_str(buf)?,
+                exec_type: decode_exec_type(get_u8(buf)?)?,
+                status: decode_order_status(get_u8(buf)?)?,
+                side: get_option(buf, |buf| decode_side(get_u8(buf)?))?,
+                reject_reason: decode_reject_reason(get_u8(buf)?)?,
+                price: get_option(buf, get_decimal)?,
+                last_price: get_option(buf, get_decimal)?,
+                last_quantity: get_decimal(buf)?,
+                cumulative_quantity: get_decimal(buf)?,
+                leaves_quantity: get_decimal(buf)?,
+                timestamp: get_timestamp(buf)?,
+                text: get_text(buf)?,
+            }),
+            MessageType::OrderBookSnapshot => {
+                let symbol = get_str(buf)?;
+                let timestamp = get_timestamp(buf)?;
+                let bids = get_levels(buf)?;
+                let asks = get_levels(buf)?;
+                Message::OrderBookSnapshot(BookSnapshot {
+                    symbol,
+                    timestamp,
+                    bids,
+                    asks,
+                })
+            }
+            MessageType::Trade => Message::Trade(Trade {
+                id: get_uuid(buf)?,
+                symbol: get_str(buf)?,
+                price: get_decimal(buf)?,
+                quantity: get_decimal(buf)?,
+                buyer_order_id: get_uuid(buf)?,
+                seller_order_id: get_uuid(buf)?,
+                buyer_id: get_str(buf)?,
+                seller_id: get_str(buf)?,
+                maker_fee: get_decimal(buf)?,
+                taker_fee: get_decimal(buf)?,
+                maker_fee_currency: decode_fee_currency(get_u8(buf)?)?,
+                taker_fee_currency: decode_fee_currency(get_u8(buf)?)?,
+                timestamp: get_timestamp(buf)?,
+            }),
+            MessageType::Login => Message::Login {
+                heartbeat_interval_secs: get_u16(buf)?,
+                user_id: get_str(buf)?,
+            },
+            MessageType::LoginAck => Message::LoginAck {
+                heartbeat_interval_secs: get_u16(buf)?,
+            },
+            MessageType::Logout => Message::Logout { reason: get_text(buf)? },
+            MessageType::Heartbeat => Message::Heartbeat,
+            other => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{:?} is a replication message", other),
+                ))
+            }
+        };
+        if buf.has_remaining() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} trailing bytes after {:?}", buf.remaining(), message.message_type()),
+            ));
+        }
+        Ok(message)
+    }
+}
+
+fn get_levels(buf: &mut impl Buf) -> io::Result<Vec<BookLevel>> {
+    let count = get_u16(buf)?;
+    (0..count)
+        .map(|_| {
+            Ok(BookLevel {
+                price: get_decimal(buf)?,
+                quantity: get_decimal(buf)?,
+                orders: get_u32(buf)?,
+            })
+        })
+        .collect()
+}
+
+// Field encodings
+
+fn ensure(buf: &impl Buf, len: usize) -> io::Result<()> {
+    if buf.remaining() < len {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Incomplete message"));
+    }
+    Ok(())
+}
+
+fn get_u8(buf: &mut impl Buf) -> io::Result<u8> {
+    ensure(buf, 1)?;
+    Ok(buf.get_u8())
+}
+
+fn get_u16(buf: &mut impl Buf) -> io::Result<u16> {
+    ensure(buf, 2)?;
+    Ok(buf.get_u16())
+}
+
+fn get_u32(buf: &mut impl Buf) -> io::Result<u32> {
+    ensure(buf, 4)?;
+    Ok(buf.get_u32())
+}
+
+fn get_bool(buf: &mut impl Buf) -> io::Result<bool> {
+    match get_u8(buf)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        other => Err(Error::new(ErrorKind::InvalidData, format!("Invalid flag {}", other))),
+    }
+}
+
+fn put_uuid(buf: &mut BytesMut, id: Uuid) {
+    buf.put_slice(id.as_bytes());
+}
+
+fn get_uuid(buf: &mut impl Buf) -> io::Result<Uuid> {
+    ensure(buf, 16)?;
+    let mut bytes = [0u8; 16];
+    buf.copy_to_slice(&mut bytes);
+    Ok(Uuid::from_bytes(bytes))
+}
+
+fn put_decimal(buf: &mut BytesMut, value: Decimal) -> io::Result<()> {
+    let mantissa = i64::try_from(value.mantissa())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, format!("{} does not fit a 64-bit mantissa", value)))?;
+    buf.put_i64(mantissa);
+    buf.put_u8(value.scale() as u8);
+    Ok(())
+}
+
+fn get_decimal(buf: &mut impl Buf) -> io::Result<Decimal> {
+    ensure(buf, 9)?;
+    let mantissa = buf.get_i64();
+    let scale = buf.get_u8();
+    Decimal::try_from_i128_with_scale(mantissa.into(), scale.into())
+        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("Invalid decimal scale {}", scale)))
+}
+
+fn put_str(buf: &mut BytesMut, value: &str) -> io::Result<()> {
+    let len = u8::try_from(value.len())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, format!("String longer than 255 bytes: {}", value)))?;
+    buf.put_u8(len);
+    buf.put_slice(value.as_bytes());
+    Ok(())
+}
+
+fn get_str(buf: &mut impl Buf) -> io::Result<String> {
+    let len = get_u8(buf)? as usize;
+    get_utf8(buf, len)
+}
+
+fn put_text(buf: &mut BytesMut, value: &str) -> io::Result<()> {
+    let len = u16::try_from(value.len())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "Text longer than 65535 bytes"))?;
+    buf.put_u16(len);
+    buf.put_slice(value.as_bytes());
+    Ok(())
+}
+
+fn get_text(buf: &mut impl Buf) -> io::Result<String> {
+    let len = get_u16(buf)? as usize;
+    get_utf8(buf, len)
+}
+
+fn get_utf8(buf: &mut impl Buf, len: usize) -> io::Result<String> {
+    ensure(buf, len)?;
+    let mut bytes = vec![0u8; len];
+    buf.copy_to_slice(&mut bytes);
+    String::from_utf8(bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+fn put_timestamp(buf: &mut BytesMut, value: DateTime<Utc>) -> io::Result<()> {
+    let nanos = value
+        .timestamp_nanos_opt()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("Timestamp {} out of range", value)))?;
+    buf.put_i64(nanos);
+    Ok(())
+}
+
+fn get_timestamp(buf: &mut impl Buf) -> io::Result<DateTime<Utc>> {
+    ensure(buf, 8)?;
+    Ok(DateTime::from_timestamp_nanos(buf.get_i64()))
+}
+
+fn put_option<T>(
+    buf: &mut BytesMut,
+    value: Option<T>,
+    put: impl FnOnce(&mut BytesMut, T) -> io::Result<()>,
+) -> io::Result<()> {
+    match value {
+        Some(value) => {
+            buf.put_u8(1);
+            put(buf, value)
+        }
+        None => {
+            buf.put_u8(0);
+            Ok(())
+        }
+    }
+}
+
+fn get_option<B: Buf, T>(buf: &mut B, get: impl FnOnce(&mut B) -> io::Result<T>) -> io::Result<Option<T>> {
+    if get_bool(buf)? {
+        get(buf).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+// Enum codes
+
+fn invalid_code(what: &str, code: u8) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("Unknown {} {}", what, code))
+}
+
+fn side_code(side: OrderSide) -> u8 {
+    match side {
+        OrderSide::Buy => 0,
+        OrderSide::Sell => 1,
+    }
+}
+
+fn decode_side(code: u8) -> io::Result<OrderSide> {
+    match code {
+        0 => Ok(OrderSide::Buy),
+        1 => Ok(OrderSide::Sell),
+        _ => Err(invalid_code("side", code)),
+    }
+}
+
+fn order_type_code(order_type: OrderType) -> u8 {
+    match order_type {
+        OrderType::Limit => 0,
+        OrderType::Market => 1,
+        OrderType::PrimaryPeg => 2,
+        OrderType::MidpointPeg => 3,
+        OrderType::MarketPeg => 4,
+    }
+}
+
+fn decode_order_type(code: u8) -> io::Result<OrderType> {
+    match code {
+        0 => Ok(OrderType::Limit),
+        1 => Ok(OrderType::Market),
+        2 => Ok(OrderType::PrimaryPeg),
+        3 => Ok(OrderType::MidpointPeg),
+        4 => Ok(OrderType::MarketPeg),
+        _ => Err(invalid_code("order type", code)),
+    }
+}
+
+fn time_in_force_code(time_in_force: TimeInForce) -> u8 {
+    match time_in_force {
+        TimeInForce::GTC => 0,
+        TimeInForce::IOC => 1,
+        TimeInForce::FOK => 2,
+        TimeInForce::GTD => 3,
+        TimeInForce::DAY => 4,
+    }
+}
+
+fn decode_time_in_force(code: u8) -> io::Result<TimeInForce> {
+    match code {
+        0 => Ok(TimeInForce::GTC),
+        1 => Ok(TimeInForce::IOC),
+        2 => Ok(TimeInForce::FOK),
+        3 => Ok(TimeInForce::GTD),
+        4 => Ok(TimeInForce::DAY),
+        _ => Err(invalid_code("time in force", code)),
+    }
+}
+
+fn stp_mode_code(mode: SelfTradePreventionMode) -> u8 {
+    match mode {
+        SelfTradePreventionMode::None => 0,
+        SelfTradePreventionMode::CancelResting => 1,
+        SelfTradePreventionMode::CancelIncoming => 2,
+        SelfTradePreventionMode::CancelBoth => 3,
+        SelfTradePreventionMode::CancelSmallest => 4,
+        SelfTradePreventionMode::DecrementBoth => 5,
+    }
+}
+
+fn decode_stp_mode(code: u8) -> io::Result<SelfTradePreventionMode> {
+    match code {
+        0 => Ok(SelfTradePreventionMode::None),
+        1 => Ok(SelfTradePreventionMode::CancelResting),
+        2 => Ok(SelfTradePreventionMode::CancelIncoming),
+        3 => Ok(SelfTradePreventionMode::CancelBoth),
+        4 => Ok(SelfTradePreventionMode::CancelSmallest),
+        5 => Ok(SelfTradePreventionMode::DecrementBoth),
+        _ => Err(invalid_code("STP mode", code)),
+    }
+}
+
+fn order_status_code(status: OrderStatus) -> u8 {
+    match status {
+        OrderStatus::New => 0,
+        OrderStatus::PartiallyFilled => 1,
+        OrderStatus::Filled => 2,
+        OrderStatus::Cancelled => 3,
+        OrderStatus::Rejected => 4,
+        OrderStatus::Expired => 5,
+    }
+}
+
+fn decode_order_status(code: u8) -> io::Result<OrderStatus> {
+    match code {
+        0 => Ok(OrderStatus::New),
+        1 => Ok(OrderStatus::PartiallyFilled),
+        2 => Ok(OrderStatus::Filled),
+        3 => Ok(OrderStatus::Cancelled),
+        4 => Ok(OrderStatus::Rejected),
+        5 => Ok(OrderStatus::Expired),
+        _ => Err(invalid_code("order status", code)),
+    }
+}
+
+fn decode_exec_type(code: u8) -> io::Result<ExecType> {
+    match code {
+        0 => Ok(ExecType::New),
+        1 => Ok(ExecType::Trade),
+        2 => Ok(ExecType::Cancelled),
+        3 => Ok(ExecType::Replaced),
+        4 => Ok(ExecType::Rejected),
+        5 => Ok(ExecType::CancelRejected),
+        _ => Err(invalid_code("exec type", code)),
+    }
+}
+
+fn decode_reject_reason(code: u8) -> io::Result<RejectReason> {
+    match code {
+        0 => Ok(RejectReason::None),
+        1 => Ok(RejectReason::InvalidOrder),
+        2 => Ok(RejectReason::UnknownOrder),
+        3 => Ok(RejectReason::DuplicateOrder),
+        4 => Ok(RejectReason::RiskLimit),
+        5 => Ok(RejectReason::RateLimit),
+        6 => Ok(RejectReason::TradingHalted),
+        7 => Ok(RejectReason::OutsidePriceBand),
+        8 => Ok(RejectReason::InsufficientFunds),
+        9 => Ok(RejectReason::InsufficientLiquidity),
+        10 => Ok(RejectReason::SelfTrade),
+        11 => Ok(RejectReason::InvalidTradingPhase),
+        12 => Ok(RejectReason::Unavailable),
+        255 => Ok(RejectReason::Other),
+        _ => Err(invalid_code("reject reason", code)),
+    }
+}
+
+fn fee_currency_code(currency: FeeCurrency) -> u8 {
+    match currency {
+        FeeCurrency::Quote => 0,
+        FeeCurrency::Base => 1,
+    }
+}
+
+fn decode_fee_currency(code: u8) -> io::Result<FeeCurrency> {
+    match code {
+        0 => Ok(FeeCurrency::Quote),
+        1 => Ok(FeeCurrency::Base),
+        _ => Err(invalid_code("fee currency", code)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn round_trip(message: &Message) -> Message {
+        let mut buf = BytesMut::new();
+        message.encode(&mut buf).unwrap();
+        Message::decode(&mut buf).unwrap()
+    }
+
+    #[test]
+    fn test_new_order_carries_every_option() {
+        let mut order = Order::new_with_options(
+            "A-VERY-LONG-SYMBOL/USD".to_string(),
+            OrderSide::Sell,
+            OrderType::MidpointPeg,
+            Some(dec!(101.123456789012)),
+            dec!(5),
+            "market-maker-7".to_string(),
+            TimeInForce::GTD,
+            SelfTradePreventionMode::DecrementBoth,
+            true,
+            Some(Utc::now()),
+        );
+        order.peg_offset = Some(dec!(-0.01));
+        order.iceberg = Some(IcebergConfig::new(dec!(50), dec!(5)));
+
+        let Message::NewOrder(decoded) = round_trip(&Message::NewOrder(order.clone())) else {
+            panic!("expected a new order");
+        };
+        assert_eq!(decoded.id, order.id);
+        assert_eq!(decoded.symbol, order.symbol);
+        assert_eq!((decoded.side, decoded.order_type, decoded.time_in_force), (order.side, order.order_type, order.time_in_force));
+        assert_eq!((decoded.stp_mode, decoded.post_only), (order.stp_mode, order.post_only));
+        assert_eq!((decoded.price, decoded.quantity), (order.price, order.quantity));
+        assert_eq!(decoded.user_id, order.user_id);
+        assert_eq!((decoded.timestamp, decoded.expire_time), (order.timestamp, order.expire_time));
+        assert_eq!(decoded.peg_offset, order.peg_offset);
+        let iceberg = decoded.iceberg.unwrap();
+        assert_eq!((iceberg.total_quantity, iceberg.display_quantity), (dec!(50), dec!(5)));
+    }
+
+    #[test]
+    fn test_other_versions_and_malformed_messages_are_rejected() {
+        let mut buf = BytesMut::new();
+        Message::Heartbeat.encode(&mut buf).unwrap();
+        assert_eq!(&buf[..], &[MessageType::Heartbeat as u8, PROTOCOL_VERSION]);
+
+        buf[1] = PROTOCOL_VERSION + 1;
+        let error = Message::decode(&mut buf).unwrap_err();
+        assert!(error.to_string().contains("Unsupported schema version"));
+
+        // Trailing bytes
+        let mut buf = BytesMut::new();
+        Message::Heartbeat.encode(&mut buf).unwrap();
+        buf.put_u8(0);
+        assert!(Message::decode(&mut buf).is_err());
+
+        // Truncated body
+        let mut buf = BytesMut::new();
+        Message::CancelOrder {
+            order_id: Uuid::new_v4(),
+            symbol: "AAPL".to_string(),
+        }
+        .encode(&mut buf)
+        .unwrap();
+        let mut truncated = buf.split_to(buf.len() - 1);
+        assert_eq!(Message::decode(&mut truncated).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+
+        // Values the layout cannot hold
+        let long_symbol = Message::CancelOrder {
+            order_id: Uuid::new_v4(),
+            symbol: "X".repeat(256),
+        };
+        assert!(long_symbol.encode(&mut BytesMut::new()).is_err());
+        let huge = Message::ModifyOrder {
+            order_id: Uuid::new_v4(),
+            symbol: "AAPL".to_string(),
+            new_price: Some(Decimal::MAX),
+            new_quantity: None,
+        };
+        assert!(huge.encode(&mut BytesMut::new()).is_err());
+    }
+
+    #[test]
+    fn test_book_snapshot_lists_best_levels_first() {
+        let mut book = OrderBook::new("AAPL".to_string());
+        for (side, price) in [(OrderSide::Buy, dec!(99)), (OrderSide::Buy, dec!(98)), (OrderSide::Sell, dec!(101))] {
+            let mut level = PriceLevel::new(price);
+            level.add_order(Uuid::new_v4(), dec!(1));
+            match side {
+                OrderSide::Buy => book.bids.insert(price, level),
+                OrderSide::Sell => book.asks.insert(price, level),
+            };
+        }
+
+        let snapshot = BookSnapshot::from_book(&book, 1, Utc::now());
+        assert_eq!(snapshot.bids, vec![BookLevel { price: dec!(99), quantity: dec!(1), orders: 1 }]);
+        assert_eq!(snapshot.asks.len(), 1);
+        match round_trip(&Message::OrderBookSnapshot(snapshot.clone())) {
+            Message::OrderBookSnapshot(decoded) => assert_eq!(decoded, snapshot),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+}
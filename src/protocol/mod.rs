@@ -1,3 +1,5 @@
 pub mod binary;
+pub mod messages;
 
 pub use binary::{BinaryOrderMessage, FramedCodec, MessageType};
+pub use messages::{BookLevel, BookSnapshot, ExecType, ExecutionReport, Message, RejectReason, PROTOCOL_VERSION};
@@ -0,0 +1,12 @@
+//! Deterministic replay of recorded order flow
+//!
+//! A `Replayer` feeds recorded engine events (a write-ahead log directory,
+//! or a JSON-lines capture as written by `wal-inspect json`) through a
+//! separate `OrderBookEngine`. Each event is applied at its recorded time on
+//! a simulated clock, and every ID the engine generates comes from a seeded
+//! source, so the same input and seed produce byte-identical trades on every
+//! run. Use it to reproduce incidents and to backtest against real flow.
+
+pub mod replayer;
+
+pub use replayer::Replayer;
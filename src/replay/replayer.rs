@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::models::Trade;
+use crate::persistence::{replay_dir, WalEvent};
+use crate::utils::clock::{self, Clock, SeededIds, SimulatedClock};
+
+/// Replays recorded events into an engine on a simulated clock
+pub struct Replayer {
+    engine: OrderBookEngine,
+    clock: Arc<SimulatedClock>,
+    ids: Arc<SeededIds>,
+    /// Number of each symbol's trades already collected
+    collected: HashMap<String, usize>,
+    trades: Vec<Trade>,
+    events: u64,
+    rejected: u64,
+}
+
+impl Replayer {
+    /// Replay into `engine`, drawing generated IDs from `seed`
+    ///
+    /// The engine should hold no orders and no write-ahead log, and be
+    /// configured with the instruments and matching policies the flow was
+    /// recorded with.
+    pub fn new(engine: OrderBookEngine, seed: u64) -> Self {
+        Self {
+            engine,
+            clock: Arc::new(SimulatedClock::new(DateTime::from_timestamp_nanos(0))),
+            ids: Arc::new(SeededIds::new(seed)),
+            collected: HashMap::new(),
+            trades: Vec::new(),
+            events: 0,
+            rejected: 0,
+        }
+    }
+
+    pub fn engine(&self) -> &OrderBookEngine {
+        &self.engine
+    }
+
+    /// Time of the simulated clock (the time of the last event applied)
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Trades produced so far, in the order they were executed
+    pub fn trades(&self) -> &[Trade] {
+        &self.trades
+    }
+
+    pub fn into_trades(self) -> Vec<Trade> {
+        self.trades
+    }
+
+    /// Number of events applied
+    pub fn events(&self) -> u64 {
+        self.events
+    }
+
+    /// Number of events the engine rejected
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    /// Apply one event at its recorded time and return the trades it produced
+    ///
+    /// Recorded trades are not applied; matching the replayed orders
+    /// produces them again.
+    pub fn apply(&mut self, event: WalEvent) -> Result<Vec<Trade>, OrderBookError> {
+        self.clock.set(DateTime::from_timestamp_nanos(event.timestamp_ns() as i64));
+        let symbol = event.symbol().map(str::to_string);
+        self.events += 1;
+
+        let result = clock::with_sources(self.clock.clone(), self.ids.clone(), || self.engine.replay_event(event));
+        let trades = match &symbol {
+            Some(symbol) => self.collect_trades(symbol),
+            None => Ok(Vec::new()),
+        };
+        if let Err(e) = result {
+            self.rejected += 1;
+            return Err(e);
+        }
+        trades
+    }
+
+    /// Take the trades of `symbol` that have not been collected yet
+    fn collect_trades(&mut self, symbol: &str) -> Result<Vec<Trade>, OrderBookError> {
+        let collected = self.collected.get(symbol).copied().unwrap_or(0);
+        let trades = self.engine.get_trades_since(symbol, collected)?;
+        self.collected.insert(symbol.to_string(), collected + trades.len());
+        self.trades.extend(trades.iter().cloned());
+        Ok(trades)
+    }
+
+    /// Apply an event, counting a rejection instead of failing
+    fn apply_recorded(&mut self, event: WalEvent) {
+        let sequence = event.sequence();
+        if let Err(e) = self.apply(event) {
+            // Requests the engine rejected were rejected when they were recorded as well
+            tracing::debug!("Replayed event {} rejected: {}", sequence, e);
+        }
+    }
+
+    /// Replay every event of a write-ahead log directory; returns the number of events
+    pub fn replay_wal(&mut self, wal_dir: &Path) -> io::Result<u64> {
+        replay_dir(wal_dir, |event| {
+            self.apply_recorded(event);
+            Ok(())
+        })
+    }
+
+    /// Replay a capture with one JSON-encoded `WalEvent` per line; returns the number of events
+    pub fn replay_capture(&mut self, capture: impl BufRead) -> io::Result<u64> {
+        let mut count = 0;
+        for (index, line) in capture.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: WalEvent = serde_json::from_str(&line).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", index + 1, e))
+            })?;
+            self.apply_recorded(event);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Order, OrderSide, OrderType};
+    use rust_decimal_macros::dec;
+
+    fn submitted(order: Order, timestamp_ns: u64) -> WalEvent {
+        WalEvent::OrderSubmitted {
+            sequence: 0,
+            timestamp_ns,
+            order,
+        }
+    }
+
+    #[test]
+    fn test_trades_take_event_time_and_seeded_ids() {
+        let ask = Order::new("AAPL".to_string(), OrderSide::Sell, OrderType::Limit, Some(dec!(10)), dec!(5), "seller".to_string());
+        let bid = Order::new("AAPL".to_string(), OrderSide::Buy, OrderType::Limit, Some(dec!(10)), dec!(3), "buyer".to_string());
+        let events = [submitted(ask, 1_000), submitted(bid, 2_000)];
+
+        let run = |seed| {
+            let mut replayer = Replayer::new(OrderBookEngine::new(), seed);
+            for event in events.clone() {
+                replayer.apply(event).unwrap();
+            }
+            assert_eq!(replayer.now(), DateTime::from_timestamp_nanos(2_000));
+            replayer.into_trades()
+        };
+
+        let trades = run(1);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].timestamp, DateTime::from_timestamp_nanos(2_000));
+        assert_eq!(trades[0].quantity, dec!(3));
+        assert_eq!(bincode::serialize(&trades).unwrap(), bincode::serialize(&run(1)).unwrap());
+        assert_ne!(run(2)[0].id, trades[0].id);
+    }
+
+    #[test]
+    fn test_capture_lines_must_be_events() {
+        let mut replayer = Replayer::new(OrderBookEngine::new(), 0);
+        let error = replayer.replay_capture("\n{\"not\":\"an event\"}\n".as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("line 2:"));
+    }
+}
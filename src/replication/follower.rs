@@ -0,0 +1,194 @@
+//! Follower side: apply the primary's log to a standby engine
+
+use std::io;
+use std::sync::{Arc, Mutex, RwLock};
+
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+use tokio_util::codec::Framed;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::persistence::EngineSnapshot;
+
+use super::protocol::{ReplicationCodec, ReplicationMessage};
+
+/// Time between attempts to reach the primary
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Replication progress of a follower
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FollowerStatus {
+    /// Address of the primary
+    pub primary: String,
+    pub connected: bool,
+    /// Set once the follower has been promoted and stopped following
+    pub promoted: bool,
+    /// Last sequence number applied to the local engine
+    pub applied_sequence: u64,
+    /// Latest sequence number the primary reported
+    pub primary_sequence: u64,
+    /// Events journaled on the primary and not yet applied here
+    pub lag_events: u64,
+    /// Time from the primary journaling the last applied event to applying it here
+    pub lag_ms: Option<i64>,
+    pub last_message_at: Option<DateTime<Utc>>,
+}
+
+/// Keeps a standby engine in step with a primary until it is promoted
+pub struct ReplicationFollower {
+    engine: Arc<OrderBookEngine>,
+    status: Arc<RwLock<FollowerStatus>>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+fn engine_error(e: OrderBookError) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+impl ReplicationFollower {
+    /// Make the engine a standby and follow the primary at `primary` (`host:port`)
+    ///
+    /// Call after the engine has recovered from its own log: the follower
+    /// asks the primary for the events after the engine's last sequence
+    /// number. Lost connections are retried until the follower is promoted.
+    pub fn start(engine: Arc<OrderBookEngine>, primary: String) -> Result<Arc<Self>, OrderBookError> {
+        engine.set_standby(true);
+        let applied_sequence = engine.last_sequence()?;
+        let status = Arc::new(RwLock::new(FollowerStatus {
+            primary: primary.clone(),
+            connected: false,
+            promoted: false,
+            applied_sequence,
+            primary_sequence: applied_sequence,
+            lag_events: 0,
+            lag_ms: None,
+            last_message_at: None,
+        }));
+
+        let task = tokio::spawn(run(engine.clone(), primary, status.clone()));
+        Ok(Arc::new(Self {
+            engine,
+            status,
+            task: Mutex::new(Some(task)),
+        }))
+    }
+
+    /// Current replication progress
+    pub fn status(&self) -> FollowerStatus {
+        self.status.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Stop following and let the engine take orders
+    ///
+    /// Returns the last sequence number applied; the engine journals new
+    /// events after it.
+    pub async fn promote(&self) -> Result<u64, OrderBookError> {
+        let task = self
+            .task
+            .lock()
+            .map_err(|e| OrderBookError::LockError(format!("Failed to acquire lock: {}", e)))?
+            .take()
+            .ok_or_else(|| OrderBookError::ReplicationError("follower is already promoted".to_string()))?;
+
+        // Wait for the task to stop, so no replicated event is applied after this
+        task.abort();
+        let _ = task.await;
+
+        self.engine.set_standby(false);
+        let last_sequence = self.engine.last_sequence()?;
+        {
+            let mut status = self.status.write().unwrap_or_else(|e| e.into_inner());
+            status.connected = false;
+            status.promoted = true;
+        }
+        info!("Promoted to primary at sequence {}", last_sequence);
+        Ok(last_sequence)
+    }
+}
+
+/// Follow the primary, reconnecting whenever the connection is lost
+async fn run(engine: Arc<OrderBookEngine>, primary: String, status: Arc<RwLock<FollowerStatus>>) {
+    loop {
+        match TcpStream::connect(&primary).await {
+            Ok(stream) => {
+                info!("Following primary {}", primary);
+                if let Err(e) = follow(&engine, stream, &status).await {
+                    warn!("Replication from primary {} interrupted: {}", primary, e);
+                }
+            }
+            Err(e) => warn!("Cannot reach primary {}: {}", primary, e),
+        }
+
+        status.write().unwrap_or_else(|e| e.into_inner()).connected = false;
+        sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Apply the stream of one connection until it ends
+async fn follow(engine: &OrderBookEngine, stream: TcpStream, status: &RwLock<FollowerStatus>) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut connection = Framed::new(stream, ReplicationCodec);
+    let last_sequence = engine.last_sequence().map_err(engine_error)?;
+    connection.send(ReplicationMessage::Hello { last_sequence }).await?;
+    status.write().unwrap_or_else(|e| e.into_inner()).connected = true;
+
+    let mut snapshot = Vec::new();
+    while let Some(message) = connection.next().await {
+        let mut acknowledge = false;
+        match message? {
+            ReplicationMessage::Event(event) => {
+                let sequence = event.sequence();
+                let journaled_at = DateTime::from_timestamp_nanos(event.timestamp_ns() as i64);
+                engine.apply_replicated(*event).map_err(engine_error)?;
+
+                let mut status = status.write().unwrap_or_else(|e| e.into_inner());
+                status.applied_sequence = sequence;
+                status.primary_sequence = status.primary_sequence.max(sequence);
+                status.lag_ms = Some((Utc::now() - journaled_at).num_milliseconds());
+                // Acknowledge once the events received so far are applied
+                acknowledge = connection.read_buffer().is_empty();
+            }
+            ReplicationMessage::SnapshotChunk { last, data } => {
+                snapshot.extend_from_slice(&data);
+                if last {
+                    let installed: EngineSnapshot = bincode::deserialize(&snapshot)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    snapshot = Vec::new();
+                    let sequence = installed.sequence;
+                    engine.install_snapshot(installed).map_err(engine_error)?;
+                    info!("Installed primary snapshot at sequence {}", sequence);
+
+                    let mut status = status.write().unwrap_or_else(|e| e.into_inner());
+                    status.applied_sequence = sequence;
+                    status.primary_sequence = status.primary_sequence.max(sequence);
+                    acknowledge = true;
+                }
+            }
+            ReplicationMessage::Heartbeat { primary_sequence } => {
+                status.write().unwrap_or_else(|e| e.into_inner()).primary_sequence = primary_sequence;
+                acknowledge = true;
+            }
+            ReplicationMessage::Reject { reason } => return Err(io::Error::other(reason)),
+            other @ (ReplicationMessage::Hello { .. } | ReplicationMessage::Ack { .. }) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unexpected message {:?}", other)))
+            }
+        }
+
+        let applied_sequence = {
+            let mut status = status.write().unwrap_or_else(|e| e.into_inner());
+            status.lag_events = status.primary_sequence.saturating_sub(status.applied_sequence);
+            status.last_message_at = Some(Utc::now());
+            status.applied_sequence
+        };
+        if acknowledge {
+            connection.send(ReplicationMessage::Ack { applied_sequence }).await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,25 @@
+//! Hot-standby replication of the matching engine
+//!
+//! A primary streams its write-ahead log to followers over TCP; each
+//! follower applies the events to its own `OrderBookEngine` (and journals
+//! them to its own WAL under the primary's sequence numbers), so it holds the
+//! same books and can take over without losing them.
+//!
+//! - `protocol` - Replication messages on the `protocol::binary` framing
+//! - `primary` - Server streaming the log to connected followers
+//! - `follower` - Client applying the stream to a standby engine
+//!
+//! A follower connects and says which sequence number it has applied. The
+//! primary sends the missing events from its WAL segments, or an engine
+//! snapshot if a checkpoint has already deleted them, then every event as it
+//! is journaled. Followers acknowledge what they have applied; both sides
+//! keep lag metrics. Promoting a follower stops the stream and lets its
+//! engine take orders.
+
+pub mod follower;
+pub mod primary;
+pub mod protocol;
+
+pub use follower::{FollowerStatus, ReplicationFollower};
+pub use primary::{FollowerLag, ReplicationServer};
+pub use protocol::{ReplicationCodec, ReplicationMessage};
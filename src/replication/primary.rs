@@ -0,0 +1,273 @@
+//! Primary side: stream the write-ahead log to followers
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_util::codec::Framed;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::persistence::{list_segments, SegmentReader, WalEvent};
+
+use super::protocol::{ReplicationCodec, ReplicationMessage, SNAPSHOT_CHUNK_LEN};
+
+/// Time between heartbeats to a follower
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Replication progress of one connected follower
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FollowerLag {
+    /// Follower's address
+    pub address: String,
+    pub connected_at: DateTime<Utc>,
+    /// Last sequence number the follower reported applied
+    pub acked_sequence: u64,
+    /// Events journaled on the primary that the follower has not acknowledged
+    pub lag_events: u64,
+    pub last_ack_at: Option<DateTime<Utc>>,
+}
+
+/// Streams the engine's write-ahead log to followers
+pub struct ReplicationServer {
+    engine: Arc<OrderBookEngine>,
+    followers: RwLock<HashMap<SocketAddr, FollowerLag>>,
+}
+
+type Connection = Framed<TcpStream, ReplicationCodec>;
+
+fn engine_error(e: OrderBookError) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+impl ReplicationServer {
+    /// Create a server for an engine that journals to a write-ahead log
+    pub fn new(engine: Arc<OrderBookEngine>) -> Self {
+        Self {
+            engine,
+            followers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Accept followers until the listener fails
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) {
+        info!("Replication server listening on {:?}", listener.local_addr());
+        loop {
+            let (stream, address) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    error!("Replication server stopped accepting followers: {}", e);
+                    return;
+                }
+            };
+
+            let server = self.clone();
+            tokio::spawn(async move {
+                info!("Follower {} connected", address);
+                if let Err(e) = server.clone().stream_to(stream, address).await {
+                    warn!("Replication to follower {} ended: {}", address, e);
+                }
+                server.followers.write().unwrap_or_else(|e| e.into_inner()).remove(&address);
+                info!("Follower {} disconnected", address);
+            });
+        }
+    }
+
+    /// Replication progress of the connected followers
+    pub fn followers(&self) -> Result<Vec<FollowerLag>, OrderBookError> {
+        let last_sequence = self.engine.last_sequence()?;
+        let followers = self
+            .followers
+            .read()
+            .map_err(|e| OrderBookError::LockError(format!("Failed to acquire read lock: {}", e)))?;
+        let mut lags: Vec<FollowerLag> = followers
+            .values()
+            .map(|follower| FollowerLag {
+                lag_events: last_sequence.saturating_sub(follower.acked_sequence),
+                ..follower.clone()
+            })
+            .collect();
+        lags.sort_by(|a, b| a.address.cmp(&b.address));
+        Ok(lags)
+    }
+
+    fn record_ack(&self, address: SocketAddr, applied_sequence: u64) {
+        let mut followers = self.followers.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(follower) = followers.get_mut(&address) {
+            follower.acked_sequence = applied_sequence;
+            follower.last_ack_at = Some(Utc::now());
+        }
+    }
+
+    /// Serve one follower: catch it up, then forward every journaled event
+    async fn stream_to(self: Arc<Self>, stream: TcpStream, address: SocketAddr) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut connection = Framed::new(stream, ReplicationCodec);
+
+        let follower_sequence = match connection.next().await {
+            Some(Ok(ReplicationMessage::Hello { last_sequence })) => last_sequence,
+            Some(Ok(other)) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("expected hello, received {:?}", other)))
+            }
+            Some(Err(e)) => return Err(e),
+            None => return Ok(()),
+        };
+
+        // Subscribe first: whatever is journaled from here on arrives on `events`
+        let (subscribed_at, mut events) = self.engine.subscribe_journal().map_err(engine_error)?;
+        if follower_sequence > subscribed_at {
+            let reason = format!(
+                "follower is at sequence {}, ahead of the primary at {}",
+                follower_sequence, subscribed_at
+            );
+            connection.send(ReplicationMessage::Reject { reason: reason.clone() }).await?;
+            return Err(io::Error::other(reason));
+        }
+
+        self.followers.write().unwrap_or_else(|e| e.into_inner()).insert(
+            address,
+            FollowerLag {
+                address: address.to_string(),
+                connected_at: Utc::now(),
+                acked_sequence: follower_sequence,
+                lag_events: 0,
+                last_ack_at: None,
+            },
+        );
+
+        let mut sent = follower_sequence;
+        if follower_sequence < subscribed_at {
+            sent = self.catch_up(&mut connection, follower_sequence, subscribed_at).await?;
+            info!("Follower {} caught up from sequence {} to {}", address, follower_sequence, sent);
+        }
+
+        let mut heartbeat = interval(HEARTBEAT_INTERVAL);
+        heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let Some(event) = event else {
+                        return Ok(());
+                    };
+                    // Send everything already queued before flushing
+                    let mut next = Some(event);
+                    while let Some(event) = next {
+                        if event.sequence() > sent {
+                            sent = event.sequence();
+                            connection.feed(ReplicationMessage::Event(Box::new(event))).await?;
+                        }
+                        next = events.try_recv().ok();
+                    }
+                    connection.flush().await?;
+                }
+                message = connection.next() => match message {
+                    Some(Ok(ReplicationMessage::Ack { applied_sequence })) => self.record_ack(address, applied_sequence),
+                    Some(Ok(other)) => warn!("Unexpected message from follower {}: {:?}", address, other),
+                    Some(Err(e)) => return Err(e),
+                    None => return Ok(()),
+                },
+                _ = heartbeat.tick() => {
+                    let primary_sequence = self.engine.last_sequence().map_err(engine_error)?;
+                    connection.send(ReplicationMessage::Heartbeat { primary_sequence }).await?;
+                }
+            }
+        }
+    }
+
+    /// Send the events after `from` up to `to`; returns the last sequence sent
+    ///
+    /// The events come from the WAL segments on disk. If a checkpoint has
+    /// deleted the segment holding `from + 1`, the follower gets a snapshot
+    /// of the engine instead, which may already include later events.
+    async fn catch_up(&self, connection: &mut Connection, from: u64, to: u64) -> io::Result<u64> {
+        let wal_dir = self
+            .engine
+            .wal_dir()
+            .map_err(engine_error)?
+            .ok_or_else(|| io::Error::other("replication needs a write-ahead log"))?;
+
+        let (tx, mut rx) = mpsc::channel(1024);
+        let reader = tokio::task::spawn_blocking(move || read_log(&wal_dir, from, to, tx));
+        let mut sent = from;
+        while let Some(event) = rx.recv().await {
+            sent = event.sequence();
+            connection.feed(ReplicationMessage::Event(Box::new(event))).await?;
+        }
+        connection.flush().await?;
+
+        let complete = reader.await.map_err(io::Error::other)??;
+        if complete {
+            return Ok(sent);
+        }
+
+        let engine = self.engine.clone();
+        let snapshot = tokio::task::spawn_blocking(move || engine.capture_snapshot())
+            .await
+            .map_err(io::Error::other)?
+            .map_err(engine_error)?;
+        let encoded = bincode::serialize(&snapshot).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        info!(
+            "Log from sequence {} is gone; sending snapshot at sequence {} ({} bytes)",
+            sent + 1,
+            snapshot.sequence,
+            encoded.len()
+        );
+
+        let mut chunks = encoded.chunks(SNAPSHOT_CHUNK_LEN).peekable();
+        while let Some(chunk) = chunks.next() {
+            connection
+                .feed(ReplicationMessage::SnapshotChunk {
+                    last: chunks.peek().is_none(),
+                    data: chunk.to_vec(),
+                })
+                .await?;
+        }
+        connection.flush().await?;
+        Ok(snapshot.sequence)
+    }
+}
+
+/// Read the events after `from` up to `to` from the WAL segments
+///
+/// Returns false (possibly after sending some events) if the log on disk
+/// does not hold all of them.
+fn read_log(wal_dir: &Path, from: u64, to: u64, tx: mpsc::Sender<WalEvent>) -> io::Result<bool> {
+    let mut next = from + 1;
+    for (_, path) in list_segments(wal_dir)? {
+        let reader = match SegmentReader::open(&path) {
+            Ok(reader) => reader,
+            // Deleted by a checkpoint since it was listed
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        for record in reader {
+            let event = record?.event;
+            let sequence = event.sequence();
+            if sequence < next {
+                continue;
+            }
+            if sequence > next {
+                return Ok(false);
+            }
+            if tx.blocking_send(event).is_err() {
+                // The connection went away
+                return Ok(true);
+            }
+            if sequence == to {
+                return Ok(true);
+            }
+            next += 1;
+        }
+    }
+    Ok(false)
+}
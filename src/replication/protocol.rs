@@ -0,0 +1,191 @@
+//! Replication messages
+//!
+//! Every message is one `protocol::binary` frame (2-byte big-endian length
+//! prefix) whose payload starts with the `protocol::messages` header (its
+//! `MessageType` and schema version). Sequence numbers are
+//! big-endian `u64`; WAL events and snapshots are bincode-encoded like on disk.
+
+use bytes::{Buf, BufMut, BytesMut};
+use std::io::{self, Error, ErrorKind};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::persistence::WalEvent;
+use crate::protocol::messages::{get_header, put_header};
+use crate::protocol::{FramedCodec, MessageType};
+
+/// Largest piece of a snapshot sent in one frame
+pub const SNAPSHOT_CHUNK_LEN: usize = 60 * 1024;
+
+/// Messages between a primary and its followers
+#[derive(Debug, Clone)]
+pub enum ReplicationMessage {
+    /// Follower → primary, first message: the last sequence number it applied
+    Hello { last_sequence: u64 },
+    /// Follower → primary: everything up to this sequence number is applied
+    Ack { applied_sequence: u64 },
+    /// Primary → follower: the next WAL event
+    Event(Box<WalEvent>),
+    /// Primary → follower: part of a bincode-encoded `EngineSnapshot`;
+    /// the follower installs it after the last part
+    SnapshotChunk { last: bool, data: Vec<u8> },
+    /// Primary → follower: the primary's latest sequence number, sent while idle
+    Heartbeat { primary_sequence: u64 },
+    /// Primary → follower: the follower cannot be served (e.g. it is ahead of the primary)
+    Reject { reason: String },
+}
+
+/// Frames `ReplicationMessage`s on a TCP stream
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReplicationCodec;
+
+impl Encoder<ReplicationMessage> for ReplicationCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: ReplicationMessage, dst: &mut BytesMut) -> io::Result<()> {
+        let mut payload = BytesMut::new();
+        match message {
+            ReplicationMessage::Hello { last_sequence } => {
+                put_header(&mut payload, MessageType::ReplicationHello);
+                payload.put_u64(last_sequence);
+            }
+            ReplicationMessage::Ack { applied_sequence } => {
+                put_header(&mut payload, MessageType::ReplicationAck);
+                payload.put_u64(applied_sequence);
+            }
+            ReplicationMessage::Event(event) => {
+                put_header(&mut payload, MessageType::ReplicationEvent);
+                let encoded = bincode::serialize(&event).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                payload.put_slice(&encoded);
+            }
+            ReplicationMessage::SnapshotChunk { last, data } => {
+                put_header(&mut payload, MessageType::ReplicationSnapshot);
+                payload.put_u8(last as u8);
+                payload.put_slice(&data);
+            }
+            ReplicationMessage::Heartbeat { primary_sequence } => {
+                put_header(&mut payload, MessageType::ReplicationHeartbeat);
+                payload.put_u64(primary_sequence);
+            }
+            ReplicationMessage::Reject { reason } => {
+                put_header(&mut payload, MessageType::ReplicationReject);
+                payload.put_slice(reason.as_bytes());
+            }
+        }
+        FramedCodec::encode_frame(&payload, dst)
+    }
+}
+
+impl Decoder for ReplicationCodec {
+    type Item = ReplicationMessage;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<ReplicationMessage>> {
+        let Some(mut payload) = FramedCodec::decode_frame(src) else {
+            return Ok(None);
+        };
+        let message = match get_header(&mut payload)? {
+            MessageType::ReplicationHello => ReplicationMessage::Hello {
+                last_sequence: read_u64(&mut payload)?,
+            },
+            MessageType::ReplicationAck => ReplicationMessage::Ack {
+                applied_sequence: read_u64(&mut payload)?,
+            },
+            MessageType::ReplicationEvent => ReplicationMessage::Event(
+                bincode::deserialize(&payload).map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+            ),
+            MessageType::ReplicationSnapshot => {
+                if payload.is_empty() {
+                    return Err(Error::new(ErrorKind::UnexpectedEof, "Incomplete message"));
+                }
+                ReplicationMessage::SnapshotChunk {
+                    last: payload.get_u8() != 0,
+                    data: payload.to_vec(),
+                }
+            }
+            MessageType::ReplicationHeartbeat => ReplicationMessage::Heartbeat {
+                primary_sequence: read_u64(&mut payload)?,
+            },
+            MessageType::ReplicationReject => ReplicationMessage::Reject {
+                reason: String::from_utf8_lossy(&payload).into_owned(),
+            },
+            other => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{:?} is not a replication message", other),
+                ))
+            }
+        };
+        Ok(Some(message))
+    }
+}
+
+fn read_u64(payload: &mut BytesMut) -> io::Result<u64> {
+    if payload.remaining() < 8 {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Incomplete message"));
+    }
+    Ok(payload.get_u64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(message: ReplicationMessage) -> ReplicationMessage {
+        let mut buf = BytesMut::new();
+        ReplicationCodec.encode(message, &mut buf).unwrap();
+        let decoded = ReplicationCodec.decode(&mut buf).unwrap().unwrap();
+        assert!(buf.is_empty());
+        decoded
+    }
+
+    #[test]
+    fn test_messages_round_trip() {
+        assert!(matches!(
+            round_trip(ReplicationMessage::Hello { last_sequence: 42 }),
+            ReplicationMessage::Hello { last_sequence: 42 }
+        ));
+        assert!(matches!(
+            round_trip(ReplicationMessage::Heartbeat { primary_sequence: u64::MAX }),
+            ReplicationMessage::Heartbeat { primary_sequence: u64::MAX }
+        ));
+
+        let event = WalEvent::AuctionEnded {
+            sequence: 7,
+            timestamp_ns: 1,
+            symbol: "AAPL".to_string(),
+        };
+        match round_trip(ReplicationMessage::Event(Box::new(event))) {
+            ReplicationMessage::Event(event) => match *event {
+                WalEvent::AuctionEnded { sequence, symbol, .. } => assert_eq!((sequence, symbol.as_str()), (7, "AAPL")),
+                other => panic!("unexpected event {:?}", other),
+            },
+            other => panic!("unexpected message {:?}", other),
+        }
+
+        match round_trip(ReplicationMessage::SnapshotChunk { last: true, data: vec![1, 2, 3] }) {
+            ReplicationMessage::SnapshotChunk { last, data } => assert!(last && data == [1, 2, 3]),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_waits_for_whole_frame() {
+        let mut buf = BytesMut::new();
+        ReplicationCodec.encode(ReplicationMessage::Ack { applied_sequence: 9 }, &mut buf).unwrap();
+        let mut partial = buf.split_to(5);
+        assert!(ReplicationCodec.decode(&mut partial).unwrap().is_none());
+
+        partial.unsplit(buf);
+        assert!(matches!(
+            ReplicationCodec.decode(&mut partial).unwrap(),
+            Some(ReplicationMessage::Ack { applied_sequence: 9 })
+        ));
+    }
+
+    #[test]
+    fn test_order_messages_are_not_replication_messages() {
+        let mut buf = BytesMut::new();
+        FramedCodec::encode_frame(&[MessageType::NewOrder as u8], &mut buf).unwrap();
+        assert!(ReplicationCodec.decode(&mut buf).is_err());
+    }
+}
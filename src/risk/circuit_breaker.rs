@@ -5,7 +5,8 @@ use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::models::Order;
+use crate::models::{Order, OrderSide};
+use crate::utils::clock;
 
 /// Circuit breaker configuration
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -22,6 +23,11 @@ pub struct CircuitBreakerConfig {
     /// How long to halt trading (minutes)
     pub halt_duration_minutes: i64,
 
+    /// How long the reopening auction after a halt collects orders before it
+    /// uncrosses (minutes)
+    #[serde(default = "default_reopening_auction_minutes")]
+    pub reopening_auction_minutes: i64,
+
     /// Maximum order size (quantity)
     pub max_order_size: Decimal,
 
@@ -30,6 +36,16 @@ pub struct CircuitBreakerConfig {
 
     /// Maximum orders per second per user
     pub max_orders_per_second: u32,
+
+    /// Half-width of the limit-up/limit-down band around the average trade
+    /// price of the price window, in percent (`None` turns the band off)
+    #[serde(default)]
+    #[schema(value_type = Option<String>, example = "5")]
+    pub price_band_pct: Option<Decimal>,
+
+    /// What happens to a limit order priced through the band
+    #[serde(default)]
+    pub price_band_action: PriceBandAction,
 }
 
 impl Default for CircuitBreakerConfig {
@@ -39,13 +55,62 @@ impl Default for CircuitBreakerConfig {
             price_window_minutes: 5,
             min_trades_for_activation: 10,
             halt_duration_minutes: 5,
+            reopening_auction_minutes: default_reopening_auction_minutes(),
             max_order_size: dec!(1_000_000),
             max_order_value: dec!(10_000_000),
             max_orders_per_second: 100,
+            price_band_pct: None,
+            price_band_action: PriceBandAction::Reject,
         }
     }
 }
 
+fn default_reopening_auction_minutes() -> i64 {
+    1
+}
+
+impl CircuitBreakerConfig {
+    /// Check the configuration is usable
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_price_change_pct <= Decimal::ZERO {
+            return Err("max_price_change_pct must be positive".to_string());
+        }
+        if self.price_window_minutes <= 0 || self.halt_duration_minutes < 0 {
+            return Err("price_window_minutes must be positive and halt_duration_minutes not negative".to_string());
+        }
+        if self.reopening_auction_minutes < 0 {
+            return Err("reopening_auction_minutes must not be negative".to_string());
+        }
+        if self.price_band_pct.is_some_and(|pct| pct <= Decimal::ZERO) {
+            return Err("price_band_pct must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Treatment of a limit order that would trade outside the price band
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PriceBandAction {
+    /// Reject the order
+    #[default]
+    Reject,
+    /// Move the order's price to the band's edge
+    Reprice,
+}
+
+/// Prices a symbol may currently trade between
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PriceBand {
+    /// Average trade price of the price window the band is centred on
+    #[schema(value_type = String, example = "100.00")]
+    pub reference_price: Decimal,
+    #[schema(value_type = String, example = "95.00")]
+    pub lower: Decimal,
+    #[schema(value_type = String, example = "105.00")]
+    pub upper: Decimal,
+}
+
 /// Circuit breaker state
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -54,7 +119,8 @@ pub enum CircuitState {
     Normal,
     /// Trading halted
     Halted,
-    /// Cooling off (limited trading)
+    /// Halt is over and the symbol reopens through a call auction: orders
+    /// are accepted but nothing trades until the auction uncrosses
     CoolingOff,
 }
 
@@ -99,17 +165,18 @@ impl CircuitBreaker {
 
     /// Check if trading is allowed
     pub fn is_trading_allowed(&mut self) -> bool {
-        // Check if halt has expired
+        // Check if halt has expired: trading resumes through a reopening auction
         if let Some(until) = self.halt_until {
-            if Utc::now() >= until {
-                self.resume();
+            if clock::now() >= until {
+                self.state = CircuitState::CoolingOff;
+                self.halt_until = None;
             }
         }
 
         match self.state {
             CircuitState::Normal => true,
             CircuitState::Halted => false,
-            CircuitState::CoolingOff => true, // Limited trading allowed
+            CircuitState::CoolingOff => true, // Orders accepted into the reopening auction
         }
     }
 
@@ -145,6 +212,66 @@ impl CircuitBreaker {
         Ok(())
     }
 
+    /// Current limit-up/limit-down band (`None` when off or before the first trade)
+    ///
+    /// The band is centred on the average price of the trades in the price
+    /// window, so it follows the market as it moves. With a tick size, its
+    /// edges are rounded inwards onto the tick grid.
+    pub fn price_band(&self, tick_size: Option<Decimal>) -> Option<PriceBand> {
+        let pct = self.config.price_band_pct?;
+        if self.price_history.is_empty() {
+            return None;
+        }
+
+        let total: Decimal = self.price_history.iter().map(|(_, price)| *price).sum();
+        let reference_price = total / Decimal::from(self.price_history.len());
+        let width = reference_price * pct / dec!(100);
+        let (mut lower, mut upper) = (reference_price - width, reference_price + width);
+        if let Some(tick) = tick_size.filter(|tick| *tick > Decimal::ZERO) {
+            lower = (lower / tick).ceil() * tick;
+            upper = (upper / tick).floor() * tick;
+        }
+
+        Some(PriceBand { reference_price, lower, upper })
+    }
+
+    /// Check a limit order against the price band, repricing it to the band's
+    /// edge if the band is set to
+    ///
+    /// Only a price the order could trade through matters: a buy above the
+    /// upper edge or a sell below the lower edge. An order without a price (a
+    /// market order) is capped at the edge on its side whatever the band's
+    /// action: it trades up to the edge and the rest is dropped as usual.
+    pub fn apply_price_band(&self, order: &mut Order, tick_size: Option<Decimal>) -> Result<(), RiskError> {
+        let Some(band) = self.price_band(tick_size) else {
+            return Ok(());
+        };
+        let Some(price) = order.price else {
+            order.price = Some(match order.side {
+                OrderSide::Buy => band.upper,
+                OrderSide::Sell => band.lower,
+            });
+            return Ok(());
+        };
+
+        let edge = match order.side {
+            OrderSide::Buy if price > band.upper => band.upper,
+            OrderSide::Sell if price < band.lower => band.lower,
+            _ => return Ok(()),
+        };
+        match self.config.price_band_action {
+            PriceBandAction::Reprice => {
+                order.price = Some(edge);
+                Ok(())
+            }
+            PriceBandAction::Reject => Err(RiskError::OutsidePriceBand {
+                price,
+                lower: band.lower,
+                upper: band.upper,
+            }),
+        }
+    }
+
     /// Process a trade and check for circuit breaker triggers
     pub fn on_trade(&mut self, trade_price: Decimal, timestamp: DateTime<Utc>) -> Option<HaltReason> {
         // Update price history
@@ -192,7 +319,7 @@ impl CircuitBreaker {
     }
 
     fn trigger_halt(&mut self, reason: HaltReason) {
-        let until = Utc::now() + Duration::minutes(self.config.halt_duration_minutes);
+        let until = clock::now() + Duration::minutes(self.config.halt_duration_minutes);
         self.state = CircuitState::Halted;
         self.halt_reason = Some(reason);
         self.halt_until = Some(until);
@@ -208,7 +335,7 @@ impl CircuitBreaker {
 
     /// Manually halt trading
     pub fn manual_halt(&mut self, duration_minutes: i64) {
-        let until = Utc::now() + Duration::minutes(duration_minutes);
+        let until = clock::now() + Duration::minutes(duration_minutes);
         self.state = CircuitState::Halted;
         self.halt_reason = Some(HaltReason::Manual);
         self.halt_until = Some(until);
@@ -221,6 +348,31 @@ impl CircuitBreaker {
         self.halt_until = None;
     }
 
+    /// Check if the symbol must reopen through a call auction
+    pub fn needs_reopening_auction(&self) -> bool {
+        self.state == CircuitState::CoolingOff
+    }
+
+    /// Return to normal trading once the reopening auction has uncrossed
+    ///
+    /// The uncross price (if any) becomes the new reference price.
+    pub fn complete_reopening(&mut self, uncross_price: Option<Decimal>) {
+        self.resume();
+        if uncross_price.is_some() {
+            self.reference_price = uncross_price;
+        }
+    }
+
+    /// Get configuration
+    pub fn get_config(&self) -> &CircuitBreakerConfig {
+        &self.config
+    }
+
+    /// Replace the configuration, keeping the current state and price history
+    pub fn set_config(&mut self, config: CircuitBreakerConfig) {
+        self.config = config;
+    }
+
     /// Get current state
     pub fn get_state(&self) -> CircuitState {
         self.state
@@ -239,6 +391,7 @@ impl CircuitBreaker {
     /// Get circuit breaker status
     pub fn get_status(&self) -> CircuitBreakerStatus {
         CircuitBreakerStatus {
+            price_band: self.price_band(None),
             state: self.state,
             halt_reason: self.halt_reason,
             halt_until: self.halt_until,
@@ -263,6 +416,8 @@ pub struct CircuitBreakerStatus {
     pub reference_price: Option<Decimal>,
     pub trade_count: u32,
     pub price_history_size: usize,
+    #[serde(default)]
+    pub price_band: Option<PriceBand>,
 }
 
 #[derive(Debug, thiserror::Error, Serialize, Deserialize, ToSchema)]
@@ -290,6 +445,26 @@ pub enum RiskError {
     RateLimitExceeded {
         orders_per_second: u32,
     },
+
+    #[error("Price {price} is outside the price band [{lower}, {upper}]")]
+    OutsidePriceBand {
+        price: Decimal,
+        lower: Decimal,
+        upper: Decimal,
+    },
+}
+
+impl RiskError {
+    /// The reason code reported to clients
+    pub fn code(&self) -> &'static str {
+        match self {
+            RiskError::TradingHalted { .. } => "TRADING_HALTED",
+            RiskError::OrderTooLarge { .. } => "ORDER_TOO_LARGE",
+            RiskError::OrderValueTooHigh { .. } => "ORDER_VALUE_TOO_HIGH",
+            RiskError::RateLimitExceeded { .. } => "RATE_LIMIT_EXCEEDED",
+            RiskError::OutsidePriceBand { .. } => "OUTSIDE_PRICE_BAND",
+        }
+    }
 }
 
 #[cfg(test)]
@@ -317,6 +492,7 @@ mod tests {
             post_only: false,
             expire_time: None,
             iceberg: None,
+            peg_offset: None,
         }
     }
 
@@ -406,4 +582,75 @@ mod tests {
         cb.resume();
         assert_eq!(cb.get_state(), CircuitState::Normal);
     }
+
+    #[test]
+    fn test_price_band_rejects_or_reprices() {
+        let config = CircuitBreakerConfig {
+            price_band_pct: Some(dec!(5)),
+            ..Default::default()
+        };
+        let mut cb = CircuitBreaker::new(config.clone());
+
+        // No band before the first trade
+        let mut order = create_test_order(dec!(500), dec!(1));
+        assert!(cb.apply_price_band(&mut order, None).is_ok());
+
+        let now = clock::now();
+        cb.on_trade(dec!(99), now);
+        cb.on_trade(dec!(101), now);
+        let band = cb.price_band(Some(dec!(0.4))).unwrap();
+        assert_eq!((band.reference_price, band.lower, band.upper), (dec!(100), dec!(95.2), dec!(104.8)));
+
+        let mut order = create_test_order(dec!(106), dec!(1));
+        assert!(matches!(cb.apply_price_band(&mut order, None), Err(RiskError::OutsidePriceBand { .. })));
+        // A buy below the band cannot trade outside it
+        let mut order = create_test_order(dec!(90), dec!(1));
+        assert!(cb.apply_price_band(&mut order, None).is_ok());
+
+        cb.set_config(CircuitBreakerConfig {
+            price_band_action: PriceBandAction::Reprice,
+            ..config
+        });
+        let mut order = create_test_order(dec!(106), dec!(1));
+        cb.apply_price_band(&mut order, None).unwrap();
+        assert_eq!(order.price, Some(dec!(105)));
+        let mut order = create_test_order(dec!(90), dec!(1));
+        order.side = OrderSide::Sell;
+        cb.apply_price_band(&mut order, None).unwrap();
+        assert_eq!(order.price, Some(dec!(95)));
+    }
+
+    #[test]
+    fn test_price_band_caps_market_orders() {
+        let mut cb = CircuitBreaker::new(CircuitBreakerConfig {
+            price_band_pct: Some(dec!(5)),
+            ..Default::default()
+        });
+        cb.on_trade(dec!(100), clock::now());
+
+        // Capped even though the band rejects limit orders
+        let mut order = create_test_order(dec!(1), dec!(1));
+        order.order_type = OrderType::Market;
+        order.price = None;
+        cb.apply_price_band(&mut order, None).unwrap();
+        assert_eq!(order.price, Some(dec!(105)));
+        order.side = OrderSide::Sell;
+        order.price = None;
+        cb.apply_price_band(&mut order, None).unwrap();
+        assert_eq!(order.price, Some(dec!(95)));
+    }
+
+    #[test]
+    fn test_halt_expiry_enters_reopening_auction() {
+        let mut cb = CircuitBreaker::default();
+
+        cb.manual_halt(0);
+        assert!(cb.is_trading_allowed());
+        assert_eq!(cb.get_state(), CircuitState::CoolingOff);
+        assert!(cb.needs_reopening_auction());
+
+        cb.complete_reopening(Some(dec!(101)));
+        assert_eq!(cb.get_state(), CircuitState::Normal);
+        assert_eq!(cb.get_status().reference_price, Some(dec!(101)));
+    }
 }
@@ -0,0 +1,662 @@
+//! Per-account pre-trade risk limits
+//!
+//! The risk engine sees every order an account has working and every fill it
+//! gets, and checks each new order, amend and cancel against the account's
+//! limits before it reaches the book. Accounts without their own limits use
+//! the default limits; a limit left unset is not checked.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::{Order, OrderSide, Trade};
+use crate::positions::PositionState;
+
+/// Limits of one account (unset limits are not checked)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct RiskLimits {
+    /// Orders the account may have working across all symbols
+    #[schema(example = 100)]
+    pub max_open_orders: Option<usize>,
+    /// Largest long or short quantity (position plus working orders on that
+    /// side) the account may build in a symbol, valued at the order's price
+    #[schema(value_type = Option<String>, example = "1000000")]
+    pub max_notional_exposure: Option<Decimal>,
+    /// How far a limit price may be from the symbol's last trade, in percent
+    #[schema(value_type = Option<String>, example = "10")]
+    pub max_price_deviation_pct: Option<Decimal>,
+    /// Orders, amends and cancels per second
+    #[schema(example = 50)]
+    pub max_messages_per_second: Option<usize>,
+    /// Realized loss since midnight UTC at which new orders are rejected
+    #[schema(value_type = Option<String>, example = "25000")]
+    pub max_daily_loss: Option<Decimal>,
+}
+
+impl RiskLimits {
+    /// Check the limits are usable (zero counts are allowed and block the account)
+    pub fn validate(&self) -> Result<(), String> {
+        let amounts = [
+            ("max_notional_exposure", self.max_notional_exposure),
+            ("max_price_deviation_pct", self.max_price_deviation_pct),
+            ("max_daily_loss", self.max_daily_loss),
+        ];
+        for (name, amount) in amounts {
+            if amount.is_some_and(|amount| amount <= Decimal::ZERO) {
+                return Err(format!("{} must be positive", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Why the risk engine rejected a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RiskRejectReason {
+    MaxOpenOrders,
+    MaxNotionalExposure,
+    PriceDeviation,
+    MessageRate,
+    DailyLoss,
+}
+
+impl RiskRejectReason {
+    /// The reason code reported to clients
+    pub fn code(&self) -> &'static str {
+        match self {
+            RiskRejectReason::MaxOpenOrders => "MAX_OPEN_ORDERS",
+            RiskRejectReason::MaxNotionalExposure => "MAX_NOTIONAL_EXPOSURE",
+            RiskRejectReason::PriceDeviation => "PRICE_DEVIATION",
+            RiskRejectReason::MessageRate => "MESSAGE_RATE",
+            RiskRejectReason::DailyLoss => "DAILY_LOSS",
+        }
+    }
+}
+
+impl fmt::Display for RiskRejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// A request the risk engine turned down
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{reason}: {message}")]
+pub struct RiskRejection {
+    pub reason: RiskRejectReason,
+    pub message: String,
+}
+
+impl RiskRejection {
+    fn new(reason: RiskRejectReason, message: String) -> Self {
+        Self { reason, message }
+    }
+}
+
+/// Current use of an account's limits
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct RiskUsage {
+    pub open_orders: usize,
+    /// Realized PnL since midnight UTC, before fees
+    #[schema(value_type = String, example = "-1250.00")]
+    pub daily_realized_pnl: Decimal,
+    /// Messages in the last second
+    pub messages_last_second: usize,
+}
+
+/// The limits that apply to an account and its use of them
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct AccountRiskStatus {
+    pub user_id: String,
+    pub limits: RiskLimits,
+    /// Whether the account has its own limits rather than the defaults
+    pub own_limits: bool,
+    pub usage: RiskUsage,
+}
+
+/// Realized PnL of an account on its latest trading day, as kept in snapshots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyPnl {
+    pub user_id: String,
+    pub day: NaiveDate,
+    #[serde(with = "crate::models::decimal")]
+    pub pnl: Decimal,
+}
+
+#[derive(Debug, Clone)]
+struct WorkingOrder {
+    symbol: String,
+    side: OrderSide,
+    remaining: Decimal,
+}
+
+#[derive(Debug, Default)]
+struct AccountRisk {
+    orders: HashMap<Uuid, WorkingOrder>,
+    positions: HashMap<String, PositionState>,
+    /// Times of the messages within the rate window
+    messages: VecDeque<DateTime<Utc>>,
+    /// Realized PnL of the latest trading day the account traded on
+    daily_pnl: Option<(NaiveDate, Decimal)>,
+}
+
+impl AccountRisk {
+    fn daily_pnl(&self, today: NaiveDate) -> Decimal {
+        match self.daily_pnl {
+            Some((day, pnl)) if day == today => pnl,
+            _ => Decimal::ZERO,
+        }
+    }
+
+    fn book_pnl(&mut self, day: NaiveDate, pnl: Decimal) {
+        self.daily_pnl = match self.daily_pnl {
+            Some((current, total)) if current == day => Some((day, total + pnl)),
+            // A fill from an earlier day (replayed late) no longer counts
+            Some((current, total)) if current > day => Some((current, total)),
+            _ => Some((day, pnl)),
+        };
+    }
+
+    fn expire_messages(&mut self, now: DateTime<Utc>) {
+        let window_start = now - Duration::seconds(1);
+        while self.messages.front().is_some_and(|&time| time <= window_start) {
+            self.messages.pop_front();
+        }
+    }
+}
+
+/// Pre-trade risk checks against per-account limits
+///
+/// The engine reports every order that starts working, changes size or
+/// leaves the book, and every trade; the risk engine keeps each account's
+/// working orders, positions and daily realized PnL from that. Daily PnL is
+/// part of engine snapshots; working orders and positions are rebuilt from
+/// the restored books, like fee volumes.
+#[derive(Debug, Default)]
+pub struct RiskEngine {
+    default_limits: RiskLimits,
+    account_limits: BTreeMap<String, RiskLimits>,
+    accounts: HashMap<String, AccountRisk>,
+    /// Owner of every working order
+    owners: HashMap<Uuid, String>,
+    last_prices: HashMap<String, Decimal>,
+}
+
+impl RiskEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits of accounts without their own
+    pub fn default_limits(&self) -> &RiskLimits {
+        &self.default_limits
+    }
+
+    pub fn set_default_limits(&mut self, limits: RiskLimits) -> Result<(), String> {
+        limits.validate()?;
+        self.default_limits = limits;
+        Ok(())
+    }
+
+    /// Accounts with their own limits
+    pub fn account_limits(&self) -> &BTreeMap<String, RiskLimits> {
+        &self.account_limits
+    }
+
+    /// Set (or with `None`, remove) an account's own limits, which replace
+    /// the default limits as a whole
+    pub fn set_account_limits(&mut self, user_id: &str, limits: Option<RiskLimits>) -> Result<(), String> {
+        match limits {
+            Some(limits) => {
+                limits.validate()?;
+                self.account_limits.insert(user_id.to_string(), limits);
+            }
+            None => {
+                self.account_limits.remove(user_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Limits that apply to an account
+    pub fn limits_for(&self, user_id: &str) -> &RiskLimits {
+        self.account_limits.get(user_id).unwrap_or(&self.default_limits)
+    }
+
+    /// Current use of an account's limits
+    pub fn usage(&self, user_id: &str, now: DateTime<Utc>) -> RiskUsage {
+        let Some(account) = self.accounts.get(user_id) else {
+            return RiskUsage {
+                open_orders: 0,
+                daily_realized_pnl: Decimal::ZERO,
+                messages_last_second: 0,
+            };
+        };
+        let window_start = now - Duration::seconds(1);
+        RiskUsage {
+            open_orders: account.orders.len(),
+            daily_realized_pnl: account.daily_pnl(now.date_naive()),
+            messages_last_second: account.messages.iter().filter(|&&time| time > window_start).count(),
+        }
+    }
+
+    /// Limits and usage of an account
+    pub fn status(&self, user_id: &str, now: DateTime<Utc>) -> AccountRiskStatus {
+        AccountRiskStatus {
+            user_id: user_id.to_string(),
+            limits: self.limits_for(user_id).clone(),
+            own_limits: self.account_limits.contains_key(user_id),
+            usage: self.usage(user_id, now),
+        }
+    }
+
+    /// Owner of a working order
+    pub fn owner(&self, order_id: Uuid) -> Option<&str> {
+        self.owners.get(&order_id).map(String::as_str)
+    }
+
+    /// Count a message of an account against its rate limit
+    ///
+    /// Rejected messages are not counted, so an account that backs off is
+    /// let through again once its last second holds fewer messages than the limit.
+    pub fn check_message(&mut self, user_id: &str, now: DateTime<Utc>) -> Result<(), RiskRejection> {
+        let limit = self.limits_for(user_id).max_messages_per_second;
+        let account = self.accounts.entry(user_id.to_string()).or_default();
+        let Some(limit) = limit else {
+            return Ok(());
+        };
+
+        account.expire_messages(now);
+        if account.messages.len() >= limit {
+            return Err(RiskRejection::new(
+                RiskRejectReason::MessageRate,
+                format!("{} exceeds {} messages per second", user_id, limit),
+            ));
+        }
+        account.messages.push_back(now);
+        Ok(())
+    }
+
+    /// Check a new order, or the amended form of a working one, against its
+    /// account's limits
+    pub fn check_order(&self, order: &Order, now: DateTime<Utc>) -> Result<(), RiskRejection> {
+        let limits = self.limits_for(&order.user_id);
+        let account = self.accounts.get(&order.user_id);
+        let others = || {
+            account
+                .into_iter()
+                .flat_map(|account| &account.orders)
+                .filter(|(id, _)| **id != order.id)
+                .map(|(_, working)| working)
+        };
+
+        if let Some(max) = limits.max_open_orders {
+            if others().count() >= max {
+                return Err(RiskRejection::new(
+                    RiskRejectReason::MaxOpenOrders,
+                    format!("{} already has {} open orders", order.user_id, max),
+                ));
+            }
+        }
+
+        if let Some(max) = limits.max_daily_loss {
+            let loss = -account.map_or(Decimal::ZERO, |account| account.daily_pnl(now.date_naive()));
+            if loss >= max {
+                return Err(RiskRejection::new(
+                    RiskRejectReason::DailyLoss,
+                    format!("{} lost {} today, limit {}", order.user_id, loss, max),
+                ));
+            }
+        }
+
+        let last_price = self.last_prices.get(&order.symbol).copied();
+        if let (Some(max), Some(price), Some(last)) = (limits.max_price_deviation_pct, order.price, last_price) {
+            let deviation = (price - last).abs() / last * Decimal::ONE_HUNDRED;
+            if deviation > max {
+                return Err(RiskRejection::new(
+                    RiskRejectReason::PriceDeviation,
+                    format!("price {} is {}% from the last trade at {}, limit {}%", price, deviation.round_dp(2), last, max),
+                ));
+            }
+        }
+
+        // Market orders are valued at the last trade; without one there is no value to check
+        if let (Some(max), Some(price)) = (limits.max_notional_exposure, order.price.or(last_price)) {
+            let net = account
+                .and_then(|account| account.positions.get(&order.symbol))
+                .map_or(Decimal::ZERO, |position| position.net_quantity);
+            let working: Decimal = others()
+                .filter(|working| working.symbol == order.symbol && working.side == order.side)
+                .map(|working| working.remaining)
+                .sum();
+            let position = match order.side {
+                OrderSide::Buy => net,
+                OrderSide::Sell => -net,
+            };
+            let exposure = (position + working + order.remaining_quantity()) * price;
+            if exposure > max {
+                return Err(RiskRejection::new(
+                    RiskRejectReason::MaxNotionalExposure,
+                    format!("{} {:?} exposure in {} would reach {}, limit {}", order.user_id, order.side, order.symbol, exposure, max),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start tracking a working order, or update one that changed size
+    pub fn track_order(&mut self, order: &Order) {
+        let remaining = order.remaining_quantity();
+        if remaining <= Decimal::ZERO {
+            self.untrack_order(order.id);
+            return;
+        }
+        self.owners.insert(order.id, order.user_id.clone());
+        self.accounts.entry(order.user_id.clone()).or_default().orders.insert(
+            order.id,
+            WorkingOrder {
+                symbol: order.symbol.clone(),
+                side: order.side,
+                remaining,
+            },
+        );
+    }
+
+    /// Stop tracking an order that left the book
+    pub fn untrack_order(&mut self, order_id: Uuid) {
+        if let Some(user_id) = self.owners.remove(&order_id) {
+            if let Some(account) = self.accounts.get_mut(&user_id) {
+                account.orders.remove(&order_id);
+            }
+        }
+    }
+
+    /// Apply a trade to both sides' positions and daily PnL
+    pub fn record_trade(&mut self, trade: &Trade) {
+        self.last_prices.insert(trade.symbol.clone(), trade.price);
+        let day = trade.timestamp.date_naive();
+        for (user_id, side) in [(&trade.buyer_id, OrderSide::Buy), (&trade.seller_id, OrderSide::Sell)] {
+            let account = self.accounts.entry(user_id.clone()).or_default();
+            let position = account.positions.entry(trade.symbol.clone()).or_default();
+            let realized_before = position.realized_pnl;
+            position.apply_fill(side, trade.price, trade.quantity);
+            let realized = position.realized_pnl - realized_before;
+            account.book_pnl(day, realized);
+        }
+    }
+
+    /// Daily realized PnL of every account that has traded
+    pub fn daily_pnl(&self) -> Vec<DailyPnl> {
+        let mut daily_pnl: Vec<DailyPnl> = self
+            .accounts
+            .iter()
+            .filter_map(|(user_id, account)| {
+                account.daily_pnl.map(|(day, pnl)| DailyPnl {
+                    user_id: user_id.clone(),
+                    day,
+                    pnl,
+                })
+            })
+            .collect();
+        daily_pnl.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+        daily_pnl
+    }
+
+    /// Replace the tracked orders, positions and prices with the state of
+    /// restored books, and daily PnL with the snapshot's (limits are kept)
+    ///
+    /// The books' trades only rebuild positions: daily PnL comes from
+    /// `daily_pnl` alone, so it does not depend on how much trade history
+    /// the books hold.
+    pub fn rebuild<'a>(
+        &mut self,
+        orders: impl IntoIterator<Item = &'a Order>,
+        trades: impl IntoIterator<Item = &'a Trade>,
+        daily_pnl: impl IntoIterator<Item = DailyPnl>,
+    ) {
+        self.accounts.clear();
+        self.owners.clear();
+        self.last_prices.clear();
+
+        let mut trades: Vec<&Trade> = trades.into_iter().collect();
+        trades.sort_by_key(|trade| trade.timestamp);
+        for trade in trades {
+            self.record_trade(trade);
+        }
+        for account in self.accounts.values_mut() {
+            account.daily_pnl = None;
+        }
+        for DailyPnl { user_id, day, pnl } in daily_pnl {
+            self.accounts.entry(user_id).or_default().daily_pnl = Some((day, pnl));
+        }
+        for order in orders {
+            self.track_order(order);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::OrderType;
+    use chrono::TimeZone;
+    use rust_decimal_macros::dec;
+
+    fn limit(side: OrderSide, price: Decimal, quantity: Decimal, user_id: &str) -> Order {
+        Order::new("AAPL".to_string(), side, OrderType::Limit, Some(price), quantity, user_id.to_string())
+    }
+
+    fn trade(price: Decimal, quantity: Decimal, buyer: &str, seller: &str, at: DateTime<Utc>) -> Trade {
+        let mut trade = Trade::new(
+            "AAPL".to_string(),
+            price,
+            quantity,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            buyer.to_string(),
+            seller.to_string(),
+            Decimal::ZERO,
+            Decimal::ZERO,
+        );
+        trade.timestamp = at;
+        trade
+    }
+
+    fn reason(result: Result<(), RiskRejection>) -> Option<RiskRejectReason> {
+        result.err().map(|rejection| rejection.reason)
+    }
+
+    #[test]
+    fn test_unset_limits_are_not_checked() {
+        let mut risk = RiskEngine::new();
+        let now = Utc::now();
+        for _ in 0..1000 {
+            risk.check_message("alice", now).unwrap();
+            let order = limit(OrderSide::Buy, dec!(1000000), dec!(1000000), "alice");
+            risk.check_order(&order, now).unwrap();
+            risk.track_order(&order);
+        }
+        assert_eq!(risk.usage("alice", now).open_orders, 1000);
+    }
+
+    #[test]
+    fn test_open_orders_and_exposure() {
+        let mut risk = RiskEngine::new();
+        let now = Utc::now();
+        risk.set_account_limits(
+            "alice",
+            Some(RiskLimits {
+                max_open_orders: Some(2),
+                max_notional_exposure: Some(dec!(1000)),
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+
+        let first = limit(OrderSide::Buy, dec!(100), dec!(6), "alice");
+        risk.check_order(&first, now).unwrap();
+        risk.track_order(&first);
+
+        // 6 working + 5 more at 100 is 1100 of long exposure
+        let too_big = limit(OrderSide::Buy, dec!(100), dec!(5), "alice");
+        assert_eq!(reason(risk.check_order(&too_big, now)), Some(RiskRejectReason::MaxNotionalExposure));
+        // Sells count towards the short side only
+        let sell = limit(OrderSide::Sell, dec!(101), dec!(9), "alice");
+        risk.check_order(&sell, now).unwrap();
+        risk.track_order(&sell);
+
+        let third = limit(OrderSide::Buy, dec!(100), dec!(1), "alice");
+        assert_eq!(reason(risk.check_order(&third, now)), Some(RiskRejectReason::MaxOpenOrders));
+        // Amending a working order does not count it twice
+        let mut amended = first.clone();
+        amended.quantity = dec!(10);
+        risk.check_order(&amended, now).unwrap();
+
+        // A fill turns working quantity into position: still 1000 long
+        risk.record_trade(&trade(dec!(100), dec!(4), "alice", "bob", now));
+        let mut filled = first.clone();
+        filled.filled_quantity = dec!(4);
+        risk.track_order(&filled);
+        risk.untrack_order(sell.id);
+        let more = limit(OrderSide::Buy, dec!(100), dec!(5), "alice");
+        assert_eq!(reason(risk.check_order(&more, now)), Some(RiskRejectReason::MaxNotionalExposure));
+        let fits = limit(OrderSide::Buy, dec!(100), dec!(4), "alice");
+        risk.check_order(&fits, now).unwrap();
+
+        // Other accounts fall back to the (empty) default limits
+        risk.check_order(&limit(OrderSide::Buy, dec!(100), dec!(100), "bob"), now).unwrap();
+        assert_eq!(risk.usage("alice", now).open_orders, 1);
+        assert_eq!(risk.owner(first.id), Some("alice"));
+    }
+
+    #[test]
+    fn test_price_deviation_from_last_trade() {
+        let mut risk = RiskEngine::new();
+        let now = Utc::now();
+        risk.set_default_limits(RiskLimits {
+            max_price_deviation_pct: Some(dec!(5)),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Nothing to compare with before the first trade
+        risk.check_order(&limit(OrderSide::Buy, dec!(500), dec!(1), "alice"), now).unwrap();
+
+        risk.record_trade(&trade(dec!(100), dec!(1), "bob", "carol", now));
+        risk.check_order(&limit(OrderSide::Buy, dec!(105), dec!(1), "alice"), now).unwrap();
+        risk.check_order(&limit(OrderSide::Sell, dec!(95), dec!(1), "alice"), now).unwrap();
+        let fat_finger = limit(OrderSide::Buy, dec!(1050), dec!(1), "alice");
+        let rejection = risk.check_order(&fat_finger, now).unwrap_err();
+        assert_eq!(rejection.reason, RiskRejectReason::PriceDeviation);
+        assert!(rejection.to_string().starts_with("PRICE_DEVIATION: "));
+
+        let market = Order::new("AAPL".to_string(), OrderSide::Buy, OrderType::Market, None, dec!(1), "alice".to_string());
+        risk.check_order(&market, now).unwrap();
+    }
+
+    #[test]
+    fn test_message_rate_window() {
+        let mut risk = RiskEngine::new();
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap();
+        risk.set_default_limits(RiskLimits {
+            max_messages_per_second: Some(3),
+            ..Default::default()
+        })
+        .unwrap();
+
+        for i in 0..3 {
+            risk.check_message("alice", start + Duration::milliseconds(i * 100)).unwrap();
+        }
+        let flooded = risk.check_message("alice", start + Duration::milliseconds(500));
+        assert_eq!(reason(flooded), Some(RiskRejectReason::MessageRate));
+        risk.check_message("bob", start + Duration::milliseconds(500)).unwrap();
+
+        // The first message leaves the window after a second
+        risk.check_message("alice", start + Duration::milliseconds(1000)).unwrap();
+        let flooded = risk.check_message("alice", start + Duration::milliseconds(1050));
+        assert_eq!(reason(flooded), Some(RiskRejectReason::MessageRate));
+        assert_eq!(risk.usage("alice", start + Duration::milliseconds(1050)).messages_last_second, 3);
+    }
+
+    #[test]
+    fn test_daily_loss_resets_at_midnight() {
+        let mut risk = RiskEngine::new();
+        let day = Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap();
+        risk.set_default_limits(RiskLimits {
+            max_daily_loss: Some(dec!(100)),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Yesterday's loss does not count
+        risk.record_trade(&trade(dec!(100), dec!(10), "alice", "bob", day - Duration::days(1)));
+        risk.record_trade(&trade(dec!(50), dec!(5), "bob", "alice", day - Duration::days(1)));
+        risk.check_order(&limit(OrderSide::Buy, dec!(50), dec!(1), "alice"), day).unwrap();
+
+        // Selling the other 5 at 80 realizes 100 of loss today
+        risk.record_trade(&trade(dec!(80), dec!(5), "bob", "alice", day));
+        assert_eq!(risk.usage("alice", day).daily_realized_pnl, dec!(-100));
+        let order = limit(OrderSide::Buy, dec!(80), dec!(1), "alice");
+        assert_eq!(reason(risk.check_order(&order, day)), Some(RiskRejectReason::DailyLoss));
+
+        // bob gained and is not affected; alice trades again tomorrow
+        risk.check_order(&limit(OrderSide::Buy, dec!(80), dec!(1), "bob"), day).unwrap();
+        risk.check_order(&order, day + Duration::days(1)).unwrap();
+    }
+
+    #[test]
+    fn test_rebuild_keeps_daily_pnl_without_the_trades() {
+        let mut risk = RiskEngine::new();
+        let day = Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap();
+        risk.set_default_limits(RiskLimits {
+            max_daily_loss: Some(dec!(100)),
+            ..Default::default()
+        })
+        .unwrap();
+        risk.record_trade(&trade(dec!(100), dec!(10), "alice", "bob", day));
+        risk.record_trade(&trade(dec!(80), dec!(5), "bob", "alice", day));
+        let daily_pnl = risk.daily_pnl();
+        assert_eq!(daily_pnl.len(), 2);
+
+        // The books no longer hold the losing trades; the loss still counts
+        risk.rebuild([], [], daily_pnl);
+        assert_eq!(risk.usage("alice", day).daily_realized_pnl, dec!(-100));
+        let order = limit(OrderSide::Buy, dec!(80), dec!(1), "alice");
+        assert_eq!(reason(risk.check_order(&order, day)), Some(RiskRejectReason::DailyLoss));
+
+        // Trades in the books rebuild positions without counting twice
+        let trades = [trade(dec!(100), dec!(10), "alice", "bob", day), trade(dec!(80), dec!(5), "bob", "alice", day)];
+        risk.rebuild([], &trades, risk.daily_pnl());
+        assert_eq!(risk.usage("alice", day).daily_realized_pnl, dec!(-100));
+        assert_eq!(risk.usage("bob", day).daily_realized_pnl, dec!(100));
+    }
+
+    #[test]
+    fn test_limits_are_validated() {
+        let mut risk = RiskEngine::new();
+        let negative = RiskLimits {
+            max_daily_loss: Some(dec!(-1)),
+            ..Default::default()
+        };
+        assert!(risk.set_default_limits(negative.clone()).is_err());
+        assert!(risk.set_account_limits("alice", Some(negative)).is_err());
+
+        let blocked = RiskLimits {
+            max_open_orders: Some(0),
+            ..Default::default()
+        };
+        risk.set_account_limits("alice", Some(blocked.clone())).unwrap();
+        assert_eq!(risk.limits_for("alice"), &blocked);
+        risk.set_account_limits("alice", None).unwrap();
+        assert_eq!(risk.limits_for("alice"), &RiskLimits::default());
+    }
+}
@@ -1,6 +1,8 @@
 pub mod circuit_breaker;
+pub mod limits;
 
 pub use circuit_breaker::{
     CircuitBreaker, CircuitBreakerConfig, CircuitBreakerStatus,
-    CircuitState, HaltReason, RiskError,
+    CircuitState, HaltReason, PriceBand, PriceBandAction, RiskError,
 };
+pub use limits::{AccountRiskStatus, DailyPnl, RiskEngine, RiskLimits, RiskRejectReason, RiskRejection, RiskUsage};
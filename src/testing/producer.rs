@@ -365,6 +365,7 @@ impl OrderProducer {
             post_only,
             expire_time,
             iceberg,
+            peg_offset: None,
             timestamp: Utc::now(),
         }
     }
@@ -53,6 +53,7 @@ pub struct TestingMetrics {
     pub total_orders: u64,
     pub market_orders: u64,
     pub limit_orders: u64,
+    pub pegged_orders: u64,
     pub iceberg_orders: u64,
     pub post_only_orders: u64,
 
@@ -106,6 +107,7 @@ impl Default for TestingMetrics {
             total_orders: 0,
             market_orders: 0,
             limit_orders: 0,
+            pegged_orders: 0,
             iceberg_orders: 0,
             post_only_orders: 0,
             gtc_orders: 0,
@@ -146,6 +148,7 @@ impl TestingMetrics {
         match order_type {
             OrderType::Market => self.market_orders += 1,
             OrderType::Limit => self.limit_orders += 1,
+            OrderType::PrimaryPeg | OrderType::MidpointPeg | OrderType::MarketPeg => self.pegged_orders += 1,
         }
         self.last_updated = Utc::now();
     }
@@ -0,0 +1,207 @@
+//! Injectable time and ID sources
+//!
+//! Everything that stamps engine state with the current time or a fresh ID
+//! (order and trade construction, stop triggers, algorithm slices) goes
+//! through `now()` and `new_id()` instead of `Utc::now()` and `Uuid::new_v4()`.
+//! They read the wall clock and random UUIDs unless a thread runs inside
+//! `with_sources`, which swaps in other sources for the duration of a closure.
+//! The replay harness uses that to drive the engine from a `SimulatedClock`
+//! and `SeededIds`, so the same input produces the same trades on every run.
+
+use chrono::{DateTime, Duration, Utc};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Source of unique IDs
+pub trait IdSource: Send + Sync {
+    fn next_id(&self) -> Uuid;
+}
+
+/// The wall clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Random version 4 UUIDs
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RandomIds;
+
+impl IdSource for RandomIds {
+    fn next_id(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// A clock that only moves when it is told to
+#[derive(Debug)]
+pub struct SimulatedClock {
+    now_ns: AtomicI64,
+}
+
+impl SimulatedClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now_ns: AtomicI64::new(start.timestamp_nanos_opt().unwrap_or(0)),
+        }
+    }
+
+    /// Move the clock to `time` (it may go backwards)
+    pub fn set(&self, time: DateTime<Utc>) {
+        self.now_ns.store(time.timestamp_nanos_opt().unwrap_or(0), Ordering::SeqCst);
+    }
+
+    /// Move the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        self.now_ns.fetch_add(duration.num_nanoseconds().unwrap_or(0), Ordering::SeqCst);
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_nanos(self.now_ns.load(Ordering::SeqCst))
+    }
+}
+
+/// Version 4 UUIDs drawn from a seeded generator: the same seed yields the
+/// same sequence of IDs
+#[derive(Debug)]
+pub struct SeededIds {
+    seed: u64,
+    counter: AtomicU64,
+}
+
+impl SeededIds {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            counter: AtomicU64::new(0),
+        }
+    }
+}
+
+/// SplitMix64 finalizer
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl IdSource for SeededIds {
+    fn next_id(&self) -> Uuid {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed);
+        let base = self.seed.wrapping_add(n.wrapping_mul(2).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let high = mix(base);
+        let low = mix(base.wrapping_add(0x9e37_79b9_7f4a_7c15));
+
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&high.to_be_bytes());
+        bytes[8..].copy_from_slice(&low.to_be_bytes());
+        uuid::Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
+
+struct Sources {
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn IdSource>,
+}
+
+thread_local! {
+    /// Sources installed by `with_sources` on this thread
+    static SOURCES: RefCell<Option<Sources>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with `clock` and `ids` as this thread's time and ID sources
+///
+/// The previous sources are restored afterwards, also when `f` panics.
+pub fn with_sources<T>(clock: Arc<dyn Clock>, ids: Arc<dyn IdSource>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<Sources>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            SOURCES.with(|sources| *sources.borrow_mut() = previous);
+        }
+    }
+
+    let previous = SOURCES.with(|sources| sources.borrow_mut().replace(Sources { clock, ids }));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// Run `f` with `clock` as this thread's time source, keeping its ID source
+pub fn with_clock<T>(clock: Arc<dyn Clock>, f: impl FnOnce() -> T) -> T {
+    let ids = SOURCES
+        .with(|sources| sources.borrow().as_ref().map(|sources| sources.ids.clone()))
+        .unwrap_or_else(|| Arc::new(RandomIds));
+    with_sources(clock, ids, f)
+}
+
+/// The current time of this thread's clock
+pub fn now() -> DateTime<Utc> {
+    SOURCES
+        .with(|sources| sources.borrow().as_ref().map(|sources| sources.clock.clone()))
+        .map_or_else(Utc::now, |clock| clock.now())
+}
+
+/// A fresh ID from this thread's ID source
+pub fn new_id() -> Uuid {
+    SOURCES
+        .with(|sources| sources.borrow().as_ref().map(|sources| sources.ids.clone()))
+        .map_or_else(Uuid::new_v4, |ids| ids.next_id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_ids_repeat_per_seed() {
+        let ids = SeededIds::new(7);
+        let a: Vec<Uuid> = (0..100).map(|_| ids.next_id()).collect();
+        let ids = SeededIds::new(7);
+        let b: Vec<Uuid> = (0..100).map(|_| ids.next_id()).collect();
+        assert_eq!(a, b);
+        assert_eq!(a.iter().collect::<std::collections::HashSet<_>>().len(), 100);
+        assert_eq!(a[0].get_version_num(), 4);
+        assert_ne!(SeededIds::new(8).next_id(), a[0]);
+    }
+
+    #[test]
+    fn test_with_sources_is_scoped() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = Arc::new(SimulatedClock::new(start));
+
+        let (inside, advanced, id) = with_sources(clock.clone(), Arc::new(SeededIds::new(1)), || {
+            let inside = now();
+            clock.advance(Duration::seconds(5));
+            (inside, now(), new_id())
+        });
+        assert_eq!(inside, start);
+        assert_eq!(advanced, start + Duration::seconds(5));
+        assert_eq!(id, SeededIds::new(1).next_id());
+
+        // Outside the scope the wall clock is back
+        assert!(now() > start + Duration::days(365));
+    }
+
+    #[test]
+    fn test_with_clock_keeps_the_id_source() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let (inside, id) = with_sources(Arc::new(SystemClock), Arc::new(SeededIds::new(3)), || {
+            with_clock(Arc::new(SimulatedClock::new(start)), || (now(), new_id()))
+        });
+        assert_eq!(inside, start);
+        assert_eq!(id, SeededIds::new(3).next_id());
+    }
+}
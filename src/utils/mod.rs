@@ -1,6 +1,8 @@
 // Utility functions and validation
 // Can be extended with input validation, helper functions, etc.
 
+pub mod clock;
+
 pub mod validation {
     use rust_decimal::Decimal;
 
@@ -0,0 +1,60 @@
+//! Publishes the indicative uncross of running call auctions on the
+//! `auction:{symbol}` topics
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use rust_decimal::Decimal;
+use tokio::time::{interval, Duration};
+use tracing::{error, info};
+
+use crate::engine::OrderBookEngine;
+use crate::models::{AuctionKind, AuctionState, OrderSide};
+
+use super::broadcaster::{topics, Broadcaster};
+use super::messages::WsMessage;
+
+/// Build the WebSocket message for an auction's indicative uncross
+pub fn auction_indicative_message(state: &AuctionState) -> WsMessage {
+    let kind = match state.kind {
+        AuctionKind::Opening => "opening",
+        AuctionKind::Closing => "closing",
+        AuctionKind::Reopening => "reopening",
+    };
+    let indicative = state.indicative.as_ref();
+
+    WsMessage::AuctionIndicative {
+        symbol: state.symbol.clone(),
+        kind: kind.to_string(),
+        price: indicative.map(|i| i.price),
+        volume: indicative.map(|i| i.volume).unwrap_or(Decimal::ZERO),
+        imbalance: indicative.map(|i| i.imbalance).unwrap_or(Decimal::ZERO),
+        imbalance_side: indicative.and_then(|i| i.imbalance_side).map(|side| match side {
+            OrderSide::Buy => "buy".to_string(),
+            OrderSide::Sell => "sell".to_string(),
+        }),
+        timestamp: Utc::now(),
+    }
+}
+
+/// Broadcast the indicative uncross of every running auction once per second
+pub async fn run_auction_publisher(engine: Arc<OrderBookEngine>, broadcaster: Broadcaster) {
+    info!("Auction publisher starting");
+    let mut tick_interval = interval(Duration::from_secs(1));
+
+    loop {
+        tick_interval.tick().await;
+
+        let auctions = match engine.get_auctions() {
+            Ok(auctions) => auctions,
+            Err(e) => {
+                error!("Failed to read running auctions: {}", e);
+                continue;
+            }
+        };
+
+        for state in &auctions {
+            broadcaster.broadcast(&topics::auction(&state.symbol), auction_indicative_message(state));
+        }
+    }
+}
@@ -82,7 +82,27 @@ pub mod topics {
         format!("ticker:{}", symbol)
     }
 
+    pub fn orders(symbol: &str) -> String {
+        format!("orders:{}", symbol)
+    }
+
+    pub fn auction(symbol: &str) -> String {
+        format!("auction:{}", symbol)
+    }
+
+    pub fn positions(user_id: &str) -> String {
+        format!("positions:{}", user_id)
+    }
+
     pub fn all_trades() -> &'static str {
         "trades:*"
     }
+
+    pub fn trading_status(symbol: &str) -> String {
+        format!("trading_status:{}", symbol)
+    }
+
+    pub fn all_trading_status() -> &'static str {
+        "trading_status:*"
+    }
 }
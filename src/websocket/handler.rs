@@ -14,13 +14,23 @@ use tracing::{error, info};
 use super::{
     broadcaster::{topics, Broadcaster},
     messages::{ClientMessage, WsMessage},
+    positions::positions_message,
+    trading_status::trading_status_message,
 };
-use crate::engine::OrderBookEngine;
+use crate::api::handlers::order_from_request;
+use crate::disruptor::IngestionPipeline;
+use crate::engine::{OrderBookEngine, OrderBookError};
+use crate::models::Order;
+use crate::positions::PositionTracker;
 
 /// WebSocket connection state
 pub struct WsState {
     pub broadcaster: Broadcaster,
     pub engine: Arc<OrderBookEngine>,
+    /// Order entry requests are published to the ingestion pipeline
+    pub pipeline: Arc<IngestionPipeline>,
+    /// Serves the initial snapshot of the private positions channel
+    pub positions: Arc<PositionTracker>,
 }
 
 /// Handle WebSocket upgrade request
@@ -117,8 +127,8 @@ async fn handle_client_message(
     let client_msg: ClientMessage = serde_json::from_str(text)?;
 
     match client_msg {
-        ClientMessage::Subscribe { channel, symbol } => {
-            let topic = build_topic(&channel, symbol.as_deref())?;
+        ClientMessage::Subscribe { channel, symbol, user_id } => {
+            let topic = build_topic(&channel, symbol.as_deref(), user_id.as_deref())?;
             let rx = state.broadcaster.subscribe(&topic);
 
             subscriptions.push((topic.clone(), rx));
@@ -136,13 +146,31 @@ async fn handle_client_message(
                 }
             }
 
+            // Send the current positions on a positions subscription
+            if channel == "positions" {
+                if let Some(user_id) = &user_id {
+                    let positions = state.positions.positions(user_id)?;
+                    let json = serde_json::to_string(&positions_message(user_id, positions))?;
+                    sender.send(Message::Text(json)).await?;
+                }
+            }
+
+            // Send the current status on a trading status subscription for one symbol
+            if channel == "trading_status" {
+                if let Some(sym) = &symbol {
+                    let status = state.engine.get_circuit_breaker(sym)?;
+                    let json = serde_json::to_string(&trading_status_message(sym, &status))?;
+                    sender.send(Message::Text(json)).await?;
+                }
+            }
+
             let json = serde_json::to_string(&response)?;
             sender.send(Message::Text(json)).await?;
 
             info!("Client subscribed to: {}", topic);
         }
-        ClientMessage::Unsubscribe { channel, symbol } => {
-            let topic = build_topic(&channel, symbol.as_deref())?;
+        ClientMessage::Unsubscribe { channel, symbol, user_id } => {
+            let topic = build_topic(&channel, symbol.as_deref(), user_id.as_deref())?;
 
             subscriptions.retain(|(t, _)| t != &topic);
 
@@ -162,13 +190,56 @@ async fn handle_client_message(
             let json = serde_json::to_string(&response)?;
             sender.send(Message::Text(json)).await?;
         }
+        ClientMessage::SubmitOrder(request) => {
+            let result = state.pipeline.submit_order(order_from_request(request)).await;
+            let response = order_entry_response("submit_order", result.map(|(order, trades)| (order, trades.len())));
+            let json = serde_json::to_string(&response)?;
+            sender.send(Message::Text(json)).await?;
+        }
+        ClientMessage::CancelOrder { symbol, order_id } => {
+            let result = state.pipeline.cancel_order(&symbol, order_id).await;
+            let response = order_entry_response("cancel_order", result.map(|order| (order, 0)));
+            let json = serde_json::to_string(&response)?;
+            sender.send(Message::Text(json)).await?;
+        }
+        ClientMessage::AmendOrder {
+            symbol,
+            order_id,
+            price,
+            quantity,
+        } => {
+            let result = state.pipeline.amend_order(&symbol, order_id, price, quantity).await;
+            let response = order_entry_response("amend_order", result.map(|(order, trades)| (order, trades.len())));
+            let json = serde_json::to_string(&response)?;
+            sender.send(Message::Text(json)).await?;
+        }
     }
 
     Ok(())
 }
 
-/// Build topic string from channel and symbol
-fn build_topic(channel: &str, symbol: Option<&str>) -> Result<String, String> {
+/// Reply to an order entry request: an acknowledgement or the error
+fn order_entry_response(action: &str, result: Result<(Order, usize), OrderBookError>) -> WsMessage {
+    match result {
+        Ok((order, trades)) => WsMessage::OrderAck {
+            action: action.to_string(),
+            order_id: order.id.to_string(),
+            symbol: order.symbol.clone(),
+            status: serde_json::to_value(order.status)
+                .ok()
+                .and_then(|status| status.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            filled_quantity: order.filled_quantity,
+            remaining_quantity: order.remaining_quantity(),
+            trades,
+            timestamp: chrono::Utc::now(),
+        },
+        Err(e) => WsMessage::Error { message: e.to_string() },
+    }
+}
+
+/// Build topic string from channel and symbol (or user for private channels)
+fn build_topic(channel: &str, symbol: Option<&str>, user_id: Option<&str>) -> Result<String, String> {
     match channel {
         "orderbook" => symbol
             .map(topics::orderbook)
@@ -183,6 +254,18 @@ fn build_topic(channel: &str, symbol: Option<&str>) -> Result<String, String> {
         "ticker" => symbol
             .map(topics::ticker)
             .ok_or_else(|| "ticker channel requires symbol".to_string()),
+        "orders" => symbol
+            .map(topics::orders)
+            .ok_or_else(|| "orders channel requires symbol".to_string()),
+        "auction" => symbol
+            .map(topics::auction)
+            .ok_or_else(|| "auction channel requires symbol".to_string()),
+        "positions" => user_id
+            .map(topics::positions)
+            .ok_or_else(|| "positions channel requires user_id".to_string()),
+        "trading_status" => Ok(symbol
+            .map(topics::trading_status)
+            .unwrap_or_else(|| topics::all_trading_status().to_string())),
         _ => Err(format!("Unknown channel: {}", channel)),
     }
 }
@@ -1,6 +1,11 @@
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::api::responses::SubmitOrderRequest;
+use crate::positions::Position;
+use crate::risk::{CircuitState, HaltReason, PriceBand};
 
 /// WebSocket message types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,7 +32,7 @@ pub enum WsMessage {
         trade_id: String,
         price: Decimal,
         quantity: Decimal,
-        side: String, // Taker side: "buy" or "sell"
+        side: String, // Taker side: "buy" or "sell" ("auction" for an uncross)
         timestamp: DateTime<Utc>,
     },
     /// Ticker update (best bid/ask)
@@ -69,6 +74,50 @@ pub enum WsMessage {
         symbol: String,
         timestamp: DateTime<Utc>,
     },
+    /// Order removed from the book without a user request (e.g. expired)
+    OrderCancelled {
+        order_id: String,
+        symbol: String,
+        user_id: String,
+        reason: String, // e.g. "expired"
+        timestamp: DateTime<Utc>,
+    },
+    /// Indicative uncross of a running call auction
+    AuctionIndicative {
+        symbol: String,
+        kind: String, // "opening", "closing" or "reopening"
+        price: Option<Decimal>, // None while the book does not cross
+        volume: Decimal,
+        imbalance: Decimal,
+        imbalance_side: Option<String>, // "buy" or "sell"
+        timestamp: DateTime<Utc>,
+    },
+    /// A user's positions, sent on the private `positions:{user_id}` channel
+    Positions {
+        user_id: String,
+        positions: Vec<Position>,
+        timestamp: DateTime<Utc>,
+    },
+    /// A symbol's circuit breaker changed state (halted, reopening, resumed)
+    TradingStatus {
+        symbol: String,
+        state: CircuitState,
+        halt_reason: Option<HaltReason>,
+        halt_until: Option<DateTime<Utc>>,
+        price_band: Option<PriceBand>,
+        timestamp: DateTime<Utc>,
+    },
+    /// Reply to an order entry request sent over the socket
+    OrderAck {
+        action: String, // "submit_order", "cancel_order" or "amend_order"
+        order_id: String,
+        symbol: String,
+        status: String,
+        filled_quantity: Decimal,
+        remaining_quantity: Decimal,
+        trades: usize,
+        timestamp: DateTime<Utc>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -86,12 +135,30 @@ pub enum ClientMessage {
     Subscribe {
         channel: String,
         symbol: Option<String>,
+        /// Owner of a private channel (`positions`)
+        #[serde(default)]
+        user_id: Option<String>,
     },
     Unsubscribe {
         channel: String,
         symbol: Option<String>,
+        /// Owner of a private channel (`positions`)
+        #[serde(default)]
+        user_id: Option<String>,
     },
     Ping,
+    /// Enter an order (same fields as `POST /api/v1/orders`)
+    SubmitOrder(SubmitOrderRequest),
+    CancelOrder {
+        symbol: String,
+        order_id: Uuid,
+    },
+    AmendOrder {
+        symbol: String,
+        order_id: Uuid,
+        price: Option<Decimal>,
+        quantity: Option<Decimal>,
+    },
 }
 
 /// Order book update for broadcasting
@@ -1,7 +1,13 @@
 pub mod messages;
 pub mod broadcaster;
 pub mod handler;
+pub mod auction;
+pub mod positions;
+pub mod trading_status;
 
 pub use messages::{WsMessage, OrderBookUpdate, TradeUpdate, TickerUpdate};
 pub use broadcaster::Broadcaster;
 pub use handler::{websocket_handler, WsState};
+pub use auction::run_auction_publisher;
+pub use positions::run_position_publisher;
+pub use trading_status::run_trading_status_publisher;
@@ -0,0 +1,82 @@
+//! Publishes users' positions on the private `positions:{user_id}` topics
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::Utc;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::{interval, Duration};
+use tracing::{error, info};
+
+use crate::ctrader_fix::market_data::MarketTick;
+use crate::positions::{Position, PositionTracker};
+
+use super::broadcaster::{topics, Broadcaster};
+use super::messages::WsMessage;
+
+/// Build the WebSocket message for a user's positions
+pub fn positions_message(user_id: &str, positions: Vec<Position>) -> WsMessage {
+    WsMessage::Positions {
+        user_id: user_id.to_string(),
+        positions,
+        timestamp: Utc::now(),
+    }
+}
+
+/// Push every subscribed user's positions whenever they change
+///
+/// Positions move with trades and with the mark price, so they are checked
+/// every 200ms and on each FIX tick from `ticks`, which also updates the
+/// marks of externally priced symbols. Only users with a subscriber are
+/// looked at, and only changed positions are sent.
+pub async fn run_position_publisher(
+    tracker: Arc<PositionTracker>,
+    broadcaster: Broadcaster,
+    mut ticks: Option<UnboundedReceiver<MarketTick>>,
+) {
+    info!("Position publisher starting");
+    let mut publish_interval = interval(Duration::from_millis(200));
+    let mut last_sent: HashMap<String, Vec<Position>> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            _ = publish_interval.tick() => {}
+            tick = async { ticks.as_mut()?.recv().await }, if ticks.is_some() => match tick {
+                Some(tick) => {
+                    if let Err(e) = tracker.on_tick(&tick) {
+                        error!("Failed to mark positions to tick: {}", e);
+                    }
+                }
+                None => ticks = None,
+            },
+        }
+
+        let users = match tracker.users() {
+            Ok(users) => users,
+            Err(e) => {
+                error!("Failed to read positions: {}", e);
+                continue;
+            }
+        };
+
+        for user_id in users {
+            let topic = topics::positions(&user_id);
+            if broadcaster.subscriber_count(&topic) == 0 {
+                last_sent.remove(&user_id);
+                continue;
+            }
+
+            let positions = match tracker.positions(&user_id) {
+                Ok(positions) => positions,
+                Err(e) => {
+                    error!("Failed to read positions of {}: {}", user_id, e);
+                    continue;
+                }
+            };
+            if last_sent.get(&user_id) != Some(&positions) {
+                broadcaster.broadcast(&topic, positions_message(&user_id, positions.clone()));
+                last_sent.insert(user_id, positions);
+            }
+        }
+    }
+}
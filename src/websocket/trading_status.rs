@@ -0,0 +1,69 @@
+//! Publishes circuit breaker halts and resumptions on the
+//! `trading_status:{symbol}` topics (and `trading_status:*`)
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use tokio::time::{interval, Duration};
+use tracing::{error, info};
+
+use crate::engine::OrderBookEngine;
+use crate::risk::{CircuitBreakerStatus, CircuitState, HaltReason};
+
+use super::broadcaster::{topics, Broadcaster};
+use super::messages::WsMessage;
+
+/// What a subscriber is told about when it changes
+type TradingStatusKey = (CircuitState, Option<HaltReason>, Option<DateTime<Utc>>);
+
+/// Build the WebSocket message for a symbol's circuit breaker status
+pub fn trading_status_message(symbol: &str, status: &CircuitBreakerStatus) -> WsMessage {
+    WsMessage::TradingStatus {
+        symbol: symbol.to_string(),
+        state: status.state,
+        halt_reason: status.halt_reason,
+        halt_until: status.halt_until,
+        price_band: status.price_band,
+        timestamp: Utc::now(),
+    }
+}
+
+/// Broadcast every change of a symbol's trading status
+///
+/// Circuit breakers are polled every 500ms; polling also moves symbols whose
+/// halt has expired into their reopening auction. Symbols start out trading
+/// normally, so only halts and what follows them are sent.
+pub async fn run_trading_status_publisher(engine: Arc<OrderBookEngine>, broadcaster: Broadcaster) {
+    info!("Trading status publisher starting");
+    let mut poll_interval = interval(Duration::from_millis(500));
+    let mut last_sent: HashMap<String, TradingStatusKey> = HashMap::new();
+
+    loop {
+        poll_interval.tick().await;
+
+        let breakers = match engine.get_circuit_breakers() {
+            Ok(breakers) => breakers,
+            Err(e) => {
+                error!("Failed to read circuit breakers: {}", e);
+                continue;
+            }
+        };
+
+        for (symbol, status) in breakers {
+            let current = (status.state, status.halt_reason, status.halt_until);
+            let previous = last_sent
+                .get(&symbol)
+                .copied()
+                .unwrap_or((CircuitState::Normal, None, None));
+            if previous == current {
+                continue;
+            }
+
+            let message = trading_status_message(&symbol, &status);
+            broadcaster.broadcast(&topics::trading_status(&symbol), message.clone());
+            broadcaster.broadcast(topics::all_trading_status(), message);
+            last_sent.insert(symbol, current);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Account balances under a stream of orders
+//!
+//! Funded traders run the shared order stream through an engine that keeps
+//! accounts. Trades and fees only move assets between accounts, so every
+//! asset's total must stay at what was deposited; each account's reserved
+//! balance must be exactly what its open orders hold; and a restarted engine
+//! must recover the same balances from its snapshot and log.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use order_book_api::engine::accounts::{symbol_assets, FEE_ACCOUNT};
+use order_book_api::engine::{Balance, OrderBookEngine};
+use order_book_api::models::OrderSide;
+use order_book_api::persistence::{AlgorithmSnapshot, SnapshotStore, SyncMode, WriteAheadLog};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use tempfile::TempDir;
+
+mod common;
+
+use common::{apply, order_stream, states, SYMBOLS};
+
+const USERS: [&str; 5] = ["trader0", "trader1", "trader2", "trader3", "stopper"];
+const ASSETS: [&str; 3] = ["AAPL", "MSFT", "USD"];
+
+fn open_engine(dir: &Path) -> OrderBookEngine {
+    let wal = Arc::new(Mutex::new(WriteAheadLog::open(dir, SyncMode::EveryWrite).unwrap()));
+    let store = SnapshotStore::open(dir.join("snapshots")).unwrap();
+    let engine = OrderBookEngine::with_wal(wal).with_snapshot_store(store).with_accounts();
+    engine.recover().unwrap();
+    engine
+}
+
+/// Deposit a little of everything: enough to trade, too little for every order
+fn fund(engine: &OrderBookEngine) {
+    for user in USERS {
+        engine.deposit(user, "USD", dec!(2500)).unwrap();
+        engine.deposit(user, "AAPL", dec!(25)).unwrap();
+        engine.deposit(user, "MSFT", dec!(25)).unwrap();
+    }
+}
+
+fn all_balances(engine: &OrderBookEngine) -> Vec<BTreeMap<String, Balance>> {
+    USERS
+        .iter()
+        .chain([&FEE_ACCOUNT])
+        .map(|user| engine.get_balances(user).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_trading_conserves_assets_and_reservations_match_open_orders() {
+    let dir = TempDir::new().unwrap();
+    let engine = open_engine(dir.path());
+    fund(&engine);
+    apply(&engine, &order_stream(600));
+
+    assert!(engine.get_total_trades().unwrap() > 0);
+    let fees = engine.get_balances(FEE_ACCOUNT).unwrap()["USD"].available;
+    assert!(fees > Decimal::ZERO);
+    for asset in ASSETS {
+        let deposited = if asset == "USD" { dec!(2500) } else { dec!(25) } * Decimal::from(USERS.len());
+        assert_eq!(engine.get_asset_total(asset).unwrap(), deposited, "{} is not conserved", asset);
+    }
+
+    // What the open orders hold, per user and asset
+    let mut held: BTreeMap<(String, String), Decimal> = BTreeMap::new();
+    for symbol in SYMBOLS {
+        let (base, quote) = symbol_assets(symbol);
+        for order in engine.get_order_book(symbol).unwrap().orders.values() {
+            let asset = if order.side == OrderSide::Buy { &quote } else { &base };
+            let reserved = engine.get_reserved_funds(order.id).unwrap().unwrap();
+            *held.entry((order.user_id.clone(), asset.clone())).or_default() += reserved;
+        }
+    }
+    assert!(!held.is_empty());
+
+    for user in USERS {
+        for (asset, balance) in engine.get_balances(user).unwrap() {
+            assert!(balance.available >= Decimal::ZERO, "{} {} overdrawn", user, asset);
+            let expected = held.get(&(user.to_string(), asset.clone())).copied().unwrap_or_default();
+            assert_eq!(balance.reserved, expected, "{} {} reservation", user, asset);
+        }
+    }
+}
+
+#[test]
+fn test_balances_recover_from_snapshot_and_log() {
+    let ops = order_stream(400);
+    let dir = TempDir::new().unwrap();
+
+    let engine = open_engine(dir.path());
+    fund(&engine);
+    apply(&engine, &ops[..200]);
+    engine.checkpoint(AlgorithmSnapshot::default()).unwrap().unwrap();
+    apply(&engine, &ops[200..]);
+    engine.withdraw("trader0", "AAPL", dec!(1)).unwrap();
+    let (expected_states, expected_balances) = (states(&engine), all_balances(&engine));
+    drop(engine);
+
+    let recovered = open_engine(dir.path());
+    assert_eq!(states(&recovered), expected_states);
+    assert_eq!(all_balances(&recovered), expected_balances);
+}
@@ -0,0 +1,346 @@
+//! Round trips of the versioned binary message layouts
+//!
+//! Every message must decode to what was encoded, for any value the layout
+//! can hold: decimals of any scale, timestamps to the nanosecond, symbols
+//! and user IDs of any length up to 255 bytes and every enum code. Encoding
+//! the decoded message again must give the same bytes, and cutting a message
+//! short must be an error rather than a different message.
+
+use bytes::BytesMut;
+use chrono::{DateTime, Utc};
+use order_book_api::gateway::{GatewayCodec, SequencedMessage};
+use order_book_api::models::{
+    FeeCurrency, IcebergConfig, Order, OrderSide, OrderStatus, OrderType, SelfTradePreventionMode, TimeInForce, Trade,
+};
+use order_book_api::protocol::{BookLevel, BookSnapshot, ExecType, ExecutionReport, Message, RejectReason};
+use proptest::prelude::*;
+use proptest::sample::select;
+use rust_decimal::Decimal;
+use tokio_util::codec::{Decoder, Encoder};
+use uuid::Uuid;
+
+fn decimal() -> impl Strategy<Value = Decimal> {
+    (any::<i64>(), 0u32..=28).prop_map(|(mantissa, scale)| Decimal::from_i128_with_scale(mantissa.into(), scale))
+}
+
+fn timestamp() -> impl Strategy<Value = DateTime<Utc>> {
+    any::<i64>().prop_map(DateTime::from_timestamp_nanos)
+}
+
+fn uuid() -> impl Strategy<Value = Uuid> {
+    any::<u128>().prop_map(Uuid::from_u128)
+}
+
+/// Symbols and user IDs
+fn identifier() -> impl Strategy<Value = String> {
+    prop_oneof!["[A-Z]{1,8}", "[A-Za-z0-9./_-]{0,64}", "\\PC{0,60}"]
+}
+
+fn text() -> impl Strategy<Value = String> {
+    "\\PC{0,300}"
+}
+
+fn side() -> impl Strategy<Value = OrderSide> {
+    select(vec![OrderSide::Buy, OrderSide::Sell])
+}
+
+fn order_type() -> impl Strategy<Value = OrderType> {
+    select(vec![
+        OrderType::Limit,
+        OrderType::Market,
+        OrderType::PrimaryPeg,
+        OrderType::MidpointPeg,
+        OrderType::MarketPeg,
+    ])
+}
+
+fn time_in_force() -> impl Strategy<Value = TimeInForce> {
+    select(vec![TimeInForce::GTC, TimeInForce::IOC, TimeInForce::FOK, TimeInForce::GTD, TimeInForce::DAY])
+}
+
+fn stp_mode() -> impl Strategy<Value = SelfTradePreventionMode> {
+    select(vec![
+        SelfTradePreventionMode::None,
+        SelfTradePreventionMode::CancelResting,
+        SelfTradePreventionMode::CancelIncoming,
+        SelfTradePreventionMode::CancelBoth,
+        SelfTradePreventionMode::CancelSmallest,
+        SelfTradePreventionMode::DecrementBoth,
+    ])
+}
+
+fn order_status() -> impl Strategy<Value = OrderStatus> {
+    select(vec![
+        OrderStatus::New,
+        OrderStatus::PartiallyFilled,
+        OrderStatus::Filled,
+        OrderStatus::Cancelled,
+        OrderStatus::Rejected,
+        OrderStatus::Expired,
+    ])
+}
+
+fn fee_currency() -> impl Strategy<Value = FeeCurrency> {
+    select(vec![FeeCurrency::Quote, FeeCurrency::Base])
+}
+
+fn exec_type() -> impl Strategy<Value = ExecType> {
+    select(vec![
+        ExecType::New,
+        ExecType::Trade,
+        ExecType::Cancelled,
+        ExecType::Replaced,
+        ExecType::Rejected,
+        ExecType::CancelRejected,
+    ])
+}
+
+fn reject_reason() -> impl Strategy<Value = RejectReason> {
+    select(vec![
+        RejectReason::None,
+        RejectReason::InvalidOrder,
+        RejectReason::UnknownOrder,
+        RejectReason::DuplicateOrder,
+        RejectReason::RiskLimit,
+        RejectReason::RateLimit,
+        RejectReason::TradingHalted,
+        RejectReason::OutsidePriceBand,
+        RejectReason::InsufficientFunds,
+        RejectReason::InsufficientLiquidity,
+        RejectReason::SelfTrade,
+        RejectReason::InvalidTradingPhase,
+        RejectReason::Unavailable,
+        RejectReason::Other,
+    ])
+}
+
+fn order() -> impl Strategy<Value = Order> {
+    let kind = (side(), order_type(), time_in_force(), stp_mode(), any::<bool>());
+    let values = (prop::option::of(decimal()), decimal(), prop::option::of(decimal()));
+    let iceberg = prop::option::of((decimal(), decimal()));
+    let meta = (uuid(), identifier(), identifier(), timestamp(), prop::option::of(timestamp()));
+    (kind, values, iceberg, meta).prop_map(
+        |(
+            (side, order_type, time_in_force, stp_mode, post_only),
+            (price, quantity, peg_offset),
+            iceberg,
+            (id, symbol, user_id, timestamp, expire_time),
+        )| {
+            let mut order = Order::new_with_options(
+                symbol,
+                side,
+                order_type,
+                price,
+                quantity,
+                user_id,
+                time_in_force,
+                stp_mode,
+                post_only,
+                expire_time,
+            );
+            order.id = id;
+            order.timestamp = timestamp;
+            order.peg_offset = peg_offset;
+            // The display quantity is capped at the total
+            order.iceberg = iceberg.map(|(a, b)| IcebergConfig::new(a.max(b), a.min(b)));
+            order
+        },
+    )
+}
+
+fn execution_report() -> impl Strategy<Value = ExecutionReport> {
+    let codes = (exec_type(), order_status(), prop::option::of(side()), reject_reason());
+    let values = (prop::option::of(decimal()), prop::option::of(decimal()), decimal(), decimal(), decimal());
+    (uuid(), identifier(), codes, values, timestamp(), text()).prop_map(
+        |(
+            order_id,
+            symbol,
+            (exec_type, status, side, reject_reason),
+            (price, last_price, last_quantity, cumulative_quantity, leaves_quantity),
+            timestamp,
+            text,
+        )| ExecutionReport {
+            order_id,
+            symbol,
+            exec_type,
+            status,
+            side,
+            reject_reason,
+            price,
+            last_price,
+            last_quantity,
+            cumulative_quantity,
+            leaves_quantity,
+            timestamp,
+            text,
+        },
+    )
+}
+
+fn book_levels() -> impl Strategy<Value = Vec<BookLevel>> {
+    prop::collection::vec(
+        (decimal(), decimal(), any::<u32>()).prop_map(|(price, quantity, orders)| BookLevel {
+            price,
+            quantity,
+            orders,
+        }),
+        0..20,
+    )
+}
+
+fn trade() -> impl Strategy<Value = Trade> {
+    let ids = (uuid(), uuid(), uuid(), identifier(), identifier(), identifier());
+    let values = (decimal(), decimal(), decimal(), decimal(), fee_currency(), fee_currency());
+    (ids, values, timestamp()).prop_map(
+        |(
+            (id, buyer_order_id, seller_order_id, symbol, buyer_id, seller_id),
+            (price, quantity, maker_fee, taker_fee, maker_fee_currency, taker_fee_currency),
+            timestamp,
+        )| Trade {
+            id,
+            symbol,
+            price,
+            quantity,
+            buyer_order_id,
+            seller_order_id,
+            buyer_id,
+            seller_id,
+            maker_fee,
+            taker_fee,
+            timestamp,
+            maker_fee_currency,
+            taker_fee_currency,
+        },
+    )
+}
+
+fn message() -> impl Strategy<Value = Message> {
+    prop_oneof![
+        order().prop_map(Message::NewOrder),
+        (uuid(), identifier()).prop_map(|(order_id, symbol)| Message::CancelOrder { order_id, symbol }),
+        (uuid(), identifier(), prop::option::of(decimal()), prop::option::of(decimal())).prop_map(
+            |(order_id, symbol, new_price, new_quantity)| Message::ModifyOrder {
+                order_id,
+                symbol,
+                new_price,
+                new_quantity,
+            }
+        ),
+        execution_report().prop_map(Message::ExecutionReport),
+        (identifier(), timestamp(), book_levels(), book_levels()).prop_map(|(symbol, timestamp, bids, asks)| {
+            Message::OrderBookSnapshot(BookSnapshot {
+                symbol,
+                timestamp,
+                bids,
+                asks,
+            })
+        }),
+        trade().prop_map(Message::Trade),
+        (identifier(), any::<u16>()).prop_map(|(user_id, heartbeat_interval_secs)| Message::Login {
+            user_id,
+            heartbeat_interval_secs,
+        }),
+        any::<u16>().prop_map(|heartbeat_interval_secs| Message::LoginAck { heartbeat_interval_secs }),
+        text().prop_map(|reason| Message::Logout { reason }),
+        Just(Message::Heartbeat),
+    ]
+}
+
+fn encode(message: &Message) -> BytesMut {
+    let mut buf = BytesMut::new();
+    message.encode(&mut buf).unwrap();
+    buf
+}
+
+/// Fields of an order the layout carries
+fn order_fields(order: &Order) -> impl PartialEq + std::fmt::Debug {
+    (
+        (order.id, order.symbol.clone(), order.user_id.clone()),
+        (order.side, order.order_type, order.time_in_force, order.stp_mode, order.post_only),
+        (order.price, order.quantity, order.peg_offset),
+        (order.timestamp, order.expire_time),
+        order.iceberg.as_ref().map(|iceberg| (iceberg.total_quantity, iceberg.display_quantity)),
+    )
+}
+
+proptest! {
+    #[test]
+    fn messages_round_trip(message in message()) {
+        let encoded = encode(&message);
+        let decoded = Message::decode(&mut encoded.clone()).unwrap();
+        prop_assert_eq!(decoded.message_type(), message.message_type());
+        prop_assert_eq!(&encode(&decoded)[..], &encoded[..]);
+
+        match (&message, &decoded) {
+            (Message::NewOrder(order), Message::NewOrder(decoded)) => {
+                prop_assert_eq!(order_fields(decoded), order_fields(order));
+                // Same value and same scale
+                prop_assert_eq!(decoded.quantity.to_string(), order.quantity.to_string());
+            }
+            (Message::ExecutionReport(report), Message::ExecutionReport(decoded)) => {
+                prop_assert_eq!(decoded, report);
+            }
+            (Message::OrderBookSnapshot(snapshot), Message::OrderBookSnapshot(decoded)) => {
+                prop_assert_eq!(decoded, snapshot);
+            }
+            (Message::Trade(trade), Message::Trade(decoded)) => {
+                prop_assert_eq!(
+                    (decoded.id, &decoded.symbol, decoded.price, decoded.quantity, decoded.timestamp),
+                    (trade.id, &trade.symbol, trade.price, trade.quantity, trade.timestamp)
+                );
+                prop_assert_eq!(
+                    (&decoded.buyer_id, &decoded.seller_id, decoded.buyer_order_id, decoded.seller_order_id),
+                    (&trade.buyer_id, &trade.seller_id, trade.buyer_order_id, trade.seller_order_id)
+                );
+                prop_assert_eq!(
+                    (decoded.maker_fee, decoded.taker_fee, decoded.maker_fee_currency, decoded.taker_fee_currency),
+                    (trade.maker_fee, trade.taker_fee, trade.maker_fee_currency, trade.taker_fee_currency)
+                );
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn truncated_messages_are_errors(message in message(), cut in any::<prop::sample::Index>()) {
+        let encoded = encode(&message);
+        let len = cut.index(encoded.len());
+        prop_assert!(Message::decode(&mut &encoded[..len]).is_err());
+    }
+
+    #[test]
+    fn gateway_frames_round_trip(
+        messages in prop::collection::vec((any::<u64>(), message()), 1..8),
+        split in any::<prop::sample::Index>(),
+    ) {
+        // Snapshots and trades are not session messages
+        let messages: Vec<_> = messages
+            .into_iter()
+            .filter(|(_, message)| !matches!(message, Message::OrderBookSnapshot(_) | Message::Trade(_)))
+            .collect();
+        let mut stream = BytesMut::new();
+        for (sequence, message) in &messages {
+            GatewayCodec
+                .encode(SequencedMessage { sequence: *sequence, message: message.clone() }, &mut stream)
+                .unwrap();
+        }
+
+        // Frames may arrive in any number of pieces
+        let mut buf = stream.split_to(split.index(stream.len() + 1));
+        let mut decoded = Vec::new();
+        while let Some(message) = GatewayCodec.decode(&mut buf).unwrap() {
+            decoded.push(message);
+        }
+        buf.unsplit(stream);
+        while let Some(message) = GatewayCodec.decode(&mut buf).unwrap() {
+            decoded.push(message);
+        }
+
+        prop_assert!(buf.is_empty());
+        prop_assert_eq!(decoded.len(), messages.len());
+        for (decoded, (sequence, message)) in decoded.iter().zip(&messages) {
+            prop_assert_eq!(decoded.sequence, *sequence);
+            prop_assert_eq!(&encode(&decoded.message)[..], &encode(message)[..]);
+        }
+    }
+}
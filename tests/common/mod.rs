@@ -0,0 +1,176 @@
+//! Order stream and book comparison shared by the recovery tests
+
+#![allow(dead_code)]
+
+use chrono::Utc;
+use order_book_api::engine::OrderBookEngine;
+use order_book_api::models::{
+    Order, OrderSide, OrderStatus, OrderType, SelfTradePreventionMode, StopOrder, StopOrderStatus, StopOrderType,
+    TimeInForce, TriggerCondition,
+};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use uuid::Uuid;
+
+pub const SYMBOLS: [&str; 2] = ["AAPL", "MSFT"];
+
+#[derive(Debug, Clone)]
+pub enum Op {
+    Submit(Order),
+    Cancel { symbol: String, order_id: Uuid },
+    Amend { symbol: String, order_id: Uuid, price: Option<Decimal>, quantity: Option<Decimal> },
+    Stop(StopOrder),
+}
+
+/// Small deterministic generator so every engine sees the same stream
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: u64) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) % bound
+    }
+}
+
+pub fn stop_order(symbol: &str, side: OrderSide, trigger_price: Decimal, quantity: Decimal) -> StopOrder {
+    StopOrder {
+        id: Uuid::new_v4(),
+        symbol: symbol.to_string(),
+        user_id: "stopper".to_string(),
+        trigger_price,
+        trigger_condition: match side {
+            OrderSide::Sell => TriggerCondition::AtOrBelow,
+            OrderSide::Buy => TriggerCondition::AtOrAbove,
+        },
+        stop_type: StopOrderType::StopMarket,
+        side,
+        quantity,
+        limit_price: None,
+        trail_amount: None,
+        trail_percent: None,
+        highest_price: None,
+        lowest_price: None,
+        created_at: Utc::now(),
+        expire_time: None,
+        status: StopOrderStatus::Pending,
+        time_in_force: TimeInForce::GTC,
+        stp_mode: SelfTradePreventionMode::None,
+        post_only: false,
+    }
+}
+
+pub fn order_stream(len: usize) -> Vec<Op> {
+    let mut rng = Lcg(42);
+    let mut submitted: Vec<(String, Uuid)> = Vec::new();
+    let mut ops = Vec::with_capacity(len);
+
+    while ops.len() < len {
+        let symbol = SYMBOLS[rng.next(2) as usize].to_string();
+        let op = match rng.next(20) {
+            0..=12 => {
+                let side = if rng.next(2) == 0 { OrderSide::Buy } else { OrderSide::Sell };
+                let price = dec!(100) + Decimal::from(rng.next(11) as i64 - 5) / dec!(10);
+                let quantity = Decimal::from(rng.next(10) + 1);
+                let time_in_force = if rng.next(8) == 0 { TimeInForce::IOC } else { TimeInForce::GTC };
+                let order = Order::new_with_options(
+                    symbol.clone(),
+                    side,
+                    OrderType::Limit,
+                    Some(price),
+                    quantity,
+                    format!("trader{}", rng.next(4)),
+                    time_in_force,
+                    SelfTradePreventionMode::None,
+                    false,
+                    None,
+                );
+                submitted.push((symbol, order.id));
+                Op::Submit(order)
+            }
+            13..=15 if !submitted.is_empty() => {
+                let (symbol, order_id) = submitted[rng.next(submitted.len() as u64) as usize].clone();
+                Op::Cancel { symbol, order_id }
+            }
+            16..=18 if !submitted.is_empty() => {
+                let (symbol, order_id) = submitted[rng.next(submitted.len() as u64) as usize].clone();
+                let price = (rng.next(2) == 0).then(|| dec!(100) + Decimal::from(rng.next(11) as i64 - 5) / dec!(10));
+                let quantity = Some(Decimal::from(rng.next(10) + 1));
+                Op::Amend { symbol, order_id, price, quantity }
+            }
+            _ => {
+                let (side, trigger_price) = if rng.next(2) == 0 {
+                    (OrderSide::Sell, dec!(99.6))
+                } else {
+                    (OrderSide::Buy, dec!(100.4))
+                };
+                Op::Stop(stop_order(&symbol, side, trigger_price, Decimal::from(rng.next(3) + 1)))
+            }
+        };
+        ops.push(op);
+    }
+
+    ops
+}
+
+/// Apply operations the way a gateway would: rejected requests are dropped
+pub fn apply(engine: &OrderBookEngine, ops: &[Op]) {
+    for op in ops {
+        let _ = match op.clone() {
+            Op::Submit(order) => engine.add_order(order).map(|_| ()),
+            Op::Cancel { symbol, order_id } => engine.cancel_order(&symbol, order_id).map(|_| ()),
+            Op::Amend { symbol, order_id, price, quantity } => {
+                engine.amend_order(&symbol, order_id, price, quantity).map(|_| ())
+            }
+            Op::Stop(stop) => engine.add_stop_order(stop),
+        };
+    }
+}
+
+pub type Level = (Decimal, Decimal, Vec<Uuid>);
+
+/// Everything about a symbol that recovery must restore
+#[derive(Debug, PartialEq)]
+pub struct BookState {
+    bids: Vec<Level>,
+    asks: Vec<Level>,
+    orders: Vec<(Uuid, Decimal, Decimal, OrderStatus)>,
+    trade_count: usize,
+    stops: Vec<Uuid>,
+    last_trade_price: Option<Decimal>,
+}
+
+pub fn book_state(engine: &OrderBookEngine, symbol: &str) -> BookState {
+    let book = engine.get_order_book(symbol).unwrap();
+    let levels = |levels: &std::collections::BTreeMap<Decimal, order_book_api::models::PriceLevel>| {
+        levels
+            .values()
+            .map(|level| (level.price, level.total_quantity, level.orders.iter().copied().collect()))
+            .collect::<Vec<Level>>()
+    };
+    let mut orders: Vec<_> = book
+        .orders
+        .values()
+        .map(|order| (order.id, order.quantity, order.filled_quantity, order.status))
+        .collect();
+    orders.sort_by_key(|order| order.0);
+    let mut stops: Vec<_> = engine
+        .get_stop_orders_by_symbol(symbol)
+        .unwrap()
+        .into_iter()
+        .map(|stop| stop.id)
+        .collect();
+    stops.sort();
+
+    BookState {
+        bids: levels(&book.bids),
+        asks: levels(&book.asks),
+        orders,
+        trade_count: book.trades.len(),
+        stops,
+        last_trade_price: engine.get_last_trade_price(symbol).unwrap(),
+    }
+}
+
+pub fn states(engine: &OrderBookEngine) -> Vec<BookState> {
+    SYMBOLS.iter().map(|symbol| book_state(engine, symbol)).collect()
+}
@@ -0,0 +1,275 @@
+//! cTrader FIX client against a scripted mock server on localhost
+//!
+//! The server plays cTrader's side of the session step by step: it checks
+//! what the client sends and answers, skips sequence numbers, sends
+//! TestRequests, goes silent and drops the connection. The client must
+//! recover gaps, answer and detect silence, and reconnect and resubscribe,
+//! with its state visible through `DatasourceManager::get_health`.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use order_book_api::ctrader_fix::client::ReconnectPolicy;
+use order_book_api::ctrader_fix::CTraderFixClient;
+use order_book_api::datasource::DatasourceManager;
+use order_book_api::fix::session::{msg_type, tag};
+use order_book_api::fix::{FixCodec, FixSession, Inbound};
+use order_book_api::models::datasource::{
+    ConnectionState, FixConfig, FixCredentials, FixSessionStatus, HealthState, HealthStatus,
+};
+use rust_decimal_macros::dec;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::time::{sleep, timeout};
+use tokio_util::codec::Framed;
+
+const CLIENT: &str = "demo.ctrader.1";
+const SERVER: &str = "cServer";
+
+fn config(port: u16) -> FixConfig {
+    FixConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        credentials: FixCredentials {
+            sender_comp_id: CLIENT.to_string(),
+            target_comp_id: SERVER.to_string(),
+            sender_sub_id: "QUOTE".to_string(),
+            target_sub_id: "QUOTE".to_string(),
+            username: "1234".to_string(),
+            password: "secret".to_string(),
+        },
+    }
+}
+
+type Fields = HashMap<u32, String>;
+
+fn field(fields: &Fields, tag: u32) -> &str {
+    fields.get(&tag).map(String::as_str).unwrap_or_default()
+}
+
+fn pairs(fields: &[(u32, &str)]) -> Vec<(u32, String)> {
+    fields.iter().map(|(tag, value)| (*tag, value.to_string())).collect()
+}
+
+/// Bid and offer of a symbol, as cTrader streams them
+fn quote(symbol_id: &str, bid: &str, ask: &str) -> Vec<(u32, String)> {
+    pairs(&[(55, symbol_id), (268, "2"), (269, "0"), (270, bid), (269, "1"), (270, ask)])
+}
+
+/// cTrader's side of the session, kept across connections like the client's
+struct MockServer {
+    listener: TcpListener,
+    session: FixSession,
+}
+
+impl MockServer {
+    async fn start() -> (Self, u16) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let session = FixSession::new(SERVER, CLIENT).with_sub_ids("QUOTE", "QUOTE");
+        (Self { listener, session }, port)
+    }
+
+    async fn accept(&self) -> Framed<TcpStream, FixCodec> {
+        let (stream, _) = timeout(Duration::from_secs(10), self.listener.accept())
+            .await
+            .expect("client did not connect")
+            .unwrap();
+        Framed::new(stream, FixCodec)
+    }
+
+    /// Next message from the client, whatever its type, without answering
+    /// it; the fields and the raw message
+    async fn expect_any(&mut self, connection: &mut Framed<TcpStream, FixCodec>) -> (Fields, String) {
+        let frame = timeout(Duration::from_secs(10), connection.next())
+            .await
+            .expect("timed out waiting for the client")
+            .expect("client closed the connection")
+            .unwrap();
+        let fields = frame.fields().to_map();
+        let raw = String::from_utf8_lossy(frame.as_bytes()).into_owned();
+        if field(&fields, tag::MSG_TYPE) == msg_type::LOGON && field(&fields, tag::RESET_SEQ_NUM_FLAG) == "Y" {
+            self.session.reset();
+        }
+        (fields, raw)
+    }
+
+    /// Next message from the client, which must be of `expected` type,
+    /// answering session-level requests
+    async fn expect(&mut self, connection: &mut Framed<TcpStream, FixCodec>, expected: &str) -> (Fields, String) {
+        let (fields, raw) = self.expect_any(connection).await;
+        assert_eq!(field(&fields, tag::MSG_TYPE), expected, "{}", raw.replace('\x01', "|"));
+        match self.session.receive(&fields) {
+            Ok(Inbound::Handled(replies)) => {
+                for reply in replies {
+                    connection.send(reply).await.unwrap();
+                }
+            }
+            Ok(_) => {}
+            Err(e) => panic!("client broke the session: {}", e),
+        }
+        (fields, raw)
+    }
+
+    async fn send(&mut self, connection: &mut Framed<TcpStream, FixCodec>, msg_type: &str, fields: Vec<(u32, String)>) {
+        let message = self.session.send(msg_type, fields);
+        connection.send(message).await.unwrap();
+    }
+
+    async fn accept_logon(&mut self, connection: &mut Framed<TcpStream, FixCodec>, heartbeat_interval_secs: &str) -> Fields {
+        let (logon, _) = self.expect(connection, msg_type::LOGON).await;
+        assert_eq!((field(&logon, 553), field(&logon, 554)), ("1234", "secret"));
+        assert_eq!((field(&logon, tag::SENDER_SUB_ID), field(&logon, tag::TARGET_SUB_ID)), ("QUOTE", "QUOTE"));
+        let mut reply = pairs(&[(tag::ENCRYPT_METHOD, "0"), (tag::HEART_BT_INT, heartbeat_interval_secs)]);
+        if field(&logon, tag::RESET_SEQ_NUM_FLAG) == "Y" {
+            reply.push((tag::RESET_SEQ_NUM_FLAG, "Y".to_string()));
+        }
+        self.send(connection, msg_type::LOGON, reply).await;
+        logon
+    }
+}
+
+async fn health_until(manager: &DatasourceManager, done: impl Fn(&HealthStatus) -> bool) -> HealthStatus {
+    for _ in 0..200 {
+        let health = manager.get_health().await;
+        if done(&health) {
+            return health;
+        }
+        sleep(Duration::from_millis(25)).await;
+    }
+    panic!("health never got there: {:?}", manager.get_health().await);
+}
+
+async fn status_until(status: &mut watch::Receiver<FixSessionStatus>, done: impl Fn(&FixSessionStatus) -> bool) -> FixSessionStatus {
+    timeout(Duration::from_secs(10), status.wait_for(|status| done(status)))
+        .await
+        .expect("status never got there")
+        .unwrap()
+        .clone()
+}
+
+#[tokio::test]
+async fn test_gaps_are_recovered_and_symbols_resubscribed_after_reconnect() {
+    let (mut server, port) = MockServer::start().await;
+    let manager = DatasourceManager::new();
+    let (tick_tx, mut ticks) = tokio::sync::mpsc::unbounded_channel();
+    manager.start_live_fix(config(port), tick_tx).await.unwrap();
+    assert!(manager.start_live_fix(config(port), tokio::sync::mpsc::unbounded_channel().0).await.is_err());
+
+    let mut connection = server.accept().await;
+    let logon = server.accept_logon(&mut connection, "30").await;
+    assert_eq!((field(&logon, tag::MSG_SEQ_NUM), field(&logon, tag::RESET_SEQ_NUM_FLAG)), ("1", "Y"));
+    let health = health_until(&manager, |health| health.fix_connection == ConnectionState::Connected).await;
+    assert_eq!(health.reconnect_attempts, None);
+
+    let (request, _) = server.expect(&mut connection, "x").await;
+    let symbols = pairs(&[
+        (320, field(&request, 320)),
+        (560, "0"),
+        (146, "2"),
+        (55, "1"),
+        (1007, "EURUSD"),
+        (1008, "5"),
+        (55, "41"),
+        (1007, "XAUUSD"),
+        (1008, "2"),
+    ]);
+    server.send(&mut connection, "y", symbols).await;
+    let (_, subscribe) = server.expect(&mut connection, "V").await;
+    assert!(subscribe.contains("\x01146=2\x0155=1\x0155=41\x01267=2\x01269=0\x01269=1\x01"), "{}", subscribe);
+
+    server.send(&mut connection, "W", quote("1", "1.1", "1.2")).await;
+    let tick = timeout(Duration::from_secs(5), ticks.recv()).await.unwrap().unwrap();
+    assert_eq!((tick.symbol_id.as_str(), tick.bid_price, tick.ask_price), ("1", Some(dec!(1.1)), Some(dec!(1.2))));
+
+    // A quote goes missing: the client asks for it and gets both again
+    let _lost = server.session.send("W", quote("1", "1.3", "1.4"));
+    server.send(&mut connection, "W", quote("41", "2400", "2401")).await;
+    let (resend, _) = server.expect(&mut connection, msg_type::RESEND_REQUEST).await;
+    assert_eq!((field(&resend, tag::BEGIN_SEQ_NO), field(&resend, tag::END_SEQ_NO)), ("4", "0"));
+    for expected in [dec!(1.3), dec!(2400)] {
+        let tick = timeout(Duration::from_secs(5), ticks.recv()).await.unwrap().unwrap();
+        assert_eq!(tick.bid_price, Some(expected));
+    }
+
+    server.send(&mut connection, msg_type::TEST_REQUEST, pairs(&[(tag::TEST_REQ_ID, "are-you-there")])).await;
+    let (heartbeat, _) = server.expect(&mut connection, msg_type::HEARTBEAT).await;
+    assert_eq!(field(&heartbeat, tag::TEST_REQ_ID), "are-you-there");
+
+    // The connection drops; the client comes back where it left off
+    drop(connection);
+    let health = health_until(&manager, |health| health.fix_connection != ConnectionState::Connected).await;
+    assert_eq!((health.status, health.reconnect_attempts), (HealthState::Unhealthy, Some(1)));
+    assert!(health.warning.unwrap().contains("reconnect attempt 1"));
+
+    let mut connection = server.accept().await;
+    let logon = server.accept_logon(&mut connection, "30").await;
+    // Logon, SecurityListRequest, MarketDataRequest, ResendRequest, Heartbeat
+    assert_eq!((field(&logon, tag::MSG_SEQ_NUM), field(&logon, tag::RESET_SEQ_NUM_FLAG)), ("6", ""));
+    let (_, resubscribe) = server.expect(&mut connection, "V").await;
+    assert!(resubscribe.contains("\x01146=2\x0155=1\x0155=41\x01"), "{}", resubscribe);
+    let health = health_until(&manager, |health| health.fix_connection == ConnectionState::Connected).await;
+    assert_eq!(health.reconnect_attempts, None);
+    assert_eq!(manager.get_symbol_name("41").await.as_deref(), Some("XAUUSD"));
+
+    manager.stop().await.unwrap();
+    assert_eq!(manager.get_health().await.fix_connection, ConnectionState::Disconnected);
+}
+
+#[tokio::test]
+async fn test_silent_server_times_out_and_rejected_logon_resets_sequence_numbers() {
+    let (mut server, port) = MockServer::start().await;
+    let (mut client, _ticks) = CTraderFixClient::with_tick_channel(config(port));
+    client.set_heartbeat_interval(1);
+    client.set_reconnect_policy(ReconnectPolicy {
+        initial_backoff: Duration::from_millis(50),
+        max_backoff: Duration::from_millis(100),
+        max_attempts: Some(3),
+    });
+    let mut status = client.connection_status();
+    let client = tokio::spawn(async move { client.connect_and_run().await.map_err(|e| e.to_string()) });
+
+    let mut connection = server.accept().await;
+    server.accept_logon(&mut connection, "1").await;
+    server.expect(&mut connection, "x").await;
+    status_until(&mut status, |status| status.state == ConnectionState::Connected).await;
+
+    // Silence: a heartbeat of its own, then a TestRequest, then it gives up
+    loop {
+        let (message, _) = server.expect_any(&mut connection).await;
+        assert!(server.session.receive(&message).is_ok());
+        match field(&message, tag::MSG_TYPE) {
+            msg_type::HEARTBEAT => continue,
+            msg_type::TEST_REQUEST => break,
+            other => panic!("unexpected {}", other),
+        }
+    }
+    let (logout, _) = server.expect(&mut connection, msg_type::LOGOUT).await;
+    assert_eq!(field(&logout, tag::TEXT), "heartbeat timeout");
+    let lost = status_until(&mut status, |status| status.state != ConnectionState::Connected).await;
+    assert_eq!(lost.last_error.as_deref(), Some("heartbeat timeout"));
+
+    // Sequence numbers continue; a Logout instead of a Logon makes the next
+    // attempt start over
+    let mut connection = server.accept().await;
+    let (logon, _) = server.expect(&mut connection, msg_type::LOGON).await;
+    assert_eq!(field(&logon, tag::RESET_SEQ_NUM_FLAG), "");
+    server.send(&mut connection, msg_type::LOGOUT, pairs(&[(tag::TEXT, "MsgSeqNum too high")])).await;
+    server.expect(&mut connection, msg_type::LOGOUT).await;
+
+    let mut connection = server.accept().await;
+    let logon = server.accept_logon(&mut connection, "1").await;
+    assert_eq!((field(&logon, tag::MSG_SEQ_NUM), field(&logon, tag::RESET_SEQ_NUM_FLAG)), ("1", "Y"));
+    // Still no symbols, so it asks for them again
+    server.expect(&mut connection, "x").await;
+    let connected = status_until(&mut status, |status| status.state == ConnectionState::Connected).await;
+    assert_eq!(connected.reconnect_attempts, 0);
+
+    // Three connections in a row fail and the client gives up
+    drop(connection);
+    drop(server);
+    let result = timeout(Duration::from_secs(10), client).await.unwrap().unwrap();
+    assert!(result.unwrap_err().contains("3 times in a row"));
+    assert_eq!(status.borrow().state, ConnectionState::Disconnected);
+}
@@ -0,0 +1,366 @@
+//! FIX 4.4 acceptor on localhost
+//!
+//! A local initiator logs on, trades through the ingestion pipeline and must
+//! get an ExecutionReport for every step of its orders. The session layer
+//! must keep sequence numbers across reconnects, answer ResendRequests with
+//! gap fills and duplicates, ask for resends on gaps and log out broken
+//! sessions.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use order_book_api::disruptor::{IngestionPipeline, PipelineConfig};
+use order_book_api::engine::OrderBookEngine;
+use order_book_api::fix::session::{msg_type, tag};
+use order_book_api::fix::{FixAcceptor, FixCodec, FixSession, Inbound};
+use order_book_api::gateway::{ExecutionReportPublisher, OrderRegistry};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+use tokio_util::codec::Framed;
+
+const COMP_ID: &str = "ORDERBOOK";
+
+async fn start_acceptor() -> String {
+    let engine = Arc::new(OrderBookEngine::new());
+    let registry = Arc::new(OrderRegistry::new());
+    let pipeline = Arc::new(IngestionPipeline::start(
+        engine,
+        PipelineConfig::default(),
+        vec![Box::new(ExecutionReportPublisher::new(registry.clone()))],
+    ));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(Arc::new(FixAcceptor::new(COMP_ID, pipeline, registry)).serve(listener));
+    address
+}
+
+type Fields = HashMap<u32, String>;
+
+fn field(fields: &Fields, tag: u32) -> &str {
+    fields.get(&tag).map(String::as_str).unwrap_or_default()
+}
+
+fn pairs(fields: &[(u32, &str)]) -> Vec<(u32, String)> {
+    fields.iter().map(|(tag, value)| (*tag, value.to_string())).collect()
+}
+
+/// Initiator side of a session
+struct Initiator {
+    connection: Framed<TcpStream, FixCodec>,
+    session: FixSession,
+}
+
+impl Initiator {
+    async fn connect(address: &str, session: FixSession) -> Self {
+        let stream = TcpStream::connect(address).await.unwrap();
+        Self {
+            connection: Framed::new(stream, FixCodec),
+            session,
+        }
+    }
+
+    /// Log on with a reset session
+    async fn logon(address: &str, user_id: &str) -> Self {
+        Self::logon_with(address, FixSession::new(user_id, COMP_ID), true).await
+    }
+
+    async fn logon_with(address: &str, session: FixSession, reset: bool) -> Self {
+        let mut initiator = Self::connect(address, session).await;
+        initiator.send_logon(reset).await;
+        let logon = initiator.receive().await;
+        assert_eq!(field(&logon, tag::MSG_TYPE), msg_type::LOGON, "{:?}", logon);
+        assert_eq!(field(&logon, tag::HEART_BT_INT), "30");
+        initiator
+    }
+
+    async fn send_logon(&mut self, reset: bool) {
+        let mut fields = pairs(&[(tag::ENCRYPT_METHOD, "0"), (tag::HEART_BT_INT, "30")]);
+        if reset {
+            self.session.reset();
+            fields.push((tag::RESET_SEQ_NUM_FLAG, "Y".to_string()));
+        }
+        self.send(msg_type::LOGON, fields).await;
+    }
+
+    async fn send(&mut self, msg_type: &str, fields: Vec<(u32, String)>) {
+        let message = self.session.send(msg_type, fields);
+        self.connection.send(message).await.unwrap();
+    }
+
+    /// Next message as it came off the wire
+    async fn receive_raw(&mut self) -> Fields {
+        let message = timeout(Duration::from_secs(5), self.connection.next())
+            .await
+            .expect("timed out waiting for the acceptor")
+            .expect("acceptor closed the connection")
+            .unwrap();
+        message.fields().to_map()
+    }
+
+    /// Next message the session accepts, answering session-level requests
+    async fn receive(&mut self) -> Fields {
+        loop {
+            let fields = self.receive_raw().await;
+            match self.session.receive(&fields) {
+                Ok(Inbound::Application) | Ok(Inbound::Logout(_)) => return fields,
+                Ok(Inbound::Handled(replies)) => {
+                    for reply in replies {
+                        self.connection.send(reply).await.unwrap();
+                    }
+                    let msg_type = field(&fields, tag::MSG_TYPE);
+                    if matches!(msg_type, "0" | "3" | "A") && !fields.contains_key(&tag::POSS_DUP_FLAG) {
+                        return fields;
+                    }
+                }
+                Err(e) => panic!("session error on {:?}: {}", fields, e),
+            }
+        }
+    }
+
+    /// Next `count` application messages, by ClOrdID
+    async fn receive_by_cl_ord_id(&mut self, count: usize) -> HashMap<String, Vec<Fields>> {
+        let mut messages: HashMap<String, Vec<Fields>> = HashMap::new();
+        for _ in 0..count {
+            let message = self.receive().await;
+            messages.entry(field(&message, 11).to_string()).or_default().push(message);
+        }
+        messages
+    }
+
+    async fn new_order(&mut self, cl_ord_id: &str, symbol: &str, side: &str, price: &str, quantity: &str, tif: &str) {
+        let fields = pairs(&[
+            (11, cl_ord_id),
+            (55, symbol),
+            (54, side),
+            (40, "2"),
+            (44, price),
+            (38, quantity),
+            (59, tif),
+            (60, "20300101-00:00:00.000"),
+        ]);
+        self.send("D", fields).await;
+    }
+}
+
+/// Check an ExecutionReport: ExecType, OrdStatus, CumQty and LeavesQty
+fn assert_report(report: &Fields, exec_type: &str, status: &str, cum_qty: &str, leaves_qty: &str) {
+    assert_eq!(field(report, tag::MSG_TYPE), "8", "{:?}", report);
+    assert_eq!(
+        (field(report, 150), field(report, 39), field(report, 14), field(report, 151)),
+        (exec_type, status, cum_qty, leaves_qty),
+        "{:?}",
+        report
+    );
+}
+
+#[tokio::test]
+async fn test_orders_are_filled_replaced_and_cancelled() {
+    let address = start_acceptor().await;
+    let mut alice = Initiator::logon(&address, "alice").await;
+    let mut bob = Initiator::logon(&address, "bob").await;
+
+    alice.new_order("a1", "AAPL", "2", "100", "10", "1").await;
+    let new = alice.receive().await;
+    assert_report(&new, "0", "0", "0", "10");
+    assert_eq!((field(&new, 11), field(&new, 55), field(&new, 54), field(&new, 44)), ("a1", "AAPL", "2", "100"));
+    let order_id = field(&new, 37).to_string();
+
+    bob.new_order("b1", "AAPL", "1", "100", "4", "3").await;
+    let bob_new = bob.receive().await;
+    assert_report(&bob_new, "0", "0", "0", "4");
+    let bob_fill = bob.receive().await;
+    assert_report(&bob_fill, "F", "2", "4", "0");
+
+    let fill = alice.receive().await;
+    assert_report(&fill, "F", "1", "4", "6");
+    assert_eq!((field(&fill, 31), field(&fill, 32), field(&fill, 6)), ("100", "4", "100"));
+    assert_eq!((field(&fill, 37), field(&fill, 11)), (order_id.as_str(), "a1"));
+
+    // Replace by OrigClOrdID; the order keeps its OrderID
+    alice
+        .send("G", pairs(&[(11, "a2"), (41, "a1"), (55, "AAPL"), (54, "2"), (40, "2"), (44, "101"), (38, "8")]))
+        .await;
+    let replaced = alice.receive().await;
+    assert_report(&replaced, "5", "1", "4", "4");
+    assert_eq!(
+        (field(&replaced, 37), field(&replaced, 11), field(&replaced, 41), field(&replaced, 44), field(&replaced, 38)),
+        (order_id.as_str(), "a2", "a1", "101", "8")
+    );
+
+    alice.send("F", pairs(&[(11, "a3"), (41, "a2"), (55, "AAPL"), (54, "2")])).await;
+    let cancelled = alice.receive().await;
+    assert_report(&cancelled, "4", "4", "4", "0");
+    assert_eq!((field(&cancelled, 11), field(&cancelled, 41)), ("a3", "a2"));
+
+    // The order is gone
+    alice.send("F", pairs(&[(11, "a4"), (41, "a3"), (37, &order_id), (55, "AAPL"), (54, "2")])).await;
+    let reject = alice.receive().await;
+    assert_eq!(field(&reject, tag::MSG_TYPE), "9", "{:?}", reject);
+    assert_eq!((field(&reject, 37), field(&reject, 434), field(&reject, 102)), ("NONE", "1", "1"));
+}
+
+#[tokio::test]
+async fn test_mass_cancel_by_symbol_and_all() {
+    let address = start_acceptor().await;
+    let mut alice = Initiator::logon(&address, "alice").await;
+    for (cl_ord_id, symbol) in [("a1", "AAPL"), ("a2", "AAPL"), ("a3", "MSFT")] {
+        alice.new_order(cl_ord_id, symbol, "1", "50", "1", "1").await;
+        assert_report(&alice.receive().await, "0", "0", "0", "1");
+    }
+
+    alice.send("q", pairs(&[(11, "m1"), (530, "1"), (55, "AAPL")])).await;
+    let messages = alice.receive_by_cl_ord_id(3).await;
+    let report = &messages["m1"][0];
+    assert_eq!(field(report, tag::MSG_TYPE), "r");
+    assert_eq!((field(report, 530), field(report, 531), field(report, 533)), ("1", "1", "2"));
+    assert_report(&messages["a1"][0], "4", "4", "0", "0");
+    assert_report(&messages["a2"][0], "4", "4", "0", "0");
+
+    alice.send("q", pairs(&[(11, "m2"), (530, "7")])).await;
+    let messages = alice.receive_by_cl_ord_id(2).await;
+    assert_eq!((field(&messages["m2"][0], 531), field(&messages["m2"][0], 533)), ("7", "1"));
+    assert_report(&messages["a3"][0], "4", "4", "0", "0");
+
+    alice.send("q", pairs(&[(11, "m3"), (530, "3")])).await;
+    let rejected = alice.receive().await;
+    assert_eq!((field(&rejected, 531), field(&rejected, 532)), ("0", "0"));
+}
+
+#[tokio::test]
+async fn test_invalid_requests_are_rejected() {
+    let address = start_acceptor().await;
+    let mut alice = Initiator::logon(&address, "alice").await;
+
+    alice.new_order("a1", "AAPL", "1", "50", "1", "1").await;
+    assert_report(&alice.receive().await, "0", "0", "0", "1");
+    alice.new_order("a1", "AAPL", "1", "50", "1", "1").await;
+    let duplicate = alice.receive().await;
+    assert_report(&duplicate, "8", "8", "0", "0");
+    assert_eq!(field(&duplicate, 103), "6");
+
+    // Missing OrdType
+    alice.send("D", pairs(&[(11, "a2"), (55, "AAPL"), (54, "1"), (38, "1")])).await;
+    let reject = alice.receive().await;
+    assert_eq!(field(&reject, tag::MSG_TYPE), msg_type::REJECT);
+    assert_eq!((field(&reject, tag::REF_TAG_ID), field(&reject, tag::SESSION_REJECT_REASON)), ("40", "1"));
+
+    alice.send("AE", pairs(&[(571, "x")])).await;
+    let reject = alice.receive().await;
+    assert_eq!(field(&reject, tag::MSG_TYPE), "j");
+    assert_eq!((field(&reject, tag::REF_MSG_TYPE), field(&reject, 380)), ("AE", "3"));
+
+    alice.send("G", pairs(&[(11, "a3"), (41, "nope"), (44, "1")])).await;
+    let reject = alice.receive().await;
+    assert_eq!((field(&reject, tag::MSG_TYPE), field(&reject, 434)), ("9", "2"));
+
+    // The session is still in sequence
+    alice.send(msg_type::TEST_REQUEST, pairs(&[(tag::TEST_REQ_ID, "ping")])).await;
+    let heartbeat = alice.receive().await;
+    assert_eq!(
+        (field(&heartbeat, tag::MSG_TYPE), field(&heartbeat, tag::TEST_REQ_ID)),
+        (msg_type::HEARTBEAT, "ping")
+    );
+}
+
+#[tokio::test]
+async fn test_resend_request_is_answered_with_gap_fills_and_duplicates() {
+    let address = start_acceptor().await;
+    let mut alice = Initiator::logon(&address, "alice").await;
+    alice.new_order("a1", "AAPL", "1", "50", "1", "1").await;
+    let new = alice.receive().await;
+    assert_eq!(field(&new, tag::MSG_SEQ_NUM), "2");
+
+    alice
+        .send(msg_type::RESEND_REQUEST, pairs(&[(tag::BEGIN_SEQ_NO, "1"), (tag::END_SEQ_NO, "0")]))
+        .await;
+    // The Logon is filled over, the report comes again
+    let gap_fill = alice.receive_raw().await;
+    assert_eq!(field(&gap_fill, tag::MSG_TYPE), msg_type::SEQUENCE_RESET);
+    assert_eq!(
+        (field(&gap_fill, tag::MSG_SEQ_NUM), field(&gap_fill, tag::GAP_FILL_FLAG), field(&gap_fill, tag::NEW_SEQ_NO)),
+        ("1", "Y", "2")
+    );
+    let resent = alice.receive_raw().await;
+    assert_eq!((field(&resent, tag::MSG_TYPE), field(&resent, tag::MSG_SEQ_NUM)), ("8", "2"));
+    assert_eq!(field(&resent, tag::POSS_DUP_FLAG), "Y");
+    assert_eq!(field(&resent, tag::ORIG_SENDING_TIME), field(&new, tag::SENDING_TIME));
+    assert_eq!(field(&resent, 37), field(&new, 37));
+}
+
+#[tokio::test]
+async fn test_gap_from_the_initiator_is_resent() {
+    let address = start_acceptor().await;
+    let mut alice = Initiator::logon(&address, "alice").await;
+
+    // Sequence number 2 never arrives
+    let _lost = alice.session.heartbeat(None);
+    alice.new_order("a1", "AAPL", "1", "50", "1", "1").await;
+    let request = alice.receive_raw().await;
+    assert_eq!(field(&request, tag::MSG_TYPE), msg_type::RESEND_REQUEST);
+    assert_eq!((field(&request, tag::BEGIN_SEQ_NO), field(&request, tag::END_SEQ_NO)), ("2", "0"));
+
+    // The initiator's session fills the heartbeat and resends the order
+    let Ok(Inbound::Handled(replies)) = alice.session.receive(&request) else {
+        panic!("ResendRequest not handled");
+    };
+    assert_eq!(replies.len(), 2);
+    for reply in replies {
+        alice.connection.send(reply).await.unwrap();
+    }
+    let new = alice.receive().await;
+    assert_report(&new, "0", "0", "0", "1");
+    assert_eq!(field(&new, 11), "a1");
+}
+
+#[tokio::test]
+async fn test_sequence_numbers_survive_reconnects() {
+    let address = start_acceptor().await;
+    let mut alice = Initiator::logon(&address, "alice").await;
+
+    // A second session of the same user is turned away
+    let mut twin = Initiator::connect(&address, FixSession::new("alice", COMP_ID)).await;
+    twin.send_logon(true).await;
+    let logout = twin.receive_raw().await;
+    assert_eq!(field(&logout, tag::MSG_TYPE), msg_type::LOGOUT);
+
+    alice.send(msg_type::LOGOUT, Vec::new()).await;
+    let logout = alice.receive().await;
+    assert_eq!(field(&logout, tag::MSG_TYPE), msg_type::LOGOUT);
+    let session = alice.session;
+
+    // Starting over at 1 without a reset is too low
+    let mut stale = Initiator::connect(&address, FixSession::new("alice", COMP_ID)).await;
+    stale.send_logon(false).await;
+    let logout = stale.receive_raw().await;
+    assert_eq!(field(&logout, tag::MSG_TYPE), msg_type::LOGOUT);
+    assert!(field(&logout, tag::TEXT).contains("MsgSeqNum too low"), "{:?}", logout);
+
+    // Picking up where the session left off works; the Logout sent to the
+    // stale connection is a gap the initiator asks to be resent
+    let mut alice = Initiator::logon_with(&address, session, false).await;
+    alice.new_order("a1", "AAPL", "1", "50", "1", "1").await;
+    assert_report(&alice.receive().await, "0", "0", "0", "1");
+    // Logon, Logout, Logon, ResendRequest, NewOrderSingle; and from the
+    // acceptor Logon, Logout, the stale Logout, Logon, ExecutionReport
+    assert_eq!(alice.session.next_sender_seq(), 6);
+    assert_eq!(alice.session.next_target_seq(), 6);
+}
+
+#[tokio::test]
+async fn test_bad_logons_are_logged_out() {
+    let address = start_acceptor().await;
+
+    let mut wrong_target = Initiator::connect(&address, FixSession::new("alice", "SOMEONE")).await;
+    wrong_target.send_logon(true).await;
+    let logout = wrong_target.receive_raw().await;
+    assert_eq!(field(&logout, tag::MSG_TYPE), msg_type::LOGOUT);
+    assert!(field(&logout, tag::TEXT).contains("TargetCompID"));
+
+    // Application messages before a Logon are dropped with the connection
+    let mut early = Initiator::connect(&address, FixSession::new("alice", COMP_ID)).await;
+    early.new_order("a1", "AAPL", "1", "50", "1", "1").await;
+    let closed = timeout(Duration::from_secs(5), early.connection.next()).await.unwrap();
+    assert!(closed.is_none());
+}
@@ -0,0 +1,274 @@
+//! Binary order entry gateway on localhost
+//!
+//! Clients log in over TCP, trade against each other through the ingestion
+//! pipeline and must get an execution report for every step of their
+//! orders, in sequence. Broken sessions (sequence gaps, silent clients,
+//! double logins) must be logged out.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use order_book_api::disruptor::{IngestionPipeline, PipelineConfig};
+use order_book_api::engine::OrderBookEngine;
+use order_book_api::gateway::{ExecutionReportPublisher, GatewayCodec, OrderGateway, OrderRegistry, SequencedMessage};
+use order_book_api::models::{Order, OrderSide, OrderStatus, OrderType, SelfTradePreventionMode, TimeInForce};
+use order_book_api::protocol::{ExecType, ExecutionReport, Message, RejectReason};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+use tokio_util::codec::Framed;
+
+async fn start_gateway() -> (Arc<OrderBookEngine>, String) {
+    let engine = Arc::new(OrderBookEngine::new());
+    let registry = Arc::new(OrderRegistry::new());
+    let pipeline = Arc::new(IngestionPipeline::start(
+        engine.clone(),
+        PipelineConfig::default(),
+        vec![Box::new(ExecutionReportPublisher::new(registry.clone()))],
+    ));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(Arc::new(OrderGateway::new(pipeline, registry)).serve(listener));
+    (engine, address)
+}
+
+struct Client {
+    connection: Framed<TcpStream, GatewayCodec>,
+    sent: u64,
+    received: u64,
+}
+
+impl Client {
+    async fn connect(address: &str) -> Self {
+        let stream = TcpStream::connect(address).await.unwrap();
+        Self {
+            connection: Framed::new(stream, GatewayCodec),
+            sent: 0,
+            received: 0,
+        }
+    }
+
+    async fn login(address: &str, user_id: &str) -> Self {
+        let mut client = Self::connect(address).await;
+        client
+            .send(Message::Login {
+                user_id: user_id.to_string(),
+                heartbeat_interval_secs: 30,
+            })
+            .await;
+        assert!(matches!(
+            client.receive().await,
+            Message::LoginAck { heartbeat_interval_secs: 30 }
+        ));
+        client
+    }
+
+    async fn send(&mut self, message: Message) {
+        self.sent += 1;
+        let sequence = self.sent;
+        self.connection.send(SequencedMessage { sequence, message }).await.unwrap();
+    }
+
+    /// Next message; the gateway's sequence numbers must have no gaps
+    async fn receive(&mut self) -> Message {
+        let message = timeout(Duration::from_secs(5), self.connection.next())
+            .await
+            .expect("timed out waiting for the gateway")
+            .expect("gateway closed the connection")
+            .unwrap();
+        self.received += 1;
+        assert_eq!(message.sequence, self.received);
+        message.message
+    }
+
+    async fn report(&mut self) -> ExecutionReport {
+        match self.receive().await {
+            Message::ExecutionReport(report) => report,
+            other => panic!("expected an execution report, received {:?}", other),
+        }
+    }
+}
+
+fn limit(side: OrderSide, price: Decimal, quantity: Decimal) -> Order {
+    Order::new("AAPL".to_string(), side, OrderType::Limit, Some(price), quantity, "ignored".to_string())
+}
+
+/// Exec type, status, last quantity, cumulative and leaves quantity of a report
+fn summary(report: &ExecutionReport) -> (ExecType, OrderStatus, Decimal, Decimal, Decimal) {
+    (
+        report.exec_type,
+        report.status,
+        report.last_quantity,
+        report.cumulative_quantity,
+        report.leaves_quantity,
+    )
+}
+
+fn cancel(order: &Order) -> Message {
+    Message::CancelOrder {
+        order_id: order.id,
+        symbol: order.symbol.clone(),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_orders_get_execution_reports() {
+    let (engine, address) = start_gateway().await;
+    let mut seller = Client::login(&address, "seller").await;
+    let mut buyer = Client::login(&address, "buyer").await;
+
+    let ask = limit(OrderSide::Sell, dec!(100), dec!(10));
+    seller.send(Message::NewOrder(ask.clone())).await;
+    let report = seller.report().await;
+    assert_eq!(report.order_id, ask.id);
+    assert_eq!(summary(&report), (ExecType::New, OrderStatus::New, dec!(0), dec!(0), dec!(10)));
+    // The session's user owns the order, whatever the message said
+    assert_eq!(engine.get_order("AAPL", ask.id).unwrap().user_id, "seller");
+
+    // Both sides of a trade hear about it
+    let bid = limit(OrderSide::Buy, dec!(100), dec!(4));
+    buyer.send(Message::NewOrder(bid)).await;
+    assert_eq!(summary(&buyer.report().await).0, ExecType::New);
+    let fill = buyer.report().await;
+    assert_eq!(fill.last_price, Some(dec!(100)));
+    assert_eq!(summary(&fill), (ExecType::Trade, OrderStatus::Filled, dec!(4), dec!(4), dec!(0)));
+    assert_eq!(
+        summary(&seller.report().await),
+        (ExecType::Trade, OrderStatus::PartiallyFilled, dec!(4), dec!(4), dec!(6))
+    );
+
+    // The unfilled rest of an IOC order is cancelled after its fills
+    let mut ioc = limit(OrderSide::Buy, dec!(100), dec!(8));
+    ioc.time_in_force = TimeInForce::IOC;
+    buyer.send(Message::NewOrder(ioc)).await;
+    assert_eq!(summary(&buyer.report().await).0, ExecType::New);
+    assert_eq!(
+        summary(&buyer.report().await),
+        (ExecType::Trade, OrderStatus::PartiallyFilled, dec!(6), dec!(6), dec!(2))
+    );
+    assert_eq!(
+        summary(&buyer.report().await),
+        (ExecType::Cancelled, OrderStatus::Cancelled, dec!(0), dec!(6), dec!(0))
+    );
+    assert_eq!(
+        summary(&seller.report().await),
+        (ExecType::Trade, OrderStatus::Filled, dec!(6), dec!(10), dec!(0))
+    );
+
+    // Modify, then cancel, a resting order
+    let rest = limit(OrderSide::Sell, dec!(105), dec!(5));
+    seller.send(Message::NewOrder(rest.clone())).await;
+    assert_eq!(summary(&seller.report().await).0, ExecType::New);
+    seller
+        .send(Message::ModifyOrder {
+            order_id: rest.id,
+            symbol: rest.symbol.clone(),
+            new_price: None,
+            new_quantity: Some(dec!(3)),
+        })
+        .await;
+    assert_eq!(
+        summary(&seller.report().await),
+        (ExecType::Replaced, OrderStatus::New, dec!(0), dec!(0), dec!(3))
+    );
+    assert_eq!(engine.get_order("AAPL", rest.id).unwrap().quantity, dec!(3));
+
+    seller.send(cancel(&rest)).await;
+    assert_eq!(
+        summary(&seller.report().await),
+        (ExecType::Cancelled, OrderStatus::Cancelled, dec!(0), dec!(0), dec!(0))
+    );
+
+    // Requests for orders the session does not have, and invalid orders, are rejected
+    seller.send(cancel(&rest)).await;
+    let report = seller.report().await;
+    assert_eq!(summary(&report).0, ExecType::CancelRejected);
+    assert_eq!(report.reject_reason, RejectReason::UnknownOrder);
+    assert_eq!(report.side, None);
+
+    buyer.send(cancel(&ask)).await;
+    assert_eq!(summary(&buyer.report().await).0, ExecType::CancelRejected);
+
+    let invalid = limit(OrderSide::Buy, dec!(100), dec!(-1));
+    buyer.send(Message::NewOrder(invalid)).await;
+    let report = buyer.report().await;
+    assert_eq!(summary(&report).0, ExecType::Rejected);
+    assert_eq!(report.status, OrderStatus::Rejected);
+    assert_eq!(report.reject_reason, RejectReason::InvalidOrder);
+    assert!(!report.text.is_empty());
+    assert!(engine.get_order_book("AAPL").unwrap().orders.is_empty());
+
+    // Order options beyond the legacy layout reach the engine
+    let mut post_only = limit(OrderSide::Buy, dec!(99), dec!(2));
+    post_only.symbol = "BRK.A-LONGSYMBOL".to_string();
+    post_only.post_only = true;
+    post_only.stp_mode = SelfTradePreventionMode::CancelBoth;
+    buyer.send(Message::NewOrder(post_only.clone())).await;
+    let report = buyer.report().await;
+    assert_eq!((report.exec_type, report.symbol.as_str()), (ExecType::New, "BRK.A-LONGSYMBOL"));
+    let resting = engine.get_order("BRK.A-LONGSYMBOL", post_only.id).unwrap();
+    assert!(resting.post_only);
+    assert_eq!(resting.stp_mode, SelfTradePreventionMode::CancelBoth);
+
+    buyer.send(Message::Logout { reason: String::new() }).await;
+    assert!(matches!(buyer.receive().await, Message::Logout { .. }));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_broken_sessions_are_logged_out() {
+    let (_engine, address) = start_gateway().await;
+
+    // Orders before a login
+    let mut client = Client::connect(&address).await;
+    let order = limit(OrderSide::Buy, dec!(1), dec!(1));
+    client.send(Message::NewOrder(order)).await;
+    assert!(matches!(client.receive().await, Message::Logout { .. }));
+
+    // A second session for a logged-in user
+    let mut first = Client::login(&address, "alice").await;
+    let mut second = Client::connect(&address).await;
+    second
+        .send(Message::Login {
+            user_id: "alice".to_string(),
+            heartbeat_interval_secs: 30,
+        })
+        .await;
+    match second.receive().await {
+        Message::Logout { reason } => assert!(reason.contains("already logged in"), "{}", reason),
+        other => panic!("unexpected message {:?}", other),
+    }
+
+    // A sequence gap
+    first.sent += 1;
+    first.send(Message::Heartbeat).await;
+    match first.receive().await {
+        Message::Logout { reason } => assert!(reason.contains("expected sequence number 2"), "{}", reason),
+        other => panic!("unexpected message {:?}", other),
+    }
+    assert!(first.connection.next().await.is_none());
+
+    // The user can log in again once the session is gone
+    let mut client = Client::connect(&address).await;
+    client
+        .send(Message::Login {
+            user_id: "alice".to_string(),
+            heartbeat_interval_secs: 1,
+        })
+        .await;
+    assert!(matches!(client.receive().await, Message::LoginAck { .. }));
+
+    // An idle session gets heartbeats, and a silent client is logged out
+    assert!(matches!(client.receive().await, Message::Heartbeat));
+    loop {
+        match client.receive().await {
+            Message::Heartbeat => {}
+            Message::Logout { reason } => {
+                assert_eq!(reason, "heartbeat timeout");
+                break;
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+}
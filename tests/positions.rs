@@ -0,0 +1,76 @@
+//! Positions under a stream of orders
+//!
+//! Every trade has a buyer and a seller, so across all users each symbol's
+//! net quantity must be zero, and with every position marked to the same
+//! price the realized and unrealized PnL must sum to zero as well. A tracker
+//! started late must catch up with the trade history to the same positions.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use order_book_api::ctrader_fix::market_data::MarketTick;
+use order_book_api::engine::OrderBookEngine;
+use order_book_api::positions::{Position, PositionTracker};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+mod common;
+
+use common::{apply, order_stream, SYMBOLS};
+
+const USERS: [&str; 5] = ["trader0", "trader1", "trader2", "trader3", "stopper"];
+
+fn all_positions(tracker: &PositionTracker) -> Vec<Position> {
+    USERS.iter().flat_map(|user| tracker.positions(user).unwrap()).collect()
+}
+
+#[test]
+fn test_positions_net_to_zero_and_pnl_is_zero_sum() {
+    let ops = order_stream(600);
+    let engine = Arc::new(OrderBookEngine::new());
+    let following = PositionTracker::new(engine.clone());
+
+    apply(&engine, &ops[..300]);
+    all_positions(&following);
+    apply(&engine, &ops[300..]);
+    assert!(engine.get_total_trades().unwrap() > 0);
+
+    // Mark every symbol to one external price
+    for symbol in SYMBOLS {
+        following
+            .on_tick(&MarketTick {
+                symbol_id: symbol.to_string(),
+                timestamp: Utc::now(),
+                bid_price: Some(dec!(99.9)),
+                ask_price: Some(dec!(100.1)),
+            })
+            .unwrap();
+    }
+
+    let positions = all_positions(&following);
+    for symbol in SYMBOLS {
+        let in_symbol: Vec<&Position> = positions.iter().filter(|p| p.symbol == symbol).collect();
+        assert!(!in_symbol.is_empty());
+
+        let net: Decimal = in_symbol.iter().map(|p| p.net_quantity).sum();
+        assert_eq!(net, Decimal::ZERO, "{} net quantity", symbol);
+
+        let pnl: Decimal = in_symbol
+            .iter()
+            .map(|p| p.realized_pnl + p.unrealized_pnl.unwrap())
+            .sum();
+        // Average entry prices are divided out, so allow for their rounding
+        assert_eq!(pnl.round_dp(12), Decimal::ZERO, "{} PnL", symbol);
+        assert!(in_symbol.iter().all(|p| p.mark_price == Some(dec!(100))));
+    }
+
+    // A tracker started now replays the whole trade history
+    let late = PositionTracker::new(engine.clone());
+    let without_marks = |positions: Vec<Position>| -> Vec<_> {
+        positions
+            .into_iter()
+            .map(|p| (p.user_id, p.symbol, p.net_quantity, p.average_entry_price, p.realized_pnl))
+            .collect()
+    };
+    assert_eq!(without_marks(all_positions(&late)), without_marks(positions));
+}
@@ -0,0 +1,125 @@
+//! Hot-standby replication between two engines on localhost
+//!
+//! A primary journals a stream of orders and serves its log over TCP; a
+//! follower with its own WAL applies it. The follower must end up with the
+//! primary's books, reject orders while it is a standby, and after promotion
+//! continue the stream as if it had been the primary all along.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use order_book_api::engine::{OrderBookEngine, OrderBookError};
+use order_book_api::models::{Order, OrderSide, OrderType};
+use order_book_api::persistence::{AlgorithmSnapshot, SnapshotStore, SyncMode, WriteAheadLog};
+use order_book_api::replication::{ReplicationFollower, ReplicationServer};
+use rust_decimal_macros::dec;
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+
+mod common;
+
+use common::{apply, order_stream, states};
+
+fn open_engine(dir: &Path) -> Arc<OrderBookEngine> {
+    let wal = Arc::new(Mutex::new(WriteAheadLog::open(dir, SyncMode::None).unwrap()));
+    let store = SnapshotStore::open(dir.join("snapshots")).unwrap();
+    let engine = OrderBookEngine::with_wal(wal).with_snapshot_store(store);
+    engine.recover().unwrap();
+    Arc::new(engine)
+}
+
+async fn start_server(engine: &Arc<OrderBookEngine>) -> (Arc<ReplicationServer>, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = Arc::new(ReplicationServer::new(engine.clone()));
+    tokio::spawn(server.clone().serve(listener));
+    (server, address)
+}
+
+async fn wait_for(what: &str, mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_follower_tracks_primary_and_takes_over_after_promote() {
+    let ops = order_stream(400);
+    let reference = OrderBookEngine::new();
+    apply(&reference, &ops);
+
+    let primary_dir = TempDir::new().unwrap();
+    let follower_dir = TempDir::new().unwrap();
+    let primary = open_engine(primary_dir.path());
+    let (server, address) = start_server(&primary).await;
+
+    // The first part is caught up from the primary's log files, the rest streamed live
+    apply(&primary, &ops[..100]);
+    let follower_engine = open_engine(follower_dir.path());
+    let follower = ReplicationFollower::start(follower_engine.clone(), address).unwrap();
+    apply(&primary, &ops[100..300]);
+
+    let primary_sequence = primary.last_sequence().unwrap();
+    wait_for("follower to apply the stream", || follower.status().applied_sequence == primary_sequence).await;
+    assert_eq!(states(&follower_engine), states(&primary));
+    assert_eq!(follower_engine.last_sequence().unwrap(), primary_sequence);
+
+    // Lag is reported on both sides
+    wait_for("acknowledgement", || {
+        server.followers().unwrap().first().map(|lag| lag.acked_sequence) == Some(primary_sequence)
+    })
+    .await;
+    assert_eq!(server.followers().unwrap()[0].lag_events, 0);
+    let status = follower.status();
+    assert!(status.connected);
+    assert_eq!(status.lag_events, 0);
+    assert!(status.lag_ms.is_some());
+
+    // A standby only changes through replication
+    let order = Order::new("AAPL".to_string(), OrderSide::Buy, OrderType::Limit, Some(dec!(100)), dec!(1), "trader".to_string());
+    assert!(matches!(follower_engine.add_order(order), Err(OrderBookError::Standby)));
+
+    // The primary fails; the follower takes over where it stopped
+    drop(server);
+    assert_eq!(follower.promote().await.unwrap(), primary_sequence);
+    assert!(follower.status().promoted);
+    assert!(follower.promote().await.is_err());
+
+    apply(&follower_engine, &ops[300..]);
+    assert_eq!(states(&follower_engine), states(&reference));
+    assert!(follower_engine.last_sequence().unwrap() > primary_sequence);
+}
+
+#[tokio::test]
+async fn test_follower_behind_a_checkpoint_installs_a_snapshot() {
+    let ops = order_stream(300);
+
+    let primary_dir = TempDir::new().unwrap();
+    let follower_dir = TempDir::new().unwrap();
+    let primary = open_engine(primary_dir.path());
+    let (_server, address) = start_server(&primary).await;
+
+    // The checkpoint deletes the log a new follower would need
+    apply(&primary, &ops[..150]);
+    primary.checkpoint(AlgorithmSnapshot::default()).unwrap();
+    apply(&primary, &ops[150..200]);
+
+    let follower_engine = open_engine(follower_dir.path());
+    let follower = ReplicationFollower::start(follower_engine.clone(), address).unwrap();
+    apply(&primary, &ops[200..]);
+
+    let primary_sequence = primary.last_sequence().unwrap();
+    wait_for("follower to catch up", || follower.status().applied_sequence == primary_sequence).await;
+    assert_eq!(states(&follower_engine), states(&primary));
+
+    // The follower's own snapshot and log bring it back after a restart
+    follower.promote().await.unwrap();
+    drop(follower);
+    drop(follower_engine);
+    let restarted = open_engine(follower_dir.path());
+    assert_eq!(states(&restarted), states(&primary));
+    assert_eq!(restarted.last_sequence().unwrap(), primary_sequence);
+}
@@ -0,0 +1,128 @@
+//! Risk engine bookkeeping under a stream of orders
+//!
+//! The risk engine follows every order that starts working, fills, is
+//! amended, cancelled or expires. Its open order count per account must
+//! always match the books, limits must hold throughout the stream, and a
+//! restarted engine must rebuild the same picture from its snapshot and log.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use order_book_api::engine::{OrderBookEngine, OrderBookError};
+use order_book_api::models::{Order, OrderSide, OrderType};
+use order_book_api::persistence::{AlgorithmSnapshot, SnapshotStore, SyncMode, WriteAheadLog};
+use order_book_api::risk::{RiskLimits, RiskRejectReason};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use tempfile::TempDir;
+
+mod common;
+
+use common::{apply, order_stream, SYMBOLS};
+
+const USERS: [&str; 5] = ["trader0", "trader1", "trader2", "trader3", "stopper"];
+
+fn open_engine(dir: &Path) -> OrderBookEngine {
+    let wal = Arc::new(Mutex::new(WriteAheadLog::open(dir, SyncMode::EveryWrite).unwrap()));
+    let store = SnapshotStore::open(dir.join("snapshots")).unwrap();
+    let engine = OrderBookEngine::with_wal(wal).with_snapshot_store(store);
+    engine.recover().unwrap();
+    engine
+}
+
+fn open_orders_in_books(engine: &OrderBookEngine, user: &str) -> usize {
+    SYMBOLS
+        .iter()
+        .map(|symbol| {
+            let book = engine.get_order_book(symbol).unwrap();
+            book.orders.values().filter(|order| order.user_id == user).count()
+        })
+        .sum()
+}
+
+fn open_orders_tracked(engine: &OrderBookEngine) -> Vec<usize> {
+    USERS
+        .iter()
+        .map(|user| engine.get_account_risk(user).unwrap().usage.open_orders)
+        .collect()
+}
+
+#[test]
+fn test_open_orders_follow_the_books_and_limits_hold() {
+    let dir = TempDir::new().unwrap();
+    let engine = open_engine(dir.path());
+    engine
+        .set_default_risk_limits(RiskLimits {
+            max_open_orders: Some(8),
+            ..Default::default()
+        })
+        .unwrap();
+
+    for chunk in order_stream(600).chunks(50) {
+        apply(&engine, chunk);
+        for user in USERS {
+            let in_books = open_orders_in_books(&engine, user);
+            assert!(in_books <= 8, "{} has {} open orders", user, in_books);
+            assert_eq!(engine.get_account_risk(user).unwrap().usage.open_orders, in_books, "{}", user);
+        }
+    }
+    assert!(engine.get_total_trades().unwrap() > 0);
+}
+
+#[test]
+fn test_risk_state_recovers_from_snapshot_and_log() {
+    let ops = order_stream(400);
+    let dir = TempDir::new().unwrap();
+
+    let engine = open_engine(dir.path());
+    apply(&engine, &ops[..200]);
+    engine.checkpoint(AlgorithmSnapshot::default()).unwrap().unwrap();
+    apply(&engine, &ops[200..]);
+    let expected = open_orders_tracked(&engine);
+    let pnl: Vec<_> = USERS
+        .iter()
+        .map(|user| engine.get_account_risk(user).unwrap().usage.daily_realized_pnl)
+        .collect();
+    drop(engine);
+
+    let recovered = open_engine(dir.path());
+    assert_eq!(open_orders_tracked(&recovered), expected);
+    let recovered_pnl: Vec<_> = USERS
+        .iter()
+        .map(|user| recovered.get_account_risk(user).unwrap().usage.daily_realized_pnl)
+        .collect();
+    assert_eq!(recovered_pnl, pnl);
+}
+
+fn limit(side: OrderSide, price: Decimal, quantity: Decimal, user_id: &str) -> Order {
+    Order::new("AAPL".to_string(), side, OrderType::Limit, Some(price), quantity, user_id.to_string())
+}
+
+#[test]
+fn test_daily_loss_survives_a_restart_after_a_checkpoint() {
+    let dir = TempDir::new().unwrap();
+    let limits = RiskLimits {
+        max_daily_loss: Some(dec!(100)),
+        ..Default::default()
+    };
+
+    let engine = open_engine(dir.path());
+    engine.set_default_risk_limits(limits.clone()).unwrap();
+    // alice buys 10 at 100 and sells them at 90
+    engine.add_order(limit(OrderSide::Sell, dec!(100), dec!(10), "bob")).unwrap();
+    engine.add_order(limit(OrderSide::Buy, dec!(100), dec!(10), "alice")).unwrap();
+    engine.add_order(limit(OrderSide::Buy, dec!(90), dec!(10), "bob")).unwrap();
+    engine.add_order(limit(OrderSide::Sell, dec!(90), dec!(10), "alice")).unwrap();
+    assert_eq!(engine.get_account_risk("alice").unwrap().usage.daily_realized_pnl, dec!(-100));
+    engine.checkpoint(AlgorithmSnapshot::default()).unwrap().unwrap();
+    drop(engine);
+
+    let recovered = open_engine(dir.path());
+    recovered.set_default_risk_limits(limits).unwrap();
+    assert_eq!(recovered.get_account_risk("alice").unwrap().usage.daily_realized_pnl, dec!(-100));
+    assert_eq!(recovered.get_account_risk("bob").unwrap().usage.daily_realized_pnl, dec!(100));
+    match recovered.add_order(limit(OrderSide::Buy, dec!(90), dec!(1), "alice")) {
+        Err(OrderBookError::RiskRejected(rejection)) => assert_eq!(rejection.reason, RiskRejectReason::DailyLoss),
+        other => panic!("expected a daily loss rejection, got {:?}", other),
+    }
+}
@@ -0,0 +1,226 @@
+//! Recovery from a snapshot plus the tail of the write-ahead log
+//!
+//! An engine journaling to a WAL takes checkpoints part-way through a stream
+//! of orders. Each checkpoint must be recorded in the log and delete the
+//! segments the snapshots cover; a restarted engine must load the newest
+//! snapshot, replay only the events after it and end up with the same books.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use order_book_api::engine::{FeeSchedule, FifoTopProRata, MatchingPolicyConfig, OrderBookEngine, ProRata};
+use order_book_api::models::{
+    ContingencyType, ContingentGroup, ContingentGroupStatus, LegOrder, LegStatus, Order, OrderSide, OrderType,
+};
+use order_book_api::persistence::{AlgorithmSnapshot, SnapshotStore, SyncMode, WalEvent, WriteAheadLog};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use tempfile::TempDir;
+use uuid::Uuid;
+
+mod common;
+
+use common::{apply, order_stream, states, stop_order};
+
+fn open_engine(dir: &Path) -> (OrderBookEngine, Arc<Mutex<WriteAheadLog>>, u64) {
+    let wal = Arc::new(Mutex::new(WriteAheadLog::open(dir, SyncMode::EveryWrite).unwrap()));
+    let store = SnapshotStore::open(dir.join("snapshots")).unwrap();
+    let engine = OrderBookEngine::with_wal(wal.clone()).with_snapshot_store(store);
+    let replayed = engine.recover().unwrap();
+    (engine, wal, replayed)
+}
+
+fn wal_segments(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with("wal_") && name.ends_with(".log"))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_recovery_replays_only_the_tail_after_a_checkpoint() {
+    let ops = order_stream(400);
+
+    let reference = OrderBookEngine::new();
+    apply(&reference, &ops);
+
+    let dir = TempDir::new().unwrap();
+    let (engine, wal, _) = open_engine(dir.path());
+
+    apply(&engine, &ops[..150]);
+    let first = engine.checkpoint(AlgorithmSnapshot::default()).unwrap().unwrap();
+    assert!(first.exists());
+    assert!(!wal_segments(dir.path()).contains(&"wal_00000000.log".to_string()));
+
+    apply(&engine, &ops[150..300]);
+    let second = engine.checkpoint(AlgorithmSnapshot::default()).unwrap().unwrap();
+    let checkpoint_sequence = wal.lock().unwrap().current_sequence();
+
+    // The checkpoint is journaled right after the snapshot's last event
+    let mut checkpoints = Vec::new();
+    wal.lock()
+        .unwrap()
+        .replay(|event| {
+            if let WalEvent::Checkpoint { sequence, checkpoint_path, .. } = event {
+                checkpoints.push((sequence, checkpoint_path));
+            }
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(checkpoints.last().unwrap(), &(checkpoint_sequence, second.to_string_lossy().into_owned()));
+
+    apply(&engine, &ops[300..350]);
+    let before_crash = states(&engine);
+    let tail = wal.lock().unwrap().current_sequence() - (checkpoint_sequence - 1);
+    drop(engine);
+    drop(wal);
+
+    // Only the segments after the older of the two kept snapshots remain
+    let store = SnapshotStore::open(dir.path().join("snapshots")).unwrap();
+    assert_eq!(store.sequences().unwrap().len(), 2);
+    let oldest_segment = store.oldest_wal_segment().unwrap().unwrap();
+    assert_eq!(wal_segments(dir.path())[0], format!("wal_{:08}.log", oldest_segment));
+
+    let (recovered, _wal, replayed) = open_engine(dir.path());
+    assert_eq!(replayed, tail);
+    assert_eq!(states(&recovered), before_crash);
+
+    apply(&recovered, &ops[350..]);
+    assert_eq!(states(&recovered), states(&reference));
+}
+
+#[test]
+fn test_recovery_falls_back_to_previous_snapshot() {
+    let ops = order_stream(200);
+
+    let dir = TempDir::new().unwrap();
+    let (engine, wal, _) = open_engine(dir.path());
+    apply(&engine, &ops[..80]);
+    engine.checkpoint(AlgorithmSnapshot::default()).unwrap();
+    apply(&engine, &ops[80..160]);
+    let newest = engine.checkpoint(AlgorithmSnapshot::default()).unwrap().unwrap();
+    apply(&engine, &ops[160..]);
+    let before_crash = states(&engine);
+    drop(engine);
+    drop(wal);
+
+    std::fs::write(&newest, b"not a snapshot").unwrap();
+
+    let (recovered, _, _) = open_engine(dir.path());
+    assert_eq!(states(&recovered), before_crash);
+}
+
+#[test]
+fn test_fee_schedules_recover_from_snapshot_and_log() {
+    let dir = TempDir::new().unwrap();
+    let (engine, wal, _) = open_engine(dir.path());
+    engine.set_default_fee_schedule(FeeSchedule::flat(dec!(0.0005), dec!(0.001))).unwrap();
+    engine.set_user_fee_schedule("whale", Some(FeeSchedule::flat(dec!(-0.0001), dec!(0.0004)))).unwrap();
+    engine.checkpoint(AlgorithmSnapshot::default()).unwrap().unwrap();
+    engine.set_symbol_fee_schedule("AAPL", Some(FeeSchedule::flat(dec!(0), dec!(0.0008)))).unwrap();
+    engine.set_user_fee_schedule("whale", None).unwrap();
+    let expected = serde_json::to_value(engine.get_fee_schedules().unwrap()).unwrap();
+    drop(engine);
+    drop(wal);
+
+    let (recovered, _, replayed) = open_engine(dir.path());
+    // The checkpoint marker and the two changes after the snapshot
+    assert_eq!(replayed, 3);
+    assert_eq!(serde_json::to_value(recovered.get_fee_schedules().unwrap()).unwrap(), expected);
+    assert!(recovered.get_fee_schedules().unwrap().users.is_empty());
+}
+
+fn limit(side: OrderSide, price: Decimal, quantity: Decimal, user_id: &str) -> Order {
+    Order::new("AAPL".to_string(), side, OrderType::Limit, Some(price), quantity, user_id.to_string())
+}
+
+fn group_state(engine: &OrderBookEngine, group_id: Uuid) -> (ContingentGroupStatus, Vec<LegStatus>) {
+    let group = engine.get_contingent_order(group_id).unwrap();
+    (group.status, group.all_legs().map(|leg| leg.status).collect())
+}
+
+#[test]
+fn test_contingent_groups_recover_from_snapshot_and_log() {
+    let dir = TempDir::new().unwrap();
+    let (engine, wal, _) = open_engine(dir.path());
+
+    let bracket = ContingentGroup::new(
+        "AAPL".to_string(),
+        "stopper".to_string(),
+        ContingencyType::Bracket,
+        Some(LegOrder::Order(limit(OrderSide::Buy, dec!(100), dec!(10), "stopper"))),
+        vec![
+            LegOrder::Order(limit(OrderSide::Sell, dec!(110), dec!(10), "stopper")),
+            LegOrder::Stop(stop_order("AAPL", OrderSide::Sell, dec!(95), dec!(10))),
+        ],
+    );
+    let bracket = engine.submit_contingent_order(bracket).unwrap();
+    engine.checkpoint(AlgorithmSnapshot::default()).unwrap().unwrap();
+
+    // After the snapshot: the entry fills and releases the exits, and an OCO
+    // group only the log knows about
+    engine.add_order(limit(OrderSide::Sell, dec!(100), dec!(10), "seller")).unwrap();
+    let oco = ContingentGroup::new(
+        "AAPL".to_string(),
+        "stopper".to_string(),
+        ContingencyType::Oco,
+        None,
+        vec![
+            LegOrder::Order(limit(OrderSide::Sell, dec!(130), dec!(5), "stopper")),
+            LegOrder::Stop(stop_order("AAPL", OrderSide::Sell, dec!(80), dec!(5))),
+        ],
+    );
+    let oco = engine.submit_contingent_order(oco).unwrap();
+    assert_eq!(engine.get_total_stop_orders().unwrap(), 2);
+    let before_crash = states(&engine);
+    let groups = [group_state(&engine, bracket.id), group_state(&engine, oco.id)];
+    drop(engine);
+    drop(wal);
+
+    let (recovered, _, _) = open_engine(dir.path());
+    assert_eq!(states(&recovered), before_crash);
+    assert_eq!([group_state(&recovered, bracket.id), group_state(&recovered, oco.id)], groups);
+    assert_eq!(recovered.get_total_stop_orders().unwrap(), 2);
+
+    // The recovered groups still work: the take-profit filling disarms the stop-loss
+    recovered.add_order(limit(OrderSide::Buy, dec!(110), dec!(10), "buyer")).unwrap();
+    assert_eq!(
+        group_state(&recovered, bracket.id),
+        (ContingentGroupStatus::Completed, vec![LegStatus::Filled, LegStatus::Filled, LegStatus::Cancelled])
+    );
+    assert_eq!(recovered.get_total_stop_orders().unwrap(), 1);
+
+    recovered.cancel_contingent_order(oco.id).unwrap();
+    assert_eq!(recovered.get_total_stop_orders().unwrap(), 0);
+}
+
+#[test]
+fn test_matching_policies_recover_from_snapshot_and_log() {
+    let dir = TempDir::new().unwrap();
+    let (engine, wal, _) = open_engine(dir.path());
+    let pro_rata = MatchingPolicyConfig::ProRata(ProRata { min_allocation: dec!(1), lot_size: dec!(1) });
+    engine.set_matching_policy("MSFT", pro_rata).unwrap();
+    engine.checkpoint(AlgorithmSnapshot::default()).unwrap().unwrap();
+    engine.set_matching_policy("AAPL", pro_rata).unwrap();
+    engine
+        .set_matching_policy(
+            "MSFT",
+            MatchingPolicyConfig::FifoTopProRata(FifoTopProRata { min_allocation: dec!(1), lot_size: dec!(1) }),
+        )
+        .unwrap();
+    let expected = engine.get_matching_policies().unwrap();
+    drop(engine);
+    drop(wal);
+
+    let (recovered, _, _) = open_engine(dir.path());
+    assert_eq!(recovered.get_matching_policies().unwrap(), expected);
+
+    // AAPL now splits a level between its resting orders
+    recovered.add_order(limit(OrderSide::Sell, dec!(100), dec!(10), "seller1")).unwrap();
+    recovered.add_order(limit(OrderSide::Sell, dec!(100), dec!(10), "seller2")).unwrap();
+    let (_, trades) = recovered.add_order(limit(OrderSide::Buy, dec!(100), dec!(10), "buyer")).unwrap();
+    assert_eq!(trades.iter().map(|trade| trade.quantity).collect::<Vec<_>>(), vec![dec!(5), dec!(5)]);
+}
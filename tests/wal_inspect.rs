@@ -0,0 +1,88 @@
+//! The `wal-inspect` binary against a log written by the engine
+
+use std::path::Path;
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+
+use order_book_api::engine::OrderBookEngine;
+use order_book_api::models::{Order, OrderSide, OrderType};
+use order_book_api::persistence::{SyncMode, WalEvent, WriteAheadLog};
+use rust_decimal_macros::dec;
+use tempfile::TempDir;
+
+fn wal_inspect(args: &[&str], dir: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_wal-inspect"))
+        .args(args)
+        .arg(dir)
+        .output()
+        .unwrap()
+}
+
+fn stdout_lines(output: &Output) -> Vec<String> {
+    String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect()
+}
+
+/// Two crossing AAPL orders (submit, submit, trade) and a resting MSFT order
+fn write_log(dir: &Path) -> Order {
+    let wal = Arc::new(Mutex::new(WriteAheadLog::open(dir, SyncMode::EveryWrite).unwrap()));
+    let engine = OrderBookEngine::with_wal(wal);
+    let ask = Order::new("AAPL".to_string(), OrderSide::Sell, OrderType::Limit, Some(dec!(150)), dec!(10), "seller".to_string());
+    engine.add_order(ask.clone()).unwrap();
+    engine
+        .add_order(Order::new("AAPL".to_string(), OrderSide::Buy, OrderType::Limit, Some(dec!(150)), dec!(4), "buyer".to_string()))
+        .unwrap();
+    engine
+        .add_order(Order::new("MSFT".to_string(), OrderSide::Buy, OrderType::Limit, Some(dec!(300)), dec!(1), "buyer".to_string()))
+        .unwrap();
+    ask
+}
+
+#[test]
+fn test_dump_json_and_filters() {
+    let dir = TempDir::new().unwrap();
+    let ask = write_log(dir.path());
+
+    let dump = wal_inspect(&["dump"], dir.path());
+    assert!(dump.status.success());
+    let lines = stdout_lines(&dump);
+    assert_eq!(lines.len(), 4);
+    assert!(lines[2].contains("TradeExecuted"));
+
+    let by_symbol = wal_inspect(&["dump", "--symbol", "MSFT"], dir.path());
+    assert_eq!(stdout_lines(&by_symbol).len(), 1);
+
+    // The resting ask was submitted and then traded against
+    let ask_id = ask.id.to_string();
+    let json = wal_inspect(&["json", "--order", &ask_id], dir.path());
+    assert!(json.status.success());
+    let events: Vec<WalEvent> = stdout_lines(&json)
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert!(matches!(&events[0], WalEvent::OrderSubmitted { order, .. } if order.id == ask.id));
+    assert!(matches!(&events[1], WalEvent::TradeExecuted { trade, .. } if trade.seller_order_id == ask.id));
+}
+
+#[test]
+fn test_verify_reports_corruption() {
+    let dir = TempDir::new().unwrap();
+    write_log(dir.path());
+
+    let verify = wal_inspect(&["verify"], dir.path());
+    assert!(verify.status.success());
+    assert!(stdout_lines(&verify)[0].contains("OK, 4 records, sequences 1..=4"));
+
+    let segment = dir.path().join("wal_00000000.log");
+    let mut bytes = std::fs::read(&segment).unwrap();
+    let last = bytes.len() - 3;
+    bytes[last] ^= 0xFF;
+    std::fs::write(&segment, bytes).unwrap();
+
+    let verify = wal_inspect(&["verify"], dir.path());
+    assert!(!verify.status.success());
+    assert!(stdout_lines(&verify)[0].contains("checksum mismatch"));
+
+    let usage = wal_inspect(&["verify", "--symbol", "AAPL"], dir.path());
+    assert_eq!(usage.status.code(), Some(2));
+}
@@ -0,0 +1,249 @@
+//! Crash recovery from the write-ahead log
+//!
+//! An engine journaling to a WAL in a temp dir is "killed" part-way through a
+//! stream of orders, cancels, amends and stop orders: it is dropped, and the
+//! log is left with a partially written record as if the process died in the
+//! middle of an append. A new engine recovering from the same directory must
+//! end up with the same books, and must keep matching them once the rest of
+//! the stream is applied.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use order_book_api::engine::OrderBookEngine;
+use order_book_api::persistence::{SyncMode, WriteAheadLog};
+use tempfile::TempDir;
+
+mod common;
+
+use common::{apply, order_stream, states};
+
+fn open_engine(dir: &Path) -> (OrderBookEngine, Arc<Mutex<WriteAheadLog>>) {
+    let wal = Arc::new(Mutex::new(WriteAheadLog::open(dir, SyncMode::EveryWrite).unwrap()));
+    let engine = OrderBookEngine::with_wal(wal.clone());
+    engine.recover().unwrap();
+    (engine, wal)
+}
+
+/// Leave half a record at the end of the log, as a process killed mid-append would
+fn tear_last_record(dir: &Path) {
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(dir.join("wal_00000000.log"))
+        .unwrap();
+    file.write_all(&512u32.to_le_bytes()).unwrap();
+    file.write_all(&[0xAB; 37]).unwrap();
+}
+
+#[test]
+fn test_recovered_books_match_after_crash_mid_stream() {
+    let ops = order_stream(400);
+
+    let reference = OrderBookEngine::new();
+    apply(&reference, &ops);
+    assert!(reference.get_total_trades().unwrap() > 0, "stream should trade");
+
+    for crash_at in [1, 57, 200, 399] {
+        let dir = TempDir::new().unwrap();
+
+        let (engine, wal) = open_engine(dir.path());
+        apply(&engine, &ops[..crash_at]);
+        let before_crash = states(&engine);
+        let last_sequence = wal.lock().unwrap().current_sequence();
+        drop(engine);
+        drop(wal);
+        tear_last_record(dir.path());
+
+        let (recovered, wal) = open_engine(dir.path());
+        assert_eq!(states(&recovered), before_crash, "books differ after crash at op {}", crash_at);
+        assert_eq!(wal.lock().unwrap().current_sequence(), last_sequence);
+
+        // Journaling resumes after the last complete record
+        apply(&recovered, &ops[crash_at..]);
+        assert_eq!(states(&recovered), states(&reference), "books diverge after crash at op {}", crash_at);
+        assert!(wal.lock().unwrap().current_sequence() > last_sequence);
+
+        // A second restart replays the whole log, with the torn record cut off
+        drop(recovered);
+        drop(wal);
+        let (restarted, _) = open_engine(dir.path());
+        assert_eq!(states(&restarted), states(&reference));
+    }
+}
+
+#[test]
+fn test_recovery_from_empty_log() {
+    let dir = TempDir::new().unwrap();
+    let (engine, wal) = open_engine(dir.path());
+
+    assert_eq!(engine.get_total_active_orders().unwrap(), 0);
+    assert_eq!(wal.lock().unwrap().current_sequence(), 0);
+}
+
+#[test]
+fn test_expiry_sweep_recovers_as_one_batch() {
+    use chrono::{Duration, Utc};
+    use order_book_api::models::{Order, OrderSide, OrderType, TimeInForce};
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    let order = |side: OrderSide, order_type: OrderType, price: Option<Decimal>, user_id: &str| {
+        Order::new("AAPL".to_string(), side, order_type, price, dec!(10), user_id.to_string())
+    };
+    let gtd = |side: OrderSide, price: Decimal, user_id: &str| {
+        let mut order = order(side, OrderType::Limit, Some(price), user_id);
+        order.time_in_force = TimeInForce::GTD;
+        order.expire_time = Some(Utc::now() + Duration::hours(1));
+        order
+    };
+
+    let dir = TempDir::new().unwrap();
+    let (engine, _) = open_engine(dir.path());
+    engine.add_order(gtd(OrderSide::Buy, dec!(100), "buyer1")).unwrap();
+    engine.add_order(order(OrderSide::Buy, OrderType::Limit, Some(dec!(90)), "buyer2")).unwrap();
+    engine.add_order(gtd(OrderSide::Sell, dec!(102), "seller1")).unwrap();
+    engine.add_order(order(OrderSide::Sell, OrderType::Limit, Some(dec!(110)), "seller2")).unwrap();
+
+    // A buy pegged 3 under the best ask and a sell pegged 3 over the best bid:
+    // 99 and 103. Which one crosses the other depends on whether the bid or
+    // the ask expired first, so expiring them one at a time could trade at 99
+    let mut buy_peg = order(OrderSide::Buy, OrderType::MarketPeg, None, "mm1");
+    buy_peg.peg_offset = Some(dec!(-3));
+    let mut sell_peg = order(OrderSide::Sell, OrderType::MarketPeg, None, "mm2");
+    sell_peg.peg_offset = Some(dec!(3));
+    engine.add_order(buy_peg).unwrap();
+    engine.add_order(sell_peg).unwrap();
+
+    // Both expire, then the buy peg moves up to 107 first and takes the sell peg at 103
+    let expired = engine.expire_orders(Utc::now() + Duration::hours(2)).unwrap();
+    assert_eq!(expired.len(), 2);
+    let trades = engine.get_recent_trades("AAPL", 10).unwrap();
+    assert_eq!(trades.iter().map(|trade| trade.price).collect::<Vec<_>>(), vec![dec!(103)]);
+    let before_restart = states(&engine);
+    drop(engine);
+
+    let (recovered, _) = open_engine(dir.path());
+    assert_eq!(states(&recovered), before_restart);
+    let recovered_trades = recovered.get_recent_trades("AAPL", 10).unwrap();
+    assert_eq!(recovered_trades.iter().map(|trade| trade.price).collect::<Vec<_>>(), vec![dec!(103)]);
+}
+
+#[test]
+fn test_recovery_after_expire_time_keeps_trades_of_gtd_orders() {
+    use chrono::{DateTime, Duration};
+    use order_book_api::models::{Order, OrderSide, OrderType, TimeInForce};
+    use order_book_api::utils::clock::{self, SeededIds, SimulatedClock};
+    use rust_decimal_macros::dec;
+
+    // The live run happens an hour before the GTD expiry; recovery runs on
+    // the wall clock, long after it
+    let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+    let dir = TempDir::new().unwrap();
+    let before_restart = clock::with_sources(Arc::new(SimulatedClock::new(start)), Arc::new(SeededIds::new(1)), || {
+        let (engine, _) = open_engine(dir.path());
+        let mut buy = Order::new("AAPL".to_string(), OrderSide::Buy, OrderType::Limit, Some(dec!(100)), dec!(10), "buyer1".to_string());
+        buy.time_in_force = TimeInForce::GTD;
+        buy.expire_time = Some(start + Duration::hours(1));
+        engine.add_order(buy).unwrap();
+        engine
+            .add_order(Order::new("AAPL".to_string(), OrderSide::Sell, OrderType::Limit, Some(dec!(100)), dec!(4), "seller1".to_string()))
+            .unwrap();
+        assert_eq!(engine.get_total_trades().unwrap(), 1);
+        states(&engine)
+    });
+
+    let (recovered, _) = open_engine(dir.path());
+    assert_eq!(states(&recovered), before_restart);
+    assert_eq!(recovered.get_total_trades().unwrap(), 1);
+    let book = recovered.get_order_book("AAPL").unwrap();
+    assert_eq!(book.get_best_bid(), Some(dec!(100)));
+    let buy = book.orders.values().find(|order| order.user_id == "buyer1").unwrap();
+    assert_eq!(buy.remaining_quantity(), dec!(6));
+    assert_eq!(book.get_best_ask(), None);
+}
+
+#[test]
+fn test_peg_held_at_the_price_band_recovers_at_the_band() {
+    use order_book_api::models::{Order, OrderSide, OrderType};
+    use order_book_api::risk::{CircuitBreakerConfig, PriceBandAction};
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    let order = |side: OrderSide, order_type: OrderType, price: Option<Decimal>, quantity: Decimal, user_id: &str| {
+        Order::new("AAPL".to_string(), side, order_type, price, quantity, user_id.to_string())
+    };
+
+    let dir = TempDir::new().unwrap();
+    let (engine, _) = open_engine(dir.path());
+    let config = CircuitBreakerConfig {
+        min_trades_for_activation: 1,
+        price_band_pct: Some(dec!(10)),
+        price_band_action: PriceBandAction::Reprice,
+        ..CircuitBreakerConfig::default()
+    };
+    engine.set_circuit_breaker_config("AAPL", config).unwrap();
+    engine.add_order(order(OrderSide::Sell, OrderType::Limit, Some(dec!(100)), dec!(1), "mm_seller")).unwrap();
+    engine.add_order(order(OrderSide::Buy, OrderType::Limit, Some(dec!(100)), dec!(1), "mm_buyer")).unwrap();
+
+    let (ask, _) = engine.add_order(order(OrderSide::Sell, OrderType::Limit, Some(dec!(105)), dec!(10), "seller1")).unwrap();
+    let mut peg = order(OrderSide::Buy, OrderType::MarketPeg, None, dec!(10), "mm1");
+    peg.peg_offset = Some(dec!(-1));
+    let (peg, _) = engine.add_order(peg).unwrap();
+
+    // The best ask moves out to 125: the peg follows only as far as the band's 110
+    engine.add_order(order(OrderSide::Sell, OrderType::Limit, Some(dec!(125)), dec!(10), "seller2")).unwrap();
+    engine.cancel_order("AAPL", ask.id).unwrap();
+    assert_eq!(engine.get_order("AAPL", peg.id).unwrap().price, Some(dec!(110)));
+    let before_restart = states(&engine);
+    drop(engine);
+
+    // The recovered engine has no price band configured; the log holds the reprice
+    let (recovered, _) = open_engine(dir.path());
+    assert_eq!(states(&recovered), before_restart);
+    assert_eq!(recovered.get_order("AAPL", peg.id).unwrap().price, Some(dec!(110)));
+}
+
+#[tokio::test]
+async fn test_pipeline_consumers_sync_and_checkpoint_the_log() {
+    use order_book_api::algorithms::AlgorithmManager;
+    use order_book_api::disruptor::{IngestionPipeline, JournalConsumer, PipelineConfig, SnapshotConsumer};
+    use order_book_api::models::{Order, OrderSide, OrderType};
+    use order_book_api::persistence::SnapshotStore;
+    use order_book_api::websocket::Broadcaster;
+    use rust_decimal::Decimal;
+
+    let dir = TempDir::new().unwrap();
+    let open = |dir: &Path| {
+        // Durability is left to the journal consumer
+        let wal = Arc::new(Mutex::new(WriteAheadLog::open(dir, SyncMode::None).unwrap()));
+        let engine = OrderBookEngine::with_wal(wal).with_snapshot_store(SnapshotStore::open(dir.join("snapshots")).unwrap());
+        engine.recover().unwrap();
+        Arc::new(engine)
+    };
+
+    let engine = open(dir.path());
+    let algorithms = Arc::new(AlgorithmManager::new(engine.clone(), Broadcaster::new()));
+    let mut pipeline = IngestionPipeline::start(
+        engine.clone(),
+        PipelineConfig::default(),
+        vec![
+            Box::new(JournalConsumer::new(engine.clone())),
+            Box::new(SnapshotConsumer::new(engine.clone(), algorithms, 10)),
+        ],
+    );
+    for i in 0..20 {
+        let side = if i % 2 == 0 { OrderSide::Sell } else { OrderSide::Buy };
+        let price = Decimal::from(100 + i % 3);
+        let order = Order::new("AAPL".to_string(), side, OrderType::Limit, Some(price), Decimal::ONE, "trader1".to_string());
+        pipeline.submit_order(order).await.unwrap();
+    }
+    pipeline.shutdown();
+
+    let store = SnapshotStore::open(dir.path().join("snapshots")).unwrap();
+    assert!(store.load_latest().unwrap().is_some());
+
+    let recovered = open(dir.path());
+    assert_eq!(states(&recovered), states(&engine));
+}